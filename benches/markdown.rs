@@ -0,0 +1,119 @@
+//! Markdown pipeline benchmarks: `cargo bench --bench markdown`
+//!
+//! Uncached groups use a service whose cache size limit is zero so every
+//! iteration runs the full parse -> highlight -> sanitize -> enhance pipeline.
+
+use std::time::Duration;
+
+use bluster::services::MarkdownService;
+use pulldown_cmark::{html, Options, Parser};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const SMALL: &str = "# Hello\n\nA short post with **bold**, *italic* and a [link](https://example.com).\n\n- one\n- two\n";
+
+const SECTION: &str = r#"
+## Section
+
+Paragraph with `inline code`, a [relative link](/about), an [external link](https://example.com/docs)
+and an image ![diagram](https://example.com/diagram.png "Diagram").
+
+| Name | Value | Notes |
+|------|-------|-------|
+| alpha | 1 | ~~old~~ |
+| beta | 2 | **new** |
+
+> A quote with *emphasis*.
+
+1. First
+2. Second
+   - [x] done
+   - [ ] todo
+"#;
+
+const CODE_BLOCK: &str = r#"
+```rust
+use std::collections::HashMap;
+
+fn word_count(text: &str) -> HashMap<&str, usize> {
+    let mut counts = HashMap::new();
+    for word in text.split_whitespace() {
+        *counts.entry(word).or_insert(0) += 1;
+    }
+    counts
+}
+```
+
+```python
+def fib(n):
+    a, b = 0, 1
+    for _ in range(n):
+        a, b = b, a + b
+    return a
+```
+
+```javascript
+const debounce = (fn, ms) => {
+  let timer;
+  return (...args) => { clearTimeout(timer); timer = setTimeout(() => fn(...args), ms); };
+};
+```
+"#;
+
+fn large_document() -> String {
+    SECTION.repeat(200)
+}
+
+fn code_heavy_document() -> String {
+    CODE_BLOCK.repeat(40)
+}
+
+fn uncached_service() -> MarkdownService {
+    MarkdownService::with_cache_config(Duration::from_secs(3600), 1000, 0, 0, Duration::from_secs(3600))
+}
+
+fn bench_render(c: &mut Criterion) {
+    let service = uncached_service();
+    let documents = [
+        ("small", SMALL.to_string()),
+        ("large", large_document()),
+        ("code_heavy", code_heavy_document()),
+    ];
+
+    let mut group = c.benchmark_group("render_to_html");
+    for (name, markdown) in &documents {
+        group.throughput(Throughput::Bytes(markdown.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), markdown, |b, markdown| {
+            b.iter(|| service.render_to_html(black_box(markdown)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_sanitize(c: &mut Criterion) {
+    let service = uncached_service();
+    // Unsanitized HTML as produced by pulldown-cmark, so this isolates the ammonia
+    // pass plus the link/image/table enhancement passes
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut raw_html = String::new();
+    html::push_html(&mut raw_html, Parser::new_ext(&large_document(), options));
+
+    let mut group = c.benchmark_group("sanitize_html");
+    group.throughput(Throughput::Bytes(raw_html.len() as u64));
+    group.bench_function("large", |b| b.iter(|| service.sanitize_html(black_box(&raw_html)).unwrap()));
+    group.finish();
+}
+
+fn bench_cache(c: &mut Criterion) {
+    let markdown = large_document();
+    let cached = MarkdownService::new();
+    cached.render_to_html(&markdown).unwrap();
+    let uncached = uncached_service();
+
+    let mut group = c.benchmark_group("render_cache");
+    group.bench_function("hit", |b| b.iter(|| cached.render_to_html(black_box(&markdown)).unwrap()));
+    group.bench_function("miss", |b| b.iter(|| uncached.render_to_html(black_box(&markdown)).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, bench_render, bench_sanitize, bench_cache);
+criterion_main!(benches);
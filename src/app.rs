@@ -0,0 +1,907 @@
+//! Shared state of the HTTP server: `AppState`, the template filters and
+//! functions registered with it, and the bookkeeping handlers do after
+//! changing content (activity log, revisions, caches).
+
+use actix_web::web;
+use log::{info, warn, error};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use crate::{models, services};
+use crate::services::markdown::RenderFeatures;
+use crate::services::feed::FeedOptions;
+use crate::services::media::{BrandAsset, Branding};
+use crate::services::navigation::NavLink;
+use crate::services::text::TextDirection;
+use crate::services::search::SearchRanking;
+use crate::services::feature_flags::FeatureFlags;
+use crate::services::dates::DateOptions;
+use crate::services::trash::TrashOptions;
+use crate::services::well_known::{RobotsOptions, SecurityTxtOptions};
+use crate::services::summary::SummaryOptions;
+use crate::services::{MarkdownService, EmailService, DuplicateFilter, RateLimiter, Signer};
+use tera::Tera;
+use crate::{metrics, middleware};
+
+/// Where a render or query ran, named in slow operation logs
+#[derive(Clone, Copy)]
+pub struct Origin {
+    pub route: &'static str,
+    pub article_id: Option<i64>,
+}
+
+impl Origin {
+    pub fn route(route: &'static str) -> Self {
+        Self { route, article_id: None }
+    }
+
+    pub fn article(route: &'static str, article_id: i64) -> Self {
+        Self { route, article_id: Some(article_id) }
+    }
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.article_id {
+            Some(id) => write!(f, "{} (article {})", self.route, id),
+            None => f.write_str(self.route),
+        }
+    }
+}
+
+pub fn is_slow(threshold: std::time::Duration, elapsed: std::time::Duration) -> bool {
+    !threshold.is_zero() && elapsed >= threshold
+}
+
+// 慢查询：记录路由和文章 id，便于定位拖慢页面的具体文章（SQL 文本由 sqlx 的慢语句日志输出）
+pub async fn timed_query<T>(data: &AppState, origin: Origin, query: impl std::future::Future<Output = T>) -> T {
+    let started = std::time::Instant::now();
+    let result = query.await;
+    let elapsed = started.elapsed();
+    if is_slow(data.slow_query_threshold, elapsed) {
+        data.request_metrics.record_slow_query();
+        warn!("Slow query on {}: {:?}", origin, elapsed);
+    }
+    result
+}
+
+// 大文档渲染（语法高亮较重）移到阻塞线程池，避免占住 Actix worker；小文档和缓存命中直接渲染
+pub async fn render_markdown<T: Send + 'static>(
+    data: &web::Data<AppState>,
+    origin: Origin,
+    content: String,
+    features: RenderFeatures,
+    render: fn(&MarkdownService, &str, RenderFeatures) -> T,
+) -> Result<T, actix_web::error::BlockingError> {
+    let started = std::time::Instant::now();
+    let content_len = content.len();
+    let result = if data.markdown_service.should_offload_with(&content, features) {
+        let _guard = data.markdown_service.begin_offload();
+        let state = data.clone();
+        web::block(move || render(&state.markdown_service, &content, features)).await
+    } else {
+        Ok(render(&data.markdown_service, &content, features))
+    };
+    let elapsed = started.elapsed();
+    if is_slow(data.slow_render_threshold, elapsed) {
+        data.request_metrics.record_slow_render();
+        warn!("Slow markdown render on {}: {:?} for {} bytes", origin, elapsed, content_len);
+    }
+    result
+}
+
+// 预热渲染缓存：后台渲染最近发布的文章，避免部署后首批访客同时冷渲染
+pub fn schedule_cache_prewarm(data: web::Data<AppState>, pool: SqlitePool) {
+    if data.prewarm_count == 0 {
+        return;
+    }
+    tokio::spawn(async move {
+        let started = std::time::Instant::now();
+        // 草稿不占预热名额；按发布时间排序，与订阅源一致
+        match sqlx::query_as::<_, (i64, String)>(
+            "SELECT id, content FROM articles WHERE deleted_at IS NULL AND status = 'published' \
+             ORDER BY COALESCE(published_at, created_at) DESC LIMIT ?"
+        )
+        .bind(data.prewarm_count as i64)
+        .fetch_all(&pool)
+        .await
+        {
+            Ok(articles) => {
+                let count = articles.len();
+                for (id, content) in articles {
+                    render_markdown_with_fallback(&data, Origin::article("cache prewarm", id), content).await;
+                }
+                info!("Pre-warmed markdown cache with {} article(s) in {:?}", count, started.elapsed());
+            }
+            Err(e) => error!("Failed to load articles for cache pre-warm: {}", e),
+        }
+    });
+}
+
+// 带回退的渲染，阻塞线程池不可用时返回转义后的原文
+pub async fn render_markdown_with_fallback(data: &web::Data<AppState>, origin: Origin, content: String) -> String {
+    render_article_with_fallback(data, origin, content, RenderFeatures::default()).await
+}
+
+// 按文章前言开启的功能(数学公式、标题锚点)渲染
+pub async fn render_article_with_fallback(data: &web::Data<AppState>, origin: Origin, content: String, features: RenderFeatures) -> String {
+    let escaped = html_escape::encode_text(&content).to_string();
+    render_markdown(data, origin, content, features, MarkdownService::render_with_features_fallback)
+        .await
+        .unwrap_or_else(|e| {
+            error!("Offloaded markdown render failed: {}", e);
+            escaped
+        })
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ArticleForm {
+    pub title: String,
+    pub content: String,
+    // SEO字段；更新时缺省表示保持原值
+    #[serde(default)]
+    pub meta_description: Option<String>,
+    #[serde(default)]
+    pub meta_keywords: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_checkbox")]
+    pub noindex: Option<bool>,
+    // auto / ltr / rtl；更新时缺省表示保持原值
+    #[serde(default)]
+    pub text_direction: Option<String>,
+    // 转载文章的原文地址；更新时缺省表示保持原值，空字符串表示清空
+    #[serde(default)]
+    pub canonical_url: Option<String>,
+    // 新建时已有标题相近的文章仍然保存
+    #[serde(default, deserialize_with = "deserialize_checkbox")]
+    pub allow_duplicate: Option<bool>,
+    // 逗号分隔的标签；更新时缺省表示保持原值，空字符串表示清空
+    #[serde(default)]
+    pub tags: Option<String>,
+    // 分类的 slug；更新时缺省表示保持原值，空字符串表示不分类
+    #[serde(default)]
+    pub category: Option<String>,
+    // 保存为草稿；更新时为 true 表示撤回为草稿，发布草稿走单独的接口
+    #[serde(default, deserialize_with = "deserialize_checkbox")]
+    pub draft: Option<bool>,
+}
+
+// 同时接受JSON布尔值和表单复选框的 "on"
+pub fn deserialize_checkbox<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Text(String),
+    }
+    Ok(Option::<Flag>::deserialize(deserializer)?.map(|flag| match flag {
+        Flag::Bool(b) => b,
+        Flag::Text(t) => matches!(t.as_str(), "on" | "true" | "1"),
+    }))
+}
+
+impl ArticleForm {
+    // 去除首尾空白，空字符串表示清空
+    pub(crate) fn meta_description(&self) -> Option<String> {
+        self.meta_description.as_deref().map(|d| d.trim().to_string())
+    }
+
+    pub(crate) fn meta_keywords(&self) -> Option<String> {
+        self.meta_keywords.as_deref().map(|k| k.trim().to_string())
+    }
+
+    pub(crate) fn is_draft(&self) -> bool {
+        self.draft.unwrap_or(false)
+    }
+
+    pub(crate) fn status(&self) -> &'static str {
+        if self.is_draft() { models::STATUS_DRAFT } else { models::STATUS_PUBLISHED }
+    }
+
+    pub(crate) fn tags(&self) -> Option<Vec<String>> {
+        self.tags.as_deref().map(services::tags::parse_tags)
+    }
+
+    // 无法识别的取值按未填写处理
+    pub(crate) fn text_direction(&self) -> Option<&'static str> {
+        self.text_direction.as_deref().and_then(TextDirection::parse).map(|d| d.as_str())
+    }
+
+    // 不是合法的 http(s) 地址时返回 Err
+    pub(crate) fn canonical_url(&self) -> Result<Option<String>, ()> {
+        match self.canonical_url.as_deref().map(str::trim) {
+            None => Ok(None),
+            Some("") => Ok(Some(String::new())),
+            Some(url) => services::canonical::normalize(url).map(Some).ok_or(()),
+        }
+    }
+}
+
+// Blog post structure
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Post {
+    pub id: u32,
+    // 文章页的路径，有 slug 时为 /post/{slug}
+    pub url: String,
+    pub title: String,
+    pub summary: String,
+    pub content: String,
+    pub date: String,
+    // 解析后的 dir 属性值：ltr 或 rtl
+    pub dir: &'static str,
+}
+
+// Application state, storing blog posts
+pub struct AppState {
+    pub template: Tera,
+    pub markdown_service: MarkdownService,
+    // Most recent articles rendered into the cache at startup and after cache clears
+    pub prewarm_count: usize,
+    pub request_metrics: metrics::RequestMetrics,
+    pub metrics_history: metrics::MetricsHistory,
+    // Reported with the uptime by /api/status
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    // Where `deploy.sh backup` writes its snapshots; the newest is shown on the dashboard
+    pub backup_dir: std::path::PathBuf,
+    // Zero disables the per-request deadline
+    pub request_timeout: std::time::Duration,
+    // Renders and queries at least this slow are logged and counted; zero disables
+    pub slow_render_threshold: std::time::Duration,
+    pub slow_query_threshold: std::time::Duration,
+    pub email_service: Option<EmailService>,
+    // Email the admin when someone signs in from an IP not seen before
+    pub notify_new_login_ip: bool,
+    // Proxies allowed to name the client in Forwarded / X-Forwarded-For; everyone else is keyed by peer address
+    pub trusted_proxies: services::client_ip::TrustedProxies,
+    // Emoji readers may react with, in display order
+    pub reaction_emojis: Vec<String>,
+    pub reaction_limiter: RateLimiter,
+    // Replies deeper than this are shown at the last level
+    pub comment_max_depth: usize,
+    // Publish comments immediately instead of queueing them for moderation
+    pub comment_auto_approve: bool,
+    pub comment_limiter: RateLimiter,
+    // At most one comment per IP within this window
+    pub comment_cooldown: RateLimiter,
+    // Rejects the same comment body posted again by one IP or on one article
+    pub comment_duplicates: DuplicateFilter,
+    // Signs links that act without a session, such as moderation links in emails
+    pub signer: Signer,
+    // Public base URL used to build absolute links, without trailing slash
+    pub site_url: String,
+    pub avatar_mode: services::avatar::AvatarMode,
+    // Consulted before storing comments; flagged ones go to the queue as spam
+    pub spam_checker: Option<Box<dyn services::spam::SpamChecker>>,
+    // Search engine / WebSub notifications after publishing; None when nothing is configured
+    pub pinger: Option<services::ping::Pinger>,
+    // Uploaded files such as article attachments
+    pub media: services::media::MediaStorage,
+    // Public API list responses; cleared whenever an article changes
+    pub api_cache: services::response_cache::ResponseCache,
+    pub sitemap_cache: services::response_cache::ResponseCache,
+    // Cache-Control headers by route, added by middleware::cache_control
+    pub cache_policy: services::cache_policy::CachePolicy,
+    // Loaded from the settings table at startup and after each save; shared with template functions
+    pub settings: std::sync::Arc<std::sync::RwLock<SiteSettings>>,
+    // Articles pinned to the navigation menu, reloaded whenever a pin is added or removed
+    pub navigation: std::sync::Arc<std::sync::RwLock<Vec<NavLink>>>,
+    // Loaded from PLUGINS at startup; their routes and render stages are added once
+    pub plugins: services::plugins::PluginRegistry,
+    // Handlers publish article, comment and login events here; side effects live in `subscribers`
+    pub events: services::events::EventBus,
+}
+
+// 后台可修改的站点设置
+#[derive(Debug, Clone, Default)]
+pub struct SiteSettings {
+    pub summary: SummaryOptions,
+    pub robots: RobotsOptions,
+    pub security: SecurityTxtOptions,
+    pub branding: Branding,
+    pub trash: TrashOptions,
+    pub feed: FeedOptions,
+    pub search: SearchRanking,
+    pub features: FeatureFlags,
+    pub dates: DateOptions,
+}
+
+impl SiteSettings {
+    pub fn from_map(settings: &std::collections::HashMap<String, String>) -> Self {
+        Self {
+            summary: SummaryOptions::from_settings(settings),
+            robots: RobotsOptions::from_settings(settings),
+            security: SecurityTxtOptions::from_settings(settings),
+            branding: Branding::from_settings(settings),
+            trash: TrashOptions::from_settings(settings),
+            feed: FeedOptions::from_settings(settings),
+            search: SearchRanking::from_settings(settings),
+            features: FeatureFlags::from_settings(settings),
+            dates: DateOptions::from_settings(settings),
+        }
+    }
+}
+
+impl AppState {
+    pub fn settings(&self) -> SiteSettings {
+        self.settings.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+// Previous sign-in shown on the dashboard so unexpected logins stand out
+#[derive(Serialize, Deserialize)]
+pub struct LastLogin {
+    pub at: String,
+    pub ip: String,
+}
+
+// 模板过滤器：标题最后一个词不单独成行
+pub fn nowidow_filter(value: &tera::Value, _args: &std::collections::HashMap<String, tera::Value>) -> tera::Result<tera::Value> {
+    match value.as_str() {
+        Some(text) => Ok(tera::Value::String(services::text::no_widow(text))),
+        None => Ok(value.clone()),
+    }
+}
+
+// 日期过滤器的输出形式
+#[derive(Clone, Copy)]
+pub enum DateStyle {
+    Date,
+    DateTime,
+    Iso,
+}
+
+// 模板过滤器 format_date / format_datetime / iso_datetime：按站点时区和日期格式显示数据库中的时间。
+// format_date 和 format_datetime 可用 format 参数临时指定其他格式；iso_datetime 用于 <time datetime>
+pub fn date_filter(
+    settings: std::sync::Arc<std::sync::RwLock<SiteSettings>>,
+    style: DateStyle,
+) -> impl Fn(&tera::Value, &std::collections::HashMap<String, tera::Value>) -> tera::Result<tera::Value> + Send + Sync {
+    move |value, args| {
+        let Some(text) = value.as_str() else {
+            return Ok(value.clone());
+        };
+        let dates = &settings.read().unwrap_or_else(|e| e.into_inner()).dates;
+        let formatted = match (style, args.get("format").and_then(|f| f.as_str())) {
+            (DateStyle::Iso, _) => dates.rfc3339(text),
+            (_, Some(format)) => dates.format(text, format),
+            (DateStyle::Date, None) => dates.format_date(text),
+            (DateStyle::DateTime, None) => dates.format_datetime(text),
+        };
+        Ok(tera::Value::String(formatted))
+    }
+}
+
+// 模板函数 site_asset(name="favicon"|"logo")：已上传时返回带版本号的地址，否则为 null
+pub fn site_asset_function(
+    settings: std::sync::Arc<std::sync::RwLock<SiteSettings>>,
+) -> impl Fn(&std::collections::HashMap<String, tera::Value>) -> tera::Result<tera::Value> + Send + Sync {
+    move |args| {
+        let asset = args
+            .get("name")
+            .and_then(|v| v.as_str())
+            .and_then(BrandAsset::parse)
+            .ok_or_else(|| tera::Error::msg("site_asset expects name=\"favicon\" or name=\"logo\""))?;
+        let branding = &settings.read().unwrap_or_else(|e| e.into_inner()).branding;
+        Ok(branding.url(asset).map(tera::Value::String).unwrap_or(tera::Value::Null))
+    }
+}
+
+// 模板函数 nav_links()：固定到导航菜单的文章
+pub fn nav_links_function(
+    navigation: std::sync::Arc<std::sync::RwLock<Vec<NavLink>>>,
+) -> impl Fn(&std::collections::HashMap<String, tera::Value>) -> tera::Result<tera::Value> + Send + Sync {
+    move |_| {
+        let links = navigation.read().unwrap_or_else(|e| e.into_inner());
+        tera::to_value(&*links).map_err(tera::Error::from)
+    }
+}
+
+// 按部署配置开启的功能，模板据此显示或隐藏相应部分
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateFeatures {
+    pub reactions: bool,
+    pub avatars: bool,
+    // 评论需审核后才显示
+    pub comment_moderation: bool,
+}
+
+// site().features：部署配置的功能加上站点设置中的功能开关
+#[derive(Serialize)]
+pub struct SiteFeatures<'a> {
+    #[serde(flatten)]
+    pub deployment: &'a TemplateFeatures,
+    #[serde(flatten)]
+    pub flags: FeatureFlags,
+}
+
+// 模板函数 site()：每次渲染都可用的站点信息，处理函数无需逐个插入上下文。
+// 返回 {url, favicon, logo, nav, user, theme, path, features, summary, feed}；user 为当前登录用户，访客为 null，
+// theme 为读者选择的配色（auto / light / dark），path 为当前请求的路径
+pub fn site_function(
+    settings: std::sync::Arc<std::sync::RwLock<SiteSettings>>,
+    navigation: std::sync::Arc<std::sync::RwLock<Vec<NavLink>>>,
+    site_url: String,
+    features: TemplateFeatures,
+) -> impl Fn(&std::collections::HashMap<String, tera::Value>) -> tera::Result<tera::Value> + Send + Sync {
+    move |_| {
+        let settings = settings.read().unwrap_or_else(|e| e.into_inner());
+        let navigation = navigation.read().unwrap_or_else(|e| e.into_inner());
+        // 在请求之外（如后台任务）渲染时没有当前用户，使用默认配色
+        let request = middleware::REQUEST_GLOBALS.try_with(|globals| globals.clone()).unwrap_or_default();
+        Ok(serde_json::json!({
+            "url": site_url,
+            "favicon": settings.branding.url(BrandAsset::Favicon),
+            "logo": settings.branding.url(BrandAsset::Logo),
+            "nav": *navigation,
+            "user": request.user.map(|username| serde_json::json!({ "username": username })),
+            "theme": request.theme,
+            "path": request.path,
+            "features": SiteFeatures { deployment: &features, flags: settings.features },
+            "summary": settings.summary,
+            "feed": settings.feed,
+        }))
+    }
+}
+
+// 匿名读者的回应标识，保存在session中用于去重
+pub const REACTION_SESSION_KEY: &str = "reaction_key";
+
+// 评论邮件中免登录操作的签名链接
+pub fn signed_comment_link(data: &AppState, comment_id: i64, action: &str, valid_days: i64) -> String {
+    let expires_at = chrono::Utc::now().timestamp() + valid_days * 24 * 3600;
+    let token = data.signer.sign(&format!("comment:{}:{}", comment_id, action), expires_at);
+    let path = match action {
+        "unsubscribe" => format!("/comments/{}/unsubscribe", comment_id),
+        _ => format!("/comments/{}/moderate/{}", comment_id, action),
+    };
+    format!("{}{}?token={}", data.site_url, path, token)
+}
+
+// 回复公开后通知订阅了回复提醒的父评论作者
+pub async fn notify_comment_reply(data: &AppState, pool: &SqlitePool, reply: &models::Comment) {
+    let (Some(email), Some(parent_id)) = (data.email_service.clone(), reply.parent_id) else {
+        return;
+    };
+    let parent = match models::get_comment(pool, parent_id).await {
+        Ok(parent) => parent,
+        Err(e) => {
+            error!("Failed to load parent comment {}: {}", parent_id, e);
+            return;
+        }
+    };
+    let Some(address) = parent.author_email.as_deref().filter(|_| parent.notify_replies) else {
+        return;
+    };
+    // 自己回复自己不通知
+    if reply.author_email.as_deref().is_some_and(|e| e.eq_ignore_ascii_case(address)) {
+        return;
+    }
+    let to = match EmailService::parse_mailbox(address) {
+        Ok(to) => to,
+        Err(e) => {
+            log::warn!("Skipping reply notification: {}", e);
+            return;
+        }
+    };
+
+    let subject = format!("{} replied to your comment", reply.author_name);
+    let body = format!(
+        "{} replied to your comment:\n\n{}\n\nView the conversation: {}/post/{}#comments\n\n\
+         Stop receiving reply notifications for this comment: {}\n",
+        reply.author_name,
+        reply.content,
+        data.site_url,
+        reply.article_id,
+        signed_comment_link(data, parent.id, "unsubscribe", 365),
+    );
+    tokio::spawn(async move {
+        if let Err(e) = email.send(to, &subject, &body).await {
+            error!("Failed to send reply notification: {}", e);
+        }
+    });
+}
+
+// 链接表为空时（首次升级）按现有文章内容建立链接图
+pub async fn backfill_article_links(data: &AppState, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let links: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM article_links").fetch_one(pool).await?;
+    if links > 0 {
+        return Ok(());
+    }
+    let articles = sqlx::query_as::<_, (i64, String)>("SELECT id, content FROM articles").fetch_all(pool).await?;
+    for (id, content) in &articles {
+        index_article_links(data, pool, *id, content).await;
+    }
+    Ok(())
+}
+
+// 文章新建、修改或删除后调用，清除依赖文章列表的缓存
+pub fn articles_changed(data: &AppState) {
+    data.api_cache.invalidate();
+    data.sitemap_cache.invalidate();
+}
+
+// 永久删除在回收站中超过保留期的文章，返回删除的数量
+pub async fn purge_expired_trash(data: &AppState, pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+    let Some(before) = data.settings().trash.purge_before(chrono::Utc::now().naive_utc()) else {
+        return Ok(0);
+    };
+    let mut purged = 0;
+    for article_id in models::get_trashed_before(pool, &before).await? {
+        if purge_article(data, pool, article_id, "system").await?.is_some() {
+            purged += 1;
+        }
+    }
+    Ok(purged)
+}
+
+// 永久删除文章并清理其附件文件，记录到审计日志；返回文章标题，文章不存在时返回 None
+pub async fn purge_article(data: &AppState, pool: &SqlitePool, article_id: i64, actor: &str) -> Result<Option<String>, sqlx::Error> {
+    let Some((title, attachments)) = models::delete_article(pool, article_id).await? else {
+        return Ok(None);
+    };
+    for stored_name in attachments {
+        if let Err(e) = data.media.delete(&stored_name).await {
+            error!("Failed to remove media file {}: {}", stored_name, e);
+        }
+    }
+    record_activity(pool, actor, "purge_article", &services::activity::article_target(article_id), &title).await;
+    Ok(Some(title))
+}
+
+// 写入审计日志，同时出现在仪表盘动态中；失败只记录日志
+pub async fn record_activity(pool: &SqlitePool, actor: &str, action: &str, target: &str, detail: &str) {
+    if let Err(e) = models::record_audit(pool, actor, action, target, detail).await {
+        error!("Failed to record audit entry: {}", e);
+    }
+}
+
+// 保存修订快照；失败只记录日志，不影响文章保存
+pub async fn snapshot_revision(pool: &SqlitePool, article_id: i64, author: Option<&str>) {
+    if let Err(e) = models::save_revision(pool, article_id, author).await {
+        error!("Failed to save revision for article {}: {}", article_id, e);
+    }
+}
+
+// 新建文章后生成 slug，改标题后重新生成；失败时文章仍可通过 /post/{id} 访问
+pub async fn refresh_article_slug(pool: &SqlitePool, article_id: i64) {
+    if let Err(e) = models::update_slug(pool, article_id).await {
+        error!("Failed to update slug of article {}: {}", article_id, e);
+    }
+}
+
+// 标题与 title 相近、可能是同一篇的已有文章；查询失败时不阻止保存
+pub async fn find_duplicate_article(pool: &SqlitePool, title: &str) -> Option<(i64, String)> {
+    let titles = models::get_article_titles(pool).await.unwrap_or_else(|e| {
+        error!("Failed to fetch article titles: {}", e);
+        Vec::new()
+    });
+    services::duplicates::find_duplicate(title, titles.iter().map(|(id, title)| (*id, title.as_str())))
+        .map(|(id, title)| (id, title.to_string()))
+}
+
+// 保存文章后记录它链接到的其他文章
+pub async fn index_article_links(data: &AppState, pool: &SqlitePool, article_id: i64, content: &str) {
+    let targets = services::link_graph::internal_links(content, &data.site_url);
+    if let Err(e) = models::replace_article_links(pool, article_id, &targets).await {
+        error!("Failed to record links of article {}: {}", article_id, e);
+    }
+}
+
+// 保存文章的标签，None 表示不修改；失败只记录日志
+pub async fn save_article_tags(pool: &SqlitePool, article_id: i64, tags: Option<Vec<String>>) {
+    let Some(tags) = tags else {
+        return;
+    };
+    if let Err(e) = models::set_article_tags(pool, article_id, &tags).await {
+        error!("Failed to save tags of article {}: {}", article_id, e);
+    }
+}
+
+// 附件在模板和 JSON 中的展示形式
+#[derive(Serialize)]
+pub struct AttachmentView {
+    pub id: i64,
+    pub name: String,
+    pub url: String,
+    pub content_type: String,
+    pub size: String,
+}
+
+impl From<models::Media> for AttachmentView {
+    fn from(media: models::Media) -> Self {
+        Self {
+            id: media.id,
+            url: services::media::MediaStorage::url(&media.stored_name),
+            name: media.original_name,
+            content_type: media.content_type,
+            size: services::media::human_size(media.size),
+        }
+    }
+}
+
+// 从数据库重新加载片段到Markdown服务
+pub async fn reload_snippets(data: &AppState, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let snippets = models::get_snippets(pool).await?;
+    data.markdown_service.set_snippets(snippets.into_iter().map(|s| (s.name, s.content)).collect());
+    Ok(())
+}
+
+pub async fn reload_navigation(data: &AppState, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let links = models::get_nav_items(pool)
+        .await?
+        .iter()
+        .map(|item| NavLink::for_article(item.article_id, &item.label))
+        .collect();
+    *data.navigation.write().unwrap_or_else(|e| e.into_inner()) = links;
+    Ok(())
+}
+
+pub async fn reload_settings(data: &AppState, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let settings = SiteSettings::from_map(&models::get_settings(pool).await?);
+    *data.settings.write().unwrap_or_else(|e| e.into_inner()) = settings;
+    Ok(())
+}
+
+pub async fn run_db_maintenance(pool: &SqlitePool, actor: &str) -> Result<models::MaintenanceReport, sqlx::Error> {
+    let report = models::run_maintenance(pool).await?;
+    info!(
+        "Database maintenance finished: {} -> {} bytes (vacuum {}ms, analyze {}ms, optimize {}ms)",
+        report.size_before, report.size_after, report.vacuum_ms, report.analyze_ms, report.optimize_ms
+    );
+    let detail = serde_json::to_string(&report).unwrap_or_default();
+    if let Err(e) = models::record_audit(pool, actor, "db_maintenance", "database", &detail).await {
+        error!("Failed to record audit log: {}", e);
+    }
+    Ok(report)
+}
+
+impl AppState {
+    /// State for the server configured by environment variables; templates are
+    /// loaded from `templates/`. Settings, snippets and navigation start empty
+    /// until `reload_settings` and friends read them from the database.
+    pub fn from_env() -> Result<Self, tera::Error> {
+        // Initialize template system
+        let mut tera = Tera::new("templates/**/*")?;
+        tera.autoescape_on(vec!["html", ".html", ".htm"]);
+        tera.register_filter("nowidow", nowidow_filter);
+        let site_settings = std::sync::Arc::new(std::sync::RwLock::new(SiteSettings::default()));
+        tera.register_function("site_asset", site_asset_function(site_settings.clone()));
+        tera.register_filter("format_date", date_filter(site_settings.clone(), DateStyle::Date));
+        tera.register_filter("format_datetime", date_filter(site_settings.clone(), DateStyle::DateTime));
+        tera.register_filter("iso_datetime", date_filter(site_settings.clone(), DateStyle::Iso));
+        let navigation = std::sync::Arc::new(std::sync::RwLock::new(Vec::new()));
+        tera.register_function("nav_links", nav_links_function(navigation.clone()));
+
+        // Create application state with optimized markdown service
+        let cache_ttl = std::env::var("MARKDOWN_CACHE_TTL")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<u64>()
+            .unwrap_or(3600);
+
+        let max_cache_size = std::env::var("MARKDOWN_MAX_CACHE_SIZE")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse::<usize>()
+            .unwrap_or(1000);
+
+        let max_content_size = std::env::var("MARKDOWN_MAX_CONTENT_SIZE")
+            .unwrap_or_else(|_| "1048576".to_string())
+            .parse::<usize>()
+            .unwrap_or(1024 * 1024);
+
+        // 超过该大小且未命中缓存的文档在阻塞线程池中渲染，0 表示始终在当前 worker 渲染
+        let offload_threshold = std::env::var("MARKDOWN_OFFLOAD_THRESHOLD")
+            .unwrap_or_else(|_| "32768".to_string())
+            .parse::<usize>()
+            .unwrap_or(32 * 1024);
+
+        // 启动和清空缓存后预热的文章数，不超过缓存容量，0 表示不预热
+        let prewarm_count = std::env::var("MARKDOWN_PREWARM_COUNT")
+            .unwrap_or_else(|_| "20".to_string())
+            .parse::<usize>()
+            .unwrap_or(20)
+            .min(max_cache_size);
+
+        // 缓存的HTML总大小上限（字节），按内容大小而非条目数限制内存
+        let max_cache_bytes = std::env::var("MARKDOWN_MAX_CACHE_BYTES")
+            .unwrap_or_else(|_| "67108864".to_string())
+            .parse::<usize>()
+            .unwrap_or(64 * 1024 * 1024);
+
+        // 代码块超过该大小(字节)时截断并显示提示，0 表示不限制
+        let max_code_block_size = std::env::var("MARKDOWN_MAX_CODE_BLOCK_SIZE")
+            .unwrap_or_else(|_| services::markdown::DEFAULT_MAX_CODE_BLOCK_SIZE.to_string())
+            .parse::<usize>()
+            .unwrap_or(services::markdown::DEFAULT_MAX_CODE_BLOCK_SIZE);
+
+        // 额外的代码语言别名，格式 alias=Language，逗号分隔；覆盖同名内置别名
+        let language_aliases = services::markdown::parse_language_aliases(
+            &std::env::var("MARKDOWN_LANGUAGE_ALIASES").unwrap_or_default()
+        );
+
+        // 热门条目每次命中都会延长 TTL，但渲染超过该时长后仍会重新渲染
+        let cache_max_lifetime = std::env::var("MARKDOWN_CACHE_MAX_LIFETIME")
+            .unwrap_or_else(|_| "86400".to_string())
+            .parse::<u64>()
+            .unwrap_or(86400);
+
+        let mut markdown_service = MarkdownService::with_cache_config(
+            std::time::Duration::from_secs(cache_ttl),
+            max_cache_size,
+            max_content_size,
+            max_cache_bytes,
+            std::time::Duration::from_secs(cache_max_lifetime),
+        )
+        .with_offload_threshold(offload_threshold)
+        .with_max_code_block_size(max_code_block_size)
+        .with_language_aliases(language_aliases);
+
+        // 额外的 .sublime-syntax 语法定义目录(递归加载)，加载失败时仅使用内置语法
+        if let Ok(syntax_dir) = std::env::var("MARKDOWN_SYNTAX_DIR") {
+            match markdown_service.load_extra_syntaxes(&syntax_dir) {
+                Ok(count) => info!("Loaded {} extra syntax definitions from {}", count, syntax_dir),
+                Err(e) => error!("Failed to load syntax definitions from {}: {}", syntax_dir, e),
+            }
+        }
+
+        // 额外的 .tmTheme 代码高亮主题目录，文章可在前言中用 highlight_theme 选用
+        if let Ok(theme_dir) = std::env::var("MARKDOWN_THEME_DIR") {
+            match markdown_service.load_extra_themes(&theme_dir) {
+                Ok(count) => info!("Loaded {} extra highlight themes from {}", count, theme_dir),
+                Err(e) => error!("Failed to load highlight themes from {}: {}", theme_dir, e),
+            }
+        }
+
+        info!("Markdown service configured with cache TTL: {}s (max lifetime {}s), max cache size: {} entries / {} bytes, max content size: {} bytes, offload threshold: {} bytes, max code block size: {} bytes", 
+              cache_ttl, cache_max_lifetime, max_cache_size, max_cache_bytes, max_content_size, offload_threshold, max_code_block_size);
+
+        let request_timeout = std::env::var("REQUEST_TIMEOUT")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse::<u64>()
+            .unwrap_or(30);
+        info!("Request timeout: {}s", request_timeout);
+
+        let notify_new_login_ip = std::env::var("LOGIN_NOTIFY_NEW_IP")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        let trusted_proxies = services::client_ip::TrustedProxies::from_env();
+        if trusted_proxies.is_empty() {
+            info!("No trusted proxies; client IPs come from the connection");
+        }
+
+        // 逗号分隔的可用表情列表
+        let reaction_emojis: Vec<String> = std::env::var("REACTION_EMOJIS")
+            .unwrap_or_else(|_| "👍,❤️,🎉,😂,🤔".to_string())
+            .split(',')
+            .map(|e| e.trim().to_string())
+            .filter(|e| !e.is_empty())
+            .collect();
+
+        let reaction_rate_limit = std::env::var("REACTION_RATE_LIMIT")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse::<u32>()
+            .unwrap_or(30);
+
+        let comment_max_depth = std::env::var("COMMENT_MAX_DEPTH")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse::<usize>()
+            .unwrap_or(3);
+
+        let comment_auto_approve = std::env::var("COMMENT_AUTO_APPROVE")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        let comment_rate_limit = std::env::var("COMMENT_RATE_LIMIT")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse::<u32>()
+            .unwrap_or(5);
+
+        let comment_min_interval = std::env::var("COMMENT_MIN_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "15".to_string())
+            .parse::<u64>()
+            .unwrap_or(15);
+
+        let comment_duplicate_window = std::env::var("COMMENT_DUPLICATE_WINDOW_MINUTES")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse::<u64>()
+            .unwrap_or(60);
+
+        let site_url = std::env::var("SITE_URL")
+            .unwrap_or_else(|_| "http://localhost:8080".to_string())
+            .trim_end_matches('/')
+            .to_string();
+
+        // 插件的渲染阶段在服务开始前加入，之后不再变化
+        let plugins = services::plugins::PluginRegistry::from_env(
+            services::plugins::BUILTIN_PLUGINS,
+            &services::plugins::PluginConfig { site_url: site_url.clone() },
+        );
+        plugins.install_render_stages(&mut markdown_service);
+
+        let spam_checker = services::spam::Akismet::from_env(&site_url)
+            .map(|akismet| Box::new(akismet) as Box<dyn services::spam::SpamChecker>);
+
+        // Milliseconds before a markdown render / SQL query is reported as slow; 0 disables
+        let slow_render_threshold = std::env::var("SLOW_RENDER_THRESHOLD_MS")
+            .unwrap_or_else(|_| "200".to_string())
+            .parse::<u64>()
+            .unwrap_or(200);
+        let slow_query_threshold = std::env::var("SLOW_QUERY_THRESHOLD_MS")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse::<u64>()
+            .unwrap_or(100);
+
+        let metrics_snapshot_interval = metrics_snapshot_interval();
+
+        // Seconds public API list responses are cached in memory and by clients; 0 disables
+        let api_cache_ttl = std::env::var("API_CACHE_TTL")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse::<u64>()
+            .unwrap_or(60);
+
+        // Seconds generated sitemaps are kept in memory; cleared whenever an article changes, 0 disables
+        let sitemap_cache_ttl = std::env::var("SITEMAP_CACHE_TTL")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<u64>()
+            .unwrap_or(3600);
+
+        // Cache-Control by route pattern for responses whose handler sets none
+        let cache_policy = services::cache_policy::CachePolicy::from_env();
+        info!("Cache-Control rules: {}", cache_policy.len());
+
+        tera.register_function("site", site_function(
+            site_settings.clone(),
+            navigation.clone(),
+            site_url.clone(),
+            TemplateFeatures {
+                reactions: !reaction_emojis.is_empty(),
+                avatars: services::avatar::AvatarMode::from_env() != services::avatar::AvatarMode::Off,
+                comment_moderation: !comment_auto_approve,
+            },
+        ));
+
+        Ok(AppState {
+            template: tera,
+            markdown_service,
+            prewarm_count,
+            request_metrics: metrics::RequestMetrics::new(),
+            metrics_history: metrics::MetricsHistory::new((86400 / metrics_snapshot_interval) as usize),
+            started_at: chrono::Utc::now(),
+            backup_dir: std::env::var("BACKUP_DIR").unwrap_or_else(|_| "./backups".to_string()).into(),
+            request_timeout: std::time::Duration::from_secs(request_timeout),
+            slow_render_threshold: std::time::Duration::from_millis(slow_render_threshold),
+            slow_query_threshold: std::time::Duration::from_millis(slow_query_threshold),
+            email_service: EmailService::from_env(),
+            notify_new_login_ip,
+            trusted_proxies,
+            reaction_emojis,
+            reaction_limiter: RateLimiter::new(reaction_rate_limit, std::time::Duration::from_secs(60)),
+            comment_max_depth,
+            comment_auto_approve,
+            comment_limiter: RateLimiter::new(comment_rate_limit, std::time::Duration::from_secs(60)),
+            // 窗口为 0 时每次都重新计数，相当于不限制
+            comment_cooldown: RateLimiter::new(1, std::time::Duration::from_secs(comment_min_interval)),
+            comment_duplicates: DuplicateFilter::new(std::time::Duration::from_secs(comment_duplicate_window * 60)),
+            signer: Signer::from_env(),
+            site_url: site_url.clone(),
+            avatar_mode: services::avatar::AvatarMode::from_env(),
+            spam_checker,
+            pinger: services::ping::Pinger::from_env(&site_url),
+            media: services::media::MediaStorage::from_env(),
+            api_cache: services::response_cache::ResponseCache::new(std::time::Duration::from_secs(api_cache_ttl)),
+            sitemap_cache: services::response_cache::ResponseCache::new(std::time::Duration::from_secs(sitemap_cache_ttl)),
+            cache_policy,
+            settings: site_settings,
+            navigation,
+            plugins,
+            events: services::events::EventBus::new(),
+        })
+    }
+}
+
+/// Seconds between metrics history snapshots (`METRICS_SNAPSHOT_INTERVAL`); 24 hours of snapshots are kept
+pub fn metrics_snapshot_interval() -> u64 {
+    std::env::var("METRICS_SNAPSHOT_INTERVAL")
+        .unwrap_or_else(|_| "300".to_string())
+        .parse::<u64>()
+        .unwrap_or(300)
+        .max(1)
+}
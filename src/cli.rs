@@ -0,0 +1,210 @@
+//! Maintenance commands that run instead of the server:
+//!
+//! ```text
+//! bluster export-site <archive.zip> [--with-passwords]
+//! bluster import-site <archive.zip>
+//! bluster seed --demo
+//! ```
+//!
+//! They use the same `data/blog.db` and `MEDIA_DIR` as the server, so they are
+//! run from the directory the server normally starts in.
+
+use bluster::models;
+use bluster::services::media::MediaStorage;
+use bluster::services::site_archive::SiteArchive;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage:
+  bluster                                             start the server
+  bluster export-site <archive.zip> [--with-passwords] write the whole site to an archive
+  bluster import-site <archive.zip>                   load an archive into a site without articles
+  bluster seed --demo                                 add demo articles and comments for theme work";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Serve,
+    ExportSite {
+        path: PathBuf,
+        /// Include password and security answer hashes so accounts keep working
+        with_passwords: bool,
+    },
+    ImportSite {
+        path: PathBuf,
+    },
+    /// Demo articles and comments from `services::demo`
+    SeedDemo,
+}
+
+/// Parse the arguments after the program name
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(Command::Serve);
+    };
+    let (flags, paths): (Vec<&String>, Vec<&String>) = rest.iter().partition(|arg| arg.starts_with("--"));
+    if command == "seed" {
+        if let Some(arg) = paths.first() {
+            return Err(format!("Unexpected argument for seed: {}", arg));
+        }
+        if let Some(flag) = flags.iter().find(|flag| flag.as_str() != "--demo") {
+            return Err(format!("Unknown option for seed: {}", flag));
+        }
+        if flags.is_empty() {
+            return Err("seed needs --demo".to_string());
+        }
+        return Ok(Command::SeedDemo);
+    }
+    let path = match paths.as_slice() {
+        [path] => PathBuf::from(path.as_str()),
+        [] => return Err(format!("{} needs an archive path", command)),
+        _ => return Err(format!("{} takes a single archive path", command)),
+    };
+    match command.as_str() {
+        "export-site" => {
+            let mut with_passwords = false;
+            for flag in flags {
+                match flag.as_str() {
+                    "--with-passwords" => with_passwords = true,
+                    other => return Err(format!("Unknown option for export-site: {}", other)),
+                }
+            }
+            Ok(Command::ExportSite { path, with_passwords })
+        }
+        "import-site" => match flags.first() {
+            Some(flag) => Err(format!("Unknown option for import-site: {}", flag)),
+            None => Ok(Command::ImportSite { path }),
+        },
+        other => Err(format!("Unknown command: {}", other)),
+    }
+}
+
+/// Run a maintenance command and return the process exit code
+pub async fn run(command: Command) -> i32 {
+    let result = match command {
+        Command::Serve => return 0,
+        Command::ExportSite { path, with_passwords } => export_site(&path, with_passwords).await,
+        Command::ImportSite { path } => import_site(&path).await,
+        Command::SeedDemo => seed_demo().await,
+    };
+    match result {
+        Ok(()) => 0,
+        Err(message) => {
+            eprintln!("{}", message);
+            1
+        }
+    }
+}
+
+async fn export_site(path: &std::path::Path, with_passwords: bool) -> Result<(), String> {
+    let pool = models::init_db(std::time::Duration::ZERO)
+        .await
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let archive = models::export_site(&pool, with_passwords)
+        .await
+        .map_err(|e| format!("Failed to read site: {}", e))?;
+
+    let media = MediaStorage::from_env();
+    let names = media.stored_names().await.map_err(|e| format!("Failed to list media: {}", e))?;
+    let mut files = Vec::with_capacity(names.len());
+    for name in names {
+        let Some(file_path) = media.path_for(&name) else {
+            continue;
+        };
+        let bytes = tokio::fs::read(&file_path)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", file_path.display(), e))?;
+        files.push((name, bytes));
+    }
+
+    let bytes = archive.write(&files).map_err(|e| e.to_string())?;
+    tokio::fs::write(path, &bytes)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    println!(
+        "Exported {} articles, {} comments, {} users and {} media files to {} ({} bytes)",
+        archive.articles.len(),
+        archive.comments.len(),
+        archive.users.len(),
+        files.len(),
+        path.display(),
+        bytes.len()
+    );
+    if !with_passwords {
+        println!("Passwords were not exported; accounts created by the import get temporary passwords");
+    }
+    Ok(())
+}
+
+async fn import_site(path: &std::path::Path) -> Result<(), String> {
+    let bytes = tokio::fs::read(path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let (archive, files) = SiteArchive::read(&bytes).map_err(|e| e.to_string())?;
+    let pool = models::init_db(std::time::Duration::ZERO)
+        .await
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+
+    // 先写入文件：数据库导入失败时多出的文件不会被引用，可以在媒体页清理
+    let media = MediaStorage::from_env();
+    for (name, data) in &files {
+        media
+            .restore(name, data)
+            .await
+            .map_err(|e| format!("Failed to write media file {}: {}", name, e))?;
+    }
+    let temporary_passwords = models::import_site(&pool, &archive).await.map_err(|e| e.to_string())?;
+
+    println!(
+        "Imported {} articles, {} comments, {} users and {} media files exported at {} UTC",
+        archive.articles.len(),
+        archive.comments.len(),
+        archive.users.len(),
+        files.len(),
+        archive.exported_at
+    );
+    for (username, password) in temporary_passwords {
+        println!("Temporary password for {}: {} (must be changed after signing in)", username, password);
+    }
+    Ok(())
+}
+
+async fn seed_demo() -> Result<(), String> {
+    let pool = models::init_db(std::time::Duration::ZERO)
+        .await
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let report = models::seed_demo(&pool).await.map_err(|e| format!("Failed to add demo content: {}", e))?;
+    println!("Added {} demo articles and {} comments", report.articles, report.comments);
+    for title in report.skipped {
+        println!("Skipped \"{}\": a similar article already exists", title);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(&[]), Ok(Command::Serve));
+        assert_eq!(
+            parse(&args(&["export-site", "--with-passwords", "site.zip"])),
+            Ok(Command::ExportSite { path: "site.zip".into(), with_passwords: true })
+        );
+        assert_eq!(
+            parse(&args(&["import-site", "site.zip"])),
+            Ok(Command::ImportSite { path: "site.zip".into() })
+        );
+        assert!(parse(&args(&["export-site"])).is_err());
+        assert!(parse(&args(&["import-site", "a.zip", "b.zip"])).is_err());
+        assert!(parse(&args(&["import-site", "--with-passwords", "a.zip"])).is_err());
+        assert!(parse(&args(&["serve-site", "a.zip"])).is_err());
+        assert_eq!(parse(&args(&["seed", "--demo"])), Ok(Command::SeedDemo));
+        assert!(parse(&args(&["seed"])).is_err());
+        assert!(parse(&args(&["seed", "--demo", "--force"])).is_err());
+        assert!(parse(&args(&["seed", "--demo", "demo.db"])).is_err());
+    }
+}
@@ -0,0 +1,3479 @@
+//! The admin panel. Every route lives in the `/admin` scope, which
+//! `middleware::require_login` closes to anonymous requests; handlers take the
+//! signed-in username as a `CurrentUser`. The app-level
+//! `middleware::require_password_change` keeps users on the password page
+//! until the initial password is changed.
+
+use actix_web::{web, Responder, HttpResponse};
+use actix_web::middleware::from_fn;
+use actix_session::Session;
+use actix_multipart::Multipart;
+use futures_util::TryStreamExt;
+use log::{info, error};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use crate::{models, services};
+use crate::services::events::Event;
+use crate::services::markdown::{MarkdownError, RenderFeatures};
+use crate::services::feed::{FeedContent, FeedOptions};
+use crate::services::file::{ExportProfile, ImportFormat, MarkdownFile, ParsedImport, RevisionExport};
+use crate::services::media::BrandAsset;
+use crate::services::article_filter::ArticleFilter;
+use crate::services::search::SearchRanking;
+use crate::services::feature_flags::FeatureFlags;
+use crate::services::dates::DateOptions;
+use crate::services::trash::TrashOptions;
+use crate::services::well_known::{RobotsOptions, SecurityTxtOptions};
+use crate::services::summary::{SummaryOptions, SummarySource, SummaryUnit};
+use crate::services::{MarkdownService, FileService};
+use tera::Context;
+use crate::app::{
+    AppState, ArticleForm, AttachmentView, LastLogin, Origin, articles_changed, deserialize_checkbox, find_duplicate_article, index_article_links, notify_comment_reply, purge_article,
+    record_activity, refresh_article_slug, reload_navigation, reload_settings, reload_snippets, render_markdown, run_db_maintenance,
+    save_article_tags, schedule_cache_prewarm, snapshot_revision,
+};
+use crate::middleware::{self, CurrentUser};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin")
+            .wrap(from_fn(middleware::require_login))
+            .route("", web::get().to(admin_dashboard))
+            .route("/articles", web::get().to(admin_articles))
+            .route("/articles", web::post().to(admin_create_article))
+            .route("/articles/{id}/edit", web::get().to(admin_edit_article))
+            .route("/articles/{id}", web::put().to(admin_update_article))
+            .route("/articles/{id}", web::delete().to(super::api::delete_article))
+            .route("/articles/preview", web::post().to(admin_preview_markdown))
+            .route("/articles/validate", web::post().to(admin_validate_markdown))
+            .route("/articles/import", web::post().to(admin_import_article))
+            .route("/imports", web::get().to(admin_imports))
+            .route("/imports/{id}/rollback", web::post().to(admin_rollback_import))
+            .route("/trash", web::get().to(admin_trash))
+            .route("/trash/{id}/restore", web::post().to(admin_restore_article))
+            .route("/trash/{id}", web::delete().to(admin_purge_article))
+            .route("/articles/{id}/export", web::get().to(admin_export_article))
+            .route("/articles/{id}/short-link", web::post().to(admin_short_link))
+            .route("/articles/{id}/publish", web::post().to(admin_publish_article))
+            .route("/articles/{id}/presence", web::post().to(admin_article_heartbeat))
+            .route("/articles/{id}/presence", web::delete().to(admin_leave_article))
+            .route("/articles/{id}/pin", web::post().to(admin_pin_article))
+            .route("/tools/replace", web::post().to(admin_batch_replace))
+            .route("/filters", web::get().to(admin_saved_filters))
+            .route("/filters", web::post().to(admin_save_filter))
+            .route("/filters/{id}", web::delete().to(admin_delete_filter))
+            .route("/articles/{id}/pin", web::delete().to(admin_unpin_article))
+            .route("/articles/{id}/tasks/{index}", web::post().to(admin_set_task))
+            .route("/articles/{id}/attachments", web::post().to(admin_upload_attachment))
+            .route("/articles/{id}/revisions", web::get().to(admin_article_revisions))
+            .route("/articles/{id}/revisions/{a}/diff/{b}", web::get().to(admin_revision_diff))
+            .route("/attachments/{id}", web::delete().to(admin_delete_attachment))
+            .route("/media/orphans", web::get().to(admin_orphaned_media))
+            .route("/media/orphans", web::post().to(admin_delete_orphaned_media))
+            .route("/editor/upload", web::post().to(admin_editor_upload))
+            .route("/about/edit", web::get().to(admin_about_edit))
+            .route("/about", web::put().to(admin_update_about))
+            .route("/comments", web::get().to(admin_comments))
+            .route("/comments/{id}/approve", web::post().to(admin_approve_comment))
+            .route("/comments/{id}/spam", web::post().to(admin_spam_comment))
+            .route("/comments/{id}", web::delete().to(admin_delete_comment))
+            .route("/settings", web::get().to(admin_settings))
+            .route("/settings", web::post().to(admin_save_settings))
+            .route("/settings/branding/{asset}", web::post().to(admin_upload_brand_asset))
+            .route("/settings/branding/{asset}", web::delete().to(admin_delete_brand_asset))
+            .route("/categories", web::get().to(admin_categories))
+            .route("/categories", web::post().to(admin_create_category))
+            .route("/categories/{id}", web::delete().to(admin_delete_category))
+            .route("/tags", web::get().to(admin_tags))
+            .route("/tags/merge", web::post().to(admin_merge_tags))
+            .route("/tags/purge", web::post().to(admin_purge_tags))
+            .route("/snippets", web::get().to(admin_snippets))
+            .route("/snippets", web::post().to(admin_save_snippet))
+            .route("/snippets/{id}", web::delete().to(admin_delete_snippet))
+            .route("/templates", web::get().to(admin_content_templates))
+            .route("/templates", web::post().to(admin_save_content_template))
+            .route("/templates/manage", web::get().to(admin_manage_content_templates))
+            .route("/templates/{id}", web::delete().to(admin_delete_content_template))
+            .route("/profile", web::get().to(admin_profile))
+            .route("/profile", web::post().to(admin_save_profile))
+            .route("/password", web::get().to(admin_password_settings))
+            .route("/password/change", web::post().to(admin_change_password))
+            .route("/security-question", web::post().to(admin_set_security_question))
+            .route("/performance", web::get().to(admin_performance_stats))
+            .route("/analytics/referrers", web::get().to(admin_referrer_report))
+            .route("/cache/clear", web::post().to(admin_cache_clear))
+            .route("/cache/optimize", web::post().to(admin_cache_optimize))
+            .route("/db/maintenance", web::post().to(admin_db_maintenance)),
+    );
+}
+
+#[derive(Deserialize, Serialize)]
+struct AboutForm {
+    title: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ChangePasswordForm {
+    current_password: String,
+    new_password: String,
+    confirm_password: String,
+}
+
+#[derive(Deserialize)]
+struct ProfileForm {
+    #[serde(default)]
+    display_name: String,
+    #[serde(default)]
+    bio: String,
+    #[serde(default)]
+    website: String,
+    #[serde(default)]
+    avatar_url: String,
+}
+
+#[derive(Deserialize)]
+struct SecurityQuestionForm {
+    question: String,
+    answer: String,
+}
+
+#[derive(Deserialize)]
+struct PreviewRequest {
+    content: String,
+}
+
+#[derive(Serialize)]
+struct PreviewResponse {
+    html: String,
+}
+
+#[derive(Deserialize)]
+struct CategoryForm {
+    name: String,
+    // 留空时由名称生成
+    #[serde(default)]
+    slug: Option<String>,
+    // 上级分类的 ID，留空为顶级分类
+    #[serde(default)]
+    parent_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SnippetForm {
+    name: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ContentTemplateForm {
+    name: String,
+    title_pattern: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct DashboardQuery {
+    // 动态列表页码，从 1 开始
+    page: Option<i64>,
+    // 只列出符合该已保存筛选条件的文章
+    filter: Option<i64>,
+}
+
+// 仪表盘文章列表行：文章及其短链接和导航菜单名称
+#[derive(Debug, sqlx::FromRow)]
+struct DashboardArticle {
+    #[sqlx(flatten)]
+    article: models::Article,
+    short_code: Option<String>,
+    short_clicks: Option<i64>,
+    menu_label: Option<String>,
+}
+
+async fn admin_dashboard(
+    data: web::Data<AppState>,
+    query: web::Query<DashboardQuery>,
+    session: Session,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> actix_web::Result<HttpResponse> {
+    let last_login = session.get::<LastLogin>("last_login")?;
+    let (saved_filters, active_filter) = match dashboard_filters(_pool.get_ref(), &username, query.filter).await {
+        Ok(filters) => filters,
+        Err(e) => {
+            error!("Failed to fetch saved filters: {}", e);
+            (Vec::new(), None)
+        }
+    };
+    // 显示dashboard
+    match sqlx::query_as::<_, DashboardArticle>(
+        "SELECT a.*, s.code AS short_code, s.clicks AS short_clicks, n.label AS menu_label FROM articles a \
+         LEFT JOIN short_links s ON s.article_id = a.id LEFT JOIN nav_items n ON n.article_id = a.id \
+         WHERE a.deleted_at IS NULL ORDER BY a.created_at DESC"
+    )
+    .fetch_all(_pool.get_ref())
+    .await {
+        Ok(mut articles) => {
+            let mut ctx = Context::new();
+            ctx.insert("total_articles", &articles.len());
+            if let Some((_, filter)) = &active_filter {
+                let now = chrono::Utc::now().naive_utc();
+                articles.retain(|row| filter.matches(&filter_candidate(&data, &row.article), now));
+            }
+            ctx.insert("saved_filters", &saved_filters);
+            ctx.insert("active_filter", &active_filter.map(|(id, _)| id));
+            // 打印articles调试信息
+            info!("Articles data: {:?}", articles);
+            // 转换articles为模板需要的格式
+            #[derive(serde::Serialize)]
+            struct TemplateArticle {
+                id: i64,
+                title: String,
+                content: String,
+                created_at: String,
+                short_code: Option<String>,
+                short_clicks: i64,
+                // 固定到导航菜单时的名称
+                menu_label: Option<String>,
+                draft: bool,
+            }
+
+            let template_articles: Vec<TemplateArticle> = articles.into_iter().map(|row| {
+                TemplateArticle {
+                    id: row.article.id,
+                    title: row.article.title,
+                    content: row.article.content,
+                    created_at: row.article.created_at,
+                    short_code: row.short_code,
+                    short_clicks: row.short_clicks.unwrap_or(0),
+                    menu_label: row.menu_label,
+                    draft: row.article.status == models::STATUS_DRAFT,
+                }
+            }).collect();
+            ctx.insert("articles", &template_articles);
+            ctx.insert("last_login", &last_login);
+            ctx.insert("activity", &dashboard_activity(_pool.get_ref(), query.page.unwrap_or(1)).await);
+            ctx.insert("stats", &dashboard_stats(&data, _pool.get_ref()).await);
+            ctx.insert("categories", &category_choices(_pool.get_ref()).await);
+            match data.template.render("admin/dashboard.html", &ctx) {
+                Ok(html) => Ok(HttpResponse::Ok().content_type("text/html").body(html)),
+                Err(e) => {
+                    error!("Detailed template rendering error: {:#?}", e);
+                    Ok(HttpResponse::InternalServerError().body(format!("Detailed template error: {:#?}", e)))
+                }
+            }
+        },
+        Err(e) => {
+            error!("Failed to fetch articles: {}", e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+// 已保存筛选条件的快捷链接
+#[derive(Serialize)]
+struct SavedFilterView {
+    id: i64,
+    name: String,
+    criteria: Option<ArticleFilter>,
+    // 按该条件筛选的管理 API 和仪表盘地址
+    url: String,
+    dashboard_url: String,
+}
+
+impl From<models::SavedFilter> for SavedFilterView {
+    fn from(filter: models::SavedFilter) -> Self {
+        Self {
+            id: filter.id,
+            criteria: ArticleFilter::from_json(&filter.criteria),
+            url: format!("/admin/articles?filter={}", filter.id),
+            dashboard_url: format!("/admin?filter={}", filter.id),
+            name: filter.name,
+        }
+    }
+}
+
+// 当前用户的已保存筛选条件，以及 filter_id 对应的条件
+async fn dashboard_filters(
+    pool: &SqlitePool,
+    username: &str,
+    filter_id: Option<i64>,
+) -> Result<(Vec<SavedFilterView>, Option<(i64, ArticleFilter)>), sqlx::Error> {
+    let Some(user) = models::get_user_by_username(pool, username).await? else {
+        return Ok((Vec::new(), None));
+    };
+    let filters: Vec<SavedFilterView> = models::get_saved_filters(pool, user.id)
+        .await?
+        .into_iter()
+        .map(SavedFilterView::from)
+        .collect();
+    let active = filter_id.and_then(|id| {
+        filters.iter().find(|f| f.id == id).and_then(|f| Some((id, f.criteria.clone()?)))
+    });
+    Ok((filters, active))
+}
+
+fn filter_candidate<'a>(data: &AppState, article: &'a models::Article) -> services::article_filter::Candidate<'a> {
+    services::article_filter::Candidate {
+        title: &article.title,
+        content: &article.content,
+        created_at: &article.created_at,
+        meta_description: article.meta_description.as_deref(),
+        noindex: article.noindex,
+        has_image: data.markdown_service.first_image(&article.content).is_some(),
+    }
+}
+
+// 仪表盘概览：内容和评论数量、最近一次备份、缓存状况
+async fn dashboard_stats(data: &AppState, pool: &SqlitePool) -> serde_json::Value {
+    let counts = models::get_content_counts(pool).await.unwrap_or_else(|e| {
+        error!("Failed to count content: {}", e);
+        models::ContentCounts::default()
+    });
+    let metrics = data.markdown_service.get_metrics();
+    let lookups = metrics.cache_hits + metrics.cache_misses;
+    let hit_rate = if lookups > 0 { metrics.cache_hits as f64 / lookups as f64 } else { 0.0 };
+    // 查询次数太少时命中率没有意义
+    let healthy = data.request_metrics.timed_out_requests() == 0 && (lookups < 20 || hit_rate >= 0.5);
+    serde_json::json!({
+        "counts": counts,
+        "last_backup": last_backup_time(&data.backup_dir).await,
+        "cache": {
+            "healthy": healthy,
+            "hit_rate": format!("{:.1}%", hit_rate * 100.0),
+            "entries": metrics.cache_size,
+            "memory_kb": metrics.memory_usage_bytes / 1024,
+            "slow_renders": data.request_metrics.slow_renders(),
+            "request_timeouts": data.request_metrics.timed_out_requests(),
+        },
+    })
+}
+
+// 备份目录中最新一项的修改时间(UTC)；目录不存在或为空时为 None
+async fn last_backup_time(dir: &std::path::Path) -> Option<String> {
+    let mut entries = tokio::fs::read_dir(dir).await.ok()?;
+    let mut newest: Option<std::time::SystemTime> = None;
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if let Ok(modified) = entry.metadata().await.and_then(|m| m.modified()) {
+            newest = newest.max(Some(modified));
+        }
+    }
+    newest.map(|time| chrono::DateTime::<chrono::Utc>::from(time).format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+// 仪表盘动态：审计日志的一页；读取失败时显示为空
+async fn dashboard_activity(pool: &SqlitePool, page: i64) -> services::activity::ActivityPage {
+    use services::activity::{ActivityPage, PER_PAGE};
+
+    let page = page.max(1);
+    let entries = async {
+        let total = models::count_audit_entries(pool).await?;
+        let entries = models::get_audit_entries(pool, PER_PAGE, (page - 1).saturating_mul(PER_PAGE)).await?;
+        Ok::<_, sqlx::Error>((entries, total))
+    };
+    match entries.await {
+        Ok((entries, total)) => ActivityPage::new(&entries, page, total),
+        Err(e) => {
+            error!("Failed to fetch activity: {}", e);
+            ActivityPage::new(&[], page, 0)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AdminArticlesQuery {
+    // 已保存筛选条件的 id
+    filter: Option<i64>,
+}
+
+async fn admin_articles(
+    data: web::Data<AppState>,
+    query: web::Query<AdminArticlesQuery>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let filter = match query.filter {
+        Some(filter_id) => match dashboard_filters(_pool.get_ref(), &username, Some(filter_id)).await {
+            Ok((_, Some((_, filter)))) => Some(filter),
+            Ok((_, None)) => {
+                return HttpResponse::NotFound().json(serde_json::json!({
+                    "success": false,
+                    "message": "筛选条件不存在"
+                }));
+            }
+            Err(e) => {
+                error!("Failed to fetch saved filter: {}", e);
+                return HttpResponse::InternalServerError().json("Failed to fetch articles");
+            }
+        },
+        None => None,
+    };
+    
+    match sqlx::query_as::<_, models::Article>(
+        "SELECT * FROM articles WHERE deleted_at IS NULL ORDER BY created_at DESC"
+    )
+    .fetch_all(_pool.get_ref())
+    .await {
+        Ok(articles) => {
+            let now = chrono::Utc::now().naive_utc();
+            let articles: Vec<models::ArticleContent> = articles
+                .into_iter()
+                .filter(|article| filter.as_ref().is_none_or(|filter| filter.matches(&filter_candidate(&data, article), now)))
+                .map(|article| models::ArticleContent { id: article.id, title: article.title, content: article.content })
+                .collect();
+            HttpResponse::Ok().json(articles)
+        }
+        Err(e) => {
+            error!("Failed to fetch articles: {}", e);
+            HttpResponse::InternalServerError().json("Failed to fetch articles")
+        }
+    }
+}
+
+// 编辑页发送心跳的间隔（秒）；超过 PRESENCE_WINDOW_SECS 没有心跳视为已离开
+const PRESENCE_HEARTBEAT_SECS: i64 = 20;
+const PRESENCE_WINDOW_SECS: i64 = 60;
+
+// 记录当前管理员正在编辑文章，返回同时在编辑的其他管理员；出错时当作没有
+async fn touch_presence(pool: &SqlitePool, article_id: i64, username: &str) -> Vec<models::ActiveEditor> {
+    if let Err(e) = models::touch_presence(pool, article_id, username).await {
+        error!("Failed to record presence on article {}: {}", article_id, e);
+    }
+    models::get_active_editors(pool, article_id, username, PRESENCE_WINDOW_SECS).await.unwrap_or_else(|e| {
+        error!("Failed to fetch editors of article {}: {}", article_id, e);
+        Vec::new()
+    })
+}
+
+async fn admin_edit_article(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let article_id = path.into_inner();
+    match models::get_article(_pool.get_ref(), article_id).await {
+        Ok(Some(article)) => {
+            let id = article.id;
+            let categories = category_choices(_pool.get_ref()).await;
+            let mut ctx = Context::new();
+            ctx.insert("article", &article);
+            // 只提醒，不阻止同时编辑
+            ctx.insert("editors", &touch_presence(_pool.get_ref(), id, &username).await);
+            ctx.insert("presence_heartbeat_secs", &PRESENCE_HEARTBEAT_SECS);
+            let tags = models::get_article_tags(_pool.get_ref(), id).await.unwrap_or_else(|e| {
+                error!("Failed to fetch tags: {}", e);
+                Vec::new()
+            });
+            ctx.insert("tags", &services::tags::join_tags(&tags));
+            let category_slug = article.category_id.and_then(|id| categories.iter().find(|c| c.id == id)).map(|c| c.slug.clone());
+            ctx.insert("category", &category_slug);
+            ctx.insert("categories", &categories);
+            let attachments = models::get_article_attachments(_pool.get_ref(), id).await.unwrap_or_else(|e| {
+                error!("Failed to fetch attachments: {}", e);
+                Vec::new()
+            });
+            let attachments: Vec<AttachmentView> = attachments.into_iter().map(AttachmentView::from).collect();
+            ctx.insert("attachments", &attachments);
+            ctx.insert("max_upload_size", &services::media::human_size(data.media.max_size() as i64));
+            let revisions = models::get_article_revisions(_pool.get_ref(), id).await.unwrap_or_else(|e| {
+                error!("Failed to fetch revisions: {}", e);
+                Vec::new()
+            });
+            let revisions: Vec<RevisionSummary> = revisions.into_iter().map(RevisionSummary::from).collect();
+            ctx.insert("revisions", &revisions);
+            match data.template.render("admin/edit_article.html", &ctx) {
+                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+                Err(e) => {
+                    error!("Template rendering error: {:#?}", e);
+                    HttpResponse::InternalServerError().body(format!("Template rendering error: {:#?}", e))
+                }
+            }
+        },
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!("Failed to fetch article: {}", e);
+            HttpResponse::NotFound().finish()
+        }
+    }
+}
+
+// 编辑页打开期间的心跳，返回同时在编辑的其他管理员
+async fn admin_article_heartbeat(
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let article_id = path.into_inner();
+    match models::get_article(_pool.get_ref(), article_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "success": false,
+                "message": "文章不存在"
+            }));
+        }
+        Err(e) => {
+            error!("Failed to fetch article: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "读取文章失败"
+            }));
+        }
+    }
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "editors": touch_presence(_pool.get_ref(), article_id, &username).await
+    }))
+}
+
+// 离开编辑页
+async fn admin_leave_article(
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let article_id = path.into_inner();
+    if let Err(e) = models::clear_presence(_pool.get_ref(), article_id, &username).await {
+        error!("Failed to clear presence on article {}: {}", article_id, e);
+    }
+    HttpResponse::Ok().json(serde_json::json!({ "success": true }))
+}
+
+async fn admin_update_article(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    json: web::Json<ArticleForm>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let Ok(canonical_url) = json.canonical_url() else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "原文链接必须是完整的 http(s) 地址"
+        }));
+    };
+
+    let category = match form_category(_pool.get_ref(), &json).await {
+        Ok(category) => category,
+        Err(response) => return response,
+    };
+
+    let article_id = path.into_inner();
+    // 没有修订记录（或文章被其他途径改过）时先保存修改前的内容，保证能看到本次改动
+    snapshot_revision(&_pool, article_id, None).await;
+    match sqlx::query(
+        "UPDATE articles SET title = ?, content = ?, meta_description = COALESCE(?, meta_description), \
+         meta_keywords = COALESCE(?, meta_keywords), noindex = COALESCE(?, noindex), \
+         text_direction = COALESCE(?, text_direction), canonical_url = NULLIF(COALESCE(?, canonical_url), ''), \
+         category_id = CASE WHEN ? THEN ? ELSE category_id END, \
+         status = CASE WHEN ? THEN 'draft' ELSE status END, published_at = CASE WHEN ? THEN NULL ELSE published_at END, \
+         updated_at = datetime('now') WHERE id = ? AND deleted_at IS NULL"
+    )
+    .bind(&json.title)
+    .bind(&json.content)
+    .bind(json.meta_description())
+    .bind(json.meta_keywords())
+    .bind(json.noindex)
+    .bind(json.text_direction())
+    .bind(canonical_url)
+    .bind(category.is_some())
+    .bind(category.flatten())
+    .bind(json.is_draft())
+    .bind(json.is_draft())
+    .bind(article_id)
+    .execute(_pool.get_ref())
+    .await {
+        Ok(_) => {
+            snapshot_revision(&_pool, article_id, Some(&username)).await;
+            index_article_links(&data, &_pool, article_id, &json.content).await;
+            save_article_tags(&_pool, article_id, json.tags()).await;
+            refresh_article_slug(&_pool, article_id).await;
+            data.events.publish(Event::ArticleUpdated { article_id });
+            // 撤回为草稿的文章不再出现在导航菜单中
+            if json.is_draft()
+                && let Err(e) = reload_navigation(&data, _pool.get_ref()).await
+            {
+                error!("Failed to reload navigation menu: {}", e);
+            }
+            HttpResponse::Ok().json("Article updated successfully")
+        }
+        Err(e) => {
+            error!("Failed to update article: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn admin_create_article(
+    data: web::Data<AppState>,
+    form: web::Form<ArticleForm>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let Ok(canonical_url) = form.canonical_url() else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "原文链接必须是完整的 http(s) 地址"
+        }));
+    };
+    if !form.allow_duplicate.unwrap_or(false)
+        && let Some((existing_id, existing_title)) = find_duplicate_article(_pool.get_ref(), &form.title).await
+    {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "success": false,
+            "message": format!("已有标题相近的文章「{}」", existing_title),
+            "existing_id": existing_id
+        }));
+    }
+    let category = match form_category(_pool.get_ref(), &form).await {
+        Ok(category) => category,
+        Err(response) => return response,
+    };
+    match sqlx::query(
+        "INSERT INTO articles (title, content, meta_description, meta_keywords, noindex, text_direction, canonical_url, category_id, status, author_id, created_at, updated_at) \
+         VALUES (?, ?, ?, ?, ?, ?, NULLIF(?, ''), ?, ?, (SELECT id FROM users WHERE username = ?), datetime('now'), datetime('now'))"
+    )
+    .bind(&form.title)
+    .bind(&form.content)
+    .bind(form.meta_description())
+    .bind(form.meta_keywords())
+    .bind(form.noindex.unwrap_or(false))
+    .bind(form.text_direction().unwrap_or("auto"))
+    .bind(canonical_url)
+    .bind(category.flatten())
+    .bind(form.status())
+    .bind(&username)
+    .execute(_pool.get_ref())
+    .await {
+        Ok(result) => {
+            let article_id = result.last_insert_rowid();
+            snapshot_revision(&_pool, article_id, Some(&username)).await;
+            index_article_links(&data, &_pool, article_id, &form.content).await;
+            save_article_tags(&_pool, article_id, form.tags()).await;
+            refresh_article_slug(&_pool, article_id).await;
+            let action = if form.is_draft() { "save_draft" } else { "publish_article" };
+            record_activity(&_pool, &username, action, &services::activity::article_target(article_id), &form.title).await;
+            data.events.publish(Event::ArticleCreated { article_id, published: !form.is_draft() });
+            HttpResponse::Found().append_header(("Location", "/admin")).finish()
+        }
+        Err(e) => {
+            error!("Failed to create article: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+// 发布草稿：记录发布时间，之后才出现在首页、订阅源等公开页面
+async fn admin_publish_article(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let article_id = path.into_inner();
+    match models::publish_article(_pool.get_ref(), article_id).await {
+        Ok(Some(title)) => {
+            record_activity(&_pool, &username, "publish_article", &services::activity::article_target(article_id), &title).await;
+            data.events.publish(Event::ArticlePublished { article_id });
+            if let Err(e) = reload_navigation(&data, _pool.get_ref()).await {
+                error!("Failed to reload navigation menu: {}", e);
+            }
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "message": "文章已发布"
+            }))
+        }
+        Ok(None) => match models::get_article(_pool.get_ref(), article_id).await {
+            Ok(Some(_)) => HttpResponse::Conflict().json(serde_json::json!({
+                "success": false,
+                "message": "文章已经发布"
+            })),
+            Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+                "success": false,
+                "message": "文章不存在"
+            })),
+            Err(e) => {
+                error!("Failed to fetch article: {}", e);
+                HttpResponse::InternalServerError().finish()
+            }
+        },
+        Err(e) => {
+            error!("Failed to publish article: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "发布失败"
+            }))
+        }
+    }
+}
+
+// 文章表单中可选的分类，按层级排列
+async fn category_choices(pool: &SqlitePool) -> Vec<services::categories::CategoryEntry> {
+    match models::get_categories(pool).await {
+        Ok(categories) => services::categories::tree(&categories),
+        Err(e) => {
+            error!("Failed to fetch categories: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// 表单中的分类：None 表示未填写（保持原值），Some(None) 表示不分类；分类不存在时返回错误响应
+async fn form_category(pool: &SqlitePool, form: &ArticleForm) -> Result<Option<Option<i64>>, HttpResponse> {
+    let Some(slug) = form.category.as_deref().map(str::trim) else {
+        return Ok(None);
+    };
+    if slug.is_empty() {
+        return Ok(Some(None));
+    }
+    match models::get_category_by_slug(pool, slug).await {
+        Ok(Some(category)) => Ok(Some(Some(category.id))),
+        Ok(None) => Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": format!("分类「{}」不存在", slug)
+        }))),
+        Err(e) => {
+            error!("Failed to fetch category: {}", e);
+            Err(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+async fn admin_about_edit(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let mut ctx = Context::new();
+    
+    match models::get_about(_pool.get_ref()).await {
+        Ok(about) => {
+            ctx.insert("id", &about.id);
+            ctx.insert("title", &about.title);
+            ctx.insert("content", &about.content);
+            ctx.insert("updated_at", &about.updated_at);
+            match data.template.render("admin/edit_about.html", &ctx) {
+                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+                Err(e) => {
+                    error!("Template rendering error: {:#?}", e);
+                    HttpResponse::InternalServerError().body(format!("Template rendering error: {:#?}", e))
+                }
+            }
+        },
+        Err(e) => {
+            error!("Failed to fetch about content: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn admin_update_about(
+    json: web::Json<AboutForm>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    match sqlx::query(
+        "UPDATE about SET title = ?, content = ?, updated_at = datetime('now') WHERE id = (SELECT id FROM about ORDER BY id DESC LIMIT 1)"
+    )
+    .bind(&json.title)
+    .bind(&json.content)
+    .execute(_pool.get_ref())
+    .await {
+        Ok(_) => HttpResponse::Ok().json("About content updated successfully"),
+        Err(e) => {
+            error!("Failed to update about content: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RevisionSummary {
+    id: i64,
+    title: String,
+    author: Option<String>,
+    #[serde(serialize_with = "services::dates::rfc3339::serialize")]
+    created_at: String,
+    words: usize,
+}
+
+impl From<models::ArticleRevision> for RevisionSummary {
+    fn from(revision: models::ArticleRevision) -> Self {
+        Self {
+            words: revision.content.split_whitespace().count(),
+            id: revision.id,
+            title: revision.title,
+            author: revision.author,
+            created_at: revision.created_at,
+        }
+    }
+}
+
+// 修订列表（最新的在前）
+async fn admin_article_revisions(
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    match models::get_article_revisions(_pool.get_ref(), path.into_inner()).await {
+        Ok(revisions) => {
+            let revisions: Vec<RevisionSummary> = revisions.into_iter().map(RevisionSummary::from).collect();
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "revisions": revisions
+            }))
+        }
+        Err(e) => {
+            error!("Failed to fetch revisions: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "获取修订记录失败"
+            }))
+        }
+    }
+}
+
+// 两个修订之间按词比较，删除用 <del>、新增用 <ins> 标出
+async fn admin_revision_diff(
+    data: web::Data<AppState>,
+    path: web::Path<(i64, i64, i64)>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    use services::diff::{diff_words, render_html, word_counts};
+
+    let (article_id, a, b) = path.into_inner();
+    let (old, new) = match (
+        models::get_article_revision(_pool.get_ref(), article_id, a).await,
+        models::get_article_revision(_pool.get_ref(), article_id, b).await,
+    ) {
+        (Ok(Some(old)), Ok(Some(new))) => (old, new),
+        (Ok(_), Ok(_)) => return HttpResponse::NotFound().body("Revision not found"),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to fetch revisions: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    // 较长的文章在阻塞线程池中计算
+    let (title_html, content_html, removed, added) = match web::block(move || {
+        let title = diff_words(&old.title, &new.title);
+        let content = diff_words(&old.content, &new.content);
+        let (removed, added) = word_counts(&content);
+        (render_html(&title), render_html(&content), removed, added)
+    })
+    .await
+    {
+        Ok(result) => result,
+        Err(e) => {
+            error!("Failed to diff revisions: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let mut ctx = Context::new();
+    ctx.insert("article_id", &article_id);
+    ctx.insert("from_id", &a);
+    ctx.insert("to_id", &b);
+    ctx.insert("title_diff", &title_html);
+    ctx.insert("content_diff", &content_html);
+    ctx.insert("words_removed", &removed);
+    ctx.insert("words_added", &added);
+    match data.template.render("admin/revision_diff.html", &ctx) {
+        Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+        Err(e) => {
+            error!("Template rendering error: {}", e);
+            HttpResponse::InternalServerError().body("Template rendering error")
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TaskUpdate {
+    // 省略时切换当前状态
+    checked: Option<bool>,
+}
+
+// 勾选或取消文章中的任务列表项，index 为渲染后复选框的序号
+async fn admin_set_task(
+    data: web::Data<AppState>,
+    path: web::Path<(i64, usize)>,
+    json: web::Json<TaskUpdate>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let (article_id, index) = path.into_inner();
+
+    let content = match sqlx::query_scalar::<_, String>("SELECT content FROM articles WHERE id = ? AND deleted_at IS NULL")
+        .bind(article_id)
+        .fetch_optional(_pool.get_ref())
+        .await
+    {
+        Ok(Some(content)) => content,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "success": false,
+                "message": "文章不存在"
+            }));
+        }
+        Err(e) => {
+            error!("Failed to fetch article: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let (updated, checked) = match data.markdown_service.set_task(&content, index, json.checked) {
+        Ok(result) => result,
+        Err(e) => {
+            let message = match e {
+                services::tasks::TaskError::NotFound(_) => "任务不存在",
+                services::tasks::TaskError::FromSnippet => "文章包含来自内容片段的任务，无法定位",
+            };
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": message
+            }));
+        }
+    };
+
+    snapshot_revision(&_pool, article_id, None).await;
+    // 仅在内容未被其他请求修改时更新，避免覆盖并发编辑
+    match sqlx::query("UPDATE articles SET content = ?, updated_at = datetime('now') WHERE id = ? AND content = ? AND deleted_at IS NULL")
+        .bind(&updated)
+        .bind(article_id)
+        .bind(&content)
+        .execute(_pool.get_ref())
+        .await
+    {
+        Ok(result) if result.rows_affected() == 0 => HttpResponse::Conflict().json(serde_json::json!({
+            "success": false,
+            "message": "文章已被修改，请刷新后重试"
+        })),
+        Ok(_) => {
+            snapshot_revision(&_pool, article_id, Some(&username)).await;
+            data.events.publish(Event::ArticleUpdated { article_id });
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "index": index,
+                "checked": checked
+            }))
+        }
+        Err(e) => {
+            error!("Failed to update task: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "保存失败"
+            }))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PinForm {
+    // 菜单名称，留空时使用文章标题
+    label: Option<String>,
+}
+
+// 把文章固定到导航菜单
+async fn admin_pin_article(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    form: web::Form<PinForm>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let article_id = path.into_inner();
+    let title = match sqlx::query_scalar::<_, String>("SELECT title FROM articles WHERE id = ? AND deleted_at IS NULL")
+        .bind(article_id)
+        .fetch_optional(_pool.get_ref())
+        .await
+    {
+        Ok(Some(title)) => title,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "success": false,
+                "message": "文章不存在"
+            }));
+        }
+        Err(e) => {
+            error!("Failed to fetch article: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "固定到菜单失败"
+            }));
+        }
+    };
+    let label = match services::navigation::menu_label(form.label.as_deref(), &title) {
+        Ok(label) => label,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": format!("菜单名称不能超过 {} 个字符", services::navigation::MAX_LABEL_LENGTH)
+            }));
+        }
+    };
+    if let Err(e) = models::pin_article(_pool.get_ref(), article_id, &label).await {
+        error!("Failed to pin article: {}", e);
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "success": false,
+            "message": "固定到菜单失败"
+        }));
+    }
+    if let Err(e) = reload_navigation(&data, _pool.get_ref()).await {
+        error!("Failed to reload navigation menu: {}", e);
+    }
+    record_activity(&_pool, &username, "pin_article", &services::activity::article_target(article_id), &label).await;
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "label": label
+    }))
+}
+
+// 从导航菜单中移除
+async fn admin_unpin_article(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let article_id = path.into_inner();
+    match models::unpin_article(_pool.get_ref(), article_id).await {
+        Ok(removed) => {
+            if removed {
+                if let Err(e) = reload_navigation(&data, _pool.get_ref()).await {
+                    error!("Failed to reload navigation menu: {}", e);
+                }
+                record_activity(&_pool, &username, "unpin_article", &services::activity::article_target(article_id), "").await;
+            }
+            HttpResponse::Ok().json(serde_json::json!({ "success": true }))
+        }
+        Err(e) => {
+            error!("Failed to unpin article: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "从菜单移除失败"
+            }))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ReplaceRequest {
+    find: String,
+    #[serde(default)]
+    replace: String,
+    #[serde(default)]
+    regex: bool,
+    #[serde(default)]
+    case_insensitive: bool,
+    // 默认只预览，明确传 false 才写入
+    #[serde(default = "default_true")]
+    dry_run: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// 批量查找替换文章内容；写入前后各保存一份修订
+async fn admin_batch_replace(
+    data: web::Data<AppState>,
+    body: web::Json<ReplaceRequest>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    use services::replace::FindReplace;
+
+    let replacer = match FindReplace::new(&body.find, &body.replace, body.regex, body.case_insensitive) {
+        Ok(replacer) => replacer,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": e.to_string()
+            }));
+        }
+    };
+    let articles = match sqlx::query_as::<_, models::ArticleContent>(
+        "SELECT id, title, content FROM articles WHERE deleted_at IS NULL ORDER BY id"
+    )
+    .fetch_all(_pool.get_ref())
+    .await {
+        Ok(articles) => articles,
+        Err(e) => {
+            error!("Failed to fetch articles: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "查找替换失败"
+            }));
+        }
+    };
+
+    let mut results = Vec::new();
+    let mut total_matches = 0;
+    for models::ArticleContent { id, title, content } in articles {
+        let Some(replaced) = replacer.apply(&content) else {
+            continue;
+        };
+        total_matches += replaced.matches;
+        if !body.dry_run {
+            snapshot_revision(&_pool, id, None).await;
+            if let Err(e) = sqlx::query("UPDATE articles SET content = ?, updated_at = datetime('now') WHERE id = ? AND content = ?")
+                .bind(&replaced.content)
+                .bind(id)
+                .bind(&content)
+                .execute(_pool.get_ref())
+                .await
+            {
+                error!("Failed to update article {}: {}", id, e);
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "success": false,
+                    "message": format!("更新文章 {} 失败，之前的文章已修改", id)
+                }));
+            }
+            snapshot_revision(&_pool, id, Some(&username)).await;
+            index_article_links(&data, &_pool, id, &replaced.content).await;
+        }
+        results.push(serde_json::json!({
+            "id": id,
+            "title": title,
+            "matches": replaced.matches,
+            "changes": replaced.changes,
+        }));
+    }
+
+    if !body.dry_run && !results.is_empty() {
+        let detail = format!("\"{}\" → \"{}\" in {} articles", body.find, body.replace, results.len());
+        record_activity(&_pool, &username, "batch_replace", "articles", &detail).await;
+        articles_changed(&data);
+    }
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "dry_run": body.dry_run,
+        "total_matches": total_matches,
+        "articles": results,
+    }))
+}
+
+// 当前登录用户的 id；用户已不存在时为 None
+async fn current_user_id(pool: &SqlitePool, user: &CurrentUser) -> Result<Option<i64>, sqlx::Error> {
+    Ok(models::get_user_by_username(pool, &user.0).await?.map(|user| user.id))
+}
+
+// 当前用户保存的文章筛选条件，附带快捷链接
+async fn admin_saved_filters(
+    user: CurrentUser,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let user_id = match current_user_id(_pool.get_ref(), &user).await {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => {
+            return HttpResponse::Unauthorized().json(serde_json::json!({
+                "success": false,
+                "message": "未登录"
+            }));
+        }
+        Err(e) => {
+            error!("Failed to fetch user: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    match models::get_saved_filters(_pool.get_ref(), user_id).await {
+        Ok(filters) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "filters": filters.into_iter().map(SavedFilterView::from).collect::<Vec<_>>()
+        })),
+        Err(e) => {
+            error!("Failed to fetch saved filters: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "获取筛选条件失败"
+            }))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SavedFilterRequest {
+    name: String,
+    criteria: serde_json::Value,
+}
+
+// 保存文章筛选条件，同名的会被覆盖
+async fn admin_save_filter(
+    body: web::Json<SavedFilterRequest>,
+    user: CurrentUser,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    use services::article_filter::validate_name;
+
+    let user_id = match current_user_id(_pool.get_ref(), &user).await {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => {
+            return HttpResponse::Unauthorized().json(serde_json::json!({
+                "success": false,
+                "message": "未登录"
+            }));
+        }
+        Err(e) => {
+            error!("Failed to fetch user: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let (name, criteria) = match (validate_name(&body.name), ArticleFilter::parse(&body.criteria)) {
+        (Ok(name), Ok(criteria)) => (name, criteria),
+        (Err(message), _) | (_, Err(message)) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": message
+            }));
+        }
+    };
+    match models::save_filter(_pool.get_ref(), user_id, name, &criteria.to_json()).await {
+        Ok(filter) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "filter": SavedFilterView::from(filter)
+        })),
+        Err(e) => {
+            error!("Failed to save filter: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "保存筛选条件失败"
+            }))
+        }
+    }
+}
+
+async fn admin_delete_filter(
+    path: web::Path<i64>,
+    user: CurrentUser,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let user_id = match current_user_id(_pool.get_ref(), &user).await {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => {
+            return HttpResponse::Unauthorized().json(serde_json::json!({
+                "success": false,
+                "message": "未登录"
+            }));
+        }
+        Err(e) => {
+            error!("Failed to fetch user: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    match models::delete_saved_filter(_pool.get_ref(), user_id, path.into_inner()).await {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({ "success": true })),
+        Ok(false) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "message": "筛选条件不存在"
+        })),
+        Err(e) => {
+            error!("Failed to delete saved filter: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "删除筛选条件失败"
+            }))
+        }
+    }
+}
+
+// 生成（或返回已有的）文章短链接
+async fn admin_short_link(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let article_id = path.into_inner();
+    let exists: bool = match sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM articles WHERE id = ? AND deleted_at IS NULL)")
+        .bind(article_id)
+        .fetch_one(_pool.get_ref())
+        .await
+    {
+        Ok(exists) => exists,
+        Err(e) => {
+            error!("Failed to fetch article: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "生成短链接失败"
+            }));
+        }
+    };
+    if !exists {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "message": "文章不存在"
+        }));
+    }
+    match models::get_or_create_short_link(_pool.get_ref(), article_id).await {
+        Ok(link) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "code": link.code,
+            "url": format!("{}/s/{}", data.site_url, link.code),
+            "clicks": link.clicks
+        })),
+        Err(e) => {
+            error!("Failed to create short link: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "生成短链接失败"
+            }))
+        }
+    }
+}
+
+// multipart 上传的单个文件
+struct Upload {
+    filename: String,
+    // 客户端声明的类型，粘贴的图片常常只有这个可用
+    content_type: Option<String>,
+    bytes: Vec<u8>,
+}
+
+// 读取 multipart 中第一个带文件名的字段；超过 max_size 时提前停止读取
+async fn read_upload(payload: &mut Multipart, max_size: usize) -> Result<Option<Upload>, services::media::MediaError> {
+    while let Some(mut field) = payload.try_next().await.unwrap_or(None) {
+        let Some(filename) = field.content_disposition().get_filename().map(str::to_string) else {
+            continue;
+        };
+        let content_type = field.content_type().map(|mime| mime.essence_str().to_string());
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.try_next().await.unwrap_or(None) {
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() > max_size {
+                return Err(services::media::MediaError::TooLarge(bytes.len()));
+            }
+        }
+        return Ok(Some(Upload { filename, content_type, bytes }));
+    }
+    Ok(None)
+}
+
+// 上传文章附件（PDF、幻灯片等）
+async fn admin_upload_attachment(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    mut payload: Multipart,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let article_id = path.into_inner();
+    match sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM articles WHERE id = ? AND deleted_at IS NULL)")
+        .bind(article_id)
+        .fetch_one(_pool.get_ref())
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "success": false,
+                "message": "文章不存在"
+            }));
+        }
+        Err(e) => {
+            error!("Failed to fetch article: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "上传附件失败"
+            }));
+        }
+    }
+
+    let Upload { filename, bytes, .. } = match read_upload(&mut payload, data.media.max_size()).await {
+        Ok(Some(upload)) => upload,
+        Ok(None) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": "未找到上传的文件"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": format!("上传附件失败: {}", e)
+            }));
+        }
+    };
+    let stored = match data.media.store(&filename, &bytes).await {
+        Ok(stored) => stored,
+        Err(services::media::MediaError::Io(e)) => {
+            error!("Failed to store attachment: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "上传附件失败"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": format!("上传附件失败: {}", e)
+            }));
+        }
+    };
+    match models::insert_media(_pool.get_ref(), &stored.stored_name, &filename, stored.content_type, stored.size as i64, Some(article_id)).await {
+        Ok(media) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "attachment": AttachmentView::from(media)
+        })),
+        Err(e) => {
+            error!("Failed to save attachment: {}", e);
+            if let Err(e) = data.media.delete(&stored.stored_name).await {
+                error!("Failed to remove orphaned upload: {}", e);
+            }
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "上传附件失败"
+            }))
+        }
+    }
+}
+
+// 编辑器粘贴/选择图片：保存到媒体库并返回可直接插入的 Markdown
+async fn admin_editor_upload(
+    data: web::Data<AppState>,
+    mut payload: Multipart,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    use services::media::{content_type_for, image_alt_text, image_extension_for, MediaError, MediaStorage};
+
+    let upload = match read_upload(&mut payload, data.media.max_size()).await {
+        Ok(Some(upload)) => upload,
+        Ok(None) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": "未找到上传的图片"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": format!("上传图片失败: {}", e)
+            }));
+        }
+    };
+
+    // 文件名没有图片扩展名时（如剪贴板中的 "blob"），按声明的类型补上
+    let filename = if content_type_for(&upload.filename).starts_with("image/") {
+        upload.filename
+    } else if let Some(ext) = upload.content_type.as_deref().and_then(image_extension_for) {
+        format!("{}.{}", upload.filename, ext)
+    } else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "只能上传图片"
+        }));
+    };
+    let stored = match data.media.store(&filename, &upload.bytes).await {
+        Ok(stored) => stored,
+        Err(MediaError::Io(e)) => {
+            error!("Failed to store image: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "上传图片失败"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": format!("上传图片失败: {}", e)
+            }));
+        }
+    };
+    match models::insert_media(_pool.get_ref(), &stored.stored_name, &filename, stored.content_type, stored.size as i64, None).await {
+        Ok(media) => {
+            let url = MediaStorage::url(&media.stored_name);
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "url": url,
+                "markdown": format!("![{}]({})", image_alt_text(&filename), url)
+            }))
+        }
+        Err(e) => {
+            error!("Failed to save image: {}", e);
+            if let Err(e) = data.media.delete(&stored.stored_name).await {
+                error!("Failed to remove orphaned upload: {}", e);
+            }
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "上传图片失败"
+            }))
+        }
+    }
+}
+
+async fn admin_delete_attachment(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    match models::delete_media(_pool.get_ref(), path.into_inner()).await {
+        Ok(Some(media)) => {
+            if let Err(e) = data.media.delete(&media.stored_name).await {
+                error!("Failed to remove media file {}: {}", media.stored_name, e);
+            }
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "message": "附件已删除"
+            }))
+        }
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "message": "附件不存在"
+        })),
+        Err(e) => {
+            error!("Failed to delete attachment: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "删除附件失败"
+            }))
+        }
+    }
+}
+
+// 未被任何内容引用、且上传已超过一天的文件
+async fn find_orphaned_media(data: &AppState, pool: &SqlitePool) -> Result<Vec<models::Media>, sqlx::Error> {
+    use services::media::{referenced_names, ORPHAN_MIN_AGE_HOURS};
+
+    let texts = models::get_media_reference_texts(pool).await?;
+    let mut referenced = referenced_names(texts.iter().map(String::as_str));
+    let branding = data.settings().branding;
+    referenced.extend([branding.favicon, branding.logo].into_iter().flatten());
+    let cutoff = (chrono::Utc::now().naive_utc() - chrono::Duration::hours(ORPHAN_MIN_AGE_HOURS))
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string();
+    Ok(models::get_unattached_media(pool)
+        .await?
+        .into_iter()
+        .filter(|media| !referenced.contains(&media.stored_name) && media.created_at <= cutoff)
+        .collect())
+}
+
+#[derive(Serialize)]
+struct OrphanedMediaView {
+    #[serde(flatten)]
+    attachment: AttachmentView,
+    created_at: String,
+}
+
+// 未使用的上传文件列表
+async fn admin_orphaned_media(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let orphans = match find_orphaned_media(&data, _pool.get_ref()).await {
+        Ok(orphans) => orphans,
+        Err(e) => {
+            error!("Failed to find orphaned media: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let total_size: i64 = orphans.iter().map(|media| media.size).sum();
+    let files: Vec<OrphanedMediaView> = orphans
+        .into_iter()
+        .map(|media| OrphanedMediaView {
+            created_at: media.created_at.clone(),
+            attachment: AttachmentView::from(media),
+        })
+        .collect();
+    let mut ctx = Context::new();
+    ctx.insert("files", &files);
+    ctx.insert("total_size", &services::media::human_size(total_size));
+    ctx.insert("min_age_hours", &services::media::ORPHAN_MIN_AGE_HOURS);
+    match data.template.render("admin/media.html", &ctx) {
+        Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+        Err(e) => {
+            error!("Template rendering error: {}", e);
+            HttpResponse::InternalServerError().body("Template rendering error")
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DeleteMediaRequest {
+    ids: Vec<i64>,
+}
+
+// 批量删除未使用的上传文件；删除前重新检查，仍被引用的跳过
+async fn admin_delete_orphaned_media(
+    data: web::Data<AppState>,
+    body: web::Json<DeleteMediaRequest>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let orphans = match find_orphaned_media(&data, _pool.get_ref()).await {
+        Ok(orphans) => orphans,
+        Err(e) => {
+            error!("Failed to find orphaned media: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "删除文件失败"
+            }));
+        }
+    };
+    let mut deleted = 0;
+    let mut freed = 0;
+    for media in orphans.iter().filter(|media| body.ids.contains(&media.id)) {
+        match models::delete_media(_pool.get_ref(), media.id).await {
+            Ok(Some(media)) => {
+                if let Err(e) = data.media.delete(&media.stored_name).await {
+                    error!("Failed to remove media file {}: {}", media.stored_name, e);
+                }
+                deleted += 1;
+                freed += media.size;
+            }
+            Ok(None) => {}
+            Err(e) => error!("Failed to delete media {}: {}", media.id, e),
+        }
+    }
+    if deleted > 0 {
+        let detail = format!("{} files, {}", deleted, services::media::human_size(freed));
+        record_activity(&_pool, &username, "delete_orphaned_media", "media", &detail).await;
+    }
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "deleted": deleted,
+        "skipped": body.ids.len() - deleted,
+        "message": format!("已删除 {} 个文件", deleted)
+    }))
+}
+
+// 评论审核页面
+async fn admin_comments(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    match models::get_moderation_comments(_pool.get_ref()).await {
+        Ok(comments) => {
+            let mut ctx = Context::new();
+            ctx.insert("comments", &comments);
+            match data.template.render("admin/comments.html", &ctx) {
+                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+                Err(e) => {
+                    error!("Template rendering error: {}", e);
+                    HttpResponse::InternalServerError().body("Template rendering error")
+                }
+            }
+        },
+        Err(e) => {
+            error!("Failed to fetch comments: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn admin_approve_comment(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    update_comment_status(&data, path.into_inner(), "approved", &_pool).await
+}
+
+async fn admin_spam_comment(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    update_comment_status(&data, path.into_inner(), "spam", &_pool).await
+}
+
+async fn update_comment_status(data: &AppState, comment_id: i64, status: &str, pool: &SqlitePool) -> HttpResponse {
+    match models::set_comment_status(pool, comment_id, status).await {
+        Ok(true) => {
+            if status == "approved"
+                && let Ok(comment) = models::get_comment(pool, comment_id).await
+            {
+                notify_comment_reply(data, pool, &comment).await;
+            }
+            HttpResponse::Ok().json(serde_json::json!({ "success": true }))
+        },
+        Ok(false) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "message": "评论不存在"
+        })),
+        Err(e) => {
+            error!("Failed to update comment {}: {}", comment_id, e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "更新失败"
+            }))
+        }
+    }
+}
+
+async fn admin_delete_comment(
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    match models::delete_comment(_pool.get_ref(), path.into_inner()).await {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({ "success": true })),
+        Ok(false) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "message": "评论不存在"
+        })),
+        Err(e) => {
+            error!("Failed to delete comment: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "删除失败"
+            }))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SettingsForm {
+    summary_length: usize,
+    summary_unit: String,
+    summary_source: String,
+    #[serde(default, deserialize_with = "deserialize_checkbox")]
+    robots_disallow_admin: Option<bool>,
+    #[serde(default)]
+    robots_extra_rules: String,
+    // 每行一个联系方式
+    #[serde(default)]
+    security_contact: String,
+    #[serde(default)]
+    security_expires: String,
+    #[serde(default)]
+    security_policy: String,
+    #[serde(default)]
+    security_languages: String,
+    // 回收站保留天数，0 表示不自动清理
+    trash_retention_days: Option<u32>,
+    // 订阅源内容：summary / full；缺省表示保持原值
+    feed_content: Option<String>,
+    feed_footer: Option<String>,
+    // 订阅源中的文章数
+    feed_items: Option<u32>,
+    // 搜索排序：标题权重和按文章年龄降权的半衰期（天），0 表示不考虑时间
+    search_title_weight: Option<u32>,
+    search_recency_half_life_days: Option<u32>,
+    // 站点时区（IANA 名称）和 strftime 日期格式；缺省表示保持原值
+    site_timezone: Option<String>,
+    date_format: Option<String>,
+    datetime_format: Option<String>,
+    // 功能开关，未勾选即关闭
+    #[serde(default, deserialize_with = "deserialize_checkbox")]
+    feature_comments: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_checkbox")]
+    feature_registration: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_checkbox")]
+    feature_search: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_checkbox")]
+    feature_federation: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_checkbox")]
+    feature_outbound_tracking: Option<bool>,
+}
+
+// 站点设置页面
+async fn admin_settings(
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let settings = data.settings();
+    let mut ctx = Context::new();
+    ctx.insert("summary", &settings.summary);
+    ctx.insert("robots", &settings.robots);
+    ctx.insert("security", &settings.security);
+    ctx.insert("trash", &settings.trash);
+    ctx.insert("feed", &settings.feed);
+    ctx.insert("search", &settings.search);
+    ctx.insert("features", &settings.features);
+    ctx.insert("dates", &serde_json::json!({
+        "timezone": settings.dates.timezone.name(),
+        "date_format": settings.dates.date_format,
+        "datetime_format": settings.dates.datetime_format,
+    }));
+    ctx.insert("timezones", &chrono_tz::TZ_VARIANTS.iter().map(|tz| tz.name()).collect::<Vec<_>>());
+    let brand_assets: Vec<_> = [
+        (BrandAsset::Favicon, "Favicon", ".ico,.png,.gif", "ICO, PNG or GIF"),
+        (BrandAsset::Logo, "Logo", ".png,.jpg,.jpeg,.gif,.webp,.avif", "PNG, JPEG, GIF, WebP or AVIF"),
+    ]
+    .into_iter()
+    .map(|(asset, label, accept, hint)| serde_json::json!({
+        "name": asset.as_str(),
+        "label": label,
+        "accept": accept,
+        "hint": hint,
+        "url": settings.branding.url(asset),
+    }))
+    .collect();
+    ctx.insert("brand_assets", &brand_assets);
+    match data.template.render("admin/settings.html", &ctx) {
+        Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+        Err(e) => {
+            error!("Template rendering error: {}", e);
+            HttpResponse::InternalServerError().body("Template rendering error")
+        }
+    }
+}
+
+async fn admin_save_settings(
+    data: web::Data<AppState>,
+    form: web::Form<SettingsForm>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let (Some(unit), Some(source)) = (SummaryUnit::parse(&form.summary_unit), SummarySource::parse(&form.summary_source)) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "无效的摘要设置"
+        }));
+    };
+    if !(1..=5000).contains(&form.summary_length) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "摘要长度必须在 1 到 5000 之间"
+        }));
+    }
+    let summary = SummaryOptions { length: form.summary_length, unit, source };
+
+    let robots = RobotsOptions {
+        disallow_admin: form.robots_disallow_admin.unwrap_or(false),
+        extra_rules: form.robots_extra_rules.replace('\r', "").trim().to_string(),
+    };
+    let mut contacts = Vec::new();
+    for line in form.security_contact.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        match services::well_known::normalize_contact(line) {
+            Some(contact) => contacts.push(contact),
+            None => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "success": false,
+                    "message": format!("无效的安全联系方式：{}（应为邮箱、mailto:、https:// 或 tel: 地址）", line)
+                }));
+            }
+        }
+    }
+    let expires = Some(form.security_expires.trim().to_string()).filter(|d| !d.is_empty());
+    if expires.as_deref().is_some_and(|d| services::well_known::parse_expiry_date(d).is_none()) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "过期日期格式应为 YYYY-MM-DD"
+        }));
+    }
+    let security = SecurityTxtOptions {
+        contacts,
+        expires,
+        policy: Some(form.security_policy.trim().to_string()).filter(|p| !p.is_empty()),
+        preferred_languages: Some(form.security_languages.trim().to_string()).filter(|l| !l.is_empty()),
+    };
+
+    let trash = TrashOptions {
+        retention_days: form.trash_retention_days.unwrap_or(data.settings().trash.retention_days),
+    };
+    if trash.retention_days > services::trash::MAX_RETENTION_DAYS {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": format!("回收站保留天数不能超过 {}", services::trash::MAX_RETENTION_DAYS)
+        }));
+    }
+
+    let current_feed = data.settings().feed;
+    let feed_content = match form.feed_content.as_deref() {
+        None => current_feed.content,
+        Some(value) => match FeedContent::parse(value) {
+            Some(content) => content,
+            None => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "success": false,
+                    "message": "无效的订阅源内容设置"
+                }));
+            }
+        },
+    };
+    let feed = FeedOptions {
+        content: feed_content,
+        footer: form.feed_footer.as_deref().map(|f| f.replace('\r', "").trim().to_string()).unwrap_or(current_feed.footer),
+        items: form.feed_items.unwrap_or(current_feed.items),
+    };
+    if !(1..=services::feed::MAX_ITEMS).contains(&feed.items) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": format!("订阅源文章数必须在 1 到 {} 之间", services::feed::MAX_ITEMS)
+        }));
+    }
+    if feed.footer.chars().count() > services::feed::MAX_FOOTER_LENGTH {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": format!("订阅源页脚不能超过 {} 个字符", services::feed::MAX_FOOTER_LENGTH)
+        }));
+    }
+
+    let current_search = data.settings().search;
+    let search = SearchRanking {
+        title_weight: form.search_title_weight.unwrap_or(current_search.title_weight),
+        recency_half_life_days: form.search_recency_half_life_days.unwrap_or(current_search.recency_half_life_days),
+    };
+    if !(1..=services::search::MAX_TITLE_WEIGHT).contains(&search.title_weight) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": format!("标题权重必须在 1 到 {} 之间", services::search::MAX_TITLE_WEIGHT)
+        }));
+    }
+    if search.recency_half_life_days > services::search::MAX_RECENCY_HALF_LIFE_DAYS {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": format!("时间衰减半衰期不能超过 {} 天", services::search::MAX_RECENCY_HALF_LIFE_DAYS)
+        }));
+    }
+
+    let current_dates = data.settings().dates;
+    let timezone = match form.site_timezone.as_deref() {
+        None => current_dates.timezone,
+        Some(name) => match services::dates::parse_timezone(name) {
+            Some(timezone) => timezone,
+            None => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "success": false,
+                    "message": format!("未知的时区：{}（应为 Asia/Shanghai 这样的 IANA 名称）", name.trim())
+                }));
+            }
+        },
+    };
+    let date_format = |value: Option<&str>, current: String| match value.map(str::trim) {
+        None => Some(current),
+        Some(format) => services::dates::is_valid_format(format).then(|| format.to_string()),
+    };
+    let (Some(date_format), Some(datetime_format)) = (
+        date_format(form.date_format.as_deref(), current_dates.date_format),
+        date_format(form.datetime_format.as_deref(), current_dates.datetime_format),
+    ) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "无效的日期格式（使用 %Y、%m、%d、%H、%M 等 strftime 格式符）"
+        }));
+    };
+    let dates = DateOptions { timezone, date_format, datetime_format };
+
+    let features = FeatureFlags {
+        comments: form.feature_comments.unwrap_or(false),
+        registration: form.feature_registration.unwrap_or(false),
+        search: form.feature_search.unwrap_or(false),
+        federation: form.feature_federation.unwrap_or(false),
+        outbound_tracking: form.feature_outbound_tracking.unwrap_or(false),
+    };
+
+    let mut values: Vec<(&str, String)> = summary.to_settings().into_iter().collect();
+    values.extend(robots.to_settings());
+    values.extend(security.to_settings());
+    values.extend(trash.to_settings());
+    values.extend(feed.to_settings());
+    values.extend(search.to_settings());
+    values.extend(features.to_settings());
+    values.extend(dates.to_settings());
+    if let Err(e) = models::save_settings(_pool.get_ref(), &values).await {
+        error!("Failed to save settings: {}", e);
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "success": false,
+            "message": "保存失败"
+        }));
+    }
+    if let Err(e) = reload_settings(&data, _pool.get_ref()).await {
+        error!("Failed to reload settings: {}", e);
+    }
+    // API 列表中的摘要、搜索补全的排序和是否开启随设置变化
+    data.api_cache.invalidate();
+    let detail = format!(
+        "summary: {} {} from {}; robots disallow admin: {}; security contacts: {}; trash retention: {} days; feed: {}, {} items; search title weight: {}, recency half-life: {} days; features: {}; timezone: {}",
+        summary.length, unit.as_str(), source.as_str(), robots.disallow_admin, security.contacts.len(), trash.retention_days, feed.content.as_str(), feed.items,
+        search.title_weight, search.recency_half_life_days, features.enabled().join(", "), dates.timezone.name()
+    );
+    if let Err(e) = models::record_audit(_pool.get_ref(), &username, "update_settings", "site", &detail).await {
+        error!("Failed to record audit entry: {}", e);
+    }
+    HttpResponse::Ok().json(serde_json::json!({ "success": true }))
+}
+
+// 上传站点图标或 Logo，替换后删除旧文件
+async fn admin_upload_brand_asset(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    mut payload: Multipart,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let Some(asset) = BrandAsset::parse(&path.into_inner()) else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    let Upload { filename, bytes, .. } = match read_upload(&mut payload, data.media.max_size()).await {
+        Ok(Some(upload)) => upload,
+        Ok(None) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": "未找到上传的文件"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": format!("上传失败: {}", e)
+            }));
+        }
+    };
+    if !asset.accepts(&filename) {
+        let message = match asset {
+            BrandAsset::Favicon => "站点图标只支持 ICO、PNG 或 GIF 文件",
+            BrandAsset::Logo => "Logo 只支持 PNG、JPEG、GIF、WebP 或 AVIF 图片",
+        };
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": message
+        }));
+    }
+    let stored = match data.media.store(&filename, &bytes).await {
+        Ok(stored) => stored,
+        Err(services::media::MediaError::Io(e)) => {
+            error!("Failed to store {}: {}", asset.as_str(), e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "上传失败"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": format!("上传失败: {}", e)
+            }));
+        }
+    };
+    let saved = async {
+        models::insert_media(_pool.get_ref(), &stored.stored_name, &filename, stored.content_type, stored.size as i64, None).await?;
+        models::save_settings(_pool.get_ref(), &[(asset.setting_key(), stored.stored_name.clone())]).await
+    };
+    if let Err(e) = saved.await {
+        error!("Failed to save {}: {}", asset.as_str(), e);
+        if let Err(e) = data.media.delete(&stored.stored_name).await {
+            error!("Failed to remove orphaned upload: {}", e);
+        }
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "success": false,
+            "message": "上传失败"
+        }));
+    }
+
+    let previous = data.settings().branding.get(asset).map(str::to_string);
+    replace_brand_asset(&data, _pool.get_ref(), previous).await;
+    let detail = format!("{}: {}", asset.as_str(), filename);
+    if let Err(e) = models::record_audit(_pool.get_ref(), &username, "update_branding", "site", &detail).await {
+        error!("Failed to record audit entry: {}", e);
+    }
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "url": data.settings().branding.url(asset)
+    }))
+}
+
+// 移除站点图标或 Logo，恢复默认
+async fn admin_delete_brand_asset(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let Some(asset) = BrandAsset::parse(&path.into_inner()) else {
+        return HttpResponse::NotFound().finish();
+    };
+    if let Err(e) = models::save_settings(_pool.get_ref(), &[(asset.setting_key(), String::new())]).await {
+        error!("Failed to remove {}: {}", asset.as_str(), e);
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "success": false,
+            "message": "删除失败"
+        }));
+    }
+    let previous = data.settings().branding.get(asset).map(str::to_string);
+    replace_brand_asset(&data, _pool.get_ref(), previous).await;
+    if let Err(e) = models::record_audit(_pool.get_ref(), &username, "remove_branding", "site", asset.as_str()).await {
+        error!("Failed to record audit entry: {}", e);
+    }
+    HttpResponse::Ok().json(serde_json::json!({ "success": true }))
+}
+
+// 设置已保存后调用：重新加载设置，再删除被替换的文件和媒体记录
+async fn replace_brand_asset(data: &AppState, pool: &SqlitePool, previous: Option<String>) {
+    if let Err(e) = reload_settings(data, pool).await {
+        error!("Failed to reload settings: {}", e);
+    }
+    let Some(previous) = previous else {
+        return;
+    };
+    match models::get_media_by_name(pool, &previous).await {
+        Ok(Some(media)) => {
+            if let Err(e) = models::delete_media(pool, media.id).await {
+                error!("Failed to delete media {}: {}", previous, e);
+                return;
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!("Failed to fetch media {}: {}", previous, e);
+            return;
+        }
+    }
+    if let Err(e) = data.media.delete(&previous).await {
+        error!("Failed to remove media file {}: {}", previous, e);
+    }
+}
+
+// 内容片段管理页面
+// 分类管理页面
+async fn admin_categories(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    match models::get_categories(_pool.get_ref()).await {
+        Ok(categories) => {
+            let mut ctx = Context::new();
+            ctx.insert("categories", &services::categories::tree(&categories));
+            match data.template.render("admin/categories.html", &ctx) {
+                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+                Err(e) => {
+                    error!("Template rendering error: {}", e);
+                    HttpResponse::InternalServerError().body("Template rendering error")
+                }
+            }
+        },
+        Err(e) => {
+            error!("Failed to fetch categories: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn admin_create_category(
+    form: web::Form<CategoryForm>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let name = form.name.trim();
+    if name.is_empty() || name.chars().count() > 100 {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "分类名称不能为空且不超过 100 个字符"
+        }));
+    }
+    let slug = match form.slug.as_deref().map(str::trim).filter(|slug| !slug.is_empty()) {
+        Some(slug) if services::categories::is_valid_slug(slug) => slug.to_string(),
+        Some(_) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": "slug 只能包含小写字母、数字、- 和 _"
+            }));
+        }
+        None => match services::categories::slug_from_name(name) {
+            Some(slug) => slug,
+            None => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "success": false,
+                    "message": "无法由名称生成 slug，请手动填写"
+                }));
+            }
+        },
+    };
+    let parent_id = match form.parent_id.as_deref().map(str::trim).filter(|id| !id.is_empty()) {
+        None => None,
+        Some(id) => match id.parse::<i64>() {
+            Ok(id) => Some(id),
+            Err(_) => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "success": false,
+                    "message": "上级分类无效"
+                }));
+            }
+        },
+    };
+    if let Some(parent_id) = parent_id {
+        let categories = models::get_categories(_pool.get_ref()).await.unwrap_or_default();
+        if !categories.iter().any(|c| c.id == parent_id) {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": "上级分类不存在"
+            }));
+        }
+    }
+
+    match models::create_category(_pool.get_ref(), name, &slug, parent_id).await {
+        Ok(Some(category)) => {
+            record_activity(&_pool, &username, "create_category", &format!("category:{}", category.id), name).await;
+            HttpResponse::Ok().json(serde_json::json!({ "success": true, "category": category }))
+        }
+        Ok(None) => HttpResponse::Conflict().json(serde_json::json!({
+            "success": false,
+            "message": format!("slug「{}」已被使用", slug)
+        })),
+        Err(e) => {
+            error!("Failed to create category: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "保存失败"
+            }))
+        }
+    }
+}
+
+async fn admin_delete_category(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let category_id = path.into_inner();
+    match models::delete_category(_pool.get_ref(), category_id).await {
+        Ok(true) => {
+            record_activity(&_pool, &username, "delete_category", &format!("category:{}", category_id), "").await;
+            articles_changed(&data);
+            HttpResponse::Ok().json(serde_json::json!({ "success": true }))
+        },
+        Ok(false) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "message": "分类不存在"
+        })),
+        Err(e) => {
+            error!("Failed to delete category: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "删除失败"
+            }))
+        }
+    }
+}
+
+async fn admin_tags(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    match models::get_tag_usage(_pool.get_ref()).await {
+        Ok(tags) => {
+            let tags: Vec<serde_json::Value> = tags
+                .into_iter()
+                .map(|tag| serde_json::json!({ "url": services::tags::tag_url(&tag.name), "tag": tag }))
+                .collect();
+            let mut ctx = Context::new();
+            ctx.insert("tags", &tags);
+            match data.template.render("admin/tags.html", &ctx) {
+                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+                Err(e) => {
+                    error!("Template rendering error: {}", e);
+                    HttpResponse::InternalServerError().body("Template rendering error")
+                }
+            }
+        },
+        Err(e) => {
+            error!("Failed to fetch tags: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MergeTagsForm {
+    // 被合并掉的标签名
+    from: String,
+    // 保留的标签名
+    into: String,
+}
+
+// 合并两个标签，旧标签页跳转到保留的标签
+async fn admin_merge_tags(
+    data: web::Data<AppState>,
+    form: web::Form<MergeTagsForm>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let mut tags = Vec::with_capacity(2);
+    for name in [form.from.trim(), form.into.trim()] {
+        match models::get_tag(_pool.get_ref(), name).await {
+            Ok(Some(tag)) => tags.push(tag),
+            Ok(None) => {
+                return HttpResponse::NotFound().json(serde_json::json!({
+                    "success": false,
+                    "message": format!("标签「{}」不存在", name)
+                }));
+            }
+            Err(e) => {
+                error!("Failed to fetch tag: {}", e);
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "success": false,
+                    "message": "合并失败"
+                }));
+            }
+        }
+    }
+    let (from, into) = (&tags[0], &tags[1]);
+    if from.id == into.id {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "不能把标签合并到它自己"
+        }));
+    }
+    match models::merge_tags(_pool.get_ref(), from, into).await {
+        Ok(()) => {
+            let detail = format!("{} → {}", from.name, into.name);
+            record_activity(&_pool, &username, "merge_tags", &format!("tag:{}", into.id), &detail).await;
+            articles_changed(&data);
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "message": format!("已将「{}」合并到「{}」", from.name, into.name)
+            }))
+        }
+        Err(e) => {
+            error!("Failed to merge tags: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "合并失败"
+            }))
+        }
+    }
+}
+
+// 清理没有文章使用的标签
+async fn admin_purge_tags(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    match models::purge_unused_tags(_pool.get_ref()).await {
+        Ok(deleted) => {
+            if deleted > 0 {
+                record_activity(&_pool, &username, "purge_tags", "tags", &format!("{} tags", deleted)).await;
+                articles_changed(&data);
+            }
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "deleted": deleted,
+                "message": format!("已删除 {} 个未使用的标签", deleted)
+            }))
+        }
+        Err(e) => {
+            error!("Failed to purge tags: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "清理失败"
+            }))
+        }
+    }
+}
+
+async fn admin_snippets(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    match models::get_snippets(_pool.get_ref()).await {
+        Ok(snippets) => {
+            let mut ctx = Context::new();
+            ctx.insert("snippets", &snippets);
+            match data.template.render("admin/snippets.html", &ctx) {
+                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+                Err(e) => {
+                    error!("Template rendering error: {}", e);
+                    HttpResponse::InternalServerError().body("Template rendering error")
+                }
+            }
+        },
+        Err(e) => {
+            error!("Failed to fetch snippets: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn admin_save_snippet(
+    data: web::Data<AppState>,
+    form: web::Form<SnippetForm>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    let name = form.name.trim();
+    if name.is_empty() || name.len() > 64 || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "片段名称只能包含字母、数字、- 和 _"
+        }));
+    }
+
+    if let Err(e) = models::save_snippet(_pool.get_ref(), name, &form.content).await {
+        error!("Failed to save snippet: {}", e);
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "success": false,
+            "message": "保存失败"
+        }));
+    }
+    if let Err(e) = reload_snippets(&data, _pool.get_ref()).await {
+        error!("Failed to reload snippets: {}", e);
+    }
+    HttpResponse::Ok().json(serde_json::json!({ "success": true }))
+}
+
+async fn admin_delete_snippet(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    match models::delete_snippet(_pool.get_ref(), path.into_inner()).await {
+        Ok(true) => {
+            if let Err(e) = reload_snippets(&data, _pool.get_ref()).await {
+                error!("Failed to reload snippets: {}", e);
+            }
+            HttpResponse::Ok().json(serde_json::json!({ "success": true }))
+        },
+        Ok(false) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "message": "片段不存在"
+        })),
+        Err(e) => {
+            error!("Failed to delete snippet: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "删除失败"
+            }))
+        }
+    }
+}
+
+// 内容模板列表API，title为按当天日期展开后的标题
+async fn admin_content_templates(
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    match models::get_content_templates(_pool.get_ref()).await {
+        Ok(templates) => {
+            let today = chrono::Local::now().date_naive();
+            let templates: Vec<serde_json::Value> = templates.into_iter().map(|t| serde_json::json!({
+                "id": t.id,
+                "name": t.name,
+                "title": services::content_template::expand_title_pattern(&t.title_pattern, today),
+                "title_pattern": t.title_pattern,
+                "content": t.content,
+                "updated_at": services::dates::to_rfc3339(&t.updated_at),
+            })).collect();
+            HttpResponse::Ok().json(templates)
+        },
+        Err(e) => {
+            error!("Failed to fetch content templates: {}", e);
+            HttpResponse::InternalServerError().json("Failed to fetch content templates")
+        }
+    }
+}
+
+// 内容模板管理页面
+async fn admin_manage_content_templates(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    match models::get_content_templates(_pool.get_ref()).await {
+        Ok(templates) => {
+            let mut ctx = Context::new();
+            ctx.insert("templates", &templates);
+            match data.template.render("admin/content_templates.html", &ctx) {
+                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+                Err(e) => {
+                    error!("Template rendering error: {}", e);
+                    HttpResponse::InternalServerError().body("Template rendering error")
+                }
+            }
+        },
+        Err(e) => {
+            error!("Failed to fetch content templates: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn admin_save_content_template(
+    form: web::Form<ContentTemplateForm>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    let name = form.name.trim();
+    if name.is_empty() || name.chars().count() > 100 {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "模板名称不能为空（最多100个字符）"
+        }));
+    }
+
+    match models::save_content_template(_pool.get_ref(), name, form.title_pattern.trim(), &form.content).await {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({ "success": true })),
+        Err(e) => {
+            error!("Failed to save content template: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "保存失败"
+            }))
+        }
+    }
+}
+
+async fn admin_delete_content_template(
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    match models::delete_content_template(_pool.get_ref(), path.into_inner()).await {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({ "success": true })),
+        Ok(false) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "message": "模板不存在"
+        })),
+        Err(e) => {
+            error!("Failed to delete content template: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "删除失败"
+            }))
+        }
+    }
+}
+
+// 管理员密码设置页面
+async fn admin_password_settings(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    // 获取当前用户信息
+    match sqlx::query_as::<_, models::User>("SELECT * FROM users WHERE username = ?")
+        .bind(&username)
+        .fetch_one(_pool.get_ref())
+        .await {
+        Ok(user) => {
+            let mut ctx = Context::new();
+            ctx.insert("user", &user);
+            match data.template.render("admin/password_settings.html", &ctx) {
+                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+                Err(e) => {
+                    error!("Template rendering error: {}", e);
+                    HttpResponse::InternalServerError().body("Template rendering error")
+                }
+            }
+        },
+        Err(_) => HttpResponse::Found().append_header(("Location", "/login")).finish()
+    }
+}
+
+// 修改密码
+async fn admin_change_password(
+    form: web::Form<ChangePasswordForm>,
+    session: Session,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    if form.new_password != form.confirm_password {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "新密码和确认密码不匹配"
+        }));
+    }
+    
+    if form.new_password == form.current_password {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "新密码不能与当前密码相同"
+        }));
+    }
+    
+    // 验证当前密码
+    match models::verify_user(_pool.get_ref(), &username, &form.current_password).await {
+        Ok(user) => {
+            // 更新密码
+            match models::update_user_password(_pool.get_ref(), user.id, &form.new_password).await {
+                Ok(_) => {
+                    session.remove(middleware::MUST_CHANGE_PASSWORD);
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "success": true,
+                        "message": "密码修改成功"
+                    }))
+                },
+                Err(e) => {
+                    error!("Failed to update password: {}", e);
+                    HttpResponse::InternalServerError().json(serde_json::json!({
+                        "success": false,
+                        "message": "密码更新失败"
+                    }))
+                }
+            }
+        },
+        Err(_) => HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "当前密码错误"
+        }))
+    }
+}
+
+// 设置安全问题
+async fn admin_set_security_question(
+    form: web::Form<SecurityQuestionForm>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    // 获取用户ID
+    match sqlx::query_as::<_, models::User>("SELECT * FROM users WHERE username = ?")
+        .bind(&username)
+        .fetch_one(_pool.get_ref())
+        .await {
+        Ok(user) => {
+            match models::set_security_question(_pool.get_ref(), user.id, &form.question, &form.answer).await {
+                Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+                    "success": true,
+                    "message": "安全问题设置成功"
+                })),
+                Err(e) => {
+                    error!("Failed to set security question: {}", e);
+                    HttpResponse::InternalServerError().json(serde_json::json!({
+                        "success": false,
+                        "message": "设置失败"
+                    }))
+                }
+            }
+        },
+        Err(_) => HttpResponse::Unauthorized().json(serde_json::json!({
+            "success": false,
+            "message": "用户不存在"
+        }))
+    }
+}
+
+// 作者资料页面
+async fn admin_profile(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    match models::get_user_by_username(_pool.get_ref(), &username).await {
+        Ok(Some(user)) => {
+            let mut ctx = Context::new();
+            ctx.insert("author_url", &services::author::author_path(&user.username));
+            ctx.insert("user", &user);
+            match data.template.render("admin/profile.html", &ctx) {
+                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+                Err(e) => {
+                    error!("Template rendering error: {}", e);
+                    HttpResponse::InternalServerError().body("Template rendering error")
+                }
+            }
+        },
+        Ok(None) => HttpResponse::Found().append_header(("Location", "/login")).finish(),
+        Err(e) => {
+            error!("Failed to fetch user: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+// 保存作者资料
+async fn admin_save_profile(
+    form: web::Form<ProfileForm>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    use services::author::{is_valid_avatar, is_valid_website, MAX_BIO_LENGTH, MAX_DISPLAY_NAME_LENGTH};
+
+    let bad_request = |message: &str| HttpResponse::BadRequest().json(serde_json::json!({
+        "success": false,
+        "message": message
+    }));
+    fn non_empty(value: &str) -> Option<&str> {
+        Some(value.trim()).filter(|v| !v.is_empty())
+    }
+    let profile = models::ProfileUpdate {
+        display_name: non_empty(&form.display_name),
+        bio: non_empty(&form.bio),
+        website: non_empty(&form.website),
+        avatar_url: non_empty(&form.avatar_url),
+    };
+    if profile.display_name.is_some_and(|name| name.chars().count() > MAX_DISPLAY_NAME_LENGTH) {
+        return bad_request("显示名称不能超过 100 个字符");
+    }
+    if profile.bio.is_some_and(|bio| bio.chars().count() > MAX_BIO_LENGTH) {
+        return bad_request("简介不能超过 5000 个字符");
+    }
+    if profile.website.is_some_and(|url| !is_valid_website(url)) {
+        return bad_request("无效的网站地址（应以 http:// 或 https:// 开头）");
+    }
+    if profile.avatar_url.is_some_and(|url| !is_valid_avatar(url)) {
+        return bad_request("无效的头像地址（应为上传的图片或 http(s) 地址）");
+    }
+
+    let user = match models::get_user_by_username(_pool.get_ref(), &username).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return HttpResponse::Unauthorized().json(serde_json::json!({
+                "success": false,
+                "message": "用户不存在"
+            }));
+        }
+        Err(e) => {
+            error!("Failed to fetch user: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    match models::update_user_profile(_pool.get_ref(), user.id, &profile).await {
+        Ok(()) => {
+            record_activity(&_pool, &username, "update_profile", &format!("user:{}", user.id), "").await;
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "message": "资料已保存"
+            }))
+        }
+        Err(e) => {
+            error!("Failed to update profile: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "保存失败"
+            }))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ReferrerReportQuery {
+    // 24h、7d、30d、90d 或 all，默认 30d
+    window: Option<String>,
+    article_id: Option<i64>,
+    limit: Option<usize>,
+}
+
+// 来源统计：站点和每篇文章的主要来源站点及搜索词，以及点击最多的外部链接
+async fn admin_referrer_report(
+    query: web::Query<ReferrerReportQuery>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    use services::analytics::{ReferrerReport, ReportWindow};
+
+    let window = match query.window.as_deref() {
+        None => ReportWindow::default(),
+        Some(value) => match ReportWindow::parse(value) {
+            Some(window) => window,
+            None => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "success": false,
+                    "message": "无效的统计区间（可选 24h、7d、30d、90d、all）"
+                }));
+            }
+        },
+    };
+    let limit = query.limit.unwrap_or(10).clamp(1, 100);
+    let since = window.since(chrono::Utc::now().naive_utc());
+
+    let counts = async {
+        let referrers = models::get_referrer_counts(_pool.get_ref(), since.as_deref(), query.article_id).await?;
+        let queries = models::get_search_query_counts(_pool.get_ref(), since.as_deref(), query.article_id).await?;
+        let outbound = models::get_outbound_click_counts(_pool.get_ref(), since.as_deref(), query.article_id, limit as i64).await?;
+        Ok::<_, sqlx::Error>((referrers, queries, outbound))
+    };
+    match counts.await {
+        Ok((referrers, queries, outbound)) => {
+            let report = ReferrerReport::build(&referrers, &queries, limit);
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "window": window.as_str(),
+                "since": since,
+                "referrers": report.referrers,
+                "search_queries": report.search_queries,
+                "articles": report.articles,
+                // 开启外链点击统计后才有数据
+                "outbound_links": outbound,
+            }))
+        }
+        Err(e) => {
+            error!("Failed to build referrer report: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "获取来源统计失败"
+            }))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PerformanceStatsQuery {
+    #[serde(default)]
+    history: bool,
+}
+
+// Performance monitoring endpoint
+async fn admin_performance_stats(
+    data: web::Data<AppState>,
+    query: web::Query<PerformanceStatsQuery>,
+) -> impl Responder {
+    let metrics = data.markdown_service.get_metrics();
+    let cache_hit_rate = if metrics.cache_hits + metrics.cache_misses > 0 {
+        (metrics.cache_hits as f64 / (metrics.cache_hits + metrics.cache_misses) as f64) * 100.0
+    } else {
+        0.0
+    };
+    
+    let mut stats = serde_json::json!({
+        "total_requests": data.request_metrics.total_requests(),
+        "request_timeouts": data.request_metrics.timed_out_requests(),
+        "slow_renders": data.request_metrics.slow_renders(),
+        "slow_queries": data.request_metrics.slow_queries(),
+        "total_renders": metrics.total_renders,
+        "cache_hits": metrics.cache_hits,
+        "cache_misses": metrics.cache_misses,
+        "cache_hit_rate": format!("{:.1}%", cache_hit_rate),
+        "avg_render_time_ms": format!("{:.2}", metrics.avg_render_time_ms),
+        "cache_size": metrics.cache_size,
+        "memory_usage_kb": format!("{:.2}", metrics.memory_usage_bytes as f64 / 1024.0),
+        "offload_queue_depth": metrics.offload_queue_depth,
+        "offloaded_renders": metrics.offloaded_renders,
+        "coalesced_renders": metrics.coalesced_renders,
+        "evictions": metrics.evictions,
+        "expirations": metrics.expirations,
+        "truncated_code_blocks": metrics.truncated_code_blocks
+    });
+    // ?history=true 附带最近 24 小时的周期快照，供绘图使用
+    if query.history {
+        stats["history"] = serde_json::json!(data.metrics_history.snapshots());
+    }
+
+    HttpResponse::Ok().json(stats)
+}
+
+// Cache management endpoint
+async fn admin_cache_clear(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    data.markdown_service.clear_cache();
+    schedule_cache_prewarm(data.clone(), _pool.get_ref().clone());
+    
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Cache cleared successfully"
+    }))
+}
+
+// 数据库维护：VACUUM / ANALYZE / PRAGMA optimize，返回耗时和前后大小
+async fn admin_db_maintenance(
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    match run_db_maintenance(_pool.get_ref(), &username).await {
+        Ok(report) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "report": report
+        })),
+        Err(e) => {
+            error!("Database maintenance failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "数据库维护失败"
+            }))
+        }
+    }
+}
+
+// Cache optimization endpoint
+async fn admin_cache_optimize(
+    data: web::Data<AppState>,
+) -> impl Responder {
+    data.markdown_service.optimize_cache();
+    
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Cache optimized successfully"
+    }))
+}
+
+// Markdown预览功能
+async fn admin_preview_markdown(
+    data: web::Data<AppState>,
+    json: web::Json<PreviewRequest>,
+) -> impl Responder {
+    let origin = Origin::route("/admin/preview");
+    let rendered = render_markdown(&data, origin, json.into_inner().content, RenderFeatures::default(), MarkdownService::render_with_features)
+        .await
+        .unwrap_or_else(|e| Err(MarkdownError::ParseError(e.to_string())));
+    match rendered {
+        Ok(html) => HttpResponse::Ok().json(PreviewResponse { html }),
+        Err(e) => {
+            error!("Markdown rendering failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to render markdown",
+                "details": e.to_string()
+            }))
+        }
+    }
+}
+
+// 发布前检查：失效的站内链接、不存在的上传文件、缺少 alt 的图片、重复的标题锚点
+async fn admin_validate_markdown(
+    data: web::Data<AppState>,
+    json: web::Json<PreviewRequest>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    use services::validation::Reference;
+
+    let findings = data.markdown_service.validate(&json.content, &data.site_url);
+    let mut issues = findings.issues;
+    let mut checked: std::collections::HashMap<Reference, bool> = std::collections::HashMap::new();
+    for site in findings.references {
+        let exists = match checked.get(&site.reference) {
+            Some(exists) => *exists,
+            None => {
+                let result = match &site.reference {
+                    Reference::Post(id) => sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM articles WHERE id = ? AND deleted_at IS NULL)")
+                        .bind(id)
+                        .fetch_one(_pool.get_ref())
+                        .await,
+                    Reference::Upload(name) => models::get_media_by_name(_pool.get_ref(), name).await.map(|m| m.is_some()),
+                };
+                match result {
+                    Ok(exists) => {
+                        checked.insert(site.reference.clone(), exists);
+                        exists
+                    }
+                    Err(e) => {
+                        error!("Failed to validate markdown references: {}", e);
+                        return HttpResponse::InternalServerError().json(serde_json::json!({
+                            "success": false,
+                            "message": "检查失败"
+                        }));
+                    }
+                }
+            }
+        };
+        if !exists {
+            issues.push(site.missing());
+        }
+    }
+    issues.sort_by_key(|issue| issue.line);
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "valid": issues.is_empty(),
+        "issues": issues
+    }))
+}
+
+// 导入成功的一篇文章
+#[derive(Serialize)]
+struct ImportedArticle {
+    file: String,
+    article_id: i64,
+    title: String,
+    warnings: Vec<String>,
+}
+
+// 未能导入的文件或文章；与已有文章重复时带上已有文章的 id
+#[derive(Serialize)]
+struct FailedImport {
+    file: String,
+    #[serde(skip)]
+    status: actix_web::http::StatusCode,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    existing_id: Option<i64>,
+}
+
+impl FailedImport {
+    fn new(file: String, status: actix_web::http::StatusCode, message: String) -> Self {
+        Self { file, status, message, existing_id: None }
+    }
+}
+
+#[derive(Deserialize)]
+struct ImportQuery {
+    // 已有标题相近的文章时仍然导入
+    allow_duplicates: Option<bool>,
+}
+
+// 按扩展名解析上传的文件：Markdown 文章，或 Ghost、Medium 的导出
+fn parse_import_file(filename: &str, file_content: Vec<u8>) -> Result<ParsedImport, String> {
+    // 验证文件扩展名
+    let format = ImportFormat::from_filename(filename).map_err(|e| format!("Invalid file type: {}", e))?;
+
+    // 验证文件大小
+    let max_size = format.max_size_mb() * 1024 * 1024;
+    if file_content.len() > max_size {
+        return Err(format!("File too large: {}", services::file::FileError::FileTooLarge(file_content.len())));
+    }
+    if format == ImportFormat::MediumArchive {
+        // 解压后的大小同样受限
+        return FileService::parse_medium_archive(&file_content, max_size)
+            .map_err(|e| format!("Failed to read Medium export: {}", e));
+    }
+
+    // 转换为字符串
+    let content_str = String::from_utf8(file_content).map_err(|_| "File must be valid UTF-8 text".to_string())?;
+    match format {
+        ImportFormat::Ghost => {
+            let mut parsed = FileService::parse_ghost_export(&content_str)
+                .map_err(|e| format!("Failed to parse Ghost export: {}", e))?;
+            // 导出里的文章以标题区分
+            let sources = parsed.posts.iter_mut().map(|(source, _)| source).chain(parsed.skipped.iter_mut().map(|(source, _)| source));
+            for source in sources {
+                *source = format!("{}: {}", filename, source);
+            }
+            Ok(parsed)
+        }
+        ImportFormat::MediumPost => FileService::parse_medium_post(&content_str)
+            .map(|post| ParsedImport { posts: vec![(filename.to_string(), post)], skipped: Vec::new() })
+            .map_err(|e| format!("Failed to parse Medium post: {}", e)),
+        _ => FileService::parse_markdown_file(&content_str)
+            .map(|post| ParsedImport { posts: vec![(filename.to_string(), post)], skipped: Vec::new() })
+            .map_err(|e| format!("Failed to parse markdown file: {}", e)),
+    }
+}
+
+// 保存一篇导入的文章；日期、标签和摘要来自导出文件时一并保留
+async fn save_imported_article(
+    data: &AppState,
+    pool: &SqlitePool,
+    username: &str,
+    source: &str,
+    markdown_file: MarkdownFile,
+) -> Result<ImportedArticle, String> {
+    // 未知的代码高亮主题忽略并提示
+    let mut render_options = markdown_file.render_options.clone();
+    let mut warnings = Vec::new();
+    if let Some(theme) = render_options.highlight_theme.take() {
+        if data.markdown_service.has_theme(&theme) {
+            render_options.highlight_theme = Some(theme);
+        } else {
+            warnings.push(format!(
+                "Unknown highlight theme \"{}\" ignored; available: {}",
+                theme, data.markdown_service.theme_names().join(", ")
+            ));
+        }
+    }
+    let created_at = markdown_file.created_at.as_deref().and_then(services::file::import_timestamp);
+    let updated_at = markdown_file.updated_at.as_deref().and_then(services::file::import_timestamp);
+    let keywords = (!markdown_file.tags.is_empty()).then(|| markdown_file.tags.join(", "));
+
+    // 插入到数据库
+    let result = sqlx::query(
+        "INSERT INTO articles (title, content, text_direction, render_options, meta_description, meta_keywords, canonical_url, author_id, created_at, updated_at) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, (SELECT id FROM users WHERE username = ?), COALESCE(?, datetime('now')), COALESCE(?, ?, datetime('now')))"
+    )
+    .bind(&markdown_file.title)
+    .bind(&markdown_file.content)
+    .bind(markdown_file.direction.unwrap_or_default().as_str())
+    .bind(render_options.to_json())
+    .bind(&markdown_file.description)
+    .bind(&keywords)
+    .bind(&markdown_file.canonical_url)
+    .bind(username)
+    .bind(&created_at)
+    .bind(&updated_at)
+    .bind(&created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to insert article: {}", e);
+        "Failed to save article to database".to_string()
+    })?;
+
+    let article_id = result.last_insert_rowid();
+    snapshot_revision(pool, article_id, Some(username)).await;
+    index_article_links(data, pool, article_id, &markdown_file.content).await;
+    // 前言中的 tags 同时作为关键词和标签
+    save_article_tags(pool, article_id, Some(services::tags::parse_tags(&markdown_file.tags.join(",")))).await;
+    refresh_article_slug(pool, article_id).await;
+    let detail = format!("{} ({})", markdown_file.title, source);
+    record_activity(pool, username, "import_article", &services::activity::article_target(article_id), &detail).await;
+    Ok(ImportedArticle {
+        file: source.to_string(),
+        article_id,
+        title: markdown_file.title,
+        warnings,
+    })
+}
+
+// 文件导入功能；一次可以上传多个文件（Markdown 或 Ghost、Medium 的导出），导入的文章记为同一批次，可在导入记录中整批回滚
+async fn admin_import_article(
+    data: web::Data<AppState>,
+    mut payload: Multipart,
+    query: web::Query<ImportQuery>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    use actix_web::http::StatusCode;
+
+    let mut batch_id = None;
+    let mut imported = Vec::new();
+    let mut failed = Vec::new();
+    // 处理文件上传
+    while let Some(mut field) = payload.try_next().await.unwrap_or(None) {
+        let Some(filename) = field.content_disposition().get_filename().map(str::to_string) else {
+            continue;
+        };
+
+        // 读取文件内容
+        let mut file_content = Vec::new();
+        while let Some(chunk) = field.try_next().await.unwrap_or(None) {
+            file_content.extend_from_slice(&chunk);
+        }
+
+        let parsed = match parse_import_file(&filename, file_content) {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                failed.push(FailedImport::new(filename, StatusCode::BAD_REQUEST, message));
+                continue;
+            }
+        };
+        failed.extend(parsed.skipped.into_iter().map(|(source, reason)| FailedImport::new(source, StatusCode::BAD_REQUEST, reason)));
+        for (source, markdown_file) in parsed.posts {
+            // 同一份导出再导入一次时跳过已有的文章（同一批中先导入的也算）
+            if !query.allow_duplicates.unwrap_or(false)
+                && let Some((existing_id, existing_title)) = find_duplicate_article(_pool.get_ref(), &markdown_file.title).await
+            {
+                failed.push(FailedImport {
+                    file: source,
+                    status: StatusCode::CONFLICT,
+                    message: format!("Duplicate of existing article #{} \"{}\"", existing_id, existing_title),
+                    existing_id: Some(existing_id),
+                });
+                continue;
+            }
+            match save_imported_article(&data, _pool.get_ref(), &username, &source, markdown_file).await {
+                Ok(article) => {
+                    if batch_id.is_none() {
+                        match models::create_import_batch(_pool.get_ref(), &username).await {
+                            Ok(id) => batch_id = Some(id),
+                            Err(e) => error!("Failed to record import batch: {}", e),
+                        }
+                    }
+                    if let Some(batch_id) = batch_id
+                        && let Err(e) = models::add_import_batch_item(_pool.get_ref(), batch_id, article.article_id, &source, &article.title).await
+                    {
+                        error!("Failed to record import of article {}: {}", article.article_id, e);
+                    }
+                    imported.push(article);
+                }
+                Err(message) => failed.push(FailedImport::new(source, StatusCode::INTERNAL_SERVER_ERROR, message)),
+            }
+        }
+    }
+
+    if imported.is_empty() {
+        // 只有一个文件时保留原来的错误响应
+        return match failed.len() {
+            0 => HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": "No valid file found in upload"
+            })),
+            1 => {
+                let failure = failed.remove(0);
+                HttpResponse::build(failure.status).json(serde_json::json!({
+                    "success": false,
+                    "message": failure.message,
+                    "existing_id": failure.existing_id
+                }))
+            }
+            _ => HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": "None of the files could be imported",
+                "failed": failed
+            })),
+        };
+    }
+
+    articles_changed(&data);
+    let message = if imported.len() == 1 {
+        "Article imported successfully".to_string()
+    } else {
+        format!("{} articles imported", imported.len())
+    };
+    HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": message,
+        "article_id": imported[0].article_id,
+        "title": imported[0].title,
+        "warnings": imported[0].warnings,
+        "batch_id": batch_id,
+        "imported": imported,
+        "failed": failed
+    }))
+}
+
+// 导入记录：每个批次导入了哪些文件、对应哪篇文章，以及文章现在的状态
+async fn admin_imports(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let batches = match models::get_import_batches(_pool.get_ref(), 50).await {
+        Ok(batches) => batches,
+        Err(e) => {
+            error!("Failed to load import batches: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let mut views = Vec::with_capacity(batches.len());
+    for batch in batches {
+        let items = match models::get_import_batch_items(_pool.get_ref(), batch.id).await {
+            Ok(items) => items,
+            Err(e) => {
+                error!("Failed to load import batch {}: {}", batch.id, e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        };
+        let live = items.iter().filter(|item| item.status == "live").count();
+        views.push(serde_json::json!({
+            "batch": batch,
+            "items": items,
+            "live": live
+        }));
+    }
+    let mut ctx = Context::new();
+    ctx.insert("batches", &views);
+    match data.template.render("admin/imports.html", &ctx) {
+        Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+        Err(e) => {
+            error!("Template rendering error: {}", e);
+            HttpResponse::InternalServerError().body("Template rendering error")
+        }
+    }
+}
+
+// 整批回滚：把批次中仍公开的文章移入回收站，需要时可以从回收站恢复
+async fn admin_rollback_import(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let batch_id = path.into_inner();
+    match models::get_import_batch(_pool.get_ref(), batch_id).await {
+        Ok(Some(batch)) if batch.rolled_back_at.is_some() => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "success": false,
+                "message": "该批次已回滚"
+            }));
+        }
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "success": false,
+                "message": "导入批次不存在"
+            }));
+        }
+        Err(e) => {
+            error!("Failed to load import batch {}: {}", batch_id, e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "回滚失败"
+            }));
+        }
+    }
+    match models::rollback_import_batch(_pool.get_ref(), batch_id).await {
+        Ok(trashed) => {
+            let detail = format!("{} articles", trashed.len());
+            record_activity(&_pool, &username, "rollback_import", &format!("import:{}", batch_id), &detail).await;
+            if !trashed.is_empty() {
+                articles_changed(&data);
+                if let Err(e) = reload_navigation(&data, _pool.get_ref()).await {
+                    error!("Failed to reload navigation menu: {}", e);
+                }
+            }
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "trashed": trashed,
+                "message": format!("已将 {} 篇文章移入回收站", trashed.len())
+            }))
+        }
+        Err(e) => {
+            error!("Failed to roll back import batch {}: {}", batch_id, e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "回滚失败"
+            }))
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TrashedArticleView {
+    #[serde(flatten)]
+    article: models::TrashedArticle,
+    /// 自动永久删除的时间，未开启自动清理时为 None
+    purge_at: Option<String>,
+}
+
+// 回收站：可以恢复或永久删除
+async fn admin_trash(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let articles = match models::get_trashed_articles(_pool.get_ref()).await {
+        Ok(articles) => articles,
+        Err(e) => {
+            error!("Failed to load trashed articles: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let trash = data.settings().trash;
+    let articles: Vec<TrashedArticleView> = articles
+        .into_iter()
+        .map(|article| TrashedArticleView { purge_at: trash.purge_at(&article.deleted_at), article })
+        .collect();
+    let mut ctx = Context::new();
+    ctx.insert("articles", &articles);
+    ctx.insert("trash", &trash);
+    match data.template.render("admin/trash.html", &ctx) {
+        Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+        Err(e) => {
+            error!("Template rendering error: {}", e);
+            HttpResponse::InternalServerError().body("Template rendering error")
+        }
+    }
+}
+
+async fn admin_restore_article(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let article_id = path.into_inner();
+    let article = match models::get_trashed_article(_pool.get_ref(), article_id).await {
+        Ok(Some(article)) => article,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "success": false,
+                "message": "回收站中没有这篇文章"
+            }));
+        }
+        Err(e) => {
+            error!("Failed to load trashed article {}: {}", article_id, e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "恢复失败"
+            }));
+        }
+    };
+    match models::restore_article(_pool.get_ref(), article_id).await {
+        Ok(true) => {
+            record_activity(&_pool, &username, "restore_article", &services::activity::article_target(article_id), &article.title).await;
+            data.events.publish(Event::ArticleRestored { article_id, published: article.status != models::STATUS_DRAFT });
+            HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "message": "文章已恢复"
+            }))
+        }
+        // 同时被其他请求恢复或删除
+        Ok(false) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "message": "回收站中没有这篇文章"
+        })),
+        Err(e) => {
+            error!("Failed to restore article {}: {}", article_id, e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "恢复失败"
+            }))
+        }
+    }
+}
+
+// 只能永久删除回收站中的文章，公开的文章要先移入回收站
+async fn admin_purge_article(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>,
+    CurrentUser(username): CurrentUser,
+) -> impl Responder {
+    let article_id = path.into_inner();
+    match models::get_trashed_article(_pool.get_ref(), article_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "success": false,
+                "message": "回收站中没有这篇文章"
+            }));
+        }
+        Err(e) => {
+            error!("Failed to load trashed article {}: {}", article_id, e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "删除失败"
+            }));
+        }
+    }
+    match purge_article(&data, _pool.get_ref(), article_id, &username).await {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "message": "文章已永久删除"
+        })),
+        Err(e) => {
+            error!("Failed to purge article {}: {}", article_id, e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "删除失败"
+            }))
+        }
+    }
+}
+
+// 文章导出功能
+#[derive(Deserialize)]
+struct ExportQuery {
+    /// `append` adds revision history to the markdown file, `zip` bundles it as separate files
+    revisions: Option<String>,
+    /// `bluster` (default), `hugo`, `jekyll` or `obsidian`
+    profile: Option<String>,
+}
+
+async fn admin_export_article(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    query: web::Query<ExportQuery>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let article_id = path.into_inner();
+    let mode = RevisionExport::parse(query.revisions.as_deref());
+    let profile = match query.profile.as_deref() {
+        None | Some("") => ExportProfile::default(),
+        Some(value) => match ExportProfile::parse(value) {
+            Some(profile) => profile,
+            None => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "success": false,
+                    "message": format!("Unknown export profile: {} (expected bluster, hugo, jekyll or obsidian)", value)
+                }));
+            }
+        },
+    };
+    
+    // 从数据库获取文章
+    match sqlx::query_as::<_, models::Article>(
+        "SELECT * FROM articles WHERE id = ? AND deleted_at IS NULL"
+    )
+    .bind(article_id)
+    .fetch_one(_pool.get_ref())
+    .await {
+        Ok(article) => {
+            // 需要时附带修订历史
+            let revisions = if mode == RevisionExport::None || profile != ExportProfile::Bluster {
+                Vec::new()
+            } else {
+                match models::get_article_revisions(_pool.get_ref(), article_id).await {
+                    Ok(revisions) => revisions,
+                    Err(e) => {
+                        error!("Failed to fetch revisions for export: {}", e);
+                        return HttpResponse::InternalServerError().json(serde_json::json!({
+                            "success": false,
+                            "message": "Failed to load revision history"
+                        }));
+                    }
+                }
+            };
+
+            // 生成导出内容（文件名已做安全处理）
+            let export = match FileService::generate_export(&article, &revisions, mode, profile, data.settings().dates.timezone) {
+                Ok(export) => export,
+                Err(e) => {
+                    error!("Failed to generate markdown export: {}", e);
+                    return HttpResponse::InternalServerError().json(serde_json::json!({
+                        "success": false,
+                        "message": format!("Export generation failed: {}", e)
+                    }));
+                }
+            };
+            
+            // 返回文件下载响应
+            HttpResponse::Ok()
+                .content_type(export.content_type)
+                .append_header(("Content-Disposition", format!("attachment; filename=\"{}\"", export.filename)))
+                .body(export.bytes)
+        },
+        Err(e) => {
+            error!("Failed to fetch article for export: {}", e);
+            HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Article not found"
+            }))
+        }
+    }
+}
@@ -0,0 +1,844 @@
+//! JSON endpoints: the public read-only API under `/api/v1`, the status
+//! probe, and the `/articles` endpoints the admin editor saves through.
+
+use actix_web::{web, Responder, HttpResponse};
+use actix_session::Session;
+use log::error;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use crate::{models, services};
+use crate::services::events::Event;
+use crate::services::pagination::{Cursor, Pagination};
+use crate::services::summary::summarize;
+use crate::app::{
+    AppState, ArticleForm, Origin, REACTION_SESSION_KEY, find_duplicate_article, index_article_links, record_activity,
+    refresh_article_slug, reload_navigation, render_markdown_with_fallback, save_article_tags, snapshot_revision, timed_query,
+};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/articles", web::get().to(get_articles))
+        .route("/articles/{id}", web::get().to(get_article))
+        .route("/articles/{id}/reactions", web::get().to(get_article_reactions))
+        .route("/articles", web::post().to(create_article))
+        .route("/articles/{id}", web::put().to(update_article))
+        .route("/articles/{id}", web::delete().to(delete_article))
+        .route("/api/status", web::get().to(api_status))
+        .route("/api/search", web::get().to(api_search))
+        .service(
+            web::scope("/api/v1")
+                .route("/articles", web::get().to(api_list_articles))
+                .service(
+                    web::resource("/articles/batch")
+                        .app_data(web::JsonConfig::default().limit(MAX_BATCH_BYTES))
+                        .route(web::post().to(api_create_articles)),
+                )
+                .route("/articles/{id}/toc", web::get().to(get_article_toc))
+                .route("/graph", web::get().to(api_link_graph))
+                .route("/search/suggest", web::get().to(api_search_suggest)),
+        );
+}
+
+#[derive(Deserialize)]
+struct ArticlesQuery {
+    // 上一页响应中的 next_cursor
+    cursor: Option<String>,
+    // 按页码分页，与 cursor 二选一
+    page: Option<i64>,
+    per_page: Option<i64>,
+    // 只返回摘要，不含正文
+    #[serde(default)]
+    summary: bool,
+}
+
+// 正文或摘要，取决于 ?summary=
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ArticlePayload {
+    Full(models::ArticleContent),
+    Summary(ApiArticle),
+}
+
+// 不带参数时返回全部文章（旧的客户端依赖这种格式）；带 page 时按页码分页，否则按游标分页
+async fn get_articles(
+    data: web::Data<AppState>,
+    query: web::Query<ArticlesQuery>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    if query.cursor.is_none() && query.page.is_none() && query.per_page.is_none() && !query.summary {
+        return match sqlx::query_as::<_, models::ArticleContent>(
+            "SELECT id, title, content FROM articles WHERE deleted_at IS NULL AND status = 'published' ORDER BY created_at DESC, id DESC"
+        )
+        .fetch_all(_pool.get_ref())
+        .await {
+            Ok(articles) => HttpResponse::Ok().json(articles),
+            Err(e) => {
+                error!("Failed to fetch articles: {}", e);
+                HttpResponse::InternalServerError().finish()
+            }
+        };
+    }
+    if query.cursor.is_some() && query.page.is_some() {
+        return HttpResponse::BadRequest().json("Use either cursor or page");
+    }
+    let origin = Origin::route("/articles");
+
+    if query.page.is_some() {
+        let total = match timed_query(&data, origin, models::count_published_articles(_pool.get_ref())).await {
+            Ok(total) => total,
+            Err(e) => {
+                error!("Failed to count articles: {}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        };
+        let pagination = Pagination::new("/articles", query.page, query.per_page, API_DEFAULT_PER_PAGE, API_MAX_PER_PAGE, total);
+        let fetch = models::get_published_articles(_pool.get_ref(), pagination.per_page, pagination.offset());
+        let rows = match timed_query(&data, origin, fetch).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                error!("Failed to fetch articles: {}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        };
+        return HttpResponse::Ok().json(serde_json::json!({
+            "articles": article_payloads(&data, rows, query.summary).await,
+            "page": pagination.page,
+            "per_page": pagination.per_page,
+            "total": pagination.total,
+            "total_pages": pagination.total_pages,
+        }));
+    }
+
+    let cursor = match query.cursor.as_deref().filter(|c| !c.is_empty()) {
+        Some(token) => match Cursor::decode(token) {
+            Some(cursor) => Some(cursor),
+            None => return HttpResponse::BadRequest().json("Invalid cursor"),
+        },
+        None => None,
+    };
+    let per_page = query.per_page.unwrap_or(API_DEFAULT_PER_PAGE).clamp(1, API_MAX_PER_PAGE);
+    // 多取一篇，判断后面是否还有
+    let fetch = models::get_published_articles_after(_pool.get_ref(), cursor.as_ref(), per_page + 1);
+    let mut rows = match timed_query(&data, origin, fetch).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Failed to fetch articles: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let next_cursor = if rows.len() as i64 > per_page {
+        rows.truncate(per_page as usize);
+        rows.last().map(|last| Cursor::new(&last.created_at, last.id).encode())
+    } else {
+        None
+    };
+    HttpResponse::Ok().json(serde_json::json!({
+        "articles": article_payloads(&data, rows, query.summary).await,
+        "per_page": per_page,
+        "next_cursor": next_cursor,
+    }))
+}
+
+async fn article_payloads(data: &web::Data<AppState>, rows: Vec<models::Article>, summary: bool) -> Vec<ArticlePayload> {
+    let mut payloads = Vec::with_capacity(rows.len());
+    for row in rows {
+        payloads.push(if summary {
+            ArticlePayload::Summary(api_article(data, "/articles", row).await)
+        } else {
+            ArticlePayload::Full(models::ArticleContent { id: row.id, title: row.title, content: row.content })
+        });
+    }
+    payloads
+}
+
+async fn get_article(
+    _pool: web::Data<SqlitePool>,
+    path: web::Path<i64>
+) -> impl Responder {
+    let article_id = path.into_inner();
+    match sqlx::query_as::<_, models::ArticleContent>(
+        "SELECT id, title, content FROM articles WHERE id = ? AND deleted_at IS NULL AND status = 'published'"
+    )
+    .bind(article_id)
+    .fetch_optional(_pool.get_ref())
+    .await {
+        Ok(Some(article)) => HttpResponse::Ok().json(article),
+        // 草稿、回收站中的文章和不存在的 id 一样处理
+        Ok(None) => HttpResponse::NotFound().json("Article not found"),
+        Err(e) => {
+            error!("Failed to fetch article: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn get_article_reactions(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+    path: web::Path<i64>,
+    session: Session,
+) -> impl Responder {
+    let article_id = path.into_inner();
+    let session_key = session.get::<String>(REACTION_SESSION_KEY).unwrap_or(None);
+    match models::get_reaction_counts(_pool.get_ref(), article_id, &data.reaction_emojis, session_key.as_deref()).await {
+        Ok(reactions) => HttpResponse::Ok().json(reactions),
+        Err(e) => {
+            error!("Failed to fetch reactions: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiArticlesQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct ApiArticle {
+    id: i64,
+    title: String,
+    summary: String,
+    url: String,
+    #[serde(serialize_with = "services::dates::rfc3339::serialize")]
+    created_at: String,
+    #[serde(serialize_with = "services::dates::rfc3339::serialize")]
+    updated_at: String,
+}
+
+// 摘要按站点设置从渲染后的正文生成
+async fn api_article(data: &web::Data<AppState>, route: &'static str, row: models::Article) -> ApiArticle {
+    let rendered = render_markdown_with_fallback(data, Origin::article(route, row.id), row.content).await;
+    ApiArticle {
+        id: row.id,
+        title: row.title,
+        summary: summarize(&data.settings().summary, row.meta_description.as_deref(), &rendered),
+        url: format!("{}{}", data.site_url, services::slugs::post_path(row.id, row.slug.as_deref())),
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    }
+}
+
+// 公开 API 每页文章数
+const API_DEFAULT_PER_PAGE: i64 = 10;
+
+const API_MAX_PER_PAGE: i64 = 50;
+
+async fn api_list_articles(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+    query: web::Query<ApiArticlesQuery>,
+) -> impl Responder {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(API_DEFAULT_PER_PAGE).clamp(1, API_MAX_PER_PAGE);
+    let cache = &data.api_cache;
+    let cache_control = if cache.ttl().is_zero() {
+        "no-cache".to_string()
+    } else {
+        format!("public, max-age={}", cache.ttl().as_secs())
+    };
+    // 按规范化后的参数缓存，参数顺序或越界值不同也能命中
+    let key = services::response_cache::ResponseCache::key([("page", page.to_string()), ("per_page", per_page.to_string())]);
+    if let Some(body) = cache.get(&key) {
+        return HttpResponse::Ok()
+            .content_type("application/json")
+            .insert_header(("Cache-Control", cache_control))
+            .insert_header(("X-Cache", "HIT"))
+            .body(body);
+    }
+
+    let generation = cache.generation();
+    let query = sqlx::query_as::<_, models::Article>(
+        "SELECT * FROM articles WHERE deleted_at IS NULL AND status = 'published' ORDER BY created_at DESC LIMIT ? OFFSET ?"
+    )
+    .bind(per_page)
+    .bind((page - 1).saturating_mul(per_page))
+    .fetch_all(_pool.get_ref());
+    let rows = match timed_query(&data, Origin::route("/api/v1/articles"), query).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Failed to fetch articles: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let total = match sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM articles WHERE deleted_at IS NULL AND status = 'published'")
+        .fetch_one(_pool.get_ref())
+        .await
+    {
+        Ok(total) => total,
+        Err(e) => {
+            error!("Failed to count articles: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let mut articles = Vec::with_capacity(rows.len());
+    for row in rows {
+        articles.push(api_article(&data, "/api/v1/articles", row).await);
+    }
+    let body = serde_json::json!({
+        "articles": articles,
+        "page": page,
+        "per_page": per_page,
+        "total": total,
+    })
+    .to_string();
+    cache.insert(key, generation, body.clone());
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .insert_header(("Cache-Control", cache_control))
+        .insert_header(("X-Cache", "MISS"))
+        .body(body)
+}
+
+// 文章目录（标题树），供外部阅读器和编辑器大纲使用
+async fn get_article_toc(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let article_id = path.into_inner();
+    match sqlx::query_scalar::<_, String>("SELECT content FROM articles WHERE id = ? AND deleted_at IS NULL AND status = 'published'")
+        .bind(article_id)
+        .fetch_optional(_pool.get_ref())
+        .await
+    {
+        Ok(Some(content)) => {
+            let toc = services::toc::build_tree(data.markdown_service.collect_headings(&content));
+            HttpResponse::Ok().json(serde_json::json!({
+                "article_id": article_id,
+                "toc": toc,
+            }))
+        }
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "message": "文章不存在"
+        })),
+        Err(e) => {
+            error!("Failed to fetch article: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+const GRAPH_CACHE_KEY: &str = "graph";
+
+// 文章之间的链接图：节点为公开的文章，边为文章中指向其他文章的链接
+async fn api_link_graph(data: web::Data<AppState>, _pool: web::Data<SqlitePool>) -> impl Responder {
+    let cache = &data.api_cache;
+    let cache_control = if cache.ttl().is_zero() {
+        "no-cache".to_string()
+    } else {
+        format!("public, max-age={}", cache.ttl().as_secs())
+    };
+    if let Some(body) = cache.get(GRAPH_CACHE_KEY) {
+        return HttpResponse::Ok()
+            .content_type("application/json")
+            .insert_header(("Cache-Control", cache_control))
+            .insert_header(("X-Cache", "HIT"))
+            .body(body);
+    }
+
+    let generation = cache.generation();
+    let query = sqlx::query_as::<_, (i64, String)>("SELECT id, title FROM articles WHERE deleted_at IS NULL AND status = 'published'")
+        .fetch_all(_pool.get_ref());
+    let articles = match timed_query(&data, Origin::route("/api/v1/graph"), query).await {
+        Ok(articles) => articles,
+        Err(e) => {
+            error!("Failed to fetch articles: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let links = match models::get_article_links(_pool.get_ref()).await {
+        Ok(links) => links,
+        Err(e) => {
+            error!("Failed to fetch article links: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let nodes = articles
+        .into_iter()
+        .map(|(id, title)| (id, title, format!("{}/post/{}", data.site_url, id)))
+        .collect();
+    let body = serde_json::to_string(&services::link_graph::LinkGraph::build(nodes, &links)).unwrap_or_default();
+    cache.insert(GRAPH_CACHE_KEY.to_string(), generation, body.clone());
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .insert_header(("Cache-Control", cache_control))
+        .insert_header(("X-Cache", "MISS"))
+        .body(body)
+}
+
+#[derive(Deserialize)]
+struct SearchSuggestQuery {
+    q: Option<String>,
+    limit: Option<i64>,
+}
+
+// 搜索框补全条数
+const SUGGEST_DEFAULT_LIMIT: i64 = 8;
+
+const SUGGEST_MAX_LIMIT: i64 = 20;
+
+// 输入时的标题补全：已输入的词都要匹配，最后一个按前缀匹配
+async fn api_search_suggest(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+    query: web::Query<SearchSuggestQuery>,
+) -> impl Responder {
+    if !data.settings().features.search {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "message": "搜索功能未开启"
+        }));
+    }
+    let input: String = query.q.as_deref().unwrap_or("").trim().chars().take(services::search::MAX_QUERY_LENGTH).collect();
+    let limit = query.limit.unwrap_or(SUGGEST_DEFAULT_LIMIT).clamp(1, SUGGEST_MAX_LIMIT);
+    if services::search::title_prefix_query(&input).is_none() {
+        return HttpResponse::Ok().json(serde_json::json!({
+            "query": input,
+            "suggestions": [],
+        }));
+    }
+    let cache = &data.api_cache;
+    let cache_control = if cache.ttl().is_zero() {
+        "no-cache".to_string()
+    } else {
+        format!("public, max-age={}", cache.ttl().as_secs())
+    };
+    let key = services::response_cache::ResponseCache::key([("suggest", input.clone()), ("limit", limit.to_string())]);
+    if let Some(body) = cache.get(&key) {
+        return HttpResponse::Ok()
+            .content_type("application/json")
+            .insert_header(("Cache-Control", cache_control))
+            .insert_header(("X-Cache", "HIT"))
+            .body(body);
+    }
+
+    let generation = cache.generation();
+    let ranking = data.settings().search;
+    let query = services::search::suggest(_pool.get_ref(), &input, &ranking, limit);
+    let rows = match timed_query(&data, Origin::route("/api/v1/search/suggest"), query).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Failed to search suggestions: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let suggestions: Vec<_> = rows
+        .into_iter()
+        .map(|row| serde_json::json!({
+            "id": row.id,
+            "title": row.title,
+            "url": format!("{}/post/{}", data.site_url, row.id),
+        }))
+        .collect();
+    let body = serde_json::json!({
+        "query": input,
+        "suggestions": suggestions,
+    })
+    .to_string();
+    cache.insert(key, generation, body.clone());
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .insert_header(("Cache-Control", cache_control))
+        .insert_header(("X-Cache", "MISS"))
+        .body(body)
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: Option<String>,
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct SearchResultJson {
+    #[serde(flatten)]
+    hit: services::search::SearchHit,
+    url: String,
+}
+
+// 全文搜索，结果与 /search 页面相同；title_html 和 snippet_html 已转义，匹配的词在 <mark> 中
+async fn api_search(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+    query: web::Query<SearchQuery>,
+) -> impl Responder {
+    if !data.settings().features.search {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "message": "搜索功能未开启"
+        }));
+    }
+    let input: String = query.q.as_deref().unwrap_or("").trim().chars().take(services::search::MAX_QUERY_LENGTH).collect();
+    let ranking = data.settings().search;
+    let search = services::search::search(_pool.get_ref(), &input, &ranking, query.page, query.per_page);
+    let (pagination, hits) = match timed_query(&data, Origin::route("/api/search"), search).await {
+        Ok(page) => page,
+        Err(e) => {
+            error!("Failed to search articles: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let results: Vec<_> = hits
+        .into_iter()
+        .map(|hit| SearchResultJson { url: format!("{}{}", data.site_url, hit.path), hit })
+        .collect();
+    HttpResponse::Ok().json(serde_json::json!({
+        "query": input,
+        "total": pagination.total,
+        "page": pagination.page,
+        "per_page": pagination.per_page,
+        "total_pages": pagination.total_pages,
+        "results": results,
+    }))
+}
+
+async fn update_article(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    form: web::Form<ArticleForm>,
+    _pool: web::Data<SqlitePool>,
+    session: Session,
+) -> impl Responder {
+    // 检查session中的登录状态
+    let username = session.get::<String>("username").unwrap_or(None);
+    if username.is_none() {
+        return HttpResponse::Unauthorized().json("Unauthorized");
+    }
+    let Ok(canonical_url) = form.canonical_url() else {
+        return HttpResponse::BadRequest().json("Invalid canonical URL");
+    };
+    let article_id = path.into_inner();
+    snapshot_revision(&_pool, article_id, None).await;
+    match sqlx::query(
+        "UPDATE articles SET title = ?, content = ?, meta_description = COALESCE(?, meta_description), \
+         meta_keywords = COALESCE(?, meta_keywords), noindex = COALESCE(?, noindex), \
+         text_direction = COALESCE(?, text_direction), canonical_url = NULLIF(COALESCE(?, canonical_url), ''), \
+         status = CASE WHEN ? THEN 'draft' ELSE status END, published_at = CASE WHEN ? THEN NULL ELSE published_at END, \
+         updated_at = datetime('now') WHERE id = ? AND deleted_at IS NULL"
+    )
+    .bind(&form.title)
+    .bind(&form.content)
+    .bind(form.meta_description())
+    .bind(form.meta_keywords())
+    .bind(form.noindex)
+    .bind(form.text_direction())
+    .bind(canonical_url)
+    .bind(form.is_draft())
+    .bind(form.is_draft())
+    .bind(article_id)
+    .execute(_pool.get_ref())
+    .await {
+        Ok(_) => {
+            snapshot_revision(&_pool, article_id, username.as_deref()).await;
+            index_article_links(&data, &_pool, article_id, &form.content).await;
+            save_article_tags(&_pool, article_id, form.tags()).await;
+            refresh_article_slug(&_pool, article_id).await;
+            data.events.publish(Event::ArticleUpdated { article_id });
+            // 撤回为草稿的文章不再出现在导航菜单中
+            if form.is_draft()
+                && let Err(e) = reload_navigation(&data, _pool.get_ref()).await
+            {
+                error!("Failed to reload navigation menu: {}", e);
+            }
+            HttpResponse::Ok().json("Article updated successfully")
+        }
+        Err(e) => {
+            error!("Failed to update article: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+pub(super) async fn delete_article(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>,
+    session: Session
+) -> impl Responder {
+    // 检查session中的登录状态
+    let Some(username) = session.get::<String>("username").unwrap_or(None) else {
+        return HttpResponse::Found()
+            .append_header(("Location", "/login"))
+            .finish();
+    };
+    let article_id = path.into_inner();
+    // 先移到回收站，超过保留期后由定时任务永久删除
+    match models::trash_article(_pool.get_ref(), article_id).await {
+        Ok(trashed) => {
+            if trashed {
+                record_activity(&_pool, &username, "trash_article", &services::activity::article_target(article_id), "").await;
+                data.events.publish(Event::ArticleDeleted { article_id });
+                if let Err(e) = reload_navigation(&data, _pool.get_ref()).await {
+                    error!("Failed to reload navigation menu: {}", e);
+                }
+            }
+            HttpResponse::Ok().json("Article deleted successfully")
+        }
+        Err(e) => {
+            error!("Failed to delete article: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn create_article(
+    data: web::Data<AppState>,
+    form: web::Form<ArticleForm>,
+    _pool: web::Data<SqlitePool>,
+    session: Session,
+) -> impl Responder {
+    // 检查session中的登录状态
+    let username = session.get::<String>("username").unwrap_or(None);
+    if username.is_none() {
+        return HttpResponse::Unauthorized().json("Unauthorized");
+    }
+    let Ok(canonical_url) = form.canonical_url() else {
+        return HttpResponse::BadRequest().json("Invalid canonical URL");
+    };
+    // 重复提交或重复导入时不再插入第二份，除非明确要求
+    if !form.allow_duplicate.unwrap_or(false)
+        && let Some((existing_id, existing_title)) = find_duplicate_article(_pool.get_ref(), &form.title).await
+    {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "message": format!("An article with a similar title already exists: \"{}\"; set allow_duplicate to create it anyway", existing_title),
+            "existing_id": existing_id,
+        }));
+    }
+    match sqlx::query(
+        "INSERT INTO articles (title, content, meta_description, meta_keywords, noindex, text_direction, canonical_url, status, author_id, created_at, updated_at) \
+         VALUES (?, ?, ?, ?, ?, ?, NULLIF(?, ''), ?, (SELECT id FROM users WHERE username = ?), datetime('now'), datetime('now'))"
+    )
+    .bind(&form.title)
+    .bind(&form.content)
+    .bind(form.meta_description())
+    .bind(form.meta_keywords())
+    .bind(form.noindex.unwrap_or(false))
+    .bind(form.text_direction().unwrap_or("auto"))
+    .bind(canonical_url)
+    .bind(form.status())
+    .bind(username.as_deref())
+    .execute(_pool.get_ref())
+    .await {
+        Ok(result) => {
+            let article_id = result.last_insert_rowid();
+            snapshot_revision(&_pool, article_id, username.as_deref()).await;
+            index_article_links(&data, &_pool, article_id, &form.content).await;
+            save_article_tags(&_pool, article_id, form.tags()).await;
+            refresh_article_slug(&_pool, article_id).await;
+            let actor = username.as_deref().unwrap_or("admin");
+            let action = if form.is_draft() { "save_draft" } else { "publish_article" };
+            record_activity(&_pool, actor, action, &services::activity::article_target(article_id), &form.title).await;
+            data.events.publish(Event::ArticleCreated { article_id, published: !form.is_draft() });
+            HttpResponse::Ok().json("Article created successfully")
+        }
+        Err(e) => {
+            error!("Failed to create article: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+// 批量创建一次最多的文章数和请求体大小
+const MAX_BATCH_ARTICLES: usize = 100;
+
+const MAX_BATCH_BYTES: usize = 8 * 1024 * 1024;
+
+// 批量创建中每篇文章的结果，index 对应请求数组中的位置
+#[derive(Serialize)]
+struct BatchItemResult {
+    index: usize,
+    created: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    // 标题相近的已有文章
+    #[serde(skip_serializing_if = "Option::is_none")]
+    existing_id: Option<i64>,
+}
+
+impl BatchItemResult {
+    fn failed(index: usize, error: String) -> Self {
+        Self { index, created: false, id: None, url: None, error: Some(error), existing_id: None }
+    }
+}
+
+// 批量创建文章，字段与 POST /articles 相同。每篇单独校验，通过的在同一个事务中创建，
+// 未通过的在结果中说明原因；数据库出错时一篇也不创建
+async fn api_create_articles(
+    data: web::Data<AppState>,
+    body: web::Json<Vec<ArticleForm>>,
+    _pool: web::Data<SqlitePool>,
+    session: Session,
+) -> impl Responder {
+    let Some(username) = session.get::<String>("username").unwrap_or(None) else {
+        return HttpResponse::Unauthorized().json("Unauthorized");
+    };
+    let forms = body.into_inner();
+    if forms.is_empty() {
+        return HttpResponse::BadRequest().json("Expected a non-empty array of articles");
+    }
+    if forms.len() > MAX_BATCH_ARTICLES {
+        return HttpResponse::PayloadTooLarge().json(format!("At most {} articles per batch", MAX_BATCH_ARTICLES));
+    }
+    let existing = models::get_article_titles(_pool.get_ref()).await.unwrap_or_else(|e| {
+        error!("Failed to fetch article titles: {}", e);
+        Vec::new()
+    });
+
+    let mut results: Vec<Option<BatchItemResult>> = Vec::with_capacity(forms.len());
+    let mut accepted: Vec<(usize, models::NewArticle)> = Vec::new();
+    for (index, form) in forms.iter().enumerate() {
+        if form.title.trim().is_empty() {
+            results.push(Some(BatchItemResult::failed(index, "Title is required".to_string())));
+            continue;
+        }
+        let Ok(canonical_url) = form.canonical_url() else {
+            results.push(Some(BatchItemResult::failed(index, "Invalid canonical URL".to_string())));
+            continue;
+        };
+        // 与已有文章或同一批中前面的文章重复时跳过，除非明确要求
+        if !form.allow_duplicate.unwrap_or(false) {
+            if let Some((existing_id, existing_title)) =
+                services::duplicates::find_duplicate(&form.title, existing.iter().map(|(id, title)| (*id, title.as_str())))
+            {
+                results.push(Some(BatchItemResult {
+                    existing_id: Some(existing_id),
+                    ..BatchItemResult::failed(
+                        index,
+                        format!("An article with a similar title already exists: \"{}\"; set allow_duplicate to create it anyway", existing_title),
+                    )
+                }));
+                continue;
+            }
+            let earlier = accepted.iter().map(|(i, article)| (*i as i64, article.title));
+            if let Some((earlier_index, _)) = services::duplicates::find_duplicate(&form.title, earlier) {
+                results.push(Some(BatchItemResult::failed(
+                    index,
+                    format!("Duplicates article {} of this batch; set allow_duplicate to create it anyway", earlier_index),
+                )));
+                continue;
+            }
+        }
+        let category_id = match form.category.as_deref().map(str::trim).filter(|slug| !slug.is_empty()) {
+            None => None,
+            Some(slug) => match models::get_category_by_slug(_pool.get_ref(), slug).await {
+                Ok(Some(category)) => Some(category.id),
+                Ok(None) => {
+                    results.push(Some(BatchItemResult::failed(index, format!("Unknown category: {}", slug))));
+                    continue;
+                }
+                Err(e) => {
+                    error!("Failed to fetch category: {}", e);
+                    return HttpResponse::InternalServerError().finish();
+                }
+            },
+        };
+        accepted.push((index, models::NewArticle {
+            title: &form.title,
+            content: &form.content,
+            meta_description: form.meta_description(),
+            meta_keywords: form.meta_keywords(),
+            noindex: form.noindex.unwrap_or(false),
+            text_direction: form.text_direction().unwrap_or("auto"),
+            canonical_url,
+            category_id,
+            status: form.status(),
+            tags: form.tags().unwrap_or_default(),
+            links: services::link_graph::internal_links(&form.content, &data.site_url),
+        }));
+        // 创建结果在事务提交后填入
+        results.push(None);
+    }
+
+    let articles: Vec<models::NewArticle> = accepted.into_iter().map(|(_, article)| article).collect();
+    let created = if articles.is_empty() {
+        Vec::new()
+    } else {
+        match models::create_articles(_pool.get_ref(), &articles, Some(&username)).await {
+            Ok(created) => created,
+            Err(e) => {
+                error!("Failed to create articles: {}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        }
+    };
+    let mut created = articles.iter().zip(created);
+    let mut items = Vec::with_capacity(results.len());
+    for (index, result) in results.into_iter().enumerate() {
+        if let Some(result) = result {
+            items.push(result);
+            continue;
+        }
+        let Some((article, (article_id, slug))) = created.next() else {
+            break;
+        };
+        let action = if article.status == models::STATUS_DRAFT { "save_draft" } else { "publish_article" };
+        record_activity(&_pool, &username, action, &services::activity::article_target(article_id), article.title).await;
+        data.events.publish(Event::ArticleCreated { article_id, published: article.status != models::STATUS_DRAFT });
+        items.push(BatchItemResult {
+            index,
+            created: true,
+            id: Some(article_id),
+            url: Some(services::slugs::post_path(article_id, Some(&slug))),
+            error: None,
+            existing_id: None,
+        });
+    }
+    let created = items.iter().filter(|item| item.created).count();
+    HttpResponse::Ok().json(serde_json::json!({
+        "created": created,
+        "failed": items.len() - created,
+        "results": items,
+    }))
+}
+
+// 公开的运行状态，供状态组件和外部监控使用；数据库不可用时返回 503
+async fn api_status(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let now = chrono::Utc::now();
+    let started = std::time::Instant::now();
+    let articles = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM articles WHERE deleted_at IS NULL AND status = 'published'")
+        .fetch_one(_pool.get_ref())
+        .await;
+    let db_latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+    let database = match &articles {
+        Ok(_) => serde_json::json!({ "ok": true, "latency_ms": (db_latency_ms * 100.0).round() / 100.0 }),
+        Err(e) => {
+            error!("Status check failed to reach the database: {}", e);
+            serde_json::json!({ "ok": false, "latency_ms": null })
+        }
+    };
+
+    let metrics = data.markdown_service.get_metrics();
+    let lookups = metrics.cache_hits + metrics.cache_misses;
+    let body = serde_json::json!({
+        "status": if articles.is_ok() { "ok" } else { "degraded" },
+        "version": env!("CARGO_PKG_VERSION"),
+        "started_at": data.started_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        "uptime_seconds": (now - data.started_at).num_seconds().max(0),
+        "articles": articles.as_ref().ok(),
+        "cache": {
+            "markdown": {
+                "entries": metrics.cache_size,
+                "hits": metrics.cache_hits,
+                "misses": metrics.cache_misses,
+                "hit_rate": if lookups > 0 { metrics.cache_hits as f64 / lookups as f64 } else { 0.0 },
+            },
+            "api_entries": data.api_cache.len(),
+            "sitemap_entries": data.sitemap_cache.len(),
+        },
+        "database": database,
+        "plugins": data.plugins.names(),
+    });
+    let mut response = if articles.is_ok() { HttpResponse::Ok() } else { HttpResponse::ServiceUnavailable() };
+    response.insert_header(("Cache-Control", "no-store")).json(body)
+}
@@ -0,0 +1,187 @@
+//! Signing in and out, and resetting a forgotten password with the
+//! account's security question.
+
+use actix_web::{web, HttpRequest, Responder, HttpResponse};
+use actix_session::Session;
+use log::error;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use crate::models;
+use crate::models::verify_user;
+use tera::Context;
+use crate::services::events::Event;
+use crate::app::{AppState, LastLogin, record_activity};
+use crate::middleware;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/login", web::get().to(login_page))
+        .route("/login", web::post().to(login))
+        .route("/logout", web::post().to(logout))
+        .route("/reset-password", web::get().to(reset_password_page))
+        .route("/reset-password", web::post().to(reset_password))
+        .route("/api/security-question", web::get().to(get_security_question));
+}
+
+#[derive(Deserialize)]
+struct LoginForm {
+    username: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+struct ResetPasswordForm {
+    username: String,
+    security_answer: String,
+    new_password: String,
+    confirm_password: String,
+}
+
+async fn login_page(data: web::Data<AppState>) -> impl Responder {
+    match data.template.render("login.html", &Context::new()) {
+        Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+        Err(e) => {
+            error!("Template rendering error: {}", e);
+            HttpResponse::InternalServerError().body("Template rendering error")
+        }
+    }
+}
+
+async fn login(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    form: web::Form<LoginForm>,
+    _pool: web::Data<SqlitePool>,
+    session: Session,
+) -> impl Responder {
+    match verify_user(&_pool, &form.username, &form.password).await {
+        Ok(user) => {
+            // 登录成功，设置session
+            if let Err(e) = session.insert("username", &form.username) {
+                error!("Failed to set session: {}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+
+            // 记录上次登录信息，供仪表盘显示
+            if let (Some(at), Some(ip)) = (user.last_login_at.clone(), user.last_login_ip.clone())
+                && let Err(e) = session.insert("last_login", LastLogin { at, ip })
+            {
+                error!("Failed to store last login in session: {}", e);
+            }
+
+            let ip = data.trusted_proxies.client_ip(&req);
+            match models::record_login(_pool.get_ref(), user.id, &ip).await {
+                Ok(new_ip) => data.events.publish(Event::UserLoggedIn { username: user.username.clone(), ip, new_ip }),
+                Err(e) => error!("Failed to record login for {}: {}", user.username, e),
+            }
+
+            // 仍在使用初始密码时先跳转到密码设置页
+            if user.must_change_password {
+                if let Err(e) = session.insert(middleware::MUST_CHANGE_PASSWORD, true) {
+                    error!("Failed to set session: {}", e);
+                    return HttpResponse::InternalServerError().finish();
+                }
+                return HttpResponse::Found().append_header(("Location", "/admin/password")).finish();
+            }
+
+            HttpResponse::Found().append_header(("Location", "/admin")).finish()
+        },
+        Err(_) => {
+            let ip = data.trusted_proxies.client_ip(&req);
+            let username: String = form.username.chars().take(64).collect();
+            record_activity(&_pool, &username, "login_failed", &ip, "").await;
+            HttpResponse::Unauthorized().body("Invalid credentials")
+        }
+    }
+}
+
+async fn logout(session: Session) -> impl Responder {
+    // 清除session
+    session.clear();
+    HttpResponse::Found()
+        .append_header(("Location", "/login"))
+        .finish()
+}
+
+// 重置密码页面
+async fn reset_password_page(data: web::Data<AppState>) -> impl Responder {
+    match data.template.render("reset_password.html", &Context::new()) {
+        Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+        Err(e) => {
+            error!("Template rendering error: {}", e);
+            HttpResponse::InternalServerError().body("Template rendering error")
+        }
+    }
+}
+
+// 处理重置密码
+async fn reset_password(
+    form: web::Form<ResetPasswordForm>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    if form.new_password != form.confirm_password {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "新密码和确认密码不匹配"
+        }));
+    }
+    
+    // 验证安全问题答案
+    match models::verify_security_answer(_pool.get_ref(), &form.username, &form.security_answer).await {
+        Ok(_) => {
+            // 重置密码
+            match models::reset_password_by_username(_pool.get_ref(), &form.username, &form.new_password).await {
+                Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+                    "success": true,
+                    "message": "密码重置成功，请使用新密码登录"
+                })),
+                Err(e) => {
+                    error!("Failed to reset password: {}", e);
+                    HttpResponse::InternalServerError().json(serde_json::json!({
+                        "success": false,
+                        "message": "密码重置失败"
+                    }))
+                }
+            }
+        },
+        Err(_) => HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "用户名或安全问题答案错误"
+        }))
+    }
+}
+
+// 获取用户安全问题
+async fn get_security_question(
+    query: web::Query<std::collections::HashMap<String, String>>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    if let Some(username) = query.get("username") {
+        match sqlx::query_as::<_, models::User>("SELECT * FROM users WHERE username = ?")
+            .bind(username)
+            .fetch_one(_pool.get_ref())
+            .await {
+            Ok(user) => {
+                if let Some(question) = user.security_question {
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "success": true,
+                        "question": question
+                    }))
+                } else {
+                    HttpResponse::BadRequest().json(serde_json::json!({
+                        "success": false,
+                        "message": "该用户未设置安全问题"
+                    }))
+                }
+            },
+            Err(_) => HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": "用户不存在"
+            }))
+        }
+    } else {
+        HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "缺少用户名参数"
+        }))
+    }
+}
@@ -0,0 +1,17 @@
+//! HTTP handlers grouped by audience. Each module registers its routes with
+//! `configure`; the top-level `configure` applies them all in order.
+
+use actix_web::web;
+
+pub mod admin;
+pub mod api;
+pub mod auth;
+pub mod public;
+
+/// Every route of the server except those added by plugins
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.configure(public::configure)
+        .configure(auth::configure)
+        .configure(api::configure)
+        .configure(admin::configure);
+}
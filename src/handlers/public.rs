@@ -0,0 +1,1459 @@
+//! Pages and files served to readers: the home page, posts and comments,
+//! author pages, feeds, sitemaps, uploaded media and site assets.
+
+use actix_web::{web, HttpRequest, Responder, HttpResponse};
+use actix_session::Session;
+use log::{info, error};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use crate::{models, services};
+use crate::services::events::Event;
+use crate::services::feed::FeedContent;
+use crate::services::media::BrandAsset;
+use crate::services::pagination::Pagination;
+use crate::services::markdown::RenderFeatures;
+use crate::services::render_options::RenderOptions;
+use crate::services::text::TextDirection;
+use crate::services::summary::{strip_html_tags, summarize, truncate, SummaryUnit};
+use tera::Context;
+use crate::app::{
+    AppState, AttachmentView, Origin, Post, REACTION_SESSION_KEY, notify_comment_reply, record_activity,
+    render_article_with_fallback, render_markdown_with_fallback, timed_query,
+};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/", web::get().to(index))
+        .route("/post/{id}", web::get().to(post_detail))
+        .route("/post/{id}/lite", web::get().to(post_lite))
+        .route("/post/{id}/react", web::post().to(react_to_post))
+        .route("/post/{id}/comments", web::post().to(submit_comment))
+        .route("/comments/{id}/moderate/{action}", web::get().to(moderate_comment_link))
+        .route("/comments/{id}/unsubscribe", web::get().to(unsubscribe_comment_link))
+        .route("/avatar/{seed}.svg", web::get().to(avatar_image))
+        .route("/about", web::get().to(about))
+        .route("/author/{username}", web::get().to(author_page))
+        .route("/tag/{name}", web::get().to(tag_page))
+        .route("/category/{slug}", web::get().to(category_page))
+        .route("/search", web::get().to(search_page))
+        .route("/sitemap.xml", web::get().to(sitemap))
+        .route("/sitemap-{part}.xml", web::get().to(sitemap_part))
+        .route("/robots.txt", web::get().to(robots_txt))
+        .route("/favicon.ico", web::get().to(favicon))
+        .route("/highlight/{theme}.css", web::get().to(highlight_theme_css))
+        .route("/logo", web::get().to(logo))
+        .route("/.well-known/security.txt", web::get().to(security_txt))
+        .route("/feed.xml", web::get().to(feed_rss))
+        .route("/atom.xml", web::get().to(feed_atom))
+        .route("/oembed", web::get().to(oembed))
+        .route("/s/{code}", web::get().to(short_link_redirect))
+        .route("/out", web::get().to(outbound_redirect))
+        .route("/prefs/theme", web::post().to(set_theme_preference))
+        .route("/media/{name}", web::get().to(serve_media));
+}
+
+#[derive(Deserialize)]
+struct ReactionForm {
+    emoji: String,
+}
+
+#[derive(Deserialize)]
+struct CommentForm {
+    author_name: String,
+    author_email: Option<String>,
+    content: String,
+    // 表单中的空字符串表示顶层评论
+    parent_id: Option<String>,
+    // 复选框，勾选时为 "on"
+    notify_replies: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OEmbedQuery {
+    url: String,
+    format: Option<String>,
+    maxwidth: Option<u32>,
+    maxheight: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct SignedLinkQuery {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct ThemeForm {
+    theme: String,
+    // 切换后返回的页面
+    next: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PageQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+// 首页每页的文章数，可用 ?per_page= 调整
+const INDEX_DEFAULT_PER_PAGE: i64 = 12;
+const INDEX_MAX_PER_PAGE: i64 = 50;
+
+async fn index(
+    data: web::Data<AppState>,
+    query: web::Query<PageQuery>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    let origin = Origin::route("/");
+    let total = match timed_query(&data, origin, models::count_published_articles(_pool.get_ref())).await {
+        Ok(total) => total,
+        Err(e) => {
+            error!("Failed to count articles: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let pagination = Pagination::new("/", query.page, query.per_page, INDEX_DEFAULT_PER_PAGE, INDEX_MAX_PER_PAGE, total);
+    if pagination.is_out_of_range() {
+        return HttpResponse::NotFound().finish();
+    }
+
+    let query = models::get_published_articles(_pool.get_ref(), pagination.per_page, pagination.offset());
+    match timed_query(&data, origin, query).await {
+        Ok(articles) => {
+            let mut ctx = Context::new();
+            ctx.insert("posts", &summarize_posts(&data, "/", articles).await);
+            ctx.insert("pagination", &pagination);
+            match data.template.render("index.html", &ctx) {
+                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+                Err(e) => {
+                    error!("Template rendering error: {}", e);
+                    HttpResponse::InternalServerError().body("Template rendering error")
+                }
+            }
+        },
+        Err(e) => {
+            error!("Failed to fetch articles: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+// 首页、作者页等列表中的文章卡片
+async fn summarize_posts(data: &web::Data<AppState>, route: &'static str, articles: Vec<models::Article>) -> Vec<Post> {
+    let summary_options = data.settings().summary;
+    let mut posts: Vec<Post> = Vec::with_capacity(articles.len());
+    for article in articles {
+        // Render markdown content to HTML with fallback
+        let rendered_content = render_markdown_with_fallback(data, Origin::article(route, article.id), article.content).await;
+        
+        // Create summary as configured in site settings
+        let summary = summarize(&summary_options, article.meta_description.as_deref(), &rendered_content);
+        let dir = article_direction(&article.text_direction, &article.title, &strip_html_tags(&rendered_content));
+        
+        posts.push(Post {
+            id: article.id as u32,
+            url: services::slugs::post_path(article.id, article.slug.as_deref()),
+            title: article.title,
+            summary,
+            content: rendered_content,
+            date: article.created_at,
+            dir,
+        });
+    }
+    posts
+}
+
+// 作者页：资料和该作者的文章
+async fn author_page(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let origin = Origin::route("/author/{username}");
+    let user = match timed_query(&data, origin, models::get_user_by_username(_pool.get_ref(), &path.into_inner())).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!("Failed to fetch author: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let author = author_profile(&data, origin, &user, true).await;
+
+    let query = sqlx::query_as::<_, models::Article>(
+        "SELECT * FROM articles WHERE author_id = ? AND deleted_at IS NULL AND status = 'published' ORDER BY created_at DESC"
+    )
+    .bind(user.id)
+    .fetch_all(_pool.get_ref());
+    let articles = match timed_query(&data, origin, query).await {
+        Ok(articles) => articles,
+        Err(e) => {
+            error!("Failed to fetch author articles: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let mut ctx = Context::new();
+    ctx.insert("author", &author);
+    ctx.insert("posts", &summarize_posts(&data, "/author/{username}", articles).await);
+    ctx.insert("canonical", &format!("{}{}", data.site_url, author.url));
+    match data.template.render("author.html", &ctx) {
+        Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+        Err(e) => {
+            error!("Template rendering error: {}", e);
+            HttpResponse::InternalServerError().body("Template rendering error")
+        }
+    }
+}
+
+// 标签页：带有该标签的文章
+async fn tag_page(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let origin = Origin::route("/tag/{name}");
+    let name = path.trim();
+    let tag = match timed_query(&data, origin, models::get_tag(_pool.get_ref(), name)).await {
+        Ok(Some(tag)) => tag,
+        // 合并掉的标签永久跳转到合并进的标签
+        Ok(None) => match timed_query(&data, origin, models::get_tag_redirect(_pool.get_ref(), name)).await {
+            Ok(Some(tag)) => {
+                return HttpResponse::MovedPermanently()
+                    .append_header(("Location", services::tags::tag_url(&tag.name)))
+                    .finish();
+            }
+            Ok(None) => return HttpResponse::NotFound().finish(),
+            Err(e) => {
+                error!("Failed to fetch tag redirect: {}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        },
+        Err(e) => {
+            error!("Failed to fetch tag: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let articles = match timed_query(&data, origin, models::get_tagged_articles(_pool.get_ref(), tag.id)).await {
+        Ok(articles) => articles,
+        Err(e) => {
+            error!("Failed to fetch tagged articles: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let mut ctx = Context::new();
+    let tag = services::tags::TagLink::new(tag.name);
+    ctx.insert("canonical", &format!("{}{}", data.site_url, tag.url));
+    ctx.insert("tag", &tag);
+    ctx.insert("posts", &summarize_posts(&data, "/tag/{name}", articles).await);
+    match data.template.render("tag.html", &ctx) {
+        Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+        Err(e) => {
+            error!("Template rendering error: {}", e);
+            HttpResponse::InternalServerError().body("Template rendering error")
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: Option<String>,
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+// 搜索页：按相关度排序的公开文章，标题和正文片段中标出匹配的词
+async fn search_page(
+    data: web::Data<AppState>,
+    query: web::Query<SearchQuery>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    if !data.settings().features.search {
+        return HttpResponse::NotFound().finish();
+    }
+    let input: String = query.q.as_deref().unwrap_or("").trim().chars().take(services::search::MAX_QUERY_LENGTH).collect();
+    let ranking = data.settings().search;
+    let search = services::search::search(_pool.get_ref(), &input, &ranking, query.page, query.per_page);
+    let (pagination, results) = match timed_query(&data, Origin::route("/search"), search).await {
+        Ok(page) => page,
+        Err(e) => {
+            error!("Failed to search articles: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    if pagination.is_out_of_range() {
+        return HttpResponse::NotFound().finish();
+    }
+
+    let mut ctx = Context::new();
+    ctx.insert("query", &input);
+    ctx.insert("results", &results);
+    ctx.insert("pagination", &pagination);
+    match data.template.render("search.html", &ctx) {
+        Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+        Err(e) => {
+            error!("Template rendering error: {}", e);
+            HttpResponse::InternalServerError().body("Template rendering error")
+        }
+    }
+}
+
+// 分类页：该分类及其所有子分类中的文章
+async fn category_page(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let origin = Origin::route("/category/{slug}");
+    let categories = match timed_query(&data, origin, models::get_categories(_pool.get_ref())).await {
+        Ok(categories) => categories,
+        Err(e) => {
+            error!("Failed to fetch categories: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let slug = path.into_inner();
+    let Some(category) = categories.iter().find(|c| c.slug == slug) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let ids = services::categories::descendants(&categories, category.id);
+    let articles = match timed_query(&data, origin, models::get_category_articles(_pool.get_ref(), &ids)).await {
+        Ok(articles) => articles,
+        Err(e) => {
+            error!("Failed to fetch category articles: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let children: Vec<services::categories::CategoryEntry> = services::categories::tree(&categories)
+        .into_iter()
+        .filter(|c| c.parent_id == Some(category.id))
+        .collect();
+
+    let url = services::categories::category_url(&category.slug);
+    let mut ctx = Context::new();
+    ctx.insert("category", &serde_json::json!({ "name": category.name, "url": url }));
+    ctx.insert("ancestors", &services::categories::ancestors(&categories, category.id));
+    ctx.insert("children", &children);
+    ctx.insert("canonical", &format!("{}{}", data.site_url, url));
+    ctx.insert("posts", &summarize_posts(&data, "/category/{slug}", articles).await);
+    match data.template.render("category.html", &ctx) {
+        Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+        Err(e) => {
+            error!("Template rendering error: {}", e);
+            HttpResponse::InternalServerError().body("Template rendering error")
+        }
+    }
+}
+
+// 作者资料，需要时渲染 Markdown 简介
+async fn author_profile(data: &web::Data<AppState>, origin: Origin, user: &models::User, with_bio: bool) -> services::author::AuthorProfile {
+    let mut author = services::author::AuthorProfile::new(user);
+    if let Some(bio) = user.bio.as_deref().filter(|bio| with_bio && !bio.trim().is_empty()) {
+        author.bio_html = Some(render_markdown_with_fallback(data, origin, bio.to_string()).await);
+    }
+    author
+}
+
+// 文章的 dir 属性：auto 时根据标题和正文文字判断
+fn article_direction(setting: &str, title: &str, plain_text: &str) -> &'static str {
+    TextDirection::parse(setting)
+        .unwrap_or_default()
+        .resolve(&format!("{} {}", title, plain_text))
+        .as_str()
+}
+
+// 页面描述（meta description / og:description）的最大字符数
+const SEO_DESCRIPTION_LENGTH: usize = 160;
+
+// 站内相对地址补全为绝对地址，OpenGraph 要求图片使用绝对 URL
+fn absolute_url(site_url: &str, url: &str) -> String {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        url.to_string()
+    } else if let Some(rest) = url.strip_prefix("//") {
+        format!("https://{}", rest)
+    } else {
+        format!("{}/{}", site_url, url.trim_start_matches('/'))
+    }
+}
+
+// 文章页地址中的 slug 或 id 对应的文章
+enum PostKey {
+    Article { id: i64, by_id: bool },
+    // 旧 slug，跳转到文章当前的地址 path
+    Moved { id: i64, path: String },
+}
+
+async fn resolve_post_key(data: &AppState, pool: &SqlitePool, key: &str) -> Result<Option<PostKey>, sqlx::Error> {
+    let origin = Origin::route("/post/{id}");
+    if let Ok(id) = key.parse::<i64>() {
+        return Ok(Some(PostKey::Article { id, by_id: true }));
+    }
+    if let Some(id) = timed_query(data, origin, models::get_article_id_by_slug(pool, key)).await? {
+        return Ok(Some(PostKey::Article { id, by_id: false }));
+    }
+    let redirect = timed_query(data, origin, models::get_slug_redirect(pool, key)).await?;
+    Ok(redirect.map(|(id, slug)| PostKey::Moved { id, path: services::slugs::post_path(id, slug.as_deref()) }))
+}
+
+// 永久跳转到文章的规范地址，保留查询参数
+fn redirect_to_post(req: &HttpRequest, path: String) -> HttpResponse {
+    let location = match req.query_string() {
+        "" => path,
+        query => format!("{}?{}", path, query),
+    };
+    HttpResponse::MovedPermanently().append_header(("Location", location)).finish()
+}
+
+// 文章页：/post/{slug}；/post/{id} 和标题修改前的旧 slug 跳转到这里
+async fn post_detail(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    _pool: web::Data<SqlitePool>,
+    session: Session,
+) -> impl Responder {
+    let (post_id, by_id) = match resolve_post_key(&data, &_pool, &path).await {
+        Ok(Some(PostKey::Article { id, by_id })) => (id, by_id),
+        Ok(Some(PostKey::Moved { path, .. })) => return redirect_to_post(&req, path),
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!("Failed to resolve post {}: {}", path, e);
+            return HttpResponse::NotFound().finish();
+        }
+    };
+    let mut ctx = Context::new();
+    
+    let origin = Origin::article("/post/{id}", post_id);
+    let signed_in = session.get::<String>("username").unwrap_or(None).is_some();
+    match timed_query(&data, origin, models::get_article(_pool.get_ref(), post_id)).await {
+        // 草稿只有登录后才能预览
+        Ok(Some(article)) if article.status == models::STATUS_DRAFT && !signed_in => HttpResponse::NotFound().finish(),
+        Ok(Some(models::Article { id, slug: Some(slug), .. })) if by_id => {
+            redirect_to_post(&req, services::slugs::post_path(id, Some(&slug)))
+        }
+        Ok(Some(article)) => {
+            let draft = article.status == models::STATUS_DRAFT;
+            let post_path = services::slugs::post_path(article.id, article.slug.as_deref());
+            let models::Article {
+                id, title, content, created_at, meta_description, meta_keywords, noindex, text_direction, render_options, author_id,
+                canonical_url, category_id, ..
+            } = article;
+            record_referral(&req, &data, &_pool, id).await;
+
+            // 署名：作者名、头像和作者页链接
+            if let Some(author_id) = author_id {
+                match timed_query(&data, origin, models::get_user(_pool.get_ref(), author_id)).await {
+                    Ok(Some(user)) => ctx.insert("author", &author_profile(&data, origin, &user, false).await),
+                    Ok(None) => {}
+                    Err(e) => error!("Failed to fetch author: {}", e),
+                }
+            }
+
+            // 作者未填写描述和图片时，取首段和首张图片
+            let lead = data.markdown_service.first_paragraph(&content);
+            let hero_image = data.markdown_service.first_image(&content);
+
+            // 前言中的渲染选项：数学公式、目录和代码高亮主题
+            let render_options = RenderOptions::from_json(render_options.as_deref());
+            let headings = data.markdown_service.collect_headings(&content);
+            let features = render_options.features(headings.len());
+            ctx.insert("render", &serde_json::json!({
+                "math": features.math,
+                "toc": if features.heading_anchors { services::toc::links(&headings) } else { Vec::new() },
+                "highlight_theme": render_options.highlight_theme.filter(|theme| data.markdown_service.has_theme(theme)),
+            }));
+
+            // Render markdown content to HTML with fallback
+            let mut rendered_content = render_article_with_fallback(&data, origin, content, features).await;
+            // 外链改为经 /out 跳转以统计点击；缓存的渲染结果不含此改写
+            if data.settings().features.outbound_tracking {
+                let site_host = services::analytics::host_of(&data.site_url).unwrap_or_default();
+                rendered_content = services::outbound::rewrite_links(&rendered_content, &site_host, &data.signer, id);
+            }
+            
+            // Create summary from plain text
+            let plain_text = strip_html_tags(&rendered_content);
+            let summary = truncate(&plain_text, 100, SummaryUnit::Characters);
+            let dir = article_direction(&text_direction, &title, &plain_text);
+            
+            let post = Post {
+                id: id as u32,
+                url: post_path.clone(),
+                title,
+                summary,
+                content: rendered_content,
+                date: created_at,
+                dir,
+            };
+            // 未填写描述时依次使用首段、摘要
+            let description = meta_description
+                .filter(|d| !d.is_empty())
+                .or_else(|| lead.map(|lead| truncate(&lead, SEO_DESCRIPTION_LENGTH, SummaryUnit::Characters)))
+                .unwrap_or_else(|| post.summary.clone());
+            ctx.insert("seo", &serde_json::json!({
+                "description": description,
+                "image": hero_image.map(|url| absolute_url(&data.site_url, &url)),
+                "keywords": meta_keywords.filter(|k| !k.is_empty()),
+                "noindex": noindex || draft,
+                // 转载文章指向原文
+                "canonical": canonical_url.unwrap_or_else(|| format!("{}{}", data.site_url, post_path)),
+            }));
+            ctx.insert("post", &post);
+            ctx.insert("draft", &draft);
+
+            // 登录后可直接在文章页勾选任务列表
+            ctx.insert("can_edit_tasks", &signed_in);
+
+            let session_key = session.get::<String>(REACTION_SESSION_KEY).unwrap_or(None);
+            let reactions = models::get_reaction_counts(_pool.get_ref(), id, &data.reaction_emojis, session_key.as_deref());
+            match timed_query(&data, origin, reactions).await {
+                Ok(reactions) => ctx.insert("reactions", &reactions),
+                Err(e) => {
+                    error!("Failed to fetch reactions: {}", e);
+                    ctx.insert("reactions", &Vec::<models::ReactionCount>::new());
+                }
+            }
+
+            // 关闭评论功能时不读取评论，模板也不显示评论区
+            if data.settings().features.comments {
+                match timed_query(&data, origin, models::get_approved_comments(_pool.get_ref(), id)).await {
+                    Ok(comments) => {
+                        ctx.insert("comment_count", &comments.len());
+                        let mut threads = services::comment::build_threads(comments, data.comment_max_depth);
+                        for thread in &mut threads {
+                            thread.avatar_url = comment_avatar_url(&data, &thread.comment);
+                        }
+                        ctx.insert("comments", &threads);
+                    },
+                    Err(e) => {
+                        error!("Failed to fetch comments: {}", e);
+                        ctx.insert("comment_count", &0);
+                        ctx.insert("comments", &Vec::<services::comment::ThreadedComment>::new());
+                    }
+                }
+            }
+            // 分类路径：从顶级分类到文章所属分类
+            if let Some(category_id) = category_id {
+                match timed_query(&data, origin, models::get_categories(_pool.get_ref())).await {
+                    Ok(categories) => {
+                        let mut path = services::categories::ancestors(&categories, category_id);
+                        let entry = services::categories::tree(&categories).into_iter().find(|c| c.id == category_id);
+                        if let Some(entry) = entry {
+                            path.push(entry);
+                            ctx.insert("category_path", &path);
+                        }
+                    }
+                    Err(e) => error!("Failed to fetch categories: {}", e),
+                }
+            }
+            match timed_query(&data, origin, models::get_article_tags(_pool.get_ref(), id)).await {
+                Ok(tags) => {
+                    let tags: Vec<services::tags::TagLink> = tags.into_iter().map(services::tags::TagLink::new).collect();
+                    ctx.insert("tags", &tags);
+                }
+                Err(e) => {
+                    error!("Failed to fetch tags: {}", e);
+                    ctx.insert("tags", &Vec::<services::tags::TagLink>::new());
+                }
+            }
+            match timed_query(&data, origin, models::get_article_attachments(_pool.get_ref(), id)).await {
+                Ok(attachments) => {
+                    let attachments: Vec<AttachmentView> = attachments.into_iter().map(AttachmentView::from).collect();
+                    ctx.insert("attachments", &attachments);
+                }
+                Err(e) => {
+                    error!("Failed to fetch attachments: {}", e);
+                    ctx.insert("attachments", &Vec::<AttachmentView>::new());
+                }
+            }
+            // 引用了本文的其他文章，来自保存文章时记录的链接
+            match timed_query(&data, origin, models::get_backlinks(_pool.get_ref(), id)).await {
+                Ok(backlinks) => ctx.insert("backlinks", &backlinks),
+                Err(e) => {
+                    error!("Failed to fetch backlinks: {}", e);
+                    ctx.insert("backlinks", &Vec::<models::Backlink>::new());
+                }
+            }
+            ctx.insert("comment_max_depth", &data.comment_max_depth);
+            if let Some(Ok(notice)) = session.remove_as::<String>(COMMENT_NOTICE_KEY) {
+                ctx.insert("comment_notice", &notice);
+            }
+            match data.template.render("post.html", &ctx) {
+                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+                Err(e) => {
+                    error!("Template rendering error: {}", e);
+                    HttpResponse::InternalServerError().body("Template rendering error")
+                }
+            }
+        },
+        Ok(None) => article_gone_or_missing(&data, &_pool, post_id).await,
+        Err(e) => {
+            error!("Failed to fetch article: {}", e);
+            HttpResponse::NotFound().finish()
+        }
+    }
+}
+
+// 轻量版文章页：极简 HTML，无代码高亮、图片和嵌入内容，供慢速网络和文本浏览器使用
+async fn post_lite(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    _pool: web::Data<SqlitePool>,
+    session: Session,
+) -> impl Responder {
+    let post_id = path.into_inner();
+    let origin = Origin::article("/post/{id}/lite", post_id);
+    let signed_in = session.get::<String>("username").unwrap_or(None).is_some();
+    match timed_query(&data, origin, models::get_article(_pool.get_ref(), post_id)).await {
+        Ok(Some(article)) if article.status == models::STATUS_DRAFT && !signed_in => HttpResponse::NotFound().finish(),
+        Ok(Some(article)) => {
+            let features = RenderFeatures { lite: true, ..RenderFeatures::default() };
+            let content = render_article_with_fallback(&data, origin, article.content, features).await;
+            let dir = article_direction(&article.text_direction, &article.title, &strip_html_tags(&content));
+            let mut ctx = Context::new();
+            let post_path = services::slugs::post_path(article.id, article.slug.as_deref());
+            ctx.insert("post", &serde_json::json!({
+                "id": article.id,
+                "url": post_path,
+                "title": article.title,
+                "date": article.created_at,
+                "content": content,
+                "dir": dir,
+            }));
+            let canonical = article.canonical_url.unwrap_or_else(|| format!("{}{}", data.site_url, post_path));
+            ctx.insert("canonical", &canonical);
+            ctx.insert("noindex", &article.noindex);
+            match data.template.render("post_lite.html", &ctx) {
+                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+                Err(e) => {
+                    error!("Template rendering error: {}", e);
+                    HttpResponse::InternalServerError().body("Template rendering error")
+                }
+            }
+        }
+        Ok(None) => article_gone_or_missing(&data, &_pool, post_id).await,
+        Err(e) => {
+            error!("Failed to fetch article: {}", e);
+            HttpResponse::NotFound().finish()
+        }
+    }
+}
+
+// 已删除的文章返回 410，让搜索引擎和订阅阅读器移除它；从未存在的返回 404
+async fn article_gone_or_missing(data: &AppState, pool: &SqlitePool, article_id: i64) -> HttpResponse {
+    let tombstone = match models::get_tombstone(pool, article_id).await {
+        Ok(Some(tombstone)) => tombstone,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!("Failed to fetch tombstone: {}", e);
+            return HttpResponse::NotFound().finish();
+        }
+    };
+    let mut ctx = Context::new();
+    ctx.insert("tombstone", &tombstone);
+    match data.template.render("gone.html", &ctx) {
+        Ok(html) => HttpResponse::Gone().content_type("text/html").body(html),
+        Err(e) => {
+            error!("Template rendering error: {}", e);
+            HttpResponse::Gone().finish()
+        }
+    }
+}
+
+// 记录来自外部站点的文章访问，用于来源统计；站内跳转、直接访问和爬虫不记录
+async fn record_referral(req: &HttpRequest, data: &AppState, pool: &SqlitePool, article_id: i64) {
+    use services::analytics::{host_of, is_bot, parse_referrer};
+
+    let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok()).unwrap_or("");
+    if is_bot(header("User-Agent")) {
+        return;
+    }
+    let site_host = host_of(&data.site_url).unwrap_or_default();
+    let Some(referral) = parse_referrer(header("Referer"), &site_host) else {
+        return;
+    };
+    if let Err(e) = models::record_page_view(pool, article_id, &referral).await {
+        error!("Failed to record page view: {}", e);
+    }
+}
+
+fn xml_response(xml: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/xml; charset=utf-8")
+        .body(xml)
+}
+
+// sitemap 索引：页面 sitemap 加上按文章创建年份拆分的子 sitemap
+async fn sitemap(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    use services::sitemap::{build_sitemap_index, SitemapEntry, SitemapPart, MAX_URLS_PER_SITEMAP};
+
+    let cache = &data.sitemap_cache;
+    let key = "/sitemap.xml";
+    if let Some(xml) = cache.get(key) {
+        return xml_response(xml);
+    }
+    let generation = cache.generation();
+
+    match sqlx::query_as::<_, (Option<String>, i64, String)>(
+        "SELECT strftime('%Y', created_at) AS year, COUNT(*), MAX(updated_at) FROM articles \
+         WHERE noindex = 0 AND deleted_at IS NULL AND status = 'published' GROUP BY year ORDER BY year DESC"
+    )
+    .fetch_all(_pool.get_ref())
+    .await {
+        Ok(years) => {
+            let mut sitemaps = vec![SitemapEntry {
+                path: SitemapPart::Pages.path(),
+                lastmod: years.iter().map(|(_, _, updated)| updated).max().cloned(),
+            }];
+            for (year, count, updated) in years {
+                let Some(year) = year.and_then(|y| y.parse::<i32>().ok()) else {
+                    continue;
+                };
+                sitemaps.extend(SitemapPart::for_year(year, count as usize, MAX_URLS_PER_SITEMAP).into_iter().map(|part| SitemapEntry {
+                    path: part.path(),
+                    lastmod: Some(updated.clone()),
+                }));
+            }
+            let xml = build_sitemap_index(&data.site_url, &sitemaps);
+            cache.insert(key.to_string(), generation, xml.clone());
+            xml_response(xml)
+        },
+        Err(e) => {
+            error!("Failed to build sitemap index: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+// 子 sitemap：/sitemap-pages.xml、/sitemap-2024.xml，单年超过上限时为 /sitemap-2024-2.xml 等
+async fn sitemap_part(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    use services::sitemap::{build_sitemap, SitemapEntry, SitemapPart, MAX_URLS_PER_SITEMAP};
+
+    let Some(part) = SitemapPart::parse(&path.into_inner()) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let cache = &data.sitemap_cache;
+    let key = part.path();
+    if let Some(xml) = cache.get(&key) {
+        return xml_response(xml);
+    }
+    let generation = cache.generation();
+
+    let entries = match part {
+        SitemapPart::Pages => sqlx::query_scalar::<_, Option<String>>(
+            "SELECT MAX(updated_at) FROM articles WHERE noindex = 0 AND deleted_at IS NULL AND status = 'published'"
+        )
+        .fetch_one(_pool.get_ref())
+        .await
+        .map(|latest| vec![
+            SitemapEntry { path: "/".to_string(), lastmod: latest },
+            SitemapEntry { path: "/about".to_string(), lastmod: None },
+        ]),
+        SitemapPart::Year { year, page } => sqlx::query_as::<_, (i64, Option<String>, String)>(
+            "SELECT id, slug, updated_at FROM articles WHERE noindex = 0 AND deleted_at IS NULL AND status = 'published' \
+             AND strftime('%Y', created_at) = ? \
+             ORDER BY created_at, id LIMIT ? OFFSET ?"
+        )
+        .bind(format!("{:04}", year))
+        .bind(MAX_URLS_PER_SITEMAP as i64)
+        .bind(((page - 1) * MAX_URLS_PER_SITEMAP) as i64)
+        .fetch_all(_pool.get_ref())
+        .await
+        .map(|articles| articles.into_iter().map(|(id, slug, updated_at)| SitemapEntry {
+            path: services::slugs::post_path(id, slug.as_deref()),
+            lastmod: Some(updated_at),
+        }).collect()),
+    };
+
+    match entries {
+        Ok(entries) if entries.is_empty() => HttpResponse::NotFound().finish(),
+        Ok(entries) => {
+            let xml = build_sitemap(&data.site_url, &entries);
+            cache.insert(key, generation, xml.clone());
+            xml_response(xml)
+        }
+        Err(e) => {
+            error!("Failed to build sitemap {}: {}", key, e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn robots_txt(data: web::Data<AppState>) -> impl Responder {
+    let robots = services::well_known::robots_txt(&data.settings().robots, &data.site_url);
+    HttpResponse::Ok().content_type("text/plain; charset=utf-8").body(robots)
+}
+
+// 未配置联系方式时不提供 security.txt
+async fn security_txt(data: web::Data<AppState>) -> impl Responder {
+    match services::well_known::security_txt(&data.settings().security, &data.site_url, chrono::Utc::now()) {
+        Some(txt) => HttpResponse::Ok().content_type("text/plain; charset=utf-8").body(txt),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+async fn favicon(data: web::Data<AppState>) -> impl Responder {
+    serve_brand_asset(&data, BrandAsset::Favicon).await
+}
+
+async fn logo(data: web::Data<AppState>) -> impl Responder {
+    serve_brand_asset(&data, BrandAsset::Logo).await
+}
+
+// 固定地址的站点图标和 Logo；页面引用时带 ?v= 版本号，所以缓存一天即可
+async fn serve_brand_asset(data: &AppState, asset: BrandAsset) -> HttpResponse {
+    let branding = data.settings().branding;
+    let Some(file_path) = branding.get(asset).and_then(|name| data.media.path_for(name)) else {
+        return HttpResponse::NotFound().finish();
+    };
+    match tokio::fs::read(&file_path).await {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type(services::media::content_type_for(&file_path.to_string_lossy()))
+            .insert_header(("X-Content-Type-Options", "nosniff"))
+            .insert_header(("Cache-Control", "public, max-age=86400"))
+            .body(bytes),
+        Err(e) => {
+            error!("Failed to read {} file: {}", asset.as_str(), e);
+            HttpResponse::NotFound().finish()
+        }
+    }
+}
+
+// 站点名称，用于订阅源和 oEmbed
+const SITE_NAME: &str = "Rust Blog";
+
+async fn feed_rss(req: HttpRequest, data: web::Data<AppState>, _pool: web::Data<SqlitePool>) -> impl Responder {
+    serve_feed(&req, &data, &_pool, "/feed.xml", "application/rss+xml; charset=utf-8", services::feed::build_rss).await
+}
+
+async fn feed_atom(req: HttpRequest, data: web::Data<AppState>, _pool: web::Data<SqlitePool>) -> impl Responder {
+    serve_feed(&req, &data, &_pool, "/atom.xml", "application/atom+xml; charset=utf-8", services::feed::build_atom).await
+}
+
+async fn serve_feed(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+    pool: &SqlitePool,
+    self_path: &'static str,
+    content_type: &str,
+    build: fn(&services::feed::FeedChannel, &[services::feed::FeedItem]) -> String,
+) -> HttpResponse {
+    use services::feed::{FeedChannel, FeedItem, FeedValidators};
+
+    // 订阅源被频繁轮询：先用最近一次变化的时间判断是否需要返回 304，避免渲染文章。
+    // 变化包括发布、编辑，以及移入回收站和永久删除（文章从订阅源中消失）
+    let validators = match sqlx::query_as::<_, (Option<String>, i64)>(
+        "SELECT (SELECT MAX(changed_at) FROM ( \
+             SELECT MAX(MAX(COALESCE(published_at, created_at), COALESCE(updated_at, created_at))) AS changed_at \
+             FROM articles WHERE deleted_at IS NULL AND status = 'published' \
+             UNION ALL SELECT MAX(deleted_at) FROM articles WHERE deleted_at IS NOT NULL \
+             UNION ALL SELECT MAX(deleted_at) FROM article_tombstones)), \
+         (SELECT COUNT(*) FROM articles WHERE deleted_at IS NULL AND status = 'published')"
+    )
+    .fetch_one(pool)
+    .await
+    {
+        Ok((newest, count)) => FeedValidators::new(newest.as_deref(), count),
+        Err(e) => {
+            error!("Failed to build feed: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    // 更换图标、Logo、订阅源内容设置或时区也要让订阅源缓存失效
+    let settings = data.settings();
+    let branding = settings.branding;
+    let feed_options = settings.feed;
+    let version: String = [branding.get(BrandAsset::Favicon), branding.get(BrandAsset::Logo)]
+        .iter()
+        .map(|name| name.map_or("", |n| &n[..8]))
+        .chain(std::iter::once(feed_options.version().as_str()))
+        .chain(Some(settings.dates.timezone.name()).filter(|_| settings.dates.timezone != chrono_tz::Tz::UTC))
+        .collect();
+    let validators = if version.is_empty() { validators } else { validators.with_version(&version) };
+    let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+    if validators.is_not_modified(header("If-None-Match"), header("If-Modified-Since")) {
+        return HttpResponse::NotModified()
+            .insert_header(("ETag", validators.etag.as_str()))
+            .insert_header(("Last-Modified", validators.last_modified_header()))
+            .finish();
+    }
+
+    // 最近公开的在前：草稿发布后排在创建时间更晚的文章前面
+    let query = sqlx::query_as::<_, models::Article>(
+        "SELECT * FROM articles WHERE deleted_at IS NULL AND status = 'published' \
+         ORDER BY COALESCE(published_at, created_at) DESC, id DESC LIMIT ?"
+    )
+    .bind(feed_options.items)
+    .fetch_all(pool);
+    let articles = match timed_query(data, Origin::route(self_path), query).await {
+        Ok(articles) => articles,
+        Err(e) => {
+            error!("Failed to build feed: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let summary_options = settings.summary;
+    let mut items: Vec<FeedItem> = Vec::with_capacity(articles.len());
+    for article in articles {
+        let rendered = render_markdown_with_fallback(data, Origin::article(self_path, article.id), article.content).await;
+        items.push(FeedItem {
+            id: article.id,
+            path: services::slugs::post_path(article.id, article.slug.as_deref()),
+            title: article.title,
+            summary: summarize(&summary_options, article.meta_description.as_deref(), &rendered),
+            content: (feed_options.content == FeedContent::Full).then_some(rendered),
+            published: article.published_at.unwrap_or(article.created_at),
+            updated: article.updated_at,
+        });
+    }
+
+    let hub = data.pinger.as_ref().and_then(|p| p.websub_hub());
+    let absolute = |url: Option<String>| url.map(|url| format!("{}{}", data.site_url, url));
+    let icon = absolute(branding.url(BrandAsset::Favicon));
+    let logo = absolute(branding.url(BrandAsset::Logo));
+    let channel = FeedChannel {
+        title: SITE_NAME,
+        site_url: &data.site_url,
+        hub,
+        icon: icon.as_deref(),
+        logo: logo.as_deref(),
+        footer: Some(feed_options.footer.as_str()),
+        timezone: settings.dates.timezone,
+    };
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type(content_type)
+        .insert_header(("ETag", validators.etag.as_str()))
+        .insert_header(("Last-Modified", validators.last_modified_header()));
+    // WebSub 发现也可通过 HTTP Link 头
+    if let Some(hub) = hub {
+        response.insert_header((
+            "Link",
+            format!("<{}>; rel=\"hub\", <{}{}>; rel=\"self\"", hub, data.site_url, self_path),
+        ));
+    }
+    response.body(build(&channel, &items))
+}
+
+// 文章及作者署名
+#[derive(sqlx::FromRow)]
+struct OEmbedArticle {
+    #[sqlx(flatten)]
+    article: models::Article,
+    author_name: Option<String>,
+}
+
+// oEmbed 提供者：为本站文章链接返回嵌入预览
+async fn oembed(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+    query: web::Query<OEmbedQuery>,
+) -> impl Responder {
+    use services::oembed::{embed_html, embed_size, post_key_from_url, OEmbedResponse};
+
+    // 规范要求不支持的格式返回 501
+    if query.format.as_deref().is_some_and(|f| f != "json") {
+        return HttpResponse::NotImplemented().finish();
+    }
+    let Some(key) = post_key_from_url(&data.site_url, &query.url) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let article_id = match resolve_post_key(&data, &_pool, &key).await {
+        Ok(Some(PostKey::Article { id, .. } | PostKey::Moved { id, .. })) => id,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!("Failed to resolve post {}: {}", key, e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    match sqlx::query_as::<_, OEmbedArticle>(
+        "SELECT a.*, COALESCE(NULLIF(u.display_name, ''), u.username) AS author_name FROM articles a \
+         LEFT JOIN users u ON u.id = a.author_id WHERE a.id = ? AND a.deleted_at IS NULL AND a.status = 'published'"
+    )
+    .bind(article_id)
+    .fetch_optional(_pool.get_ref())
+    .await {
+        Ok(Some(OEmbedArticle { article: models::Article { title, content, meta_description, slug, .. }, author_name: author })) => {
+            let excerpt = match meta_description
+                .filter(|d| !d.trim().is_empty())
+                .or_else(|| data.markdown_service.first_paragraph(&content).map(|lead| truncate(&lead, 200, SummaryUnit::Characters)))
+            {
+                Some(excerpt) => excerpt,
+                None => {
+                    let rendered = render_markdown_with_fallback(&data, Origin::article("/oembed", article_id), content).await;
+                    truncate(&strip_html_tags(&rendered), 200, SummaryUnit::Characters)
+                }
+            };
+            let link = format!("{}{}", data.site_url, services::slugs::post_path(article_id, slug.as_deref()));
+            let (width, height) = embed_size(query.maxwidth, query.maxheight);
+            HttpResponse::Ok().json(OEmbedResponse {
+                kind: "rich",
+                version: "1.0",
+                html: embed_html(&title, &link, &excerpt, SITE_NAME),
+                title,
+                author_name: author.unwrap_or_else(|| SITE_NAME.to_string()),
+                provider_name: SITE_NAME.to_string(),
+                provider_url: format!("{}/", data.site_url),
+                width,
+                height,
+            })
+        }
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!("Failed to fetch article for oEmbed: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+// 短链接跳转，计入点击数
+async fn short_link_redirect(
+    path: web::Path<String>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let code = path.into_inner();
+    if !services::short_link::is_valid_code(&code) {
+        return HttpResponse::NotFound().finish();
+    }
+    match models::resolve_short_link(_pool.get_ref(), &code).await {
+        Ok(Some(article_id)) => HttpResponse::Found()
+            .append_header(("Location", format!("/post/{}", article_id)))
+            .finish(),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!("Failed to resolve short link: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OutboundQuery {
+    url: String,
+    article: i64,
+    sig: String,
+}
+
+// 外链点击：校验签名后记录点击再跳转；签名不对时不跳转，避免成为开放重定向
+async fn outbound_redirect(
+    req: HttpRequest,
+    query: web::Query<OutboundQuery>,
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    use services::analytics::{host_of, is_bot};
+
+    let Some(host) = host_of(&query.url).filter(|_| services::outbound::verify(&data.signer, &query.url, query.article, &query.sig)) else {
+        return HttpResponse::BadRequest().body("Invalid link");
+    };
+    let user_agent = req.headers().get("User-Agent").and_then(|v| v.to_str().ok()).unwrap_or("");
+    if !is_bot(user_agent)
+        && let Err(e) = models::record_outbound_click(_pool.get_ref(), query.article, &query.url, &host).await
+    {
+        error!("Failed to record outbound click: {}", e);
+    }
+    HttpResponse::Found()
+        .append_header(("Location", query.url.as_str()))
+        .append_header(("Cache-Control", "no-store"))
+        .append_header(("X-Robots-Tag", "noindex"))
+        .finish()
+}
+
+// 保存读者选择的配色到 Cookie，然后回到原页面；表单提交即可，无需 JavaScript
+async fn set_theme_preference(form: web::Form<ThemeForm>) -> impl Responder {
+    use actix_web::cookie::{time::Duration, Cookie, SameSite};
+    use services::theme::{return_path, Theme, THEME_COOKIE, THEME_COOKIE_DAYS};
+
+    let Some(theme) = Theme::parse(&form.theme) else {
+        return HttpResponse::BadRequest().body("Unknown theme");
+    };
+    let cookie = Cookie::build(THEME_COOKIE, theme.as_str())
+        .path("/")
+        .max_age(Duration::days(THEME_COOKIE_DAYS))
+        .same_site(SameSite::Lax)
+        .http_only(true)
+        .finish();
+    HttpResponse::SeeOther()
+        .cookie(cookie)
+        .append_header(("Location", return_path(form.next.as_deref())))
+        .finish()
+}
+
+async fn react_to_post(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    form: web::Form<ReactionForm>,
+    _pool: web::Data<SqlitePool>,
+    session: Session,
+) -> impl Responder {
+    let post_id = path.into_inner();
+
+    if !data.reaction_emojis.contains(&form.emoji) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": "Unsupported reaction"
+        }));
+    }
+
+    let ip = data.trusted_proxies.client_ip(&req);
+    if !data.reaction_limiter.check(&ip) {
+        return HttpResponse::TooManyRequests().json(serde_json::json!({
+            "success": false,
+            "message": "Too many reactions, please slow down"
+        }));
+    }
+
+    match sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM articles WHERE id = ? AND deleted_at IS NULL AND status = 'published')")
+        .bind(post_id)
+        .fetch_one(_pool.get_ref())
+        .await {
+        Ok(true) => {},
+        Ok(false) => return HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "message": "Article not found"
+        })),
+        Err(e) => {
+            error!("Failed to check article: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    }
+
+    let session_key = match session.get::<String>(REACTION_SESSION_KEY).unwrap_or(None) {
+        Some(key) => key,
+        None => {
+            let key: String = (0..16).map(|_| format!("{:02x}", rand::random::<u8>())).collect();
+            if let Err(e) = session.insert(REACTION_SESSION_KEY, &key) {
+                error!("Failed to set session: {}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+            key
+        }
+    };
+
+    if let Err(e) = models::add_reaction(_pool.get_ref(), post_id, &form.emoji, &session_key).await {
+        error!("Failed to add reaction: {}", e);
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    match models::get_reaction_counts(_pool.get_ref(), post_id, &data.reaction_emojis, Some(&session_key)).await {
+        Ok(reactions) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "reactions": reactions
+        })),
+        Err(e) => {
+            error!("Failed to fetch reactions: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+// 提交评论后在文章页显示一次的提示
+const COMMENT_NOTICE_KEY: &str = "comment_notice";
+
+async fn submit_comment(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    form: web::Form<CommentForm>,
+    _pool: web::Data<SqlitePool>,
+    session: Session,
+) -> impl Responder {
+    if !data.settings().features.comments {
+        return HttpResponse::Forbidden().body("Comments are disabled");
+    }
+    let post_id = path.into_inner();
+    let author_name = form.author_name.trim();
+    let content = form.content.trim();
+    let author_email = form.author_email.as_deref().map(str::trim).filter(|e| !e.is_empty());
+
+    if author_name.is_empty() || author_name.chars().count() > 100 {
+        return HttpResponse::BadRequest().body("Name is required (max 100 characters)");
+    }
+    if content.is_empty() || content.chars().count() > 5000 {
+        return HttpResponse::BadRequest().body("Comment is required (max 5000 characters)");
+    }
+    if author_email.is_some_and(|e| !e.contains('@') || e.len() > 254) {
+        return HttpResponse::BadRequest().body("Invalid email address");
+    }
+    let parent_id = match form.parent_id.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(p) => match p.parse::<i64>() {
+            Ok(id) => Some(id),
+            Err(_) => return HttpResponse::BadRequest().body("Invalid parent comment"),
+        },
+        None => None,
+    };
+
+    let ip = data.trusted_proxies.client_ip(&req);
+    if !data.comment_limiter.check(&ip) {
+        return HttpResponse::TooManyRequests().body("Too many comments, please try again later");
+    }
+    if !data.comment_cooldown.check(&ip) {
+        return HttpResponse::TooManyRequests().body("You are commenting too quickly, please wait a moment");
+    }
+    let scope = services::activity::article_target(post_id);
+    if data.comment_duplicates.is_duplicate(&ip, &scope, content) {
+        return HttpResponse::Conflict().body("Duplicate comment detected; it looks like you've already said that");
+    }
+
+    let article_title = match sqlx::query_scalar::<_, String>(
+        "SELECT title FROM articles WHERE id = ? AND deleted_at IS NULL AND status = 'published'"
+    )
+    .bind(post_id)
+    .fetch_optional(_pool.get_ref())
+    .await {
+        Ok(Some(title)) => title,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!("Failed to check article: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    let mut status = if data.comment_auto_approve { "approved" } else { "pending" };
+    if let Some(checker) = &data.spam_checker {
+        let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok()).unwrap_or("").to_string();
+        let (user_agent, referrer) = (header("User-Agent"), header("Referer"));
+        let permalink = format!("{}/post/{}", data.site_url, post_id);
+        let submission = services::spam::Submission {
+            kind: if parent_id.is_some() { "reply" } else { "comment" },
+            ip: &ip,
+            user_agent: &user_agent,
+            referrer: &referrer,
+            permalink: &permalink,
+            author: author_name,
+            author_email,
+            content,
+        };
+        // 检查失败时放行，按正常流程审核
+        match checker.is_spam(&submission).await {
+            Ok(true) => {
+                info!("{} flagged comment from {} on article {} as spam", checker.name(), ip, post_id);
+                status = "spam";
+            },
+            Ok(false) => {},
+            Err(e) => error!("{} check failed: {}", checker.name(), e),
+        }
+    }
+
+    let new_comment = models::NewComment {
+        article_id: post_id,
+        parent_id,
+        author_name,
+        author_email,
+        content,
+        status,
+        notify_replies: author_email.is_some() && form.notify_replies.is_some(),
+    };
+    match models::create_comment(_pool.get_ref(), &new_comment).await {
+        Ok(comment) => {
+            data.comment_duplicates.record(&ip, &scope, content);
+            if comment.status == "pending" {
+                let target = services::activity::article_target(post_id);
+                let detail = format!("on {}", article_title);
+                record_activity(_pool.get_ref(), &comment.author_name, "comment_pending", &target, &detail).await;
+            }
+            data.events.publish(Event::CommentPosted {
+                comment_id: comment.id,
+                article_id: post_id,
+                status: comment.status.clone(),
+            });
+
+            let notice = if comment.status == "approved" {
+                "Thanks, your comment has been published."
+            } else {
+                "Thanks, your comment is awaiting moderation."
+            };
+            let _ = session.insert(COMMENT_NOTICE_KEY, notice);
+            HttpResponse::SeeOther()
+                .append_header(("Location", format!("/post/{}#comments", post_id)))
+                .finish()
+        },
+        Err(sqlx::Error::RowNotFound) => HttpResponse::BadRequest().body("Invalid parent comment"),
+        Err(e) => {
+            error!("Failed to create comment: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+// 按隐私设置生成评论者头像地址；本地头像用带密钥的摘要，不暴露邮箱哈希
+fn comment_avatar_url(data: &AppState, comment: &models::Comment) -> Option<String> {
+    use services::avatar::{self, AvatarMode};
+
+    let identity = comment.author_email.as_deref().unwrap_or(&comment.author_name);
+    let identicon = format!("/avatar/{}.svg", &data.signer.digest(&format!("avatar:{}", identity))[..32]);
+    match (data.avatar_mode, comment.author_email.as_deref()) {
+        (AvatarMode::Off, _) => None,
+        (AvatarMode::Gravatar, Some(email)) => {
+            Some(avatar::gravatar_url(email, 80, &format!("{}{}", data.site_url, identicon)))
+        },
+        _ => Some(identicon),
+    }
+}
+
+async fn avatar_image(path: web::Path<String>) -> impl Responder {
+    let seed = path.into_inner();
+    if seed.is_empty() || seed.len() > 64 || !seed.chars().all(|c| c.is_ascii_hexdigit()) {
+        return HttpResponse::NotFound().finish();
+    }
+    HttpResponse::Ok()
+        .content_type("image/svg+xml")
+        .insert_header(("Cache-Control", "public, max-age=604800"))
+        .body(services::avatar::identicon_svg(&seed))
+}
+
+// 邮件中的一键审核链接
+async fn moderate_comment_link(
+    data: web::Data<AppState>,
+    path: web::Path<(i64, String)>,
+    query: web::Query<SignedLinkQuery>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    let (comment_id, action) = path.into_inner();
+    let status = match action.as_str() {
+        "approve" => "approved",
+        "spam" => "spam",
+        _ => return HttpResponse::NotFound().finish(),
+    };
+    let payload = format!("comment:{}:{}", comment_id, action);
+    if !data.signer.verify(&payload, &query.token, chrono::Utc::now().timestamp()) {
+        return HttpResponse::Forbidden().body("This link is invalid or has expired.");
+    }
+
+    match models::set_comment_status(_pool.get_ref(), comment_id, status).await {
+        Ok(true) => {
+            if status == "approved"
+                && let Ok(comment) = models::get_comment(_pool.get_ref(), comment_id).await
+            {
+                notify_comment_reply(&data, _pool.get_ref(), &comment).await;
+            }
+            HttpResponse::Ok().content_type("text/plain; charset=utf-8")
+                .body(format!("Comment #{} has been marked as {}.", comment_id, status))
+        },
+        Ok(false) => HttpResponse::NotFound().body("Comment not found."),
+        Err(e) => {
+            error!("Failed to update comment {}: {}", comment_id, e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+// 邮件中的退订回复通知链接
+async fn unsubscribe_comment_link(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+    query: web::Query<SignedLinkQuery>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    let comment_id = path.into_inner();
+    let payload = format!("comment:{}:unsubscribe", comment_id);
+    if !data.signer.verify(&payload, &query.token, chrono::Utc::now().timestamp()) {
+        return HttpResponse::Forbidden().body("This link is invalid or has expired.");
+    }
+
+    match models::disable_reply_notifications(_pool.get_ref(), comment_id).await {
+        Ok(true) => HttpResponse::Ok().content_type("text/plain; charset=utf-8")
+            .body("You will no longer receive reply notifications for this comment."),
+        Ok(false) => HttpResponse::NotFound().body("Comment not found."),
+        Err(e) => {
+            error!("Failed to unsubscribe comment {}: {}", comment_id, e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn about(
+    data: web::Data<AppState>,
+    _pool: web::Data<SqlitePool>
+) -> impl Responder {
+    let mut ctx = Context::new();
+    
+    match models::get_about(_pool.get_ref()).await {
+        Ok(about) => {
+            ctx.insert("title", &about.title);
+            ctx.insert("content", &about.content);
+            match data.template.render("about.html", &ctx) {
+                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+                Err(e) => {
+                    error!("Template rendering error: {}", e);
+                    HttpResponse::InternalServerError().body("Template rendering error")
+                }
+            }
+        },
+        Err(e) => {
+            error!("Failed to fetch about content: {}", e);
+            // Fallback to default content
+            ctx.insert("title", "About My Blog");
+            ctx.insert("content", "This is a blog system built with Rust language and the Actix-web framework.");
+            match data.template.render("about.html", &ctx) {
+                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
+                Err(e) => {
+                    error!("Template rendering error: {}", e);
+                    HttpResponse::InternalServerError().body("Template rendering error")
+                }
+            }
+        }
+    }
+}
+
+// 文章前言 highlight_theme 选用的代码高亮样式
+async fn highlight_theme_css(data: web::Data<AppState>, path: web::Path<String>) -> impl Responder {
+    match data.markdown_service.theme_css(&path.into_inner()) {
+        Some(css) => HttpResponse::Ok()
+            .content_type("text/css; charset=utf-8")
+            .insert_header(("Cache-Control", "public, max-age=86400"))
+            .body(css),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+// 上传的文件；图片和 PDF 直接显示，其他类型以原文件名下载
+async fn serve_media(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+    _pool: web::Data<SqlitePool>,
+) -> impl Responder {
+    let stored_name = path.into_inner();
+    let Some(file_path) = data.media.path_for(&stored_name) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let media = match models::get_media_by_name(_pool.get_ref(), &stored_name).await {
+        Ok(Some(media)) => media,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!("Failed to fetch media: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    match tokio::fs::read(&file_path).await {
+        Ok(bytes) => {
+            let disposition = if services::media::is_inline(&media.content_type) { "inline" } else { "attachment" };
+            // 文件名中的引号和非 ASCII 字符用 RFC 5987 编码
+            let encoded_name: String = media
+                .original_name
+                .bytes()
+                .map(|b| match b {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' => (b as char).to_string(),
+                    _ => format!("%{:02X}", b),
+                })
+                .collect();
+            HttpResponse::Ok()
+                .content_type(media.content_type.as_str())
+                .insert_header(("Content-Disposition", format!("{}; filename*=UTF-8''{}", disposition, encoded_name)))
+                .insert_header(("X-Content-Type-Options", "nosniff"))
+                .insert_header(("Cache-Control", "public, max-age=31536000, immutable"))
+                .body(bytes)
+        }
+        Err(e) => {
+            error!("Failed to read media file {}: {}", stored_name, e);
+            HttpResponse::NotFound().finish()
+        }
+    }
+}
@@ -0,0 +1,14 @@
+//! Library half of Bluster: data access, the services behind the HTTP
+//! handlers and the handlers themselves, split out so benchmarks and
+//! integration tests can drive them directly. The binary only reads the
+//! configuration and runs the server.
+
+pub mod app;
+pub mod handlers;
+pub mod metrics;
+pub mod middleware;
+pub mod models;
+pub mod services;
+pub mod subscribers;
+#[cfg(feature = "test-support")]
+pub mod test_support;
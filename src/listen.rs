@@ -0,0 +1,175 @@
+use log::{info, warn};
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+
+/// First file descriptor passed by systemd socket activation (SD_LISTEN_FDS_START)
+#[cfg(unix)]
+const SD_LISTEN_FDS_START: i32 = 3;
+
+/// A socket the HTTP server should accept connections on
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+/// Number of sockets systemd passed to this process (`LISTEN_FDS`/`LISTEN_PID`).
+/// The activation variables are removed so nothing we spawn inherits them;
+/// changing the environment is only sound while the process has one thread,
+/// so `main` calls this before starting the async runtime.
+pub fn take_systemd_fds() -> usize {
+    let count = listen_fds_count(
+        std::process::id(),
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+    );
+
+    // SAFETY: called from main before the runtime or any other thread is started
+    unsafe {
+        std::env::remove_var("LISTEN_PID");
+        std::env::remove_var("LISTEN_FDS");
+        std::env::remove_var("LISTEN_FDNAMES");
+    }
+    count
+}
+
+/// Resolve listening sockets in priority order:
+/// 1. the `systemd_fds` sockets passed by systemd (see `take_systemd_fds`)
+/// 2. a unix domain socket at `SERVER_SOCKET`
+/// 3. TCP on `SERVER_HOST:SERVER_PORT` (default 0.0.0.0:8080)
+#[cfg_attr(not(unix), allow(unused_variables))]
+pub fn resolve_listeners(systemd_fds: usize) -> std::io::Result<Vec<Listener>> {
+    #[cfg(unix)]
+    {
+        let inherited = systemd_listeners(systemd_fds);
+        if !inherited.is_empty() {
+            info!("Using {} socket(s) passed by systemd", inherited.len());
+            return Ok(inherited);
+        }
+    }
+
+    if let Ok(path) = std::env::var("SERVER_SOCKET") {
+        #[cfg(unix)]
+        {
+            let mode = std::env::var("SERVER_SOCKET_MODE")
+                .ok()
+                .and_then(|m| u32::from_str_radix(&m, 8).ok())
+                .unwrap_or(0o660);
+            let listener = bind_unix_socket(&path, mode)?;
+            info!("Listening on unix socket {} (mode {:o})", path, mode);
+            return Ok(vec![Listener::Unix(listener)]);
+        }
+        #[cfg(not(unix))]
+        warn!("SERVER_SOCKET={} ignored: unix sockets are not supported on this platform", path);
+    }
+
+    let host = std::env::var("SERVER_HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
+    let port = std::env::var("SERVER_PORT")
+        .unwrap_or_else(|_| "8080".to_string())
+        .parse::<u16>()
+        .unwrap_or(8080);
+    let listener = TcpListener::bind((host.as_str(), port))?;
+    info!("Listening on http://{}:{}", host, port);
+    Ok(vec![Listener::Tcp(listener)])
+}
+
+/// Number of descriptors systemd passed to this process, if they are meant for us
+fn listen_fds_count(own_pid: u32, listen_pid: Option<&str>, listen_fds: Option<&str>) -> usize {
+    let for_us = listen_pid
+        .and_then(|pid| pid.trim().parse::<u32>().ok())
+        .is_some_and(|pid| pid == own_pid);
+    if !for_us {
+        return 0;
+    }
+    listen_fds
+        .and_then(|n| n.trim().parse::<usize>().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(unix)]
+fn systemd_listeners(count: usize) -> Vec<Listener> {
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+    (0..count as i32)
+        .map(|offset| {
+            let fd = SD_LISTEN_FDS_START + offset;
+            // SAFETY: systemd guarantees these descriptors are open listening sockets owned by us
+            let tcp = unsafe { TcpListener::from_raw_fd(fd) };
+            if tcp.local_addr().is_ok() {
+                Listener::Tcp(tcp)
+            } else {
+                // getsockname can't produce an inet address, so it's a unix socket
+                let fd = tcp.into_raw_fd();
+                Listener::Unix(unsafe { UnixListener::from_raw_fd(fd) })
+            }
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn bind_unix_socket(path: &str, mode: u32) -> std::io::Result<UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    // A stale socket left behind by an unclean exit would make bind fail.
+    // Anything else at the path is left alone: it is most likely a mistyped setting.
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            warn!("Removing stale socket file {}", path);
+            std::fs::remove_file(path)?;
+        }
+        Ok(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path),
+            ));
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    Ok(listener)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listen_fds_for_this_process() {
+        assert_eq!(listen_fds_count(42, Some("42"), Some("2")), 2);
+    }
+
+    #[test]
+    fn test_listen_fds_for_other_process_ignored() {
+        assert_eq!(listen_fds_count(42, Some("7"), Some("2")), 0);
+        assert_eq!(listen_fds_count(42, None, Some("2")), 0);
+    }
+
+    #[test]
+    fn test_listen_fds_malformed() {
+        assert_eq!(listen_fds_count(42, Some("42"), Some("many")), 0);
+        assert_eq!(listen_fds_count(42, Some("42"), None), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bind_unix_socket_replaces_only_sockets() {
+        let dir = std::env::temp_dir().join(format!("bluster-listen-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let file = dir.join("not-a-socket");
+        std::fs::write(&file, "keep me").unwrap();
+        let err = bind_unix_socket(file.to_str().unwrap(), 0o600).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep me");
+
+        // The socket file outlives the listener, as after a crash
+        let socket = dir.join("stale.sock");
+        drop(UnixListener::bind(&socket).unwrap());
+        assert!(bind_unix_socket(socket.to_str().unwrap(), 0o600).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
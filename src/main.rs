@@ -1,1144 +1,195 @@
-use actix_web::{web, App, HttpServer, Responder, HttpResponse};
-use actix_web::middleware::Logger;
-use actix_session::{Session, SessionMiddleware};
-use actix_multipart::Multipart;
-use futures_util::TryStreamExt;
+use actix_web::{web, App, HttpServer};
+use actix_web::middleware::{from_fn, Logger};
+use actix_session::SessionMiddleware;
 use log::{info, error};
-use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
-use crate::models::{init_db, verify_user};
-use crate::services::{MarkdownService, FileService};
-use tera::{Tera, Context};
+use bluster::app::{
+    self, AppState, backfill_article_links, purge_expired_trash, reload_navigation, reload_settings, reload_snippets,
+    run_db_maintenance, schedule_cache_prewarm,
+};
+use bluster::models::init_db;
+use bluster::{handlers, metrics, middleware, subscribers};
 
-mod models;
-mod services;
+mod cli;
+mod listen;
 
-// Helper function to strip HTML tags for creating plain text summaries
-fn strip_html_tags(html: &str) -> String {
-    let mut result = String::new();
-    let mut in_tag = false;
-    
-    for ch in html.chars() {
-        match ch {
-            '<' => in_tag = true,
-            '>' => in_tag = false,
-            _ if !in_tag => result.push(ch),
-            _ => {}
-        }
-    }
-    
-    // Clean up extra whitespace
-    result.split_whitespace().collect::<Vec<&str>>().join(" ")
-}
-
-#[derive(Deserialize)]
-struct LoginForm {
-    username: String,
-    password: String,
-}
-
-#[derive(Deserialize, Serialize)]
-struct ArticleForm {
-    title: String,
-    content: String,
-}
-
-#[derive(Deserialize, Serialize)]
-struct AboutForm {
-    title: String,
-    content: String,
-}
-
-#[derive(Deserialize)]
-struct ChangePasswordForm {
-    current_password: String,
-    new_password: String,
-    confirm_password: String,
-}
-
-#[derive(Deserialize)]
-struct SecurityQuestionForm {
-    question: String,
-    answer: String,
-}
-
-#[derive(Deserialize)]
-struct ResetPasswordForm {
-    username: String,
-    security_answer: String,
-    new_password: String,
-    confirm_password: String,
-}
-
-#[derive(Deserialize)]
-struct PreviewRequest {
-    content: String,
-}
-
-#[derive(Serialize)]
-struct PreviewResponse {
-    html: String,
-}
-
-// Blog post structure
-#[derive(Serialize, Deserialize, Clone)]
-struct Post {
-    id: u32,
-    title: String,
-    summary: String,
-    content: String,
-    date: String,
+fn main() -> std::io::Result<()> {
+    // systemd 的激活变量须在运行时启动线程之前读取并清除
+    let systemd_fds = listen::take_systemd_fds();
+    actix_web::rt::System::new().block_on(run(systemd_fds))
 }
 
-// Application state, storing blog posts
-struct AppState {
-    template: Tera,
-    markdown_service: MarkdownService,
-}
-
-async fn index(
-    data: web::Data<AppState>,
-    _pool: web::Data<SqlitePool>
-) -> impl Responder {
-    let mut ctx = Context::new();
+async fn run(systemd_fds: usize) -> std::io::Result<()> {
+    // Initialize logging
+    env_logger::Builder::new()
+        .filter_level(log::LevelFilter::Info)
+        .init();
     
-    match sqlx::query_as::<_, (i64, String, String, String)>(
-        "SELECT id, title, content, created_at FROM articles ORDER BY created_at DESC"
-    )
-    .fetch_all(_pool.get_ref())
-    .await {
-        Ok(articles) => {
-            let posts: Vec<Post> = articles.into_iter().map(|(id, title, content, date)| {
-                // Render markdown content to HTML with fallback
-                let rendered_content = data.markdown_service.render_to_html_with_fallback(&content);
-                
-                // Create summary from plain text (strip HTML tags for summary)
-                let plain_text = strip_html_tags(&rendered_content);
-                let summary = plain_text.chars().take(100).collect();
-                
-                Post {
-                    id: id as u32,
-                    title,
-                    summary,
-                    content: rendered_content,
-                    date
-                }
-            }).collect();
-            ctx.insert("posts", &posts);
-            match data.template.render("index.html", &ctx) {
-                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
-                Err(e) => {
-                    error!("Template rendering error: {}", e);
-                    HttpResponse::InternalServerError().body("Template rendering error")
-                }
-            }
-        },
-        Err(e) => {
-            error!("Failed to fetch articles: {}", e);
-            HttpResponse::InternalServerError().finish()
+    // 维护命令（导出/导入整个站点）执行完即退出，不启动服务器
+    match cli::parse(&std::env::args().skip(1).collect::<Vec<_>>()) {
+        Ok(cli::Command::Serve) => {}
+        Ok(command) => std::process::exit(cli::run(command).await),
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, cli::USAGE);
+            std::process::exit(2);
         }
     }
-}
 
-async fn post_detail(
-    data: web::Data<AppState>,
-    path: web::Path<i64>,
-    _pool: web::Data<SqlitePool>
-) -> impl Responder {
-    let post_id = path.into_inner();
-    let mut ctx = Context::new();
+    info!("Starting blog server...");
     
-    match sqlx::query_as::<_, (i64, String, String, String)>(
-        "SELECT id, title, content, created_at FROM articles WHERE id = ?"
-    )
-    .bind(post_id)
-    .fetch_one(_pool.get_ref())
-    .await {
-        Ok((id, title, content, created_at)) => {
-            // Render markdown content to HTML with fallback
-            let rendered_content = data.markdown_service.render_to_html_with_fallback(&content);
-            
-            // Create summary from plain text
-            let plain_text = strip_html_tags(&rendered_content);
-            let summary = plain_text.chars().take(100).collect();
-            
-            let post = Post {
-                id: id as u32,
-                title,
-                summary,
-                content: rendered_content,
-                date: created_at
-            };
-            ctx.insert("post", &post);
-            match data.template.render("post.html", &ctx) {
-                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
-                Err(e) => {
-                    error!("Template rendering error: {}", e);
-                    HttpResponse::InternalServerError().body("Template rendering error")
-                }
-            }
-        },
-        Err(e) => {
-            error!("Failed to fetch article: {}", e);
-            HttpResponse::NotFound().finish()
-        }
-    }
-}
-
-async fn about(
-    data: web::Data<AppState>,
-    _pool: web::Data<SqlitePool>
-) -> impl Responder {
-    let mut ctx = Context::new();
+    let listeners = listen::resolve_listeners(systemd_fds)?;
     
-    match sqlx::query_as::<_, (i64, String, String, String)>(
-        "SELECT id, title, content, updated_at FROM about ORDER BY id DESC LIMIT 1"
-    )
-    .fetch_one(_pool.get_ref())
-    .await {
-        Ok((_, title, content, _)) => {
-            ctx.insert("title", &title);
-            ctx.insert("content", &content);
-            match data.template.render("about.html", &ctx) {
-                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
-                Err(e) => {
-                    error!("Template rendering error: {}", e);
-                    HttpResponse::InternalServerError().body("Template rendering error")
-                }
-            }
-        },
+    let app_state = match AppState::from_env() {
+        Ok(state) => web::Data::new(state),
         Err(e) => {
-            error!("Failed to fetch about content: {}", e);
-            // Fallback to default content
-            ctx.insert("title", "About My Blog");
-            ctx.insert("content", "This is a blog system built with Rust language and the Actix-web framework.");
-            match data.template.render("about.html", &ctx) {
-                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
-                Err(e) => {
-                    error!("Template rendering error: {}", e);
-                    HttpResponse::InternalServerError().body("Template rendering error")
-                }
-            }
-        }
-    }
-}
-
-async fn login_page(data: web::Data<AppState>) -> impl Responder {
-    match data.template.render("login.html", &Context::new()) {
-        Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
-        Err(e) => {
-            error!("Template rendering error: {}", e);
-            HttpResponse::InternalServerError().body("Template rendering error")
-        }
-    }
-}
-async fn admin_dashboard(
-    data: web::Data<AppState>,
-    session: Session,
-    _pool: web::Data<SqlitePool>
-) -> actix_web::Result<HttpResponse> {
-    // 检查session中的登录状态
-    if let Some(_) = session.get::<String>("username")? {
-        // 已登录，显示dashboard
-        match sqlx::query_as::<_, (i64, String, String, String)>(
-            "SELECT id, title, content, created_at FROM articles ORDER BY created_at DESC"
-        )
-        .fetch_all(_pool.get_ref())
-        .await {
-            Ok(articles) => {
-                let mut ctx = Context::new();
-                // 打印articles调试信息
-                info!("Articles data: {:?}", articles);
-                // 转换articles为模板需要的格式
-                #[derive(serde::Serialize)]
-                struct TemplateArticle {
-                    id: i64,
-                    title: String,
-                    content: String,
-                    created_at: String,
-                }
-
-                let template_articles: Vec<TemplateArticle> = articles.into_iter().map(|(id, title, content, created_at)| {
-                    TemplateArticle {
-                        id,
-                        title,
-                        content,
-                        created_at,
-                    }
-                }).collect();
-                ctx.insert("articles", &template_articles);
-                match data.template.render("admin/dashboard.html", &ctx) {
-                    Ok(html) => Ok(HttpResponse::Ok().content_type("text/html").body(html)),
-                    Err(e) => {
-                        error!("Detailed template rendering error: {:#?}", e);
-                        Ok(HttpResponse::InternalServerError().body(format!("Detailed template error: {:#?}", e)))
-                    }
-                }
-            },
-            Err(e) => {
-                error!("Failed to fetch articles: {}", e);
-                Ok(HttpResponse::InternalServerError().finish())
-            }
+            error!("Template parsing error: {}", e);
+            ::std::process::exit(1);
         }
-    } else {
-        // 未登录，重定向到登录页面
-        Ok(HttpResponse::Found()
-            .append_header(("Location", "/login"))
-            .finish())
-    }
-}
-
-async fn admin_articles(
-    _pool: web::Data<SqlitePool>,
-    session: Session
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Found()
-            .append_header(("Location", "/login"))
-            .finish();
-    }
+    };
+    let metrics_snapshot_interval = app::metrics_snapshot_interval();
     
-    match sqlx::query_as::<_, (i64, String, String)>(
-        "SELECT id, title, content FROM articles ORDER BY created_at DESC"
-    )
-    .fetch_all(_pool.get_ref())
-    .await {
-        Ok(articles) => HttpResponse::Ok().json(articles),
-        Err(e) => {
-            error!("Failed to fetch articles: {}", e);
-            HttpResponse::InternalServerError().json("Failed to fetch articles")
-        }
-    }
-}
+    // Shutdown signal shared with background tasks so they can finish their current run and exit
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    let mut background_tasks = Vec::new();
 
-async fn admin_edit_article(
-    data: web::Data<AppState>,
-    path: web::Path<i64>,
-    _pool: web::Data<SqlitePool>,
-    session: Session
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Found()
-            .append_header(("Location", "/login"))
-            .finish();
-    }
-    let article_id = path.into_inner();
-    match sqlx::query_as::<_, (i64, String, String)>(
-        "SELECT id, title, content FROM articles WHERE id = ?"
-    )
-    .bind(article_id)
-    .fetch_one(_pool.get_ref())
-    .await {
-        Ok((id, title, content)) => {
-            #[derive(serde::Serialize)]
-            struct TemplateArticle {
-                id: i64,
-                title: String,
-                content: String
-            }
-            let mut ctx = Context::new();
-            ctx.insert("article", &TemplateArticle {
-                id,
-                title,
-                content
-            });
-            match data.template.render("admin/edit_article.html", &ctx) {
-                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
-                Err(e) => {
-                    error!("Template rendering error: {:#?}", e);
-                    HttpResponse::InternalServerError().body(format!("Template rendering error: {:#?}", e))
+    // Start periodic cache optimization task
+    let app_state_for_task = app_state.clone();
+    let mut shutdown = shutdown_rx.clone();
+    background_tasks.push(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(1800)); // Every 30 minutes
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    log::info!("Running periodic cache optimization...");
+                    app_state_for_task.markdown_service.optimize_cache();
                 }
+                _ = shutdown.changed() => break,
             }
-        },
-        Err(e) => {
-            error!("Failed to fetch article: {}", e);
-            HttpResponse::NotFound().finish()
         }
-    }
-}
+    }));
 
-async fn admin_update_article(
-    path: web::Path<i64>,
-    json: web::Json<ArticleForm>,
-    _pool: web::Data<SqlitePool>,
-    session: Session
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Found()
-            .append_header(("Location", "/login"))
-            .finish();
-    }
-    
-    let article_id = path.into_inner();
-    match sqlx::query(
-        "UPDATE articles SET title = ?, content = ?, updated_at = datetime('now') WHERE id = ?"
-    )
-    .bind(&json.title)
-    .bind(&json.content)
-    .bind(article_id)
-    .execute(_pool.get_ref())
-    .await {
-        Ok(_) => HttpResponse::Ok().json("Article updated successfully"),
-        Err(e) => {
-            error!("Failed to update article: {}", e);
-            HttpResponse::InternalServerError().finish()
-        }
-    }
-}
-
-async fn admin_create_article(
-    form: web::Form<ArticleForm>,
-    _pool: web::Data<SqlitePool>,
-    session: Session,
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Found()
-            .append_header(("Location", "/login"))
-            .finish();
-    }
-    match sqlx::query(
-        "INSERT INTO articles (title, content, created_at, updated_at) VALUES (?, ?, datetime('now'), datetime('now'))"
-    )
-    .bind(&form.title)
-    .bind(&form.content)
-    .execute(_pool.get_ref())
-    .await {
-        Ok(_) => HttpResponse::Found().append_header(("Location", "/admin")).finish(),
-        Err(e) => {
-            error!("Failed to create article: {}", e);
-            HttpResponse::InternalServerError().finish()
-        }
-    }
-}
-
-async fn admin_about_edit(
-    data: web::Data<AppState>,
-    _pool: web::Data<SqlitePool>,
-    session: Session
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Found()
-            .append_header(("Location", "/login"))
-            .finish();
-    }
-    
-    let mut ctx = Context::new();
-    
-    match sqlx::query_as::<_, (i64, String, String, String)>(
-        "SELECT id, title, content, updated_at FROM about ORDER BY id DESC LIMIT 1"
-    )
-    .fetch_one(_pool.get_ref())
-    .await {
-        Ok((id, title, content, updated_at)) => {
-            ctx.insert("id", &id);
-            ctx.insert("title", &title);
-            ctx.insert("content", &content);
-            ctx.insert("updated_at", &updated_at);
-            match data.template.render("admin/edit_about.html", &ctx) {
-                Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
-                Err(e) => {
-                    error!("Template rendering error: {:#?}", e);
-                    HttpResponse::InternalServerError().body(format!("Template rendering error: {:#?}", e))
+    // Record metrics snapshots for the history graph; the first tick sets the baseline
+    let app_state_for_task = app_state.clone();
+    let mut shutdown = shutdown_rx.clone();
+    background_tasks.push(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(metrics_snapshot_interval));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let render = app_state_for_task.markdown_service.get_metrics();
+                    let requests = &app_state_for_task.request_metrics;
+                    app_state_for_task.metrics_history.record(
+                        metrics::CounterReading {
+                            requests: requests.total_requests(),
+                            timeouts: requests.timed_out_requests(),
+                            renders: render.total_renders,
+                            cache_hits: render.cache_hits,
+                            cache_misses: render.cache_misses,
+                            avg_render_time_ms: render.avg_render_time_ms,
+                        },
+                        chrono::Utc::now().timestamp(),
+                    );
                 }
+                _ = shutdown.changed() => break,
             }
-        },
-        Err(e) => {
-            error!("Failed to fetch about content: {}", e);
-            HttpResponse::InternalServerError().finish()
         }
-    }
-}
-
-async fn admin_update_about(
-    json: web::Json<AboutForm>,
-    _pool: web::Data<SqlitePool>,
-    session: Session
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Found()
-            .append_header(("Location", "/login"))
-            .finish();
-    }
+    }));
     
-    match sqlx::query(
-        "UPDATE about SET title = ?, content = ?, updated_at = datetime('now') WHERE id = (SELECT id FROM about ORDER BY id DESC LIMIT 1)"
-    )
-    .bind(&json.title)
-    .bind(&json.content)
-    .execute(_pool.get_ref())
-    .await {
-        Ok(_) => HttpResponse::Ok().json("About content updated successfully"),
-        Err(e) => {
-            error!("Failed to update about content: {}", e);
-            HttpResponse::InternalServerError().finish()
-        }
-    }
-}
-
-async fn login(
-    form: web::Form<LoginForm>,
-    _pool: web::Data<SqlitePool>,
-    session: Session,
-) -> impl Responder {
-    match verify_user(&_pool, &form.username, &form.password).await {
-        Ok(_) => {
-            // 登录成功，设置session
-            if let Err(e) = session.insert("username", &form.username) {
-                error!("Failed to set session: {}", e);
-                return HttpResponse::InternalServerError().finish();
-            }
-            HttpResponse::Found().append_header(("Location", "/admin")).finish()
-        },
-        Err(_) => HttpResponse::Unauthorized().body("Invalid credentials")
-    }
-}
-
-async fn logout(session: Session) -> impl Responder {
-    // 清除session
-    session.clear();
-    HttpResponse::Found()
-        .append_header(("Location", "/login"))
-        .finish()
-}
-
-async fn get_articles(_pool: web::Data<SqlitePool>) -> impl Responder {
-    match sqlx::query_as::<_, (i64, String, String)>(
-        "SELECT id, title, content FROM articles ORDER BY created_at DESC"
-    )
-    .fetch_all(_pool.get_ref())
-    .await {
-        Ok(articles) => HttpResponse::Ok().json(articles),
-        Err(e) => {
-            error!("Failed to fetch articles: {}", e);
-            HttpResponse::InternalServerError().finish()
-        }
-    }
-}
-
-async fn get_article(
-    _pool: web::Data<SqlitePool>,
-    path: web::Path<i64>
-) -> impl Responder {
-    let article_id = path.into_inner();
-    match sqlx::query_as::<_, (i64, String, String)>(
-        "SELECT id, title, content FROM articles WHERE id = ?"
-    )
-    .bind(article_id)
-    .fetch_one(_pool.get_ref())
-    .await {
-        Ok(article) => HttpResponse::Ok().json(article),
-        Err(e) => {
-            error!("Failed to fetch article: {}", e);
-            HttpResponse::InternalServerError().finish()
-        }
-    }
-}
-
-async fn update_article(
-    path: web::Path<i64>,
-    form: web::Form<ArticleForm>,
-    _pool: web::Data<SqlitePool>,
-    session: Session,
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Unauthorized().json("Unauthorized");
-    }
-    let article_id = path.into_inner();
-    match sqlx::query(
-        "UPDATE articles SET title = ?, content = ?, updated_at = datetime('now') WHERE id = ?"
-    )
-    .bind(&form.title)
-    .bind(&form.content)
-    .bind(article_id)
-    .execute(_pool.get_ref())
-    .await {
-        Ok(_) => HttpResponse::Ok().json("Article updated successfully"),
-        Err(e) => {
-            error!("Failed to update article: {}", e);
-            HttpResponse::InternalServerError().finish()
-        }
-    }
-}
-
-async fn delete_article(
-    path: web::Path<i64>,
-    _pool: web::Data<SqlitePool>,
-    session: Session
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Found()
-            .append_header(("Location", "/login"))
-            .finish();
-    }
-    let article_id = path.into_inner();
-    match sqlx::query(
-        "DELETE FROM articles WHERE id = ?"
-    )
-    .bind(article_id)
-    .execute(_pool.get_ref())
-    .await {
-        Ok(_) => HttpResponse::Ok().json("Article deleted successfully"),
+    // Initialize database
+    let pool = match init_db(app_state.slow_query_threshold).await {
+        Ok(pool) => pool,
         Err(e) => {
-            error!("Failed to delete article: {}", e);
-            HttpResponse::InternalServerError().finish()
+            error!("Failed to initialize database: {}", e);
+            std::process::exit(1);
         }
-    }
-}
+    };
 
-async fn create_article(
-    form: web::Form<ArticleForm>,
-    _pool: web::Data<SqlitePool>,
-    session: Session,
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Unauthorized().json("Unauthorized");
+    if let Err(e) = reload_snippets(&app_state, &pool).await {
+        error!("Failed to load content snippets: {}", e);
     }
-    match sqlx::query(
-        "INSERT INTO articles (title, content, created_at, updated_at) VALUES (?, ?, datetime('now'), datetime('now'))"
-    )
-    .bind(&form.title)
-    .bind(&form.content)
-    .execute(_pool.get_ref())
-    .await {
-        Ok(_) => HttpResponse::Ok().json("Article created successfully"),
-        Err(e) => {
-            error!("Failed to create article: {}", e);
-            HttpResponse::InternalServerError().finish()
-        }
+    if let Err(e) = reload_settings(&app_state, &pool).await {
+        error!("Failed to load site settings: {}", e);
     }
-}
-
-// 管理员密码设置页面
-async fn admin_password_settings(
-    data: web::Data<AppState>,
-    session: Session,
-    _pool: web::Data<SqlitePool>
-) -> impl Responder {
-    if let Ok(Some(_)) = session.get::<String>("username") {
-        // 获取当前用户信息
-        let username = session.get::<String>("username").unwrap().unwrap();
-        match sqlx::query_as::<_, models::User>("SELECT * FROM users WHERE username = ?")
-            .bind(&username)
-            .fetch_one(_pool.get_ref())
-            .await {
-            Ok(user) => {
-                let mut ctx = Context::new();
-                ctx.insert("user", &user);
-                match data.template.render("admin/password_settings.html", &ctx) {
-                    Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
-                    Err(e) => {
-                        error!("Template rendering error: {}", e);
-                        HttpResponse::InternalServerError().body("Template rendering error")
-                    }
-                }
-            },
-            Err(_) => HttpResponse::Found().append_header(("Location", "/login")).finish()
-        }
-    } else {
-        HttpResponse::Found().append_header(("Location", "/login")).finish()
+    if let Err(e) = reload_navigation(&app_state, &pool).await {
+        error!("Failed to load navigation menu: {}", e);
     }
-}
-
-// 修改密码
-async fn admin_change_password(
-    form: web::Form<ChangePasswordForm>,
-    session: Session,
-    _pool: web::Data<SqlitePool>
-) -> impl Responder {
-    if let Ok(Some(username)) = session.get::<String>("username") {
-        if form.new_password != form.confirm_password {
-            return HttpResponse::BadRequest().json(serde_json::json!({
-                "success": false,
-                "message": "新密码和确认密码不匹配"
-            }));
-        }
-        
-        // 验证当前密码
-        match models::verify_user(_pool.get_ref(), &username, &form.current_password).await {
-            Ok(user) => {
-                // 更新密码
-                match models::update_user_password(_pool.get_ref(), user.id, &form.new_password).await {
-                    Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-                        "success": true,
-                        "message": "密码修改成功"
-                    })),
-                    Err(e) => {
-                        error!("Failed to update password: {}", e);
-                        HttpResponse::InternalServerError().json(serde_json::json!({
-                            "success": false,
-                            "message": "密码更新失败"
-                        }))
-                    }
-                }
-            },
-            Err(_) => HttpResponse::BadRequest().json(serde_json::json!({
-                "success": false,
-                "message": "当前密码错误"
-            }))
-        }
-    } else {
-        HttpResponse::Unauthorized().json(serde_json::json!({
-            "success": false,
-            "message": "未登录"
-        }))
+    if let Err(e) = backfill_article_links(&app_state, &pool).await {
+        error!("Failed to build article link graph: {}", e);
     }
-}
+    schedule_cache_prewarm(app_state.clone(), pool.clone());
+    let event_workers = subscribers::subscribe_all(&app_state.events, &app_state, &pool);
 
-// 设置安全问题
-async fn admin_set_security_question(
-    form: web::Form<SecurityQuestionForm>,
-    session: Session,
-    _pool: web::Data<SqlitePool>
-) -> impl Responder {
-    if let Ok(Some(username)) = session.get::<String>("username") {
-        // 获取用户ID
-        match sqlx::query_as::<_, models::User>("SELECT * FROM users WHERE username = ?")
-            .bind(&username)
-            .fetch_one(_pool.get_ref())
-            .await {
-            Ok(user) => {
-                match models::set_security_question(_pool.get_ref(), user.id, &form.question, &form.answer).await {
-                    Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-                        "success": true,
-                        "message": "安全问题设置成功"
-                    })),
-                    Err(e) => {
-                        error!("Failed to set security question: {}", e);
-                        HttpResponse::InternalServerError().json(serde_json::json!({
-                            "success": false,
-                            "message": "设置失败"
-                        }))
+    // Scheduled database maintenance every N hours; 0 disables
+    let db_maintenance_interval = std::env::var("DB_MAINTENANCE_INTERVAL_HOURS")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse::<u64>()
+        .unwrap_or(0);
+    if db_maintenance_interval > 0 {
+        let maintenance_pool = pool.clone();
+        let mut shutdown = shutdown_rx.clone();
+        background_tasks.push(tokio::spawn(async move {
+            let period = std::time::Duration::from_secs(db_maintenance_interval * 3600);
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        if let Err(e) = run_db_maintenance(&maintenance_pool, "system").await {
+                            error!("Scheduled database maintenance failed: {}", e);
+                        }
                     }
-                }
-            },
-            Err(_) => HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "message": "用户不存在"
-            }))
-        }
-    } else {
-        HttpResponse::Unauthorized().json(serde_json::json!({
-            "success": false,
-            "message": "未登录"
-        }))
-    }
-}
-
-// 重置密码页面
-async fn reset_password_page(data: web::Data<AppState>) -> impl Responder {
-    match data.template.render("reset_password.html", &Context::new()) {
-        Ok(html) => HttpResponse::Ok().content_type("text/html").body(html),
-        Err(e) => {
-            error!("Template rendering error: {}", e);
-            HttpResponse::InternalServerError().body("Template rendering error")
-        }
-    }
-}
-
-// 处理重置密码
-async fn reset_password(
-    form: web::Form<ResetPasswordForm>,
-    _pool: web::Data<SqlitePool>
-) -> impl Responder {
-    if form.new_password != form.confirm_password {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "message": "新密码和确认密码不匹配"
-        }));
-    }
-    
-    // 验证安全问题答案
-    match models::verify_security_answer(_pool.get_ref(), &form.username, &form.security_answer).await {
-        Ok(_) => {
-            // 重置密码
-            match models::reset_password_by_username(_pool.get_ref(), &form.username, &form.new_password).await {
-                Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "message": "密码重置成功，请使用新密码登录"
-                })),
-                Err(e) => {
-                    error!("Failed to reset password: {}", e);
-                    HttpResponse::InternalServerError().json(serde_json::json!({
-                        "success": false,
-                        "message": "密码重置失败"
-                    }))
+                    _ = shutdown.changed() => break,
                 }
             }
-        },
-        Err(_) => HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "message": "用户名或安全问题答案错误"
-        }))
-    }
-}
-
-// 获取用户安全问题
-async fn get_security_question(
-    query: web::Query<std::collections::HashMap<String, String>>,
-    _pool: web::Data<SqlitePool>
-) -> impl Responder {
-    if let Some(username) = query.get("username") {
-        match sqlx::query_as::<_, models::User>("SELECT * FROM users WHERE username = ?")
-            .bind(username)
-            .fetch_one(_pool.get_ref())
-            .await {
-            Ok(user) => {
-                if let Some(question) = user.security_question {
-                    HttpResponse::Ok().json(serde_json::json!({
-                        "success": true,
-                        "question": question
-                    }))
-                } else {
-                    HttpResponse::BadRequest().json(serde_json::json!({
-                        "success": false,
-                        "message": "该用户未设置安全问题"
-                    }))
-                }
-            },
-            Err(_) => HttpResponse::BadRequest().json(serde_json::json!({
-                "success": false,
-                "message": "用户不存在"
-            }))
-        }
-    } else {
-        HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "message": "缺少用户名参数"
-        }))
-    }
-}
-
-// Performance monitoring endpoint
-async fn admin_performance_stats(
-    data: web::Data<AppState>,
-    session: Session
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Unauthorized().json(serde_json::json!({
-            "error": "Unauthorized"
         }));
     }
-    
-    let metrics = data.markdown_service.get_metrics();
-    let cache_hit_rate = if metrics.cache_hits + metrics.cache_misses > 0 {
-        (metrics.cache_hits as f64 / (metrics.cache_hits + metrics.cache_misses) as f64) * 100.0
-    } else {
-        0.0
-    };
-    
-    HttpResponse::Ok().json(serde_json::json!({
-        "total_renders": metrics.total_renders,
-        "cache_hits": metrics.cache_hits,
-        "cache_misses": metrics.cache_misses,
-        "cache_hit_rate": format!("{:.1}%", cache_hit_rate),
-        "avg_render_time_ms": format!("{:.2}", metrics.avg_render_time_ms),
-        "cache_size": metrics.cache_size,
-        "memory_usage_kb": format!("{:.2}", metrics.memory_usage_bytes as f64 / 1024.0)
-    }))
-}
-
-// Cache management endpoint
-async fn admin_cache_clear(
-    data: web::Data<AppState>,
-    session: Session
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Unauthorized().json(serde_json::json!({
-            "error": "Unauthorized"
-        }));
-    }
-    
-    data.markdown_service.clear_cache();
-    
-    HttpResponse::Ok().json(serde_json::json!({
-        "success": true,
-        "message": "Cache cleared successfully"
-    }))
-}
-
-// Cache optimization endpoint
-async fn admin_cache_optimize(
-    data: web::Data<AppState>,
-    session: Session
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Unauthorized().json(serde_json::json!({
-            "error": "Unauthorized"
-        }));
-    }
-    
-    data.markdown_service.optimize_cache();
-    
-    HttpResponse::Ok().json(serde_json::json!({
-        "success": true,
-        "message": "Cache optimized successfully"
-    }))
-}
-
-// Markdown预览功能
-async fn admin_preview_markdown(
-    data: web::Data<AppState>,
-    json: web::Json<PreviewRequest>,
-    session: Session
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Unauthorized().json(serde_json::json!({
-            "error": "Unauthorized"
-        }));
-    }
-    
-    match data.markdown_service.render_to_html(&json.content) {
-        Ok(html) => HttpResponse::Ok().json(PreviewResponse { html }),
-        Err(e) => {
-            error!("Markdown rendering failed: {}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Failed to render markdown",
-                "details": e.to_string()
-            }))
-        }
-    }
-}
 
-// 文件导入功能
-async fn admin_import_article(
-    mut payload: Multipart,
-    _pool: web::Data<SqlitePool>,
-    session: Session
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Unauthorized().json(serde_json::json!({
-            "success": false,
-            "message": "Unauthorized"
-        }));
-    }
-
-    // 处理文件上传
-    while let Some(mut field) = payload.try_next().await.unwrap_or(None) {
-        let content_disposition = field.content_disposition();
-        
-        if let Some(filename) = content_disposition.get_filename() {
-            // 验证文件扩展名
-            if let Err(e) = FileService::validate_file_extension(filename) {
-                return HttpResponse::BadRequest().json(serde_json::json!({
-                    "success": false,
-                    "message": format!("Invalid file type: {}", e)
-                }));
-            }
-
-            // 读取文件内容
-            let mut file_content = Vec::new();
-            while let Some(chunk) = field.try_next().await.unwrap_or(None) {
-                file_content.extend_from_slice(&chunk);
-            }
-
-            // 转换为字符串
-            let content_str = match String::from_utf8(file_content) {
-                Ok(content) => content,
-                Err(_) => {
-                    return HttpResponse::BadRequest().json(serde_json::json!({
-                        "success": false,
-                        "message": "File must be valid UTF-8 text"
-                    }));
-                }
-            };
-
-            // 验证文件大小 (5MB limit)
-            if let Err(e) = FileService::validate_file_size(&content_str, 5) {
-                return HttpResponse::BadRequest().json(serde_json::json!({
-                    "success": false,
-                    "message": format!("File too large: {}", e)
-                }));
-            }
-
-            // 解析Markdown文件
-            match FileService::parse_markdown_file(&content_str) {
-                Ok(markdown_file) => {
-                    // 插入到数据库
-                    match sqlx::query(
-                        "INSERT INTO articles (title, content, created_at, updated_at) VALUES (?, ?, datetime('now'), datetime('now'))"
-                    )
-                    .bind(&markdown_file.title)
-                    .bind(&markdown_file.content)
-                    .execute(_pool.get_ref())
-                    .await {
-                        Ok(result) => {
-                            let article_id = result.last_insert_rowid();
-                            return HttpResponse::Ok().json(serde_json::json!({
-                                "success": true,
-                                "message": "Article imported successfully",
-                                "article_id": article_id,
-                                "title": markdown_file.title
-                            }));
-                        },
-                        Err(e) => {
-                            error!("Failed to insert article: {}", e);
-                            return HttpResponse::InternalServerError().json(serde_json::json!({
-                                "success": false,
-                                "message": "Failed to save article to database"
-                            }));
+    // Purge articles kept in the trash longer than the retention setting, every N hours; 0 disables
+    let trash_purge_interval = std::env::var("TRASH_PURGE_INTERVAL_HOURS")
+        .unwrap_or_else(|_| "6".to_string())
+        .parse::<u64>()
+        .unwrap_or(6);
+    if trash_purge_interval > 0 {
+        let purge_state = app_state.clone();
+        let purge_pool = pool.clone();
+        let mut shutdown = shutdown_rx.clone();
+        background_tasks.push(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(trash_purge_interval * 3600));
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        match purge_expired_trash(&purge_state, &purge_pool).await {
+                            Ok(0) => {}
+                            Ok(purged) => info!("Purged {} article(s) from the trash", purged),
+                            Err(e) => error!("Trash purge failed: {}", e),
                         }
                     }
-                },
-                Err(e) => {
-                    return HttpResponse::BadRequest().json(serde_json::json!({
-                        "success": false,
-                        "message": format!("Failed to parse markdown file: {}", e)
-                    }));
+                    _ = shutdown.changed() => break,
                 }
             }
-        }
-    }
-
-    HttpResponse::BadRequest().json(serde_json::json!({
-        "success": false,
-        "message": "No valid file found in upload"
-    }))
-}
-
-// 文章导出功能
-async fn admin_export_article(
-    path: web::Path<i64>,
-    _pool: web::Data<SqlitePool>,
-    session: Session
-) -> impl Responder {
-    // 检查session中的登录状态
-    if session.get::<String>("username").unwrap_or(None).is_none() {
-        return HttpResponse::Unauthorized().json(serde_json::json!({
-            "error": "Unauthorized"
         }));
     }
 
-    let article_id = path.into_inner();
-    
-    // 从数据库获取文章
-    match sqlx::query_as::<_, models::Article>(
-        "SELECT id, title, content, author_id, created_at, updated_at FROM articles WHERE id = ?"
-    )
-    .bind(article_id)
-    .fetch_one(_pool.get_ref())
-    .await {
-        Ok(article) => {
-            // 生成Markdown导出内容
-            let markdown_content = match FileService::generate_markdown_export(&article) {
-                Ok(content) => content,
-                Err(e) => {
-                    error!("Failed to generate markdown export: {}", e);
-                    return HttpResponse::InternalServerError().json(serde_json::json!({
-                        "success": false,
-                        "message": format!("Export generation failed: {}", e)
-                    }));
-                }
-            };
-            
-            // 生成安全的文件名
-            let safe_filename = FileService::sanitize_filename_with_fallback(&article.title);
-            let filename = format!("{}.md", safe_filename);
-            
-            // 返回文件下载响应
-            HttpResponse::Ok()
-                .content_type("text/markdown; charset=utf-8")
-                .append_header(("Content-Disposition", format!("attachment; filename=\"{}\"", filename)))
-                .body(markdown_content)
-        },
-        Err(e) => {
-            error!("Failed to fetch article for export: {}", e);
-            HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Article not found"
-            }))
-        }
-    }
-}
-
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    // Initialize logging
-    env_logger::Builder::new()
-        .filter_level(log::LevelFilter::Info)
-        .init();
-    
-    info!("Starting blog server...");
-    
-    // Initialize template system
-    let mut tera = match Tera::new("templates/**/*") {
-        Ok(t) => t,
-        Err(e) => {
-            error!("Template parsing error: {}", e);
-            ::std::process::exit(1);
-        }
-    };
-    tera.autoescape_on(vec!["html", ".html", ".htm"]);
-    
-    // Create application state with optimized markdown service
-    let cache_ttl = std::env::var("MARKDOWN_CACHE_TTL")
-        .unwrap_or_else(|_| "3600".to_string())
+    // Seconds to wait for in-flight requests to finish after SIGTERM/SIGINT
+    let shutdown_timeout = std::env::var("SHUTDOWN_TIMEOUT")
+        .unwrap_or_else(|_| "30".to_string())
         .parse::<u64>()
-        .unwrap_or(3600);
-    
-    let max_cache_size = std::env::var("MARKDOWN_MAX_CACHE_SIZE")
-        .unwrap_or_else(|_| "1000".to_string())
-        .parse::<usize>()
-        .unwrap_or(1000);
-    
-    let max_content_size = std::env::var("MARKDOWN_MAX_CONTENT_SIZE")
-        .unwrap_or_else(|_| "1048576".to_string())
-        .parse::<usize>()
-        .unwrap_or(1024 * 1024);
-    
-    let markdown_service = MarkdownService::with_cache_config(
-        std::time::Duration::from_secs(cache_ttl),
-        max_cache_size,
-        max_content_size,
-    );
-    
-    info!("Markdown service configured with cache TTL: {}s, max cache size: {}, max content size: {} bytes", 
-          cache_ttl, max_cache_size, max_content_size);
-    
-    let app_state = web::Data::new(AppState {
-        template: tera,
-        markdown_service,
-    });
-    
-    // Start periodic cache optimization task
-    let app_state_for_task = app_state.clone();
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(std::time::Duration::from_secs(1800)); // Every 30 minutes
-        loop {
-            interval.tick().await;
-            log::info!("Running periodic cache optimization...");
-            app_state_for_task.markdown_service.optimize_cache();
-        }
-    });
-    
-    // Initialize database
-    let pool = match init_db().await {
-        Ok(pool) => pool,
-        Err(e) => {
-            error!("Failed to initialize database: {}", e);
-            std::process::exit(1);
-        }
-    };
+        .unwrap_or(30);
 
     // Start HTTP server
     let secret_key = actix_web::cookie::Key::generate(); // 生成固定密钥
-    HttpServer::new(move || {
+    let server_state = app_state.clone();
+    let server_pool = pool.clone();
+    let mut server = HttpServer::new(move || {
         
         App::new()
-            .app_data(app_state.clone())
-            .app_data(web::Data::new(pool.clone()))
+            .app_data(server_state.clone())
+            .app_data(web::Data::new(server_pool.clone()))
+            .wrap(from_fn(middleware::require_password_change))
+            .wrap(from_fn(middleware::cache_control))
+            .wrap(from_fn(middleware::template_globals))
+            .wrap(from_fn(middleware::request_timeout))
+            .wrap(from_fn(middleware::normalize_url))
             .wrap(Logger::default())
             .wrap(
                 SessionMiddleware::builder(
@@ -1150,39 +201,41 @@ async fn main() -> std::io::Result<()> {
                 .cookie_http_only(true)
                 .build()
             )
-            .route("/", web::get().to(index))
-            .route("/admin", web::get().to(admin_dashboard))
-            .route("/post/{id}", web::get().to(post_detail))
-            .route("/about", web::get().to(about))
-            .route("/login", web::get().to(login_page))
-            .route("/login", web::post().to(login))
-            .route("/logout", web::post().to(logout))
-            .route("/articles", web::get().to(get_articles))
-            .route("/articles/{id}", web::get().to(get_article))
-            .route("/articles", web::post().to(create_article))
-            .route("/articles/{id}", web::put().to(update_article))
-            .route("/articles/{id}", web::delete().to(delete_article))
-            .route("/admin/articles", web::get().to(admin_articles))
-            .route("/admin/articles", web::post().to(admin_create_article))
-            .route("/admin/articles/{id}/edit", web::get().to(admin_edit_article))
-            .route("/admin/articles/{id}", web::put().to(admin_update_article))
-            .route("/admin/articles/{id}", web::delete().to(delete_article))
-            .route("/admin/articles/preview", web::post().to(admin_preview_markdown))
-            .route("/admin/articles/import", web::post().to(admin_import_article))
-            .route("/admin/articles/{id}/export", web::get().to(admin_export_article))
-            .route("/admin/about/edit", web::get().to(admin_about_edit))
-            .route("/admin/about", web::put().to(admin_update_about))
-            .route("/admin/password", web::get().to(admin_password_settings))
-            .route("/admin/password/change", web::post().to(admin_change_password))
-            .route("/admin/security-question", web::post().to(admin_set_security_question))
-            .route("/admin/performance", web::get().to(admin_performance_stats))
-            .route("/admin/cache/clear", web::post().to(admin_cache_clear))
-            .route("/admin/cache/optimize", web::post().to(admin_cache_optimize))
-            .route("/reset-password", web::get().to(reset_password_page))
-            .route("/reset-password", web::post().to(reset_password))
-            .route("/api/security-question", web::get().to(get_security_question))
+            .configure(handlers::configure)
+            .configure(|cfg| server_state.plugins.configure(cfg))
     })
-    .bind("0.0.0.0:8080")?
-    .run()
-    .await
+    // Actix stops accepting on SIGTERM/SIGINT and drains in-flight requests for this long
+    .shutdown_timeout(shutdown_timeout);
+
+    for listener in listeners {
+        server = match listener {
+            listen::Listener::Tcp(l) => server.listen(l)?,
+            #[cfg(unix)]
+            listen::Listener::Unix(l) => server.listen_uds(l)?,
+        };
+    }
+    let server = server.run();
+
+    let result = server.await;
+
+    // Server has drained; stop background tasks before tearing down shared state
+    info!("HTTP server stopped, shutting down background tasks...");
+    let _ = shutdown_tx.send(true);
+    // Events published by the last requests are still delivered
+    app_state.events.close();
+    background_tasks.extend(event_workers);
+    for task in background_tasks {
+        match tokio::time::timeout(std::time::Duration::from_secs(10), task).await {
+            Ok(Err(e)) => error!("Background task failed during shutdown: {}", e),
+            Err(_) => error!("Background task did not stop within 10s"),
+            Ok(Ok(())) => {}
+        }
+    }
+
+    // Flush final metrics to the log and close the pool so SQLite checkpoints its journal
+    app_state.markdown_service.log_performance_stats();
+    pool.close().await;
+    info!("Shutdown complete");
+
+    result
 }
@@ -0,0 +1,160 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Server-level request counters, complementing the render metrics kept by `MarkdownService`
+#[derive(Debug, Default)]
+pub struct RequestMetrics {
+    total_requests: AtomicU64,
+    timed_out_requests: AtomicU64,
+    slow_renders: AtomicU64,
+    slow_queries: AtomicU64,
+}
+
+impl RequestMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_request(&self) {
+        self.total_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_timeout(&self) {
+        self.timed_out_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn total_requests(&self) -> u64 {
+        self.total_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn timed_out_requests(&self) -> u64 {
+        self.timed_out_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn record_slow_render(&self) {
+        self.slow_renders.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_slow_query(&self) {
+        self.slow_queries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn slow_renders(&self) -> u64 {
+        self.slow_renders.load(Ordering::Relaxed)
+    }
+
+    pub fn slow_queries(&self) -> u64 {
+        self.slow_queries.load(Ordering::Relaxed)
+    }
+}
+
+/// Cumulative counters read at one point in time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CounterReading {
+    pub requests: u64,
+    pub timeouts: u64,
+    pub renders: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub avg_render_time_ms: f64,
+}
+
+/// Activity during one snapshot interval, ending at `timestamp` (Unix seconds)
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MetricsSnapshot {
+    pub timestamp: i64,
+    pub requests: u64,
+    pub timeouts: u64,
+    pub renders: u64,
+    /// Percentage of cache lookups that hit; None when nothing was looked up
+    pub cache_hit_rate: Option<f64>,
+    pub avg_render_time_ms: f64,
+}
+
+/// Ring buffer of periodic snapshots for graphing recent history
+#[derive(Debug)]
+pub struct MetricsHistory {
+    capacity: usize,
+    state: Mutex<HistoryState>,
+}
+
+#[derive(Debug, Default)]
+struct HistoryState {
+    last: Option<CounterReading>,
+    snapshots: VecDeque<MetricsSnapshot>,
+}
+
+impl MetricsHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Mutex::new(HistoryState::default()),
+        }
+    }
+
+    /// Store the change since the previous reading; the first reading only sets the baseline
+    pub fn record(&self, reading: CounterReading, timestamp: i64) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(last) = state.last {
+            let hits = reading.cache_hits.saturating_sub(last.cache_hits);
+            let lookups = hits + reading.cache_misses.saturating_sub(last.cache_misses);
+            state.snapshots.push_back(MetricsSnapshot {
+                timestamp,
+                requests: reading.requests.saturating_sub(last.requests),
+                timeouts: reading.timeouts.saturating_sub(last.timeouts),
+                renders: reading.renders.saturating_sub(last.renders),
+                cache_hit_rate: (lookups > 0).then(|| hits as f64 * 100.0 / lookups as f64),
+                avg_render_time_ms: reading.avg_render_time_ms,
+            });
+            while state.snapshots.len() > self.capacity {
+                state.snapshots.pop_front();
+            }
+        }
+        state.last = Some(reading);
+    }
+
+    /// Snapshots oldest first
+    pub fn snapshots(&self) -> Vec<MetricsSnapshot> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.snapshots.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(requests: u64, hits: u64, misses: u64) -> CounterReading {
+        CounterReading { requests, cache_hits: hits, cache_misses: misses, renders: misses, ..Default::default() }
+    }
+
+    #[test]
+    fn test_history_records_deltas() {
+        let history = MetricsHistory::new(10);
+        history.record(reading(5, 1, 1), 100);
+        assert!(history.snapshots().is_empty());
+
+        history.record(reading(15, 4, 2), 400);
+        history.record(reading(15, 4, 2), 700);
+        let snapshots = history.snapshots();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].timestamp, 400);
+        assert_eq!(snapshots[0].requests, 10);
+        assert_eq!(snapshots[0].renders, 1);
+        assert_eq!(snapshots[0].cache_hit_rate, Some(75.0));
+        assert_eq!(snapshots[1].requests, 0);
+        assert_eq!(snapshots[1].cache_hit_rate, None);
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let history = MetricsHistory::new(3);
+        for i in 0..10 {
+            history.record(reading(i, 0, 0), i as i64);
+        }
+        let timestamps: Vec<i64> = history.snapshots().iter().map(|s| s.timestamp).collect();
+        assert_eq!(timestamps, vec![7, 8, 9]);
+    }
+}
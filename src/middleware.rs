@@ -0,0 +1,327 @@
+use actix_session::SessionExt;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::{ErrorUnauthorized, InternalError};
+use actix_web::middleware::Next;
+use actix_web::http::Method;
+use actix_web::http::header::{HeaderValue, ACCEPT, CACHE_CONTROL, CONTENT_TYPE, VARY};
+use actix_web::{web, Error, FromRequest, HttpMessage, HttpRequest, HttpResponse};
+use std::future::{ready, Ready};
+use crate::services::theme::{Theme, THEME_COOKIE};
+use log::warn;
+
+use crate::app::AppState;
+
+/// Abort handlers that run past `AppState::request_timeout` and answer 503 instead.
+///
+/// The deadline is only observed at await points, so CPU-bound work that never
+/// yields (e.g. a synchronous markdown render) still runs to completion first.
+///
+/// The 503 is returned as an error response: the `HttpRequest` must not be cloned
+/// before routing (actix needs unique access to fill in the match info).
+pub async fn request_timeout<B: MessageBody + 'static>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<B>, Error> {
+    let state = req.app_data::<web::Data<AppState>>().cloned();
+    let Some(state) = state else {
+        return next.call(req).await;
+    };
+
+    state.request_metrics.record_request();
+    if state.request_timeout.is_zero() {
+        return next.call(req).await;
+    }
+
+    let path = req.path().to_string();
+    match tokio::time::timeout(state.request_timeout, next.call(req)).await {
+        Ok(result) => result,
+        Err(_) => {
+            state.request_metrics.record_timeout();
+            warn!("Request to {} exceeded {:?}, aborting", path, state.request_timeout);
+            let response = HttpResponse::ServiceUnavailable()
+                .insert_header(("Retry-After", "5"))
+                .body("Request timed out");
+            Err(InternalError::from_response("Request timed out", response).into())
+        }
+    }
+}
+
+/// Add the `Cache-Control` header configured for the route (`CACHE_CONTROL_RULES`)
+/// to successful GET and HEAD responses. Handlers that set their own header,
+/// such as the API and media routes, keep it.
+pub async fn cache_control<B: MessageBody + 'static>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<B>, Error> {
+    let value = match req.app_data::<web::Data<AppState>>() {
+        Some(state) if matches!(*req.method(), Method::GET | Method::HEAD) => {
+            state.cache_policy.for_path(req.path()).cloned()
+        }
+        _ => None,
+    };
+    let mut res = next.call(req).await?;
+    if let Some(value) = value
+        && res.status().is_success()
+        && !res.headers().contains_key(CACHE_CONTROL)
+    {
+        res.headers_mut().insert(CACHE_CONTROL, value);
+    }
+    Ok(res)
+}
+
+/// Per-request values read by the `site()` template function
+#[derive(Debug, Clone, Default)]
+pub struct RequestGlobals {
+    /// Username signed in; `None` for visitors
+    pub user: Option<String>,
+    /// Theme chosen by the reader
+    pub theme: Theme,
+    /// Path and query of the request, for forms that return to the page
+    pub path: String,
+}
+
+tokio::task_local! {
+    pub static REQUEST_GLOBALS: RequestGlobals;
+}
+
+/// Make the signed-in user and the reader's theme visible to templates rendered
+/// while handling the request. HTML depends on the theme cookie, so it is sent
+/// with `Vary: Cookie` to keep caches from serving one reader's theme to another.
+pub async fn template_globals<B: MessageBody + 'static>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<B>, Error> {
+    let globals = RequestGlobals {
+        user: req.get_session().get::<String>("username").unwrap_or(None),
+        theme: Theme::from_cookie(req.cookie(THEME_COOKIE).as_ref().map(|c| c.value())),
+        path: req.uri().path_and_query().map_or("/", |p| p.as_str()).to_string(),
+    };
+    let mut res = REQUEST_GLOBALS.scope(globals, next.call(req)).await?;
+    let is_html = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"));
+    if is_html {
+        res.headers_mut().append(VARY, HeaderValue::from_static("Cookie"));
+    }
+    Ok(res)
+}
+
+/// Session key set at login while the account still has its initial password
+pub const MUST_CHANGE_PASSWORD: &str = "must_change_password";
+
+/// What a session that still has to change its password gets instead of the route
+#[derive(Debug, PartialEq, Eq)]
+enum PasswordGate {
+    /// Admin pages send the user to the password settings page
+    Redirect,
+    /// API writes are refused; the editor cannot follow a redirect
+    Refuse,
+}
+
+fn password_gate(method: &Method, path: &str) -> Option<PasswordGate> {
+    if path.starts_with("/admin") {
+        return (!path.starts_with("/admin/password")).then_some(PasswordGate::Redirect);
+    }
+    let writes = !matches!(*method, Method::GET | Method::HEAD);
+    let api = path == "/articles" || path.starts_with("/articles/") || path.starts_with("/api/");
+    (writes && api).then_some(PasswordGate::Refuse)
+}
+
+/// Keep signed-in users on the password settings page until they replace the
+/// initial password, and refuse their writes through `/articles` and `/api`.
+/// Public pages and API reads are left alone.
+pub async fn require_password_change<B: MessageBody + 'static>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let gate = password_gate(req.method(), req.path()).filter(|_| {
+        req.get_session()
+            .get::<bool>(MUST_CHANGE_PASSWORD)
+            .unwrap_or(None)
+            .unwrap_or(false)
+    });
+
+    let response = match gate {
+        Some(PasswordGate::Redirect) => HttpResponse::Found()
+            .append_header(("Location", "/admin/password"))
+            .finish(),
+        Some(PasswordGate::Refuse) => HttpResponse::Forbidden().json("Password change required"),
+        None => return next.call(req).await.map(ServiceResponse::map_into_left_body),
+    };
+    Ok(req.into_response(response).map_into_right_body())
+}
+
+/// Username of the signed-in user, put in the request extensions by `require_login`
+#[derive(Debug, Clone)]
+pub struct CurrentUser(pub String);
+
+impl FromRequest for CurrentUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        // 只在 require_login 之后的路由里可用
+        ready(req.extensions().get::<CurrentUser>().cloned().ok_or_else(|| ErrorUnauthorized("未登录")))
+    }
+}
+
+/// Turn away anonymous requests to the admin scope: pages redirect to `/login`,
+/// while writes and requests asking for JSON get a 401 the admin scripts can show.
+pub async fn require_login<B: MessageBody + 'static>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    if let Some(username) = req.get_session().get::<String>("username").unwrap_or(None) {
+        req.extensions_mut().insert(CurrentUser(username));
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    }
+
+    let wants_json = req
+        .headers()
+        .get(ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("application/json"));
+    let response = if matches!(*req.method(), Method::GET | Method::HEAD) && !wants_json {
+        HttpResponse::Found().append_header(("Location", "/login")).finish()
+    } else {
+        HttpResponse::Unauthorized().json(serde_json::json!({
+            "success": false,
+            "message": "未登录"
+        }))
+    };
+    Ok(req.into_response(response).map_into_right_body())
+}
+
+/// Paths whose remaining segments are slugs, matched case-insensitively. Other
+/// paths keep their case: short link codes and media names are case-sensitive.
+const SLUG_PREFIXES: &[&str] = &["/post/"];
+
+/// Canonical form of a request path, or `None` if it already is one: runs of
+/// slashes collapse to one, a trailing slash is dropped and slug paths are
+/// lowercased, except for percent escapes (`%E7` and `%e7` are the same byte)
+pub fn normalize_path(path: &str) -> Option<String> {
+    let mut normalized = String::with_capacity(path.len());
+    for ch in path.chars() {
+        if ch == '/' && normalized.ends_with('/') {
+            continue;
+        }
+        normalized.push(ch);
+    }
+    if normalized.len() > 1 && normalized.ends_with('/') {
+        normalized.pop();
+    }
+    if SLUG_PREFIXES.iter().any(|prefix| normalized.starts_with(prefix)) {
+        let mut escape = 0;
+        normalized = normalized
+            .chars()
+            .map(|ch| {
+                if ch == '%' {
+                    escape = 2;
+                    ch
+                } else if escape > 0 {
+                    escape -= 1;
+                    ch
+                } else {
+                    ch.to_ascii_lowercase()
+                }
+            })
+            .collect();
+    }
+    (normalized != path).then_some(normalized)
+}
+
+/// 301 GET and HEAD requests for non-canonical paths (`/post/5/`, `//about`) to the
+/// canonical URL so each page is indexed once. Other methods pass through, as a
+/// redirect would drop their body.
+pub async fn normalize_url<B: MessageBody + 'static>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let redirect = matches!(*req.method(), Method::GET | Method::HEAD)
+        .then(|| normalize_path(req.path()))
+        .flatten();
+
+    if let Some(path) = redirect {
+        let location = match req.query_string() {
+            "" => path,
+            query => format!("{}?{}", path, query),
+        };
+        let response = HttpResponse::MovedPermanently()
+            .append_header(("Location", location))
+            .finish();
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_session::{Session, SessionMiddleware};
+    use actix_session::storage::CookieSessionStore;
+    use actix_web::cookie::Key;
+    use actix_web::middleware::from_fn;
+    use actix_web::App;
+    use actix_web::test::{call_service, init_service, TestRequest};
+
+    #[test]
+    fn test_password_gate() {
+        assert_eq!(password_gate(&Method::GET, "/admin"), Some(PasswordGate::Redirect));
+        assert_eq!(password_gate(&Method::POST, "/admin/articles"), Some(PasswordGate::Redirect));
+        assert_eq!(password_gate(&Method::GET, "/admin/password"), None);
+        assert_eq!(password_gate(&Method::POST, "/articles"), Some(PasswordGate::Refuse));
+        assert_eq!(password_gate(&Method::DELETE, "/articles/3"), Some(PasswordGate::Refuse));
+        assert_eq!(password_gate(&Method::POST, "/api/v1/articles/batch"), Some(PasswordGate::Refuse));
+        // Reads and public pages stay open
+        assert_eq!(password_gate(&Method::GET, "/articles/3"), None);
+        assert_eq!(password_gate(&Method::GET, "/api/v1/articles"), None);
+        assert_eq!(password_gate(&Method::POST, "/post/3/comments"), None);
+    }
+
+    #[actix_web::test]
+    async fn test_password_change_refuses_api_writes() {
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(require_password_change))
+                .wrap(SessionMiddleware::new(CookieSessionStore::default(), Key::generate()))
+                .route("/login", web::get().to(|session: Session| async move {
+                    session.insert(MUST_CHANGE_PASSWORD, true).unwrap();
+                    HttpResponse::Ok().finish()
+                }))
+                .route("/articles", web::get().to(HttpResponse::Ok))
+                .route("/articles", web::post().to(HttpResponse::Created)),
+        )
+        .await;
+
+        let res = call_service(&app, TestRequest::get().uri("/login").to_request()).await;
+        let cookie = res.response().cookies().next().unwrap().into_owned();
+
+        let req = TestRequest::post().uri("/articles").cookie(cookie.clone()).to_request();
+        assert_eq!(call_service(&app, req).await.status(), 403);
+        let req = TestRequest::get().uri("/articles").cookie(cookie).to_request();
+        assert_eq!(call_service(&app, req).await.status(), 200);
+        // Sessions without the flag can still write
+        let req = TestRequest::post().uri("/articles").to_request();
+        assert_eq!(call_service(&app, req).await.status(), 201);
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/post/5/"), Some("/post/5".to_string()));
+        assert_eq!(normalize_path("//post//5"), Some("/post/5".to_string()));
+        assert_eq!(normalize_path("/post/My-Slug"), Some("/post/my-slug".to_string()));
+        assert_eq!(normalize_path("/about/"), Some("/about".to_string()));
+        assert_eq!(normalize_path("//"), Some("/".to_string()));
+        assert_eq!(normalize_path("/"), None);
+        assert_eq!(normalize_path("/post/5"), None);
+        assert_eq!(normalize_path("/post/%E7%BC%96-Go"), Some("/post/%E7%BC%96-go".to_string()));
+        assert_eq!(normalize_path("/post/%e7%bc%96"), None);
+        // Case-sensitive codes are left alone
+        assert_eq!(normalize_path("/s/AbC123"), None);
+        assert_eq!(normalize_path("/media/ABC.png/"), Some("/media/ABC.png".to_string()));
+    }
+}
@@ -1,10 +1,15 @@
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
+use sqlx::ConnectOptions;
+use sqlx::sqlite::SqliteConnectOptions;
+use std::str::FromStr;
 use log::error;
 use serde::{Serialize, Deserialize};
 // 使用String存储时间简化处理
 use bcrypt::{hash, verify, DEFAULT_COST};
 
 use sqlx::FromRow;
+use crate::services::pagination::Cursor;
+use crate::services::slugs;
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct User {
@@ -12,10 +17,20 @@ pub struct User {
     pub username: String,
     #[serde(skip_serializing)]
     pub password_hash: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
     pub created_at: String,
     pub security_question: Option<String>,
     #[serde(skip_serializing)]
     pub security_answer_hash: Option<String>,
+    #[serde(default, with = "crate::services::dates::rfc3339::option")]
+    pub last_login_at: Option<String>,
+    pub last_login_ip: Option<String>,
+    pub must_change_password: bool,
+    // 作者资料，显示在作者页和文章署名中
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -24,8 +39,51 @@ pub struct Article {
     pub title: String,
     pub content: String,
     pub author_id: Option<i64>,
+    #[serde(with = "crate::services::dates::rfc3339")]
     pub created_at: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
     pub updated_at: String,
+    pub meta_description: Option<String>,
+    pub meta_keywords: Option<String>,
+    // 不希望被搜索引擎收录
+    pub noindex: bool,
+    // auto / ltr / rtl
+    pub text_direction: String,
+    // 前言中的渲染选项(JSON)，见 services::render_options
+    pub render_options: Option<String>,
+    // 移入回收站的时间
+    #[serde(default, with = "crate::services::dates::rfc3339::option")]
+    pub deleted_at: Option<String>,
+    // 转载文章的原文地址，页面 canonical 指向它
+    pub canonical_url: Option<String>,
+    // 所属分类，可为空
+    pub category_id: Option<i64>,
+    // draft / published；草稿只在后台可见
+    pub status: String,
+    // 最近一次发布的时间，草稿为空
+    #[serde(default, with = "crate::services::dates::rfc3339::option")]
+    pub published_at: Option<String>,
+    // /post/{slug} 中的部分，创建后由 assign_missing_slugs 填写
+    #[serde(default)]
+    pub slug: Option<String>,
+}
+
+pub const STATUS_DRAFT: &str = "draft";
+pub const STATUS_PUBLISHED: &str = "published";
+
+// 只需标题和正文时的文章行，如查找替换和旧版 /articles 接口
+#[derive(Debug, FromRow)]
+pub struct ArticleContent {
+    pub id: i64,
+    pub title: String,
+    pub content: String,
+}
+
+// 旧的客户端按 [id, title, content] 数组解析，序列化时保持这个格式
+impl Serialize for ArticleContent {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.id, &self.title, &self.content).serialize(serializer)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -33,23 +91,509 @@ pub struct About {
     pub id: i64,
     pub title: String,
     pub content: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Comment {
+    pub id: i64,
+    pub article_id: i64,
+    // 回复的评论ID，顶层评论为None
+    pub parent_id: Option<i64>,
+    pub author_name: String,
+    #[serde(skip_serializing)]
+    pub author_email: Option<String>,
+    pub content: String,
+    // pending / approved / spam
+    pub status: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub created_at: String,
+    // 有人回复时是否邮件通知作者
+    pub notify_replies: bool,
+}
+
+// 新评论的输入
+pub struct NewComment<'a> {
+    pub article_id: i64,
+    pub parent_id: Option<i64>,
+    pub author_name: &'a str,
+    pub author_email: Option<&'a str>,
+    pub content: &'a str,
+    pub status: &'a str,
+    pub notify_replies: bool,
+}
+
+// 新文章的输入，用于批量创建
+pub struct NewArticle<'a> {
+    pub title: &'a str,
+    pub content: &'a str,
+    pub meta_description: Option<String>,
+    pub meta_keywords: Option<String>,
+    pub noindex: bool,
+    pub text_direction: &'a str,
+    pub canonical_url: Option<String>,
+    pub category_id: Option<i64>,
+    pub status: &'a str,
+    pub tags: Vec<String>,
+    /// 正文中链接到的其他文章
+    pub links: Vec<i64>,
+}
+
+// 后台审核列表中的评论，附带文章标题和回复数
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ModerationComment {
+    pub id: i64,
+    pub article_id: i64,
+    pub article_title: Option<String>,
+    pub parent_id: Option<i64>,
+    pub author_name: String,
+    pub author_email: Option<String>,
+    pub content: String,
+    pub status: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub created_at: String,
+    pub reply_count: i64,
+}
+
+// 可复用的Markdown片段，正文中以 {{snippet:name}} 引用
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Snippet {
+    pub id: i64,
+    pub name: String,
+    pub content: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub updated_at: String,
+}
+
+// 文章短链接 /s/{code}
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ShortLink {
+    pub id: i64,
+    pub code: String,
+    pub article_id: i64,
+    pub clicks: i64,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub created_at: String,
+}
+
+// 文章某次保存时的标题和正文
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ArticleRevision {
+    pub id: i64,
+    pub article_id: i64,
+    pub title: String,
+    pub content: String,
+    pub author: Option<String>,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub created_at: String,
+}
+
+// 上传的文件，保存在 MEDIA_DIR 中，通过 /media/{stored_name} 访问
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Media {
+    pub id: i64,
+    pub stored_name: String,
+    pub original_name: String,
+    pub content_type: String,
+    pub size: i64,
+    pub article_id: Option<i64>,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub created_at: String,
+}
+
+// 仪表盘统计；created_at 在未来的文章为定时发布
+#[derive(Debug, Default, Serialize, FromRow)]
+pub struct ContentCounts {
+    pub published: i64,
+    pub scheduled: i64,
+    pub drafts: i64,
+    pub trashed: i64,
+    pub pending_comments: i64,
+    pub spam_comments: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor: String,
+    pub action: String,
+    pub target: String,
+    pub detail: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub created_at: String,
+}
+
+// 固定到导航菜单的文章
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct NavItem {
+    pub article_id: i64,
+    pub label: String,
+    pub position: i64,
+}
+
+// 管理员保存的文章筛选条件，criteria 为 JSON
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct SavedFilter {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub criteria: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub created_at: String,
+}
+
+// 正在编辑某篇文章的管理员
+#[derive(Debug, Serialize, FromRow)]
+pub struct ActiveEditor {
+    pub username: String,
+    // 显示名，未设置时为用户名
+    pub name: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub last_seen: String,
+}
+
+// 搜索框的补全建议
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct SearchSuggestion {
+    pub id: i64,
+    pub title: String,
+}
+
+// 全文搜索结果；title_marked 和 snippet 中的匹配词由 search::MATCH_START / MATCH_END 包围
+#[derive(Debug, FromRow)]
+pub struct SearchResult {
+    pub id: i64,
+    pub slug: Option<String>,
+    pub title: String,
+    pub title_marked: String,
+    pub snippet: String,
+    pub created_at: String,
+}
+
+// 站点归档（export-site / import-site）中的用户；文章作者按用户名对应，换服务器后 id 可能不同
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SiteUser {
+    pub username: String,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub created_at: String,
+    // 以下三项仅在导出时指定 --with-passwords 才包含
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub security_question: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub security_answer_hash: Option<String>,
+}
+
+// 站点归档中的文章（包括回收站中的），保留原 id 以免 /post/{id} 链接失效
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SiteArticle {
+    pub id: i64,
+    pub title: String,
+    pub content: String,
+    // 作者用户名
+    pub author: Option<String>,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub created_at: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub updated_at: String,
+    pub meta_description: Option<String>,
+    pub meta_keywords: Option<String>,
+    pub noindex: bool,
+    pub text_direction: String,
+    #[serde(default, with = "crate::services::dates::rfc3339::option")]
+    pub deleted_at: Option<String>,
+    pub render_options: Option<String>,
+    // 旧版归档中没有此字段
+    #[serde(default)]
+    pub canonical_url: Option<String>,
+    // 旧版归档中没有此字段，导入时按标题生成
+    #[serde(default)]
+    pub slug: Option<String>,
+}
+
+// 站点归档中的独立页面（目前只有关于页）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SitePage {
+    pub title: String,
+    pub content: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SiteComment {
+    pub id: i64,
+    pub article_id: i64,
+    pub parent_id: Option<i64>,
+    pub author_name: String,
+    pub author_email: Option<String>,
+    pub content: String,
+    pub status: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub created_at: String,
+    pub notify_replies: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SiteSnippet {
+    pub name: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SiteContentTemplate {
+    pub name: String,
+    pub title_pattern: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SiteNavItem {
+    pub article_id: i64,
+    pub label: String,
+    pub position: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SiteShortLink {
+    pub code: String,
+    pub article_id: i64,
+    pub clicks: i64,
+}
+
+// 站点归档中的媒体记录，文件本身以 media/{stored_name} 存放在归档中
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct SiteMedia {
+    pub stored_name: String,
+    pub original_name: String,
+    pub content_type: String,
+    pub size: i64,
+    pub article_id: Option<i64>,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub created_at: String,
+}
+
+// 链接到某篇文章的其他文章
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Backlink {
+    pub id: i64,
+    pub title: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub created_at: String,
+}
+
+// 一次批量导入；rolled_back_at 非空表示已整批撤销
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ImportBatch {
+    pub id: i64,
+    pub created_by: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub created_at: String,
+    #[serde(default, with = "crate::services::dates::rfc3339::option")]
+    pub rolled_back_at: Option<String>,
+}
+
+// 导入批次中的一篇文章；文章被永久删除后 article_id 为空，标题保留导入时的
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ImportBatchItem {
+    pub batch_id: i64,
+    pub article_id: Option<i64>,
+    pub source_name: String,
+    pub title: String,
+    /// live、trashed 或 deleted
+    pub status: String,
+    /// 导入后是否又被编辑过
+    pub edited: bool,
+}
+
+// 回收站中的文章，最近移入的在前
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct TrashedArticle {
+    pub id: i64,
+    pub title: String,
+    pub status: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub created_at: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub deleted_at: String,
+}
+
+// 已永久删除的文章，访问时返回 410 而不是 404
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ArticleTombstone {
+    pub article_id: i64,
+    pub title: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
+    pub deleted_at: String,
+}
+
+// 来源统计：某篇文章来自某个来源站点的访问次数
+#[derive(Debug, Clone, FromRow)]
+pub struct ReferrerRow {
+    pub article_id: i64,
+    pub title: String,
+    pub host: String,
+    pub views: i64,
+}
+
+// 来源统计：某篇文章来自某个搜索词的访问次数
+#[derive(Debug, Clone, FromRow)]
+pub struct SearchQueryRow {
+    pub article_id: i64,
+    pub title: String,
+    pub engine: String,
+    pub query: String,
+    pub views: i64,
+}
+
+// 外链点击统计：某个外部链接的点击次数
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct OutboundClickRow {
+    pub url: String,
+    pub host: String,
+    pub clicks: i64,
+    // 出现该链接的文章数
+    pub articles: i64,
+}
+
+// 新建文章时可选的内容模板
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ContentTemplate {
+    pub id: i64,
+    pub name: String,
+    // 标题模式，支持 {date}/{year}/{month}/{day}/{week} 占位符
+    pub title_pattern: String,
+    pub content: String,
+    #[serde(with = "crate::services::dates::rfc3339")]
     pub updated_at: String,
 }
 
-pub async fn init_db() -> Result<SqlitePool, sqlx::Error> {
+// 文章分类，可以有上级分类
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Category {
+    pub id: i64,
+    pub name: String,
+    pub slug: String,
+    pub parent_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+}
+
+// 后台标签列表：标签及使用它的文章数（含草稿和回收站中的）
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct TagUsage {
+    pub id: i64,
+    pub name: String,
+    pub articles: i64,
+}
+
+// 文章的某个表情回应及其数量
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: i64,
+    // 当前会话是否已回应过
+    pub reacted: bool,
+}
+
+/// Open the database and create missing tables. Statements slower than
+/// `slow_query_threshold` are logged with their SQL; zero disables that log.
+pub async fn init_db(slow_query_threshold: std::time::Duration) -> Result<SqlitePool, sqlx::Error> {
     // Create absolute path to database file
     let db_path = "sqlite:./data/blog.db?mode=rwc";
 
-    
+    let mut options = SqliteConnectOptions::from_str(db_path)?;
+    options = if slow_query_threshold.is_zero() {
+        options.log_slow_statements(log::LevelFilter::Off, slow_query_threshold)
+    } else {
+        options.log_slow_statements(log::LevelFilter::Warn, slow_query_threshold)
+    };
+
     // Try to connect to database (will create if not exists)
-    let pool = match SqlitePool::connect(db_path).await {
+    let pool = match SqlitePool::connect_with(options).await {
         Ok(pool) => pool,
         Err(e) => {
             error!("Failed to connect to database: {}", e);
             return Err(e);
         }
     };
-    
+
+    create_schema(&pool).await?;
+
+    // Check if admin user exists, if not create one
+    let admin_exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE username = 'admin')"
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    if !admin_exists {
+        let admin = create_user(&pool, "admin", "admin").await?;
+        sqlx::query("UPDATE users SET must_change_password = 1 WHERE id = ?")
+            .bind(admin.id)
+            .execute(&pool)
+            .await?;
+        log::info!("Default admin user created with password 'admin'");
+    } else if let Ok(admin) = verify_user(&pool, "admin", "admin").await
+        && !admin.must_change_password
+    {
+        // 旧版本创建的默认账号同样强制修改密码
+        sqlx::query("UPDATE users SET must_change_password = 1 WHERE id = ?")
+            .bind(admin.id)
+            .execute(&pool)
+            .await?;
+    }
+
+    // 早期版本未记录作者，这些文章归属第一个用户
+    sqlx::query("UPDATE articles SET author_id = (SELECT MIN(id) FROM users) WHERE author_id IS NULL")
+        .execute(&pool)
+        .await?;
+
+    let pending: Vec<String> = sqlx::query_scalar(
+        "SELECT username FROM users WHERE must_change_password = 1"
+    )
+    .fetch_all(&pool)
+    .await?;
+    for username in pending {
+        log::warn!("************************************************************");
+        log::warn!("* User '{}' is still using its initial password.", username);
+        log::warn!("* Sign in and change it at /admin/password before exposing this site.");
+        log::warn!("************************************************************");
+    }
+
+    // Check if about content exists, if not create default one
+    let about_exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM about LIMIT 1)"
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    if !about_exists {
+        sqlx::query(
+            "INSERT INTO about (title, content) VALUES (?, ?)"
+        )
+        .bind("About My Blog")
+        .bind("This is a blog system built with Rust language and the Actix-web framework. This blog is a project for me to learn Rust, and I hope to gain a deeper understanding of Rust's web development capabilities through this project.")
+        .execute(&pool)
+        .await?;
+        log::info!("Default about content created");
+    }
+
+    Ok(pool)
+}
+
+// 建表并把旧版本的表结构和数据迁移到当前版本；可以重复执行
+pub async fn create_schema(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     // Create tables
     if let Err(e) = sqlx::query(
         r#"
@@ -62,7 +606,7 @@ pub async fn init_db() -> Result<SqlitePool, sqlx::Error> {
             security_answer_hash TEXT
         )
         "#
-    ).execute(&pool).await {
+    ).execute(pool).await {
         error!("Failed to create users table: {}", e);
         return Err(e);
     }
@@ -79,7 +623,7 @@ pub async fn init_db() -> Result<SqlitePool, sqlx::Error> {
             FOREIGN KEY(author_id) REFERENCES users(id)
         )
         "#
-    ).execute(&pool).await?;
+    ).execute(pool).await?;
 
     sqlx::query(
         r#"
@@ -90,47 +634,501 @@ pub async fn init_db() -> Result<SqlitePool, sqlx::Error> {
             updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
         )
         "#
-    ).execute(&pool).await?;
+    ).execute(pool).await?;
 
-    // Check if admin user exists, if not create one
-    let admin_exists: bool = sqlx::query_scalar(
-        "SELECT EXISTS(SELECT 1 FROM users WHERE username = 'admin')"
+    ensure_column(pool, "users", "last_login_at", "DATETIME").await?;
+    ensure_column(pool, "users", "last_login_ip", "TEXT").await?;
+    ensure_column(pool, "users", "must_change_password", "BOOLEAN NOT NULL DEFAULT 0").await?;
+    ensure_column(pool, "users", "display_name", "TEXT").await?;
+    ensure_column(pool, "users", "bio", "TEXT").await?;
+    ensure_column(pool, "users", "website", "TEXT").await?;
+    ensure_column(pool, "users", "avatar_url", "TEXT").await?;
+    ensure_column(pool, "articles", "meta_description", "TEXT").await?;
+    ensure_column(pool, "articles", "meta_keywords", "TEXT").await?;
+    ensure_column(pool, "articles", "noindex", "BOOLEAN NOT NULL DEFAULT 0").await?;
+    ensure_column(pool, "articles", "text_direction", "TEXT NOT NULL DEFAULT 'auto'").await?;
+    // 前言中的 math / toc / highlight_theme 等渲染选项(JSON)，为空表示使用站点默认
+    ensure_column(pool, "articles", "render_options", "TEXT").await?;
+    // 非空表示文章在回收站中，超过保留期后被永久删除
+    ensure_column(pool, "articles", "deleted_at", "DATETIME").await?;
+    // 转载文章的原文地址，为空时 canonical 指向本站
+    ensure_column(pool, "articles", "canonical_url", "TEXT").await?;
+    ensure_column(pool, "articles", "category_id", "INTEGER").await?;
+    // 草稿不出现在任何公开页面，发布后记录发布时间
+    ensure_column(pool, "articles", "status", "TEXT NOT NULL DEFAULT 'published'").await?;
+    ensure_column(pool, "articles", "published_at", "DATETIME").await?;
+    ensure_column(pool, "articles", "slug", "TEXT").await?;
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_articles_slug ON articles(slug)")
+        .execute(pool)
+        .await?;
+    // 直接发布的文章（包括导入的）以发表时间为发布时间
+    sqlx::query(
+        "CREATE TRIGGER IF NOT EXISTS articles_published_at AFTER INSERT ON articles \
+         WHEN new.status = 'published' AND new.published_at IS NULL BEGIN \
+         UPDATE articles SET published_at = new.created_at WHERE id = new.id; END"
     )
-    .fetch_one(&pool)
+    .execute(pool)
     .await?;
+    sqlx::query("UPDATE articles SET published_at = created_at WHERE status = 'published' AND published_at IS NULL")
+        .execute(pool)
+        .await?;
 
-    if !admin_exists {
-        let _ = create_user(&pool, "admin", "admin").await?;
-        log::info!("Default admin user created with password 'admin'");
-    }
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS login_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            ip TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(user_id) REFERENCES users(id)
+        )
+        "#
+    ).execute(pool).await?;
 
-    // Check if about content exists, if not create default one
-    let about_exists: bool = sqlx::query_scalar(
-        "SELECT EXISTS(SELECT 1 FROM about LIMIT 1)"
-    )
-    .fetch_one(&pool)
-    .await?;
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS reactions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            article_id INTEGER NOT NULL,
+            emoji TEXT NOT NULL,
+            session_key TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(article_id, emoji, session_key),
+            FOREIGN KEY(article_id) REFERENCES articles(id)
+        )
+        "#
+    ).execute(pool).await?;
 
-    if !about_exists {
-        sqlx::query(
-            "INSERT INTO about (title, content) VALUES (?, ?)"
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS comments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            article_id INTEGER NOT NULL,
+            parent_id INTEGER,
+            author_name TEXT NOT NULL,
+            author_email TEXT,
+            content TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(article_id) REFERENCES articles(id),
+            FOREIGN KEY(parent_id) REFERENCES comments(id)
         )
-        .bind("About My Blog")
-        .bind("This is a blog system built with Rust language and the Actix-web framework. This blog is a project for me to learn Rust, and I hope to gain a deeper understanding of Rust's web development capabilities through this project.")
-        .execute(&pool)
-        .await?;
-        log::info!("Default about content created");
-    }
+        "#
+    ).execute(pool).await?;
 
-    Ok(pool)
-}
+    ensure_column(pool, "comments", "notify_replies", "BOOLEAN NOT NULL DEFAULT 0").await?;
 
-pub async fn create_user(pool: &SqlitePool, username: &str, password: &str) -> Result<User, sqlx::Error> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS snippets (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            content TEXT NOT NULL,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+        "#
+    ).execute(pool).await?;
+
+    // 站点设置（键值对），由后台设置页面维护
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+        "#
+    ).execute(pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS content_templates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            title_pattern TEXT NOT NULL DEFAULT '',
+            content TEXT NOT NULL,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+        "#
+    ).execute(pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            actor TEXT NOT NULL,
+            action TEXT NOT NULL,
+            target TEXT NOT NULL DEFAULT '',
+            detail TEXT NOT NULL DEFAULT '',
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+        "#
+    ).execute(pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS short_links (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            code TEXT NOT NULL UNIQUE,
+            article_id INTEGER NOT NULL UNIQUE,
+            clicks INTEGER NOT NULL DEFAULT 0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(article_id) REFERENCES articles(id)
+        )
+        "#
+    ).execute(pool).await?;
+
+    // 导航菜单项，绑定到文章；文章移入回收站时一并移除
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS nav_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            article_id INTEGER NOT NULL UNIQUE,
+            label TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(article_id) REFERENCES articles(id)
+        )
+        "#
+    ).execute(pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS saved_filters (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            criteria TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(user_id, name),
+            FOREIGN KEY(user_id) REFERENCES users(id)
+        )
+        "#
+    ).execute(pool).await?;
+
+    // 文章之间的内部链接，保存文章时更新；目标文章可能尚不存在或已删除
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS article_links (
+            source_id INTEGER NOT NULL,
+            target_id INTEGER NOT NULL,
+            PRIMARY KEY(source_id, target_id),
+            FOREIGN KEY(source_id) REFERENCES articles(id)
+        )
+        "#
+    ).execute(pool).await?;
+
+    // 批量导入记录，用于查看导入结果和整批回滚
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS import_batches (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            created_by TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            rolled_back_at DATETIME
+        )
+        "#
+    ).execute(pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS import_batch_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            batch_id INTEGER NOT NULL,
+            article_id INTEGER,
+            source_name TEXT NOT NULL,
+            title TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(batch_id) REFERENCES import_batches(id),
+            FOREIGN KEY(article_id) REFERENCES articles(id)
+        )
+        "#
+    ).execute(pool).await?;
+
+    // 文章修订历史，每次保存一份快照
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS article_revisions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            article_id INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            content TEXT NOT NULL,
+            author TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(article_id) REFERENCES articles(id)
+        )
+        "#
+    ).execute(pool).await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_article_revisions_article ON article_revisions(article_id)")
+        .execute(pool)
+        .await?;
+
+    // 上传的文件；article_id 非空的是文章附件
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS media (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            stored_name TEXT NOT NULL UNIQUE,
+            original_name TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            article_id INTEGER,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(article_id) REFERENCES articles(id)
+        )
+        "#
+    ).execute(pool).await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_media_article ON media(article_id)")
+        .execute(pool)
+        .await?;
+
+    // 已删除文章的记录；文章 id 自增不复用，所以不会和新文章冲突
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS article_tombstones (
+            article_id INTEGER PRIMARY KEY,
+            title TEXT NOT NULL,
+            deleted_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+        "#
+    ).execute(pool).await?;
+
+    // 编辑页打开期间定时刷新 last_seen，用来提醒同时编辑同一篇文章的管理员
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS article_presence (
+            article_id INTEGER NOT NULL,
+            username TEXT NOT NULL,
+            last_seen DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY(article_id, username),
+            FOREIGN KEY(article_id) REFERENCES articles(id)
+        )
+        "#
+    ).execute(pool).await?;
+
+    // 标题修改前的 slug，旧链接跳转到文章当前的地址
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS article_slug_redirects (
+            slug TEXT PRIMARY KEY,
+            article_id INTEGER NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(article_id) REFERENCES articles(id)
+        )
+        "#
+    ).execute(pool).await?;
+
+    // 文章的外部来源访问，只保存来源域名和搜索词
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS page_views (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            article_id INTEGER NOT NULL,
+            referrer_host TEXT NOT NULL,
+            search_engine TEXT,
+            search_query TEXT,
+            viewed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(article_id) REFERENCES articles(id)
+        )
+        "#
+    ).execute(pool).await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_page_views_viewed_at ON page_views(viewed_at)")
+        .execute(pool)
+        .await?;
+
+    // 文章中外部链接的点击，经 /out 跳转时记录
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS outbound_clicks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            article_id INTEGER NOT NULL,
+            target_url TEXT NOT NULL,
+            target_host TEXT NOT NULL,
+            clicked_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(article_id) REFERENCES articles(id)
+        )
+        "#
+    ).execute(pool).await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_outbound_clicks_clicked_at ON outbound_clicks(clicked_at)")
+        .execute(pool)
+        .await?;
+
+    // 文章分类；删除分类时子分类移到其上级，文章变为未分类
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS categories (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            slug TEXT NOT NULL UNIQUE,
+            parent_id INTEGER,
+            FOREIGN KEY(parent_id) REFERENCES categories(id)
+        )
+        "#
+    ).execute(pool).await?;
+
+    // 文章标签，名称不区分大小写；没有文章使用的标签随时清除
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE
+        )
+        "#
+    ).execute(pool).await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS article_tags (
+            article_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY(article_id, tag_id),
+            FOREIGN KEY(article_id) REFERENCES articles(id),
+            FOREIGN KEY(tag_id) REFERENCES tags(id)
+        )
+        "#
+    ).execute(pool).await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_article_tags_tag ON article_tags(tag_id)")
+        .execute(pool)
+        .await?;
+
+    // 合并后不再存在的标签名，其页面跳转到合并进的标签
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS tag_redirects (
+            name TEXT PRIMARY KEY COLLATE NOCASE,
+            tag_id INTEGER NOT NULL,
+            FOREIGN KEY(tag_id) REFERENCES tags(id)
+        )
+        "#
+    ).execute(pool).await?;
+
+    // 标题和正文的全文索引（FTS5 外部内容表），由触发器与 articles 保持同步
+    let fts_exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = 'articles_fts')")
+        .fetch_one(pool)
+        .await?;
+    sqlx::query(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS articles_fts USING fts5(\
+         title, content, content = 'articles', content_rowid = 'id', tokenize = 'unicode61 remove_diacritics 2')"
+    )
+    .execute(pool)
+    .await?;
+    for trigger in [
+        "CREATE TRIGGER IF NOT EXISTS articles_fts_insert AFTER INSERT ON articles BEGIN \
+         INSERT INTO articles_fts (rowid, title, content) VALUES (new.id, new.title, new.content); END",
+        "CREATE TRIGGER IF NOT EXISTS articles_fts_delete AFTER DELETE ON articles BEGIN \
+         INSERT INTO articles_fts (articles_fts, rowid, title, content) VALUES ('delete', old.id, old.title, old.content); END",
+        "CREATE TRIGGER IF NOT EXISTS articles_fts_update AFTER UPDATE OF title, content ON articles BEGIN \
+         INSERT INTO articles_fts (articles_fts, rowid, title, content) VALUES ('delete', old.id, old.title, old.content); \
+         INSERT INTO articles_fts (rowid, title, content) VALUES (new.id, new.title, new.content); END",
+    ] {
+        sqlx::query(trigger).execute(pool).await?;
+    }
+    if !fts_exists {
+        // 索引已有文章
+        sqlx::query("INSERT INTO articles_fts (articles_fts) VALUES ('rebuild')")
+            .execute(pool)
+            .await?;
+    }
+
+    normalize_timestamps(pool).await?;
+    // 升级前的文章
+    assign_missing_slugs(pool).await?;
+    Ok(())
+}
+
+// 为已存在的表补充新增列（CREATE TABLE IF NOT EXISTS 不会修改旧表）
+async fn ensure_column(pool: &SqlitePool, table: &str, column: &str, definition: &str) -> Result<(), sqlx::Error> {
+    let exists: bool = sqlx::query_scalar(
+        &format!("SELECT EXISTS(SELECT 1 FROM pragma_table_info('{}') WHERE name = ?)", table)
+    )
+    .bind(column)
+    .fetch_one(pool)
+    .await?;
+
+    if !exists {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(pool)
+            .await?;
+        log::info!("Added column {}.{}", table, column);
+    }
+
+    Ok(())
+}
+
+// 时间一律以 UTC "YYYY-MM-DD HH:MM:SS" 存储（与 datetime('now') 相同，按文本比较即按时间比较）；
+// 早期版本和导入写入过带时区或本地格式的值，启动时统一改写
+const TIMESTAMP_COLUMNS: &[(&str, &str)] = &[
+    ("users", "created_at"),
+    ("users", "last_login_at"),
+    ("articles", "created_at"),
+    ("articles", "updated_at"),
+    ("articles", "deleted_at"),
+    ("articles", "published_at"),
+    ("about", "updated_at"),
+    ("login_history", "created_at"),
+    ("reactions", "created_at"),
+    ("comments", "created_at"),
+    ("snippets", "updated_at"),
+    ("short_links", "created_at"),
+    ("article_revisions", "created_at"),
+    ("media", "created_at"),
+    ("settings", "updated_at"),
+    ("audit_log", "created_at"),
+    ("nav_items", "created_at"),
+    ("saved_filters", "created_at"),
+    ("import_batches", "created_at"),
+    ("import_batches", "rolled_back_at"),
+    ("import_batch_items", "created_at"),
+    ("article_tombstones", "deleted_at"),
+    ("content_templates", "updated_at"),
+    ("page_views", "viewed_at"),
+    ("outbound_clicks", "clicked_at"),
+];
+
+async fn normalize_timestamps(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    for (table, column) in TIMESTAMP_COLUMNS {
+        let rows: Vec<(i64, String)> = sqlx::query_as(&format!(
+            "SELECT rowid, CAST({column} AS TEXT) FROM {table} WHERE {column} IS NOT NULL \
+             AND {column} NOT GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9] [0-9][0-9]:[0-9][0-9]:[0-9][0-9]'"
+        ))
+        .fetch_all(pool)
+        .await?;
+        if rows.is_empty() {
+            continue;
+        }
+
+        let mut rewritten = 0;
+        for (rowid, value) in rows {
+            match crate::services::dates::to_stored(&value) {
+                Some(stored) => {
+                    sqlx::query(&format!("UPDATE {table} SET {column} = ? WHERE rowid = ?"))
+                        .bind(stored)
+                        .bind(rowid)
+                        .execute(pool)
+                        .await?;
+                    rewritten += 1;
+                }
+                None => log::warn!("Unrecognised timestamp in {}.{} (rowid {}): {:?}", table, column, rowid, value),
+            }
+        }
+        if rewritten > 0 {
+            log::info!("Normalized {} timestamps in {}.{}", rewritten, table, column);
+        }
+    }
+    Ok(())
+}
+
+pub async fn create_user(pool: &SqlitePool, username: &str, password: &str) -> Result<User, sqlx::Error> {
     let password_hash = hash(password, DEFAULT_COST).map_err(|e| {
         sqlx::Error::Decode(Box::new(e))
     })?;
-    let user_id = sqlx::query_scalar(
-        "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id"
+    let (user_id, created_at): (i64, String) = sqlx::query_as(
+        "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id, created_at"
     )
     .bind(username)
     .bind(&password_hash)
@@ -141,9 +1139,16 @@ pub async fn create_user(pool: &SqlitePool, username: &str, password: &str) -> R
         id: user_id,
         username: username.to_string(),
         password_hash,
-        created_at: chrono::Local::now().to_string(),
+        created_at,
         security_question: None,
         security_answer_hash: None,
+        last_login_at: None,
+        last_login_ip: None,
+        must_change_password: false,
+        display_name: None,
+        bio: None,
+        website: None,
+        avatar_url: None,
     })
 }
 
@@ -168,7 +1173,7 @@ pub async fn update_user_password(pool: &SqlitePool, user_id: i64, new_password:
         sqlx::Error::Decode(Box::new(e))
     })?;
     
-    sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+    sqlx::query("UPDATE users SET password_hash = ?, must_change_password = 0 WHERE id = ?")
         .bind(password_hash)
         .bind(user_id)
         .execute(pool)
@@ -177,6 +1182,55 @@ pub async fn update_user_password(pool: &SqlitePool, user_id: i64, new_password:
     Ok(())
 }
 
+pub async fn get_user(pool: &SqlitePool, user_id: i64) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+// 未删除的文章
+pub async fn get_article(pool: &SqlitePool, article_id: i64) -> Result<Option<Article>, sqlx::Error> {
+    sqlx::query_as::<_, Article>("SELECT * FROM articles WHERE id = ? AND deleted_at IS NULL")
+        .bind(article_id)
+        .fetch_optional(pool)
+        .await
+}
+
+// 关于页面（取最新一条）
+pub async fn get_about(pool: &SqlitePool) -> Result<About, sqlx::Error> {
+    sqlx::query_as::<_, About>("SELECT id, title, content, updated_at FROM about ORDER BY id DESC LIMIT 1")
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn get_user_by_username(pool: &SqlitePool, username: &str) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = ?")
+        .bind(username)
+        .fetch_optional(pool)
+        .await
+}
+
+// 作者资料，空值表示清除
+pub struct ProfileUpdate<'a> {
+    pub display_name: Option<&'a str>,
+    pub bio: Option<&'a str>,
+    pub website: Option<&'a str>,
+    pub avatar_url: Option<&'a str>,
+}
+
+pub async fn update_user_profile(pool: &SqlitePool, user_id: i64, profile: &ProfileUpdate<'_>) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET display_name = ?, bio = ?, website = ?, avatar_url = ? WHERE id = ?")
+        .bind(profile.display_name)
+        .bind(profile.bio)
+        .bind(profile.website)
+        .bind(profile.avatar_url)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
 // 设置安全问题
 pub async fn set_security_question(pool: &SqlitePool, user_id: i64, question: &str, answer: &str) -> Result<(), sqlx::Error> {
     let answer_hash = hash(answer, DEFAULT_COST).map_err(|e| {
@@ -219,11 +1273,1698 @@ pub async fn reset_password_by_username(pool: &SqlitePool, username: &str, new_p
         sqlx::Error::Decode(Box::new(e))
     })?;
     
-    sqlx::query("UPDATE users SET password_hash = ? WHERE username = ?")
+    sqlx::query("UPDATE users SET password_hash = ?, must_change_password = 0 WHERE username = ?")
         .bind(password_hash)
         .bind(username)
         .execute(pool)
         .await?;
     
     Ok(())
-}
\ No newline at end of file
+}
+
+// 记录登录时间和IP，返回该IP是否是此用户首次使用
+pub async fn record_login(pool: &SqlitePool, user_id: i64, ip: &str) -> Result<bool, sqlx::Error> {
+    let seen_before: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM login_history WHERE user_id = ? AND ip = ?)"
+    )
+    .bind(user_id)
+    .bind(ip)
+    .fetch_one(pool)
+    .await?;
+
+    sqlx::query("INSERT INTO login_history (user_id, ip) VALUES (?, ?)")
+        .bind(user_id)
+        .bind(ip)
+        .execute(pool)
+        .await?;
+
+    sqlx::query("UPDATE users SET last_login_at = datetime('now'), last_login_ip = ? WHERE id = ?")
+        .bind(ip)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(!seen_before)
+}
+
+// 添加表情回应，同一会话对同一文章的同一表情只计一次；返回是否新增
+pub async fn add_reaction(pool: &SqlitePool, article_id: i64, emoji: &str, session_key: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT OR IGNORE INTO reactions (article_id, emoji, session_key) VALUES (?, ?, ?)"
+    )
+    .bind(article_id)
+    .bind(emoji)
+    .bind(session_key)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// 按允许的表情顺序返回回应数量，未被回应的表情计为0
+pub async fn get_reaction_counts(
+    pool: &SqlitePool,
+    article_id: i64,
+    allowed: &[String],
+    session_key: Option<&str>,
+) -> Result<Vec<ReactionCount>, sqlx::Error> {
+    let counts: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT emoji, COUNT(*) FROM reactions WHERE article_id = ? GROUP BY emoji"
+    )
+    .bind(article_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mine: Vec<String> = match session_key {
+        Some(key) => sqlx::query_scalar(
+            "SELECT emoji FROM reactions WHERE article_id = ? AND session_key = ?"
+        )
+        .bind(article_id)
+        .bind(key)
+        .fetch_all(pool)
+        .await?,
+        None => Vec::new(),
+    };
+
+    Ok(allowed
+        .iter()
+        .map(|emoji| ReactionCount {
+            emoji: emoji.clone(),
+            count: counts.iter().find(|(e, _)| e == emoji).map(|(_, c)| *c).unwrap_or(0),
+            reacted: mine.contains(emoji),
+        })
+        .collect())
+}
+
+// 创建评论；回复时父评论必须属于同一篇文章
+pub async fn create_comment(pool: &SqlitePool, comment: &NewComment<'_>) -> Result<Comment, sqlx::Error> {
+    if let Some(parent_id) = comment.parent_id {
+        let parent_ok: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM comments WHERE id = ? AND article_id = ?)"
+        )
+        .bind(parent_id)
+        .bind(comment.article_id)
+        .fetch_one(pool)
+        .await?;
+        if !parent_ok {
+            return Err(sqlx::Error::RowNotFound);
+        }
+    }
+
+    sqlx::query_as::<_, Comment>(
+        "INSERT INTO comments (article_id, parent_id, author_name, author_email, content, status, notify_replies) \
+         VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING *"
+    )
+    .bind(comment.article_id)
+    .bind(comment.parent_id)
+    .bind(comment.author_name)
+    .bind(comment.author_email)
+    .bind(comment.content)
+    .bind(comment.status)
+    .bind(comment.notify_replies)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn get_comment(pool: &SqlitePool, comment_id: i64) -> Result<Comment, sqlx::Error> {
+    sqlx::query_as::<_, Comment>("SELECT * FROM comments WHERE id = ?")
+        .bind(comment_id)
+        .fetch_one(pool)
+        .await
+}
+
+// 退订回复通知
+pub async fn disable_reply_notifications(pool: &SqlitePool, comment_id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE comments SET notify_replies = 0 WHERE id = ?")
+        .bind(comment_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// 获取文章下已通过审核的评论，按时间排序
+pub async fn get_approved_comments(pool: &SqlitePool, article_id: i64) -> Result<Vec<Comment>, sqlx::Error> {
+    sqlx::query_as::<_, Comment>(
+        "SELECT * FROM comments WHERE article_id = ? AND status = 'approved' ORDER BY created_at ASC, id ASC"
+    )
+    .bind(article_id)
+    .fetch_all(pool)
+    .await
+}
+
+// 审核列表：全部评论及其回复数，最新的在前
+pub async fn get_moderation_comments(pool: &SqlitePool) -> Result<Vec<ModerationComment>, sqlx::Error> {
+    sqlx::query_as::<_, ModerationComment>(
+        r#"
+        SELECT c.id, c.article_id, a.title AS article_title, c.parent_id, c.author_name,
+               c.author_email, c.content, c.status, c.created_at,
+               (SELECT COUNT(*) FROM comments r WHERE r.parent_id = c.id) AS reply_count
+        FROM comments c
+        LEFT JOIN articles a ON a.id = c.article_id
+        ORDER BY c.created_at DESC, c.id DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn set_comment_status(pool: &SqlitePool, comment_id: i64, status: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE comments SET status = ? WHERE id = ?")
+        .bind(status)
+        .bind(comment_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// 删除评论时回复提升到上一级，避免留下孤儿
+pub async fn delete_comment(pool: &SqlitePool, comment_id: i64) -> Result<bool, sqlx::Error> {
+    sqlx::query("UPDATE comments SET parent_id = (SELECT parent_id FROM comments WHERE id = ?) WHERE parent_id = ?")
+        .bind(comment_id)
+        .bind(comment_id)
+        .execute(pool)
+        .await?;
+    let result = sqlx::query("DELETE FROM comments WHERE id = ?")
+        .bind(comment_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_snippets(pool: &SqlitePool) -> Result<Vec<Snippet>, sqlx::Error> {
+    sqlx::query_as::<_, Snippet>("SELECT * FROM snippets ORDER BY name")
+        .fetch_all(pool)
+        .await
+}
+
+// 按名称新建或更新片段
+pub async fn save_snippet(pool: &SqlitePool, name: &str, content: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO snippets (name, content) VALUES (?, ?) \
+         ON CONFLICT(name) DO UPDATE SET content = excluded.content, updated_at = CURRENT_TIMESTAMP"
+    )
+    .bind(name)
+    .bind(content)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_settings(pool: &SqlitePool) -> Result<std::collections::HashMap<String, String>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, String)>("SELECT key, value FROM settings")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().collect())
+}
+
+// 在同一事务中保存多项设置
+pub async fn save_settings(pool: &SqlitePool, settings: &[(&str, String)]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for (key, value) in settings {
+        sqlx::query(
+            "INSERT INTO settings (key, value) VALUES (?, ?) \
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = CURRENT_TIMESTAMP"
+        )
+        .bind(key)
+        .bind(value)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+// 发布草稿并记录发布时间，返回标题；不是草稿（已发布、在回收站或不存在）时返回 None
+pub async fn publish_article(pool: &SqlitePool, article_id: i64) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "UPDATE articles SET status = 'published', published_at = datetime('now'), updated_at = datetime('now') \
+         WHERE id = ? AND status = 'draft' AND deleted_at IS NULL RETURNING title"
+    )
+    .bind(article_id)
+    .fetch_optional(pool)
+    .await
+}
+
+// slug 未被其他文章或其他文章的旧 slug 占用
+async fn slug_is_free(conn: &mut SqliteConnection, slug: &str, article_id: i64) -> Result<bool, sqlx::Error> {
+    let taken: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM articles WHERE slug = ?1 AND id != ?2) \
+         OR EXISTS(SELECT 1 FROM article_slug_redirects WHERE slug = ?1 AND article_id != ?2)"
+    )
+    .bind(slug)
+    .bind(article_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(!taken)
+}
+
+// 由标题生成的第一个可用 slug：title、title-2、title-3……
+async fn unique_slug(conn: &mut SqliteConnection, title: &str, article_id: i64) -> Result<String, sqlx::Error> {
+    let base = slugs::from_title(title);
+    let mut n = 1;
+    loop {
+        let slug = slugs::candidate(&base, n);
+        if slug_is_free(conn, &slug, article_id).await? {
+            return Ok(slug);
+        }
+        n += 1;
+    }
+}
+
+async fn fill_missing_slugs(conn: &mut SqliteConnection) -> Result<usize, sqlx::Error> {
+    let articles = sqlx::query_as::<_, (i64, String)>("SELECT id, title FROM articles WHERE slug IS NULL ORDER BY id")
+        .fetch_all(&mut *conn)
+        .await?;
+    for (id, title) in &articles {
+        let slug = unique_slug(conn, title, *id).await?;
+        sqlx::query("UPDATE articles SET slug = ? WHERE id = ? AND slug IS NULL")
+            .bind(&slug)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(articles.len())
+}
+
+// 为还没有 slug 的文章（新建、导入的）按标题生成；返回处理的文章数
+pub async fn assign_missing_slugs(pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let assigned = fill_missing_slugs(&mut tx).await?;
+    tx.commit().await?;
+    Ok(assigned)
+}
+
+// 标题修改后重新生成 slug，旧 slug 留作跳转；新标题得到的 slug 与原来相同时不变。
+// 返回文章当前的 slug，文章不存在时返回 None
+pub async fn update_slug(pool: &SqlitePool, article_id: i64) -> Result<Option<String>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let Some((title, current)) = sqlx::query_as::<_, (String, Option<String>)>("SELECT title, slug FROM articles WHERE id = ?")
+        .bind(article_id)
+        .fetch_optional(&mut *tx)
+        .await?
+    else {
+        return Ok(None);
+    };
+    if let Some(current) = current.as_deref()
+        && slugs::is_candidate_of(current, &slugs::from_title(&title))
+    {
+        return Ok(Some(current.to_string()));
+    }
+    let slug = unique_slug(&mut tx, &title, article_id).await?;
+    if let Some(current) = &current {
+        sqlx::query(
+            "INSERT INTO article_slug_redirects (slug, article_id) VALUES (?, ?) \
+             ON CONFLICT(slug) DO UPDATE SET article_id = excluded.article_id, created_at = CURRENT_TIMESTAMP"
+        )
+        .bind(current)
+        .bind(article_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    // 改回以前的标题时收回旧 slug
+    sqlx::query("DELETE FROM article_slug_redirects WHERE slug = ?")
+        .bind(&slug)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE articles SET slug = ? WHERE id = ?")
+        .bind(&slug)
+        .bind(article_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(Some(slug))
+}
+
+// slug 对应的文章 id，包括回收站中的
+pub async fn get_article_id_by_slug(pool: &SqlitePool, slug: &str) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT id FROM articles WHERE slug = ?")
+        .bind(slug)
+        .fetch_optional(pool)
+        .await
+}
+
+// 旧 slug 跳转到的文章的 (id, 当前 slug)
+pub async fn get_slug_redirect(pool: &SqlitePool, slug: &str) -> Result<Option<(i64, Option<String>)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, Option<String>)>(
+        "SELECT a.id, a.slug FROM article_slug_redirects r JOIN articles a ON a.id = r.article_id WHERE r.slug = ?"
+    )
+    .bind(slug)
+    .fetch_optional(pool)
+    .await
+}
+
+// 记录 username 正在编辑文章，并清理早已离开的记录
+pub async fn touch_presence(pool: &SqlitePool, article_id: i64, username: &str) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM article_presence WHERE last_seen < datetime('now', '-1 hour')")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO article_presence (article_id, username, last_seen) VALUES (?, ?, datetime('now')) \
+         ON CONFLICT(article_id, username) DO UPDATE SET last_seen = excluded.last_seen"
+    )
+    .bind(article_id)
+    .bind(username)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+
+// 关闭编辑页时调用
+pub async fn clear_presence(pool: &SqlitePool, article_id: i64, username: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM article_presence WHERE article_id = ? AND username = ?")
+        .bind(article_id)
+        .bind(username)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// 最近 window_secs 秒内在编辑文章的其他管理员，最近活动的在前
+pub async fn get_active_editors(
+    pool: &SqlitePool,
+    article_id: i64,
+    except_username: &str,
+    window_secs: i64,
+) -> Result<Vec<ActiveEditor>, sqlx::Error> {
+    sqlx::query_as::<_, ActiveEditor>(
+        "SELECT p.username, COALESCE(NULLIF(u.display_name, ''), p.username) AS name, p.last_seen \
+         FROM article_presence p LEFT JOIN users u ON u.username = p.username \
+         WHERE p.article_id = ? AND p.username != ? AND p.last_seen >= datetime('now', ?) \
+         ORDER BY p.last_seen DESC, p.username"
+    )
+    .bind(article_id)
+    .bind(except_username)
+    .bind(format!("-{} seconds", window_secs))
+    .fetch_all(pool)
+    .await
+}
+
+// 移到回收站，返回文章是否存在且此前未在回收站中
+pub async fn trash_article(pool: &SqlitePool, article_id: i64) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query("UPDATE articles SET deleted_at = datetime('now') WHERE id = ? AND deleted_at IS NULL")
+        .bind(article_id)
+        .execute(&mut *tx)
+        .await?;
+    // 不再公开的文章从导航菜单中移除
+    sqlx::query("DELETE FROM nav_items WHERE article_id = ?")
+        .bind(article_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_trashed_articles(pool: &SqlitePool) -> Result<Vec<TrashedArticle>, sqlx::Error> {
+    sqlx::query_as::<_, TrashedArticle>(
+        "SELECT id, title, status, created_at, deleted_at FROM articles WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC, id DESC"
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_trashed_article(pool: &SqlitePool, article_id: i64) -> Result<Option<TrashedArticle>, sqlx::Error> {
+    sqlx::query_as::<_, TrashedArticle>(
+        "SELECT id, title, status, created_at, deleted_at FROM articles WHERE id = ? AND deleted_at IS NOT NULL"
+    )
+    .bind(article_id)
+    .fetch_optional(pool)
+    .await
+}
+
+// 从回收站恢复；slug 在回收站期间一直保留，恢复后原链接继续有效。
+// 导航菜单项在移入回收站时已删除，不会恢复。更新 updated_at，让订阅源和站点地图的缓存失效
+pub async fn restore_article(pool: &SqlitePool, article_id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE articles SET deleted_at = NULL, updated_at = datetime('now') WHERE id = ? AND deleted_at IS NOT NULL")
+        .bind(article_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// 在回收站中早于 before 的文章，供定时清理
+pub async fn get_trashed_before(pool: &SqlitePool, before: &str) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT id FROM articles WHERE deleted_at IS NOT NULL AND deleted_at <= ? ORDER BY deleted_at")
+        .bind(before)
+        .fetch_all(pool)
+        .await
+}
+
+// 永久删除文章及其评论、修订等关联数据，并留下墓碑记录。
+// 返回文章标题和被删除附件的文件名以便调用方清理文件；文章不存在时返回 None
+pub async fn delete_article(pool: &SqlitePool, article_id: i64) -> Result<Option<(String, Vec<String>)>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for table in [
+        "reactions", "comments", "short_links", "nav_items", "article_revisions", "page_views", "outbound_clicks", "article_tags",
+        "article_slug_redirects", "article_presence",
+    ] {
+        sqlx::query(&format!("DELETE FROM {} WHERE article_id = ?", table))
+            .bind(article_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query("DELETE FROM article_links WHERE source_id = ?")
+        .bind(article_id)
+        .execute(&mut *tx)
+        .await?;
+    // 导入记录保留，只断开与文章的关联
+    sqlx::query("UPDATE import_batch_items SET article_id = NULL WHERE article_id = ?")
+        .bind(article_id)
+        .execute(&mut *tx)
+        .await?;
+    let attachments = sqlx::query_scalar::<_, String>("DELETE FROM media WHERE article_id = ? RETURNING stored_name")
+        .bind(article_id)
+        .fetch_all(&mut *tx)
+        .await?;
+    let title = sqlx::query_scalar::<_, String>("DELETE FROM articles WHERE id = ? RETURNING title")
+        .bind(article_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(title) = title else {
+        return Ok(None);
+    };
+    sqlx::query("INSERT OR REPLACE INTO article_tombstones (article_id, title) VALUES (?, ?)")
+        .bind(article_id)
+        .bind(&title)
+        .execute(&mut *tx)
+        .await?;
+    delete_unused_tags(&mut tx).await?;
+    tx.commit().await?;
+    Ok(Some((title, attachments)))
+}
+
+// 导航菜单项，按位置排序；只包含仍公开的文章（草稿和回收站中的不算）
+pub async fn get_nav_items(pool: &SqlitePool) -> Result<Vec<NavItem>, sqlx::Error> {
+    sqlx::query_as::<_, NavItem>(
+        "SELECT n.article_id, n.label, n.position FROM nav_items n \
+         JOIN articles a ON a.id = n.article_id WHERE a.deleted_at IS NULL AND a.status = 'published' \
+         ORDER BY n.position, n.id"
+    )
+    .fetch_all(pool)
+    .await
+}
+
+// 固定到菜单末尾；已固定时只更新名称
+pub async fn pin_article(pool: &SqlitePool, article_id: i64, label: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO nav_items (article_id, label, position) \
+         VALUES (?, ?, (SELECT COALESCE(MAX(position), 0) + 1 FROM nav_items)) \
+         ON CONFLICT(article_id) DO UPDATE SET label = excluded.label"
+    )
+    .bind(article_id)
+    .bind(label)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn unpin_article(pool: &SqlitePool, article_id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM nav_items WHERE article_id = ?")
+        .bind(article_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_saved_filters(pool: &SqlitePool, user_id: i64) -> Result<Vec<SavedFilter>, sqlx::Error> {
+    sqlx::query_as::<_, SavedFilter>("SELECT * FROM saved_filters WHERE user_id = ? ORDER BY name")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_saved_filter(pool: &SqlitePool, user_id: i64, filter_id: i64) -> Result<Option<SavedFilter>, sqlx::Error> {
+    sqlx::query_as::<_, SavedFilter>("SELECT * FROM saved_filters WHERE id = ? AND user_id = ?")
+        .bind(filter_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+// 同名筛选条件直接覆盖
+pub async fn save_filter(pool: &SqlitePool, user_id: i64, name: &str, criteria: &str) -> Result<SavedFilter, sqlx::Error> {
+    sqlx::query_as::<_, SavedFilter>(
+        "INSERT INTO saved_filters (user_id, name, criteria) VALUES (?, ?, ?) \
+         ON CONFLICT(user_id, name) DO UPDATE SET criteria = excluded.criteria RETURNING *"
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(criteria)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn delete_saved_filter(pool: &SqlitePool, user_id: i64, filter_id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM saved_filters WHERE id = ? AND user_id = ?")
+        .bind(filter_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// 用文章当前内容中的链接替换之前记录的链接
+pub async fn replace_article_links(pool: &SqlitePool, source_id: i64, targets: &[i64]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM article_links WHERE source_id = ?")
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?;
+    for target in targets {
+        sqlx::query("INSERT OR IGNORE INTO article_links (source_id, target_id) VALUES (?, ?)")
+            .bind(source_id)
+            .bind(target)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+// 全部链接 (source_id, target_id)
+pub async fn get_article_links(pool: &SqlitePool) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, i64)>("SELECT source_id, target_id FROM article_links ORDER BY source_id, target_id")
+        .fetch_all(pool)
+        .await
+}
+
+// 全部分类，层级由 services::categories::tree 整理
+pub async fn get_categories(pool: &SqlitePool) -> Result<Vec<Category>, sqlx::Error> {
+    sqlx::query_as::<_, Category>("SELECT * FROM categories ORDER BY name")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_category_by_slug(pool: &SqlitePool, slug: &str) -> Result<Option<Category>, sqlx::Error> {
+    sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE slug = ?")
+        .bind(slug)
+        .fetch_optional(pool)
+        .await
+}
+
+// slug 已被使用时返回 None
+pub async fn create_category(pool: &SqlitePool, name: &str, slug: &str, parent_id: Option<i64>) -> Result<Option<Category>, sqlx::Error> {
+    sqlx::query_as::<_, Category>(
+        "INSERT INTO categories (name, slug, parent_id) VALUES (?, ?, ?) ON CONFLICT(slug) DO NOTHING RETURNING *"
+    )
+    .bind(name)
+    .bind(slug)
+    .bind(parent_id)
+    .fetch_optional(pool)
+    .await
+}
+
+// 删除分类：子分类移到它的上级，其中的文章变为未分类
+pub async fn delete_category(pool: &SqlitePool, category_id: i64) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE categories SET parent_id = (SELECT parent_id FROM categories WHERE id = ?) WHERE parent_id = ?")
+        .bind(category_id)
+        .bind(category_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE articles SET category_id = NULL WHERE category_id = ?")
+        .bind(category_id)
+        .execute(&mut *tx)
+        .await?;
+    let result = sqlx::query("DELETE FROM categories WHERE id = ?")
+        .bind(category_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// 属于这些分类的已发布文章，新的在前
+pub async fn get_category_articles(pool: &SqlitePool, category_ids: &[i64]) -> Result<Vec<Article>, sqlx::Error> {
+    if category_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let placeholders = vec!["?"; category_ids.len()].join(", ");
+    let sql = format!(
+        "SELECT * FROM articles WHERE category_id IN ({}) AND deleted_at IS NULL AND status = 'published' \
+         ORDER BY created_at DESC",
+        placeholders
+    );
+    let mut query = sqlx::query_as::<_, Article>(&sql);
+    for id in category_ids {
+        query = query.bind(id);
+    }
+    query.fetch_all(pool).await
+}
+
+// 用 names 替换文章的标签，新名称自动建立标签
+pub async fn set_article_tags(pool: &SqlitePool, article_id: i64, names: &[String]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    replace_article_tags(&mut tx, article_id, names).await?;
+    delete_unused_tags(&mut tx).await?;
+    tx.commit().await
+}
+
+async fn replace_article_tags(conn: &mut sqlx::SqliteConnection, article_id: i64, names: &[String]) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM article_tags WHERE article_id = ?")
+        .bind(article_id)
+        .execute(&mut *conn)
+        .await?;
+    for name in names {
+        sqlx::query("INSERT INTO tags (name) VALUES (?) ON CONFLICT(name) DO NOTHING")
+            .bind(name)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("INSERT OR IGNORE INTO article_tags (article_id, tag_id) SELECT ?, id FROM tags WHERE name = ?")
+            .bind(article_id)
+            .bind(name)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+// 删除没有文章使用的标签及指向它们的跳转，返回删除的标签数
+async fn delete_unused_tags(conn: &mut sqlx::SqliteConnection) -> Result<u64, sqlx::Error> {
+    sqlx::query("DELETE FROM tag_redirects WHERE tag_id NOT IN (SELECT tag_id FROM article_tags)")
+        .execute(&mut *conn)
+        .await?;
+    let deleted = sqlx::query("DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM article_tags)")
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    Ok(deleted)
+}
+
+// 清理没有文章使用的标签，返回删除的数量
+pub async fn purge_unused_tags(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let deleted = delete_unused_tags(&mut tx).await?;
+    tx.commit().await?;
+    Ok(deleted)
+}
+
+// 所有标签及其文章数，按名称排序
+pub async fn get_tag_usage(pool: &SqlitePool) -> Result<Vec<TagUsage>, sqlx::Error> {
+    sqlx::query_as::<_, TagUsage>(
+        "SELECT t.id, t.name, COUNT(at.article_id) AS articles FROM tags t \
+         LEFT JOIN article_tags at ON at.tag_id = t.id GROUP BY t.id ORDER BY t.name"
+    )
+    .fetch_all(pool)
+    .await
+}
+
+// 把标签 from 合并进 into：文章改用 into，from 的名称及其原有的跳转都指向 into，然后删除 from
+pub async fn merge_tags(pool: &SqlitePool, from: &Tag, into: &Tag) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("INSERT OR IGNORE INTO article_tags (article_id, tag_id) SELECT article_id, ? FROM article_tags WHERE tag_id = ?")
+        .bind(into.id)
+        .bind(from.id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM article_tags WHERE tag_id = ?")
+        .bind(from.id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE tag_redirects SET tag_id = ? WHERE tag_id = ?")
+        .bind(into.id)
+        .bind(from.id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO tag_redirects (name, tag_id) VALUES (?, ?) \
+         ON CONFLICT(name) DO UPDATE SET tag_id = excluded.tag_id"
+    )
+    .bind(&from.name)
+    .bind(into.id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM tags WHERE id = ?")
+        .bind(from.id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+// 合并掉的标签名现在对应的标签
+pub async fn get_tag_redirect(pool: &SqlitePool, name: &str) -> Result<Option<Tag>, sqlx::Error> {
+    sqlx::query_as::<_, Tag>("SELECT t.* FROM tag_redirects r JOIN tags t ON t.id = r.tag_id WHERE r.name = ?")
+        .bind(name)
+        .fetch_optional(pool)
+        .await
+}
+
+// 文章的标签名，按名称排序
+pub async fn get_article_tags(pool: &SqlitePool, article_id: i64) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT t.name FROM tags t JOIN article_tags at ON at.tag_id = t.id WHERE at.article_id = ? ORDER BY t.name"
+    )
+    .bind(article_id)
+    .fetch_all(pool)
+    .await
+}
+
+// 按名称查找标签，不区分大小写
+pub async fn get_tag(pool: &SqlitePool, name: &str) -> Result<Option<Tag>, sqlx::Error> {
+    sqlx::query_as::<_, Tag>("SELECT * FROM tags WHERE name = ?")
+        .bind(name)
+        .fetch_optional(pool)
+        .await
+}
+
+// 带有该标签的已发布文章，新的在前
+pub async fn get_tagged_articles(pool: &SqlitePool, tag_id: i64) -> Result<Vec<Article>, sqlx::Error> {
+    sqlx::query_as::<_, Article>(
+        "SELECT a.* FROM articles a JOIN article_tags at ON at.article_id = a.id \
+         WHERE at.tag_id = ? AND a.deleted_at IS NULL AND a.status = 'published' ORDER BY a.created_at DESC"
+    )
+    .bind(tag_id)
+    .fetch_all(pool)
+    .await
+}
+
+// 公开的文章数，用于分页
+pub async fn count_published_articles(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM articles WHERE deleted_at IS NULL AND status = 'published'")
+        .fetch_one(pool)
+        .await
+}
+
+// 按页取公开的文章，新的在前；同一时间发表的按 id 排序，翻页时顺序不变
+pub async fn get_published_articles(pool: &SqlitePool, limit: i64, offset: i64) -> Result<Vec<Article>, sqlx::Error> {
+    sqlx::query_as::<_, Article>(
+        "SELECT * FROM articles WHERE deleted_at IS NULL AND status = 'published' \
+         ORDER BY created_at DESC, id DESC LIMIT ? OFFSET ?"
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+// 游标之后的公开文章（None 时从头开始），顺序与 get_published_articles 相同
+pub async fn get_published_articles_after(pool: &SqlitePool, cursor: Option<&Cursor>, limit: i64) -> Result<Vec<Article>, sqlx::Error> {
+    sqlx::query_as::<_, Article>(
+        "SELECT * FROM articles WHERE deleted_at IS NULL AND status = 'published' \
+         AND (? IS NULL OR (created_at, id) < (?, ?)) ORDER BY created_at DESC, id DESC LIMIT ?"
+    )
+    .bind(cursor.map(|c| c.id))
+    .bind(cursor.map(|c| c.created_at.as_str()))
+    .bind(cursor.map(|c| c.id))
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+// 在同一个事务中创建多篇文章，连同首个修订、标签、链接和 slug；任何一篇失败时全部回滚。
+// 返回每篇文章的 (id, slug)，顺序与输入相同
+pub async fn create_articles(pool: &SqlitePool, articles: &[NewArticle<'_>], author: Option<&str>) -> Result<Vec<(i64, String)>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut created = Vec::with_capacity(articles.len());
+    for article in articles {
+        let id: i64 = sqlx::query_scalar(
+            "INSERT INTO articles (title, content, meta_description, meta_keywords, noindex, text_direction, canonical_url, category_id, status, author_id, created_at, updated_at) \
+             VALUES (?, ?, ?, ?, ?, ?, NULLIF(?, ''), ?, ?, (SELECT id FROM users WHERE username = ?), datetime('now'), datetime('now')) RETURNING id"
+        )
+        .bind(article.title)
+        .bind(article.content)
+        .bind(&article.meta_description)
+        .bind(&article.meta_keywords)
+        .bind(article.noindex)
+        .bind(article.text_direction)
+        .bind(&article.canonical_url)
+        .bind(article.category_id)
+        .bind(article.status)
+        .bind(author)
+        .fetch_one(&mut *tx)
+        .await?;
+        sqlx::query("INSERT INTO article_revisions (article_id, title, content, author) VALUES (?, ?, ?, ?)")
+            .bind(id)
+            .bind(article.title)
+            .bind(article.content)
+            .bind(author)
+            .execute(&mut *tx)
+            .await?;
+        replace_article_tags(&mut tx, id, &article.tags).await?;
+        for target in &article.links {
+            sqlx::query("INSERT OR IGNORE INTO article_links (source_id, target_id) VALUES (?, ?)")
+                .bind(id)
+                .bind(target)
+                .execute(&mut *tx)
+                .await?;
+        }
+        let slug = unique_slug(&mut tx, article.title, id).await?;
+        sqlx::query("UPDATE articles SET slug = ? WHERE id = ?")
+            .bind(&slug)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        created.push((id, slug));
+    }
+    tx.commit().await?;
+    Ok(created)
+}
+
+// 未在回收站中的文章 (id, title)，用于新建和导入时的查重
+pub async fn get_article_titles(pool: &SqlitePool) -> Result<Vec<(i64, String)>, sqlx::Error> {
+    sqlx::query_as::<_, (i64, String)>("SELECT id, title FROM articles WHERE deleted_at IS NULL ORDER BY id")
+        .fetch_all(pool)
+        .await
+}
+
+// 标题匹配 FTS5 查询 match_expr 或带有匹配 tag_pattern 的标签的公开文章，每篇一次，
+// 按 ranking 加权后的相关度排序
+pub async fn search_suggestions(
+    pool: &SqlitePool,
+    match_expr: &str,
+    tag_pattern: &str,
+    ranking: &crate::services::search::SearchRanking,
+    limit: i64,
+) -> Result<Vec<SearchSuggestion>, sqlx::Error> {
+    let (title_weight, content_weight) = ranking.column_weights();
+    // bm25() 越小越相关；标签前缀命中按一次标题命中计分，与标题得分相加，
+    // 再除以随文章年龄增长的系数使旧文章排在后面
+    sqlx::query_as::<_, SearchSuggestion>(
+        "WITH matches(id, score) AS ( \
+             SELECT rowid, bm25(articles_fts, ?, ?) FROM articles_fts WHERE articles_fts MATCH ? \
+             UNION ALL \
+             SELECT at.article_id, -? FROM article_tags at JOIN tags t ON t.id = at.tag_id \
+             WHERE t.name LIKE ? ESCAPE '\\' \
+         ) \
+         SELECT a.id, a.title FROM (SELECT id, SUM(score) AS score FROM matches GROUP BY id) m \
+         JOIN articles a ON a.id = m.id \
+         WHERE a.deleted_at IS NULL AND a.status = 'published' \
+         ORDER BY m.score / (1.0 + MAX(julianday('now') - julianday(a.created_at), 0) * ?), a.created_at DESC LIMIT ?"
+    )
+    .bind(title_weight)
+    .bind(content_weight)
+    .bind(match_expr)
+    .bind(title_weight)
+    .bind(tag_pattern)
+    .bind(ranking.recency_rate())
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+// 匹配 match_expr 的公开文章数
+pub async fn count_search_results(pool: &SqlitePool, match_expr: &str) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM articles_fts JOIN articles a ON a.id = articles_fts.rowid \
+         WHERE articles_fts MATCH ? AND a.deleted_at IS NULL AND a.status = 'published'"
+    )
+    .bind(match_expr)
+    .fetch_one(pool)
+    .await
+}
+
+// 全文搜索的一页结果，排序同 search_suggestions；附带标记了匹配词的标题和正文片段
+pub async fn search_articles(
+    pool: &SqlitePool,
+    match_expr: &str,
+    ranking: &crate::services::search::SearchRanking,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<SearchResult>, sqlx::Error> {
+    use crate::services::search::{MATCH_END, MATCH_START};
+    let (title_weight, content_weight) = ranking.column_weights();
+    sqlx::query_as::<_, SearchResult>(
+        "SELECT a.id, a.slug, a.title, a.created_at, highlight(articles_fts, 0, ?1, ?2) AS title_marked, \
+         snippet(articles_fts, 1, ?1, ?2, '…', 32) AS snippet \
+         FROM articles_fts JOIN articles a ON a.id = articles_fts.rowid \
+         WHERE articles_fts MATCH ?3 AND a.deleted_at IS NULL AND a.status = 'published' \
+         ORDER BY bm25(articles_fts, ?4, ?5) / (1.0 + MAX(julianday('now') - julianday(a.created_at), 0) * ?6), \
+         a.created_at DESC, a.id DESC LIMIT ?7 OFFSET ?8"
+    )
+    .bind(MATCH_START.to_string())
+    .bind(MATCH_END.to_string())
+    .bind(match_expr)
+    .bind(title_weight)
+    .bind(content_weight)
+    .bind(ranking.recency_rate())
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+// 链接到该文章的公开文章，新的在前
+pub async fn get_backlinks(pool: &SqlitePool, article_id: i64) -> Result<Vec<Backlink>, sqlx::Error> {
+    sqlx::query_as::<_, Backlink>(
+        "SELECT a.id, a.title, a.created_at FROM article_links l JOIN articles a ON a.id = l.source_id \
+         WHERE l.target_id = ? AND l.source_id != l.target_id AND a.deleted_at IS NULL AND a.status = 'published' \
+         ORDER BY a.created_at DESC, a.id DESC"
+    )
+    .bind(article_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn create_import_batch(pool: &SqlitePool, created_by: &str) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query("INSERT INTO import_batches (created_by) VALUES (?)")
+        .bind(created_by)
+        .execute(pool)
+        .await?;
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn add_import_batch_item(
+    pool: &SqlitePool,
+    batch_id: i64,
+    article_id: i64,
+    source_name: &str,
+    title: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO import_batch_items (batch_id, article_id, source_name, title) VALUES (?, ?, ?, ?)")
+        .bind(batch_id)
+        .bind(article_id)
+        .bind(source_name)
+        .bind(title)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// 最近的导入批次，新的在前
+pub async fn get_import_batches(pool: &SqlitePool, limit: i64) -> Result<Vec<ImportBatch>, sqlx::Error> {
+    sqlx::query_as::<_, ImportBatch>(
+        "SELECT id, created_by, created_at, rolled_back_at FROM import_batches ORDER BY id DESC LIMIT ?"
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_import_batch(pool: &SqlitePool, batch_id: i64) -> Result<Option<ImportBatch>, sqlx::Error> {
+    sqlx::query_as::<_, ImportBatch>("SELECT id, created_by, created_at, rolled_back_at FROM import_batches WHERE id = ?")
+        .bind(batch_id)
+        .fetch_optional(pool)
+        .await
+}
+
+// 批次导入的文章及其当前状态
+pub async fn get_import_batch_items(pool: &SqlitePool, batch_id: i64) -> Result<Vec<ImportBatchItem>, sqlx::Error> {
+    sqlx::query_as::<_, ImportBatchItem>(
+        "SELECT i.batch_id, i.article_id, i.source_name, COALESCE(a.title, i.title) AS title, \
+         CASE WHEN a.id IS NULL THEN 'deleted' WHEN a.deleted_at IS NOT NULL THEN 'trashed' ELSE 'live' END AS status, \
+         COALESCE(a.updated_at > i.created_at, 0) AS edited \
+         FROM import_batch_items i LEFT JOIN articles a ON a.id = i.article_id \
+         WHERE i.batch_id = ? ORDER BY i.id"
+    )
+    .bind(batch_id)
+    .fetch_all(pool)
+    .await
+}
+
+// 把批次中仍公开的文章移入回收站并标记批次已回滚，返回移入回收站的文章 id
+pub async fn rollback_import_batch(pool: &SqlitePool, batch_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let trashed = sqlx::query_scalar::<_, i64>(
+        "UPDATE articles SET deleted_at = datetime('now') WHERE deleted_at IS NULL \
+         AND id IN (SELECT article_id FROM import_batch_items WHERE batch_id = ?) RETURNING id"
+    )
+    .bind(batch_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM nav_items WHERE article_id IN (SELECT article_id FROM import_batch_items WHERE batch_id = ?)")
+        .bind(batch_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE import_batches SET rolled_back_at = datetime('now') WHERE id = ?")
+        .bind(batch_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(trashed)
+}
+
+pub async fn get_tombstone(pool: &SqlitePool, article_id: i64) -> Result<Option<ArticleTombstone>, sqlx::Error> {
+    sqlx::query_as::<_, ArticleTombstone>("SELECT article_id, title, deleted_at FROM article_tombstones WHERE article_id = ?")
+        .bind(article_id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn delete_snippet(pool: &SqlitePool, snippet_id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM snippets WHERE id = ?")
+        .bind(snippet_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_content_templates(pool: &SqlitePool) -> Result<Vec<ContentTemplate>, sqlx::Error> {
+    sqlx::query_as::<_, ContentTemplate>("SELECT * FROM content_templates ORDER BY name")
+        .fetch_all(pool)
+        .await
+}
+
+// 按名称新建或更新内容模板
+pub async fn save_content_template(pool: &SqlitePool, name: &str, title_pattern: &str, content: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO content_templates (name, title_pattern, content) VALUES (?, ?, ?) \
+         ON CONFLICT(name) DO UPDATE SET title_pattern = excluded.title_pattern, \
+         content = excluded.content, updated_at = CURRENT_TIMESTAMP"
+    )
+    .bind(name)
+    .bind(title_pattern)
+    .bind(content)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_content_template(pool: &SqlitePool, template_id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM content_templates WHERE id = ?")
+        .bind(template_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// 记录审计日志，actor 为用户名或 "system"（后台任务）
+pub async fn record_audit(pool: &SqlitePool, actor: &str, action: &str, target: &str, detail: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO audit_log (actor, action, target, detail) VALUES (?, ?, ?, ?)")
+        .bind(actor)
+        .bind(action)
+        .bind(target)
+        .bind(detail)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn record_page_view(
+    pool: &SqlitePool,
+    article_id: i64,
+    referral: &crate::services::analytics::Referral,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO page_views (article_id, referrer_host, search_engine, search_query) VALUES (?, ?, ?, ?)")
+        .bind(article_id)
+        .bind(&referral.host)
+        .bind(referral.search_engine)
+        .bind(&referral.search_query)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// since 为空时统计全部；已删除文章的访问不计入
+pub async fn get_referrer_counts(
+    pool: &SqlitePool,
+    since: Option<&str>,
+    article_id: Option<i64>,
+) -> Result<Vec<ReferrerRow>, sqlx::Error> {
+    sqlx::query_as::<_, ReferrerRow>(
+        "SELECT v.article_id, a.title, v.referrer_host AS host, COUNT(*) AS views \
+         FROM page_views v JOIN articles a ON a.id = v.article_id AND a.deleted_at IS NULL \
+         WHERE (? IS NULL OR v.viewed_at >= ?) AND (? IS NULL OR v.article_id = ?) \
+         GROUP BY v.article_id, v.referrer_host"
+    )
+    .bind(since)
+    .bind(since)
+    .bind(article_id)
+    .bind(article_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_search_query_counts(
+    pool: &SqlitePool,
+    since: Option<&str>,
+    article_id: Option<i64>,
+) -> Result<Vec<SearchQueryRow>, sqlx::Error> {
+    sqlx::query_as::<_, SearchQueryRow>(
+        "SELECT v.article_id, a.title, v.search_engine AS engine, v.search_query AS query, COUNT(*) AS views \
+         FROM page_views v JOIN articles a ON a.id = v.article_id AND a.deleted_at IS NULL \
+         WHERE v.search_query IS NOT NULL AND v.search_engine IS NOT NULL \
+         AND (? IS NULL OR v.viewed_at >= ?) AND (? IS NULL OR v.article_id = ?) \
+         GROUP BY v.article_id, v.search_engine, v.search_query"
+    )
+    .bind(since)
+    .bind(since)
+    .bind(article_id)
+    .bind(article_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn record_outbound_click(pool: &SqlitePool, article_id: i64, url: &str, host: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO outbound_clicks (article_id, target_url, target_host) VALUES (?, ?, ?)")
+        .bind(article_id)
+        .bind(url)
+        .bind(host)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// 点击最多的外部链接；since 为空时统计全部，已删除文章中的点击不计入
+pub async fn get_outbound_click_counts(
+    pool: &SqlitePool,
+    since: Option<&str>,
+    article_id: Option<i64>,
+    limit: i64,
+) -> Result<Vec<OutboundClickRow>, sqlx::Error> {
+    sqlx::query_as::<_, OutboundClickRow>(
+        "SELECT c.target_url AS url, c.target_host AS host, COUNT(*) AS clicks, COUNT(DISTINCT c.article_id) AS articles \
+         FROM outbound_clicks c JOIN articles a ON a.id = c.article_id AND a.deleted_at IS NULL \
+         WHERE (? IS NULL OR c.clicked_at >= ?) AND (? IS NULL OR c.article_id = ?) \
+         GROUP BY c.target_url ORDER BY clicks DESC, url LIMIT ?"
+    )
+    .bind(since)
+    .bind(since)
+    .bind(article_id)
+    .bind(article_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_content_counts(pool: &SqlitePool) -> Result<ContentCounts, sqlx::Error> {
+    sqlx::query_as::<_, ContentCounts>(
+        "SELECT \
+         (SELECT COUNT(*) FROM articles WHERE deleted_at IS NULL AND status = 'published' AND created_at <= datetime('now')) AS published, \
+         (SELECT COUNT(*) FROM articles WHERE deleted_at IS NULL AND status = 'published' AND created_at > datetime('now')) AS scheduled, \
+         (SELECT COUNT(*) FROM articles WHERE deleted_at IS NULL AND status = 'draft') AS drafts, \
+         (SELECT COUNT(*) FROM articles WHERE deleted_at IS NOT NULL) AS trashed, \
+         (SELECT COUNT(*) FROM comments WHERE status = 'pending') AS pending_comments, \
+         (SELECT COUNT(*) FROM comments WHERE status = 'spam') AS spam_comments"
+    )
+    .fetch_one(pool)
+    .await
+}
+
+// 审计日志分页，最新的在前
+pub async fn get_audit_entries(pool: &SqlitePool, limit: i64, offset: i64) -> Result<Vec<AuditEntry>, sqlx::Error> {
+    sqlx::query_as::<_, AuditEntry>(
+        "SELECT id, actor, action, target, detail, created_at FROM audit_log ORDER BY id DESC LIMIT ? OFFSET ?"
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn count_audit_entries(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM audit_log").fetch_one(pool).await
+}
+
+/// Outcome of `run_maintenance`: database size in bytes, step durations in milliseconds
+#[derive(Debug, Serialize)]
+pub struct MaintenanceReport {
+    pub size_before: i64,
+    pub size_after: i64,
+    pub vacuum_ms: u64,
+    pub analyze_ms: u64,
+    pub optimize_ms: u64,
+}
+
+async fn database_size(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()")
+        .fetch_one(pool)
+        .await
+}
+
+async fn timed_statement(pool: &SqlitePool, sql: &str) -> Result<u64, sqlx::Error> {
+    let started = std::time::Instant::now();
+    sqlx::query(sql).execute(pool).await?;
+    Ok(started.elapsed().as_millis() as u64)
+}
+
+// 数据库维护：VACUUM 回收空间，ANALYZE 更新统计信息，PRAGMA optimize 让 SQLite 自行优化查询计划
+pub async fn run_maintenance(pool: &SqlitePool) -> Result<MaintenanceReport, sqlx::Error> {
+    let size_before = database_size(pool).await?;
+    let vacuum_ms = timed_statement(pool, "VACUUM").await?;
+    let analyze_ms = timed_statement(pool, "ANALYZE").await?;
+    let optimize_ms = timed_statement(pool, "PRAGMA optimize").await?;
+    Ok(MaintenanceReport {
+        size_before,
+        size_after: database_size(pool).await?,
+        vacuum_ms,
+        analyze_ms,
+        optimize_ms,
+    })
+}
+
+// 保存文章当前内容为一个修订；与最新修订相同时不重复保存。返回是否新增了修订
+pub async fn save_revision(pool: &SqlitePool, article_id: i64, author: Option<&str>) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO article_revisions (article_id, title, content, author) \
+         SELECT a.id, a.title, a.content, ? FROM articles a WHERE a.id = ? AND NOT EXISTS ( \
+             SELECT 1 FROM article_revisions r WHERE r.id = (SELECT MAX(id) FROM article_revisions WHERE article_id = a.id) \
+             AND r.title = a.title AND r.content = a.content)"
+    )
+    .bind(author)
+    .bind(article_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// 修订列表，最新的在前
+pub async fn get_article_revisions(pool: &SqlitePool, article_id: i64) -> Result<Vec<ArticleRevision>, sqlx::Error> {
+    sqlx::query_as::<_, ArticleRevision>(
+        "SELECT id, article_id, title, content, author, created_at FROM article_revisions \
+         WHERE article_id = ? ORDER BY id DESC"
+    )
+    .bind(article_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_article_revision(pool: &SqlitePool, article_id: i64, revision_id: i64) -> Result<Option<ArticleRevision>, sqlx::Error> {
+    sqlx::query_as::<_, ArticleRevision>(
+        "SELECT id, article_id, title, content, author, created_at FROM article_revisions \
+         WHERE article_id = ? AND id = ?"
+    )
+    .bind(article_id)
+    .bind(revision_id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn insert_media(
+    pool: &SqlitePool,
+    stored_name: &str,
+    original_name: &str,
+    content_type: &str,
+    size: i64,
+    article_id: Option<i64>,
+) -> Result<Media, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        "INSERT INTO media (stored_name, original_name, content_type, size, article_id) VALUES (?, ?, ?, ?, ?) \
+         RETURNING id, stored_name, original_name, content_type, size, article_id, created_at"
+    )
+    .bind(stored_name)
+    .bind(original_name)
+    .bind(content_type)
+    .bind(size)
+    .bind(article_id)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn get_media_by_name(pool: &SqlitePool, stored_name: &str) -> Result<Option<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        "SELECT id, stored_name, original_name, content_type, size, article_id, created_at FROM media WHERE stored_name = ?"
+    )
+    .bind(stored_name)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn get_article_attachments(pool: &SqlitePool, article_id: i64) -> Result<Vec<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        "SELECT id, stored_name, original_name, content_type, size, article_id, created_at FROM media \
+         WHERE article_id = ? ORDER BY id"
+    )
+    .bind(article_id)
+    .fetch_all(pool)
+    .await
+}
+
+// 未挂在文章下的上传（编辑器图片、头像、站点图标等），按上传时间排序
+pub async fn get_unattached_media(pool: &SqlitePool) -> Result<Vec<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        "SELECT id, stored_name, original_name, content_type, size, article_id, created_at FROM media \
+         WHERE article_id IS NULL ORDER BY created_at, id"
+    )
+    .fetch_all(pool)
+    .await
+}
+
+// 可能引用上传文件的所有文本：文章（含回收站）、关于页、片段、模板、作者资料和站点设置
+pub async fn get_media_reference_texts(pool: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT content FROM articles \
+         UNION ALL SELECT content FROM about \
+         UNION ALL SELECT content FROM snippets \
+         UNION ALL SELECT content FROM content_templates \
+         UNION ALL SELECT COALESCE(avatar_url, '') || ' ' || COALESCE(bio, '') FROM users \
+         UNION ALL SELECT value FROM settings"
+    )
+    .fetch_all(pool)
+    .await
+}
+
+// 删除媒体记录，返回被删除的记录以便调用方清理文件
+pub async fn delete_media(pool: &SqlitePool, id: i64) -> Result<Option<Media>, sqlx::Error> {
+    sqlx::query_as::<_, Media>(
+        "DELETE FROM media WHERE id = ? \
+         RETURNING id, stored_name, original_name, content_type, size, article_id, created_at"
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+// 每篇文章只有一个短链接，不存在时生成；短码冲突时换一个重试
+pub async fn get_or_create_short_link(pool: &SqlitePool, article_id: i64) -> Result<ShortLink, sqlx::Error> {
+    use crate::services::short_link::{generate_code, CODE_LENGTH};
+
+    for attempt in 0..8 {
+        if let Some(link) = sqlx::query_as::<_, ShortLink>("SELECT * FROM short_links WHERE article_id = ?")
+            .bind(article_id)
+            .fetch_optional(pool)
+            .await?
+        {
+            return Ok(link);
+        }
+        sqlx::query("INSERT OR IGNORE INTO short_links (code, article_id) VALUES (?, ?)")
+            .bind(generate_code(CODE_LENGTH + attempt / 2))
+            .bind(article_id)
+            .execute(pool)
+            .await?;
+    }
+    Err(sqlx::Error::Protocol("could not allocate a unique short link code".to_string()))
+}
+
+// 解析短码并累计点击数，文章已删除或是草稿时返回 None
+pub async fn resolve_short_link(pool: &SqlitePool, code: &str) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar(
+        "UPDATE short_links SET clicks = clicks + 1 \
+         WHERE code = ? AND article_id IN (SELECT id FROM articles WHERE deleted_at IS NULL AND status = 'published') \
+         RETURNING article_id"
+    )
+    .bind(code)
+    .fetch_optional(pool)
+    .await
+}
+
+// 导出整个站点的内容和设置；include_passwords 为 false 时不含密码和安全问题答案的哈希
+pub async fn export_site(
+    pool: &SqlitePool,
+    include_passwords: bool,
+) -> Result<crate::services::site_archive::SiteArchive, sqlx::Error> {
+    let users = sqlx::query_as::<_, SiteUser>(
+        "SELECT username, display_name, bio, website, avatar_url, created_at, \
+         CASE WHEN ? THEN password_hash END AS password_hash, \
+         CASE WHEN ? THEN security_question END AS security_question, \
+         CASE WHEN ? THEN security_answer_hash END AS security_answer_hash \
+         FROM users ORDER BY id"
+    )
+    .bind(include_passwords)
+    .bind(include_passwords)
+    .bind(include_passwords)
+    .fetch_all(pool)
+    .await?;
+    let articles = sqlx::query_as::<_, SiteArticle>(
+        "SELECT a.id, a.title, a.content, u.username AS author, a.created_at, a.updated_at, a.meta_description, \
+         a.meta_keywords, a.noindex, a.text_direction, a.deleted_at, a.render_options, a.canonical_url, a.slug \
+         FROM articles a LEFT JOIN users u ON u.id = a.author_id ORDER BY a.id"
+    )
+    .fetch_all(pool)
+    .await?;
+    let pages = sqlx::query_as::<_, SitePage>("SELECT title, content, updated_at FROM about ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+    let comments = sqlx::query_as::<_, SiteComment>(
+        "SELECT id, article_id, parent_id, author_name, author_email, content, status, created_at, notify_replies \
+         FROM comments ORDER BY id"
+    )
+    .fetch_all(pool)
+    .await?;
+    let snippets = sqlx::query_as::<_, SiteSnippet>("SELECT name, content FROM snippets ORDER BY name")
+        .fetch_all(pool)
+        .await?;
+    let content_templates = sqlx::query_as::<_, SiteContentTemplate>(
+        "SELECT name, title_pattern, content FROM content_templates ORDER BY name"
+    )
+    .fetch_all(pool)
+    .await?;
+    let nav_items = sqlx::query_as::<_, SiteNavItem>("SELECT article_id, label, position FROM nav_items ORDER BY position")
+        .fetch_all(pool)
+        .await?;
+    let short_links = sqlx::query_as::<_, SiteShortLink>("SELECT code, article_id, clicks FROM short_links ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+    let media = sqlx::query_as::<_, SiteMedia>(
+        "SELECT stored_name, original_name, content_type, size, article_id, created_at FROM media ORDER BY id"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(crate::services::site_archive::SiteArchive {
+        version: crate::services::site_archive::FORMAT_VERSION,
+        exported_at: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        users,
+        articles,
+        pages,
+        comments,
+        snippets,
+        content_templates,
+        nav_items,
+        short_links,
+        settings: get_settings(pool).await?.into_iter().collect(),
+        media,
+    })
+}
+
+// 把站点归档导入到没有文章的站点（新安装），整体在一个事务中完成。
+// 同名用户更新资料；归档不含密码的新用户使用随机临时密码并须在登录后修改，返回 (用户名, 临时密码)
+pub async fn import_site(
+    pool: &SqlitePool,
+    archive: &crate::services::site_archive::SiteArchive,
+) -> Result<Vec<(String, String)>, crate::services::site_archive::SiteArchiveError> {
+    use crate::services::site_archive::{temporary_password, SiteArchiveError};
+
+    let mut tx = pool.begin().await?;
+    let existing: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM articles").fetch_one(&mut *tx).await?;
+    if existing > 0 {
+        return Err(SiteArchiveError::NotEmpty(existing));
+    }
+
+    let mut temporary_passwords = Vec::new();
+    for user in &archive.users {
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE username = ?)")
+            .bind(&user.username)
+            .fetch_one(&mut *tx)
+            .await?;
+        if !exists {
+            let password_hash = match &user.password_hash {
+                Some(password_hash) => password_hash.clone(),
+                None => {
+                    let password = temporary_password();
+                    let password_hash = hash(&password, DEFAULT_COST).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+                    temporary_passwords.push((user.username.clone(), password));
+                    password_hash
+                }
+            };
+            sqlx::query(
+                "INSERT INTO users (username, password_hash, created_at, must_change_password) VALUES (?, ?, ?, ?)"
+            )
+            .bind(&user.username)
+            .bind(&password_hash)
+            .bind(&user.created_at)
+            .bind(user.password_hash.is_none())
+            .execute(&mut *tx)
+            .await?;
+        } else if let Some(password_hash) = &user.password_hash {
+            sqlx::query("UPDATE users SET password_hash = ?, must_change_password = 0 WHERE username = ?")
+                .bind(password_hash)
+                .bind(&user.username)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query(
+            "UPDATE users SET display_name = ?, bio = ?, website = ?, avatar_url = ?, \
+             security_question = COALESCE(?, security_question), security_answer_hash = COALESCE(?, security_answer_hash) \
+             WHERE username = ?"
+        )
+        .bind(&user.display_name)
+        .bind(&user.bio)
+        .bind(&user.website)
+        .bind(&user.avatar_url)
+        .bind(&user.security_question)
+        .bind(&user.security_answer_hash)
+        .bind(&user.username)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for article in &archive.articles {
+        // 作者不在归档中时归属第一个用户，与旧数据的迁移方式一致
+        sqlx::query(
+            "INSERT INTO articles (id, title, content, author_id, created_at, updated_at, meta_description, meta_keywords, \
+             noindex, text_direction, deleted_at, render_options, canonical_url, slug) \
+             VALUES (?, ?, ?, COALESCE((SELECT id FROM users WHERE username = ?), (SELECT MIN(id) FROM users)), \
+             ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(article.id)
+        .bind(&article.title)
+        .bind(&article.content)
+        .bind(&article.author)
+        .bind(&article.created_at)
+        .bind(&article.updated_at)
+        .bind(&article.meta_description)
+        .bind(&article.meta_keywords)
+        .bind(article.noindex)
+        .bind(&article.text_direction)
+        .bind(&article.deleted_at)
+        .bind(&article.render_options)
+        .bind(&article.canonical_url)
+        .bind(&article.slug)
+        .execute(&mut *tx)
+        .await?;
+    }
+    fill_missing_slugs(&mut tx).await?;
+
+    if !archive.pages.is_empty() {
+        sqlx::query("DELETE FROM about").execute(&mut *tx).await?;
+    }
+    for page in &archive.pages {
+        sqlx::query("INSERT INTO about (title, content, updated_at) VALUES (?, ?, ?)")
+            .bind(&page.title)
+            .bind(&page.content)
+            .bind(&page.updated_at)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    // 回复总在被回复的评论之后创建，按 id 顺序插入即可满足外键
+    for comment in &archive.comments {
+        sqlx::query(
+            "INSERT INTO comments (id, article_id, parent_id, author_name, author_email, content, status, created_at, notify_replies) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(comment.id)
+        .bind(comment.article_id)
+        .bind(comment.parent_id)
+        .bind(&comment.author_name)
+        .bind(&comment.author_email)
+        .bind(&comment.content)
+        .bind(&comment.status)
+        .bind(&comment.created_at)
+        .bind(comment.notify_replies)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for snippet in &archive.snippets {
+        sqlx::query(
+            "INSERT INTO snippets (name, content) VALUES (?, ?) \
+             ON CONFLICT(name) DO UPDATE SET content = excluded.content, updated_at = CURRENT_TIMESTAMP"
+        )
+        .bind(&snippet.name)
+        .bind(&snippet.content)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for template in &archive.content_templates {
+        sqlx::query(
+            "INSERT INTO content_templates (name, title_pattern, content) VALUES (?, ?, ?) \
+             ON CONFLICT(name) DO UPDATE SET title_pattern = excluded.title_pattern, content = excluded.content, \
+             updated_at = CURRENT_TIMESTAMP"
+        )
+        .bind(&template.name)
+        .bind(&template.title_pattern)
+        .bind(&template.content)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query("DELETE FROM nav_items").execute(&mut *tx).await?;
+    for item in &archive.nav_items {
+        sqlx::query("INSERT INTO nav_items (article_id, label, position) VALUES (?, ?, ?)")
+            .bind(item.article_id)
+            .bind(&item.label)
+            .bind(item.position)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for link in &archive.short_links {
+        sqlx::query("INSERT INTO short_links (code, article_id, clicks) VALUES (?, ?, ?)")
+            .bind(&link.code)
+            .bind(link.article_id)
+            .bind(link.clicks)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    for (key, value) in &archive.settings {
+        sqlx::query(
+            "INSERT INTO settings (key, value) VALUES (?, ?) \
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = CURRENT_TIMESTAMP"
+        )
+        .bind(key)
+        .bind(value)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for media in &archive.media {
+        sqlx::query(
+            "INSERT INTO media (stored_name, original_name, content_type, size, article_id, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?) ON CONFLICT(stored_name) DO NOTHING"
+        )
+        .bind(&media.stored_name)
+        .bind(&media.original_name)
+        .bind(&media.content_type)
+        .bind(media.size)
+        .bind(media.article_id)
+        .bind(&media.created_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(temporary_passwords)
+}
+
+/// Outcome of `seed_demo`
+#[derive(Debug, Default)]
+pub struct SeedReport {
+    pub articles: usize,
+    pub comments: usize,
+    /// Titles of demo articles already on the site
+    pub skipped: Vec<String>,
+}
+
+// 写入 services::demo 中的示例文章和评论（bluster seed --demo），作者为第一个用户；
+// 已有标题相近的文章时跳过，重复运行不会产生第二份
+pub async fn seed_demo(pool: &SqlitePool) -> Result<SeedReport, sqlx::Error> {
+    use crate::services::{demo, duplicates};
+
+    let existing = get_article_titles(pool).await?;
+    let mut report = SeedReport::default();
+    let mut tx = pool.begin().await?;
+    for article in demo::ARTICLES {
+        if duplicates::find_duplicate(article.title, existing.iter().map(|(id, title)| (*id, title.as_str()))).is_some() {
+            report.skipped.push(article.title.to_string());
+            continue;
+        }
+        let published = format!("-{} days", article.days_ago);
+        let article_id: i64 = sqlx::query_scalar(
+            "INSERT INTO articles (title, content, meta_description, meta_keywords, render_options, author_id, created_at, updated_at) \
+             VALUES (?, ?, ?, ?, ?, (SELECT MIN(id) FROM users), datetime('now', ?), datetime('now', ?)) RETURNING id"
+        )
+        .bind(article.title)
+        .bind(article.content)
+        .bind(article.description)
+        .bind(article.keywords)
+        .bind(article.render_options())
+        .bind(&published)
+        .bind(&published)
+        .fetch_one(&mut *tx)
+        .await?;
+        replace_article_tags(&mut tx, article_id, &crate::services::tags::parse_tags(article.keywords)).await?;
+        report.articles += 1;
+
+        // 评论按顺序每隔一小时一条，回复总在原评论之后
+        let mut comment_ids: Vec<i64> = Vec::with_capacity(article.comments.len());
+        for (i, comment) in article.comments.iter().enumerate() {
+            let comment_id: i64 = sqlx::query_scalar(
+                "INSERT INTO comments (article_id, parent_id, author_name, content, status, created_at) \
+                 VALUES (?, ?, ?, ?, 'approved', datetime('now', ?, ?)) RETURNING id"
+            )
+            .bind(article_id)
+            .bind(comment.reply_to.map(|parent| comment_ids[parent]))
+            .bind(comment.author_name)
+            .bind(comment.content)
+            .bind(&published)
+            .bind(format!("+{} hours", i + 1))
+            .fetch_one(&mut *tx)
+            .await?;
+            comment_ids.push(comment_id);
+            report.comments += 1;
+        }
+    }
+    fill_missing_slugs(&mut tx).await?;
+    tx.commit().await?;
+    Ok(report)
+}
@@ -0,0 +1,134 @@
+//! Dashboard activity feed: audit log entries turned into readable events
+
+use crate::models::AuditEntry;
+use serde::Serialize;
+
+/// Events shown per dashboard page
+pub const PER_PAGE: i64 = 15;
+
+/// `audit_log.target` of events about an article
+pub fn article_target(article_id: i64) -> String {
+    format!("article:{}", article_id)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ActivityItem {
+    pub at: String,
+    pub actor: String,
+    /// `content`, `comment`, `security` or `system`, used to pick a badge colour
+    pub category: &'static str,
+    pub label: String,
+    pub detail: String,
+    /// Admin page for the event's subject, if it still has one
+    pub link: Option<String>,
+}
+
+/// Category and label for an audit action; unknown actions are shown by name
+fn describe_action(action: &str) -> (&'static str, String) {
+    let (category, label) = match action {
+        "publish_article" => ("content", "Published article"),
+        "save_draft" => ("content", "Saved draft"),
+        "import_article" => ("content", "Imported article"),
+        "rollback_import" => ("content", "Rolled back import"),
+        "trash_article" => ("content", "Moved article to trash"),
+        "restore_article" => ("content", "Restored article from trash"),
+        "delete_article" => ("content", "Deleted article"),
+        "purge_article" => ("content", "Purged article from trash"),
+        "comment_pending" => ("comment", "Comment awaiting moderation"),
+        "login_failed" => ("security", "Failed sign-in"),
+        "update_settings" => ("system", "Updated site settings"),
+        "update_profile" => ("system", "Updated author profile"),
+        "pin_article" => ("content", "Pinned article to menu"),
+        "unpin_article" => ("content", "Removed article from menu"),
+        "batch_replace" => ("content", "Replaced text across articles"),
+        "create_category" => ("content", "Created category"),
+        "delete_category" => ("content", "Deleted category"),
+        "merge_tags" => ("content", "Merged tags"),
+        "purge_tags" => ("content", "Removed unused tags"),
+        "delete_orphaned_media" => ("system", "Deleted unused uploads"),
+        "update_branding" => ("system", "Uploaded branding image"),
+        "remove_branding" => ("system", "Removed branding image"),
+        "db_maintenance" => ("system", "Database maintenance"),
+        "publish_ping" => ("system", "Notified search engines"),
+        other => return ("system", other.replace('_', " ")),
+    };
+    (category, label.to_string())
+}
+
+pub fn describe(entry: &AuditEntry) -> ActivityItem {
+    let (category, label) = describe_action(&entry.action);
+    let article_id = entry
+        .target
+        .strip_prefix("article:")
+        .and_then(|id| id.parse::<i64>().ok());
+    let link = match (entry.action.as_str(), article_id) {
+        ("trash_article", _) => Some("/admin/trash".to_string()),
+        ("delete_article" | "purge_article", _) => None,
+        ("comment_pending", _) => Some("/admin/comments".to_string()),
+        ("merge_tags" | "purge_tags", _) => Some("/admin/tags".to_string()),
+        (_, Some(id)) => Some(format!("/admin/articles/{}/edit", id)),
+        _ => None,
+    };
+    ActivityItem {
+        at: entry.created_at.clone(),
+        actor: entry.actor.clone(),
+        category,
+        label,
+        detail: entry.detail.clone(),
+        link,
+    }
+}
+
+/// One page of the feed, newest first
+#[derive(Debug, Serialize)]
+pub struct ActivityPage {
+    pub items: Vec<ActivityItem>,
+    pub page: i64,
+    pub total_pages: i64,
+}
+
+impl ActivityPage {
+    pub fn new(entries: &[AuditEntry], page: i64, total: i64) -> Self {
+        Self {
+            items: entries.iter().map(describe).collect(),
+            page,
+            total_pages: ((total + PER_PAGE - 1) / PER_PAGE).max(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(action: &str, target: &str) -> AuditEntry {
+        AuditEntry {
+            id: 1,
+            actor: "admin".to_string(),
+            action: action.to_string(),
+            target: target.to_string(),
+            detail: "Hello".to_string(),
+            created_at: "2025-01-01 10:00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_describe() {
+        let published = describe(&entry("publish_article", &article_target(4)));
+        assert_eq!((published.category, published.label.as_str()), ("content", "Published article"));
+        assert_eq!(published.link.as_deref(), Some("/admin/articles/4/edit"));
+
+        assert_eq!(describe(&entry("delete_article", "article:4")).link, None);
+        assert_eq!(describe(&entry("trash_article", "article:4")).link.as_deref(), Some("/admin/trash"));
+        assert_eq!(describe(&entry("comment_pending", "article:4")).link.as_deref(), Some("/admin/comments"));
+        assert_eq!(describe(&entry("login_failed", "10.0.0.1")).category, "security");
+        assert_eq!(describe(&entry("rotate_keys", "site")).label, "rotate keys");
+    }
+
+    #[test]
+    fn test_pages() {
+        assert_eq!(ActivityPage::new(&[], 1, 0).total_pages, 1);
+        assert_eq!(ActivityPage::new(&[], 1, PER_PAGE).total_pages, 1);
+        assert_eq!(ActivityPage::new(&[], 1, PER_PAGE + 1).total_pages, 2);
+    }
+}
@@ -0,0 +1,354 @@
+//! Where article readers come from: the referring site and, for visits from a
+//! search engine, the search query.
+//!
+//! Only the referrer's host and the query are kept, never the full URL, and
+//! visits from our own pages or from crawlers are not counted.
+
+use crate::models::{ReferrerRow, SearchQueryRow};
+use chrono::{Duration, NaiveDateTime};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Longest search query stored, in characters
+const MAX_QUERY_CHARS: usize = 100;
+
+/// Search engines by host: a pattern with a dot matches the host or its subdomains,
+/// one without matches any label (so `google` covers `www.google.co.uk`)
+const SEARCH_ENGINES: &[(&str, &str, &[&str])] = &[
+    ("google", "Google", &["q"]),
+    ("bing.com", "Bing", &["q"]),
+    ("duckduckgo.com", "DuckDuckGo", &["q"]),
+    ("search.yahoo.com", "Yahoo", &["p"]),
+    ("baidu.com", "Baidu", &["wd", "word"]),
+    ("yandex", "Yandex", &["text"]),
+    ("sogou.com", "Sogou", &["query"]),
+    ("so.com", "360 Search", &["q"]),
+    ("ecosia.org", "Ecosia", &["q"]),
+    ("search.brave.com", "Brave", &["q"]),
+];
+
+/// User agent fragments of crawlers and link preview fetchers
+const BOT_MARKERS: &[&str] = &["bot", "spider", "crawl", "slurp", "facebookexternalhit", "preview", "curl", "wget"];
+
+/// The part of a `Referer` header worth keeping
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Referral {
+    /// Lowercase host without `www.`
+    pub host: String,
+    pub search_engine: Option<&'static str>,
+    /// Normalized search query; engines that hide it (Google nowadays) leave this empty
+    pub search_query: Option<String>,
+}
+
+/// Lowercase host of an `http(s)` URL, without port, credentials or `www.`
+pub fn host_of(url: &str) -> Option<String> {
+    let rest = url
+        .trim()
+        .strip_prefix("https://")
+        .or_else(|| url.trim().strip_prefix("http://"))?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    let host = host.to_ascii_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let valid = !host.is_empty() && host.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    valid.then(|| host.to_string())
+}
+
+pub(crate) fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// First non-empty value of one of `names` in the URL's query string
+fn query_param(url: &str, names: &[&str]) -> Option<String> {
+    let query = url.split_once('?')?.1.split('#').next()?;
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .filter(|(name, _)| names.contains(name))
+        .map(|(_, value)| percent_decode(value))
+        .find(|value| !value.trim().is_empty())
+}
+
+/// Search query as counted: lowercase, single spaces, at most `MAX_QUERY_CHARS`
+pub fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .chars()
+        .take(MAX_QUERY_CHARS)
+        .collect()
+}
+
+fn search_engine(host: &str) -> Option<(&'static str, &'static [&'static str])> {
+    SEARCH_ENGINES.iter().find_map(|&(pattern, name, params)| {
+        let matches = if pattern.contains('.') {
+            host == pattern || host.ends_with(&format!(".{}", pattern))
+        } else {
+            host.split('.').any(|label| label == pattern)
+        };
+        matches.then_some((name, params))
+    })
+}
+
+/// The referral behind a `Referer` header, or `None` for direct visits, links
+/// between our own pages (`site_host`) and referrers that are not web pages
+pub fn parse_referrer(referrer: &str, site_host: &str) -> Option<Referral> {
+    let host = host_of(referrer)?;
+    if host == site_host {
+        return None;
+    }
+    let (search_engine, search_query) = match search_engine(&host) {
+        Some((name, params)) => (
+            Some(name),
+            query_param(referrer, params).map(|q| normalize_query(&q)).filter(|q| !q.is_empty()),
+        ),
+        None => (None, None),
+    };
+    Some(Referral { host, search_engine, search_query })
+}
+
+/// Crawlers and empty user agents are left out of the report
+pub fn is_bot(user_agent: &str) -> bool {
+    let user_agent = user_agent.to_ascii_lowercase();
+    user_agent.trim().is_empty() || BOT_MARKERS.iter().any(|marker| user_agent.contains(marker))
+}
+
+/// Period covered by a report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportWindow {
+    Day,
+    Week,
+    #[default]
+    Month,
+    Quarter,
+    All,
+}
+
+impl ReportWindow {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "24h" => Some(ReportWindow::Day),
+            "7d" => Some(ReportWindow::Week),
+            "30d" => Some(ReportWindow::Month),
+            "90d" => Some(ReportWindow::Quarter),
+            "all" => Some(ReportWindow::All),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportWindow::Day => "24h",
+            ReportWindow::Week => "7d",
+            ReportWindow::Month => "30d",
+            ReportWindow::Quarter => "90d",
+            ReportWindow::All => "all",
+        }
+    }
+
+    /// Start of the window as stored in `page_views.viewed_at` (UTC), `None` for all time
+    pub fn since(&self, now: NaiveDateTime) -> Option<String> {
+        let days = match self {
+            ReportWindow::Day => 1,
+            ReportWindow::Week => 7,
+            ReportWindow::Month => 30,
+            ReportWindow::Quarter => 90,
+            ReportWindow::All => return None,
+        };
+        Some((now - Duration::days(days)).format("%Y-%m-%d %H:%M:%S").to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReferrerCount {
+    pub host: String,
+    pub views: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchQueryCount {
+    pub engine: String,
+    pub query: String,
+    pub views: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArticleReferrers {
+    pub article_id: i64,
+    pub title: String,
+    /// All referred views of the article, including those not in the top lists
+    pub views: i64,
+    pub referrers: Vec<ReferrerCount>,
+    pub search_queries: Vec<SearchQueryCount>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReferrerReport {
+    pub referrers: Vec<ReferrerCount>,
+    pub search_queries: Vec<SearchQueryCount>,
+    /// Articles by referred views, most first
+    pub articles: Vec<ArticleReferrers>,
+}
+
+/// Sort by views, most first, then by key, and keep `limit`
+fn top<T, K: Ord>(mut counts: Vec<T>, limit: usize, views: impl Fn(&T) -> i64, key: impl Fn(&T) -> K) -> Vec<T> {
+    counts.sort_by(|a, b| views(b).cmp(&views(a)).then_with(|| key(a).cmp(&key(b))));
+    counts.truncate(limit);
+    counts
+}
+
+impl ReferrerReport {
+    /// Site-wide and per-article top `limit` referrers and search queries
+    pub fn build(referrers: &[ReferrerRow], queries: &[SearchQueryRow], limit: usize) -> Self {
+        let mut site_referrers: HashMap<&str, i64> = HashMap::new();
+        let mut site_queries: HashMap<(&str, &str), i64> = HashMap::new();
+        let mut articles: HashMap<i64, ArticleReferrers> = HashMap::new();
+
+        for row in referrers {
+            *site_referrers.entry(&row.host).or_default() += row.views;
+            let entry = articles.entry(row.article_id).or_insert_with(|| ArticleReferrers {
+                article_id: row.article_id,
+                title: row.title.clone(),
+                views: 0,
+                referrers: Vec::new(),
+                search_queries: Vec::new(),
+            });
+            entry.views += row.views;
+            entry.referrers.push(ReferrerCount { host: row.host.clone(), views: row.views });
+        }
+        for row in queries {
+            *site_queries.entry((&row.engine, &row.query)).or_default() += row.views;
+            if let Some(entry) = articles.get_mut(&row.article_id) {
+                entry.search_queries.push(SearchQueryCount {
+                    engine: row.engine.clone(),
+                    query: row.query.clone(),
+                    views: row.views,
+                });
+            }
+        }
+
+        let articles = articles
+            .into_values()
+            .map(|mut a| {
+                a.referrers = top(std::mem::take(&mut a.referrers), limit, |c| c.views, |c| c.host.clone());
+                a.search_queries = top(std::mem::take(&mut a.search_queries), limit, |c| c.views, |c| c.query.clone());
+                a
+            })
+            .collect();
+        Self {
+            referrers: top(
+                site_referrers.into_iter().map(|(host, views)| ReferrerCount { host: host.to_string(), views }).collect(),
+                limit,
+                |c| c.views,
+                |c| c.host.clone(),
+            ),
+            search_queries: top(
+                site_queries
+                    .into_iter()
+                    .map(|((engine, query), views)| SearchQueryCount { engine: engine.to_string(), query: query.to_string(), views })
+                    .collect(),
+                limit,
+                |c| c.views,
+                |c| c.query.clone(),
+            ),
+            articles: top(articles, limit, |a| a.views, |a| a.article_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_referrer() {
+        assert_eq!(host_of("https://user@WWW.Example.com:8443/path?x=1"), Some("example.com".to_string()));
+        assert_eq!(host_of("android-app://com.slack"), None);
+        assert_eq!(parse_referrer("https://blog.example.com/post/3", "blog.example.com"), None);
+
+        let news = parse_referrer("https://news.ycombinator.com/item?id=1", "blog.example.com").unwrap();
+        assert_eq!((news.host.as_str(), news.search_engine, news.search_query), ("news.ycombinator.com", None, None));
+
+        let bing = parse_referrer("https://www.bing.com/search?form=QBLH&q=Rust++%E6%89%80%E6%9C%89%E6%9D%83&x=%zz", "b").unwrap();
+        assert_eq!(bing.search_engine, Some("Bing"));
+        assert_eq!(bing.search_query.as_deref(), Some("rust 所有权"));
+
+        let baidu = parse_referrer("https://www.baidu.com/s?ie=utf-8&wd=actix%20web", "b").unwrap();
+        assert_eq!(baidu.search_query.as_deref(), Some("actix web"));
+
+        // Google hides the query but is still recognized as a search engine
+        let google = parse_referrer("https://www.google.co.uk/", "b").unwrap();
+        assert_eq!((google.search_engine, google.search_query), (Some("Google"), None));
+        assert_eq!(parse_referrer("https://notgoogle.com/?q=x", "b").unwrap().search_engine, None);
+    }
+
+    #[test]
+    fn test_is_bot() {
+        assert!(is_bot("Mozilla/5.0 (compatible; Googlebot/2.1)"));
+        assert!(is_bot(""));
+        assert!(!is_bot("Mozilla/5.0 (X11; Linux x86_64) Firefox/120.0"));
+    }
+
+    #[test]
+    fn test_report_window() {
+        let now = NaiveDateTime::parse_from_str("2025-03-31 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(ReportWindow::parse("7d").unwrap().since(now).as_deref(), Some("2025-03-24 12:00:00"));
+        assert_eq!(ReportWindow::All.since(now), None);
+        assert_eq!(ReportWindow::parse("1y"), None);
+    }
+
+    #[test]
+    fn test_report() {
+        let referrer = |article_id, host: &str, views| ReferrerRow {
+            article_id,
+            title: format!("Post {}", article_id),
+            host: host.to_string(),
+            views,
+        };
+        let referrers = vec![
+            referrer(1, "bing.com", 5),
+            referrer(1, "news.ycombinator.com", 2),
+            referrer(2, "news.ycombinator.com", 9),
+            referrer(2, "lobste.rs", 1),
+        ];
+        let queries = vec![SearchQueryRow {
+            article_id: 1,
+            title: "Post 1".to_string(),
+            engine: "Bing".to_string(),
+            query: "rust".to_string(),
+            views: 4,
+        }];
+        let report = ReferrerReport::build(&referrers, &queries, 2);
+        let hosts: Vec<(&str, i64)> = report.referrers.iter().map(|r| (r.host.as_str(), r.views)).collect();
+        assert_eq!(hosts, vec![("news.ycombinator.com", 11), ("bing.com", 5)]);
+        assert_eq!(report.search_queries[0].views, 4);
+
+        let articles: Vec<(i64, i64)> = report.articles.iter().map(|a| (a.article_id, a.views)).collect();
+        assert_eq!(articles, vec![(2, 10), (1, 7)]);
+        assert_eq!(report.articles[1].referrers[0].host, "bing.com");
+        assert_eq!(report.articles[1].search_queries.len(), 1);
+    }
+}
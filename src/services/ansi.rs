@@ -0,0 +1,200 @@
+//! Terminal output for ```` ```ansi ```` / ```` ```console ```` code blocks.
+//!
+//! SGR escape sequences (`ESC[...m`) become `<span class="ansi-...">` runs so
+//! colors survive sanitization, which only lets `class` through. Every other
+//! escape or control sequence is dropped rather than shown as raw bytes.
+
+const COLORS: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// Fence languages rendered as terminal output instead of being highlighted
+pub fn is_ansi_language(language: &str) -> bool {
+    language.eq_ignore_ascii_case("ansi") || language.eq_ignore_ascii_case("console")
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Style {
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    /// Palette index 0-15; 8 and up are the bright variants
+    fg: Option<u8>,
+    bg: Option<u8>,
+}
+
+fn color_class(prefix: &str, index: u8) -> String {
+    let bright = if index >= 8 { "bright-" } else { "" };
+    format!("{}{}{}", prefix, bright, COLORS[(index % 8) as usize])
+}
+
+/// Palette index for a 256-color code; only the 16 standard colors have classes
+fn palette_color(code: Option<&u32>) -> Option<u8> {
+    code.filter(|&&n| n < 16).map(|&n| n as u8)
+}
+
+impl Style {
+    fn classes(&self) -> Vec<String> {
+        let mut classes = Vec::new();
+        for (on, class) in [
+            (self.bold, "ansi-bold"),
+            (self.dim, "ansi-dim"),
+            (self.italic, "ansi-italic"),
+            (self.underline, "ansi-underline"),
+        ] {
+            if on {
+                classes.push(class.to_string());
+            }
+        }
+        if let Some(fg) = self.fg {
+            classes.push(color_class("ansi-", fg));
+        }
+        if let Some(bg) = self.bg {
+            classes.push(color_class("ansi-bg-", bg));
+        }
+        classes
+    }
+
+    /// Apply the parameters of one `ESC[...m` sequence; malformed ones are ignored
+    fn apply(&mut self, params: &str) {
+        let Ok(codes) = params
+            .split(';')
+            .map(|p| if p.is_empty() { Ok(0) } else { p.parse::<u32>() })
+            .collect::<Result<Vec<u32>, _>>()
+        else {
+            return;
+        };
+
+        let mut i = 0;
+        while i < codes.len() {
+            match codes[i] {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                n @ 30..=37 => self.fg = Some((n - 30) as u8),
+                39 => self.fg = None,
+                n @ 40..=47 => self.bg = Some((n - 40) as u8),
+                49 => self.bg = None,
+                n @ 90..=97 => self.fg = Some((n - 90 + 8) as u8),
+                n @ 100..=107 => self.bg = Some((n - 100 + 8) as u8),
+                // 38;5;n / 38;2;r;g;b and the 48 background forms
+                n @ (38 | 48) => {
+                    let color = match codes.get(i + 1) {
+                        Some(5) => {
+                            let color = palette_color(codes.get(i + 2));
+                            i += 2;
+                            color
+                        }
+                        Some(2) => {
+                            i += 4;
+                            None
+                        }
+                        _ => None,
+                    };
+                    if n == 38 {
+                        self.fg = color;
+                    } else {
+                        self.bg = color;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+fn push_run(html: &mut String, style: &Style, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    let escaped = html_escape::encode_text(text);
+    let classes = style.classes();
+    if classes.is_empty() {
+        html.push_str(&escaped);
+    } else {
+        html.push_str(&format!("<span class=\"{}\">{}</span>", classes.join(" "), escaped));
+    }
+}
+
+/// Escaped HTML for terminal output with SGR colors and text attributes as spans
+pub fn to_html(input: &str) -> String {
+    let mut html = String::with_capacity(input.len());
+    let mut style = Style::default();
+    let mut text = String::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\x1b' => match chars.next() {
+                Some('[') => {
+                    // CSI: parameter and intermediate bytes up to a final byte in @..~
+                    let mut params = String::new();
+                    let mut final_byte = None;
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            final_byte = Some(c);
+                            break;
+                        }
+                        params.push(c);
+                    }
+                    if final_byte == Some('m') {
+                        push_run(&mut html, &style, &text);
+                        text.clear();
+                        style.apply(&params);
+                    }
+                }
+                Some(']') => {
+                    // OSC (window titles, hyperlinks): skip to BEL or ESC \
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' {
+                            break;
+                        }
+                        if c == '\x1b' {
+                            chars.next_if_eq(&'\\');
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            '\n' | '\r' | '\t' => text.push(ch),
+            c if c.is_control() => {}
+            c => text.push(c),
+        }
+    }
+    push_run(&mut html, &style, &text);
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colors_and_attributes() {
+        let output = "\x1b[1;32m   Compiling\x1b[0m bluster\n\x1b[31merror\x1b[39m: \x1b[4m<oops>\x1b[24m\n\x1b[38;5;11;48;5;4mhi\x1b[m";
+        assert_eq!(
+            to_html(output),
+            "<span class=\"ansi-bold ansi-green\">   Compiling</span> bluster\n\
+             <span class=\"ansi-red\">error</span>: <span class=\"ansi-underline\">&lt;oops&gt;</span>\n\
+             <span class=\"ansi-bright-yellow ansi-bg-blue\">hi</span>"
+        );
+    }
+
+    #[test]
+    fn test_other_sequences_dropped() {
+        // Cursor movement, truecolor, OSC hyperlinks and stray control bytes
+        let output = "\x1b[2K\x1b]8;;https://example.com\x07link\x1b]8;;\x1b\\ \x1b[38;2;1;2;3mrgb\x1b[0m\x07 \x1b[4:3mok";
+        assert_eq!(to_html(output), "link rgb ok");
+        assert_eq!(to_html("plain & simple"), "plain &amp; simple");
+        assert!(is_ansi_language("Console"));
+    }
+}
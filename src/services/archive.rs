@@ -0,0 +1,276 @@
+//! Minimal ZIP support. Exports are written with entries stored uncompressed,
+//! which every unzip tool accepts; archives from other platforms are read with
+//! stored or deflated entries.
+
+use std::io::Read;
+
+/// CRC-32 (IEEE 802.3), as required for each ZIP entry
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Builds a ZIP archive in memory
+#[derive(Default)]
+pub struct ZipWriter {
+    buffer: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+// Fixed 1980-01-01 00:00 DOS timestamp; exports carry their own dates in front matter
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+// Bit 11: file names are UTF-8
+const FLAGS: u16 = 1 << 11;
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file; `name` uses `/` as the directory separator
+    pub fn add_file(&mut self, name: &str, data: &[u8]) {
+        let crc = crc32(data);
+        let entry = Entry {
+            name: name.to_string(),
+            crc,
+            size: data.len() as u32,
+            offset: self.buffer.len() as u32,
+        };
+        let b = &mut self.buffer;
+        b.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        b.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        b.extend_from_slice(&FLAGS.to_le_bytes());
+        b.extend_from_slice(&0u16.to_le_bytes()); // stored
+        b.extend_from_slice(&DOS_TIME.to_le_bytes());
+        b.extend_from_slice(&DOS_DATE.to_le_bytes());
+        b.extend_from_slice(&crc.to_le_bytes());
+        b.extend_from_slice(&entry.size.to_le_bytes());
+        b.extend_from_slice(&entry.size.to_le_bytes());
+        b.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        b.extend_from_slice(&0u16.to_le_bytes()); // extra length
+        b.extend_from_slice(name.as_bytes());
+        b.extend_from_slice(data);
+        self.entries.push(entry);
+    }
+
+    /// Write the central directory and return the archive bytes
+    pub fn finish(mut self) -> Vec<u8> {
+        let directory_offset = self.buffer.len() as u32;
+        for entry in &self.entries {
+            let b = &mut self.buffer;
+            b.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            b.extend_from_slice(&20u16.to_le_bytes()); // version made by
+            b.extend_from_slice(&20u16.to_le_bytes()); // version needed
+            b.extend_from_slice(&FLAGS.to_le_bytes());
+            b.extend_from_slice(&0u16.to_le_bytes());
+            b.extend_from_slice(&DOS_TIME.to_le_bytes());
+            b.extend_from_slice(&DOS_DATE.to_le_bytes());
+            b.extend_from_slice(&entry.crc.to_le_bytes());
+            b.extend_from_slice(&entry.size.to_le_bytes());
+            b.extend_from_slice(&entry.size.to_le_bytes());
+            b.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            b.extend_from_slice(&0u16.to_le_bytes()); // extra length
+            b.extend_from_slice(&0u16.to_le_bytes()); // comment length
+            b.extend_from_slice(&0u16.to_le_bytes()); // disk number
+            b.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+            b.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+            b.extend_from_slice(&entry.offset.to_le_bytes());
+            b.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = self.buffer.len() as u32 - directory_offset;
+        let count = self.entries.len() as u16;
+        let b = &mut self.buffer;
+        b.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        b.extend_from_slice(&0u16.to_le_bytes());
+        b.extend_from_slice(&0u16.to_le_bytes());
+        b.extend_from_slice(&count.to_le_bytes());
+        b.extend_from_slice(&count.to_le_bytes());
+        b.extend_from_slice(&directory_size.to_le_bytes());
+        b.extend_from_slice(&directory_offset.to_le_bytes());
+        b.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.buffer
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ArchiveError {
+    #[error("Not a ZIP archive")]
+    NotZip,
+    #[error("ZIP archive is damaged")]
+    Corrupt,
+    #[error("Unsupported compression in {0}")]
+    UnsupportedCompression(String),
+    #[error("Archive contents exceed {0} bytes")]
+    TooLarge(usize),
+}
+
+/// A file read from an archive
+#[derive(Debug, PartialEq, Eq)]
+pub struct ZipEntry {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+fn u16_at(bytes: &[u8], pos: usize) -> Result<u16, ArchiveError> {
+    bytes.get(pos..pos + 2).map(|b| u16::from_le_bytes([b[0], b[1]])).ok_or(ArchiveError::Corrupt)
+}
+
+fn u32_at(bytes: &[u8], pos: usize) -> Result<u32, ArchiveError> {
+    bytes.get(pos..pos + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).ok_or(ArchiveError::Corrupt)
+}
+
+/// Read every file of an archive, skipping directories. `max_total` caps the
+/// uncompressed size, so a small archive cannot expand without bound.
+pub fn read_zip(bytes: &[u8], max_total: usize) -> Result<Vec<ZipEntry>, ArchiveError> {
+    // End of central directory: 22 bytes plus a comment of up to 64 KiB
+    let search_from = bytes.len().saturating_sub(22 + u16::MAX as usize);
+    let eocd = (search_from..=bytes.len().saturating_sub(22))
+        .rev()
+        .find(|&pos| bytes[pos..].starts_with(b"PK\x05\x06"))
+        .ok_or(ArchiveError::NotZip)?;
+    let count = u16_at(bytes, eocd + 10)?;
+    let mut pos = u32_at(bytes, eocd + 16)? as usize;
+
+    let mut entries = Vec::with_capacity(count as usize);
+    let mut total = 0;
+    for _ in 0..count {
+        if u32_at(bytes, pos)? != 0x0201_4b50 {
+            return Err(ArchiveError::Corrupt);
+        }
+        let method = u16_at(bytes, pos + 10)?;
+        let crc = u32_at(bytes, pos + 16)?;
+        let compressed_size = u32_at(bytes, pos + 20)? as usize;
+        let size = u32_at(bytes, pos + 24)? as usize;
+        let name_length = u16_at(bytes, pos + 28)? as usize;
+        let extra_length = u16_at(bytes, pos + 30)? as usize;
+        let comment_length = u16_at(bytes, pos + 32)? as usize;
+        let offset = u32_at(bytes, pos + 42)? as usize;
+        let name = bytes.get(pos + 46..pos + 46 + name_length).ok_or(ArchiveError::Corrupt)?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        pos += 46 + name_length + extra_length + comment_length;
+        if name.ends_with('/') {
+            continue;
+        }
+
+        // The local header repeats the name and may carry a different extra field
+        if u32_at(bytes, offset)? != 0x0403_4b50 {
+            return Err(ArchiveError::Corrupt);
+        }
+        let data_start = offset + 30 + u16_at(bytes, offset + 26)? as usize + u16_at(bytes, offset + 28)? as usize;
+        let compressed = bytes.get(data_start..data_start + compressed_size).ok_or(ArchiveError::Corrupt)?;
+        total += size;
+        if total > max_total {
+            return Err(ArchiveError::TooLarge(max_total));
+        }
+        let data = match method {
+            0 => compressed.to_vec(),
+            8 => {
+                let mut data = Vec::with_capacity(size);
+                flate2::read::DeflateDecoder::new(compressed)
+                    .take(size as u64 + 1)
+                    .read_to_end(&mut data)
+                    .map_err(|_| ArchiveError::Corrupt)?;
+                data
+            }
+            _ => return Err(ArchiveError::UnsupportedCompression(name)),
+        };
+        if data.len() != size || crc32(&data) != crc {
+            return Err(ArchiveError::Corrupt);
+        }
+        entries.push(ZipEntry { name, data });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_zip_layout() {
+        let mut zip = ZipWriter::new();
+        zip.add_file("a.md", b"hello");
+        zip.add_file("revisions/1.md", b"");
+        let bytes = zip.finish();
+
+        assert_eq!(&bytes[..4], b"PK\x03\x04");
+        // End of central directory: 22 bytes, two entries
+        let eocd = &bytes[bytes.len() - 22..];
+        assert_eq!(&eocd[..4], b"PK\x05\x06");
+        assert_eq!(u16::from_le_bytes([eocd[10], eocd[11]]), 2);
+        let directory_offset = u32::from_le_bytes([eocd[16], eocd[17], eocd[18], eocd[19]]) as usize;
+        assert_eq!(&bytes[directory_offset..directory_offset + 4], b"PK\x01\x02");
+        // First local entry holds the data uncompressed after its 30-byte header and name
+        assert_eq!(&bytes[30 + 4..30 + 4 + 5], b"hello");
+    }
+
+    #[test]
+    fn test_read_stored_zip() {
+        let mut zip = ZipWriter::new();
+        zip.add_file("posts/a.html", b"<p>A</p>");
+        zip.add_file("profile/", b"");
+        zip.add_file("README.html", b"");
+        let entries = read_zip(&zip.finish(), 1024).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], ZipEntry { name: "posts/a.html".to_string(), data: b"<p>A</p>".to_vec() });
+
+        let mut zip = ZipWriter::new();
+        zip.add_file("big.html", &[b'x'; 100]);
+        assert_eq!(read_zip(&zip.finish(), 99), Err(ArchiveError::TooLarge(99)));
+        assert_eq!(read_zip(b"not a zip", 1024), Err(ArchiveError::NotZip));
+    }
+
+    #[test]
+    fn test_read_deflated_zip() {
+        use flate2::{write::DeflateEncoder, Compression};
+        use std::io::Write;
+
+        let data = "Hello from Medium. ".repeat(50);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        // A stored archive with the entry swapped for its deflated form
+        let mut zip = ZipWriter::new();
+        zip.add_file("a.html", data.as_bytes());
+        let stored = zip.finish();
+        let name_end = 30 + "a.html".len();
+        let mut bytes = stored[..name_end].to_vec();
+        bytes.extend_from_slice(&compressed);
+        let directory = bytes.len();
+        bytes.extend_from_slice(&stored[name_end + data.len()..]);
+        bytes[8..10].copy_from_slice(&8u16.to_le_bytes());
+        bytes[18..22].copy_from_slice(&(compressed.len() as u32).to_le_bytes());
+        bytes[directory + 10..directory + 12].copy_from_slice(&8u16.to_le_bytes());
+        bytes[directory + 20..directory + 24].copy_from_slice(&(compressed.len() as u32).to_le_bytes());
+        let eocd = bytes.len() - 22;
+        bytes[eocd + 16..eocd + 20].copy_from_slice(&(directory as u32).to_le_bytes());
+
+        let entries = read_zip(&bytes, 1 << 20).unwrap();
+        assert_eq!(entries[0].data, data.as_bytes());
+
+        // A flipped byte fails the checksum
+        bytes[name_end + 2] ^= 0xFF;
+        assert!(read_zip(&bytes, 1 << 20).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+//! Saved article filters ("smart lists") for the admin article list, e.g.
+//! "older than 30 days without a meta description". Each admin keeps their own
+//! named filters; the criteria are stored as JSON.
+
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+pub const MAX_NAME_LENGTH: usize = 60;
+/// Day counts above this (a century) are rejected
+pub const MAX_DAYS: u32 = 36500;
+
+/// Criteria an article must all meet; unset ones are ignored
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArticleFilter {
+    /// Case-insensitive text in the title or content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Created more than this many days ago
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub older_than_days: Option<u32>,
+    /// Created within the last this many days
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub newer_than_days: Option<u32>,
+    /// Hidden from search engines (`true`) or not (`false`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noindex: Option<bool>,
+    /// No meta description set
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub missing_description: bool,
+    /// No image in the content to use as cover
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub missing_image: bool,
+}
+
+/// What a filter looks at for one article
+pub struct Candidate<'a> {
+    pub title: &'a str,
+    pub content: &'a str,
+    /// `YYYY-MM-DD HH:MM:SS`, UTC as stored by SQLite
+    pub created_at: &'a str,
+    pub meta_description: Option<&'a str>,
+    pub noindex: bool,
+    pub has_image: bool,
+}
+
+impl ArticleFilter {
+    /// Parse and check criteria submitted by an admin
+    pub fn parse(json: &serde_json::Value) -> Result<Self, String> {
+        let mut filter: Self = serde_json::from_value(json.clone()).map_err(|e| format!("Invalid filter: {}", e))?;
+        filter.text = filter.text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+        if filter == Self::default() {
+            return Err("Filter needs at least one criterion".to_string());
+        }
+        if filter.older_than_days.into_iter().chain(filter.newer_than_days).any(|days| days > MAX_DAYS) {
+            return Err(format!("Day counts must be at most {}", MAX_DAYS));
+        }
+        Ok(filter)
+    }
+
+    /// From `saved_filters.criteria`; `None` if it no longer parses
+    pub fn from_json(json: &str) -> Option<Self> {
+        serde_json::from_str(json).ok()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    pub fn matches(&self, article: &Candidate, now: NaiveDateTime) -> bool {
+        if let Some(text) = &self.text {
+            let text = text.to_lowercase();
+            if !article.title.to_lowercase().contains(&text) && !article.content.to_lowercase().contains(&text) {
+                return false;
+            }
+        }
+        if self.older_than_days.is_some() || self.newer_than_days.is_some() {
+            let Ok(created_at) = NaiveDateTime::parse_from_str(article.created_at, "%Y-%m-%d %H:%M:%S") else {
+                return false;
+            };
+            if self.older_than_days.is_some_and(|days| created_at > now - Duration::days(i64::from(days))) {
+                return false;
+            }
+            if self.newer_than_days.is_some_and(|days| created_at < now - Duration::days(i64::from(days))) {
+                return false;
+            }
+        }
+        if self.noindex.is_some_and(|noindex| noindex != article.noindex) {
+            return false;
+        }
+        if self.missing_description && article.meta_description.is_some_and(|d| !d.trim().is_empty()) {
+            return false;
+        }
+        !(self.missing_image && article.has_image)
+    }
+}
+
+/// Name for a saved filter, trimmed
+pub fn validate_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!("Filter name is required (max {} characters)", MAX_NAME_LENGTH));
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2025-03-31 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn article(created_at: &str) -> Candidate<'_> {
+        Candidate {
+            title: "Learning Rust",
+            content: "Ownership and borrowing",
+            created_at,
+            meta_description: None,
+            noindex: false,
+            has_image: false,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let filter = ArticleFilter::parse(&json!({"text": " rust ", "older_than_days": 30})).unwrap();
+        assert_eq!(filter.text.as_deref(), Some("rust"));
+        assert_eq!(filter.to_json(), r#"{"text":"rust","older_than_days":30}"#);
+        assert_eq!(ArticleFilter::from_json(&filter.to_json()), Some(filter));
+
+        assert!(ArticleFilter::parse(&json!({})).is_err());
+        assert!(ArticleFilter::parse(&json!({"text": "  "})).is_err());
+        assert!(ArticleFilter::parse(&json!({"tag": "rust"})).is_err());
+        assert!(ArticleFilter::parse(&json!({"newer_than_days": 99999})).is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let old = article("2025-01-01 08:00:00");
+        let recent = article("2025-03-30 08:00:00");
+
+        let stale = ArticleFilter { older_than_days: Some(30), ..Default::default() };
+        assert!(stale.matches(&old, now()) && !stale.matches(&recent, now()));
+        let fresh = ArticleFilter { newer_than_days: Some(7), ..Default::default() };
+        assert!(!fresh.matches(&old, now()) && fresh.matches(&recent, now()));
+
+        let text = ArticleFilter { text: Some("BORROW".to_string()), ..Default::default() };
+        assert!(text.matches(&old, now()));
+
+        let bare = ArticleFilter { missing_description: true, missing_image: true, ..Default::default() };
+        assert!(bare.matches(&old, now()));
+        let illustrated = Candidate { has_image: true, ..article("2025-01-01 08:00:00") };
+        assert!(!bare.matches(&illustrated, now()));
+        let described = Candidate { meta_description: Some("About Rust"), ..article("2025-01-01 08:00:00") };
+        assert!(!bare.matches(&described, now()));
+
+        let hidden = ArticleFilter { noindex: Some(true), ..Default::default() };
+        assert!(!hidden.matches(&old, now()));
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert_eq!(validate_name("  Stale posts ").unwrap(), "Stale posts");
+        assert!(validate_name(" ").is_err());
+        assert!(validate_name(&"x".repeat(MAX_NAME_LENGTH + 1)).is_err());
+    }
+}
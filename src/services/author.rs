@@ -0,0 +1,112 @@
+//! Public author profiles: display name, markdown bio, website and avatar,
+//! shown on `/author/{username}` and in post bylines.
+
+use crate::models::User;
+use serde::Serialize;
+
+pub const MAX_DISPLAY_NAME_LENGTH: usize = 100;
+pub const MAX_BIO_LENGTH: usize = 5000;
+pub const MAX_URL_LENGTH: usize = 2048;
+
+/// Path of an author's public page
+pub fn author_path(username: &str) -> String {
+    format!("/author/{}", super::avatar::url_encode(username))
+}
+
+/// Website links must be absolute http(s) URLs
+pub fn is_valid_website(url: &str) -> bool {
+    url.len() <= MAX_URL_LENGTH
+        && (url.starts_with("https://") || url.starts_with("http://"))
+        && !url.contains(char::is_whitespace)
+}
+
+/// Avatars are uploaded images under `/media/` or absolute http(s) URLs
+pub fn is_valid_avatar(url: &str) -> bool {
+    let local = url
+        .strip_prefix("/media/")
+        .is_some_and(|name| !name.is_empty() && !name.contains(['/', '\\']) && name != "..");
+    local || is_valid_website(url)
+}
+
+/// What templates need to show an author; `bio_html` is filled in by the caller
+/// since rendering markdown is async
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuthorProfile {
+    pub username: String,
+    /// Display name, or the username when none is set
+    pub name: String,
+    pub url: String,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
+    pub bio_html: Option<String>,
+}
+
+impl AuthorProfile {
+    pub fn new(user: &User) -> Self {
+        let non_empty = |value: &Option<String>| value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+        Self {
+            username: user.username.clone(),
+            name: non_empty(&user.display_name).unwrap_or_else(|| user.username.clone()),
+            url: author_path(&user.username),
+            website: non_empty(&user.website),
+            avatar_url: non_empty(&user.avatar_url),
+            bio_html: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user() -> User {
+        User {
+            id: 1,
+            username: "jane doe".to_string(),
+            password_hash: String::new(),
+            created_at: "2025-01-01 10:00:00".to_string(),
+            security_question: None,
+            security_answer_hash: None,
+            last_login_at: None,
+            last_login_ip: None,
+            must_change_password: false,
+            display_name: None,
+            bio: None,
+            website: None,
+            avatar_url: None,
+        }
+    }
+
+    #[test]
+    fn test_profile_defaults() {
+        let profile = AuthorProfile::new(&user());
+        assert_eq!(profile.name, "jane doe");
+        assert_eq!(profile.url, "/author/jane%20doe");
+        assert_eq!((profile.website, profile.avatar_url), (None, None));
+
+        let user = User {
+            display_name: Some("Jane Doe".to_string()),
+            website: Some(" ".to_string()),
+            avatar_url: Some("/media/abc.png".to_string()),
+            ..user()
+        };
+        let profile = AuthorProfile::new(&user);
+        assert_eq!(profile.name, "Jane Doe");
+        assert_eq!(profile.website, None);
+        assert_eq!(profile.avatar_url.as_deref(), Some("/media/abc.png"));
+    }
+
+    #[test]
+    fn test_url_validation() {
+        assert!(is_valid_website("https://example.com/~jane"));
+        assert!(!is_valid_website("javascript:alert(1)"));
+        assert!(!is_valid_website("example.com"));
+        assert!(!is_valid_website("https://example.com/a b"));
+
+        assert!(is_valid_avatar("/media/abc.png"));
+        assert!(is_valid_avatar("https://example.com/me.jpg"));
+        assert!(!is_valid_avatar("/media/../data/blog.db"));
+        assert!(!is_valid_avatar("/media/"));
+        assert!(!is_valid_avatar("data:image/svg+xml,<svg/>"));
+    }
+}
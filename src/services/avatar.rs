@@ -0,0 +1,98 @@
+use sha2::{Digest, Sha256};
+
+/// How commenter avatars are shown, from `AVATAR_MODE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AvatarMode {
+    /// No avatars
+    Off,
+    /// Locally generated identicons only; nothing is sent to third parties
+    Identicon,
+    /// Gravatar for commenters with an email, identicons as the fallback
+    Gravatar,
+}
+
+impl AvatarMode {
+    pub fn from_env() -> Self {
+        match std::env::var("AVATAR_MODE").unwrap_or_default().to_lowercase().as_str() {
+            "off" | "none" => AvatarMode::Off,
+            "gravatar" => AvatarMode::Gravatar,
+            _ => AvatarMode::Identicon,
+        }
+    }
+}
+
+/// Gravatar's SHA-256 hash of a trimmed, lowercased email address
+pub fn gravatar_hash(email: &str) -> String {
+    hex::encode(Sha256::digest(email.trim().to_lowercase().as_bytes()))
+}
+
+/// Gravatar image URL; `fallback` must be an absolute URL Gravatar can fetch
+pub fn gravatar_url(email: &str, size: u32, fallback: &str) -> String {
+    let fallback: String = url_encode(fallback);
+    format!("https://www.gravatar.com/avatar/{}?s={}&d={}", gravatar_hash(email), size, fallback)
+}
+
+pub(crate) fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// 5x5 horizontally symmetric identicon as SVG, derived from `seed`
+pub fn identicon_svg(seed: &str) -> String {
+    let hash = Sha256::digest(seed.as_bytes());
+    let hue = u16::from_be_bytes([hash[0], hash[1]]) % 360;
+    let color = format!("hsl({}, 55%, 50%)", hue);
+
+    let mut cells = String::new();
+    for row in 0..5 {
+        for col in 0..3 {
+            // 每个格子取一个比特，左右镜像
+            let bit = row * 3 + col;
+            if hash[2 + bit / 8] >> (bit % 8) & 1 == 1 {
+                cells.push_str(&format!(r#"<rect x="{}" y="{}" width="1" height="1"/>"#, col, row));
+                if col != 2 {
+                    cells.push_str(&format!(r#"<rect x="{}" y="{}" width="1" height="1"/>"#, 4 - col, row));
+                }
+            }
+        }
+    }
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-0.5 -0.5 6 6" width="80" height="80" shape-rendering="crispEdges"><rect x="-0.5" y="-0.5" width="6" height="6" fill="#f0f0f0"/><g fill="{}">{}</g></svg>"##,
+        color, cells
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gravatar_hash_normalizes_email() {
+        assert_eq!(gravatar_hash(" User@Example.com "), gravatar_hash("user@example.com"));
+        assert_eq!(
+            gravatar_hash("user@example.com"),
+            "b4c9a289323b21a01c3e940f150eb9b8c542587f1abfd8f0e1cc1ffc5e475514"
+        );
+    }
+
+    #[test]
+    fn test_gravatar_url_encodes_fallback() {
+        let url = gravatar_url("user@example.com", 80, "https://blog.example.com/avatar/ab.svg");
+        assert!(url.starts_with("https://www.gravatar.com/avatar/b4c9a289"));
+        assert!(url.ends_with("&d=https%3A%2F%2Fblog.example.com%2Favatar%2Fab.svg"));
+    }
+
+    #[test]
+    fn test_identicon_is_deterministic() {
+        let svg = identicon_svg("seed");
+        assert_eq!(svg, identicon_svg("seed"));
+        assert_ne!(svg, identicon_svg("other seed"));
+        assert!(svg.starts_with("<svg"));
+    }
+}
@@ -0,0 +1,118 @@
+//! `Cache-Control` headers by route, from `CACHE_CONTROL_RULES`. Rules are
+//! `pattern=value` pairs separated by `;`, e.g.
+//! `/media/*=public, max-age=86400;/admin*=no-store;/=public, max-age=60`.
+//! A pattern ending in `*` matches every path with that prefix, any other
+//! pattern only that exact path; the first matching rule wins.
+
+use actix_web::http::header::HeaderValue;
+use log::warn;
+
+/// Rules used when `CACHE_CONTROL_RULES` is not set
+pub const DEFAULT_RULES: &str = "/media/*=public, max-age=86400;/admin*=no-store;/login=no-store;/=public, max-age=60";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Pattern {
+    Exact(String),
+    Prefix(String),
+}
+
+impl Pattern {
+    fn parse(pattern: &str) -> Option<Self> {
+        if !pattern.starts_with('/') {
+            return None;
+        }
+        Some(match pattern.strip_suffix('*') {
+            Some(prefix) => Pattern::Prefix(prefix.to_string()),
+            None => Pattern::Exact(pattern.to_string()),
+        })
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        match self {
+            Pattern::Exact(exact) => path == exact,
+            Pattern::Prefix(prefix) => path.starts_with(prefix.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CachePolicy {
+    rules: Vec<(Pattern, HeaderValue)>,
+}
+
+impl CachePolicy {
+    /// Parse a rule list; malformed rules are returned separately so they can be reported
+    pub fn parse(spec: &str) -> (Self, Vec<String>) {
+        let mut rules = Vec::new();
+        let mut invalid = Vec::new();
+        for rule in spec.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            let parsed = rule.split_once('=').and_then(|(pattern, value)| {
+                let value = HeaderValue::from_str(value.trim()).ok().filter(|v| !v.is_empty())?;
+                Some((Pattern::parse(pattern.trim())?, value))
+            });
+            match parsed {
+                Some(rule) => rules.push(rule),
+                None => invalid.push(rule.to_string()),
+            }
+        }
+        (Self { rules }, invalid)
+    }
+
+    /// `CACHE_CONTROL_RULES`, or the defaults when unset; an empty value adds no headers
+    pub fn from_env() -> Self {
+        let spec = std::env::var("CACHE_CONTROL_RULES").unwrap_or_else(|_| DEFAULT_RULES.to_string());
+        let (policy, invalid) = Self::parse(&spec);
+        for rule in invalid {
+            warn!("Ignoring invalid CACHE_CONTROL_RULES entry: {}", rule);
+        }
+        policy
+    }
+
+    /// Header value for a request path, if any rule matches
+    pub fn for_path(&self, path: &str) -> Option<&HeaderValue> {
+        self.rules.iter().find(|(pattern, _)| pattern.matches(path)).map(|(_, value)| value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_rules() {
+        let (policy, invalid) = CachePolicy::parse(DEFAULT_RULES);
+        assert!(invalid.is_empty());
+        let value = |path| policy.for_path(path).map(|v| v.to_str().unwrap().to_string());
+        assert_eq!(value("/media/a.png").as_deref(), Some("public, max-age=86400"));
+        assert_eq!(value("/admin").as_deref(), Some("no-store"));
+        assert_eq!(value("/admin/settings").as_deref(), Some("no-store"));
+        assert_eq!(value("/").as_deref(), Some("public, max-age=60"));
+        assert_eq!(value("/post/1"), None);
+        assert_eq!(value("/login/x"), None);
+    }
+
+    #[test]
+    fn test_first_match_wins() {
+        let (policy, invalid) = CachePolicy::parse(" /api/v1/graph = no-cache ; /api/* = public, max-age=30 ;; ");
+        assert!(invalid.is_empty());
+        assert_eq!(policy.for_path("/api/v1/graph").unwrap(), "no-cache");
+        assert_eq!(policy.for_path("/api/v1/articles").unwrap(), "public, max-age=30");
+        assert_eq!(policy.len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        let (policy, invalid) = CachePolicy::parse("static=public;/a=;/ok=no-store;/c=bad\u{7f}");
+        assert_eq!(invalid, vec!["static=public", "/a=", "/c=bad\u{7f}"]);
+        assert_eq!(policy.len(), 1);
+        assert!(CachePolicy::parse("").0.is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+//! Canonical URL overrides for articles cross-posted from elsewhere: the
+//! page head points search engines at the original publication instead of
+//! this site's `/post/{id}`.
+
+/// Longest override accepted
+pub const MAX_CANONICAL_URL_LENGTH: usize = 2048;
+
+/// Trimmed override, or `None` unless it is an absolute `http(s)` URL with a host
+pub fn normalize(url: &str) -> Option<String> {
+    let url = url.trim();
+    let valid = url.len() <= MAX_CANONICAL_URL_LENGTH
+        && !url.chars().any(|c| c.is_whitespace() || c.is_control() || c == '"' || c == '<' || c == '>')
+        && super::analytics::host_of(url).is_some();
+    valid.then(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("  https://dev.example/posts/rust-tips?ref=1 ").as_deref(),
+            Some("https://dev.example/posts/rust-tips?ref=1")
+        );
+        assert_eq!(normalize("http://example.com").as_deref(), Some("http://example.com"));
+        assert_eq!(normalize("/post/3"), None);
+        assert_eq!(normalize("javascript:alert(1)"), None);
+        assert_eq!(normalize("https://"), None);
+        assert_eq!(normalize("https://example.com/a b"), None);
+        assert_eq!(normalize(&format!("https://example.com/{}", "a".repeat(MAX_CANONICAL_URL_LENGTH))), None);
+    }
+}
@@ -0,0 +1,182 @@
+//! Article categories. Unlike tags, an article is in at most one category, and
+//! categories nest: each may have a parent. A category's page at
+//! `/category/{slug}` lists the articles in it and in all its subcategories.
+
+use crate::models::Category;
+use serde::Serialize;
+
+pub const MAX_SLUG_LENGTH: usize = 64;
+
+/// A category with its place in the hierarchy, for pages and pickers
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryEntry {
+    pub id: i64,
+    pub name: String,
+    pub slug: String,
+    pub url: String,
+    pub parent_id: Option<i64>,
+    /// 0 for top-level categories
+    pub depth: usize,
+}
+
+impl CategoryEntry {
+    fn new(category: &Category, depth: usize) -> Self {
+        Self {
+            id: category.id,
+            name: category.name.clone(),
+            slug: category.slug.clone(),
+            url: category_url(&category.slug),
+            parent_id: category.parent_id,
+            depth,
+        }
+    }
+}
+
+/// Path of the category's listing page
+pub fn category_url(slug: &str) -> String {
+    format!("/category/{}", super::avatar::url_encode(slug))
+}
+
+/// Lowercase letters, digits, `-` and `_`, neither starting nor ending with `-`
+pub fn is_valid_slug(slug: &str) -> bool {
+    !slug.is_empty()
+        && slug.chars().count() <= MAX_SLUG_LENGTH
+        && !slug.starts_with('-')
+        && !slug.ends_with('-')
+        && slug.chars().all(|c| (c.is_alphanumeric() && !c.is_uppercase()) || c == '-' || c == '_')
+}
+
+/// Slug for a category created without one; None when the name has no letters or digits
+pub fn slug_from_name(name: &str) -> Option<String> {
+    let slug = super::toc::slugify(name);
+    let mut collapsed = String::with_capacity(slug.len());
+    for part in slug.split('-').filter(|part| !part.is_empty()) {
+        if !collapsed.is_empty() {
+            collapsed.push('-');
+        }
+        collapsed.push_str(part);
+    }
+    let slug: String = collapsed.chars().take(MAX_SLUG_LENGTH).collect();
+    let slug = slug.trim_end_matches('-').to_string();
+    // slugify 对空标题返回 "section"
+    (is_valid_slug(&slug) && name.chars().any(char::is_alphanumeric)).then_some(slug)
+}
+
+/// All categories depth first, each followed by its subcategories, siblings by
+/// name; a category whose parent is missing is shown at the top level
+pub fn tree(categories: &[Category]) -> Vec<CategoryEntry> {
+    let mut entries = Vec::with_capacity(categories.len());
+    let is_root = |c: &Category| c.parent_id.is_none_or(|parent| !categories.iter().any(|p| p.id == parent));
+    let mut roots: Vec<&Category> = categories.iter().filter(|c| is_root(c)).collect();
+    roots.sort_by_key(|c| c.name.to_lowercase());
+    for root in roots {
+        add_subtree(categories, root, 0, &mut entries);
+    }
+    entries
+}
+
+fn add_subtree(categories: &[Category], category: &Category, depth: usize, entries: &mut Vec<CategoryEntry>) {
+    // 数据被改坏成环时不再深入
+    if entries.iter().any(|e| e.id == category.id) {
+        return;
+    }
+    entries.push(CategoryEntry::new(category, depth));
+    let mut children: Vec<&Category> = categories.iter().filter(|c| c.parent_id == Some(category.id)).collect();
+    children.sort_by_key(|c| c.name.to_lowercase());
+    for child in children {
+        add_subtree(categories, child, depth + 1, entries);
+    }
+}
+
+/// `id` and the ids of all categories below it
+pub fn descendants(categories: &[Category], id: i64) -> Vec<i64> {
+    let mut ids = vec![id];
+    let mut i = 0;
+    while i < ids.len() {
+        let parent = ids[i];
+        for child in categories.iter().filter(|c| c.parent_id == Some(parent)) {
+            if !ids.contains(&child.id) {
+                ids.push(child.id);
+            }
+        }
+        i += 1;
+    }
+    ids
+}
+
+/// The categories above `id`, top-level first, for breadcrumbs
+pub fn ancestors(categories: &[Category], id: i64) -> Vec<CategoryEntry> {
+    let mut chain: Vec<&Category> = Vec::new();
+    let mut current = categories.iter().find(|c| c.id == id).and_then(|c| c.parent_id);
+    while let Some(parent_id) = current {
+        let Some(parent) = categories.iter().find(|c| c.id == parent_id) else {
+            break;
+        };
+        if parent.id == id || chain.iter().any(|c| c.id == parent.id) {
+            break;
+        }
+        chain.push(parent);
+        current = parent.parent_id;
+    }
+    chain.iter().rev().enumerate().map(|(depth, c)| CategoryEntry::new(c, depth)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn category(id: i64, name: &str, parent_id: Option<i64>) -> Category {
+        Category { id, name: name.to_string(), slug: name.to_lowercase(), parent_id }
+    }
+
+    fn sample() -> Vec<Category> {
+        vec![
+            category(1, "Programming", None),
+            category(2, "Rust", Some(1)),
+            category(3, "Life", None),
+            category(4, "Async", Some(2)),
+            category(5, "Go", Some(1)),
+            category(6, "Lost", Some(99)),
+        ]
+    }
+
+    #[test]
+    fn test_slugs() {
+        assert_eq!(slug_from_name("Rust & Web").as_deref(), Some("rust-web"));
+        assert_eq!(slug_from_name("  读书 笔记 ").as_deref(), Some("读书-笔记"));
+        assert_eq!(slug_from_name("???"), None);
+        assert!(is_valid_slug("rust-web_2"));
+        assert!(!is_valid_slug("Rust"));
+        assert!(!is_valid_slug("-rust"));
+        assert!(!is_valid_slug("a/b"));
+        assert!(!is_valid_slug(""));
+        assert_eq!(category_url("读书"), "/category/%E8%AF%BB%E4%B9%A6");
+    }
+
+    #[test]
+    fn test_tree() {
+        let entries = tree(&sample());
+        let order: Vec<(&str, usize)> = entries.iter().map(|e| (e.name.as_str(), e.depth)).collect();
+        assert_eq!(
+            order,
+            vec![("Life", 0), ("Lost", 0), ("Programming", 0), ("Go", 1), ("Rust", 1), ("Async", 2)]
+        );
+        assert_eq!(entries[3].url, "/category/go");
+    }
+
+    #[test]
+    fn test_descendants_and_ancestors() {
+        let categories = sample();
+        assert_eq!(descendants(&categories, 1), vec![1, 2, 5, 4]);
+        assert_eq!(descendants(&categories, 3), vec![3]);
+        let names: Vec<String> = ancestors(&categories, 4).into_iter().map(|e| e.name).collect();
+        assert_eq!(names, vec!["Programming", "Rust"]);
+        assert!(ancestors(&categories, 6).is_empty());
+
+        // a cycle must not hang
+        let cyclic = vec![category(1, "A", Some(2)), category(2, "B", Some(1))];
+        assert_eq!(descendants(&cyclic, 1), vec![1, 2]);
+        assert_eq!(ancestors(&cyclic, 1).len(), 1);
+        assert!(tree(&cyclic).is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+use std::net::IpAddr;
+
+use actix_web::HttpRequest;
+use actix_web::http::header::HeaderMap;
+
+/// One entry of `TRUSTED_PROXIES`: a single address or a CIDR block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Network {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Network {
+    fn parse(value: &str) -> Option<Self> {
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr.parse::<IpAddr>().ok()?, Some(prefix.parse::<u8>().ok()?)),
+            None => (value.parse::<IpAddr>().ok()?, None),
+        };
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Self { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        // IPv4 映射的 IPv6 地址按 IPv4 比较
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            v4 => v4,
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Reverse proxies whose `Forwarded` / `X-Forwarded-For` headers name the real client.
+/// Headers from any other peer are ignored, so clients can't pick their own IP to dodge
+/// rate limits or fake sign-in addresses.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    networks: Vec<Network>,
+}
+
+impl TrustedProxies {
+    /// Comma-separated addresses or CIDR blocks, e.g. `127.0.0.1,10.0.0.0/8`; invalid entries are skipped
+    pub fn parse(value: &str) -> Self {
+        let networks = value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| {
+                let network = Network::parse(entry);
+                if network.is_none() {
+                    log::warn!("Ignoring invalid TRUSTED_PROXIES entry: {}", entry);
+                }
+                network
+            })
+            .collect();
+        Self { networks }
+    }
+
+    /// `TRUSTED_PROXIES`; unset trusts no proxy and uses the connection's peer address
+    pub fn from_env() -> Self {
+        Self::parse(&std::env::var("TRUSTED_PROXIES").unwrap_or_default())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.networks.is_empty()
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(ip))
+    }
+
+    /// The client address for `req`, or "unknown" when there is no peer (e.g. in tests)
+    pub fn client_ip(&self, req: &HttpRequest) -> String {
+        match req.peer_addr() {
+            Some(peer) => self.resolve(peer.ip(), req.headers()).to_string(),
+            None => "unknown".to_string(),
+        }
+    }
+
+    /// Walk the forwarding chain from the nearest hop and stop at the first address
+    /// not belonging to a trusted proxy; earlier entries could have been sent by the client.
+    fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.is_trusted(peer) {
+            return peer;
+        }
+        let mut client = peer;
+        for hop in forwarded_chain(headers).into_iter().rev() {
+            // 无法解析的一跳之前的内容都不可信
+            let Some(ip) = hop else {
+                break;
+            };
+            client = ip;
+            if !self.is_trusted(ip) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// Addresses listed by `Forwarded`, or by `X-Forwarded-For` when that is absent, client first.
+/// Obfuscated or unparsable entries are `None`.
+fn forwarded_chain(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let forwarded: Vec<Option<IpAddr>> = headers
+        .get_all("forwarded")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (name, value) = pair.split_once('=')?;
+                name.trim().eq_ignore_ascii_case("for").then(|| parse_node(value))
+            })
+        })
+        .collect();
+    if !forwarded.is_empty() {
+        return forwarded;
+    }
+    headers
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(parse_node)
+        .collect()
+}
+
+/// `1.2.3.4`, `1.2.3.4:80`, `"[2001:db8::1]:80"` or a bare IPv6 address
+fn parse_node(value: &str) -> Option<IpAddr> {
+    let value = value.trim().trim_matches('"');
+    if let Ok(ip) = value.parse::<IpAddr>() {
+        return Some(ip);
+    }
+    if let Some(rest) = value.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+    value.rsplit_once(':')?.0.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::{HeaderName, HeaderValue};
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.append(HeaderName::from_static(name), HeaderValue::from_static(value));
+        }
+        map
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_untrusted_peer_ignores_headers() {
+        let proxies = TrustedProxies::default();
+        let spoofed = headers(&[("x-forwarded-for", "9.9.9.9"), ("forwarded", "for=8.8.8.8")]);
+        assert_eq!(proxies.resolve(ip("203.0.113.7"), &spoofed), ip("203.0.113.7"));
+
+        let proxies = TrustedProxies::parse("10.0.0.1");
+        assert_eq!(proxies.resolve(ip("203.0.113.7"), &spoofed), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn test_trusted_proxy_uses_nearest_untrusted_hop() {
+        let proxies = TrustedProxies::parse("127.0.0.1, 10.0.0.0/8");
+        // 客户端自己伪造的第一项被忽略
+        let chain = headers(&[("x-forwarded-for", "6.6.6.6, 198.51.100.4, 10.1.2.3")]);
+        assert_eq!(proxies.resolve(ip("127.0.0.1"), &chain), ip("198.51.100.4"));
+
+        let forwarded = headers(&[("forwarded", "for=6.6.6.6, for=\"198.51.100.4:4711\";proto=https")]);
+        assert_eq!(proxies.resolve(ip("10.9.9.9"), &forwarded), ip("198.51.100.4"));
+
+        let v6 = headers(&[("forwarded", "for=\"[2001:db8::1]:4711\"")]);
+        assert_eq!(proxies.resolve(ip("127.0.0.1"), &v6), ip("2001:db8::1"));
+
+        // 没有转发头时就是代理本身
+        assert_eq!(proxies.resolve(ip("127.0.0.1"), &HeaderMap::new()), ip("127.0.0.1"));
+        // 无法解析的一跳之前的内容不再采信
+        let obfuscated = headers(&[("forwarded", "for=6.6.6.6, for=_hidden")]);
+        assert_eq!(proxies.resolve(ip("127.0.0.1"), &obfuscated), ip("127.0.0.1"));
+    }
+
+    #[test]
+    fn test_parse_networks() {
+        let proxies = TrustedProxies::parse("192.168.0.0/16,::1,fd00::/8,not-an-ip,10.0.0.0/33");
+        assert_eq!(proxies.networks.len(), 3);
+        assert!(proxies.is_trusted(ip("192.168.44.1")));
+        assert!(!proxies.is_trusted(ip("192.169.0.1")));
+        assert!(proxies.is_trusted(ip("::1")));
+        assert!(proxies.is_trusted(ip("fd12::5")));
+        assert!(proxies.is_trusted(ip("::ffff:192.168.1.1")));
+        assert!(TrustedProxies::parse("").is_empty());
+        assert!(TrustedProxies::parse("0.0.0.0/0").is_trusted(ip("1.2.3.4")));
+    }
+}
@@ -0,0 +1,102 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::models::Comment;
+
+/// A comment placed in its thread, in display order
+#[derive(Debug, Serialize)]
+pub struct ThreadedComment {
+    #[serde(flatten)]
+    pub comment: Comment,
+    /// Indentation level, capped at `max_depth - 1`
+    pub depth: usize,
+    pub reply_count: usize,
+    /// Filled in by the caller according to the avatar privacy setting
+    pub avatar_url: Option<String>,
+}
+
+/// Flatten comments into depth-first thread order.
+///
+/// Replies nested deeper than `max_depth` are shown at the deepest level rather
+/// than hidden. Replies whose parent isn't in `comments` (e.g. still pending)
+/// are promoted to top level.
+pub fn build_threads(comments: Vec<Comment>, max_depth: usize) -> Vec<ThreadedComment> {
+    let max_depth = max_depth.max(1);
+    let ids: Vec<i64> = comments.iter().map(|c| c.id).collect();
+
+    let mut children: HashMap<Option<i64>, Vec<Comment>> = HashMap::new();
+    for comment in comments {
+        let parent = comment.parent_id.filter(|p| ids.contains(p));
+        children.entry(parent).or_default().push(comment);
+    }
+
+    let mut threads = Vec::with_capacity(ids.len());
+    push_children(&mut children, None, 0, max_depth, &mut threads);
+    threads
+}
+
+fn push_children(
+    children: &mut HashMap<Option<i64>, Vec<Comment>>,
+    parent: Option<i64>,
+    depth: usize,
+    max_depth: usize,
+    out: &mut Vec<ThreadedComment>,
+) {
+    let Some(replies) = children.remove(&parent) else {
+        return;
+    };
+    for comment in replies {
+        let id = comment.id;
+        let reply_count = children.get(&Some(id)).map_or(0, Vec::len);
+        out.push(ThreadedComment {
+            comment,
+            depth: depth.min(max_depth - 1),
+            reply_count,
+            avatar_url: None,
+        });
+        push_children(children, Some(id), depth + 1, max_depth, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(id: i64, parent_id: Option<i64>) -> Comment {
+        Comment {
+            id,
+            article_id: 1,
+            parent_id,
+            author_name: format!("user{}", id),
+            author_email: None,
+            content: format!("comment {}", id),
+            status: "approved".to_string(),
+            created_at: "2024-01-01 00:00:00".to_string(),
+            notify_replies: false,
+        }
+    }
+
+    #[test]
+    fn test_thread_order_and_depth() {
+        let comments = vec![comment(1, None), comment(2, None), comment(3, Some(1)), comment(4, Some(3))];
+        let threads = build_threads(comments, 5);
+        let order: Vec<(i64, usize)> = threads.iter().map(|t| (t.comment.id, t.depth)).collect();
+        assert_eq!(order, vec![(1, 0), (3, 1), (4, 2), (2, 0)]);
+        assert_eq!(threads[0].reply_count, 1);
+        assert_eq!(threads[3].reply_count, 0);
+    }
+
+    #[test]
+    fn test_depth_is_capped() {
+        let comments = vec![comment(1, None), comment(2, Some(1)), comment(3, Some(2)), comment(4, Some(3))];
+        let depths: Vec<usize> = build_threads(comments, 2).iter().map(|t| t.depth).collect();
+        assert_eq!(depths, vec![0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_orphans_become_top_level() {
+        let comments = vec![comment(2, Some(99)), comment(3, Some(2))];
+        let order: Vec<(i64, usize)> = build_threads(comments, 3).iter().map(|t| (t.comment.id, t.depth)).collect();
+        assert_eq!(order, vec![(2, 0), (3, 1)]);
+    }
+}
@@ -0,0 +1,25 @@
+use chrono::{Datelike, NaiveDate};
+
+/// Fill `{date}`, `{year}`, `{month}`, `{day}` and `{week}` (ISO week) in a template title
+pub fn expand_title_pattern(pattern: &str, today: NaiveDate) -> String {
+    pattern
+        .replace("{date}", &today.format("%Y-%m-%d").to_string())
+        .replace("{year}", &today.year().to_string())
+        .replace("{month}", &format!("{:02}", today.month()))
+        .replace("{day}", &format!("{:02}", today.day()))
+        .replace("{week}", &format!("{:02}", today.iso_week().week()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_title_pattern() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 7).unwrap();
+        assert_eq!(expand_title_pattern("Weekly Notes {year}-W{week}", today), "Weekly Notes 2024-W10");
+        assert_eq!(expand_title_pattern("Log {date}", today), "Log 2024-03-07");
+        assert_eq!(expand_title_pattern("{day}/{month}", today), "07/03");
+        assert_eq!(expand_title_pattern("Book Review: ", today), "Book Review: ");
+    }
+}
@@ -0,0 +1,252 @@
+//! Displaying stored timestamps. SQLite keeps them as UTC strings
+//! (`datetime('now')`, "YYYY-MM-DD HH:MM:SS"); pages, feeds and exports show
+//! them in the site's timezone and date format, set on the admin settings page.
+//! JSON responses carry them as RFC 3339 in UTC (see [`rfc3339`]).
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::collections::HashMap;
+
+/// How timestamps are stored: UTC, in the form `datetime('now')` writes, so
+/// they sort and compare as text in SQL
+pub const STORED_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+pub const TIMEZONE_KEY: &str = "site_timezone";
+pub const DATE_FORMAT_KEY: &str = "date_format";
+pub const DATETIME_FORMAT_KEY: &str = "datetime_format";
+
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+pub const DEFAULT_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateOptions {
+    pub timezone: Tz,
+    /// strftime pattern for dates, e.g. "%B %-d, %Y"
+    pub date_format: String,
+    /// strftime pattern for a date with the time of day
+    pub datetime_format: String,
+}
+
+impl Default for DateOptions {
+    fn default() -> Self {
+        Self {
+            timezone: Tz::UTC,
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            datetime_format: DEFAULT_DATETIME_FORMAT.to_string(),
+        }
+    }
+}
+
+impl DateOptions {
+    /// Unknown timezones and invalid patterns fall back to the defaults
+    pub fn from_settings(settings: &HashMap<String, String>) -> Self {
+        let format = |key: &str, default: &str| {
+            settings
+                .get(key)
+                .map(|f| f.trim())
+                .filter(|f| is_valid_format(f))
+                .unwrap_or(default)
+                .to_string()
+        };
+        Self {
+            timezone: settings.get(TIMEZONE_KEY).and_then(|name| parse_timezone(name)).unwrap_or(Tz::UTC),
+            date_format: format(DATE_FORMAT_KEY, DEFAULT_DATE_FORMAT),
+            datetime_format: format(DATETIME_FORMAT_KEY, DEFAULT_DATETIME_FORMAT),
+        }
+    }
+
+    pub fn to_settings(&self) -> [(&'static str, String); 3] {
+        [
+            (TIMEZONE_KEY, self.timezone.name().to_string()),
+            (DATE_FORMAT_KEY, self.date_format.clone()),
+            (DATETIME_FORMAT_KEY, self.datetime_format.clone()),
+        ]
+    }
+
+    /// A stored timestamp in the site's timezone
+    pub fn local(&self, value: &str) -> Option<DateTime<Tz>> {
+        parse_stored(value).map(|t| t.with_timezone(&self.timezone))
+    }
+
+    /// A stored timestamp in `format`, or as stored when it cannot be parsed
+    pub fn format(&self, value: &str, format: &str) -> String {
+        match self.local(value) {
+            Some(t) if is_valid_format(format) => t.format(format).to_string(),
+            _ => value.to_string(),
+        }
+    }
+
+    pub fn format_date(&self, value: &str) -> String {
+        self.format(value, &self.date_format)
+    }
+
+    pub fn format_datetime(&self, value: &str) -> String {
+        self.format(value, &self.datetime_format)
+    }
+
+    /// Machine-readable form with the site's UTC offset, for `<time datetime>`
+    pub fn rfc3339(&self, value: &str) -> String {
+        self.local(value).map(|t| t.to_rfc3339()).unwrap_or_else(|| value.to_string())
+    }
+}
+
+/// A stored timestamp: SQLite's "YYYY-MM-DD HH:MM:SS" (optionally with
+/// fractional seconds or a `T`), RFC 3339, chrono's `Display` form with an
+/// offset, or a bare date; naive values are UTC
+pub fn parse_stored(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    let with_offset = DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f %:z"))
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f %z"));
+    if let Ok(t) = with_offset {
+        return Some(t.with_timezone(&Utc));
+    }
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))
+        .map(|t| Utc.from_utc_datetime(&t))
+}
+
+/// Any timestamp `parse_stored` understands, in the stored form
+pub fn to_stored(value: &str) -> Option<String> {
+    parse_stored(value).map(|t| t.format(STORED_FORMAT).to_string())
+}
+
+/// RFC 3339 in UTC, e.g. "2024-03-05T08:09:10Z", or as stored when it cannot be parsed
+pub fn to_rfc3339(value: &str) -> String {
+    parse_stored(value)
+        .map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_else(|| value.to_string())
+}
+
+/// Serde helpers for timestamp fields, `#[serde(with = "...::dates::rfc3339")]`:
+/// written as RFC 3339, read from RFC 3339 or the stored form and kept stored
+pub mod rfc3339 {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::to_rfc3339(value))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        super::to_stored(&value).ok_or_else(|| serde::de::Error::custom(format!("invalid timestamp: {}", value)))
+    }
+
+    /// The same for optional timestamps
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(value: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(value) => super::serialize(value, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+            Option::<String>::deserialize(deserializer)?
+                .map(|value| {
+                    super::super::to_stored(&value)
+                        .ok_or_else(|| serde::de::Error::custom(format!("invalid timestamp: {}", value)))
+                })
+                .transpose()
+        }
+    }
+}
+
+/// IANA timezone name such as "Asia/Shanghai"
+pub fn parse_timezone(name: &str) -> Option<Tz> {
+    name.trim().parse().ok()
+}
+
+/// Whether chrono understands every specifier in `format`; formatting with an
+/// unknown one would fail at render time
+pub fn is_valid_format(format: &str) -> bool {
+    !format.trim().is_empty() && !StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stored() {
+        let expected = Utc.with_ymd_and_hms(2024, 3, 5, 8, 9, 10).unwrap();
+        assert_eq!(parse_stored("2024-03-05 08:09:10"), Some(expected));
+        assert_eq!(parse_stored("2024-03-05T08:09:10.000"), Some(expected));
+        assert_eq!(parse_stored("2024-03-05T16:09:10+08:00"), Some(expected));
+        assert_eq!(parse_stored("2024-03-05").unwrap().to_rfc3339(), "2024-03-05T00:00:00+00:00");
+        assert_eq!(parse_stored("2024-03-05 16:09:10 +08:00"), Some(expected));
+        assert_eq!(parse_stored("yesterday"), None);
+    }
+
+    #[test]
+    fn test_stored_and_rfc3339() {
+        assert_eq!(to_stored("2024-03-05T16:09:10+08:00").as_deref(), Some("2024-03-05 08:09:10"));
+        assert_eq!(to_stored("2024-03-05").as_deref(), Some("2024-03-05 00:00:00"));
+        assert_eq!(to_stored("05/03/2024"), None);
+        assert_eq!(to_rfc3339("2024-03-05 08:09:10"), "2024-03-05T08:09:10Z");
+        assert_eq!(to_rfc3339("05/03/2024"), "05/03/2024");
+    }
+
+    #[test]
+    fn test_serde_helpers() {
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct Row {
+            #[serde(with = "rfc3339")]
+            created_at: String,
+            #[serde(default, with = "rfc3339::option")]
+            deleted_at: Option<String>,
+        }
+        let row = Row { created_at: "2024-03-05 08:09:10".to_string(), deleted_at: None };
+        let json = serde_json::to_string(&row).unwrap();
+        assert_eq!(json, r#"{"created_at":"2024-03-05T08:09:10Z","deleted_at":null}"#);
+        assert_eq!(serde_json::from_str::<Row>(&json).unwrap(), row);
+
+        let row: Row = serde_json::from_str(r#"{"created_at":"2024-03-05 08:09:10","deleted_at":"2024-03-06T01:00:00+01:00"}"#).unwrap();
+        assert_eq!(row.deleted_at.as_deref(), Some("2024-03-06 00:00:00"));
+        assert_eq!(serde_json::from_str::<Row>(r#"{"created_at":"2024-03-05"}"#).unwrap().deleted_at, None);
+        assert!(serde_json::from_str::<Row>(r#"{"created_at":"last week"}"#).is_err());
+    }
+
+    #[test]
+    fn test_format_in_timezone() {
+        let options = DateOptions {
+            timezone: parse_timezone("Asia/Shanghai").unwrap(),
+            date_format: "%B %-d, %Y".to_string(),
+            datetime_format: DEFAULT_DATETIME_FORMAT.to_string(),
+        };
+        // 20:00 UTC is already the next day in Shanghai
+        assert_eq!(options.format_date("2024-03-05 20:00:00"), "March 6, 2024");
+        assert_eq!(options.format_datetime("2024-03-05 20:00:00"), "2024-03-06 04:00");
+        assert_eq!(options.rfc3339("2024-03-05 20:00:00"), "2024-03-06T04:00:00+08:00");
+        assert_eq!(options.format_date("not a date"), "not a date");
+        assert_eq!(options.format("2024-03-05 20:00:00", "%Q"), "2024-03-05 20:00:00");
+    }
+
+    #[test]
+    fn test_settings() {
+        assert_eq!(DateOptions::from_settings(&HashMap::new()), DateOptions::default());
+
+        let options = DateOptions {
+            timezone: parse_timezone("Europe/Berlin").unwrap(),
+            date_format: "%d.%m.%Y".to_string(),
+            datetime_format: "%d.%m.%Y %H:%M".to_string(),
+        };
+        let settings: HashMap<String, String> =
+            options.to_settings().into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+        assert_eq!(DateOptions::from_settings(&settings), options);
+
+        let settings: HashMap<String, String> = [
+            (TIMEZONE_KEY.to_string(), "Mars/Olympus".to_string()),
+            (DATE_FORMAT_KEY.to_string(), "%Y-%Q".to_string()),
+        ]
+        .into();
+        assert_eq!(DateOptions::from_settings(&settings), DateOptions::default());
+        assert!(is_valid_format("%A, %e %B"));
+        assert!(!is_valid_format("  "));
+    }
+}
@@ -0,0 +1,170 @@
+//! Definition lists in the PHP Markdown Extra style, which pulldown-cmark 0.9
+//! does not parse:
+//!
+//! ```text
+//! Term
+//! : Definition
+//! : Another definition
+//! ```
+//!
+//! CommonMark reads such a block as one paragraph with soft breaks, so the
+//! rewrite runs on the event stream: a paragraph whose later lines start with
+//! `: ` becomes `<dl>`, and consecutive ones share a single list.
+
+use super::render_pipeline::{RenderContext, RenderStage};
+use pulldown_cmark::{CowStr, Event, Tag};
+
+/// Length of the `: ` marker if the line starts a definition
+fn definition_marker(line: &[Event<'_>]) -> Option<usize> {
+    match line.first() {
+        Some(Event::Text(text)) => {
+            let rest = text.strip_prefix(':')?;
+            let content = rest.trim_start_matches([' ', '\t']);
+            (content.len() < rest.len() && !content.is_empty()).then_some(text.len() - content.len())
+        }
+        _ => None,
+    }
+}
+
+/// Merge adjacent text events so a line's leading `:` is seen in one piece
+fn merge_text(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut merged: Vec<Event> = Vec::with_capacity(events.len());
+    for event in events {
+        match (merged.last_mut(), event) {
+            (Some(Event::Text(previous)), Event::Text(text)) => {
+                *previous = CowStr::from(format!("{}{}", previous, text));
+            }
+            (_, event) => merged.push(event),
+        }
+    }
+    merged
+}
+
+/// `<dt>`/`<dd>` events for a paragraph's inline events, or the events back if it is not a definition list
+fn convert(inline: Vec<Event<'_>>) -> Result<Vec<Event<'_>>, Vec<Event<'_>>> {
+    let inline = merge_text(inline);
+    let mut lines: Vec<Vec<Event>> = vec![Vec::new()];
+    for event in inline.iter().cloned() {
+        match event {
+            Event::SoftBreak | Event::HardBreak => lines.push(Vec::new()),
+            event => lines.last_mut().expect("at least one line").push(event),
+        }
+    }
+    let Some(first_definition) = lines.iter().position(|line| definition_marker(line).is_some()) else {
+        return Err(inline);
+    };
+    if first_definition == 0 {
+        return Err(inline);
+    }
+
+    let mut output = Vec::new();
+    let mut open_definition = false;
+    for (index, mut line) in lines.into_iter().enumerate() {
+        if index < first_definition {
+            output.push(Event::Html("<dt>".into()));
+            output.extend(line);
+            output.push(Event::Html("</dt>\n".into()));
+        } else if let Some(skip) = definition_marker(&line) {
+            if open_definition {
+                output.push(Event::Html("</dd>\n".into()));
+            }
+            if let Event::Text(text) = &line[0] {
+                line[0] = Event::Text(CowStr::from(text[skip..].to_string()));
+            }
+            output.push(Event::Html("<dd>".into()));
+            output.extend(line);
+            open_definition = true;
+        } else {
+            // Lazy continuation of the previous definition
+            output.push(Event::SoftBreak);
+            output.extend(line);
+        }
+    }
+    output.push(Event::Html("</dd>\n".into()));
+    Ok(output)
+}
+
+/// Replace definition-list paragraphs in a rendered event stream
+pub fn rewrite(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut output: Vec<Event> = Vec::with_capacity(events.len());
+    // Index of the `</dl>` we emitted last, so an adjacent list can continue it
+    let mut list_end: Option<usize> = None;
+    let mut paragraph: Option<Vec<Event>> = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::Paragraph) => paragraph = Some(Vec::new()),
+            Event::End(Tag::Paragraph) => {
+                let inline = paragraph.take().unwrap_or_default();
+                match convert(inline) {
+                    Ok(items) => {
+                        if list_end.is_some_and(|end| end + 1 == output.len()) {
+                            output.pop();
+                        } else {
+                            output.push(Event::Html("<dl>\n".into()));
+                        }
+                        output.extend(items);
+                        output.push(Event::Html("</dl>\n".into()));
+                        list_end = Some(output.len() - 1);
+                    }
+                    Err(inline) => {
+                        output.push(Event::Start(Tag::Paragraph));
+                        output.extend(inline);
+                        output.push(Event::End(Tag::Paragraph));
+                    }
+                }
+            }
+            event => match paragraph.as_mut() {
+                Some(inline) => inline.push(event),
+                None => output.push(event),
+            },
+        }
+    }
+    output
+}
+
+/// The rewrite as a render stage, registered after `highlight`
+pub struct DefinitionListStage;
+
+impl RenderStage for DefinitionListStage {
+    fn name(&self) -> &str {
+        "definition_list"
+    }
+
+    fn events<'a>(&self, events: Vec<Event<'a>>, _context: &mut RenderContext) -> Vec<Event<'a>> {
+        rewrite(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::{html, Options, Parser};
+
+    fn render(markdown: &str) -> String {
+        let events = rewrite(Parser::new_ext(markdown, Options::empty()).collect());
+        let mut output = String::new();
+        html::push_html(&mut output, events.into_iter());
+        output
+    }
+
+    #[test]
+    fn test_definition_list() {
+        let html = render("Apple\n: A *fruit*.\n: A company\n  with offices.\n\nRust\nFerrous\n:\tA language\n");
+        assert_eq!(
+            html,
+            "<dl>\n<dt>Apple</dt>\n<dd>A <em>fruit</em>.</dd>\n<dd>A company\nwith offices.</dd>\n\
+             <dt>Rust</dt>\n<dt>Ferrous</dt>\n<dd>A language</dd>\n</dl>\n"
+        );
+    }
+
+    #[test]
+    fn test_other_paragraphs_untouched() {
+        assert_eq!(render("Time: 10:00\nNote:no space"), "<p>Time: 10:00\nNote:no space</p>\n");
+        assert_eq!(render(": starts with colon\n: again"), "<p>: starts with colon\n: again</p>\n");
+        assert_eq!(
+            render("Term\n: def\n\nPlain paragraph."),
+            "<dl>\n<dt>Term</dt>\n<dd>def</dd>\n</dl>\n<p>Plain paragraph.</p>\n"
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Demo content for `bluster seed --demo`: articles that exercise the Markdown
+//! renderer (tables, code, math, footnotes, definition lists, ...), with
+//! keywords and a few comment threads, for working on themes and templates
+//! against something realistic. Never loaded by the server itself.
+
+use super::render_options::RenderOptions;
+
+pub struct DemoComment {
+    pub author_name: &'static str,
+    pub content: &'static str,
+    /// Index of the earlier comment on the same article this one answers
+    pub reply_to: Option<usize>,
+}
+
+pub struct DemoArticle {
+    pub title: &'static str,
+    pub description: &'static str,
+    /// Comma separated, as in `meta_keywords`
+    pub keywords: &'static str,
+    /// Published this many days before seeding, so lists have a spread of dates
+    pub days_ago: i64,
+    pub math: bool,
+    pub toc: bool,
+    pub content: &'static str,
+    pub comments: &'static [DemoComment],
+}
+
+impl DemoArticle {
+    /// Value for `articles.render_options`
+    pub fn render_options(&self) -> Option<String> {
+        RenderOptions {
+            math: self.math.then_some(true),
+            toc: self.toc.then_some(true),
+            highlight_theme: None,
+        }
+        .to_json()
+    }
+}
+
+pub const ARTICLES: &[DemoArticle] = &[
+    DemoArticle {
+        title: "Welcome to the demo blog",
+        description: "What this demo site contains and how to get rid of it.",
+        keywords: "meta, demo",
+        days_ago: 30,
+        math: false,
+        toc: false,
+        content: r#"This site was filled by `bluster seed --demo`. Every article shows off a different
+part of the renderer, so a theme can be checked against **bold**, *italic*,
+~~struck~~ and `inline code` text, [links](https://www.rust-lang.org/) and
+[links within the site](/about).
+
+> Block quotes are used for asides.
+>
+> They can span several paragraphs.
+
+- Lists with *inline* markup
+- [x] and task items
+- [ ] that are not done yet
+
+1. Ordered lists
+2. keep their numbers
+   - and nest
+
+---
+
+Demo articles are ordinary articles: delete them from the dashboard when you are done.
+"#,
+        comments: &[
+            DemoComment { author_name: "Ada", content: "Looks great! Is the source on GitHub?", reply_to: None },
+            DemoComment { author_name: "admin", content: "It is, link in the footer.", reply_to: Some(0) },
+        ],
+    },
+    DemoArticle {
+        title: "Error handling in Rust with thiserror",
+        description: "Defining error enums for a library and turning them into HTTP responses.",
+        keywords: "rust, errors, thiserror",
+        days_ago: 21,
+        math: false,
+        toc: true,
+        content: r#"## Defining the error type
+
+Libraries describe what went wrong with an enum, and `thiserror` writes the
+`Display` and `From` implementations:
+
+```rust
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("article {0} not found")]
+    NotFound(i64),
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+```
+
+## Using it
+
+The `?` operator converts the underlying error on the way out:
+
+```rust
+async fn title(pool: &SqlitePool, id: i64) -> Result<String, StoreError> {
+    sqlx::query_scalar("SELECT title FROM articles WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(StoreError::NotFound(id))
+}
+```
+
+## Mapping to responses
+
+| Variant    | Status | Body                  |
+|------------|:------:|----------------------:|
+| `NotFound` | 404    | "Article not found"   |
+| `Database` | 500    | empty                 |
+
+Shell commands get their own highlighting too:
+
+```bash
+cargo add thiserror
+cargo test --workspace
+```
+"#,
+        comments: &[
+            DemoComment { author_name: "Grace", content: "How does this compare to `anyhow`?", reply_to: None },
+            DemoComment {
+                author_name: "admin",
+                content: "`anyhow` is great for applications; for a library, callers usually want to match on the variants.",
+                reply_to: Some(0),
+            },
+            DemoComment { author_name: "Linus", content: "The table was the most useful part for me.", reply_to: None },
+        ],
+    },
+    DemoArticle {
+        title: "A little math: Euler's identity",
+        description: "Inline and display formulas typeset with KaTeX.",
+        keywords: "math, katex",
+        days_ago: 14,
+        math: true,
+        toc: false,
+        content: r#"Euler's identity $e^{i\pi} + 1 = 0$ links five constants. It follows from
+
+$$e^{ix} = \cos x + i \sin x$$
+
+at $x = \pi$. Sums and fractions render as display math as well:
+
+$$\sum_{n=1}^{\infty} \frac{1}{n^2} = \frac{\pi^2}{6}$$
+
+Prices such as $5 and $10 stay plain text[^dollars].
+
+[^dollars]: An inline formula cannot end right before a digit, so amounts of money are safe.
+"#,
+        comments: &[DemoComment {
+            author_name: "Emmy",
+            content: "Nice to see math rendered properly on a blog.",
+            reply_to: None,
+        }],
+    },
+    DemoArticle {
+        title: "Glossary of blogging terms",
+        description: "Definition lists, images and footnotes.",
+        keywords: "writing, glossary",
+        days_ago: 9,
+        math: false,
+        toc: false,
+        content: r#"Permalink
+: The address an article keeps forever, here `/post/{id}`.
+
+Canonical URL
+: Where search engines should send readers when an article was first published elsewhere.
+
+Slug
+: A readable part of an address derived from the title.
+: Also a kind of garden pest.
+
+Footnotes keep asides out of the way[^1], and images have captions:
+
+![The Rust logo](https://www.rust-lang.org/logos/rust-logo-128x128.png "Ferris approves")
+
+[^1]: Like this one.
+"#,
+        comments: &[],
+    },
+    DemoArticle {
+        title: "Benchmarking SQLite full-text search",
+        description: "Numbers from FTS5 on a laptop, with the queries used.",
+        keywords: "sqlite, fts5, performance",
+        days_ago: 4,
+        math: false,
+        toc: true,
+        content: r#"## Setup
+
+An external-content FTS5 table mirrors the articles:
+
+```sql
+CREATE VIRTUAL TABLE articles_fts USING fts5(
+    title, content, content = 'articles', content_rowid = 'id'
+);
+SELECT rowid FROM articles_fts WHERE articles_fts MATCH 'rust*' ORDER BY rank;
+```
+
+## Results
+
+| Articles | Index size | Prefix query | Phrase query |
+|---------:|-----------:|-------------:|-------------:|
+| 1,000    | 2.1 MB     | 0.4 ms       | 0.6 ms       |
+| 10,000   | 19 MB      | 1.9 ms       | 2.7 ms       |
+| 100,000  | 187 MB     | 14 ms        | 22 ms        |
+
+## Conclusion
+
+For a personal blog, search cost is a rounding error. A very long line of code
+should not stretch the page: `SELECT a.id, a.title, snippet(articles_fts, 1, '<mark>', '</mark>', '…', 12) FROM articles_fts JOIN articles a ON a.id = articles_fts.rowid`.
+"#,
+        comments: &[
+            DemoComment { author_name: "Ada", content: "Did you try the trigram tokenizer?", reply_to: None },
+            DemoComment { author_name: "admin", content: "Not yet, maybe in a follow-up.", reply_to: Some(0) },
+            DemoComment { author_name: "Ada", content: "Looking forward to it!", reply_to: Some(1) },
+        ],
+    },
+    DemoArticle {
+        title: "Notes from a week of travel",
+        description: "Mostly paragraphs, for checking how long text reads.",
+        keywords: "travel, personal",
+        days_ago: 1,
+        math: false,
+        toc: false,
+        content: r#"Not every post is technical. This one is mostly paragraphs, which is what most
+readers will spend their time on, so line length, font size and spacing matter here
+more than anywhere else.
+
+The train left at seven. By nine the fog had lifted and the valley opened up, fields
+on one side and a river on the other, and for a while nobody in the carriage said
+anything at all.
+
+In the evening we found a small place that served one dish, and it was the right one.
+"#,
+        comments: &[],
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_demo_articles() {
+        for (i, article) in ARTICLES.iter().enumerate() {
+            // seeding skips articles that look like existing ones, so the demo titles must not clash
+            let others = ARTICLES.iter().enumerate().filter(|(j, _)| *j != i).map(|(j, a)| (j as i64, a.title));
+            assert_eq!(super::super::duplicates::find_duplicate(article.title, others), None, "{}", article.title);
+            for (j, comment) in article.comments.iter().enumerate() {
+                assert!(comment.reply_to.is_none_or(|parent| parent < j), "{} comment {}", article.title, j);
+            }
+        }
+        assert!(ARTICLES.iter().any(|a| a.math));
+        assert_eq!(ARTICLES[2].render_options().as_deref(), Some(r#"{"math":true}"#));
+        assert_eq!(ARTICLES[0].render_options(), None);
+    }
+}
@@ -0,0 +1,245 @@
+use html_escape::encode_text;
+
+/// Larger edit distances fall back to replacing the whole changed middle, which
+/// keeps memory bounded for rewrites of long articles
+const MAX_EDIT_DISTANCE: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// A run of text that is unchanged, removed from the old text or added in the new one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub op: Op,
+    pub text: String,
+}
+
+/// Words and whitespace runs; joining the tokens gives back the input
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut in_space = None;
+    for (i, ch) in text.char_indices() {
+        let space = ch.is_whitespace();
+        if in_space.is_some_and(|s| s != space) {
+            tokens.push(&text[start..i]);
+            start = i;
+        }
+        in_space = Some(space);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// Myers' O((N+M)D) shortest edit script, one op per token
+fn shortest_edit(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    if max == 0 {
+        return Vec::new();
+    }
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    // trace[d] holds v[-(d+1)..=d+1] as it was before step d
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    for d in 0..=max.min(MAX_EDIT_DISTANCE) as isize {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let down = k == -d || (k != d && v[(offset + k - 1) as usize] < v[(offset + k + 1) as usize]);
+            let mut x = if down { v[(offset + k + 1) as usize] } else { v[(offset + k - 1) as usize] + 1 };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(offset + k) as usize] = x;
+            if x >= n && y >= m {
+                return backtrack(&trace, n, m);
+            }
+        }
+    }
+
+    let mut ops = vec![Op::Delete; a.len()];
+    ops.extend(std::iter::repeat_n(Op::Insert, b.len()));
+    ops
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Op> {
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            ops.push(Op::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            ops.push(if x == prev_x { Op::Insert } else { Op::Delete });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.reverse();
+    ops
+}
+
+fn common_whitespace_suffix(a: &str, b: &str) -> usize {
+    a.chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(x, y)| x == y && x.is_whitespace())
+        .map(|(x, _)| x.len_utf8())
+        .sum()
+}
+
+/// Word-level diff. Unchanged whitespace between two edits is folded into the
+/// edit so a rewritten phrase shows as one deletion and one insertion.
+pub fn diff_words(old: &str, new: &str) -> Vec<Change> {
+    let a = tokenize(old);
+    let b = tokenize(new);
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+
+    let mut ops = vec![Op::Equal; prefix];
+    ops.extend(shortest_edit(&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]));
+    ops.extend(std::iter::repeat_n(Op::Equal, suffix));
+
+    // Token runs: (op, text)
+    let (mut i, mut j) = (0, 0);
+    let mut runs: Vec<(Op, String)> = Vec::new();
+    for op in ops {
+        let token = match op {
+            Op::Equal | Op::Delete => a[i],
+            Op::Insert => b[j],
+        };
+        match op {
+            Op::Equal => {
+                i += 1;
+                j += 1;
+            }
+            Op::Delete => i += 1,
+            Op::Insert => j += 1,
+        }
+        match runs.last_mut() {
+            Some((last, text)) if *last == op => text.push_str(token),
+            _ => runs.push((op, token.to_string())),
+        }
+    }
+
+    let mut changes: Vec<Change> = Vec::new();
+    let mut deleted = String::new();
+    let mut inserted = String::new();
+    let flush = |changes: &mut Vec<Change>, deleted: &mut String, inserted: &mut String| {
+        // Whitespace both sides end with is unchanged text
+        let shared = common_whitespace_suffix(deleted, inserted);
+        let tail = deleted.split_off(deleted.len() - shared);
+        inserted.truncate(inserted.len() - shared);
+        if !deleted.is_empty() {
+            changes.push(Change { op: Op::Delete, text: std::mem::take(deleted) });
+        }
+        if !inserted.is_empty() {
+            changes.push(Change { op: Op::Insert, text: std::mem::take(inserted) });
+        }
+        if !tail.is_empty() {
+            changes.push(Change { op: Op::Equal, text: tail });
+        }
+    };
+    for (index, (op, text)) in runs.iter().enumerate() {
+        match op {
+            Op::Delete => deleted.push_str(text),
+            Op::Insert => inserted.push_str(text),
+            Op::Equal => {
+                let between_edits = text.trim().is_empty()
+                    && (!deleted.is_empty() || !inserted.is_empty())
+                    && runs.get(index + 1).is_some_and(|(next, _)| *next != Op::Equal);
+                if between_edits {
+                    deleted.push_str(text);
+                    inserted.push_str(text);
+                } else {
+                    flush(&mut changes, &mut deleted, &mut inserted);
+                    match changes.last_mut() {
+                        Some(last) if last.op == Op::Equal => last.text.push_str(text),
+                        _ => changes.push(Change { op: Op::Equal, text: text.clone() }),
+                    }
+                }
+            }
+        }
+    }
+    flush(&mut changes, &mut deleted, &mut inserted);
+    changes
+}
+
+/// Escaped HTML with `<del>` / `<ins>` around removed and added text
+pub fn render_html(changes: &[Change]) -> String {
+    let mut html = String::new();
+    for change in changes {
+        let text = encode_text(&change.text);
+        match change.op {
+            Op::Equal => html.push_str(&text),
+            Op::Delete => html.push_str(&format!("<del>{}</del>", text)),
+            Op::Insert => html.push_str(&format!("<ins>{}</ins>", text)),
+        }
+    }
+    html
+}
+
+/// Number of words removed and added
+pub fn word_counts(changes: &[Change]) -> (usize, usize) {
+    let count = |op: Op| {
+        changes
+            .iter()
+            .filter(|c| c.op == op)
+            .map(|c| c.text.split_whitespace().count())
+            .sum()
+    };
+    (count(Op::Delete), count(Op::Insert))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rebuild(changes: &[Change], skip: Op) -> String {
+        changes.iter().filter(|c| c.op != skip).map(|c| c.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_word_diff() {
+        let old = "The quick brown fox jumps over the lazy dog.";
+        let new = "The quick red fox leaps over the lazy dog.";
+        let changes = diff_words(old, new);
+        assert_eq!(rebuild(&changes, Op::Insert), old);
+        assert_eq!(rebuild(&changes, Op::Delete), new);
+        assert_eq!(
+            render_html(&changes),
+            "The quick <del>brown</del><ins>red</ins> fox <del>jumps</del><ins>leaps</ins> over the lazy dog."
+        );
+        assert_eq!(word_counts(&changes), (2, 2));
+    }
+
+    #[test]
+    fn test_adjacent_edits_are_grouped_and_escaped() {
+        let changes = diff_words("keep <a> b c end", "keep x y end\nmore");
+        assert_eq!(render_html(&changes), "keep <del>&lt;a&gt; b c</del><ins>x y</ins> end<ins>\nmore</ins>");
+    }
+
+    #[test]
+    fn test_identical_and_empty() {
+        assert_eq!(diff_words("same text", "same text"), vec![Change { op: Op::Equal, text: "same text".into() }]);
+        assert!(diff_words("", "").is_empty());
+        assert_eq!(render_html(&diff_words("", "new")), "<ins>new</ins>");
+    }
+}
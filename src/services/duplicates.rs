@@ -0,0 +1,84 @@
+//! Spotting an article that is being created a second time, which happens
+//! easily when the same export is imported twice. Titles are compared by a
+//! slug-like key, so case, punctuation and spacing don't matter, and keys that
+//! differ by a typo or two still count as the same article.
+
+/// How alike two title keys must be (1.0 = identical) to count as duplicates
+pub const TITLE_SIMILARITY_THRESHOLD: f64 = 0.9;
+
+/// Lowercase letters and digits of `title`, one space between words
+pub fn title_key(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 1 minus the edit distance between `a` and `b` relative to the longer one
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
+
+/// The existing article whose title is closest to `title`, if it is close
+/// enough to be the same article
+pub fn find_duplicate<'a>(title: &str, existing: impl IntoIterator<Item = (i64, &'a str)>) -> Option<(i64, &'a str)> {
+    let key = title_key(title);
+    if key.is_empty() {
+        return None;
+    }
+    existing
+        .into_iter()
+        .map(|(id, other)| (id, other, similarity(&key, &title_key(other))))
+        .filter(|(_, _, score)| *score >= TITLE_SIMILARITY_THRESHOLD)
+        .max_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(id, other, _)| (id, other))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_key() {
+        assert_eq!(title_key("  Hello, World! "), "hello world");
+        assert_eq!(title_key("Rust — Ownership & Borrowing"), "rust ownership borrowing");
+        assert_eq!(title_key("学习 Rust"), "学习 rust");
+        assert_eq!(title_key("!!!"), "");
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("abc", "abc"), 1.0);
+        assert_eq!(similarity("", ""), 1.0);
+        assert_eq!(similarity("abcd", "abce"), 0.75);
+        assert_eq!(similarity("abc", ""), 0.0);
+    }
+
+    #[test]
+    fn test_find_duplicate() {
+        let existing = [(1, "Getting Started with Actix-web"), (2, "Error handling in Rust"), (3, "Hello")];
+        assert_eq!(find_duplicate("getting started with actix web", existing), Some((1, "Getting Started with Actix-web")));
+        // a typo is still the same article
+        assert_eq!(find_duplicate("Error handlng in Rust", existing), Some((2, "Error handling in Rust")));
+        assert_eq!(find_duplicate("Error handling in Go", existing), None);
+        assert_eq!(find_duplicate("Hello 2", existing), None);
+        assert_eq!(find_duplicate("???", existing), None);
+    }
+}
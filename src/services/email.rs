@@ -0,0 +1,127 @@
+use lettre::message::{Mailbox, Message, header::ContentType};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailError {
+    #[error("Invalid email address: {0}")]
+    InvalidAddress(String),
+    #[error("Failed to build email: {0}")]
+    Build(String),
+    #[error("SMTP delivery failed: {0}")]
+    Transport(String),
+}
+
+/// Outgoing mail over SMTP, configured from `SMTP_*` environment variables
+#[derive(Clone)]
+pub struct EmailService {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    admin_email: Option<Mailbox>,
+}
+
+impl EmailService {
+    /// Build the service from the environment; `None` when `SMTP_HOST` is not set
+    pub fn from_env() -> Option<Self> {
+        let host = std::env::var("SMTP_HOST").ok().filter(|h| !h.trim().is_empty())?;
+        let port = std::env::var("SMTP_PORT")
+            .ok()
+            .and_then(|p| p.parse::<u16>().ok())
+            .unwrap_or(587);
+        let from = std::env::var("SMTP_FROM").unwrap_or_else(|_| format!("Bluster <noreply@{}>", host));
+
+        let builder = match AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host) {
+            Ok(builder) => builder.port(port),
+            Err(e) => {
+                log::error!("Invalid SMTP relay {}: {}", host, e);
+                return None;
+            }
+        };
+        let builder = match (std::env::var("SMTP_USERNAME"), std::env::var("SMTP_PASSWORD")) {
+            (Ok(user), Ok(pass)) => builder.credentials(Credentials::new(user, pass)),
+            _ => builder,
+        };
+
+        let from = match Self::parse_mailbox(&from) {
+            Ok(from) => from,
+            Err(e) => {
+                log::error!("Invalid SMTP_FROM: {}", e);
+                return None;
+            }
+        };
+        let admin_email = std::env::var("ADMIN_EMAIL")
+            .ok()
+            .and_then(|addr| Self::parse_mailbox(&addr).map_err(|e| log::error!("Invalid ADMIN_EMAIL: {}", e)).ok());
+
+        log::info!("Email delivery enabled via {}:{}", host, port);
+        Some(Self {
+            transport: builder.build(),
+            from,
+            admin_email,
+        })
+    }
+
+    pub fn parse_mailbox(address: &str) -> Result<Mailbox, EmailError> {
+        address
+            .trim()
+            .parse::<Mailbox>()
+            .map_err(|_| EmailError::InvalidAddress(address.to_string()))
+    }
+
+    /// Whether there is an administrator address to send notifications to
+    pub fn has_admin_recipient(&self) -> bool {
+        self.admin_email.is_some()
+    }
+
+    fn build_message(from: &Mailbox, to: Mailbox, subject: &str, body: &str) -> Result<Message, EmailError> {
+        Message::builder()
+            .from(from.clone())
+            .to(to)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body.to_string())
+            .map_err(|e| EmailError::Build(e.to_string()))
+    }
+
+    pub async fn send(&self, to: Mailbox, subject: &str, body: &str) -> Result<(), EmailError> {
+        let message = Self::build_message(&self.from, to, subject, body)?;
+        self.transport
+            .send(message)
+            .await
+            .map(|_| ())
+            .map_err(|e| EmailError::Transport(e.to_string()))
+    }
+
+    /// Send to `ADMIN_EMAIL`; a no-op when it isn't configured
+    pub async fn send_to_admin(&self, subject: &str, body: &str) -> Result<(), EmailError> {
+        match &self.admin_email {
+            Some(admin) => self.send(admin.clone(), subject, body).await,
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mailbox() {
+        assert!(EmailService::parse_mailbox("Admin <admin@example.com>").is_ok());
+        assert!(EmailService::parse_mailbox(" admin@example.com ").is_ok());
+        match EmailService::parse_mailbox("not an address") {
+            Err(EmailError::InvalidAddress(addr)) => assert_eq!(addr, "not an address"),
+            _ => panic!("Expected InvalidAddress error"),
+        }
+    }
+
+    #[test]
+    fn test_build_message() {
+        let from = EmailService::parse_mailbox("blog@example.com").unwrap();
+        let to = EmailService::parse_mailbox("admin@example.com").unwrap();
+        let message = EmailService::build_message(&from, to, "Subject line", "Body text").unwrap();
+        let raw = String::from_utf8(message.formatted()).unwrap();
+        assert!(raw.contains("Subject: Subject line"));
+        assert!(raw.contains("Body text"));
+    }
+}
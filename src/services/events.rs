@@ -0,0 +1,198 @@
+//! Internal event bus. Handlers publish what happened (an article was created,
+//! a comment posted, ...) and the side effects — clearing caches, notifying
+//! search engines, plugins and webhooks, sending emails — live in subscribers.
+//!
+//! Each subscriber gets its own queue and worker task, so events reach it in
+//! the order they were published and a slow subscriber only delays itself.
+//! Work that later requests must already see, such as clearing a cache, goes
+//! in `Subscriber::immediate`, which runs inside `publish`.
+//!
+//! The search index needs no subscriber: the `articles_fts` triggers keep it
+//! up to date in the same statement as the change.
+
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// A new article, published at once or saved as a draft
+    ArticleCreated { article_id: i64, published: bool },
+    ArticleUpdated { article_id: i64 },
+    /// A draft was published
+    ArticlePublished { article_id: i64 },
+    /// The article was moved to the trash
+    ArticleDeleted { article_id: i64 },
+    /// The article was taken back out of the trash; `published` unless it is a draft
+    ArticleRestored { article_id: i64, published: bool },
+    /// A reader's comment was stored; `status` is pending, approved or spam
+    CommentPosted { comment_id: i64, article_id: i64, status: String },
+    UserLoggedIn { username: String, ip: String, new_ip: bool },
+}
+
+impl Event {
+    /// The article, for events about one
+    pub fn article_id(&self) -> Option<i64> {
+        match self {
+            Event::ArticleCreated { article_id, .. }
+            | Event::ArticleUpdated { article_id }
+            | Event::ArticlePublished { article_id }
+            | Event::ArticleDeleted { article_id }
+            | Event::ArticleRestored { article_id, .. } => Some(*article_id),
+            _ => None,
+        }
+    }
+
+    /// The article that just became public: created or restored without being a
+    /// draft, or published
+    pub fn published_article(&self) -> Option<i64> {
+        match self {
+            Event::ArticleCreated { article_id, published: true }
+            | Event::ArticleRestored { article_id, published: true }
+            | Event::ArticlePublished { article_id } => Some(*article_id),
+            _ => None,
+        }
+    }
+}
+
+/// Reacts to events; both hooks do nothing by default
+pub trait Subscriber: Send + Sync + 'static {
+    fn name(&self) -> &str;
+
+    /// Runs inside `publish`, before the handler responds; must be quick and not block
+    fn immediate(&self, _event: &Event) {}
+
+    /// Runs later on the subscriber's worker, one event at a time
+    fn handle<'a>(&'a self, _event: &'a Event) -> BoxFuture<'a, ()> {
+        Box::pin(async {})
+    }
+}
+
+struct Subscription {
+    subscriber: Arc<dyn Subscriber>,
+    queue: mpsc::UnboundedSender<Arc<Event>>,
+}
+
+#[derive(Default)]
+pub struct EventBus {
+    subscriptions: RwLock<Vec<Subscription>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start delivering events to `subscriber`; the returned worker ends once
+    /// the bus is closed and the subscriber's queue is empty. Must be called
+    /// from within the Tokio runtime.
+    pub fn subscribe(&self, subscriber: Arc<dyn Subscriber>) -> JoinHandle<()> {
+        let (queue, mut receiver) = mpsc::unbounded_channel::<Arc<Event>>();
+        let worker = subscriber.clone();
+        let handle = tokio::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                worker.handle(&event).await;
+            }
+        });
+        self.subscriptions.write().unwrap().push(Subscription { subscriber, queue });
+        handle
+    }
+
+    pub fn subscribers(&self) -> Vec<String> {
+        self.subscriptions.read().unwrap().iter().map(|s| s.subscriber.name().to_string()).collect()
+    }
+
+    /// Run the immediate hooks and queue the event for every subscriber; never waits
+    pub fn publish(&self, event: Event) {
+        let subscriptions = self.subscriptions.read().unwrap();
+        let event = Arc::new(event);
+        for subscription in subscriptions.iter() {
+            subscription.subscriber.immediate(&event);
+            if subscription.queue.send(event.clone()).is_err() {
+                log::warn!("Event subscriber {} has stopped; dropped {:?}", subscription.subscriber.name(), event);
+            }
+        }
+    }
+
+    /// Stop accepting events; queued ones are still delivered
+    pub fn close(&self) {
+        self.subscriptions.write().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Recorder {
+        immediate: AtomicUsize,
+        handled: Mutex<Vec<Event>>,
+    }
+
+    struct Shared(Arc<Recorder>);
+
+    impl Subscriber for Shared {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        fn immediate(&self, event: &Event) {
+            if event.article_id().is_some() {
+                self.0.immediate.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        fn handle<'a>(&'a self, event: &'a Event) -> BoxFuture<'a, ()> {
+            Box::pin(async move {
+                // 较慢的处理也按发布顺序进行
+                tokio::task::yield_now().await;
+                self.0.handled.lock().unwrap().push(event.clone());
+            })
+        }
+    }
+
+    #[test]
+    fn test_published_article() {
+        assert_eq!(Event::ArticleCreated { article_id: 1, published: true }.published_article(), Some(1));
+        assert_eq!(Event::ArticleCreated { article_id: 1, published: false }.published_article(), None);
+        assert_eq!(Event::ArticlePublished { article_id: 2 }.published_article(), Some(2));
+        assert_eq!(Event::ArticleUpdated { article_id: 3 }.published_article(), None);
+        assert_eq!(Event::ArticleRestored { article_id: 4, published: true }.published_article(), Some(4));
+        assert_eq!(Event::ArticleRestored { article_id: 4, published: false }.published_article(), None);
+        let login = Event::UserLoggedIn { username: "admin".to_string(), ip: "::1".to_string(), new_ip: false };
+        assert_eq!(login.article_id(), None);
+        assert_eq!(
+            serde_json::to_value(Event::ArticleDeleted { article_id: 4 }).unwrap(),
+            serde_json::json!({"type": "article_deleted", "article_id": 4})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivery() {
+        let bus = EventBus::new();
+        let recorder = Arc::new(Recorder::default());
+        let worker = bus.subscribe(Arc::new(Shared(recorder.clone())));
+        assert_eq!(bus.subscribers(), vec!["recorder"]);
+
+        bus.publish(Event::ArticleCreated { article_id: 1, published: false });
+        bus.publish(Event::ArticlePublished { article_id: 1 });
+        bus.publish(Event::CommentPosted { comment_id: 5, article_id: 1, status: "pending".to_string() });
+        // immediate 在 publish 返回前已经执行
+        assert_eq!(recorder.immediate.load(Ordering::SeqCst), 2);
+
+        // 关闭后已排队的事件仍会处理完
+        bus.close();
+        bus.publish(Event::ArticleDeleted { article_id: 1 });
+        worker.await.unwrap();
+        let handled = recorder.handled.lock().unwrap();
+        assert_eq!(handled.len(), 3);
+        assert_eq!(handled[1], Event::ArticlePublished { article_id: 1 });
+        assert!(matches!(handled[2], Event::CommentPosted { comment_id: 5, .. }));
+    }
+}
@@ -0,0 +1,107 @@
+//! Site-wide switches for optional subsystems, stored in site settings and
+//! changed on the admin settings page. Handlers check them before doing any
+//! work and templates read them through `site().features`, so a subsystem
+//! can ship disabled and be turned on without a restart.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+pub const FEATURE_COMMENTS_KEY: &str = "feature_comments";
+pub const FEATURE_REGISTRATION_KEY: &str = "feature_registration";
+pub const FEATURE_SEARCH_KEY: &str = "feature_search";
+pub const FEATURE_FEDERATION_KEY: &str = "feature_federation";
+pub const FEATURE_OUTBOUND_TRACKING_KEY: &str = "feature_outbound_tracking";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FeatureFlags {
+    /// Readers can post comments and see approved ones
+    pub comments: bool,
+    /// Visitors can create their own accounts. Reserved: no sign-up route
+    /// exists yet, and the subsystem that adds one must check this flag.
+    pub registration: bool,
+    /// Search endpoints answer queries
+    pub search: bool,
+    /// Articles are published to other servers. Reserved like `registration`
+    /// until a federation subsystem ships.
+    pub federation: bool,
+    /// Links to other sites go through `/out`, which counts clicks
+    pub outbound_tracking: bool,
+}
+
+/// Established features are on; experimental and reserved ones start off
+impl Default for FeatureFlags {
+    fn default() -> Self {
+        Self {
+            comments: true,
+            registration: false,
+            search: true,
+            federation: false,
+            outbound_tracking: false,
+        }
+    }
+}
+
+impl FeatureFlags {
+    pub fn from_settings(settings: &HashMap<String, String>) -> Self {
+        let defaults = Self::default();
+        let flag = |key: &str, default: bool| settings.get(key).map(|v| v == "true").unwrap_or(default);
+        Self {
+            comments: flag(FEATURE_COMMENTS_KEY, defaults.comments),
+            registration: flag(FEATURE_REGISTRATION_KEY, defaults.registration),
+            search: flag(FEATURE_SEARCH_KEY, defaults.search),
+            federation: flag(FEATURE_FEDERATION_KEY, defaults.federation),
+            outbound_tracking: flag(FEATURE_OUTBOUND_TRACKING_KEY, defaults.outbound_tracking),
+        }
+    }
+
+    pub fn to_settings(&self) -> [(&'static str, String); 5] {
+        [
+            (FEATURE_COMMENTS_KEY, self.comments.to_string()),
+            (FEATURE_REGISTRATION_KEY, self.registration.to_string()),
+            (FEATURE_SEARCH_KEY, self.search.to_string()),
+            (FEATURE_FEDERATION_KEY, self.federation.to_string()),
+            (FEATURE_OUTBOUND_TRACKING_KEY, self.outbound_tracking.to_string()),
+        ]
+    }
+
+    /// Names of the features that are on, for the audit log
+    pub fn enabled(&self) -> Vec<&'static str> {
+        [
+            ("comments", self.comments),
+            ("registration", self.registration),
+            ("search", self.search),
+            ("federation", self.federation),
+            ("outbound_tracking", self.outbound_tracking),
+        ]
+        .into_iter()
+        .filter_map(|(name, on)| on.then_some(name))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_flags() {
+        assert_eq!(FeatureFlags::from_settings(&HashMap::new()), FeatureFlags::default());
+        assert_eq!(FeatureFlags::default().enabled(), vec!["comments", "search"]);
+
+        let flags = FeatureFlags {
+            comments: false,
+            registration: true,
+            search: false,
+            federation: true,
+            outbound_tracking: true,
+        };
+        let settings: HashMap<String, String> =
+            flags.to_settings().into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+        assert_eq!(FeatureFlags::from_settings(&settings), flags);
+        assert_eq!(flags.enabled(), vec!["registration", "federation", "outbound_tracking"]);
+
+        // Anything but "true" turns a feature off
+        let settings: HashMap<String, String> = [(FEATURE_COMMENTS_KEY.to_string(), "yes".to_string())].into();
+        assert!(!FeatureFlags::from_settings(&settings).comments);
+    }
+}
@@ -0,0 +1,444 @@
+use super::dates::parse_stored;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use html_escape::encode_text;
+use serde::Serialize;
+use std::collections::HashMap;
+
+pub const FEED_CONTENT_KEY: &str = "feed_content";
+pub const FEED_FOOTER_KEY: &str = "feed_footer";
+pub const FEED_ITEMS_KEY: &str = "feed_items";
+
+/// Articles in each feed unless configured otherwise
+pub const DEFAULT_ITEMS: u32 = 20;
+
+/// Most articles a feed can be configured to carry
+pub const MAX_ITEMS: u32 = 100;
+
+/// Longest footer accepted in the settings
+pub const MAX_FOOTER_LENGTH: usize = 500;
+
+/// What each feed item carries besides its summary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedContent {
+    /// Summary only; readers follow the link for the article
+    #[default]
+    Summary,
+    /// The rendered article as well (`content:encoded` / Atom `<content>`)
+    Full,
+}
+
+impl FeedContent {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "summary" => Some(FeedContent::Summary),
+            "full" => Some(FeedContent::Full),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeedContent::Summary => "summary",
+            FeedContent::Full => "full",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeedOptions {
+    pub content: FeedContent,
+    /// Appended to every item, e.g. a copyright line; `{title}` and `{link}` are
+    /// replaced with the article's title and URL
+    pub footer: String,
+    /// How many of the latest articles the feeds carry, 1 to `MAX_ITEMS`
+    pub items: u32,
+}
+
+impl Default for FeedOptions {
+    fn default() -> Self {
+        Self { content: FeedContent::default(), footer: String::new(), items: DEFAULT_ITEMS }
+    }
+}
+
+impl FeedOptions {
+    pub fn from_settings(settings: &HashMap<String, String>) -> Self {
+        Self {
+            content: settings
+                .get(FEED_CONTENT_KEY)
+                .and_then(|v| FeedContent::parse(v))
+                .unwrap_or_default(),
+            footer: settings.get(FEED_FOOTER_KEY).map(|v| v.trim().to_string()).unwrap_or_default(),
+            items: settings
+                .get(FEED_ITEMS_KEY)
+                .and_then(|v| v.parse::<u32>().ok())
+                .filter(|items| (1..=MAX_ITEMS).contains(items))
+                .unwrap_or(DEFAULT_ITEMS),
+        }
+    }
+
+    pub fn to_settings(&self) -> [(&'static str, String); 3] {
+        [
+            (FEED_CONTENT_KEY, self.content.as_str().to_string()),
+            (FEED_FOOTER_KEY, self.footer.clone()),
+            (FEED_ITEMS_KEY, self.items.to_string()),
+        ]
+    }
+
+    /// Short tag for the feed ETag so changing these options invalidates cached
+    /// copies; empty for the defaults
+    pub fn version(&self) -> String {
+        if *self == Self::default() {
+            return String::new();
+        }
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        std::hash::Hash::hash(&(self.content.as_str(), &self.footer, self.items), &mut hasher);
+        format!("{:08x}", std::hash::Hasher::finish(&hasher) as u32)
+    }
+}
+
+/// Channel-level data shared by the RSS and Atom feeds
+pub struct FeedChannel<'a> {
+    pub title: &'a str,
+    /// Public base URL, no trailing slash
+    pub site_url: &'a str,
+    /// WebSub hub advertised to subscribers
+    pub hub: Option<&'a str>,
+    /// Absolute URLs of the site favicon and logo
+    pub icon: Option<&'a str>,
+    pub logo: Option<&'a str>,
+    /// Footer appended to each item (see `FeedOptions::footer`)
+    pub footer: Option<&'a str>,
+    /// Item dates are written with this timezone's offset
+    pub timezone: Tz,
+}
+
+/// One published article
+pub struct FeedItem {
+    pub id: i64,
+    /// Site-relative URL of the article page, `/post/{slug}`
+    pub path: String,
+    pub title: String,
+    pub summary: String,
+    /// Rendered HTML of the whole article, when the feed carries full content
+    pub content: Option<String>,
+    /// SQLite timestamps, "YYYY-MM-DD HH:MM:SS" in UTC; `published` is when the
+    /// article went public, which for a published draft is later than its creation
+    pub published: String,
+    pub updated: String,
+}
+
+fn parse_sqlite_time(value: &str) -> DateTime<Utc> {
+    parse_stored(value).unwrap_or_default()
+}
+
+fn local_time(channel: &FeedChannel, value: &str) -> DateTime<Tz> {
+    parse_sqlite_time(value).with_timezone(&channel.timezone)
+}
+
+/// Cache validators for a feed, derived from the time of the newest change to
+/// the published articles (including removals) and the article count
+pub struct FeedValidators {
+    pub etag: String,
+    pub last_modified: DateTime<Utc>,
+}
+
+impl FeedValidators {
+    pub fn new(newest_updated_at: Option<&str>, article_count: i64) -> Self {
+        let last_modified = newest_updated_at.map(parse_sqlite_time).unwrap_or_default();
+        Self {
+            etag: format!("\"{}-{}\"", last_modified.timestamp(), article_count),
+            last_modified,
+        }
+    }
+
+    /// Fold something else the feed body depends on (such as the logo) into the ETag
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.etag = format!("{}-{}\"", self.etag.trim_end_matches('"'), version);
+        self
+    }
+
+    /// `Last-Modified` header value (IMF-fixdate)
+    pub fn last_modified_header(&self) -> String {
+        self.last_modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+    }
+
+    /// True when the client's copy is current. `If-None-Match` wins over
+    /// `If-Modified-Since` when both are sent (RFC 9110 13.2.2).
+    pub fn is_not_modified(&self, if_none_match: Option<&str>, if_modified_since: Option<&str>) -> bool {
+        if let Some(tags) = if_none_match {
+            return tags
+                .split(',')
+                .map(|t| t.trim().trim_start_matches("W/"))
+                .any(|t| t == "*" || t == self.etag);
+        }
+        if_modified_since
+            .and_then(|since| DateTime::parse_from_rfc2822(since).ok())
+            .is_some_and(|since| self.last_modified.timestamp() <= since.timestamp())
+    }
+}
+
+fn item_link(channel: &FeedChannel, item: &FeedItem) -> String {
+    format!("{}{}", channel.site_url, item.path)
+}
+
+/// Permanent id of an item: the `/post/{id}` URL, which keeps working (it
+/// redirects to the current slug) when the title and with it the slug change,
+/// so renaming an article never makes readers show it again as new
+fn item_guid(channel: &FeedChannel, item: &FeedItem) -> String {
+    format!("{}/post/{}", channel.site_url, item.id)
+}
+
+/// Summary and full content of an item with the channel footer appended: as a
+/// separate paragraph of the summary text, and as an HTML paragraph of the content
+fn item_body(channel: &FeedChannel, item: &FeedItem, link: &str) -> (String, Option<String>) {
+    let footer = channel
+        .footer
+        .filter(|footer| !footer.trim().is_empty())
+        .map(|footer| footer.replace("{title}", &item.title).replace("{link}", link));
+    match footer {
+        Some(footer) => (
+            format!("{}\n\n{}", item.summary, footer),
+            item.content
+                .as_ref()
+                .map(|content| format!("{}\n<p class=\"feed-footer\">{}</p>", content, encode_text(&footer))),
+        ),
+        None => (item.summary.clone(), item.content.clone()),
+    }
+}
+
+/// Text for a CDATA section; a `]]>` inside is split across two sections
+fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+/// `<link rel="hub">` / `<link rel="self">` pair used for WebSub discovery
+fn websub_links(channel: &FeedChannel, self_path: &str, element: &str) -> String {
+    let mut links = String::new();
+    if let Some(hub) = channel.hub {
+        links.push_str(&format!("  <{} rel=\"hub\" href=\"{}\"/>\n", element, encode_text(hub)));
+    }
+    links.push_str(&format!(
+        "  <{} rel=\"self\" href=\"{}{}\"/>\n",
+        element,
+        encode_text(channel.site_url),
+        self_path
+    ));
+    links
+}
+
+/// RSS 2.0 feed served at `/feed.xml`
+pub fn build_rss(channel: &FeedChannel, items: &[FeedItem]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" xmlns:content=\"http://purl.org/rss/1.0/modules/content/\">\n<channel>\n");
+    xml.push_str(&format!("  <title>{}</title>\n", encode_text(channel.title)));
+    xml.push_str(&format!("  <link>{}/</link>\n", encode_text(channel.site_url)));
+    xml.push_str(&format!("  <description>{}</description>\n", encode_text(channel.title)));
+    xml.push_str(&websub_links(channel, "/feed.xml", "atom:link"));
+    if let Some(logo) = channel.logo {
+        xml.push_str("  <image>\n");
+        xml.push_str(&format!("    <url>{}</url>\n", encode_text(logo)));
+        xml.push_str(&format!("    <title>{}</title>\n", encode_text(channel.title)));
+        xml.push_str(&format!("    <link>{}/</link>\n", encode_text(channel.site_url)));
+        xml.push_str("  </image>\n");
+    }
+    for item in items {
+        let link = item_link(channel, item);
+        xml.push_str("  <item>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", encode_text(&item.title)));
+        xml.push_str(&format!("    <link>{}</link>\n", encode_text(&link)));
+        xml.push_str(&format!("    <guid isPermaLink=\"true\">{}</guid>\n", encode_text(&item_guid(channel, item))));
+        xml.push_str(&format!("    <pubDate>{}</pubDate>\n", local_time(channel, &item.published).to_rfc2822()));
+        let (summary, content) = item_body(channel, item, &link);
+        xml.push_str(&format!("    <description>{}</description>\n", encode_text(&summary)));
+        if let Some(content) = content {
+            xml.push_str(&format!("    <content:encoded>{}</content:encoded>\n", cdata(&content)));
+        }
+        xml.push_str("  </item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+/// Atom 1.0 feed served at `/atom.xml`
+pub fn build_atom(channel: &FeedChannel, items: &[FeedItem]) -> String {
+    let updated = items
+        .iter()
+        .map(|item| parse_sqlite_time(&item.updated))
+        .max()
+        .unwrap_or_default()
+        .with_timezone(&channel.timezone);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <title>{}</title>\n", encode_text(channel.title)));
+    xml.push_str(&format!("  <id>{}/</id>\n", encode_text(channel.site_url)));
+    xml.push_str(&format!("  <updated>{}</updated>\n", updated.to_rfc3339()));
+    xml.push_str(&format!("  <link href=\"{}/\"/>\n", encode_text(channel.site_url)));
+    xml.push_str(&websub_links(channel, "/atom.xml", "link"));
+    if let Some(icon) = channel.icon {
+        xml.push_str(&format!("  <icon>{}</icon>\n", encode_text(icon)));
+    }
+    if let Some(logo) = channel.logo {
+        xml.push_str(&format!("  <logo>{}</logo>\n", encode_text(logo)));
+    }
+    for item in items {
+        let link = item_link(channel, item);
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", encode_text(&item.title)));
+        xml.push_str(&format!("    <id>{}</id>\n", encode_text(&item_guid(channel, item))));
+        xml.push_str(&format!("    <link href=\"{}\"/>\n", encode_text(&link)));
+        xml.push_str(&format!("    <published>{}</published>\n", local_time(channel, &item.published).to_rfc3339()));
+        xml.push_str(&format!("    <updated>{}</updated>\n", local_time(channel, &item.updated).to_rfc3339()));
+        let (summary, content) = item_body(channel, item, &link);
+        xml.push_str(&format!("    <summary>{}</summary>\n", encode_text(&summary)));
+        if let Some(content) = content {
+            xml.push_str(&format!("    <content type=\"html\">{}</content>\n", encode_text(&content)));
+        }
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<FeedItem> {
+        vec![FeedItem {
+            id: 7,
+            path: "/post/tom-jerry".to_string(),
+            title: "Tom & Jerry".to_string(),
+            summary: "<b>hi</b>".to_string(),
+            content: None,
+            published: "2024-05-01 10:00:00".to_string(),
+            updated: "2024-05-02 08:30:00".to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_rss_advertises_hub() {
+        let channel = FeedChannel { title: "Blog", site_url: "https://b.example", hub: Some("https://hub.example/"), icon: None, logo: None, footer: None, timezone: Tz::UTC };
+        let xml = build_rss(&channel, &items());
+        assert!(xml.contains("<atom:link rel=\"hub\" href=\"https://hub.example/\"/>"));
+        assert!(xml.contains("<atom:link rel=\"self\" href=\"https://b.example/feed.xml\"/>"));
+        assert!(xml.contains("<title>Tom &amp; Jerry</title>"));
+        assert!(xml.contains("<description>&lt;b&gt;hi&lt;/b&gt;</description>"));
+        assert!(xml.contains("<pubDate>Wed, 1 May 2024 10:00:00 +0000</pubDate>"));
+        assert!(xml.contains("<link>https://b.example/post/tom-jerry</link>"));
+        assert!(xml.contains("<guid isPermaLink=\"true\">https://b.example/post/7</guid>"));
+        assert!(!xml.contains("<image>"));
+    }
+
+    #[test]
+    fn test_feed_validators() {
+        let validators = FeedValidators::new(Some("2024-05-02 08:30:00"), 3);
+        assert_eq!(validators.etag, "\"1714638600-3\"");
+        assert_eq!(validators.last_modified_header(), "Thu, 02 May 2024 08:30:00 GMT");
+
+        assert!(validators.is_not_modified(Some("\"1714638600-3\""), None));
+        assert!(validators.is_not_modified(Some("\"x\", W/\"1714638600-3\""), None));
+        assert!(!validators.is_not_modified(Some("\"1714638600-2\""), Some("Thu, 02 May 2024 08:30:00 GMT")));
+        assert!(validators.is_not_modified(None, Some("Thu, 02 May 2024 08:30:00 GMT")));
+        assert!(!validators.is_not_modified(None, Some("Thu, 02 May 2024 08:29:59 GMT")));
+        assert!(!validators.is_not_modified(None, Some("garbage")));
+        assert!(!validators.is_not_modified(None, None));
+
+        let versioned = FeedValidators::new(Some("2024-05-02 08:30:00"), 3).with_version("ab12");
+        assert_eq!(versioned.etag, "\"1714638600-3-ab12\"");
+    }
+
+    #[test]
+    fn test_feed_images() {
+        let channel = FeedChannel {
+            title: "Blog",
+            site_url: "https://b.example",
+            hub: None,
+            icon: Some("https://b.example/favicon.ico?v=1"),
+            logo: Some("https://b.example/logo?v=2"),
+            footer: None,
+            timezone: Tz::UTC,
+        };
+        let rss = build_rss(&channel, &items());
+        assert!(rss.contains("<image>\n    <url>https://b.example/logo?v=2</url>\n    <title>Blog</title>"));
+        let atom = build_atom(&channel, &items());
+        assert!(atom.contains("<icon>https://b.example/favicon.ico?v=1</icon>"));
+        assert!(atom.contains("<logo>https://b.example/logo?v=2</logo>"));
+    }
+
+    #[test]
+    fn test_full_content_and_footer() {
+        let channel = FeedChannel {
+            title: "Blog",
+            site_url: "https://b.example",
+            hub: None,
+            icon: None,
+            logo: None,
+            footer: Some("© Tom & co, from {link}"),
+            timezone: Tz::UTC,
+        };
+        let mut items = items();
+        items[0].content = Some("<p>Body ]]> end</p>".to_string());
+
+        let rss = build_rss(&channel, &items);
+        assert!(rss.contains("<description>&lt;b&gt;hi&lt;/b&gt;\n\n© Tom &amp; co, from https://b.example/post/tom-jerry</description>"));
+        assert!(rss.contains(
+            "<content:encoded><![CDATA[<p>Body ]]]]><![CDATA[> end</p>\n<p class=\"feed-footer\">© Tom &amp; co, from https://b.example/post/tom-jerry</p>]]></content:encoded>"
+        ));
+        let atom = build_atom(&channel, &items);
+        assert!(atom.contains("<content type=\"html\">&lt;p&gt;Body ]]&gt; end&lt;/p&gt;\n&lt;p class=\"feed-footer\"&gt;© Tom &amp;amp; co"));
+
+        // Summary-only feeds have no content element
+        items[0].content = None;
+        assert!(!build_atom(&channel, &items).contains("<content"));
+    }
+
+    #[test]
+    fn test_feed_options() {
+        assert_eq!(FeedOptions::default().version(), "");
+        let settings: HashMap<String, String> = [
+            (FEED_CONTENT_KEY.to_string(), "full".to_string()),
+            (FEED_FOOTER_KEY.to_string(), " © 2025 ".to_string()),
+        ]
+        .into();
+        let options = FeedOptions::from_settings(&settings);
+        assert_eq!(options, FeedOptions { content: FeedContent::Full, footer: "© 2025".to_string(), items: DEFAULT_ITEMS });
+        assert_eq!(options.version().len(), 8);
+        assert_ne!(FeedOptions { items: 50, ..FeedOptions::default() }.version(), "");
+        let invalid: HashMap<String, String> = [
+            (FEED_CONTENT_KEY.to_string(), "everything".to_string()),
+            (FEED_ITEMS_KEY.to_string(), "0".to_string()),
+        ]
+        .into();
+        assert_eq!(FeedOptions::from_settings(&invalid), FeedOptions::default());
+    }
+
+    #[test]
+    fn test_atom_without_hub() {
+        let channel = FeedChannel { title: "Blog", site_url: "https://b.example", hub: None, icon: None, logo: None, footer: None, timezone: Tz::UTC };
+        let xml = build_atom(&channel, &items());
+        assert!(!xml.contains("rel=\"hub\""));
+        assert!(xml.contains("<link rel=\"self\" href=\"https://b.example/atom.xml\"/>"));
+        assert!(xml.contains("<updated>2024-05-02T08:30:00+00:00</updated>"));
+        assert!(xml.contains("<id>https://b.example/post/7</id>"));
+        assert!(xml.contains("<link href=\"https://b.example/post/tom-jerry\"/>"));
+    }
+
+    #[test]
+    fn test_dates_in_site_timezone() {
+        let channel = FeedChannel {
+            title: "Blog",
+            site_url: "https://b.example",
+            hub: None,
+            icon: None,
+            logo: None,
+            footer: None,
+            timezone: "Asia/Tokyo".parse().unwrap(),
+        };
+        assert!(build_rss(&channel, &items()).contains("<pubDate>Wed, 1 May 2024 19:00:00 +0900</pubDate>"));
+        let atom = build_atom(&channel, &items());
+        assert!(atom.contains("<published>2024-05-01T19:00:00+09:00</published>"));
+        assert!(atom.contains("<updated>2024-05-02T17:30:00+09:00</updated>"));
+    }
+}
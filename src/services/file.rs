@@ -1,6 +1,46 @@
 use serde::{Deserialize, Serialize};
+use chrono::SecondsFormat;
+use chrono_tz::Tz;
 use gray_matter::{Matter, engine::YAML};
-use crate::models::Article;
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use crate::models::{Article, ArticleRevision, STATUS_DRAFT};
+use super::archive::{read_zip, ZipWriter};
+use super::html_markdown::{self, Element};
+use super::render_options::RenderOptions;
+use super::text::TextDirection;
+
+/// Marks the start of revision history appended to an export; dropped again on import
+pub const REVISION_HISTORY_MARKER: &str = "<!-- bluster:revision-history -->";
+
+/// How revision history travels with an exported article
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevisionExport {
+    /// Current content only
+    None,
+    /// Revisions appended to the markdown file after `REVISION_HISTORY_MARKER`
+    Append,
+    /// Zip with the article plus one markdown file per revision
+    Bundle,
+}
+
+impl RevisionExport {
+    /// From the `revisions` query parameter: `append`, `zip` (or `bundle`), anything else is `None`
+    pub fn parse(value: Option<&str>) -> Self {
+        match value.map(str::to_lowercase).as_deref() {
+            Some("append") => RevisionExport::Append,
+            Some("zip") | Some("bundle") => RevisionExport::Bundle,
+            _ => RevisionExport::None,
+        }
+    }
+}
+
+/// A ready-to-download export
+#[derive(Debug)]
+pub struct ExportFile {
+    pub filename: String,
+    pub content_type: &'static str,
+    pub bytes: Vec<u8>,
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum FileError {
@@ -11,6 +51,196 @@ pub enum FileError {
     FrontMatterError(String),
     #[error("File size too large: {0} bytes")]
     FileTooLarge(usize),
+    #[error("Invalid export: {0}")]
+    InvalidExport(String),
+}
+
+/// What an uploaded file is imported as, by extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// `.md` / `.markdown`, one article
+    Markdown,
+    /// Ghost's JSON export (`.json`)
+    Ghost,
+    /// One post from a Medium export (`posts/*.html`)
+    MediumPost,
+    /// The whole Medium export archive (`.zip`)
+    MediumArchive,
+}
+
+impl ImportFormat {
+    pub fn from_filename(filename: &str) -> Result<Self, FileError> {
+        let lower = filename.to_lowercase();
+        match lower.rsplit_once('.').map(|(_, extension)| extension) {
+            Some("md" | "markdown") => Ok(ImportFormat::Markdown),
+            Some("json") => Ok(ImportFormat::Ghost),
+            Some("html" | "htm") => Ok(ImportFormat::MediumPost),
+            Some("zip") => Ok(ImportFormat::MediumArchive),
+            _ => Err(FileError::UnsupportedFormat(format!(
+                "File must be .md or .markdown, a Ghost .json export, or a Medium .html post or .zip export, got: {}",
+                filename
+            ))),
+        }
+    }
+
+    /// Upload size limit; whole-site exports may be much larger than one article
+    pub fn max_size_mb(self) -> usize {
+        match self {
+            ImportFormat::Markdown | ImportFormat::MediumPost => 5,
+            ImportFormat::Ghost | ImportFormat::MediumArchive => 50,
+        }
+    }
+}
+
+/// Articles read from one uploaded file
+#[derive(Debug, Default)]
+pub struct ParsedImport {
+    /// Where each article came from (file, archive entry or post title) and the article
+    pub posts: Vec<(String, MarkdownFile)>,
+    /// Posts left out, such as drafts and pages, with the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GhostExport {
+    #[serde(default)]
+    db: Vec<GhostDatabase>,
+    /// Exports before Ghost 1.0 have no `db` wrapper
+    data: Option<GhostData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GhostDatabase {
+    data: GhostData,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct GhostData {
+    #[serde(default)]
+    posts: Vec<GhostPost>,
+    #[serde(default)]
+    tags: Vec<GhostTag>,
+    #[serde(default)]
+    posts_tags: Vec<GhostPostTag>,
+}
+
+// Ghost ids are strings in current exports and numbers in old ones
+#[derive(Debug, Deserialize)]
+struct GhostPost {
+    id: serde_json::Value,
+    title: Option<String>,
+    html: Option<String>,
+    mobiledoc: Option<String>,
+    plaintext: Option<String>,
+    status: Option<String>,
+    #[serde(rename = "type")]
+    post_type: Option<String>,
+    /// Marks pages in old exports
+    #[serde(default)]
+    page: serde_json::Value,
+    published_at: Option<String>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    custom_excerpt: Option<String>,
+    meta_description: Option<String>,
+    canonical_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GhostTag {
+    id: serde_json::Value,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GhostPostTag {
+    post_id: serde_json::Value,
+    tag_id: serde_json::Value,
+    #[serde(default)]
+    sort_order: i64,
+}
+
+/// Front matter and path conventions of the exported markdown, for moving
+/// articles to another static site generator or note app
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportProfile {
+    /// Bluster's own format; imports back without loss
+    #[default]
+    Bluster,
+    /// `date` / `lastmod` in RFC 3339, images under `/images/` (Hugo's `static/images`)
+    Hugo,
+    /// `layout: post`, `YYYY-MM-DD-slug.md` file names for `_posts`, images under `/assets/images/`
+    Jekyll,
+    /// `created` / `updated` properties, images in the vault's `attachments/` folder
+    Obsidian,
+}
+
+impl ExportProfile {
+    /// From the `profile` query parameter; `None` for an unknown profile
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "bluster" => Some(ExportProfile::Bluster),
+            "hugo" => Some(ExportProfile::Hugo),
+            "jekyll" => Some(ExportProfile::Jekyll),
+            "obsidian" => Some(ExportProfile::Obsidian),
+            _ => None,
+        }
+    }
+
+    /// Where files uploaded to `/media/` live after the move
+    fn media_prefix(self) -> &'static str {
+        match self {
+            ExportProfile::Bluster => "/media/",
+            ExportProfile::Hugo => "/images/",
+            ExportProfile::Jekyll => "/assets/images/",
+            ExportProfile::Obsidian => "attachments/",
+        }
+    }
+}
+
+/// A date from an import, RFC 3339 or as stored, in the stored UTC form
+pub fn import_timestamp(value: &str) -> Option<String> {
+    super::dates::to_stored(value)
+}
+
+/// A timestamp in `format` in the site's timezone, or as stored when it cannot be parsed
+fn format_timestamp(value: &str, format: &str, timezone: Tz) -> String {
+    super::dates::parse_stored(value)
+        .map(|t| t.with_timezone(&timezone).format(format).to_string())
+        .unwrap_or_else(|| value.to_string())
+}
+
+/// Point links and images at uploaded media (`/media/...`) to `prefix` instead
+fn rewrite_media_paths(content: &str, prefix: &str) -> String {
+    const MEDIA: &str = "/media/";
+    // (start, end) of each destination in the source; a link around an image
+    // comes before the image's own destination
+    let mut destinations: Vec<(usize, usize)> = Parser::new_ext(content, Options::all())
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::Image(_, dest, _)) | Event::Start(Tag::Link(_, dest, _)) if dest.starts_with(MEDIA) => {
+                // The destination follows the description, so it is the last occurrence
+                let offset = content[range.clone()].rfind(dest.as_ref())?;
+                Some((range.start + offset, range.start + offset + dest.len()))
+            }
+            _ => None,
+        })
+        .collect();
+    destinations.sort_unstable();
+
+    let mut rewritten = String::with_capacity(content.len());
+    let mut copied = 0;
+    for (start, end) in destinations {
+        if start < copied {
+            continue;
+        }
+        rewritten.push_str(&content[copied..start]);
+        rewritten.push_str(prefix);
+        rewritten.push_str(&content[start + MEDIA.len()..end]);
+        copied = end;
+    }
+    rewritten.push_str(&content[copied..]);
+    rewritten
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -19,6 +249,19 @@ pub struct MarkdownFile {
     pub content: String,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
+    /// `direction` (or `dir`) from front matter; absent or unrecognized values leave it unset
+    pub direction: Option<TextDirection>,
+    /// `math`, `toc` and `highlight_theme` from front matter
+    pub render_options: RenderOptions,
+    /// Tags from a Ghost export
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Excerpt or subtitle from a Ghost or Medium export
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Where the post was first published, from front matter or a Ghost or Medium export
+    #[serde(default)]
+    pub canonical_url: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -26,6 +269,14 @@ struct FrontMatterData {
     title: Option<String>,
     created_at: Option<String>,
     updated_at: Option<String>,
+    #[serde(alias = "dir")]
+    direction: Option<String>,
+    // Hugo themes spell it `canonicalURL`
+    #[serde(alias = "canonicalURL")]
+    canonical_url: Option<String>,
+    math: Option<bool>,
+    toc: Option<bool>,
+    highlight_theme: Option<String>,
 }
 
 pub struct FileService;
@@ -66,6 +317,9 @@ impl FileService {
             return Err(FileError::FileTooLarge(content.len()));
         }
 
+        // Revision history from an earlier export is not part of the article
+        let content = Self::strip_revision_history(content);
+
         // Use gray_matter to parse front matter
         let matter = Matter::<YAML>::new();
         
@@ -94,6 +348,17 @@ impl FileService {
                     content: parsed.content.to_string(),
                     created_at: front_matter.created_at,
                     updated_at: front_matter.updated_at,
+                    direction: front_matter.direction.as_deref().and_then(TextDirection::parse),
+                    render_options: RenderOptions {
+                        math: front_matter.math,
+                        toc: front_matter.toc,
+                        highlight_theme: front_matter.highlight_theme
+                            .map(|theme| theme.trim().to_string())
+                            .filter(|theme| !theme.is_empty()),
+                    },
+                    tags: Vec::new(),
+                    description: None,
+                    canonical_url: front_matter.canonical_url.as_deref().and_then(super::canonical::normalize),
                 })
             }
             None => {
@@ -114,6 +379,11 @@ impl FileService {
                     content: parsed.content.to_string(),
                     created_at: None,
                     updated_at: None,
+                    direction: None,
+                    render_options: RenderOptions::default(),
+                    tags: Vec::new(),
+                    description: None,
+                    canonical_url: None,
                 })
             }
         }
@@ -138,15 +408,17 @@ impl FileService {
         let sanitized = title.trim();
         if sanitized.is_empty() {
             "Untitled".to_string()
-        } else if sanitized.len() > 200 {
-            // Truncate very long titles
-            format!("{}...", &sanitized[..197])
+        } else if sanitized.chars().count() > 200 {
+            // Truncate very long titles at a word or CJK clause boundary
+            super::text::truncate(sanitized, 198)
         } else {
             sanitized.to_string()
         }
     }
 
-    pub fn generate_markdown_export(article: &Article) -> Result<String, FileError> {
+    /// Bluster's own profile keeps the stored UTC timestamps; the others are
+    /// written in `timezone`, which Jekyll also uses for the file name
+    pub fn generate_markdown_export(article: &Article, profile: ExportProfile, timezone: Tz) -> Result<String, FileError> {
         // Validate article data
         if article.title.trim().is_empty() {
             return Err(FileError::UnsupportedFormat("Article title is empty".to_string()));
@@ -158,11 +430,67 @@ impl FileService {
 
         // Escape quotes in title for YAML front matter
         let escaped_title = article.title.replace("\"", "\\\"");
-        
-        let export_content = format!(
-            "---\ntitle: \"{}\"\ncreated_at: \"{}\"\nupdated_at: \"{}\"\n---\n\n{}",
-            escaped_title, article.created_at, article.updated_at, article.content
-        );
+        let render_options = RenderOptions::from_json(article.render_options.as_deref());
+        // Canonical URLs never contain quotes, see `canonical::normalize`
+        let canonical = |key: &str| {
+            article.canonical_url.as_deref().map(|url| format!("{}: \"{}\"\n", key, url)).unwrap_or_default()
+        };
+
+        let export_content = match profile {
+            ExportProfile::Bluster => {
+                // Only an explicit direction is exported; auto is the default on import
+                let direction = match TextDirection::parse(&article.text_direction) {
+                    Some(direction) if direction != TextDirection::Auto => format!("direction: {}\n", direction.as_str()),
+                    _ => String::new(),
+                };
+                format!(
+                    "---\ntitle: \"{}\"\ncreated_at: \"{}\"\nupdated_at: \"{}\"\n{}{}{}---\n\n{}",
+                    escaped_title,
+                    article.created_at,
+                    article.updated_at,
+                    direction,
+                    canonical("canonical_url"),
+                    render_options.front_matter(),
+                    article.content
+                )
+            }
+            ExportProfile::Hugo | ExportProfile::Jekyll => {
+                // Both understand `math` and `toc`; a highlight theme is site-wide there
+                let shared = RenderOptions { highlight_theme: None, ..render_options }.front_matter();
+                let (header, date_key, updated_key, canonical_key) = if profile == ExportProfile::Hugo {
+                    let draft = if article.status == STATUS_DRAFT { "draft: true\n" } else { "draft: false\n" };
+                    (draft, "date", "lastmod", "canonicalURL")
+                } else {
+                    ("layout: post\n", "date", "last_modified_at", "canonical_url")
+                };
+                let timestamp = |value: &str| match profile {
+                    // RFC 3339, with `Z` for UTC
+                    ExportProfile::Hugo => super::dates::parse_stored(value)
+                        .map(|t| t.with_timezone(&timezone).to_rfc3339_opts(SecondsFormat::Secs, true))
+                        .unwrap_or_else(|| value.to_string()),
+                    _ => format_timestamp(value, "%Y-%m-%d %H:%M:%S %z", timezone),
+                };
+                format!(
+                    "---\n{}title: \"{}\"\n{}: {}\n{}: {}\n{}{}---\n\n{}",
+                    header,
+                    escaped_title,
+                    date_key,
+                    timestamp(&article.created_at),
+                    updated_key,
+                    timestamp(&article.updated_at),
+                    canonical(canonical_key),
+                    shared,
+                    rewrite_media_paths(&article.content, profile.media_prefix())
+                )
+            }
+            ExportProfile::Obsidian => format!(
+                "---\ntitle: \"{}\"\ncreated: {}\nupdated: {}\n---\n\n{}",
+                escaped_title,
+                format_timestamp(&article.created_at, "%Y-%m-%dT%H:%M:%S", timezone),
+                format_timestamp(&article.updated_at, "%Y-%m-%dT%H:%M:%S", timezone),
+                rewrite_media_paths(&article.content, profile.media_prefix())
+            ),
+        };
 
         // Validate the generated content isn't too large
         if export_content.len() > 100 * 1024 * 1024 { // 100MB limit
@@ -172,10 +500,109 @@ impl FileService {
         Ok(export_content)
     }
 
+    /// Export an article, with its revision history (newest first) included as `mode` asks.
+    /// Revision history is Bluster's own format, so other profiles export the current content only.
+    pub fn generate_export(
+        article: &Article,
+        revisions: &[ArticleRevision],
+        mode: RevisionExport,
+        profile: ExportProfile,
+        timezone: Tz,
+    ) -> Result<ExportFile, FileError> {
+        let markdown = Self::generate_markdown_export(article, profile, timezone)?;
+        let name = Self::sanitize_filename_with_fallback(&article.title);
+        if profile != ExportProfile::Bluster {
+            let filename = match profile {
+                // Jekyll only picks up posts named after their date
+                ExportProfile::Jekyll => format!(
+                    "{}-{}.md",
+                    format_timestamp(&article.created_at, "%Y-%m-%d", timezone),
+                    super::toc::slugify(&article.title)
+                ),
+                _ => format!("{}.md", name),
+            };
+            return Ok(ExportFile {
+                filename,
+                content_type: "text/markdown; charset=utf-8",
+                bytes: markdown.into_bytes(),
+            });
+        }
+        match mode {
+            RevisionExport::Append if !revisions.is_empty() => Ok(ExportFile {
+                filename: format!("{}.md", name),
+                content_type: "text/markdown; charset=utf-8",
+                bytes: format!("{}\n\n{}", markdown.trim_end(), Self::revision_history_section(revisions)).into_bytes(),
+            }),
+            RevisionExport::Bundle => {
+                let mut zip = ZipWriter::new();
+                zip.add_file(&format!("{}.md", name), markdown.as_bytes());
+                for revision in revisions {
+                    zip.add_file(&format!("revisions/{:04}.md", revision.id), Self::revision_markdown(revision).as_bytes());
+                }
+                Ok(ExportFile {
+                    filename: format!("{}.zip", name),
+                    content_type: "application/zip",
+                    bytes: zip.finish(),
+                })
+            }
+            _ => Ok(ExportFile {
+                filename: format!("{}.md", name),
+                content_type: "text/markdown; charset=utf-8",
+                bytes: markdown.into_bytes(),
+            }),
+        }
+    }
+
+    /// One revision as a standalone markdown file with front matter
+    fn revision_markdown(revision: &ArticleRevision) -> String {
+        format!(
+            "---\ntitle: \"{}\"\nrevision: {}\ncreated_at: \"{}\"\nauthor: \"{}\"\n---\n\n{}",
+            revision.title.replace('"', "\\\""),
+            revision.id,
+            revision.created_at,
+            revision.author.as_deref().unwrap_or("").replace('"', "\\\""),
+            revision.content
+        )
+    }
+
+    /// Revisions as fenced blocks after the history marker, so the article body renders unchanged
+    fn revision_history_section(revisions: &[ArticleRevision]) -> String {
+        let mut section = format!("{}\n## Revision history\n", REVISION_HISTORY_MARKER);
+        for revision in revisions {
+            // 围栏比正文中最长的反引号串更长，避免提前结束
+            let longest_run = revision
+                .content
+                .split(|c| c != '`')
+                .map(str::len)
+                .max()
+                .unwrap_or(0);
+            let fence = "`".repeat((longest_run + 1).max(4));
+            section.push_str(&format!(
+                "\n### Revision {} · {}{}\n\n**Title:** {}\n\n{}markdown\n{}\n{}\n",
+                revision.id,
+                revision.created_at,
+                revision.author.as_deref().map(|a| format!(" · {}", a)).unwrap_or_default(),
+                revision.title,
+                fence,
+                revision.content.trim_end(),
+                fence
+            ));
+        }
+        section
+    }
+
+    /// Drop revision history appended by an `Append` export
+    pub fn strip_revision_history(content: &str) -> &str {
+        match content.find(REVISION_HISTORY_MARKER) {
+            Some(index) => content[..index].trim_end(),
+            None => content,
+        }
+    }
+
     /// Generate markdown export with fallback on error
     #[allow(dead_code)] // Reserved for future use in export functionality
     pub fn generate_markdown_export_with_fallback(article: &Article) -> String {
-        match Self::generate_markdown_export(article) {
+        match Self::generate_markdown_export(article, ExportProfile::Bluster, Tz::UTC) {
             Ok(content) => content,
             Err(e) => {
                 log::warn!("Failed to generate proper markdown export, using fallback: {}", e);
@@ -202,8 +629,9 @@ impl FileService {
             .to_string();
 
         // Ensure filename isn't too long (most filesystems have 255 char limit)
+        // Cut by bytes, but never inside a character: CJK titles are 3 bytes per character
         let final_name = if sanitized.len() > 200 {
-            format!("{}...", &sanitized[..197])
+            format!("{}...", super::text::truncate_bytes(&sanitized, 197))
         } else {
             sanitized
         };
@@ -216,6 +644,153 @@ impl FileService {
         }
     }
 
+    /// Published posts from a Ghost JSON export, converted to Markdown with
+    /// their dates and tags. Images keep Ghost's `/content/images/...` paths.
+    pub fn parse_ghost_export(json: &str) -> Result<ParsedImport, FileError> {
+        let export: GhostExport = serde_json::from_str(json)
+            .map_err(|e| FileError::InvalidExport(format!("not a Ghost export ({})", e)))?;
+        let mut data = GhostData::default();
+        for database in export.db.into_iter().map(|db| db.data).chain(export.data) {
+            data.posts.extend(database.posts);
+            data.tags.extend(database.tags);
+            data.posts_tags.extend(database.posts_tags);
+        }
+        if data.posts.is_empty() {
+            return Err(FileError::InvalidExport("no posts found in Ghost export".to_string()));
+        }
+        data.posts_tags.sort_by_key(|link| link.sort_order);
+
+        let mut parsed = ParsedImport::default();
+        for post in data.posts {
+            let title = Self::sanitize_title(post.title.as_deref().unwrap_or(""));
+            let is_page = post.post_type.as_deref() == Some("page") || post.page == true || post.page == 1;
+            if is_page {
+                parsed.skipped.push((title, "Ghost pages are not imported".to_string()));
+                continue;
+            }
+            if post.status.as_deref().is_some_and(|status| status != "published") {
+                parsed.skipped.push((title, "only published posts are imported".to_string()));
+                continue;
+            }
+
+            let content = match post.html.as_deref().filter(|html| !html.trim().is_empty()) {
+                Some(html) => html_markdown::to_markdown(&html.replace("__GHOST_URL__", "")),
+                None => post
+                    .mobiledoc
+                    .as_deref()
+                    .and_then(Self::mobiledoc_markdown)
+                    .or(post.plaintext)
+                    .unwrap_or_default(),
+            };
+            if content.trim().is_empty() {
+                parsed.skipped.push((title, "post has no content".to_string()));
+                continue;
+            }
+
+            let tags = data
+                .posts_tags
+                .iter()
+                .filter(|link| link.post_id == post.id)
+                .filter_map(|link| data.tags.iter().find(|tag| tag.id == link.tag_id))
+                // 以 # 开头的是 Ghost 的内部标签
+                .filter(|tag| !tag.name.starts_with('#'))
+                .map(|tag| tag.name.trim().to_string())
+                .collect();
+            parsed.posts.push((
+                title.clone(),
+                MarkdownFile {
+                    title,
+                    content,
+                    created_at: post.published_at.or(post.created_at).as_deref().and_then(import_timestamp),
+                    updated_at: post.updated_at.as_deref().and_then(import_timestamp),
+                    direction: None,
+                    render_options: RenderOptions::default(),
+                    tags,
+                    description: post.custom_excerpt.or(post.meta_description).filter(|d| !d.trim().is_empty()),
+                    canonical_url: post.canonical_url.as_deref().and_then(super::canonical::normalize),
+                },
+            ));
+        }
+        Ok(parsed)
+    }
+
+    /// Markdown cards of a mobiledoc document, for Ghost posts exported without HTML
+    fn mobiledoc_markdown(mobiledoc: &str) -> Option<String> {
+        let document: serde_json::Value = serde_json::from_str(mobiledoc).ok()?;
+        let cards: Vec<&str> = document["cards"]
+            .as_array()?
+            .iter()
+            .filter(|card| matches!(card[0].as_str(), Some("markdown" | "card-markdown")))
+            .filter_map(|card| card[1]["markdown"].as_str())
+            .collect();
+        (!cards.is_empty()).then(|| cards.join("\n\n"))
+    }
+
+    /// One post page from a Medium export. Drafts have no publish date and are rejected.
+    pub fn parse_medium_post(html: &str) -> Result<MarkdownFile, FileError> {
+        let document = html_markdown::parse(html);
+        let field = |name: &str| document.find(&|e: &Element| e.name == "section" && e.attr("data-field") == Some(name));
+        let body = field("body").ok_or_else(|| FileError::InvalidExport("not a Medium post".to_string()))?;
+        let published = document
+            .find(&|e| e.has_class("dt-published"))
+            .and_then(|time| time.attr("datetime"))
+            .and_then(import_timestamp)
+            .ok_or_else(|| FileError::InvalidExport("Medium drafts are not imported".to_string()))?;
+
+        let title = document
+            .find(&|e| e.has_class("p-name"))
+            .or_else(|| document.find(&|e| e.name == "title"))
+            .map(Element::text)
+            .unwrap_or_default();
+        let title = Self::sanitize_title(&title);
+
+        // Medium 把标题重复在正文开头，前面还有一条分隔线
+        let markdown = body.to_markdown();
+        let mut blocks: Vec<&str> = markdown.split("\n\n").collect();
+        if blocks.first() == Some(&"---") {
+            blocks.remove(0);
+        }
+        if blocks.first().is_some_and(|b| b.starts_with('#') && b.trim_start_matches('#').trim() == title) {
+            blocks.remove(0);
+        }
+        let content = blocks.join("\n\n");
+        if content.trim().is_empty() {
+            return Err(FileError::InvalidExport("post has no content".to_string()));
+        }
+        Ok(MarkdownFile {
+            title,
+            content,
+            created_at: Some(published),
+            updated_at: None,
+            direction: None,
+            render_options: RenderOptions::default(),
+            tags: Vec::new(),
+            description: field("subtitle").map(Element::text).filter(|d| !d.is_empty()),
+            // The `p-canonical` link is the Medium copy itself, which is what is being moved away from
+            canonical_url: None,
+        })
+    }
+
+    /// Posts from a Medium export archive (`posts/*.html`); drafts are skipped
+    pub fn parse_medium_archive(bytes: &[u8], max_size: usize) -> Result<ParsedImport, FileError> {
+        let mut entries = read_zip(bytes, max_size).map_err(|e| FileError::InvalidExport(e.to_string()))?;
+        entries.retain(|entry| entry.name.starts_with("posts/") && entry.name.to_lowercase().ends_with(".html"));
+        if entries.is_empty() {
+            return Err(FileError::InvalidExport("no posts found in Medium export".to_string()));
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut parsed = ParsedImport::default();
+        for entry in entries {
+            let html = String::from_utf8_lossy(&entry.data);
+            match Self::parse_medium_post(&html) {
+                Ok(post) => parsed.posts.push((entry.name, post)),
+                Err(e) => parsed.skipped.push((entry.name, e.to_string())),
+            }
+        }
+        Ok(parsed)
+    }
+
     /// Sanitize filename with fallback on error
     pub fn sanitize_filename_with_fallback(title: &str) -> String {
         match Self::sanitize_filename(title) {
@@ -258,6 +833,159 @@ This is test content."#;
         assert!(result.content.contains("This is test content."));
     }
 
+    #[test]
+    fn test_direction_round_trip() {
+        let article = Article {
+            id: 1,
+            title: "مرحبا".to_string(),
+            content: "نص المقال".to_string(),
+            author_id: None,
+            created_at: "2024-01-01".to_string(),
+            updated_at: "2024-01-02".to_string(),
+            meta_description: None,
+            meta_keywords: None,
+            noindex: false,
+            text_direction: "rtl".to_string(),
+            render_options: None,
+            deleted_at: None,
+            canonical_url: None,
+            category_id: None,
+            status: "published".to_string(),
+            published_at: None,
+            slug: None,
+        };
+        let exported = FileService::generate_markdown_export(&article, ExportProfile::Bluster, Tz::UTC).unwrap();
+        assert!(exported.contains("\ndirection: rtl\n"));
+        assert_eq!(FileService::parse_markdown_file(&exported).unwrap().direction, Some(TextDirection::Rtl));
+
+        let parsed = FileService::parse_markdown_file("---\ntitle: A\ndir: sideways\n---\n\nBody").unwrap();
+        assert_eq!(parsed.direction, None);
+    }
+
+    #[test]
+    fn test_canonical_url_round_trip() {
+        let article = Article {
+            id: 1,
+            title: "Cross-posted".to_string(),
+            content: "Body".to_string(),
+            author_id: None,
+            created_at: "2024-01-01".to_string(),
+            updated_at: "2024-01-02".to_string(),
+            meta_description: None,
+            meta_keywords: None,
+            noindex: false,
+            text_direction: "auto".to_string(),
+            render_options: None,
+            deleted_at: None,
+            canonical_url: Some("https://dev.example/posts/cross-posted".to_string()),
+            category_id: None,
+            status: "published".to_string(),
+            published_at: None,
+            slug: None,
+        };
+        let exported = FileService::generate_markdown_export(&article, ExportProfile::Bluster, Tz::UTC).unwrap();
+        assert!(exported.contains("\ncanonical_url: \"https://dev.example/posts/cross-posted\"\n"));
+        assert_eq!(FileService::parse_markdown_file(&exported).unwrap().canonical_url, article.canonical_url);
+
+        let hugo = FileService::generate_markdown_export(&article, ExportProfile::Hugo, Tz::UTC).unwrap();
+        assert!(hugo.contains("\ncanonicalURL: \"https://dev.example/posts/cross-posted\"\n"));
+        assert_eq!(FileService::parse_markdown_file(&hugo).unwrap().canonical_url, article.canonical_url);
+        let jekyll = FileService::generate_markdown_export(&article, ExportProfile::Jekyll, Tz::UTC).unwrap();
+        assert!(jekyll.contains("\ncanonical_url: \"https://dev.example/posts/cross-posted\"\n"));
+
+        // Relative or malformed URLs are dropped on import
+        let parsed = FileService::parse_markdown_file("---\ntitle: A\ncanonical_url: /post/3\n---\n\nBody").unwrap();
+        assert_eq!(parsed.canonical_url, None);
+    }
+
+    #[test]
+    fn test_export_profiles() {
+        let article = Article {
+            id: 1,
+            title: "Hello \"World\"".to_string(),
+            content: "![Chart](/media/chart.png \"c\")\n\n[![x](/media/a.png)](/media/b.pdf) [site](https://example.com/media/x)\n\n`![c](/media/code.png)`".to_string(),
+            author_id: None,
+            created_at: "2024-03-05 08:09:10".to_string(),
+            updated_at: "2024-03-06".to_string(),
+            meta_description: None,
+            meta_keywords: None,
+            noindex: false,
+            text_direction: "rtl".to_string(),
+            render_options: Some(r#"{"math":true,"highlight_theme":"InspiredGitHub"}"#.to_string()),
+            deleted_at: None,
+            canonical_url: None,
+            category_id: None,
+            status: "published".to_string(),
+            published_at: None,
+            slug: None,
+        };
+
+        let hugo = FileService::generate_markdown_export(&article, ExportProfile::Hugo, Tz::UTC).unwrap();
+        assert!(hugo.starts_with(
+            "---\ndraft: false\ntitle: \"Hello \\\"World\\\"\"\ndate: 2024-03-05T08:09:10Z\nlastmod: 2024-03-06T00:00:00Z\nmath: true\n---\n\n"
+        ));
+        assert!(hugo.contains("![Chart](/images/chart.png \"c\")"));
+        assert!(hugo.contains("[![x](/images/a.png)](/images/b.pdf) [site](https://example.com/media/x)"));
+        assert!(hugo.contains("`![c](/media/code.png)`"));
+        assert!(!hugo.contains("direction") && !hugo.contains("highlight_theme"));
+
+        let jekyll = FileService::generate_markdown_export(&article, ExportProfile::Jekyll, Tz::UTC).unwrap();
+        assert!(jekyll.contains("layout: post\n"));
+        assert!(jekyll.contains("date: 2024-03-05 08:09:10 +0000\nlast_modified_at: 2024-03-06 00:00:00 +0000\n"));
+        assert!(jekyll.contains("(/assets/images/chart.png"));
+        let file = FileService::generate_export(&article, &[], RevisionExport::Bundle, ExportProfile::Jekyll, Tz::UTC).unwrap();
+        assert_eq!(file.filename, "2024-03-05-hello-world.md");
+
+        let obsidian = FileService::generate_markdown_export(&article, ExportProfile::Obsidian, Tz::UTC).unwrap();
+        assert!(obsidian.starts_with("---\ntitle: \"Hello \\\"World\\\"\"\ncreated: 2024-03-05T08:09:10\nupdated: 2024-03-06T00:00:00\n---\n\n"));
+        assert!(obsidian.contains("![Chart](attachments/chart.png \"c\")"));
+
+        // Other profiles are written in the site's timezone; 20:00 UTC is the next day in Shanghai
+        let late = Article { created_at: "2024-03-05 20:00:00".to_string(), ..article };
+        let shanghai: Tz = "Asia/Shanghai".parse().unwrap();
+        let hugo = FileService::generate_markdown_export(&late, ExportProfile::Hugo, shanghai).unwrap();
+        assert!(hugo.contains("\ndate: 2024-03-06T04:00:00+08:00\n"));
+        let file = FileService::generate_export(&late, &[], RevisionExport::Bundle, ExportProfile::Jekyll, shanghai).unwrap();
+        assert_eq!(file.filename, "2024-03-06-hello-world.md");
+        let bluster = FileService::generate_markdown_export(&late, ExportProfile::Bluster, shanghai).unwrap();
+        assert!(bluster.contains("created_at: \"2024-03-05 20:00:00\""));
+        let draft = Article { status: STATUS_DRAFT.to_string(), ..late };
+        assert!(FileService::generate_markdown_export(&draft, ExportProfile::Hugo, shanghai).unwrap().starts_with("---\ndraft: true\n"));
+
+        assert_eq!(ExportProfile::parse(" Hugo "), Some(ExportProfile::Hugo));
+        assert_eq!(ExportProfile::parse("wordpress"), None);
+    }
+
+    #[test]
+    fn test_render_options_round_trip() {
+        let parsed = FileService::parse_markdown_file("---\ntitle: A\nmath: true\ntoc: false\nhighlight_theme: dracula\n---\n\nBody").unwrap();
+        let options = RenderOptions { math: Some(true), toc: Some(false), highlight_theme: Some("dracula".to_string()) };
+        assert_eq!(parsed.render_options, options);
+
+        let article = Article {
+            id: 1,
+            title: "A".to_string(),
+            content: "Body".to_string(),
+            author_id: None,
+            created_at: "2024-01-01".to_string(),
+            updated_at: "2024-01-02".to_string(),
+            meta_description: None,
+            meta_keywords: None,
+            noindex: false,
+            text_direction: "auto".to_string(),
+            render_options: options.to_json(),
+            deleted_at: None,
+            canonical_url: None,
+            category_id: None,
+            status: "published".to_string(),
+            published_at: None,
+            slug: None,
+        };
+        let exported = FileService::generate_markdown_export(&article, ExportProfile::Bluster, Tz::UTC).unwrap();
+        assert!(exported.contains("\nmath: true\ntoc: false\nhighlight_theme: \"dracula\"\n---"));
+        assert_eq!(FileService::parse_markdown_file(&exported).unwrap().render_options, options);
+    }
+
     #[test]
     fn test_parse_markdown_without_front_matter() {
         let content = "# Hello World\n\nThis is test content.";
@@ -280,6 +1008,55 @@ This is test content."#;
         assert_eq!(result.content, content);
     }
 
+    fn revision(id: i64, content: &str) -> ArticleRevision {
+        ArticleRevision {
+            id,
+            article_id: 1,
+            title: format!("Title {}", id),
+            content: content.to_string(),
+            author: Some("admin".to_string()),
+            created_at: "2024-01-01 00:00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_export_with_appended_revisions_round_trips() {
+        let article = Article {
+            id: 1,
+            title: "Test Article".to_string(),
+            content: "Current body".to_string(),
+            author_id: None,
+            created_at: "2024-01-01".to_string(),
+            updated_at: "2024-01-02".to_string(),
+            meta_description: None,
+            meta_keywords: None,
+            noindex: false,
+            text_direction: "auto".to_string(),
+            render_options: None,
+            deleted_at: None,
+            canonical_url: None,
+            category_id: None,
+            status: "published".to_string(),
+            published_at: None,
+            slug: None,
+        };
+        let revisions = [revision(2, "Old body with ```code```"), revision(1, "First")];
+        let export = FileService::generate_export(&article, &revisions, RevisionExport::Append, ExportProfile::Bluster, Tz::UTC).unwrap();
+        let text = String::from_utf8(export.bytes).unwrap();
+        assert_eq!(export.filename, "Test Article.md");
+        assert!(text.contains("### Revision 2 · 2024-01-01 00:00:00 · admin"));
+        assert!(text.contains("````markdown\nOld body with ```code```\n````"));
+
+        let imported = FileService::parse_markdown_file(&text).unwrap();
+        assert_eq!(imported.content.trim(), "Current body");
+
+        let bundle = FileService::generate_export(&article, &revisions, RevisionExport::Bundle, ExportProfile::Bluster, Tz::UTC).unwrap();
+        assert_eq!(bundle.filename, "Test Article.zip");
+        assert_eq!(&bundle.bytes[..2], b"PK");
+        assert_eq!(RevisionExport::parse(Some("ZIP")), RevisionExport::Bundle);
+        assert_eq!(RevisionExport::parse(None), RevisionExport::None);
+    }
+
     #[test]
     fn test_sanitize_filename() {
         let dangerous_name = "My Article: <Test>?";
@@ -310,9 +1087,20 @@ This is test content."#;
             author_id: Some(1),
             created_at: "2024-01-01".to_string(),
             updated_at: "2024-01-02".to_string(),
+            meta_description: None,
+            meta_keywords: None,
+            noindex: false,
+            text_direction: "auto".to_string(),
+            render_options: None,
+            deleted_at: None,
+            canonical_url: None,
+            category_id: None,
+            status: "published".to_string(),
+            published_at: None,
+            slug: None,
         };
         
-        let result = FileService::generate_markdown_export(&article).unwrap();
+        let result = FileService::generate_markdown_export(&article, ExportProfile::Bluster, Tz::UTC).unwrap();
         
         assert!(result.contains("title: \"Test Title\""));
         assert!(result.contains("created_at: \"2024-01-01\""));
@@ -376,9 +1164,20 @@ This is test content."#;
             author_id: Some(1),
             created_at: "2024-01-01".to_string(),
             updated_at: "2024-01-02".to_string(),
+            meta_description: None,
+            meta_keywords: None,
+            noindex: false,
+            text_direction: "auto".to_string(),
+            render_options: None,
+            deleted_at: None,
+            canonical_url: None,
+            category_id: None,
+            status: "published".to_string(),
+            published_at: None,
+            slug: None,
         };
         
-        let result = FileService::generate_markdown_export(&article);
+        let result = FileService::generate_markdown_export(&article, ExportProfile::Bluster, Tz::UTC);
         assert!(result.is_err());
         match result.unwrap_err() {
             FileError::UnsupportedFormat(msg) => assert!(msg.contains("title is empty")),
@@ -397,6 +1196,17 @@ This is test content."#;
             author_id: Some(1),
             created_at: "2024-01-01".to_string(),
             updated_at: "2024-01-02".to_string(),
+            meta_description: None,
+            meta_keywords: None,
+            noindex: false,
+            text_direction: "auto".to_string(),
+            render_options: None,
+            deleted_at: None,
+            canonical_url: None,
+            category_id: None,
+            status: "published".to_string(),
+            published_at: None,
+            slug: None,
         };
         
         let result = FileService::generate_markdown_export_with_fallback(&article);
@@ -422,4 +1232,111 @@ This is test content."#;
         assert!(result.len() <= 200);
         assert!(result.ends_with("..."));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_long_cjk_title_and_filename() {
+        // Byte offset 197 falls inside a character here
+        let title = "长标题".repeat(100);
+        let name = FileService::sanitize_filename(&title).unwrap();
+        assert!(name.len() <= 200);
+        assert!(name.starts_with("长标题") && name.ends_with("..."));
+
+        let parsed = FileService::parse_markdown_file(&format!("---\ntitle: {}\n---\n\nBody", title)).unwrap();
+        assert!(parsed.title.chars().count() <= 200);
+        assert!(parsed.title.ends_with("……"));
+    }
+
+    #[test]
+    fn test_import_format() {
+        assert_eq!(ImportFormat::from_filename("Post.MD").unwrap(), ImportFormat::Markdown);
+        assert_eq!(ImportFormat::from_filename("blog.ghost.2024-01-01.json").unwrap(), ImportFormat::Ghost);
+        assert_eq!(ImportFormat::from_filename("2019-05-13_Hello-1a2b.html").unwrap(), ImportFormat::MediumPost);
+        assert_eq!(ImportFormat::from_filename("medium-export.zip").unwrap(), ImportFormat::MediumArchive);
+        assert!(ImportFormat::from_filename("notes.txt").is_err());
+        assert!(ImportFormat::from_filename("README").is_err());
+    }
+
+    #[test]
+    fn test_parse_ghost_export() {
+        let json = r##"{"db": [{"meta": {"version": "5.0.0"}, "data": {
+            "posts": [
+                {"id": "p1", "title": "Hello Ghost", "status": "published", "type": "post",
+                 "html": "<p>Hi <strong>there</strong></p><figure class=\"kg-card\"><img src=\"__GHOST_URL__/content/images/a.png\" alt=\"A\"></figure>",
+                 "published_at": "2021-03-04T05:06:07.000+02:00", "updated_at": "2021-04-01T00:00:00.000Z",
+                 "custom_excerpt": "First post", "canonical_url": "https://old.example/hello-ghost"},
+                {"id": "p2", "title": "Markdown card", "status": "published", "html": null,
+                 "mobiledoc": "{\"cards\":[[\"markdown\",{\"markdown\":\"*kept*\"}]]}", "created_at": "2020-01-01T00:00:00.000Z"},
+                {"id": "p3", "title": "Unfinished", "status": "draft", "html": "<p>x</p>"},
+                {"id": "p4", "title": "About", "status": "published", "type": "page", "html": "<p>x</p>"}
+            ],
+            "tags": [{"id": "t1", "name": "Rust"}, {"id": "t2", "name": "#internal"}, {"id": "t3", "name": "Web"}],
+            "posts_tags": [
+                {"post_id": "p1", "tag_id": "t3", "sort_order": 1},
+                {"post_id": "p1", "tag_id": "t1", "sort_order": 0},
+                {"post_id": "p1", "tag_id": "t2", "sort_order": 2}
+            ]
+        }}]}"##;
+        let parsed = FileService::parse_ghost_export(json).unwrap();
+        assert_eq!(parsed.posts.len(), 2);
+        assert_eq!(parsed.skipped.iter().map(|(title, _)| title.as_str()).collect::<Vec<_>>(), ["Unfinished", "About"]);
+
+        let (source, post) = &parsed.posts[0];
+        assert_eq!(source, "Hello Ghost");
+        assert_eq!(post.content, "Hi **there**\n\n![A](/content/images/a.png)");
+        assert_eq!(post.created_at.as_deref(), Some("2021-03-04 03:06:07"));
+        assert_eq!(post.updated_at.as_deref(), Some("2021-04-01 00:00:00"));
+        assert_eq!(post.tags, ["Rust", "Web"]);
+        assert_eq!(post.description.as_deref(), Some("First post"));
+        assert_eq!(post.canonical_url.as_deref(), Some("https://old.example/hello-ghost"));
+
+        let (_, card) = &parsed.posts[1];
+        assert_eq!(card.content, "*kept*");
+        assert_eq!(card.created_at.as_deref(), Some("2020-01-01 00:00:00"));
+
+        assert!(matches!(FileService::parse_ghost_export("{}"), Err(FileError::InvalidExport(_))));
+        assert!(matches!(FileService::parse_ghost_export("not json"), Err(FileError::InvalidExport(_))));
+    }
+
+    const MEDIUM_POST: &str = r#"<!DOCTYPE html><html><head><title>Moving on</title><style>p{}</style></head><body>
+        <article class="h-entry">
+        <header><h1 class="p-name">Moving on</h1></header>
+        <section data-field="subtitle" class="p-summary">Why I left</section>
+        <section data-field="body" class="e-content">
+          <section class="section"><div><hr class="section-divider"></div><div class="section-content"><div class="section-inner">
+            <h3 class="graf graf--h3 graf--title">Moving on</h3>
+            <p class="graf graf--p">It was <em>time</em>.</p>
+            <figure><img class="graf-image" src="https://cdn-images-1.medium.com/max/800/1*x.png"><figcaption>Bye</figcaption></figure>
+          </div></div></section>
+        </section>
+        <footer><p>Published on <a href="https://medium.com/@me"><time class="dt-published" datetime="2019-05-13T10:22:33.123Z">May 13, 2019</time></a>.</p></footer>
+        </article></body></html>"#;
+
+    #[test]
+    fn test_parse_medium_post() {
+        let post = FileService::parse_medium_post(MEDIUM_POST).unwrap();
+        assert_eq!(post.title, "Moving on");
+        assert_eq!(post.content, "It was *time*.\n\n![](https://cdn-images-1.medium.com/max/800/1*x.png)\n\nBye");
+        assert_eq!(post.created_at.as_deref(), Some("2019-05-13 10:22:33"));
+        assert_eq!(post.description.as_deref(), Some("Why I left"));
+
+        let draft = MEDIUM_POST.replace("dt-published", "dt-updated");
+        assert!(FileService::parse_medium_post(&draft).unwrap_err().to_string().contains("drafts"));
+        assert!(FileService::parse_medium_post("<p>Just a page</p>").is_err());
+    }
+
+    #[test]
+    fn test_parse_medium_archive() {
+        let mut zip = ZipWriter::new();
+        zip.add_file("README.html", b"<p>Your archive</p>");
+        zip.add_file("posts/2019-05-13_Moving-on-1a2b.html", MEDIUM_POST.as_bytes());
+        zip.add_file("posts/draft_Later-3c4d.html", MEDIUM_POST.replace("dt-published", "").as_bytes());
+        let parsed = FileService::parse_medium_archive(&zip.finish(), 1 << 20).unwrap();
+        assert_eq!(parsed.posts.len(), 1);
+        assert_eq!(parsed.posts[0].0, "posts/2019-05-13_Moving-on-1a2b.html");
+        assert_eq!(parsed.skipped[0].0, "posts/draft_Later-3c4d.html");
+
+        let mut empty = ZipWriter::new();
+        empty.add_file("profile/profile.html", b"");
+        assert!(FileService::parse_medium_archive(&empty.finish(), 1 << 20).is_err());
+    }
+}
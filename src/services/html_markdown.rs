@@ -0,0 +1,583 @@
+//! HTML to Markdown for posts imported from other blogging platforms. The input
+//! is the platform's own generated HTML, so a small forgiving parser is enough:
+//! unclosed paragraphs and list items are closed, stray end tags are ignored.
+
+use html_escape::decode_html_entities;
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+/// Elements whose content is not markup
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "iframe", "textarea", "title"];
+/// Elements dropped with their content
+const SKIPPED_ELEMENTS: &[&str] = &["script", "style", "head", "template", "noscript", "textarea", "title", "button", "form"];
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "body", "dd", "details", "div", "dl", "dt", "figcaption", "figure",
+    "footer", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "html", "li", "main", "nav", "ol", "p", "pre",
+    "section", "summary", "table", "ul",
+];
+const INLINE_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "br", "cite", "code", "del", "em", "i", "iframe", "img", "ins", "kbd", "mark", "q", "s", "small",
+    "span", "strike", "strong", "sub", "sup", "time", "u",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Element {
+    /// Lowercase tag name; empty for the document root
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+enum Token<'a> {
+    Start(String, Vec<(String, String)>, bool),
+    End(String),
+    Text(&'a str),
+}
+
+/// Position of the `>` closing a tag, skipping quoted attribute values
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_attrs(mut s: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    loop {
+        s = s.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        let name_end = s.find(|c: char| c.is_whitespace() || c == '=' || c == '/').unwrap_or(s.len());
+        if name_end == 0 {
+            return attrs;
+        }
+        let name = s[..name_end].to_ascii_lowercase();
+        s = s[name_end..].trim_start();
+        let mut value = String::new();
+        if let Some(rest) = s.strip_prefix('=') {
+            let rest = rest.trim_start();
+            let (raw, remaining) = match rest.chars().next() {
+                Some(q @ ('"' | '\'')) => {
+                    let end = rest[1..].find(q).map_or(rest.len(), |i| i + 1);
+                    (&rest[1..end], rest.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    (&rest[..end], &rest[end..])
+                }
+            };
+            value = decode_html_entities(raw).into_owned();
+            s = remaining;
+        }
+        attrs.push((name, value));
+    }
+}
+
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            tokens.push(Token::Text(rest));
+            break;
+        };
+        if lt > 0 {
+            tokens.push(Token::Text(&rest[..lt]));
+            rest = &rest[lt..];
+        }
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+            continue;
+        }
+        let closing = rest.starts_with("</");
+        let name_start = if closing { 2 } else { 1 };
+        if !rest[name_start..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            tokens.push(Token::Text("<"));
+            rest = &rest[1..];
+            continue;
+        }
+        let name_len = rest[name_start..]
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+            .unwrap_or(rest.len() - name_start);
+        let name = rest[name_start..name_start + name_len].to_ascii_lowercase();
+        let after_name = &rest[name_start + name_len..];
+        let Some(end) = tag_end(after_name) else {
+            // 没有闭合的标签当作文本
+            tokens.push(Token::Text(rest));
+            break;
+        };
+        let inner = &after_name[..end];
+        rest = &after_name[end + 1..];
+        if closing {
+            tokens.push(Token::End(name));
+            continue;
+        }
+        let self_closing = inner.trim_end().ends_with('/');
+        let raw_text = RAW_TEXT_ELEMENTS.contains(&name.as_str());
+        tokens.push(Token::Start(name.clone(), parse_attrs(inner), self_closing || raw_text));
+        if raw_text && !self_closing {
+            let close = format!("</{}", name);
+            let end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+            rest = &rest[end..];
+        }
+    }
+    tokens
+}
+
+/// Close the open elements from `depth` on, attaching each to its parent
+fn close_to(stack: &mut Vec<Element>, depth: usize) {
+    while stack.len() > depth.max(1) {
+        let element = stack.pop().unwrap_or_default();
+        if let Some(parent) = stack.last_mut() {
+            parent.children.push(Node::Element(element));
+        }
+    }
+}
+
+/// Parse an HTML document or fragment into a tree under an unnamed root
+pub fn parse(html: &str) -> Element {
+    let mut stack = vec![Element::default()];
+    for token in tokenize(html) {
+        match token {
+            Token::Text(text) => {
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(Node::Text(decode_html_entities(text).into_owned()));
+                }
+            }
+            Token::Start(name, attrs, self_closing) => {
+                // 块级元素会结束未闭合的段落，新列表项会结束同一列表中的上一项
+                if BLOCK_ELEMENTS.contains(&name.as_str())
+                    && let Some(depth) = stack.iter().rposition(|e| e.name == "p")
+                {
+                    close_to(&mut stack, depth);
+                }
+                if name == "li"
+                    && let Some(depth) = stack.iter().rposition(|e| e.name == "li")
+                    && !stack[depth..].iter().any(|e| e.name == "ul" || e.name == "ol")
+                {
+                    close_to(&mut stack, depth);
+                }
+                let element = Element { name, attrs, children: Vec::new() };
+                if self_closing || VOID_ELEMENTS.contains(&element.name.as_str()) {
+                    if let Some(parent) = stack.last_mut() {
+                        parent.children.push(Node::Element(element));
+                    }
+                } else {
+                    stack.push(element);
+                }
+            }
+            Token::End(name) => {
+                if let Some(depth) = stack.iter().rposition(|e| e.name == name) {
+                    close_to(&mut stack, depth);
+                }
+            }
+        }
+    }
+    close_to(&mut stack, 1);
+    stack.pop().unwrap_or_default()
+}
+
+/// Convert an HTML fragment to Markdown
+pub fn to_markdown(html: &str) -> String {
+    parse(html).to_markdown()
+}
+
+impl Element {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    pub fn has_class(&self, class: &str) -> bool {
+        self.attr("class").is_some_and(|classes| classes.split_whitespace().any(|c| c == class))
+    }
+
+    /// First descendant matching `predicate`, depth first
+    pub fn find(&self, predicate: &dyn Fn(&Element) -> bool) -> Option<&Element> {
+        self.children.iter().find_map(|child| match child {
+            Node::Element(element) if predicate(element) => Some(element),
+            Node::Element(element) => element.find(predicate),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// Text content with whitespace collapsed
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        collect_text(&self.children, &mut text);
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// The element's content as Markdown blocks
+    pub fn to_markdown(&self) -> String {
+        blocks(&self.children).join("\n\n")
+    }
+
+    fn is_block(&self) -> bool {
+        BLOCK_ELEMENTS.contains(&self.name.as_str())
+            || (!INLINE_ELEMENTS.contains(&self.name.as_str())
+                && self.children.iter().any(|child| matches!(child, Node::Element(e) if e.is_block())))
+    }
+}
+
+fn collect_text(nodes: &[Node], out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(element) if element.name == "br" => out.push('\n'),
+            Node::Element(element) if !SKIPPED_ELEMENTS.contains(&element.name.as_str()) => {
+                collect_text(&element.children, out)
+            }
+            Node::Element(_) => {}
+        }
+    }
+}
+
+fn blocks(nodes: &[Node]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut inline = String::new();
+    for node in nodes {
+        match node {
+            Node::Element(element) if element.is_block() => {
+                out.extend(paragraph(&inline));
+                inline.clear();
+                out.extend(block(element));
+            }
+            _ => inline_node(node, &mut inline),
+        }
+    }
+    out.extend(paragraph(&inline));
+    out
+}
+
+/// Escape what would start a heading, quote or list at the beginning of a line
+fn escape_line_start(line: &str) -> String {
+    if line.starts_with(['#', '>', '+', '-', '=']) {
+        return format!("\\{}", line);
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 && line[digits..].starts_with(['.', ')']) {
+        return format!("{}\\{}", &line[..digits], &line[digits..]);
+    }
+    line.to_string()
+}
+
+/// Lines of inline content as one paragraph; `<br>` becomes a hard line break
+fn paragraph(inline: &str) -> Option<String> {
+    let lines: Vec<String> = inline
+        .split('\n')
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(escape_line_start)
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\\\n"))
+}
+
+fn is_list(block: &str) -> bool {
+    let digits = block.len() - block.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    block.starts_with("- ") || (digits > 0 && block[digits..].starts_with(". "))
+}
+
+fn indent(text: &str, width: usize) -> String {
+    let pad = " ".repeat(width);
+    text.split('\n')
+        .enumerate()
+        .map(|(i, line)| if i == 0 || line.is_empty() { line.to_string() } else { format!("{}{}", pad, line) })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn block(element: &Element) -> Vec<String> {
+    match element.name.as_str() {
+        name if SKIPPED_ELEMENTS.contains(&name) => Vec::new(),
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = element.name[1..].parse().unwrap_or(1);
+            let text = inline(&element.children).split_whitespace().collect::<Vec<_>>().join(" ");
+            if text.is_empty() {
+                Vec::new()
+            } else {
+                vec![format!("{} {}", "#".repeat(level), text)]
+            }
+        }
+        "hr" => vec!["---".to_string()],
+        "pre" => vec![code_block(element)],
+        "blockquote" => {
+            let content = blocks(&element.children).join("\n\n");
+            if content.is_empty() {
+                return Vec::new();
+            }
+            let quoted: Vec<String> = content
+                .split('\n')
+                .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+                .collect();
+            vec![quoted.join("\n")]
+        }
+        "ul" | "ol" => {
+            let ordered = element.name == "ol";
+            let mut number: usize = element.attr("start").and_then(|s| s.trim().parse().ok()).unwrap_or(1);
+            let mut items = Vec::new();
+            for child in &element.children {
+                let Node::Element(item) = child else { continue };
+                let item_blocks = if item.name == "li" { blocks(&item.children) } else { block(item) };
+                let mut body = String::new();
+                for (i, item_block) in item_blocks.iter().enumerate() {
+                    if i > 0 {
+                        body.push_str(if is_list(item_block) { "\n" } else { "\n\n" });
+                    }
+                    body.push_str(item_block);
+                }
+                if body.is_empty() {
+                    continue;
+                }
+                let marker = if ordered { format!("{}. ", number) } else { "- ".to_string() };
+                number += 1;
+                items.push(format!("{}{}", marker, indent(&body, marker.len())));
+            }
+            if items.is_empty() { Vec::new() } else { vec![items.join("\n")] }
+        }
+        "table" => table(element).into_iter().collect(),
+        _ => blocks(&element.children),
+    }
+}
+
+fn code_block(pre: &Element) -> String {
+    let language = std::iter::once(pre)
+        .chain(pre.find(&|e| e.name == "code"))
+        .filter_map(|e| e.attr("class"))
+        .flat_map(str::split_whitespace)
+        .find_map(|class| class.strip_prefix("language-").or_else(|| class.strip_prefix("lang-")))
+        .unwrap_or("");
+    let mut code = String::new();
+    collect_text(&pre.children, &mut code);
+    let code = code.strip_prefix('\n').unwrap_or(&code).trim_end_matches('\n');
+    let mut fence = "```".to_string();
+    while code.contains(fence.as_str()) {
+        fence.push('`');
+    }
+    format!("{}{}\n{}\n{}", fence, language, code, fence)
+}
+
+fn table(element: &Element) -> Option<String> {
+    fn rows<'a>(element: &'a Element, out: &mut Vec<&'a Element>) {
+        for child in &element.children {
+            if let Node::Element(child) = child {
+                match child.name.as_str() {
+                    "tr" => out.push(child),
+                    "thead" | "tbody" | "tfoot" => rows(child, out),
+                    _ => {}
+                }
+            }
+        }
+    }
+    let mut row_elements = Vec::new();
+    rows(element, &mut row_elements);
+    let cells: Vec<Vec<String>> = row_elements
+        .iter()
+        .map(|row| {
+            row.children
+                .iter()
+                .filter_map(|cell| match cell {
+                    Node::Element(cell) if cell.name == "td" || cell.name == "th" => {
+                        let text = inline(&cell.children).split_whitespace().collect::<Vec<_>>().join(" ");
+                        Some(text.replace('|', "\\|"))
+                    }
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect();
+    let columns = cells.iter().map(Vec::len).max().filter(|&n| n > 0)?;
+    let line = |row: &[String]| {
+        let padded: Vec<&str> = (0..columns).map(|i| row.get(i).map_or("", String::as_str)).collect();
+        format!("| {} |", padded.join(" | "))
+    };
+    let mut lines = vec![line(&cells[0]), format!("|{}", " --- |".repeat(columns))];
+    lines.extend(cells[1..].iter().map(|row| line(row)));
+    Some(lines.join("\n"))
+}
+
+fn inline(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        inline_node(node, &mut out);
+    }
+    out
+}
+
+fn push_space(out: &mut String) {
+    if !out.is_empty() && !out.ends_with([' ', '\n']) {
+        out.push(' ');
+    }
+}
+
+fn push_text(text: &str, out: &mut String) {
+    for c in text.chars() {
+        if c.is_whitespace() {
+            push_space(out);
+        } else {
+            if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<') {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+    }
+}
+
+/// Link destination, in angle brackets when it contains spaces or parentheses
+fn destination(url: &str) -> String {
+    let url = url.trim();
+    if url.contains([' ', '(', ')']) {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_string()
+    }
+}
+
+/// Wrap inline content in `marker`, keeping surrounding whitespace outside
+fn emphasis(children: &[Node], marker: &str, out: &mut String) {
+    let content = inline(children);
+    let trimmed = content.trim();
+    if content.starts_with([' ', '\n']) {
+        push_space(out);
+    }
+    if !trimmed.is_empty() {
+        out.push_str(marker);
+        out.push_str(trimmed);
+        out.push_str(marker);
+    }
+    if content.ends_with([' ', '\n']) {
+        push_space(out);
+    }
+}
+
+fn inline_node(node: &Node, out: &mut String) {
+    let element = match node {
+        Node::Text(text) => return push_text(text, out),
+        Node::Element(element) => element,
+    };
+    match element.name.as_str() {
+        name if SKIPPED_ELEMENTS.contains(&name) => {}
+        "br" => {
+            let trimmed = out.trim_end_matches(' ').len();
+            out.truncate(trimmed);
+            out.push('\n');
+        }
+        "img" => {
+            let Some(src) = element.attr("src").filter(|s| !s.trim().is_empty()) else { return };
+            let alt = element.attr("alt").unwrap_or("").replace(['[', ']'], "");
+            out.push_str(&format!("![{}]({})", alt.trim(), destination(src)));
+        }
+        "iframe" => {
+            if let Some(src) = element.attr("src").filter(|s| !s.trim().is_empty()) {
+                push_space(out);
+                out.push_str(&format!("[Embedded content]({})", destination(src)));
+            }
+        }
+        "a" => {
+            let content = inline(&element.children);
+            let href = element.attr("href").unwrap_or("").trim();
+            if href.is_empty() || href.starts_with('#') || href.to_ascii_lowercase().starts_with("javascript:") {
+                out.push_str(&content);
+                return;
+            }
+            let text = content.trim();
+            if content.starts_with([' ', '\n']) {
+                push_space(out);
+            }
+            if !text.is_empty() {
+                out.push_str(&format!("[{}]({})", text.replace('\n', " "), destination(href)));
+            }
+            if content.ends_with([' ', '\n']) {
+                push_space(out);
+            }
+        }
+        "strong" | "b" => emphasis(&element.children, "**", out),
+        "em" | "i" | "cite" => emphasis(&element.children, "*", out),
+        "del" | "s" | "strike" => emphasis(&element.children, "~~", out),
+        "code" | "kbd" => {
+            let code = element.text();
+            if code.is_empty() {
+                return;
+            }
+            let ticks = if code.contains('`') { "``" } else { "`" };
+            let padding = if code.starts_with('`') || code.ends_with('`') { " " } else { "" };
+            out.push_str(&format!("{}{}{}{}{}", ticks, padding, code, padding, ticks));
+        }
+        _ => {
+            for child in &element.children {
+                inline_node(child, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline() {
+        assert_eq!(
+            to_markdown("<p>Some <strong>bold</strong>, <em> italic </em>and <a href=\"https://example.com/a b\">a link</a>.</p>"),
+            "Some **bold**, *italic* and [a link](<https://example.com/a b>)."
+        );
+        assert_eq!(to_markdown("<p>Use <code>a*b</code> &amp; not_this</p>"), "Use `a*b` & not\\_this");
+        assert_eq!(to_markdown("<p>One<br>two<br/></p>"), "One\\\ntwo");
+        assert_eq!(to_markdown("<p><img src=\"/a.png\" alt=\"A [cat]\"></p>"), "![A cat](/a.png)");
+        assert_eq!(to_markdown("<p>1. Not a list</p><p># not a heading</p>"), "1\\. Not a list\n\n\\# not a heading");
+    }
+
+    #[test]
+    fn test_blocks() {
+        let html = r#"<h2>Intro</h2>
+            <ul><li>One<li>Two<ol start="3"><li>Three</li></ol></li></ul>
+            <blockquote><p>Quoted</p><p>Twice</p></blockquote>
+            <pre><code class="language-rust">fn main() {
+    println!("&lt;hi&gt;");
+}</code></pre>
+            <hr>
+            <table><tr><th>A</th><th>B</th></tr><tr><td>1</td><td>x|y</td></tr></table>"#;
+        assert_eq!(
+            to_markdown(html),
+            "## Intro\n\n\
+             - One\n- Two\n  3. Three\n\n\
+             > Quoted\n>\n> Twice\n\n\
+             ```rust\nfn main() {\n    println!(\"<hi>\");\n}\n```\n\n\
+             ---\n\n\
+             | A | B |\n| --- | --- |\n| 1 | x\\|y |"
+        );
+    }
+
+    #[test]
+    fn test_forgiving_parse() {
+        // Unclosed paragraphs, stray end tags, comments and scripts
+        let html = "<!DOCTYPE html><p>First<p>Second</span><!-- note --><script>alert('<p>')</script><div>Block</div>";
+        assert_eq!(to_markdown(html), "First\n\nSecond\n\nBlock");
+        assert_eq!(to_markdown("a < b"), "a \\< b");
+        assert_eq!(to_markdown("<figure><iframe src=\"https://youtube.com/embed/x\"></iframe></figure>"), "[Embedded content](https://youtube.com/embed/x)");
+    }
+
+    #[test]
+    fn test_find() {
+        let document = parse("<article><h1 class='p-name title'>Hello &amp; bye</h1><time datetime=\"2020-01-02\">Jan 2</time></article>");
+        let title = document.find(&|e| e.has_class("p-name")).unwrap();
+        assert_eq!(title.text(), "Hello & bye");
+        assert_eq!(document.find(&|e| e.name == "time").and_then(|e| e.attr("datetime")), Some("2020-01-02"));
+        assert!(document.find(&|e| e.name == "section").is_none());
+    }
+}
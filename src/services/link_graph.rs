@@ -0,0 +1,117 @@
+//! Links between articles. Each article's outgoing links to other posts are
+//! recorded when it is saved; together they form the graph served at
+//! `/api/v1/graph` for content maps and related-post suggestions.
+
+use pulldown_cmark::{Event, Parser, Tag};
+use serde::Serialize;
+
+/// Article id a link destination points at: `/post/{id}`, optionally on this site's
+/// absolute URL and with a query or fragment
+fn linked_article(destination: &str, site_url: &str) -> Option<i64> {
+    let site_url = site_url.trim_end_matches('/');
+    let path = match destination.strip_prefix(site_url) {
+        Some(path) if !site_url.is_empty() => path,
+        _ => destination,
+    };
+    let rest = path.strip_prefix("/post/")?;
+    let id = rest.split(['?', '#', '/']).next()?;
+    id.parse().ok().filter(|&id| id > 0)
+}
+
+/// Ids of the articles `content` links to, in order of first appearance
+pub fn internal_links(content: &str, site_url: &str) -> Vec<i64> {
+    let mut targets = Vec::new();
+    for event in Parser::new(content) {
+        if let Event::Start(Tag::Link(_, destination, _)) = event
+            && let Some(id) = linked_article(&destination, site_url)
+            && !targets.contains(&id)
+        {
+            targets.push(id);
+        }
+    }
+    targets
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GraphNode {
+    pub id: i64,
+    pub title: String,
+    pub url: String,
+    /// Links from other articles
+    pub inbound: usize,
+    /// Links to other articles
+    pub outbound: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct GraphEdge {
+    pub source: i64,
+    pub target: i64,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct LinkGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl LinkGraph {
+    /// Graph of `articles` (id, title, url); links to articles not in the list
+    /// and links from an article to itself are dropped
+    pub fn build(articles: Vec<(i64, String, String)>, links: &[(i64, i64)]) -> Self {
+        let mut nodes: Vec<GraphNode> = articles
+            .into_iter()
+            .map(|(id, title, url)| GraphNode { id, title, url, inbound: 0, outbound: 0 })
+            .collect();
+        nodes.sort_by_key(|node| node.id);
+        let index = |id: i64| nodes.binary_search_by_key(&id, |node| node.id).ok();
+
+        let mut edges = Vec::new();
+        for &(source, target) in links {
+            if source == target {
+                continue;
+            }
+            let (Some(from), Some(to)) = (index(source), index(target)) else {
+                continue;
+            };
+            edges.push((from, to, GraphEdge { source, target }));
+        }
+        for (from, to, _) in &edges {
+            nodes[*from].outbound += 1;
+            nodes[*to].inbound += 1;
+        }
+        let edges = edges.into_iter().map(|(_, _, edge)| edge).collect();
+        Self { nodes, edges }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_internal_links() {
+        let content = "See [part one](/post/3) and [two](https://blog.example.com/post/7#setup).\n\n\
+                       Again [one](/post/3?ref=x), [ref][r], <https://blog.example.com/post/9>.\n\n\
+                       Not [external](https://other.example/post/5), [short](/s/abc), [bad](/post/x) or `[code](/post/4)`.\n\n\
+                       [r]: /post/8";
+        assert_eq!(internal_links(content, "https://blog.example.com/"), vec![3, 7, 8, 9]);
+        assert_eq!(internal_links("[a](/post/2)", ""), vec![2]);
+    }
+
+    #[test]
+    fn test_build_graph() {
+        let articles = vec![
+            (2, "Two".to_string(), "/post/2".to_string()),
+            (1, "One".to_string(), "/post/1".to_string()),
+            (3, "Three".to_string(), "/post/3".to_string()),
+        ];
+        // 4 is not published, 1 → 1 links to itself
+        let graph = LinkGraph::build(articles, &[(1, 2), (1, 3), (3, 2), (1, 4), (1, 1)]);
+        assert_eq!(graph.nodes.iter().map(|n| n.id).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(graph.edges.len(), 3);
+        assert_eq!((graph.nodes[0].outbound, graph.nodes[0].inbound), (2, 0));
+        assert_eq!((graph.nodes[1].outbound, graph.nodes[1].inbound), (0, 2));
+        assert_eq!((graph.nodes[2].outbound, graph.nodes[2].inbound), (1, 1));
+    }
+}
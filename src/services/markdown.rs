@@ -5,12 +5,15 @@ use syntect::html::ClassedHTMLGenerator;
 use syntect::util::LinesWithEndings;
 use html_escape;
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
 use std::time::{Duration, Instant};
 use std::hash::{Hash, Hasher};
 use std::collections::hash_map::DefaultHasher;
+use super::render_pipeline::{Builtin, Pipeline, PipelineError, RenderContext, RenderStage, StagePosition, Step};
 
 #[derive(Debug, thiserror::Error)]
+#[allow(clippy::enum_variant_names)]
 pub enum MarkdownError {
     #[error("Markdown parsing failed: {0}")]
     ParseError(String),
@@ -18,6 +21,22 @@ pub enum MarkdownError {
     SanitizationError(String),
     #[error("Syntax highlighting failed: {0}")]
     HighlightError(String),
+    #[error("Loading syntax definitions failed: {0}")]
+    SyntaxLoadError(String),
+    #[error("Loading highlight themes failed: {0}")]
+    ThemeLoadError(String),
+}
+
+/// Rendering features beyond the defaults, switched on per article; part of the cache key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RenderFeatures {
+    /// TeX between `$` or `$$` becomes math spans (see `super::math`)
+    pub math: bool,
+    /// Every heading gets an `id`, matching the anchors from `collect_headings`
+    pub heading_anchors: bool,
+    /// Minimal HTML for slow connections and text browsers: code is not
+    /// highlighted, images become links and raw HTML (embeds) is dropped
+    pub lite: bool,
 }
 
 // Cache entry structure
@@ -25,9 +44,46 @@ pub enum MarkdownError {
 struct CacheEntry {
     html: String,
     created_at: Instant,
+    last_accessed: Instant,
+    // Sliding expiry: pushed forward on every hit, never past created_at + max lifetime
+    expires_at: Instant,
     access_count: u64,
 }
 
+impl CacheEntry {
+    // Eviction weight; bounds memory rather than entry count
+    fn weight(&self) -> usize {
+        self.html.len()
+    }
+}
+
+// Rendered HTML keyed by content hash, with the total weight kept in step
+#[derive(Default)]
+struct RenderCache {
+    entries: HashMap<u64, CacheEntry>,
+    total_bytes: usize,
+}
+
+impl RenderCache {
+    fn insert(&mut self, key: u64, entry: CacheEntry) {
+        self.total_bytes += entry.weight();
+        if let Some(old) = self.entries.insert(key, entry) {
+            self.total_bytes -= old.weight();
+        }
+    }
+
+    fn remove(&mut self, key: &u64) -> Option<CacheEntry> {
+        let entry = self.entries.remove(key)?;
+        self.total_bytes -= entry.weight();
+        Some(entry)
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.total_bytes = 0;
+    }
+}
+
 // Performance metrics
 #[derive(Debug, Clone)]
 pub struct PerformanceMetrics {
@@ -37,44 +93,202 @@ pub struct PerformanceMetrics {
     pub avg_render_time_ms: f64,
     pub cache_size: usize,
     pub memory_usage_bytes: usize,
+    /// Renders currently waiting for or running on the blocking pool
+    pub offload_queue_depth: usize,
+    pub offloaded_renders: u64,
+    /// Cache misses served by waiting for an identical in-flight render
+    pub coalesced_renders: u64,
+    /// Entries removed to stay within the entry/byte limits
+    pub evictions: u64,
+    /// Entries dropped because their TTL or maximum lifetime ran out
+    pub expirations: u64,
+    /// Code blocks cut at the configured size limit
+    pub truncated_code_blocks: u64,
 }
 
 pub struct MarkdownService {
     syntax_set: SyntaxSet,
-    #[allow(dead_code)] // Reserved for future theme customization
+    // Themes articles can pick for their code blocks (front matter `highlight_theme`)
     theme_set: ThemeSet,
     options: Options,
     // HTML rendering cache with TTL and LRU eviction
-    html_cache: Arc<RwLock<HashMap<u64, CacheEntry>>>,
+    html_cache: Arc<RwLock<RenderCache>>,
     // Performance metrics
     metrics: Arc<RwLock<PerformanceMetrics>>,
     // Cache configuration
     cache_ttl: Duration,
     max_cache_size: usize,
+    // Upper bound on the total size of cached HTML (bytes)
+    max_cache_bytes: usize,
+    // Hot entries keep sliding their TTL forward, but are re-rendered after this long
+    max_entry_lifetime: Duration,
     max_content_size: usize, // Maximum content size to cache (bytes)
+    // Reusable markdown fragments referenced as {{snippet:name}}
+    snippets: Arc<RwLock<HashMap<String, String>>>,
+    // Uncached documents at least this large are rendered off the async workers (0 = never)
+    offload_threshold: usize,
+    offload_queue_depth: Arc<AtomicUsize>,
+    offloaded_renders: Arc<AtomicU64>,
+    // Renders currently in progress, keyed like the cache
+    in_flight: Mutex<HashMap<u64, Arc<Flight>>>,
+    // Code blocks are cut after this many bytes and marked as truncated (0 = no limit)
+    max_code_block_size: usize,
+    // Lowercase fence language -> syntect syntax name or extension
+    language_aliases: HashMap<String, String>,
+    // Stages every render goes through, built-in and registered
+    pipeline: Pipeline,
+}
+
+/// Common fence languages syntect does not recognize on its own
+pub const DEFAULT_LANGUAGE_ALIASES: &[(&str, &str)] = &[
+    ("rs", "Rust"),
+    ("js", "JavaScript"),
+    ("javascript", "JavaScript"),
+    ("node", "JavaScript"),
+    ("shell", "Bourne Again Shell (bash)"),
+    ("sh", "Bourne Again Shell (bash)"),
+    ("bash", "Bourne Again Shell (bash)"),
+    ("zsh", "Bourne Again Shell (bash)"),
+    ("yml", "YAML"),
+    ("yaml", "YAML"),
+    ("py", "Python"),
+    ("python3", "Python"),
+    ("rb", "Ruby"),
+    ("golang", "Go"),
+    ("c++", "C++"),
+    ("cs", "C#"),
+    ("csharp", "C#"),
+    ("md", "Markdown"),
+    ("htm", "HTML"),
+    ("sql", "SQL"),
+];
+
+/// Parse `alias=Language` pairs separated by commas, e.g. `tf=Terraform,jsx=JavaScript`
+pub fn parse_language_aliases(spec: &str) -> HashMap<String, String> {
+    spec.split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(alias, language)| (alias.trim().to_lowercase(), language.trim().to_string()))
+        .filter(|(alias, language)| !alias.is_empty() && !language.is_empty())
+        .collect()
+}
+
+/// Default for `MarkdownService::with_max_code_block_size`
+pub const DEFAULT_MAX_CODE_BLOCK_SIZE: usize = 100_000;
+
+/// Longest prefix of `text` that is at most `max` bytes and ends on a character boundary
+fn floor_char_boundary(text: &str, max: usize) -> &str {
+    if max >= text.len() {
+        return text;
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+// Result of one in-flight render, shared with requests waiting on it
+#[derive(Default)]
+struct Flight {
+    result: Mutex<Option<Result<String, String>>>,
+    done: Condvar,
+}
+
+impl Flight {
+    fn wait(&self) -> Result<String, String> {
+        let mut result = self.result.lock().unwrap();
+        while result.is_none() {
+            result = self.done.wait(result).unwrap();
+        }
+        result.clone().unwrap()
+    }
+}
+
+enum FlightRole<'a> {
+    Leader(FlightGuard<'a>),
+    Follower(Arc<Flight>),
+}
+
+// Held by the rendering request; publishes the result (or an error if the
+// render panicked) and wakes the waiters
+struct FlightGuard<'a> {
+    in_flight: &'a Mutex<HashMap<u64, Arc<Flight>>>,
+    key: u64,
+    flight: Arc<Flight>,
+    completed: bool,
+}
+
+impl FlightGuard<'_> {
+    fn complete(&mut self, result: &Result<String, MarkdownError>) {
+        self.publish(match result {
+            Ok(html) => Ok(html.clone()),
+            Err(e) => Err(e.to_string()),
+        });
+    }
+
+    fn publish(&mut self, result: Result<String, String>) {
+        self.in_flight.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.key);
+        *self.flight.result.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+        self.flight.done.notify_all();
+        self.completed = true;
+    }
+}
+
+impl Drop for FlightGuard<'_> {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.publish(Err("markdown render aborted".to_string()));
+        }
+    }
+}
+
+/// Counts a render in the offload queue until dropped
+pub struct OffloadGuard {
+    queue_depth: Arc<AtomicUsize>,
+}
+
+impl Drop for OffloadGuard {
+    fn drop(&mut self) {
+        self.queue_depth.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
+// Nested snippets deeper than this are left unexpanded
+const MAX_SNIPPET_DEPTH: usize = 5;
+
 impl MarkdownService {
     pub fn new() -> Self {
         Self::with_cache_config(
             Duration::from_secs(3600), // 1 hour TTL
             1000,                      // Max 1000 cached entries
             1024 * 1024,              // Max 1MB content size to cache
+            64 * 1024 * 1024,         // Max 64MB of cached HTML
+            Duration::from_secs(24 * 3600), // Re-render hot entries at least daily
         )
     }
 
-    pub fn with_cache_config(cache_ttl: Duration, max_cache_size: usize, max_content_size: usize) -> Self {
+    /// `cache_ttl` is an idle timeout: every hit extends an entry's expiry by
+    /// `cache_ttl`, up to `max_entry_lifetime` after it was rendered. The cache
+    /// holds at most `max_cache_size` entries and `max_cache_bytes` of HTML.
+    pub fn with_cache_config(
+        cache_ttl: Duration,
+        max_cache_size: usize,
+        max_content_size: usize,
+        max_cache_bytes: usize,
+        max_entry_lifetime: Duration,
+    ) -> Self {
         let mut options = Options::empty();
         options.insert(Options::ENABLE_STRIKETHROUGH);
         options.insert(Options::ENABLE_TABLES);
         options.insert(Options::ENABLE_FOOTNOTES);
         options.insert(Options::ENABLE_TASKLISTS);
+        options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
         
         Self {
             syntax_set: SyntaxSet::load_defaults_newlines(),
             theme_set: ThemeSet::load_defaults(),
             options,
-            html_cache: Arc::new(RwLock::new(HashMap::new())),
+            html_cache: Arc::new(RwLock::new(RenderCache::default())),
             metrics: Arc::new(RwLock::new(PerformanceMetrics {
                 cache_hits: 0,
                 cache_misses: 0,
@@ -82,16 +296,273 @@ impl MarkdownService {
                 avg_render_time_ms: 0.0,
                 cache_size: 0,
                 memory_usage_bytes: 0,
+                offload_queue_depth: 0,
+                offloaded_renders: 0,
+                coalesced_renders: 0,
+                evictions: 0,
+                expirations: 0,
+                truncated_code_blocks: 0,
             })),
             cache_ttl,
             max_cache_size,
+            max_cache_bytes,
+            max_entry_lifetime,
             max_content_size,
+            snippets: Arc::new(RwLock::new(HashMap::new())),
+            offload_threshold: 0,
+            offload_queue_depth: Arc::new(AtomicUsize::new(0)),
+            offloaded_renders: Arc::new(AtomicU64::new(0)),
+            in_flight: Mutex::new(HashMap::new()),
+            max_code_block_size: DEFAULT_MAX_CODE_BLOCK_SIZE,
+            language_aliases: DEFAULT_LANGUAGE_ALIASES
+                .iter()
+                .map(|(alias, language)| (alias.to_string(), language.to_string()))
+                .collect(),
+            pipeline: Pipeline::new(),
+        }
+    }
+
+    /// Render uncached documents of at least `bytes` on a blocking thread; 0 disables offloading
+    pub fn with_offload_threshold(mut self, bytes: usize) -> Self {
+        self.offload_threshold = bytes;
+        self
+    }
+
+    /// Cut code blocks after `bytes`, followed by a visible truncation notice; 0 disables the limit
+    pub fn with_max_code_block_size(mut self, bytes: usize) -> Self {
+        self.max_code_block_size = bytes;
+        self
+    }
+
+    pub fn max_code_block_size(&self) -> usize {
+        self.max_code_block_size
+    }
+
+    /// Add or override fence language aliases on top of `DEFAULT_LANGUAGE_ALIASES`
+    pub fn with_language_aliases(mut self, aliases: HashMap<String, String>) -> Self {
+        self.language_aliases
+            .extend(aliases.into_iter().map(|(alias, language)| (alias.to_lowercase(), language)));
+        self
+    }
+
+    /// Add a stage to every render from now on, e.g. shortcodes before `sanitize`
+    pub fn register_stage(&mut self, position: StagePosition, stage: Arc<dyn RenderStage>) -> Result<(), PipelineError> {
+        self.pipeline.register(position, stage)?;
+        // 已缓存的结果没有经过新阶段
+        self.clear_cache();
+        Ok(())
+    }
+
+    /// Names of the stages a render goes through, in order
+    pub fn stage_names(&self) -> Vec<&str> {
+        self.pipeline.names()
+    }
+
+    /// Add the `.sublime-syntax` definitions found under `dir` (recursively) to the
+    /// built-in ones; returns how many were added. On error the current set is kept.
+    pub fn load_extra_syntaxes(&mut self, dir: impl AsRef<std::path::Path>) -> Result<usize, MarkdownError> {
+        let before = self.syntax_set.syntaxes().len();
+        let mut builder = self.syntax_set.clone().into_builder();
+        builder
+            .add_from_folder(dir, true)
+            .map_err(|e| MarkdownError::SyntaxLoadError(e.to_string()))?;
+        self.syntax_set = builder.build();
+        Ok(self.syntax_set.syntaxes().len().saturating_sub(before))
+    }
+
+    /// Add the `.tmTheme` files found under `dir` (recursively) to the built-in
+    /// highlight themes; returns how many were added. On error the current set is kept.
+    pub fn load_extra_themes(&mut self, dir: impl AsRef<std::path::Path>) -> Result<usize, MarkdownError> {
+        let before = self.theme_set.themes.len();
+        let extra = ThemeSet::load_from_folder(dir).map_err(|e| MarkdownError::ThemeLoadError(e.to_string()))?;
+        self.theme_set.themes.extend(extra.themes);
+        Ok(self.theme_set.themes.len().saturating_sub(before))
+    }
+
+    pub fn has_theme(&self, name: &str) -> bool {
+        self.theme_set.themes.contains_key(name)
+    }
+
+    pub fn theme_names(&self) -> Vec<&str> {
+        self.theme_set.themes.keys().map(String::as_str).collect()
+    }
+
+    /// Stylesheet for a highlight theme, scoped to `.highlight` so it overrides
+    /// the site's default code colours
+    pub fn theme_css(&self, name: &str) -> Option<String> {
+        let theme = self.theme_set.themes.get(name)?;
+        let css = syntect::html::css_for_theme_with_class_style(theme, syntect::html::ClassStyle::Spaced).ok()?;
+        let mut scoped = String::with_capacity(css.len() * 2);
+        for line in css.lines() {
+            match line.strip_suffix(" {") {
+                Some(".code") => scoped.push_str("pre.highlight {"),
+                Some(selectors) => {
+                    let selectors: Vec<String> = selectors.split(", ").map(|s| format!(".highlight {}", s)).collect();
+                    scoped.push_str(&selectors.join(", "));
+                    scoped.push_str(" {");
+                }
+                None => scoped.push_str(line),
+            }
+            scoped.push('\n');
+        }
+        Some(scoped)
+    }
+
+    /// Syntax for a fence language: exact extension or name, then the alias table,
+    /// then a case-insensitive match; plain text if nothing fits
+    fn find_syntax(&self, language: &str) -> &syntect::parsing::SyntaxReference {
+        let lookup = |token: &str| {
+            self.syntax_set
+                .find_syntax_by_extension(token)
+                .or_else(|| self.syntax_set.find_syntax_by_name(token))
+        };
+        lookup(language)
+            .or_else(|| {
+                self.language_aliases
+                    .get(&language.to_lowercase())
+                    .and_then(|target| lookup(target))
+            })
+            .or_else(|| self.syntax_set.find_syntax_by_token(language))
+            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
+    }
+
+    /// Whether rendering `markdown` is heavy enough to move off the async workers.
+    /// Cache hits are cheap, so they never are.
+    pub fn should_offload(&self, markdown: &str) -> bool {
+        self.should_offload_with(markdown, RenderFeatures::default())
+    }
+
+    /// `should_offload` for a render with `features`
+    pub fn should_offload_with(&self, markdown: &str, features: RenderFeatures) -> bool {
+        if self.offload_threshold == 0 || markdown.len() < self.offload_threshold {
+            return false;
+        }
+        let expanded = self.expand_snippets(markdown);
+        if !self.should_cache_content(&expanded) {
+            return true;
+        }
+        let key = self.generate_cache_key(&expanded, features);
+        match self.html_cache.read() {
+            Ok(cache) => cache
+                .entries
+                .get(&key)
+                .is_none_or(|entry| Instant::now() > entry.expires_at),
+            Err(_) => true,
+        }
+    }
+
+    /// Register an offloaded render; hold the guard until the render finishes
+    pub fn begin_offload(&self) -> OffloadGuard {
+        self.offload_queue_depth.fetch_add(1, Ordering::Relaxed);
+        self.offloaded_renders.fetch_add(1, Ordering::Relaxed);
+        OffloadGuard { queue_depth: self.offload_queue_depth.clone() }
+    }
+
+    /// Replace the snippet set used by `{{snippet:name}}` references
+    pub fn set_snippets(&self, snippets: HashMap<String, String>) {
+        log::info!("Loaded {} content snippet(s)", snippets.len());
+        *self.snippets.write().unwrap() = snippets;
+    }
+
+    /// Expand `{{snippet:name}}` references; unknown, cyclic or too deeply nested
+    /// references are left as written. A leading backslash escapes a reference.
+    pub fn expand_snippets(&self, markdown: &str) -> String {
+        let snippets = self.snippets.read().unwrap();
+        if snippets.is_empty() || !markdown.contains("{{snippet:") {
+            return markdown.to_string();
         }
+        expand_snippet_refs(markdown, &snippets, &mut Vec::new())
+    }
+
+    /// Heading-collection pass: headings in document order with unique anchors
+    pub fn collect_headings(&self, markdown: &str) -> Vec<super::toc::Heading> {
+        super::toc::collect_headings(&self.expand_snippets(markdown), self.options)
+    }
+
+    /// Link, image and heading checks run before publishing, plus code blocks
+    /// that will be truncated when rendered
+    pub fn validate(&self, markdown: &str, site_url: &str) -> super::validation::Findings {
+        let mut findings = super::validation::check(markdown, self.options, site_url);
+        findings.issues.extend(super::validation::oversized_code_blocks(markdown, self.options, self.max_code_block_size));
+        findings
+    }
+
+    /// Plain text of the first paragraph with any text in it, whitespace collapsed.
+    /// Used as the excerpt when the author has not written a description.
+    pub fn first_paragraph(&self, markdown: &str) -> Option<String> {
+        let markdown = self.expand_snippets(markdown);
+        let mut text: Option<String> = None;
+        // Paragraphs inside lists, quotes and tables are not the lead paragraph
+        let mut depth = 0usize;
+        for event in Parser::new_ext(&markdown, self.options) {
+            match event {
+                Event::Start(Tag::Paragraph) if depth == 0 => text = Some(String::new()),
+                Event::End(Tag::Paragraph) if depth == 0 => {
+                    let paragraph = text.take().unwrap_or_default();
+                    let paragraph = paragraph.split_whitespace().collect::<Vec<_>>().join(" ");
+                    if !paragraph.is_empty() {
+                        return Some(paragraph);
+                    }
+                }
+                Event::Start(Tag::List(_) | Tag::BlockQuote | Tag::Table(_) | Tag::FootnoteDefinition(_)) => depth += 1,
+                Event::End(Tag::List(_) | Tag::BlockQuote | Tag::Table(_) | Tag::FootnoteDefinition(_)) => {
+                    depth = depth.saturating_sub(1)
+                }
+                // Image alt text is not part of the prose
+                Event::Start(Tag::Image(..)) => depth += 1,
+                Event::End(Tag::Image(..)) => depth = depth.saturating_sub(1),
+                Event::Text(t) | Event::Code(t) => {
+                    if let Some(buffer) = text.as_mut().filter(|_| depth == 0) {
+                        buffer.push_str(&t);
+                    }
+                }
+                Event::SoftBreak | Event::HardBreak => {
+                    if let Some(buffer) = text.as_mut() {
+                        buffer.push(' ');
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Task list markers of an article's source, indexed like the rendered
+    /// checkboxes. Fails when snippets add tasks, since those indices would not
+    /// line up with the source.
+    pub fn task_markers(&self, markdown: &str) -> Result<Vec<super::tasks::TaskMarker>, super::tasks::TaskError> {
+        let markers = super::tasks::task_markers(markdown, self.options);
+        let expanded = self.expand_snippets(markdown);
+        if expanded != markdown && super::tasks::task_markers(&expanded, self.options).len() != markers.len() {
+            return Err(super::tasks::TaskError::FromSnippet);
+        }
+        Ok(markers)
+    }
+
+    /// Check or uncheck the task at a rendered checkbox index; `None` toggles it.
+    /// Returns the updated markdown and the new state.
+    pub fn set_task(&self, markdown: &str, index: usize, checked: Option<bool>) -> Result<(String, bool), super::tasks::TaskError> {
+        let markers = self.task_markers(markdown)?;
+        let marker = markers.get(index).ok_or(super::tasks::TaskError::NotFound(index))?;
+        let checked = checked.unwrap_or(!marker.checked);
+        Ok((super::tasks::set_task(markdown, marker, checked), checked))
+    }
+
+    /// URL of the first image in the document, used as the OpenGraph image
+    pub fn first_image(&self, markdown: &str) -> Option<String> {
+        let markdown = self.expand_snippets(markdown);
+        Parser::new_ext(&markdown, self.options).find_map(|event| match event {
+            Event::Start(Tag::Image(_, dest, _)) if !dest.trim().is_empty() => Some(dest.trim().to_string()),
+            _ => None,
+        })
     }
 
     /// Get performance metrics
     pub fn get_metrics(&self) -> PerformanceMetrics {
-        self.metrics.read().unwrap().clone()
+        let mut metrics = self.metrics.read().unwrap().clone();
+        metrics.offload_queue_depth = self.offload_queue_depth.load(Ordering::Relaxed);
+        metrics.offloaded_renders = self.offloaded_renders.load(Ordering::Relaxed);
+        metrics
     }
 
     /// Clear cache and reset metrics
@@ -106,10 +577,11 @@ impl MarkdownService {
         log::info!("Markdown cache cleared");
     }
 
-    /// Generate cache key from markdown content
-    fn generate_cache_key(&self, markdown: &str) -> u64 {
+    /// Generate cache key from markdown content and the features it is rendered with
+    fn generate_cache_key(&self, markdown: &str, features: RenderFeatures) -> u64 {
         let mut hasher = DefaultHasher::new();
         markdown.hash(&mut hasher);
+        features.hash(&mut hasher);
         hasher.finish()
     }
 
@@ -118,48 +590,67 @@ impl MarkdownService {
         content.len() <= self.max_content_size
     }
 
-    /// Evict expired and least recently used cache entries
-    fn evict_cache_entries(&self) {
-        let mut cache = self.html_cache.write().unwrap();
-        let now = Instant::now();
-        
-        // Remove expired entries
+    /// Drop expired entries, then evict the least used (oldest access first on
+    /// ties) until `incoming_bytes` more fit within both limits. Returns the number
+    /// of entries (expired, evicted).
+    fn make_room(&self, cache: &mut RenderCache, incoming_bytes: usize, now: Instant) -> (u64, u64) {
         let expired_keys: Vec<u64> = cache
+            .entries
             .iter()
-            .filter(|(_, entry)| now.duration_since(entry.created_at) > self.cache_ttl)
+            .filter(|(_, entry)| now > entry.expires_at)
             .map(|(key, _)| *key)
             .collect();
-        
-        for key in expired_keys {
-            cache.remove(&key);
+        for key in &expired_keys {
+            cache.remove(key);
         }
-        
-        // If still over limit, remove least recently used entries
-        if cache.len() > self.max_cache_size {
-            let mut entries: Vec<(u64, u64)> = cache
+
+        let incoming_entries = usize::from(incoming_bytes > 0);
+        let over_limit = |cache: &RenderCache| {
+            cache.entries.len() + incoming_entries > self.max_cache_size
+                || cache.total_bytes + incoming_bytes > self.max_cache_bytes
+        };
+        let mut evicted = 0;
+        if over_limit(cache) {
+            let mut candidates: Vec<(u64, u64, Instant)> = cache
+                .entries
                 .iter()
-                .map(|(key, entry)| (*key, entry.access_count))
+                .map(|(key, entry)| (*key, entry.access_count, entry.last_accessed))
                 .collect();
-            
-            // Sort by access count (ascending) to remove least used first
-            entries.sort_by_key(|(_, access_count)| *access_count);
-            
-            let remove_count = cache.len() - self.max_cache_size;
-            for (key, _) in entries.iter().take(remove_count) {
-                cache.remove(key);
+            candidates.sort_by_key(|(_, access_count, last_accessed)| (*access_count, *last_accessed));
+            for (key, _, _) in candidates {
+                if !over_limit(cache) {
+                    break;
+                }
+                cache.remove(&key);
+                evicted += 1;
             }
         }
-        
-        // Update metrics
-        let mut metrics = self.metrics.write().unwrap();
-        metrics.cache_size = cache.len();
-        metrics.memory_usage_bytes = cache
-            .values()
-            .map(|entry| entry.html.len())
-            .sum();
+        (expired_keys.len() as u64, evicted)
+    }
+
+    /// Refresh the cache gauges and add to the eviction counters
+    fn record_cache_stats(&self, cache: &RenderCache, expired: u64, evicted: u64) {
+        if let Ok(mut metrics) = self.metrics.write() {
+            metrics.cache_size = cache.entries.len();
+            metrics.memory_usage_bytes = cache.total_bytes;
+            metrics.expirations += expired;
+            metrics.evictions += evicted;
+        }
+    }
+
+    /// Evict expired entries and trim the cache back within its limits
+    fn evict_cache_entries(&self) {
+        let mut cache = self.html_cache.write().unwrap();
+        let (expired, evicted) = self.make_room(&mut cache, 0, Instant::now());
+        self.record_cache_stats(&cache, expired, evicted);
     }
 
     pub fn render_to_html(&self, markdown: &str) -> Result<String, MarkdownError> {
+        self.render_with_features(markdown, RenderFeatures::default())
+    }
+
+    /// Render with optional features, e.g. those an article turns on in its front matter
+    pub fn render_with_features(&self, markdown: &str, features: RenderFeatures) -> Result<String, MarkdownError> {
         let start_time = Instant::now();
         
         // Validate input
@@ -167,72 +658,114 @@ impl MarkdownService {
             return Ok(String::new());
         }
 
-        // Check cache first if content is cacheable
-        let cache_key = if self.should_cache_content(markdown) {
-            Some(self.generate_cache_key(markdown))
-        } else {
-            None
-        };
+        // 先展开片段，缓存键基于展开后的内容，片段修改后自动失效
+        let expanded = self.expand_snippets(markdown);
+        let markdown = expanded.as_str();
 
-        if let Some(key) = cache_key {
-            // Try to get from cache
-            if let Ok(mut cache) = self.html_cache.write() {
-                if let Some(entry) = cache.get_mut(&key) {
-                    // Check if entry is still valid
-                    if start_time.duration_since(entry.created_at) <= self.cache_ttl {
-                        // Cache hit - update access count and return cached result
-                        entry.access_count += 1;
-                        
-                        // Update metrics
-                        if let Ok(mut metrics) = self.metrics.write() {
-                            metrics.cache_hits += 1;
-                        }
-                        
-                        log::debug!("Markdown cache hit for content hash: {}", key);
-                        return Ok(entry.html.clone());
-                    } else {
-                        // Entry expired, remove it
-                        cache.remove(&key);
-                    }
+        // Non-cacheable content is always rendered directly
+        if !self.should_cache_content(markdown) {
+            return self.render_uncached(markdown, features, start_time, None);
+        }
+        let key = self.generate_cache_key(markdown, features);
+
+        // Try to get from cache
+        if let Some(html) = self.cached_html(key, start_time) {
+            return Ok(html);
+        }
+
+        // Single-flight: the first miss renders, concurrent misses for the same
+        // content wait for its result instead of rendering it again
+        let mut flight = match self.join_flight(key) {
+            FlightRole::Leader(flight) => flight,
+            FlightRole::Follower(flight) => {
+                if let Ok(mut metrics) = self.metrics.write() {
+                    metrics.coalesced_renders += 1;
                 }
+                log::debug!("Waiting for in-flight render of content hash: {}", key);
+                return flight.wait().map_err(MarkdownError::ParseError);
             }
+        };
+
+        // The previous leader may have filled the cache between our lookup and joining
+        let result = match self.cached_html(key, start_time) {
+            Some(html) => Ok(html),
+            None => self.render_uncached(markdown, features, start_time, Some(key)),
+        };
+        flight.complete(&result);
+        result
+    }
+
+    /// Look up a live cache entry, counting the hit
+    fn cached_html(&self, key: u64, now: Instant) -> Option<String> {
+        let mut cache = self.html_cache.write().ok()?;
+        let entry = cache.entries.get_mut(&key)?;
+
+        // Check if entry is still valid
+        if now > entry.expires_at {
+            // Entry expired, remove it
+            cache.remove(&key);
+            self.record_cache_stats(&cache, 1, 0);
+            return None;
         }
 
+        // Cache hit - update access count, slide the expiry and return cached result
+        entry.access_count += 1;
+        entry.last_accessed = now;
+        entry.expires_at = (now + self.cache_ttl).min(entry.created_at + self.max_entry_lifetime);
+        let html = entry.html.clone();
+        drop(cache);
+
+        // Update metrics
+        if let Ok(mut metrics) = self.metrics.write() {
+            metrics.cache_hits += 1;
+        }
+
+        log::debug!("Markdown cache hit for content hash: {}", key);
+        Some(html)
+    }
+
+    /// Become the leader for `key`, or get the flight already rendering it
+    fn join_flight(&self, key: u64) -> FlightRole<'_> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if let Some(flight) = in_flight.get(&key) {
+            return FlightRole::Follower(flight.clone());
+        }
+        let flight = Arc::new(Flight::default());
+        in_flight.insert(key, flight.clone());
+        FlightRole::Leader(FlightGuard { in_flight: &self.in_flight, key, flight, completed: false })
+    }
+
+    /// Render and (when `cache_key` is set) cache the result
+    fn render_uncached(&self, markdown: &str, features: RenderFeatures, start_time: Instant, cache_key: Option<u64>) -> Result<String, MarkdownError> {
         // Cache miss or non-cacheable content - render markdown
         log::debug!("Rendering markdown content (size: {} bytes)", markdown.len());
-        
-        // Parse markdown with custom event processing for code highlighting
-        let parser = Parser::new_ext(markdown, self.options);
-        let events = self.process_events(parser)
-            .map_err(|e| MarkdownError::ParseError(format!("Event processing failed: {}", e)))?;
-        
-        // Convert processed events to HTML
-        let mut html_output = String::new();
-        html::push_html(&mut html_output, events.into_iter());
-        
-        // Sanitize HTML to prevent XSS
-        let sanitized_html = self.sanitize_html(&html_output)
-            .map_err(|e| MarkdownError::SanitizationError(format!("HTML sanitization failed: {}", e)))?;
+
+        let sanitized_html = self.run_pipeline(markdown, features)?;
         
         let render_time = start_time.elapsed();
         
-        // Cache the result if applicable
-        if let Some(key) = cache_key {
-            if let Ok(mut cache) = self.html_cache.write() {
-                // Evict old entries if needed
-                if cache.len() >= self.max_cache_size {
-                    self.evict_cache_entries();
-                }
-                
-                // Add new entry to cache
-                cache.insert(key, CacheEntry {
-                    html: sanitized_html.clone(),
-                    created_at: start_time,
-                    access_count: 1,
-                });
-                
-                log::debug!("Cached markdown result for content hash: {}", key);
-            }
+        // Cache the result if applicable; entries heavier than the whole budget are skipped
+        if let Some(key) = cache_key
+            && sanitized_html.len() <= self.max_cache_bytes
+            && self.max_cache_size > 0
+            && let Ok(mut cache) = self.html_cache.write()
+        {
+            let now = Instant::now();
+            cache.remove(&key);
+            // Evict old entries if needed
+            let (expired, evicted) = self.make_room(&mut cache, sanitized_html.len().max(1), now);
+            
+            // Add new entry to cache
+            cache.insert(key, CacheEntry {
+                html: sanitized_html.clone(),
+                created_at: now,
+                last_accessed: now,
+                expires_at: (now + self.cache_ttl).min(now + self.max_entry_lifetime),
+                access_count: 1,
+            });
+            self.record_cache_stats(&cache, expired, evicted);
+            
+            log::debug!("Cached markdown result for content hash: {}", key);
         }
         
         // Update performance metrics
@@ -249,14 +782,6 @@ impl MarkdownService {
                 metrics.avg_render_time_ms = 0.9 * metrics.avg_render_time_ms + 0.1 * render_time_ms;
             }
             
-            // Update cache size
-            if let Ok(cache) = self.html_cache.read() {
-                metrics.cache_size = cache.len();
-                metrics.memory_usage_bytes = cache
-                    .values()
-                    .map(|entry| entry.html.len())
-                    .sum();
-            }
         }
         
         log::debug!("Markdown rendering completed in {:.2}ms", render_time.as_millis());
@@ -264,9 +789,63 @@ impl MarkdownService {
         Ok(sanitized_html)
     }
 
+    /// Take the document through every stage of the pipeline (see `super::render_pipeline`)
+    fn run_pipeline(&self, markdown: &str, features: RenderFeatures) -> Result<String, MarkdownError> {
+        let steps = self.pipeline.steps();
+        let stages = || {
+            steps.iter().filter(|step| match step {
+                Step::Stage(stage) => stage.enabled(features),
+                Step::Builtin(_) => true,
+            })
+        };
+        let mut context = RenderContext::new(features, self.options);
+
+        // Anchors come from the text as `parse` sees it, e.g. before formulas are taken out
+        let mut markdown = markdown.to_string();
+        let mut anchors = Vec::new();
+        for step in stages() {
+            match step {
+                Step::Builtin(Builtin::Parse) if features.heading_anchors => {
+                    anchors = super::toc::collect_headings(&markdown, self.options);
+                }
+                Step::Stage(stage) => markdown = stage.markdown(markdown, &mut context),
+                Step::Builtin(_) => {}
+            }
+        }
+
+        let mut events: Vec<Event> = Parser::new_ext(&markdown, self.options).collect();
+        for step in stages() {
+            match step {
+                Step::Builtin(Builtin::Highlight) => {
+                    events = self.process_events(events, &anchors, features.lite)
+                        .map_err(|e| MarkdownError::ParseError(format!("Event processing failed: {}", e)))?;
+                }
+                Step::Stage(stage) => events = stage.events(events, &mut context),
+                Step::Builtin(_) => {}
+            }
+        }
+
+        let mut html_output = String::new();
+        html::push_html(&mut html_output, events.into_iter());
+        for step in stages() {
+            match step {
+                Step::Builtin(Builtin::Sanitize) => html_output = self.clean_html(&html_output),
+                Step::Builtin(Builtin::Enhance) => html_output = self.enhance_html_security_and_accessibility(&html_output),
+                Step::Stage(stage) => html_output = stage.html(html_output, &mut context),
+                Step::Builtin(_) => {}
+            }
+        }
+        Ok(html_output)
+    }
+
     /// Render markdown with fallback to original content on error
     pub fn render_to_html_with_fallback(&self, markdown: &str) -> String {
-        match self.render_to_html(markdown) {
+        self.render_with_features_fallback(markdown, RenderFeatures::default())
+    }
+
+    /// `render_with_features` with fallback to the escaped original content on error
+    pub fn render_with_features_fallback(&self, markdown: &str, features: RenderFeatures) -> String {
+        match self.render_with_features(markdown, features) {
             Ok(html) => html,
             Err(e) => {
                 log::warn!("Markdown rendering failed, falling back to escaped original content: {}", e);
@@ -276,31 +855,46 @@ impl MarkdownService {
         }
     }
 
-    fn process_events<'a>(&self, parser: Parser<'a, 'a>) -> Result<Vec<Event<'a>>, MarkdownError> {
+    /// `anchors` (from `collect_headings`, in document order) give headings without
+    /// an explicit id one; pass none to leave them without. `lite` is `RenderFeatures::lite`.
+    fn process_events<'a>(&self, parsed: Vec<Event<'a>>, anchors: &'a [super::toc::Heading], lite: bool) -> Result<Vec<Event<'a>>, MarkdownError> {
         // Pre-allocate with reasonable capacity to reduce reallocations
         let mut events = Vec::with_capacity(256);
         let mut in_code_block = false;
         let mut code_block_lang = String::new();
         let mut code_block_content = String::new();
+        // Bytes of the current code block beyond the size limit
+        let mut code_block_dropped = 0usize;
+        let mut heading_index = 0usize;
+        // Lite mode: whether the image being turned into a link has alt text yet
+        let mut image_has_text = true;
         
         // Reserve capacity for code block content to reduce reallocations
         code_block_content.reserve(1024);
 
-        for event in parser {
+        for event in parsed {
             match event {
                 Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) => {
                     in_code_block = true;
                     code_block_lang = lang.to_string();
                     code_block_content.clear();
+                    code_block_dropped = 0;
                 }
                 Event::Start(Tag::CodeBlock(CodeBlockKind::Indented)) => {
                     in_code_block = true;
                     code_block_lang = String::new(); // No language for indented code blocks
                     code_block_content.clear();
+                    code_block_dropped = 0;
                 }
                 Event::End(Tag::CodeBlock(CodeBlockKind::Fenced(_))) | 
                 Event::End(Tag::CodeBlock(CodeBlockKind::Indented)) => {
-                    if in_code_block {
+                    if in_code_block && lite {
+                        events.push(Event::Html(format!(
+                            "<pre><code>{}</code></pre>",
+                            html_escape::encode_text(&code_block_content)
+                        ).into()));
+                        in_code_block = false;
+                    } else if in_code_block {
                         // Generate syntax highlighted HTML with fallback
                         let highlighted = self.highlight_code_with_fallback(&code_block_content, &code_block_lang);
                         
@@ -321,22 +915,60 @@ impl MarkdownService {
                             "<pre class=\"{}\"><code class=\"{}\">{}</code></pre>",
                             class_attr, code_class, highlighted
                         ).into()));
+
+                        if code_block_dropped > 0 {
+                            let total = code_block_content.len() + code_block_dropped;
+                            log::warn!(
+                                "Code block truncated at {} of {} bytes (language: {})",
+                                code_block_content.len(), total,
+                                if code_block_lang.is_empty() { "none" } else { &code_block_lang }
+                            );
+                            events.push(Event::Html(format!(
+                                "<div class=\"code-truncated\">Code block truncated: showing {} of {} bytes</div>",
+                                code_block_content.len(), total
+                            ).into()));
+                            if let Ok(mut metrics) = self.metrics.write() {
+                                metrics.truncated_code_blocks += 1;
+                            }
+                        }
                         
                         in_code_block = false;
                     }
                 }
                 Event::Text(text) if in_code_block => {
                     // Limit code block size to prevent memory issues
-                    if code_block_content.len() + text.len() > 100_000 { // 100KB limit
-                        log::warn!("Code block too large, truncating at 100KB");
-                        let remaining_capacity = 100_000 - code_block_content.len();
-                        if remaining_capacity > 0 {
-                            code_block_content.push_str(&text[..remaining_capacity.min(text.len())]);
-                        }
+                    let limit = self.max_code_block_size;
+                    if limit > 0 && code_block_content.len() + text.len() > limit {
+                        let kept = if code_block_dropped == 0 {
+                            floor_char_boundary(&text, limit - code_block_content.len())
+                        } else {
+                            ""
+                        };
+                        code_block_content.push_str(kept);
+                        code_block_dropped += text.len() - kept.len();
                     } else {
                         code_block_content.push_str(&text);
                     }
                 }
+                Event::Code(code) if lite => {
+                    events.push(Event::Html(format!("<code>{}</code>", html_escape::encode_text(&code)).into()));
+                }
+                Event::Start(Tag::Image(link_type, url, title)) if lite => {
+                    image_has_text = false;
+                    events.push(Event::Start(Tag::Link(link_type, url, title)));
+                }
+                Event::End(Tag::Image(link_type, url, title)) if lite => {
+                    if !image_has_text {
+                        events.push(Event::Text("Image".into()));
+                    }
+                    image_has_text = true;
+                    events.push(Event::End(Tag::Link(link_type, url, title)));
+                }
+                Event::Text(text) if lite && !image_has_text => {
+                    image_has_text = !text.trim().is_empty();
+                    events.push(Event::Text(text));
+                }
+                Event::Html(_) if lite => {}
                 Event::Code(code) => {
                     // Handle inline code with CSS class
                     events.push(Event::Html(format!(
@@ -344,6 +976,15 @@ impl MarkdownService {
                         html_escape::encode_text(&code)
                     ).into()));
                 }
+                Event::Start(Tag::Heading(level, id, classes)) => {
+                    // {#id .class}: drop values that are not plain identifiers
+                    let id = id
+                        .filter(|id| super::toc::is_safe_identifier(id))
+                        .or_else(|| anchors.get(heading_index).map(|heading| heading.anchor.as_str()));
+                    heading_index += 1;
+                    let classes = classes.into_iter().filter(|c| super::toc::is_safe_identifier(c)).collect();
+                    events.push(Event::Start(Tag::Heading(level, id, classes)));
+                }
                 _ => {
                     events.push(event);
                 }
@@ -353,7 +994,14 @@ impl MarkdownService {
         Ok(events)
     }
 
+    /// The `sanitize` and `enhance` stages
     pub fn sanitize_html(&self, html: &str) -> Result<String, MarkdownError> {
+        let cleaned = self.clean_html(html);
+        Ok(self.enhance_html_security_and_accessibility(&cleaned))
+    }
+
+    /// Strip everything but the tags and attributes Markdown produces
+    fn clean_html(&self, html: &str) -> String {
         // Configure ammonia to allow safe HTML tags
         let mut builder = ammonia::Builder::new();
         
@@ -364,6 +1012,7 @@ impl MarkdownService {
                 "p", "br", "hr",
                 "strong", "em", "u", "s", "del", "ins",
                 "ul", "ol", "li",
+                "dl", "dt", "dd",
                 "blockquote",
                 "code", "pre",
                 "table", "thead", "tbody", "tfoot", "tr", "th", "td", "caption",
@@ -383,14 +1032,27 @@ impl MarkdownService {
             .add_tag_attributes("tr", &["class"])
             .add_tag_attributes("th", &["class", "scope"])
             .add_tag_attributes("td", &["class"])
-            .add_tag_attributes("input", &["type", "checked", "disabled"]);
+            .add_tag_attributes("input", &["type", "checked", "disabled"])
+            .add_tag_attributes("h1", &["id", "class"])
+            .add_tag_attributes("h2", &["id", "class"])
+            .add_tag_attributes("h3", &["id", "class"])
+            .add_tag_attributes("h4", &["id", "class"])
+            .add_tag_attributes("h5", &["id", "class"])
+            .add_tag_attributes("h6", &["id", "class"])
+            // Raw HTML headings are held to the same identifier rule as {#id .class}
+            .attribute_filter(|element, attribute, value| {
+                let is_heading = matches!(element, "h1" | "h2" | "h3" | "h4" | "h5" | "h6");
+                match attribute {
+                    "id" if is_heading => super::toc::is_safe_identifier(value).then_some(value.into()),
+                    "class" if is_heading => {
+                        let classes: Vec<&str> = value.split_whitespace().filter(|c| super::toc::is_safe_identifier(c)).collect();
+                        (!classes.is_empty()).then(|| classes.join(" ").into())
+                    }
+                    _ => Some(value.into()),
+                }
+            });
 
-        let cleaned = builder.clean(html).to_string();
-        
-        // Post-process to add security attributes and table accessibility
-        let enhanced_html = self.enhance_html_security_and_accessibility(&cleaned);
-        
-        Ok(enhanced_html)
+        builder.clean(html).to_string()
     }
 
     /// Enhance HTML with security attributes and table accessibility
@@ -407,59 +1069,34 @@ impl MarkdownService {
         // Block dangerous links
         enhanced = self.block_dangerous_links(&enhanced);
         
-        // Wrap tables in responsive container
-        enhanced = enhanced.replace("<table>", "<div class=\"table-responsive\"><table class=\"markdown-table\">");
-        enhanced = enhanced.replace("</table>", "</table></div>");
-        
-        // Add scope attributes to table headers for accessibility
-        enhanced = enhanced.replace("<th>", "<th scope=\"col\">");
-        
-        enhanced
+        // Wrap tables in responsive container and add scope attributes to table
+        // headers for accessibility; raw HTML tables may carry their own class
+        map_tags(&enhanced, |tag| match tag {
+            "<table>" => Some("<div class=\"table-responsive\"><table class=\"markdown-table\">".to_string()),
+            "</table>" => Some("</table></div>".to_string()),
+            "<th>" => Some("<th scope=\"col\">".to_string()),
+            _ if tag.starts_with("<table ") => Some(format!("<div class=\"table-responsive\">{}", tag)),
+            _ => None,
+        })
     }
 
     /// Add security attributes to external links using simple string processing
     fn add_security_to_external_links(&self, html: &str) -> String {
-        let mut result = String::new();
-        let mut chars = html.chars().peekable();
-        
-        while let Some(ch) = chars.next() {
-            if ch == '<' && chars.peek() == Some(&'a') {
-                // Found potential link start
-                let mut tag = String::from("<");
-                
-                // Collect the entire tag
-                while let Some(ch) = chars.next() {
-                    tag.push(ch);
-                    if ch == '>' {
-                        break;
-                    }
-                }
-                
-                // Check if it's an external link and add security attributes
-                if tag.contains("href=\"http://") || tag.contains("href=\"https://") {
-                    // Insert security attributes before the closing >
-                    if let Some(pos) = tag.rfind('>') {
-                        let mut secure_tag = tag[..pos].to_string();
-                        if !secure_tag.contains("rel=") {
-                            secure_tag.push_str(" rel=\"noopener noreferrer\"");
-                        }
-                        if !secure_tag.contains("target=") {
-                            secure_tag.push_str(" target=\"_blank\"");
-                        }
-                        secure_tag.push('>');
-                        result.push_str(&secure_tag);
-                    } else {
-                        result.push_str(&tag);
-                    }
-                } else {
-                    result.push_str(&tag);
-                }
-            } else {
-                result.push(ch);
+        map_tags(html, |tag| {
+            let is_external = tag.starts_with("<a ")
+                && (tag.contains(" href=\"http://") || tag.contains(" href=\"https://"));
+            if !is_external {
+                return None;
             }
-        }
-        
-        result
+            let mut attributes = String::new();
+            if !has_attribute(tag, "rel") {
+                attributes.push_str(" rel=\"noopener noreferrer\"");
+            }
+            if !has_attribute(tag, "target") {
+                attributes.push_str(" target=\"_blank\"");
+            }
+            Some(insert_attributes(tag, &attributes))
+        })
     }
 
     pub fn highlight_code(&self, code: &str, language: &str) -> Result<String, MarkdownError> {
@@ -472,10 +1109,11 @@ impl MarkdownService {
             return Ok(html_escape::encode_text(code).to_string());
         }
 
-        let syntax = self.syntax_set
-            .find_syntax_by_extension(language)
-            .or_else(|| self.syntax_set.find_syntax_by_name(language))
-            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text());
+        if super::ansi::is_ansi_language(language) {
+            return Ok(super::ansi::to_html(code));
+        }
+
+        let syntax = self.find_syntax(language);
 
         // Use syntect to highlight the code with CSS classes
         let mut highlighter = ClassedHTMLGenerator::new_with_class_style(
@@ -542,6 +1180,7 @@ impl MarkdownService {
             log::info!("  Average render time: {:.2}ms", metrics.avg_render_time_ms);
             log::info!("  Cache size: {} entries", metrics.cache_size);
             log::info!("  Memory usage: {:.2}KB", metrics.memory_usage_bytes as f64 / 1024.0);
+            log::info!("  Evictions: {}, expirations: {}", metrics.evictions, metrics.expirations);
         }
     }
 
@@ -559,64 +1198,124 @@ impl MarkdownService {
 
     /// Add loading="lazy" to images
     fn add_lazy_loading_to_images(&self, html: &str) -> String {
-        // Simple regex-like replacement for images
-        let _result = html.to_string();
-        
-        // Find <img tags and add loading="lazy" if not present
-        let mut new_result = String::new();
-        let mut chars = html.chars().peekable();
-        
-        while let Some(ch) = chars.next() {
-            if ch == '<' && chars.peek() == Some(&'i') {
-                // Check if this is an img tag
-                let mut tag = String::from("<");
-                let mut is_img_tag = false;
-                
-                // Collect the tag
-                while let Some(ch) = chars.next() {
-                    tag.push(ch);
-                    if tag.len() == 4 && tag == "<img" {
-                        is_img_tag = true;
-                    }
-                    if ch == '>' {
-                        break;
-                    }
-                }
-                
-                if is_img_tag && !tag.contains("loading=") {
-                    // Insert loading="lazy" before the closing >
-                    if let Some(pos) = tag.rfind('>') {
-                        let mut new_tag = tag[..pos].to_string();
-                        new_tag.push_str(" loading=\"lazy\">");
-                        new_result.push_str(&new_tag);
-                    } else {
-                        new_result.push_str(&tag);
-                    }
-                } else {
-                    new_result.push_str(&tag);
-                }
-            } else {
-                new_result.push(ch);
-            }
-        }
-        
-        new_result
+        map_tags(html, |tag| {
+            let is_img = tag
+                .strip_prefix("<img")
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>'));
+            (is_img && !has_attribute(tag, "loading")).then(|| insert_attributes(tag, " loading=\"lazy\""))
+        })
     }
 
     /// Block dangerous links by replacing them with safe fallbacks
     fn block_dangerous_links(&self, html: &str) -> String {
-        let mut result = html.to_string();
-        
-        // Block javascript: links
-        result = result.replace("href=\"javascript:", "href=\"#\" data-blocked=\"javascript:");
-        
-        // Block data: links (except safe image data URLs)
-        if result.contains("href=\"data:") && !result.contains("href=\"data:image/") {
-            result = result.replace("href=\"data:", "href=\"#\" data-blocked=\"data:");
+        map_tags(html, |tag| {
+            // Block javascript: links, and data: links except safe image data URLs
+            let dangerous = tag.contains("href=\"javascript:")
+                || (tag.contains("href=\"data:") && !tag.contains("href=\"data:image/"));
+            dangerous.then(|| {
+                tag.replace("href=\"javascript:", "href=\"#\" data-blocked=\"javascript:")
+                    .replace("href=\"data:", "href=\"#\" data-blocked=\"data:")
+            })
+        })
+    }
+}
+
+/// Length of the tag at the start of `html`, up to and including the `>` that
+/// closes it; a `>` inside a quoted attribute value does not
+fn tag_len(html: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, ch) in html.char_indices().skip(1) {
+        match quote {
+            Some(q) if ch == q => quote = None,
+            Some(_) => {}
+            None if ch == '"' || ch == '\'' => quote = Some(ch),
+            None if ch == '>' => return Some(i + 1),
+            None => {}
+        }
+    }
+    None
+}
+
+/// Replace the tags of `html` for which `f` returns something; text between
+/// tags and attribute values are never touched, and an unterminated tag is
+/// left as it is
+fn map_tags(html: &str, mut f: impl FnMut(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        let Some(len) = tag_len(&rest[start..]) else {
+            break;
+        };
+        let tag = &rest[start..start + len];
+        result.push_str(&rest[..start]);
+        match f(tag) {
+            Some(replacement) => result.push_str(&replacement),
+            None => result.push_str(tag),
+        }
+        rest = &rest[start + len..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Whether the tag has the attribute, looking only outside quoted values
+fn has_attribute(tag: &str, name: &str) -> bool {
+    let mut unquoted = String::with_capacity(tag.len());
+    let mut quote = None;
+    for ch in tag.chars() {
+        match quote {
+            Some(q) if ch == q => quote = None,
+            Some(_) => {}
+            None if ch == '"' || ch == '\'' => quote = Some(ch),
+            None => unquoted.push(ch),
         }
-        
-        result
     }
+    unquoted
+        .split(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+        .skip(1)
+        .any(|word| word.eq_ignore_ascii_case(name))
+}
+
+/// The tag with `attributes` added before its closing `>` (or `/>`)
+fn insert_attributes(tag: &str, attributes: &str) -> String {
+    let end = if tag.ends_with("/>") { tag.len() - 2 } else { tag.len() - 1 };
+    format!("{}{}{}", tag[..end].trim_end(), attributes, &tag[end..])
+}
+
+fn expand_snippet_refs(text: &str, snippets: &HashMap<String, String>, stack: &mut Vec<String>) -> String {
+    const OPEN: &str = "{{snippet:";
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(OPEN) {
+        let after_open = &rest[start + OPEN.len()..];
+        let Some(end) = after_open.find("}}") else {
+            break;
+        };
+        let name = after_open[..end].trim();
+        let escaped = rest[..start].ends_with('\\');
+        let valid_name = !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        output.push_str(&rest[..start]);
+        let reference = &rest[start..start + OPEN.len() + end + 2];
+        match snippets.get(name) {
+            Some(content) if !escaped && valid_name && stack.len() < MAX_SNIPPET_DEPTH && !stack.iter().any(|n| n == name) => {
+                stack.push(name.to_string());
+                output.push_str(&expand_snippet_refs(content, snippets, stack));
+                stack.pop();
+            }
+            Some(_) if !escaped && valid_name => {
+                log::warn!("Snippet '{}' is nested too deeply or references itself; left unexpanded", name);
+                output.push_str(reference);
+            }
+            _ => output.push_str(reference),
+        }
+        rest = &rest[start + OPEN.len() + end + 2..];
+    }
+
+    output.push_str(rest);
+    output
 }
 
 impl Default for MarkdownService {
@@ -628,6 +1327,17 @@ impl Default for MarkdownService {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::render_pipeline::{PARSE, SANITIZE};
+
+    #[test]
+    fn test_first_paragraph_and_image() {
+        let service = MarkdownService::new();
+        let markdown = "# Title\n\n![Hero](/media/hero.png)\n\n- list item\n\n> quoted\n\nThe **lead**\nparagraph with `code`.\n\nSecond.";
+        assert_eq!(service.first_paragraph(markdown).as_deref(), Some("The lead paragraph with code."));
+        assert_eq!(service.first_image(markdown).as_deref(), Some("/media/hero.png"));
+        assert_eq!(service.first_paragraph("# Only a heading"), None);
+        assert_eq!(service.first_image("No images here"), None);
+    }
 
     #[test]
     fn test_basic_markdown_rendering() {
@@ -815,6 +1525,142 @@ The table above should have:
         println!("Comprehensive test passed - all table and link features working correctly");
     }
 
+    #[test]
+    fn test_console_code_block() {
+        let service = MarkdownService::new();
+        let result = service.render_to_html("```console\n$ cargo test\n\x1b[1;31merror\x1b[0m: <failed>\n```").unwrap();
+        assert!(result.contains("<span class=\"ansi-bold ansi-red\">error</span>: &lt;failed&gt;"));
+        assert!(!result.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_code_block_truncation_marker() {
+        let service = MarkdownService::new().with_max_code_block_size(5);
+        let result = service.render_to_html("```\nlet é = 1;\nlet b = 2;\n```").unwrap();
+        // The limit falls inside "é", so the cut moves back to the character boundary
+        assert!(result.contains("Code block truncated: showing 4 of 23 bytes"));
+        assert!(!result.contains("b = 2"));
+        assert_eq!(service.get_metrics().truncated_code_blocks, 1);
+
+        let unlimited = MarkdownService::new().with_max_code_block_size(0);
+        let result = unlimited.render_to_html("```\nlet é = 1;\nlet b = 2;\n```").unwrap();
+        assert!(!result.contains("code-truncated"));
+        assert_eq!(floor_char_boundary("é", 1), "");
+    }
+
+    #[test]
+    fn test_heading_attributes() {
+        let service = MarkdownService::new();
+        let result = service.render_to_html("## Install {#setup .wide .bad!class}\n\n# Plain").unwrap();
+        assert!(result.contains("<h2 id=\"setup\" class=\"wide\">Install</h2>"));
+        assert!(result.contains("<h1>Plain</h1>"));
+
+        let unsafe_id = service.render_to_html("# Title {#9lives}\n\n<h3 id=\"x y\" class=\"ok 1no\">Raw</h3>").unwrap();
+        assert!(unsafe_id.contains("<h1>Title</h1>"));
+        assert!(unsafe_id.contains("<h3 class=\"ok\">Raw</h3>"));
+    }
+
+    #[test]
+    fn test_render_features() {
+        let service = MarkdownService::new();
+        let markdown = "# Intro {#start}\n\n## Energy $E = mc_2$\n\nWith $a_1 * b_1$ and `$x$`.";
+        let plain = service.render_to_html(markdown).unwrap();
+        assert!(plain.contains("<h2>Energy $E = mc_2$</h2>"));
+
+        let features = RenderFeatures { math: true, heading_anchors: true, lite: false };
+        let html = service.render_with_features(markdown, features).unwrap();
+        assert!(html.contains("<h1 id=\"start\">Intro</h1>"));
+        assert!(html.contains("<h2 id=\"energy-e--mc_2\">Energy <span class=\"math math-inline\">\\(E = mc_2\\)</span></h2>"));
+        assert!(html.contains("<span class=\"math math-inline\">\\(a_1 * b_1\\)</span>"));
+        assert!(html.contains("<code class=\"inline-code\">$x$</code>"));
+        // Cached separately from the plain render
+        assert_eq!(service.render_to_html(markdown).unwrap(), plain);
+    }
+
+    struct Shortcodes;
+
+    impl RenderStage for Shortcodes {
+        fn name(&self) -> &str {
+            "shortcodes"
+        }
+
+        fn markdown(&self, markdown: String, _context: &mut RenderContext) -> String {
+            markdown.replace("{{year}}", "2024")
+        }
+    }
+
+    // Appends its HTML wherever it is registered
+    struct Append(&'static str, &'static str);
+
+    impl RenderStage for Append {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn enabled(&self, features: RenderFeatures) -> bool {
+            !features.lite
+        }
+
+        fn html(&self, html: String, _context: &mut RenderContext) -> String {
+            html + self.1
+        }
+    }
+
+    #[test]
+    fn test_registered_stages() {
+        let mut service = MarkdownService::new();
+        let markdown = "# Since {{year}}";
+        assert_eq!(service.render_to_html(markdown).unwrap(), "<h1>Since {{year}}</h1>\n");
+
+        service.register_stage(StagePosition::After(PARSE), Arc::new(Shortcodes)).unwrap();
+        service.register_stage(StagePosition::Before(SANITIZE), Arc::new(Append("raw", "<script>x</script><em>raw</em>"))).unwrap();
+        service.register_stage(StagePosition::Last, Arc::new(Append("trusted", "<aside>trusted</aside>"))).unwrap();
+        assert!(service.register_stage(StagePosition::Last, Arc::new(Shortcodes)).is_err());
+
+        // The cached render from before the stages were added is not reused
+        let html = service.render_to_html(markdown).unwrap();
+        assert_eq!(html, "<h1>Since 2024</h1>\n<em>raw</em><aside>trusted</aside>");
+
+        // Stages that are not enabled for the features are skipped
+        let lite = RenderFeatures { lite: true, ..RenderFeatures::default() };
+        assert_eq!(service.render_with_features(markdown, lite).unwrap(), "<h1>Since 2024</h1>\n");
+    }
+
+    #[test]
+    fn test_lite_render() {
+        let service = MarkdownService::new();
+        let markdown = "Run `cargo`:\n\n```rust\nfn main() {}\n```\n\n![A cat](/media/cat.png) ![](/media/dog.png)\n\n<iframe src=\"https://video.example/1\"></iframe>\n\n<b>bold</b> text";
+        let lite = RenderFeatures { lite: true, ..RenderFeatures::default() };
+        let html = service.render_with_features(markdown, lite).unwrap();
+        assert!(html.contains("<code>cargo</code>"));
+        assert!(html.contains("<pre><code>fn main() {}\n</code></pre>"));
+                assert!(html.contains("<a href=\"/media/cat.png\" rel=\"noopener noreferrer\">A cat</a>"));
+        assert!(html.contains("<a href=\"/media/dog.png\" rel=\"noopener noreferrer\">Image</a>"));
+        assert!(!html.contains("<span") && !html.contains("<img") && !html.contains("iframe") && !html.contains("<b>"));
+        assert!(html.contains("bold text"));
+        // The full render is cached separately
+        assert!(service.render_to_html(markdown).unwrap().contains("<img"));
+    }
+
+    #[test]
+    fn test_theme_css() {
+        let service = MarkdownService::new();
+        assert!(service.has_theme("InspiredGitHub"));
+        let css = service.theme_css("InspiredGitHub").unwrap();
+        assert!(css.contains("pre.highlight {"));
+        assert!(css.lines().filter(|l| l.ends_with(" {")).all(|l| l.starts_with(".highlight ") || l == "pre.highlight {"));
+        assert_eq!(service.theme_css("dracula"), None);
+    }
+
+    #[test]
+    fn test_definition_list_survives_sanitizer() {
+        let service = MarkdownService::new();
+        let result = service.render_to_html("Glossary\n: A list of `terms`").unwrap();
+        assert!(result.contains("<dl>"));
+        assert!(result.contains("<dt>Glossary</dt>"));
+        assert!(result.contains("<dd>A list of <code class=\"inline-code\">terms</code></dd>"));
+    }
+
     #[test]
     fn test_strikethrough_and_tasklist() {
         let service = MarkdownService::new();
@@ -825,6 +1671,156 @@ The table above should have:
         assert!(result.contains("type=\"checkbox\""));
     }
 
+    #[test]
+    fn test_set_task_by_rendered_index() {
+        let service = MarkdownService::new();
+        let markdown = "- [ ] first\n- [x] second";
+        assert_eq!(service.set_task(markdown, 1, None).unwrap(), ("- [ ] first\n- [ ] second".to_string(), false));
+        assert_eq!(service.set_task(markdown, 0, Some(true)).unwrap().0, "- [x] first\n- [x] second");
+        assert_eq!(service.set_task(markdown, 2, None), Err(super::super::tasks::TaskError::NotFound(2)));
+
+        service.set_snippets(HashMap::from([("todo".to_string(), "- [ ] from snippet".to_string())]));
+        let with_snippet = "{{snippet:todo}}\n\n- [ ] own";
+        assert_eq!(service.set_task(with_snippet, 0, None), Err(super::super::tasks::TaskError::FromSnippet));
+    }
+
+    #[test]
+    fn test_snippet_expansion() {
+        let service = MarkdownService::new();
+        service.set_snippets(HashMap::from([
+            ("disclaimer".to_string(), "*Affiliate links ahead.*".to_string()),
+            ("footer".to_string(), "Thanks! {{snippet:disclaimer}}".to_string()),
+        ]));
+
+        let result = service.render_to_html("Intro\n\n{{snippet:footer}}").unwrap();
+        assert!(result.contains("Thanks! <em>Affiliate links ahead.</em>"));
+
+        // 未知片段原样保留
+        assert_eq!(service.expand_snippets("{{snippet:missing}}"), "{{snippet:missing}}");
+        // 反斜杠转义
+        assert_eq!(service.expand_snippets("\\{{snippet:disclaimer}}"), "\\{{snippet:disclaimer}}");
+    }
+
+    #[test]
+    fn test_snippet_recursion_protection() {
+        let service = MarkdownService::new();
+        service.set_snippets(HashMap::from([
+            ("a".to_string(), "A {{snippet:b}}".to_string()),
+            ("b".to_string(), "B {{snippet:a}}".to_string()),
+            ("self".to_string(), "loop {{snippet:self}}".to_string()),
+        ]));
+
+        assert_eq!(service.expand_snippets("{{snippet:a}}"), "A B {{snippet:a}}");
+        assert_eq!(service.expand_snippets("{{snippet:self}}"), "loop {{snippet:self}}");
+    }
+
+    #[test]
+    fn test_should_offload() {
+        let service = MarkdownService::new().with_offload_threshold(64);
+        let large = "word ".repeat(20);
+        assert!(!service.should_offload("# small"));
+        assert!(service.should_offload(&large));
+
+        // Cached documents render cheaply in place
+        service.render_to_html(&large).unwrap();
+        assert!(!service.should_offload(&large));
+
+        {
+            let _guard = service.begin_offload();
+            assert_eq!(service.get_metrics().offload_queue_depth, 1);
+        }
+        let metrics = service.get_metrics();
+        assert_eq!(metrics.offload_queue_depth, 0);
+        assert_eq!(metrics.offloaded_renders, 1);
+
+        assert!(!MarkdownService::new().should_offload(&large));
+    }
+
+    #[test]
+    fn test_concurrent_misses_render_once() {
+        let service = Arc::new(MarkdownService::new());
+        let markdown = "```rust\nfn main() { println!(\"hi\"); }\n```\n\n".repeat(200);
+        let barrier = Arc::new(std::sync::Barrier::new(8));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let (service, markdown, barrier) = (service.clone(), markdown.clone(), barrier.clone());
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    service.render_to_html(&markdown).unwrap()
+                })
+            })
+            .collect();
+        let results: Vec<String> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        assert!(results.windows(2).all(|pair| pair[0] == pair[1]));
+        let metrics = service.get_metrics();
+        assert_eq!(metrics.total_renders, 1);
+        assert_eq!(metrics.cache_hits + metrics.coalesced_renders, 7);
+        assert!(service.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_eviction_when_cache_is_full() {
+        let service = MarkdownService::with_cache_config(
+            Duration::from_secs(60), 2, 1024 * 1024, 1024 * 1024, Duration::from_secs(600),
+        );
+        service.render_to_html("# one").unwrap();
+        service.render_to_html("# one").unwrap();
+        service.render_to_html("# two").unwrap();
+        service.render_to_html("# three").unwrap();
+
+        let metrics = service.get_metrics();
+        assert_eq!(metrics.cache_size, 2);
+        assert_eq!(metrics.evictions, 1);
+        // The entry hit twice survives, the least used one goes
+        service.render_to_html("# one").unwrap();
+        assert_eq!(service.get_metrics().cache_hits, 2);
+    }
+
+    #[test]
+    fn test_weight_based_eviction() {
+        let service = MarkdownService::with_cache_config(
+            Duration::from_secs(60), 1000, 1024 * 1024, 300, Duration::from_secs(600),
+        );
+        let docs: Vec<String> = (0..3).map(|i| format!("{} {}", "word".repeat(25), i)).collect();
+        for doc in &docs {
+            service.render_to_html(doc).unwrap();
+        }
+        let metrics = service.get_metrics();
+        assert!(metrics.memory_usage_bytes <= 300);
+        assert_eq!(metrics.cache_size, 2);
+        assert_eq!(metrics.evictions, 1);
+
+        // Documents larger than the whole budget are rendered but not cached
+        service.render_to_html(&"word ".repeat(100)).unwrap();
+        assert_eq!(service.get_metrics().cache_size, 2);
+    }
+
+    #[test]
+    fn test_sliding_ttl() {
+        let service = MarkdownService::with_cache_config(
+            Duration::from_millis(300), 1000, 1024 * 1024, 1024 * 1024, Duration::from_millis(800),
+        );
+        service.render_to_html("# hot").unwrap();
+        service.render_to_html("# cold").unwrap();
+        for _ in 0..2 {
+            std::thread::sleep(Duration::from_millis(200));
+            service.render_to_html("# hot").unwrap();
+        }
+        // "hot" stays alive past the base TTL, "cold" expired
+        assert_eq!(service.get_metrics().cache_hits, 2);
+        service.render_to_html("# cold").unwrap();
+        assert_eq!(service.get_metrics().expirations, 1);
+
+        // The maximum lifetime caps the sliding expiry
+        std::thread::sleep(Duration::from_millis(200));
+        service.render_to_html("# hot").unwrap();
+        std::thread::sleep(Duration::from_millis(260));
+        service.render_to_html("# hot").unwrap();
+        assert_eq!(service.get_metrics().total_renders, 4);
+    }
+
     #[test]
     fn test_render_empty_markdown() {
         let service = MarkdownService::new();
@@ -865,6 +1861,43 @@ The table above should have:
         assert!(result.contains("some code"));
     }
 
+    #[test]
+    fn test_language_aliases() {
+        let service = MarkdownService::new();
+        for (alias, language) in DEFAULT_LANGUAGE_ALIASES {
+            assert_eq!(service.find_syntax(alias).name, *language, "alias {}", alias);
+        }
+        assert_eq!(service.find_syntax("Shell").name, "Bourne Again Shell (bash)");
+        assert_eq!(service.find_syntax("unknown_language").name, "Plain Text");
+
+        let custom = MarkdownService::new().with_language_aliases(parse_language_aliases(" Starlark = Python , bad, =x"));
+        assert_eq!(custom.find_syntax("starlark").name, "Python");
+        assert!(custom.highlight_code("let x = 1;", "rs").unwrap().contains("<span"));
+    }
+
+    #[test]
+    fn test_load_extra_syntaxes() {
+        let dir = std::env::temp_dir().join(format!("bluster-syntaxes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("TOML.sublime-syntax"),
+            "%YAML 1.2\n---\nname: TOML\nfile_extensions: [toml]\nscope: source.toml\ncontexts:\n  main:\n    - match: '#.*$'\n      scope: comment.line.number-sign.toml\n",
+        )
+        .unwrap();
+
+        let mut service = MarkdownService::new();
+        assert_eq!(service.find_syntax("toml").name, "Plain Text");
+        assert_eq!(service.load_extra_syntaxes(&dir).unwrap(), 1);
+        assert_eq!(service.find_syntax("toml").name, "TOML");
+        assert!(service.highlight_code("# comment\n", "toml").unwrap().contains("comment"));
+        // Built-in syntaxes survive the rebuild
+        assert_eq!(service.find_syntax("rs").name, "Rust");
+
+        assert!(matches!(service.load_extra_syntaxes(dir.join("missing")), Err(MarkdownError::SyntaxLoadError(_))));
+        assert_eq!(service.find_syntax("toml").name, "TOML");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
     #[test]
     fn test_highlight_empty_code() {
         let service = MarkdownService::new();
@@ -1234,4 +2267,203 @@ fn render_markdown(input: &str) -> String {
         assert!(result.contains("alt=\"Architecture Diagram\""));
         assert!(result.contains("title=\"System Architecture\""));
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod property_tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::sync::OnceLock;
+
+    fn service() -> &'static MarkdownService {
+        static SERVICE: OnceLock<MarkdownService> = OnceLock::new();
+        SERVICE.get_or_init(MarkdownService::new)
+    }
+
+    /// Elements that have no end tag
+    const VOID: &[&str] = &["br", "hr", "img", "input", "wbr"];
+
+    /// Tags of `html` as (name, is_end, text between `<` and `>`), or an error
+    /// when a tag is not closed; `>` inside quoted attribute values is skipped
+    fn tags(html: &str) -> Result<Vec<(String, bool, String)>, String> {
+        let mut tags = Vec::new();
+        let mut rest = html;
+        while let Some(start) = rest.find('<') {
+            let mut quote = None;
+            let mut end = None;
+            for (i, ch) in rest[start + 1..].char_indices() {
+                match quote {
+                    Some(q) if ch == q => quote = None,
+                    Some(_) => {}
+                    None if ch == '"' => quote = Some(ch),
+                    None if ch == '>' => {
+                        end = Some(start + 1 + i);
+                        break;
+                    }
+                    None => {}
+                }
+            }
+            let end = end.ok_or_else(|| format!("unterminated tag in {:?}", html))?;
+            let inner = &rest[start + 1..end];
+            let is_end = inner.starts_with('/');
+            let name: String = inner.trim_start_matches('/').chars().take_while(|c| c.is_ascii_alphanumeric()).collect();
+            tags.push((name.to_ascii_lowercase(), is_end, inner.to_string()));
+            rest = &rest[end + 1..];
+        }
+        Ok(tags)
+    }
+
+    /// Every element closed in order; every tag a name followed by distinct,
+    /// double-quoted attributes
+    fn assert_well_formed(html: &str) -> Result<(), TestCaseError> {
+        static TAG: OnceLock<regex::Regex> = OnceLock::new();
+        static ATTRIBUTE: OnceLock<regex::Regex> = OnceLock::new();
+        let tag = TAG.get_or_init(|| regex::Regex::new(r#"^/?[a-z0-9]+(\s+[a-z][-a-z0-9]*(="[^"]*")?)*\s*/?$"#).unwrap());
+        let attribute = ATTRIBUTE.get_or_init(|| regex::Regex::new(r#"\s([a-z][-a-z0-9]*)(="[^"]*")?"#).unwrap());
+        let mut open: Vec<String> = Vec::new();
+        for (name, is_end, inner) in tags(html).map_err(TestCaseError::fail)? {
+            prop_assert!(tag.is_match(&inner), "malformed <{}> in {:?}", inner, html);
+            let mut names: Vec<&str> = attribute.captures_iter(&inner).map(|c| c.get(1).unwrap().as_str()).collect();
+            let count = names.len();
+            names.sort_unstable();
+            names.dedup();
+            prop_assert_eq!(names.len(), count, "repeated attribute in <{}> of {:?}", inner, html);
+            if VOID.contains(&name.as_str()) {
+                prop_assert!(!is_end, "end tag for void element in {:?}", html);
+            } else if is_end {
+                let last = open.pop();
+                prop_assert_eq!(last.as_deref(), Some(name.as_str()), "misnested </{}> in {:?}", name, html);
+            } else {
+                open.push(name);
+            }
+        }
+        prop_assert!(open.is_empty(), "unclosed {:?} in {:?}", open, html);
+        Ok(())
+    }
+
+    /// Values of `attribute` on every tag, entities decoded
+    fn attribute_values(html: &str, attribute: &str) -> Vec<String> {
+        let needle = format!(" {}=\"", attribute);
+        tags(html)
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|(_, _, inner)| {
+                let mut values = Vec::new();
+                let mut rest = inner.as_str();
+                while let Some(start) = rest.find(&needle) {
+                    let value = &rest[start + needle.len()..];
+                    let end = value.find('"').unwrap_or(value.len());
+                    values.push(html_escape::decode_html_entities(&value[..end]).into_owned());
+                    rest = &value[end..];
+                }
+                values
+            })
+            .collect()
+    }
+
+    /// `output` is `input` with text inserted, nothing removed or reordered
+    fn only_inserts(input: &str, output: &str) -> bool {
+        let mut output = output.chars();
+        input.chars().all(|ch| output.any(|o| o == ch))
+    }
+
+    fn url() -> impl Strategy<Value = String> {
+        prop_oneof![
+            Just("https://example.com/a?b=1&amp;c=2".to_string()),
+            Just("http://example.com".to_string()),
+            Just("/post/1".to_string()),
+            Just("#top".to_string()),
+            Just("javascript:alert(1)".to_string()),
+            Just("JaVaScRiPt:alert(1)".to_string()),
+            Just(" javascript:alert(1)".to_string()),
+            Just("java&#x09;script:alert(1)".to_string()),
+            Just("&#106;avascript:alert(1)".to_string()),
+            Just("vbscript:msgbox(1)".to_string()),
+            Just("data:text/html,<script>alert(1)</script>".to_string()),
+            Just("data:image/png;base64,AAAA".to_string()),
+            Just("1 > 0 <table> <th> <img".to_string()),
+            "[a-z:/.#?=&;\"'<> ]{0,20}",
+        ]
+    }
+
+    fn attribute() -> impl Strategy<Value = String> {
+        let name = prop_oneof![
+            Just("href"), Just("src"), Just("title"), Just("class"), Just("id"), Just("rel"), Just("target"),
+            Just("loading"), Just("onclick"), Just("onerror"), Just("style"), Just("alt"),
+        ];
+        (name, url(), any::<bool>()).prop_map(|(name, value, single)| match single {
+            true => format!(" {}='{}'", name, value.replace('\'', "")),
+            false => format!(" {}=\"{}\"", name, value.replace('"', "&quot;")),
+        })
+    }
+
+    /// Fragments of plausible and hostile HTML, including unbalanced and unterminated tags
+    fn html() -> impl Strategy<Value = String> {
+        let tag = prop_oneof![
+            Just("a"), Just("img"), Just("table"), Just("th"), Just("p"), Just("div"), Just("script"),
+            Just("iframe"), Just("svg"), Just("style"), Just("object"), Just("input"), Just("h2"), Just("A"),
+        ];
+        let piece = prop_oneof![
+            (tag.clone(), prop::collection::vec(attribute(), 0..4), any::<bool>())
+                .prop_map(|(tag, attributes, closed)| format!("<{}{}{}", tag, attributes.concat(), if closed { ">" } else { "" })),
+            tag.prop_map(|tag| format!("</{}>", tag)),
+            "[a-zA-Z0-9 <>&;\"'=/:]{0,12}",
+            Just("<!-- comment -->".to_string()),
+            Just("<![CDATA[x]]>".to_string()),
+        ];
+        prop::collection::vec(piece, 0..12).prop_map(|pieces| pieces.concat())
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(512))]
+
+        #[test]
+        fn sanitize_removes_dangerous_markup(input in html()) {
+            let output = service().sanitize_html(&input).unwrap();
+            let lower = output.to_ascii_lowercase();
+            for tag in tags(&lower).map_err(TestCaseError::fail)? {
+                prop_assert!(!["script", "iframe", "svg", "style", "object"].contains(&tag.0.as_str()), "{:?} -> {:?}", input, output);
+                prop_assert!(!tag.2.contains(" on") || !tag.2.contains("=\""), "event handler kept: {:?}", output);
+            }
+            for value in attribute_values(&output, "href").into_iter().chain(attribute_values(&output, "src")) {
+                let scheme: String = value.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect::<String>().to_ascii_lowercase();
+                for dangerous in ["javascript:", "vbscript:", "data:text"] {
+                    prop_assert!(!scheme.starts_with(dangerous), "{:?} survived in {:?}", value, output);
+                }
+            }
+        }
+
+        #[test]
+        fn sanitize_output_is_well_formed(input in prop_oneof![html(), ".{0,60}"]) {
+            let output = service().sanitize_html(&input).unwrap();
+            assert_well_formed(&output)?;
+            // sanitizing is idempotent apart from the enhancements applied on top
+            let again = service().sanitize_html(&output).unwrap();
+            assert_well_formed(&again)?;
+        }
+
+        #[test]
+        fn external_links_get_rel_and_target(input in prop_oneof![html(), ".{0,60}"]) {
+            let output = service().add_security_to_external_links(&input);
+            prop_assert!(only_inserts(&input, &output), "{:?} -> {:?}", input, output);
+            let cleaned = service().sanitize_html(&input).unwrap();
+            for (name, is_end, inner) in tags(&cleaned).map_err(TestCaseError::fail)? {
+                if name == "a" && !is_end && (inner.contains(" href=\"http://") || inner.contains(" href=\"https://")) {
+                    prop_assert!(inner.contains(" rel=\"") && inner.contains(" target=\""), "{:?}", cleaned);
+                }
+            }
+        }
+
+        #[test]
+        fn images_get_lazy_loading(input in prop_oneof![html(), ".{0,60}"]) {
+            let output = service().add_lazy_loading_to_images(&input);
+            prop_assert!(only_inserts(&input, &output), "{:?} -> {:?}", input, output);
+            let cleaned = service().sanitize_html(&input).unwrap();
+            for (name, is_end, inner) in tags(&cleaned).map_err(TestCaseError::fail)? {
+                if name == "img" && !is_end {
+                    prop_assert!(inner.contains(" loading=\""), "{:?}", cleaned);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! TeX math for articles that opt in with `math: true`. Formulas written as
+//! `$...$` (inline) or `$$...$$` (display) are cut out of the markdown before it
+//! is parsed, so emphasis and backslash escapes inside them are left alone, and
+//! come back as `\(...\)` / `\[...\]` in `<span class="math">` for KaTeX to
+//! typeset in the browser.
+//!
+//! Dollar signs in code, in raw HTML and after a backslash are not math. Like
+//! Pandoc, an inline formula must not start or end with a space and its closing
+//! `$` must not be followed by a digit, so "$5 and $10" stays text.
+
+use super::markdown::RenderFeatures;
+use super::render_pipeline::{RenderContext, RenderStage};
+use pulldown_cmark::{Event, Options, Parser, Tag};
+
+/// Placeholders are `OPEN index CLOSE`, in the private use area so they pass
+/// through markdown parsing and sanitizing untouched
+const OPEN: char = '\u{E000}';
+const CLOSE: char = '\u{E001}';
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Formula {
+    pub tex: String,
+    pub display: bool,
+}
+
+impl Formula {
+    /// The formula as written in the markdown
+    fn source(&self) -> String {
+        let delimiter = if self.display { "$$" } else { "$" };
+        format!("{}{}{}", delimiter, self.tex, delimiter)
+    }
+
+    fn to_html(&self) -> String {
+        let tex = html_escape::encode_text(&self.tex);
+        if self.display {
+            format!("<span class=\"math math-display\">\\[{}\\]</span>", tex)
+        } else {
+            format!("<span class=\"math math-inline\">\\({}\\)</span>", tex)
+        }
+    }
+}
+
+/// Formula starting at the beginning of `text`, with the length of its source
+fn formula_at(text: &str) -> Option<(Formula, usize)> {
+    if let Some(body) = text.strip_prefix("$$") {
+        let end = body.find("$$")?;
+        let tex = body[..end].trim();
+        return (!tex.is_empty()).then(|| (Formula { tex: tex.to_string(), display: true }, end + 4));
+    }
+    let body = text.strip_prefix('$')?;
+    if body.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let mut escaped = false;
+    for (i, ch) in body.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            // Inline math does not continue past the end of a paragraph
+            '\n' if body[i + 1..].trim_start_matches([' ', '\t']).starts_with('\n') => return None,
+            '$' => {
+                let tex = &body[..i];
+                let digit_follows = body[i + 1..].starts_with(|c: char| c.is_ascii_digit());
+                if tex.is_empty() || tex.ends_with(char::is_whitespace) || digit_follows {
+                    return None;
+                }
+                return Some((Formula { tex: tex.to_string(), display: false }, i + 2));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The markdown with every formula replaced by a placeholder, and the formulas in order
+pub fn extract(markdown: &str, options: Options) -> (String, Vec<Formula>) {
+    // Code spans, code blocks and raw HTML, in document order
+    let mut verbatim = Parser::new_ext(markdown, options)
+        .into_offset_iter()
+        .filter(|(event, _)| matches!(event, Event::Code(_) | Event::Html(_) | Event::Start(Tag::CodeBlock(_))))
+        .map(|(_, range)| range)
+        .peekable();
+
+    let mut output = String::with_capacity(markdown.len());
+    let mut formulas = Vec::new();
+    let mut i = 0;
+    while i < markdown.len() {
+        while verbatim.peek().is_some_and(|range| range.end <= i) {
+            verbatim.next();
+        }
+        let next_verbatim = match verbatim.peek() {
+            Some(range) if range.start <= i => {
+                output.push_str(&markdown[i..range.end]);
+                i = range.end;
+                continue;
+            }
+            Some(range) => range.start,
+            None => markdown.len(),
+        };
+        let rest = &markdown[i..next_verbatim];
+        if rest.starts_with("\\$") {
+            output.push_str("\\$");
+            i += 2;
+        } else if let Some((formula, len)) = formula_at(rest) {
+            output.push(OPEN);
+            output.push_str(&formulas.len().to_string());
+            output.push(CLOSE);
+            formulas.push(formula);
+            i += len;
+        } else if let Some(ch) = rest.chars().next() {
+            output.push(ch);
+            i += ch.len_utf8();
+        }
+    }
+    (output, formulas)
+}
+
+enum Segment<'t> {
+    Text(&'t str),
+    Formula(&'t Formula),
+}
+
+/// `text` split around placeholders; anything that only looks like one stays text
+fn segments<'t>(text: &'t str, formulas: &'t [Formula]) -> Vec<Segment<'t>> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(OPEN) {
+        let body = &rest[start + OPEN.len_utf8()..];
+        let formula = body
+            .find(CLOSE)
+            .and_then(|end| Some((body[..end].parse::<usize>().ok()?, end)))
+            .and_then(|(index, end)| Some((formulas.get(index)?, end)));
+        match formula {
+            Some((formula, end)) => {
+                segments.push(Segment::Text(&rest[..start]));
+                segments.push(Segment::Formula(formula));
+                rest = &body[end + CLOSE.len_utf8()..];
+            }
+            None => {
+                let next = start + OPEN.len_utf8();
+                segments.push(Segment::Text(&rest[..next]));
+                rest = &rest[next..];
+            }
+        }
+    }
+    segments.push(Segment::Text(rest));
+    segments
+}
+
+/// Placeholders back to the formulas' markdown source
+fn to_source(text: &str, formulas: &[Formula]) -> String {
+    segments(text, formulas)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => text.to_string(),
+            Segment::Formula(formula) => formula.source(),
+        })
+        .collect()
+}
+
+/// Turn placeholders in text into math spans. Image descriptions are plain text
+/// (the `alt` attribute), so formulas there get their source back instead.
+pub fn rewrite<'a>(events: Vec<Event<'a>>, formulas: &[Formula]) -> Vec<Event<'a>> {
+    let mut rewritten = Vec::with_capacity(events.len());
+    let mut image_depth = 0usize;
+    for event in events {
+        match event {
+            Event::Start(Tag::Image(..)) => {
+                image_depth += 1;
+                rewritten.push(event);
+            }
+            Event::End(Tag::Image(..)) => {
+                image_depth = image_depth.saturating_sub(1);
+                rewritten.push(event);
+            }
+            Event::Text(text) if text.contains(OPEN) => {
+                if image_depth > 0 {
+                    rewritten.push(Event::Text(to_source(&text, formulas).into()));
+                    continue;
+                }
+                for segment in segments(&text, formulas) {
+                    match segment {
+                        Segment::Text("") => {}
+                        Segment::Text(text) => rewritten.push(Event::Text(text.to_string().into())),
+                        Segment::Formula(formula) => rewritten.push(Event::Html(formula.to_html().into())),
+                    }
+                }
+            }
+            event => rewritten.push(event),
+        }
+    }
+    rewritten
+}
+
+/// Placeholders left in the rendered HTML (link targets and titles) back to
+/// their source, escaped so they are safe inside attribute values
+pub fn restore(html: &str, formulas: &[Formula]) -> String {
+    if !html.contains(OPEN) {
+        return html.to_string();
+    }
+    segments(html, formulas)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => text.to_string(),
+            Segment::Formula(formula) => html_escape::encode_safe(&formula.source()).to_string(),
+        })
+        .collect()
+}
+
+/// Math as a render stage for articles with `RenderFeatures::math`: formulas
+/// are taken out before parsing and put back as spans after sanitizing
+pub struct MathStage;
+
+// 本次渲染中取出的公式
+struct Formulas(Vec<Formula>);
+
+impl RenderStage for MathStage {
+    fn name(&self) -> &str {
+        "math"
+    }
+
+    fn enabled(&self, features: RenderFeatures) -> bool {
+        features.math
+    }
+
+    fn markdown(&self, markdown: String, context: &mut RenderContext) -> String {
+        let (extracted, formulas) = extract(&markdown, context.options);
+        context.insert(Formulas(formulas));
+        extracted
+    }
+
+    fn events<'a>(&self, events: Vec<Event<'a>>, context: &mut RenderContext) -> Vec<Event<'a>> {
+        match context.get::<Formulas>() {
+            Some(Formulas(formulas)) if !formulas.is_empty() => rewrite(events, formulas),
+            _ => events,
+        }
+    }
+
+    fn html(&self, html: String, context: &mut RenderContext) -> String {
+        match context.get::<Formulas>() {
+            Some(Formulas(formulas)) if !formulas.is_empty() => restore(&html, formulas),
+            _ => html,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::html;
+
+    fn render(markdown: &str) -> String {
+        let (extracted, formulas) = extract(markdown, Options::empty());
+        let events = rewrite(Parser::new(&extracted).collect(), &formulas);
+        let mut output = String::new();
+        html::push_html(&mut output, events.into_iter());
+        restore(&output, &formulas)
+    }
+
+    #[test]
+    fn test_extract() {
+        let (extracted, formulas) = extract("Euler: $e^{i\\pi} = -1$ and\n\n$$\n\\sum_i x_i\n$$\n", Options::empty());
+        assert_eq!(extracted, "Euler: \u{E000}0\u{E001} and\n\n\u{E000}1\u{E001}\n");
+        assert_eq!(formulas[0], Formula { tex: "e^{i\\pi} = -1".to_string(), display: false });
+        assert_eq!(formulas[1], Formula { tex: "\\sum_i x_i".to_string(), display: true });
+
+        // Prices, escaped dollars and code are not math
+        for text in ["It costs $5 and $10.", "\\$x$ is literal", "`$x$` in code", "```\n$x$\n```", "$ x $"] {
+            assert!(extract(text, Options::empty()).1.is_empty(), "{}", text);
+        }
+        // Inline math stops at a paragraph break
+        assert!(extract("$a\n\nb$", Options::empty()).1.is_empty());
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render("Area $\\pi r_1^2 < a_2$ here"),
+            "<p>Area <span class=\"math math-inline\">\\(\\pi r_1^2 &lt; a_2\\)</span> here</p>\n"
+        );
+        assert_eq!(render("$$x_1 * y_1$$"), "<p><span class=\"math math-display\">\\[x_1 * y_1\\]</span></p>\n");
+        assert_eq!(render("![$x$](a.png)"), "<p><img src=\"a.png\" alt=\"$x$\" /></p>\n");
+        assert_eq!(render("[a](/x \"cost $y$\")"), "<p><a href=\"/x\" title=\"cost $y$\">a</a></p>\n");
+    }
+}
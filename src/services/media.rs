@@ -0,0 +1,389 @@
+use rand::Rng;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MediaError {
+    #[error("File size too large: {0} bytes")]
+    TooLarge(usize),
+    #[error("Empty file")]
+    Empty,
+    #[error("Storage error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A file written by `MediaStorage::store`
+#[derive(Debug)]
+pub struct StoredFile {
+    /// Random name on disk, also the last segment of the public URL
+    pub stored_name: String,
+    pub content_type: &'static str,
+    pub size: usize,
+}
+
+/// Uploaded files kept on the local filesystem under `MEDIA_DIR` and served at `/media/{name}`
+pub struct MediaStorage {
+    root: PathBuf,
+    max_size: usize,
+}
+
+/// MIME types by extension; anything else is served as a download
+const CONTENT_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("ico", "image/x-icon"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("txt", "text/plain; charset=utf-8"),
+    ("md", "text/markdown; charset=utf-8"),
+    ("csv", "text/csv; charset=utf-8"),
+    ("ppt", "application/vnd.ms-powerpoint"),
+    ("pptx", "application/vnd.openxmlformats-officedocument.presentationml.presentation"),
+    ("doc", "application/msword"),
+    ("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+    ("xls", "application/vnd.ms-excel"),
+    ("xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+    ("odp", "application/vnd.oasis.opendocument.presentation"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+];
+
+/// Lowercased extension of an uploaded file name, if it is short and alphanumeric
+pub fn extension(filename: &str) -> Option<String> {
+    let (_, ext) = filename.rsplit_once('.')?;
+    let ext = ext.to_ascii_lowercase();
+    (!ext.is_empty() && ext.len() <= 8 && ext.chars().all(|c| c.is_ascii_alphanumeric())).then_some(ext)
+}
+
+pub fn content_type_for(filename: &str) -> &'static str {
+    extension(filename)
+        .and_then(|ext| CONTENT_TYPES.iter().find(|(e, _)| *e == ext).map(|(_, t)| *t))
+        .unwrap_or("application/octet-stream")
+}
+
+/// Extension for an image MIME type, used for pasted blobs that arrive without a usable file name
+pub fn image_extension_for(content_type: &str) -> Option<&'static str> {
+    CONTENT_TYPES
+        .iter()
+        .find(|(_, t)| t.starts_with("image/") && *t == content_type)
+        .map(|(e, _)| *e)
+}
+
+/// Alt text for an uploaded image: the file name without its extension, minus
+/// characters that would break the markdown image syntax
+pub fn image_alt_text(filename: &str) -> String {
+    let stem = filename.rsplit_once('.').map_or(filename, |(stem, _)| stem);
+    let alt: String = stem.chars().filter(|c| !matches!(c, '[' | ']' | '\\' | '\n' | '\r')).collect();
+    let alt = alt.trim();
+    if alt.is_empty() || alt == "blob" { "image".to_string() } else { alt.to_string() }
+}
+
+/// File size for display, e.g. "1.5 MB"
+pub fn human_size(bytes: i64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes.max(0) as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes.max(0))
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Images and PDFs open in the browser; everything else is downloaded
+pub fn is_inline(content_type: &str) -> bool {
+    content_type.starts_with("image/") || content_type == "application/pdf"
+}
+
+/// Names produced by `store`: hex plus an optional extension, so they never escape the media directory
+pub fn is_valid_stored_name(name: &str) -> bool {
+    let (stem, ext) = name.split_once('.').unwrap_or((name, ""));
+    stem.len() == 32
+        && stem.chars().all(|c| c.is_ascii_hexdigit())
+        && ext.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Uploads younger than this are never reported as unused, since the editor
+/// uploads images before the article that uses them is saved
+pub const ORPHAN_MIN_AGE_HOURS: i64 = 24;
+
+/// Stored names of uploads referenced as `/media/<name>` anywhere in `texts`:
+/// markdown links and images, raw HTML, absolute URLs or profile fields
+pub fn referenced_names<'t>(texts: impl IntoIterator<Item = &'t str>) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for text in texts {
+        for (start, _) in text.match_indices("/media/") {
+            let rest = &text[start + "/media/".len()..];
+            let end = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '.').unwrap_or(rest.len());
+            // 句末的点不属于文件名
+            let name = rest[..end].trim_end_matches('.');
+            if is_valid_stored_name(name) {
+                names.insert(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Site-wide images picked on the settings page and served at fixed URLs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrandAsset {
+    Favicon,
+    Logo,
+}
+
+impl BrandAsset {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "favicon" => Some(BrandAsset::Favicon),
+            "logo" => Some(BrandAsset::Logo),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BrandAsset::Favicon => "favicon",
+            BrandAsset::Logo => "logo",
+        }
+    }
+
+    /// Site setting holding the stored name of the current file
+    pub fn setting_key(&self) -> &'static str {
+        match self {
+            BrandAsset::Favicon => "favicon_media",
+            BrandAsset::Logo => "logo_media",
+        }
+    }
+
+    /// Stable public path; `Branding::url` adds a version so a new upload is fetched again
+    pub fn path(&self) -> &'static str {
+        match self {
+            BrandAsset::Favicon => "/favicon.ico",
+            BrandAsset::Logo => "/logo",
+        }
+    }
+
+    /// Whether an upload with this file name can be used; SVG stays out as it can carry scripts
+    pub fn accepts(&self, filename: &str) -> bool {
+        let allowed: &[&str] = match self {
+            BrandAsset::Favicon => &["ico", "png", "gif"],
+            BrandAsset::Logo => &["png", "jpg", "jpeg", "gif", "webp", "avif"],
+        };
+        extension(filename).is_some_and(|ext| allowed.contains(&ext.as_str()))
+    }
+}
+
+/// Stored names of the uploaded favicon and logo
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Branding {
+    pub favicon: Option<String>,
+    pub logo: Option<String>,
+}
+
+impl Branding {
+    pub fn from_settings(settings: &HashMap<String, String>) -> Self {
+        let stored = |asset: BrandAsset| {
+            settings
+                .get(asset.setting_key())
+                .filter(|name| is_valid_stored_name(name))
+                .cloned()
+        };
+        Self {
+            favicon: stored(BrandAsset::Favicon),
+            logo: stored(BrandAsset::Logo),
+        }
+    }
+
+    pub fn get(&self, asset: BrandAsset) -> Option<&str> {
+        match asset {
+            BrandAsset::Favicon => self.favicon.as_deref(),
+            BrandAsset::Logo => self.logo.as_deref(),
+        }
+    }
+
+    /// Public URL of an uploaded asset, versioned by its stored name
+    pub fn url(&self, asset: BrandAsset) -> Option<String> {
+        self.get(asset).map(|name| format!("{}?v={}", asset.path(), &name[..8]))
+    }
+}
+
+impl MediaStorage {
+    pub fn new(root: impl Into<PathBuf>, max_size: usize) -> Self {
+        Self { root: root.into(), max_size }
+    }
+
+    pub fn from_env() -> Self {
+        let root = std::env::var("MEDIA_DIR").unwrap_or_else(|_| "./data/media".to_string());
+        let max_size = std::env::var("FILE_UPLOAD_MAX_SIZE")
+            .unwrap_or_else(|_| "5242880".to_string())
+            .parse()
+            .unwrap_or(5242880);
+        Self::new(root, max_size)
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Public URL of a stored file
+    pub fn url(stored_name: &str) -> String {
+        format!("/media/{}", stored_name)
+    }
+
+    /// Path on disk, None for names `store` could not have produced
+    pub fn path_for(&self, stored_name: &str) -> Option<PathBuf> {
+        is_valid_stored_name(stored_name).then(|| self.root.join(stored_name))
+    }
+
+    pub async fn store(&self, original_name: &str, bytes: &[u8]) -> Result<StoredFile, MediaError> {
+        if bytes.is_empty() {
+            return Err(MediaError::Empty);
+        }
+        if bytes.len() > self.max_size {
+            return Err(MediaError::TooLarge(bytes.len()));
+        }
+        let token: [u8; 16] = rand::thread_rng().r#gen();
+        let stored_name = match extension(original_name) {
+            Some(ext) => format!("{}.{}", hex::encode(token), ext),
+            None => hex::encode(token),
+        };
+        tokio::fs::create_dir_all(&self.root).await?;
+        tokio::fs::write(self.root.join(&stored_name), bytes).await?;
+        Ok(StoredFile {
+            stored_name,
+            content_type: content_type_for(original_name),
+            size: bytes.len(),
+        })
+    }
+
+    /// Names of every stored file, for site exports; an absent directory is empty
+    pub async fn stored_names(&self) -> Result<Vec<String>, MediaError> {
+        let mut entries = match tokio::fs::read_dir(&self.root).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(name) = entry.file_name().to_str()
+                && is_valid_stored_name(name)
+            {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Write a file under the name it had on another server, for site imports
+    pub async fn restore(&self, stored_name: &str, bytes: &[u8]) -> Result<(), MediaError> {
+        let Some(path) = self.path_for(stored_name) else {
+            return Ok(());
+        };
+        tokio::fs::create_dir_all(&self.root).await?;
+        tokio::fs::write(path, bytes).await?;
+        Ok(())
+    }
+
+    /// Remove a stored file; a file that is already gone is not an error
+    pub async fn delete(&self, stored_name: &str) -> Result<(), MediaError> {
+        let Some(path) = self.path_for(stored_name) else {
+            return Ok(());
+        };
+        match tokio::fs::remove_file(path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_type_for() {
+        assert_eq!(content_type_for("Slides.PDF"), "application/pdf");
+        assert_eq!(content_type_for("photo.jpeg"), "image/jpeg");
+        assert_eq!(content_type_for("page.html"), "application/octet-stream");
+        assert_eq!(content_type_for("logo.svg"), "application/octet-stream");
+        assert_eq!(content_type_for("README"), "application/octet-stream");
+        assert!(is_inline("image/png"));
+        assert!(!is_inline("application/zip"));
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(1536 * 1024), "1.5 MB");
+    }
+
+    #[test]
+    fn test_stored_names() {
+        assert!(is_valid_stored_name("0123456789abcdef0123456789abcdef.pdf"));
+        assert!(is_valid_stored_name("0123456789abcdef0123456789abcdef"));
+        assert!(!is_valid_stored_name("../0123456789abcdef0123456789abcd"));
+        assert!(!is_valid_stored_name("0123456789abcdef0123456789abcdef.p/f"));
+        assert_eq!(extension("archive.tar.GZ").as_deref(), Some("gz"));
+        assert_eq!(extension("weird.<script>"), None);
+    }
+
+    #[test]
+    fn test_branding() {
+        assert!(BrandAsset::Favicon.accepts("icon.ICO"));
+        assert!(!BrandAsset::Favicon.accepts("photo.jpg"));
+        assert!(BrandAsset::Logo.accepts("logo.webp"));
+        assert!(!BrandAsset::Logo.accepts("logo.svg"));
+
+        let settings: HashMap<String, String> = [
+            ("logo_media".to_string(), "0123456789abcdef0123456789abcdef.png".to_string()),
+            ("favicon_media".to_string(), "../etc/passwd".to_string()),
+        ]
+        .into();
+        let branding = Branding::from_settings(&settings);
+        assert_eq!(branding.url(BrandAsset::Logo).as_deref(), Some("/logo?v=01234567"));
+        assert_eq!(branding.url(BrandAsset::Favicon), None);
+    }
+
+    #[test]
+    fn test_referenced_names() {
+        let a = "0123456789abcdef0123456789abcdef";
+        let b = "fedcba9876543210fedcba9876543210";
+        let markdown = format!("![x](/media/{a}.png) and <img src=\"https://blog.example/media/{b}.jpg\">.");
+        let names = referenced_names([markdown.as_str(), "See /media/not-a-name.png", ""]);
+        assert_eq!(names, HashSet::from([format!("{a}.png"), format!("{b}.jpg")]));
+        assert!(referenced_names([format!("Download /media/{a}.").as_str()]).contains(a));
+    }
+
+    #[test]
+    fn test_image_helpers() {
+        assert_eq!(image_extension_for("image/jpeg"), Some("jpg"));
+        assert_eq!(image_extension_for("application/pdf"), None);
+        assert_eq!(image_alt_text("Screen [1].png"), "Screen 1");
+        assert_eq!(image_alt_text("blob"), "image");
+    }
+
+    #[tokio::test]
+    async fn test_store_and_delete() {
+        let root = std::env::temp_dir().join(format!("bluster-media-{}", std::process::id()));
+        let storage = MediaStorage::new(&root, 4);
+        assert!(matches!(storage.store("a.txt", b"").await, Err(MediaError::Empty)));
+        assert!(matches!(storage.store("a.txt", b"12345").await, Err(MediaError::TooLarge(5))));
+
+        let stored = storage.store("notes.txt", b"1234").await.unwrap();
+        assert!(stored.stored_name.ends_with(".txt"));
+        let path = storage.path_for(&stored.stored_name).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"1234");
+
+        storage.delete(&stored.stored_name).await.unwrap();
+        assert!(!path.exists());
+        storage.delete(&stored.stored_name).await.unwrap();
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -1,5 +1,62 @@
 pub mod markdown;
 pub mod file;
+pub mod email;
+pub mod rate_limit;
+pub mod client_ip;
+pub mod comment;
+pub mod signing;
+pub mod avatar;
+pub mod spam;
+pub mod content_template;
+pub mod sitemap;
+pub mod ping;
+pub mod feed;
+pub mod toc;
+pub mod oembed;
+pub mod short_link;
+pub mod media;
+pub mod validation;
+pub mod diff;
+pub mod archive;
+pub mod response_cache;
+pub mod summary;
+pub mod tasks;
+pub mod definition_list;
+pub mod ansi;
+pub mod text;
+pub mod well_known;
+pub mod analytics;
+pub mod activity;
+pub mod trash;
+pub mod math;
+pub mod render_options;
+pub mod author;
+pub mod navigation;
+pub mod article_filter;
+pub mod replace;
+pub mod html_markdown;
+pub mod link_graph;
+pub mod search;
+pub mod site_archive;
+pub mod cache_policy;
+pub mod feature_flags;
+pub mod theme;
+pub mod canonical;
+pub mod outbound;
+pub mod dates;
+pub mod duplicates;
+pub mod demo;
+pub mod tags;
+pub mod categories;
+pub mod render_pipeline;
+pub mod plugins;
+pub mod webhook;
+pub mod events;
+pub mod pagination;
+pub mod slugs;
 
 pub use markdown::MarkdownService;
-pub use file::FileService;
\ No newline at end of file
+pub use file::FileService;
+pub use email::EmailService;
+pub use rate_limit::{DuplicateFilter, RateLimiter};
+pub use signing::Signer;
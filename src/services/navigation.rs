@@ -0,0 +1,63 @@
+//! Articles pinned to the site navigation menu. Each menu item is bound to an
+//! article and disappears with it when the article leaves the site.
+
+use serde::Serialize;
+
+/// Longest menu label, in characters
+pub const MAX_LABEL_LENGTH: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NavLink {
+    pub label: String,
+    pub url: String,
+}
+
+impl NavLink {
+    pub fn for_article(article_id: i64, label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            url: format!("/post/{}", article_id),
+        }
+    }
+}
+
+/// Menu label for a pinned article: the one given, or the article title cut to fit
+pub fn menu_label(label: Option<&str>, title: &str) -> Result<String, String> {
+    match label.map(str::trim).filter(|l| !l.is_empty()) {
+        Some(label) if label.chars().count() > MAX_LABEL_LENGTH => {
+            Err(format!("Menu label must be at most {} characters", MAX_LABEL_LENGTH))
+        }
+        Some(label) => Ok(label.to_string()),
+        None => {
+            let title = title.trim();
+            if title.chars().count() <= MAX_LABEL_LENGTH {
+                return Ok(title.to_string());
+            }
+            let cut: String = title.chars().take(MAX_LABEL_LENGTH - 1).collect();
+            Ok(format!("{}…", cut.trim_end()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_menu_label() {
+        assert_eq!(menu_label(Some(" Projects "), "My projects so far").unwrap(), "Projects");
+        assert_eq!(menu_label(None, "Now").unwrap(), "Now");
+        assert_eq!(menu_label(Some(""), "Uses").unwrap(), "Uses");
+
+        let long_title = "A very long article title that does not fit the menu";
+        let label = menu_label(None, long_title).unwrap();
+        assert_eq!(label.chars().count(), MAX_LABEL_LENGTH);
+        assert!(label.ends_with('…'));
+        assert!(menu_label(Some(long_title), "x").is_err());
+    }
+
+    #[test]
+    fn test_nav_link() {
+        assert_eq!(NavLink::for_article(7, "Now"), NavLink { label: "Now".to_string(), url: "/post/7".to_string() });
+    }
+}
@@ -0,0 +1,85 @@
+use html_escape::{encode_double_quoted_attribute, encode_text};
+use serde::Serialize;
+
+const DEFAULT_WIDTH: u32 = 600;
+const DEFAULT_HEIGHT: u32 = 200;
+
+/// oEmbed 1.0 "rich" response (https://oembed.com/)
+#[derive(Debug, Serialize)]
+pub struct OEmbedResponse {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub version: &'static str,
+    pub title: String,
+    pub author_name: String,
+    pub provider_name: String,
+    pub provider_url: String,
+    pub html: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The slug or id in one of our own post URLs (`{site_url}/post/{slug}`),
+/// decoded and lowercased, ignoring the scheme, query and fragment
+pub fn post_key_from_url(site_url: &str, url: &str) -> Option<String> {
+    fn strip_scheme(u: &str) -> &str {
+        u.strip_prefix("https://").or_else(|| u.strip_prefix("http://")).unwrap_or(u)
+    }
+    let rest = strip_scheme(url.trim()).strip_prefix(strip_scheme(site_url))?;
+    let rest = rest.strip_prefix("/post/")?;
+    let key = rest.split(['?', '#']).next()?.trim_end_matches('/');
+    // /post/{id}/lite 等子页面不算
+    (!key.is_empty() && !key.contains('/')).then(|| super::analytics::percent_decode(key).to_lowercase())
+}
+
+/// Embeddable card: linked title followed by the excerpt
+pub fn embed_html(title: &str, link: &str, excerpt: &str, provider_name: &str) -> String {
+    format!(
+        "<blockquote class=\"bluster-embed\"><p><a href=\"{link}\">{title}</a></p><p>{excerpt}</p><p>— {provider}</p></blockquote>",
+        link = encode_double_quoted_attribute(link),
+        title = encode_text(title),
+        excerpt = encode_text(excerpt),
+        provider = encode_text(provider_name),
+    )
+}
+
+/// Width honouring the consumer's `maxwidth`, height scaled to keep the aspect
+pub fn embed_size(maxwidth: Option<u32>, maxheight: Option<u32>) -> (u32, u32) {
+    let width = maxwidth.map_or(DEFAULT_WIDTH, |max| max.min(DEFAULT_WIDTH));
+    let height = (DEFAULT_HEIGHT * width / DEFAULT_WIDTH).max(1);
+    (width, maxheight.map_or(height, |max| max.min(height)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_post_key_from_url() {
+        let site = "https://blog.example.com";
+        let key = |url| post_key_from_url(site, url);
+        assert_eq!(key("https://blog.example.com/post/12").as_deref(), Some("12"));
+        assert_eq!(key("http://blog.example.com/post/12/?ref=x#comments").as_deref(), Some("12"));
+        assert_eq!(key("https://blog.example.com/post/Hello-World").as_deref(), Some("hello-world"));
+        assert_eq!(key("https://blog.example.com/post/%E7%BC%96%E7%A8%8B").as_deref(), Some("编程"));
+        assert_eq!(key("https://other.example.com/post/12"), None);
+        assert_eq!(key("https://blog.example.com/about"), None);
+        assert_eq!(key("https://blog.example.com/post/"), None);
+        assert_eq!(key("https://blog.example.com/post/12/lite"), None);
+    }
+
+    #[test]
+    fn test_embed_html_escapes() {
+        let html = embed_html("A <b> & C", "https://b.example/post/1?a=\"x\"", "1 < 2", "Blog");
+        assert!(html.contains("A &lt;b&gt; &amp; C"));
+        assert!(html.contains("href=\"https://b.example/post/1?a=&quot;x&quot;\""));
+        assert!(html.contains("1 &lt; 2"));
+    }
+
+    #[test]
+    fn test_embed_size() {
+        assert_eq!(embed_size(None, None), (600, 200));
+        assert_eq!(embed_size(Some(300), None), (300, 100));
+        assert_eq!(embed_size(Some(900), Some(50)), (600, 50));
+    }
+}
@@ -0,0 +1,95 @@
+//! Click tracking for links to other sites. When the feature is on, external
+//! links in a rendered article point at `/out`, which counts the click per
+//! target and redirects. The target is signed so `/out` is not an open redirect.
+
+use super::analytics::host_of;
+use super::avatar::url_encode;
+use super::Signer;
+
+fn payload(url: &str, article_id: i64) -> String {
+    format!("out:{}:{}", article_id, url)
+}
+
+/// `/out` link for a click on `url` in the article
+pub fn tracked_href(signer: &Signer, url: &str, article_id: i64) -> String {
+    format!(
+        "/out?url={}&article={}&sig={}",
+        url_encode(url),
+        article_id,
+        signer.digest(&payload(url, article_id))
+    )
+}
+
+/// Whether `sig` was issued by `tracked_href` for this target and article
+pub fn verify(signer: &Signer, url: &str, article_id: i64, sig: &str) -> bool {
+    signer.verify_digest(&payload(url, article_id), sig)
+}
+
+/// Point the `http(s)` links in rendered HTML that leave `site_host` at `/out`
+pub fn rewrite_links(html: &str, site_host: &str, signer: &Signer, article_id: i64) -> String {
+    const HREF: &str = "href=\"";
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<a ") {
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        let (before, tag) = (&rest[..start], &rest[start..start + len]);
+        result.push_str(before);
+        rest = &rest[start + len..];
+
+        let Some(href_start) = tag.find(HREF).map(|i| i + HREF.len()) else {
+            result.push_str(tag);
+            continue;
+        };
+        let Some(href_len) = tag[href_start..].find('"') else {
+            result.push_str(tag);
+            continue;
+        };
+        let url = html_escape::decode_html_entities(&tag[href_start..href_start + href_len]);
+        match host_of(&url).filter(|host| host != site_host) {
+            Some(_) => {
+                result.push_str(&tag[..href_start]);
+                result.push_str(&html_escape::encode_double_quoted_attribute(&tracked_href(signer, &url, article_id)));
+                result.push_str(&tag[href_start + href_len..]);
+            }
+            None => result.push_str(tag),
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracked_href() {
+        let signer = Signer::new(b"secret");
+        let href = tracked_href(&signer, "https://example.com/a?b=1&c=2", 7);
+        assert!(href.starts_with("/out?url=https%3A%2F%2Fexample.com%2Fa%3Fb%3D1%26c%3D2&article=7&sig="));
+        let sig = href.rsplit("sig=").next().unwrap();
+        assert!(verify(&signer, "https://example.com/a?b=1&c=2", 7, sig));
+        assert!(!verify(&signer, "https://evil.example/", 7, sig));
+        assert!(!verify(&signer, "https://example.com/a?b=1&c=2", 8, sig));
+        assert!(!verify(&Signer::new(b"other"), "https://example.com/a?b=1&c=2", 7, sig));
+    }
+
+    #[test]
+    fn test_rewrite_links() {
+        let signer = Signer::new(b"secret");
+        let html = concat!(
+            r#"<p><a href="https://example.com/x?a=1&amp;b=2" rel="noopener noreferrer" target="_blank">ext</a> "#,
+            r##"<a href="/post/2">local</a> <a href="https://blog.example.com/post/3">self</a> <a href="#top">top</a></p>"##
+        );
+        let rewritten = rewrite_links(html, "blog.example.com", &signer, 1);
+        let expected = html_escape::encode_double_quoted_attribute(&tracked_href(&signer, "https://example.com/x?a=1&b=2", 1))
+            .into_owned();
+        assert!(rewritten.starts_with(&format!(r#"<p><a href="{}" rel="noopener noreferrer" target="_blank">ext</a>"#, expected)));
+        assert!(rewritten.ends_with(
+            r##"<a href="/post/2">local</a> <a href="https://blog.example.com/post/3">self</a> <a href="#top">top</a></p>"##
+        ));
+        assert_eq!(rewrite_links("<a href=\"https://x.example/", "b", &signer, 1), "<a href=\"https://x.example/");
+    }
+}
@@ -0,0 +1,129 @@
+//! Splitting article lists into pages. HTML lists are numbered (`?page=2`),
+//! the JSON list can also be walked with an opaque cursor, which keeps its
+//! place when articles are published while a client is paging.
+
+use serde::Serialize;
+
+/// The page of a numbered list
+#[derive(Debug, Clone, Serialize)]
+pub struct Pagination {
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+    pub total_pages: i64,
+    /// Links to the neighbouring pages, None on the first / last
+    pub prev_url: Option<String>,
+    pub next_url: Option<String>,
+}
+
+impl Pagination {
+    /// `page` and `per_page` are clamped; a `per_page` equal to the default is
+    /// left out of the links. `path` may carry a query string of its own.
+    pub fn new(path: &str, page: Option<i64>, per_page: Option<i64>, default_per_page: i64, max_per_page: i64, total: i64) -> Self {
+        let page = page.unwrap_or(1).max(1);
+        let per_page = per_page.unwrap_or(default_per_page).clamp(1, max_per_page);
+        let total_pages = ((total + per_page - 1) / per_page).max(1);
+        let url = |page: i64| {
+            let mut url = path.to_string();
+            let mut params = Vec::new();
+            if page > 1 {
+                params.push(format!("page={}", page));
+            }
+            if per_page != default_per_page {
+                params.push(format!("per_page={}", per_page));
+            }
+            if !params.is_empty() {
+                url.push(if path.contains('?') { '&' } else { '?' });
+                url.push_str(&params.join("&"));
+            }
+            url
+        };
+        Self {
+            page,
+            per_page,
+            total,
+            total_pages,
+            prev_url: (page > 1).then(|| url((page - 1).min(total_pages))),
+            next_url: (page < total_pages).then(|| url(page + 1)),
+        }
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page - 1).saturating_mul(self.per_page)
+    }
+
+    /// Past the last page; the first page always exists, even when empty
+    pub fn is_out_of_range(&self) -> bool {
+        self.page > self.total_pages
+    }
+}
+
+/// Position after an article in a newest-first list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: String,
+    pub id: i64,
+}
+
+impl Cursor {
+    pub fn new(created_at: &str, id: i64) -> Self {
+        Self { created_at: created_at.to_string(), id }
+    }
+
+    /// Opaque to clients; they only send back what they were given
+    pub fn encode(&self) -> String {
+        hex::encode(format!("{}|{}", self.id, self.created_at))
+    }
+
+    pub fn decode(token: &str) -> Option<Self> {
+        let text = String::from_utf8(hex::decode(token).ok()?).ok()?;
+        let (id, created_at) = text.split_once('|')?;
+        // 存储格式 "YYYY-MM-DD HH:MM:SS"，其余内容一律视为无效
+        chrono::NaiveDateTime::parse_from_str(created_at, "%Y-%m-%d %H:%M:%S").ok()?;
+        Some(Self { created_at: created_at.to_string(), id: id.parse().ok()? })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages() {
+        let first = Pagination::new("/", None, None, 10, 50, 25);
+        assert_eq!((first.page, first.per_page, first.total_pages, first.offset()), (1, 10, 3, 0));
+        assert_eq!(first.prev_url, None);
+        assert_eq!(first.next_url.as_deref(), Some("/?page=2"));
+
+        let second = Pagination::new("/", Some(2), Some(10), 10, 50, 25);
+        assert_eq!(second.prev_url.as_deref(), Some("/"));
+        assert_eq!(second.offset(), 10);
+
+        let custom = Pagination::new("/", Some(2), Some(500), 10, 50, 120);
+        assert_eq!(custom.per_page, 50);
+        assert_eq!(custom.prev_url.as_deref(), Some("/?per_page=50"));
+        assert_eq!(custom.next_url.as_deref(), Some("/?page=3&per_page=50"));
+
+        let empty = Pagination::new("/", Some(0), Some(0), 10, 50, 0);
+        assert_eq!((empty.page, empty.per_page, empty.total_pages), (1, 1, 1));
+        assert!(!empty.is_out_of_range());
+        let beyond = Pagination::new("/", Some(9), None, 10, 50, 25);
+        assert!(beyond.is_out_of_range());
+        assert_eq!(beyond.prev_url.as_deref(), Some("/?page=3"));
+        assert_eq!(beyond.next_url, None);
+
+        let search = Pagination::new("/search?q=rust", Some(2), None, 10, 50, 30);
+        assert_eq!(search.prev_url.as_deref(), Some("/search?q=rust"));
+        assert_eq!(search.next_url.as_deref(), Some("/search?q=rust&page=3"));
+    }
+
+    #[test]
+    fn test_cursor() {
+        let cursor = Cursor::new("2024-03-05 08:09:10", 42);
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(Cursor::decode("zz"), None);
+        assert_eq!(Cursor::decode(&hex::encode("42")), None);
+        assert_eq!(Cursor::decode(&hex::encode("x|2024-03-05 08:09:10")), None);
+        assert_eq!(Cursor::decode(&hex::encode("1|yesterday")), None);
+    }
+}
@@ -0,0 +1,116 @@
+use reqwest::Url;
+
+/// Feed paths announced to the WebSub hub as topics
+pub const FEED_PATHS: &[&str] = &["/feed.xml", "/atom.xml"];
+
+/// Outcome of notifying one endpoint
+#[derive(Debug)]
+pub struct PingResult {
+    pub endpoint: String,
+    /// HTTP status on success, error message otherwise
+    pub outcome: Result<u16, String>,
+}
+
+/// Tells search engines and the WebSub hub that new content was published
+#[derive(Clone)]
+pub struct Pinger {
+    client: reqwest::Client,
+    site_url: String,
+    // e.g. https://www.bing.com/ping, called with ?sitemap=<url>
+    sitemap_endpoints: Vec<String>,
+    websub_hub: Option<String>,
+}
+
+impl Pinger {
+    pub fn new(site_url: &str, sitemap_endpoints: Vec<String>, websub_hub: Option<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .user_agent(concat!("Bluster/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            site_url: site_url.trim_end_matches('/').to_string(),
+            sitemap_endpoints,
+            websub_hub,
+        }
+    }
+
+    /// Reads `PING_SITEMAP_ENDPOINTS` (comma separated) and `WEBSUB_HUB`; disabled when both are empty
+    pub fn from_env(site_url: &str) -> Option<Self> {
+        let sitemap_endpoints: Vec<String> = std::env::var("PING_SITEMAP_ENDPOINTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|e| e.trim().to_string())
+            .filter(|e| !e.is_empty())
+            .collect();
+        let websub_hub = std::env::var("WEBSUB_HUB")
+            .ok()
+            .map(|h| h.trim().to_string())
+            .filter(|h| !h.is_empty());
+        if sitemap_endpoints.is_empty() && websub_hub.is_none() {
+            return None;
+        }
+        log::info!(
+            "Publish pings enabled: {} sitemap endpoint(s), WebSub hub: {}",
+            sitemap_endpoints.len(),
+            websub_hub.as_deref().unwrap_or("none")
+        );
+        Some(Self::new(site_url, sitemap_endpoints, websub_hub))
+    }
+
+    pub fn websub_hub(&self) -> Option<&str> {
+        self.websub_hub.as_deref()
+    }
+
+    /// Endpoint URL with the absolute sitemap URL appended as the `sitemap` query parameter
+    fn sitemap_ping_url(endpoint: &str, sitemap_url: &str) -> Result<Url, String> {
+        let mut url = Url::parse(endpoint).map_err(|e| format!("invalid endpoint: {}", e))?;
+        url.query_pairs_mut().append_pair("sitemap", sitemap_url);
+        Ok(url)
+    }
+
+    /// Notify every configured endpoint; failures are reported, never retried
+    pub async fn ping_all(&self) -> Vec<PingResult> {
+        let mut results = Vec::new();
+        let sitemap_url = format!("{}/sitemap.xml", self.site_url);
+        for endpoint in &self.sitemap_endpoints {
+            let outcome = match Self::sitemap_ping_url(endpoint, &sitemap_url) {
+                Ok(url) => self.send(self.client.get(url)).await,
+                Err(e) => Err(e),
+            };
+            results.push(PingResult { endpoint: endpoint.clone(), outcome });
+        }
+        if let Some(hub) = &self.websub_hub {
+            for path in FEED_PATHS {
+                let topic = format!("{}{}", self.site_url, path);
+                let params = [("hub.mode", "publish"), ("hub.url", topic.as_str())];
+                let outcome = self.send(self.client.post(hub).form(&params)).await;
+                results.push(PingResult { endpoint: format!("{} ({})", hub, topic), outcome });
+            }
+        }
+        results
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<u16, String> {
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(status.as_u16())
+        } else {
+            Err(format!("HTTP {}", status.as_u16()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sitemap_ping_url() {
+        let url = Pinger::sitemap_ping_url("https://www.bing.com/ping", "https://blog.example.com/sitemap.xml").unwrap();
+        assert_eq!(url.as_str(), "https://www.bing.com/ping?sitemap=https%3A%2F%2Fblog.example.com%2Fsitemap.xml");
+        assert!(Pinger::sitemap_ping_url("not a url", "https://blog.example.com/sitemap.xml").is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! Plugins: features that hook into the server without being part of it, such
+//! as analytics or federation. A plugin implements `Plugin` and can
+//!
+//! - add routes, after the core routes (so it cannot replace them)
+//! - add render stages to the Markdown pipeline (see `render_pipeline`)
+//! - be told when an article is published or moved to the trash
+//!
+//! Plugins are compiled in and listed with a factory, like `BUILTIN_PLUGINS`;
+//! a plugin from another crate is added to the list passed to
+//! `PluginRegistry::from_env`. Only the plugins named in `PLUGINS` (comma
+//! separated) are loaded at startup, each configuring itself from its own
+//! environment variables.
+
+use super::markdown::MarkdownService;
+use super::render_pipeline::{RenderStage, StagePosition};
+use actix_web::web;
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PluginError {
+    #[error("No plugin named {0}")]
+    Unknown(String),
+    #[error("A plugin named {0} is already loaded")]
+    Duplicate(String),
+    #[error("Plugin is not configured: {0}")]
+    Config(String),
+    #[error("{0}")]
+    Failed(String),
+}
+
+/// Settings of the server a plugin may need when it is created
+#[derive(Debug, Clone)]
+pub struct PluginConfig {
+    /// Public base URL, without trailing slash
+    pub site_url: String,
+}
+
+/// Creates a plugin, or explains why it cannot run with this configuration
+pub type PluginFactory = fn(&PluginConfig) -> Result<Arc<dyn Plugin>, PluginError>;
+
+/// Plugins that ship with the server, by name
+pub const BUILTIN_PLUGINS: &[(&str, PluginFactory)] = &[(super::webhook::NAME, super::webhook::factory)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentEventKind {
+    /// The article went public: created, its draft published, or restored from the trash
+    Publish,
+    /// The article was moved to the trash
+    Delete,
+}
+
+/// What happened to which article
+#[derive(Debug, Clone, Serialize)]
+pub struct ContentEvent {
+    #[serde(rename = "event")]
+    pub kind: ContentEventKind,
+    pub article_id: i64,
+    /// Absolute URL of the article's page
+    pub url: String,
+}
+
+impl ContentEvent {
+    pub fn new(kind: ContentEventKind, article_id: i64, site_url: &str) -> Self {
+        Self { kind, article_id, url: format!("{}/post/{}", site_url.trim_end_matches('/'), article_id) }
+    }
+}
+
+/// A feature loaded at startup. Every hook does nothing by default, so a
+/// plugin implements only those it needs.
+pub trait Plugin: Send + Sync {
+    /// Unique among loaded plugins; the name `PLUGINS` refers to it by
+    fn name(&self) -> &str;
+
+    /// Add routes; called once for each worker. The database pool is
+    /// available to handlers as `web::Data<SqlitePool>`.
+    fn configure(&self, _cfg: &mut web::ServiceConfig) {}
+
+    /// Stages added to the render pipeline, in order
+    fn render_stages(&self) -> Vec<(StagePosition<'static>, Arc<dyn RenderStage>)> {
+        Vec::new()
+    }
+
+    /// Called in the background after an article is published
+    fn on_publish<'a>(&'a self, _pool: &'a SqlitePool, _event: &'a ContentEvent) -> BoxFuture<'a, Result<(), PluginError>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Called in the background after an article is moved to the trash
+    fn on_delete<'a>(&'a self, _pool: &'a SqlitePool, _event: &'a ContentEvent) -> BoxFuture<'a, Result<(), PluginError>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// The loaded plugins, in load order
+#[derive(Clone, Default)]
+pub struct PluginRegistry {
+    plugins: Vec<Arc<dyn Plugin>>,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the plugins named in `PLUGINS` from `available`
+    pub fn from_env(available: &[(&str, PluginFactory)], config: &PluginConfig) -> Self {
+        let names = std::env::var("PLUGINS").unwrap_or_default();
+        let names: Vec<&str> = names.split(',').map(str::trim).filter(|n| !n.is_empty()).collect();
+        let registry = Self::load(&names, available, config);
+        if !registry.is_empty() {
+            log::info!("Plugins loaded: {}", registry.names().join(", "));
+        }
+        registry
+    }
+
+    /// Load the named plugins; one that is unknown or fails to start is logged and left out
+    pub fn load(names: &[&str], available: &[(&str, PluginFactory)], config: &PluginConfig) -> Self {
+        let mut registry = Self::new();
+        for name in names {
+            let result = available
+                .iter()
+                .find(|(available_name, _)| available_name == name)
+                .ok_or_else(|| PluginError::Unknown(name.to_string()))
+                .and_then(|(_, factory)| factory(config))
+                .and_then(|plugin| registry.register(plugin));
+            if let Err(e) = result {
+                log::error!("Failed to load plugin {}: {}", name, e);
+            }
+        }
+        registry
+    }
+
+    pub fn register(&mut self, plugin: Arc<dyn Plugin>) -> Result<(), PluginError> {
+        if self.plugins.iter().any(|p| p.name() == plugin.name()) {
+            return Err(PluginError::Duplicate(plugin.name().to_string()));
+        }
+        self.plugins.push(plugin);
+        Ok(())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.plugins.iter().map(|p| p.name()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Add every plugin's routes
+    pub fn configure(&self, cfg: &mut web::ServiceConfig) {
+        for plugin in &self.plugins {
+            plugin.configure(cfg);
+        }
+    }
+
+    /// Add every plugin's render stages; a stage that cannot be placed is
+    /// logged and skipped. Returns the number of stages added.
+    pub fn install_render_stages(&self, markdown: &mut MarkdownService) -> usize {
+        let mut installed = 0;
+        for plugin in &self.plugins {
+            for (position, stage) in plugin.render_stages() {
+                let stage_name = stage.name().to_string();
+                match markdown.register_stage(position, stage) {
+                    Ok(()) => installed += 1,
+                    Err(e) => log::error!("Plugin {} could not add render stage {}: {}", plugin.name(), stage_name, e),
+                }
+            }
+        }
+        installed
+    }
+
+    /// Call the matching hook of every plugin in turn; failures are logged
+    /// and do not stop the others
+    pub async fn dispatch(&self, pool: &SqlitePool, event: &ContentEvent) {
+        for plugin in &self.plugins {
+            let result = match event.kind {
+                ContentEventKind::Publish => plugin.on_publish(pool, event).await,
+                ContentEventKind::Delete => plugin.on_delete(pool, event).await,
+            };
+            if let Err(e) = result {
+                log::warn!("Plugin {} failed to handle {:?} of article {}: {}", plugin.name(), event.kind, event.article_id, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::render_pipeline::{RenderContext, SANITIZE};
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::{App, HttpResponse};
+    use std::sync::Mutex;
+
+    struct Signature;
+
+    impl RenderStage for Signature {
+        fn name(&self) -> &str {
+            "signature"
+        }
+
+        fn markdown(&self, markdown: String, _context: &mut RenderContext) -> String {
+            format!("{}\n\n*Thanks for reading*", markdown)
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        seen: Mutex<Vec<(ContentEventKind, i64)>>,
+    }
+
+    impl Plugin for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        fn configure(&self, cfg: &mut web::ServiceConfig) {
+            cfg.route("/plugins/recorder", web::get().to(|| async { HttpResponse::Ok().body("recording") }));
+        }
+
+        fn render_stages(&self) -> Vec<(StagePosition<'static>, Arc<dyn RenderStage>)> {
+            vec![
+                (StagePosition::Before(SANITIZE), Arc::new(Signature)),
+                (StagePosition::After("missing"), Arc::new(Signature)),
+            ]
+        }
+
+        fn on_publish<'a>(&'a self, _pool: &'a SqlitePool, event: &'a ContentEvent) -> BoxFuture<'a, Result<(), PluginError>> {
+            self.seen.lock().unwrap().push((event.kind, event.article_id));
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    struct Failing;
+
+    impl Plugin for Failing {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn on_publish<'a>(&'a self, _pool: &'a SqlitePool, _event: &'a ContentEvent) -> BoxFuture<'a, Result<(), PluginError>> {
+            Box::pin(async { Err(PluginError::Failed("unreachable".to_string())) })
+        }
+    }
+
+    fn failing(_config: &PluginConfig) -> Result<Arc<dyn Plugin>, PluginError> {
+        Ok(Arc::new(Failing))
+    }
+
+    fn unconfigured(_config: &PluginConfig) -> Result<Arc<dyn Plugin>, PluginError> {
+        Err(PluginError::Config("TOKEN is not set".to_string()))
+    }
+
+    fn config() -> PluginConfig {
+        PluginConfig { site_url: "https://example.com/".to_string() }
+    }
+
+    #[test]
+    fn test_load() {
+        let available: &[(&str, PluginFactory)] = &[("failing", failing), ("unconfigured", unconfigured)];
+        let registry = PluginRegistry::load(&["failing", "unconfigured", "nope", "failing"], available, &config());
+        assert_eq!(registry.names(), vec!["failing"]);
+        assert!(PluginRegistry::load(&[], available, &config()).is_empty());
+    }
+
+    #[test]
+    fn test_render_stages() {
+        let mut registry = PluginRegistry::new();
+        registry.register(Arc::new(Recorder::default())).unwrap();
+        let mut markdown = MarkdownService::new();
+        // 第二个阶段的位置不存在，只加入第一个
+        assert_eq!(registry.install_render_stages(&mut markdown), 1);
+        assert!(markdown.stage_names().contains(&"signature"));
+        assert!(markdown.render_to_html("Hello").unwrap().contains("<em>Thanks for reading</em>"));
+    }
+
+    #[actix_web::test]
+    async fn test_routes() {
+        let mut registry = PluginRegistry::new();
+        registry.register(Arc::new(Recorder::default())).unwrap();
+        let app = init_service(App::new().configure(|cfg| registry.configure(cfg))).await;
+        let response = call_service(&app, TestRequest::get().uri("/plugins/recorder").to_request()).await;
+        assert!(response.status().is_success());
+        assert_eq!(read_body(response).await, "recording");
+    }
+
+    #[actix_web::test]
+    async fn test_dispatch() {
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let recorder = Arc::new(Recorder::default());
+        let mut registry = PluginRegistry::new();
+        registry.register(Arc::new(Failing)).unwrap();
+        registry.register(recorder.clone()).unwrap();
+        assert!(matches!(registry.register(recorder.clone()), Err(PluginError::Duplicate(_))));
+
+        // 前一个插件失败不影响后面的插件
+        let event = ContentEvent::new(ContentEventKind::Publish, 7, &config().site_url);
+        assert_eq!(event.url, "https://example.com/post/7");
+        registry.dispatch(&pool, &event).await;
+        registry.dispatch(&pool, &ContentEvent::new(ContentEventKind::Delete, 7, "")).await;
+        assert_eq!(*recorder.seen.lock().unwrap(), vec![(ContentEventKind::Publish, 7)]);
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Most keys a limiter or filter tracks at once; past this the oldest entries are dropped early
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+/// Fixed-window limiter keyed by client (usually the IP address)
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    state: Mutex<Windows>,
+}
+
+#[derive(Debug, Default)]
+struct Windows {
+    hits: HashMap<String, (Instant, u32)>,
+    // 按开始时间排列的窗口，过期的从队首移除；key 重新开窗后旧记录留在队中，出队时跳过
+    order: VecDeque<(Instant, String)>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            state: Mutex::new(Windows::default()),
+        }
+    }
+
+    /// Count one hit for `key`; returns false once the key is over its limit for the current window
+    pub fn check(&self, key: &str) -> bool {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let Windows { hits, order } = &mut *state;
+
+        // 只看队首，每个窗口最多出队一次；表满时连未过期的最旧窗口也丢弃
+        while let Some((start, _)) = order.front() {
+            let expired = now.duration_since(*start) >= self.window;
+            if !expired && (hits.len() < MAX_TRACKED_KEYS || hits.contains_key(key)) {
+                break;
+            }
+            if let Some((start, old_key)) = order.pop_front()
+                && hits.get(&old_key).is_some_and(|(current, _)| *current == start)
+            {
+                hits.remove(&old_key);
+            }
+        }
+
+        let entry = hits.entry(key.to_string()).or_insert((now, 0));
+        if entry.1 == 0 || now.duration_since(entry.0) >= self.window {
+            *entry = (now, 0);
+            order.push_back((now, key.to_string()));
+        }
+        entry.1 += 1;
+        entry.1 <= self.limit
+    }
+
+    #[cfg(test)]
+    fn tracked(&self) -> usize {
+        self.state.lock().unwrap().hits.len()
+    }
+}
+
+/// Remembers recently submitted texts so the same body posted again within the
+/// window can be rejected. A text counts as a repeat when the same client sent it
+/// anywhere, or anyone sent it to the same scope (e.g. article).
+#[derive(Debug)]
+pub struct DuplicateFilter {
+    window: Duration,
+    state: Mutex<Seen>,
+}
+
+#[derive(Debug, Default)]
+struct Seen {
+    at: HashMap<(String, u64), Instant>,
+    // 与 RateLimiter 相同：按记录时间排列，出队时跳过已被重新记录的旧项
+    order: VecDeque<(Instant, (String, u64))>,
+}
+
+impl DuplicateFilter {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            state: Mutex::new(Seen::default()),
+        }
+    }
+
+    /// Case and whitespace differences don't make a text new
+    fn fingerprint(text: &str) -> u64 {
+        let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        let mut hasher = DefaultHasher::new();
+        normalized.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn keys(client: &str, scope: &str, text: &str) -> [(String, u64); 2] {
+        let fingerprint = Self::fingerprint(text);
+        [(format!("client:{}", client), fingerprint), (format!("scope:{}", scope), fingerprint)]
+    }
+
+    /// Whether `text` repeats one recorded within the window
+    pub fn is_duplicate(&self, client: &str, scope: &str, text: &str) -> bool {
+        let now = Instant::now();
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        Self::keys(client, scope, text)
+            .iter()
+            .any(|key| state.at.get(key).is_some_and(|at| now.duration_since(*at) < self.window))
+    }
+
+    /// Remember `text` once it has been accepted
+    pub fn record(&self, client: &str, scope: &str, text: &str) {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let Seen { at, order } = &mut *state;
+        while let Some((recorded, _)) = order.front() {
+            let expired = now.duration_since(*recorded) >= self.window;
+            if !expired && at.len() + 2 <= MAX_TRACKED_KEYS {
+                break;
+            }
+            if let Some((recorded, key)) = order.pop_front()
+                && at.get(&key) == Some(&recorded)
+            {
+                at.remove(&key);
+            }
+        }
+        for key in Self::keys(client, scope, text) {
+            at.insert(key.clone(), now);
+            order.push_back((now, key));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_per_key() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        assert!(limiter.check("1.2.3.4"));
+        assert!(limiter.check("1.2.3.4"));
+        assert!(!limiter.check("1.2.3.4"));
+        assert!(limiter.check("5.6.7.8"));
+    }
+
+    #[test]
+    fn test_window_resets() {
+        let limiter = RateLimiter::new(1, Duration::from_millis(20));
+        assert!(limiter.check("client"));
+        assert!(!limiter.check("client"));
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(limiter.check("client"));
+    }
+
+    #[test]
+    fn test_expired_windows_are_dropped() {
+        let limiter = RateLimiter::new(1, Duration::from_millis(20));
+        for i in 0..100 {
+            limiter.check(&format!("10.0.0.{}", i));
+        }
+        assert_eq!(limiter.tracked(), 100);
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(limiter.check("client"));
+        assert_eq!(limiter.tracked(), 1);
+    }
+
+    #[test]
+    fn test_tracked_keys_are_capped() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60));
+        assert!(limiter.check("first"));
+        assert!(!limiter.check("first"));
+        for i in 0..MAX_TRACKED_KEYS + 50 {
+            limiter.check(&format!("key-{}", i));
+        }
+        assert_eq!(limiter.tracked(), MAX_TRACKED_KEYS);
+        // 最早的窗口被提前丢弃，重新计数
+        assert!(limiter.check("first"));
+    }
+
+    #[test]
+    fn test_duplicate_filter() {
+        let filter = DuplicateFilter::new(Duration::from_secs(60));
+        assert!(!filter.is_duplicate("1.2.3.4", "article:1", "Great post!"));
+        filter.record("1.2.3.4", "article:1", "Great post!");
+
+        // Same client on another article, or another client on the same article
+        assert!(filter.is_duplicate("1.2.3.4", "article:2", "great   POST!"));
+        assert!(filter.is_duplicate("5.6.7.8", "article:1", "Great post!\n"));
+        // Someone else saying the same elsewhere is fine
+        assert!(!filter.is_duplicate("5.6.7.8", "article:2", "Great post!"));
+        assert!(!filter.is_duplicate("1.2.3.4", "article:1", "Great post, thanks!"));
+
+        let expired = DuplicateFilter::new(Duration::ZERO);
+        expired.record("5.6.7.8", "article:2", "Old news");
+        expired.record("1.2.3.4", "article:1", "Great post!");
+        assert!(!expired.is_duplicate("1.2.3.4", "article:1", "Great post!"));
+        assert_eq!(expired.state.lock().unwrap().at.len(), 2);
+    }
+}
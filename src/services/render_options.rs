@@ -0,0 +1,101 @@
+//! Per-article render options set in front matter, e.g. `math: true`,
+//! `toc: false` or `highlight_theme: dracula`. They are stored as JSON in
+//! `articles.render_options`; unset options keep the site defaults.
+
+use super::markdown::RenderFeatures;
+use serde::{Deserialize, Serialize};
+
+/// Articles with at least this many headings get a table of contents unless `toc: false`
+pub const AUTO_TOC_MIN_HEADINGS: usize = 3;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenderOptions {
+    /// Typeset `$...$` / `$$...$$` as TeX; off by default since it loads KaTeX
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub math: Option<bool>,
+    /// Show a table of contents above the article
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub toc: Option<bool>,
+    /// Name of a syntect theme used for this article's code blocks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highlight_theme: Option<String>,
+}
+
+impl RenderOptions {
+    /// From `articles.render_options`; missing or malformed JSON means no overrides
+    pub fn from_json(value: Option<&str>) -> Self {
+        value.and_then(|json| serde_json::from_str(json).ok()).unwrap_or_default()
+    }
+
+    /// JSON for `articles.render_options`, `None` when nothing is overridden
+    pub fn to_json(&self) -> Option<String> {
+        if *self == Self::default() {
+            return None;
+        }
+        serde_json::to_string(self).ok()
+    }
+
+    pub fn math(&self) -> bool {
+        self.math.unwrap_or(false)
+    }
+
+    /// Whether an article with `headings` headings shows a table of contents
+    pub fn show_toc(&self, headings: usize) -> bool {
+        match self.toc {
+            Some(toc) => toc && headings > 0,
+            None => headings >= AUTO_TOC_MIN_HEADINGS,
+        }
+    }
+
+    /// What the markdown service has to do for this article
+    pub fn features(&self, headings: usize) -> RenderFeatures {
+        RenderFeatures {
+            math: self.math(),
+            heading_anchors: self.show_toc(headings),
+            lite: false,
+        }
+    }
+
+    /// Front matter lines for the options that are set, as read back on import
+    pub fn front_matter(&self) -> String {
+        let mut lines = String::new();
+        if let Some(math) = self.math {
+            lines.push_str(&format!("math: {}\n", math));
+        }
+        if let Some(toc) = self.toc {
+            lines.push_str(&format!("toc: {}\n", toc));
+        }
+        if let Some(theme) = &self.highlight_theme {
+            lines.push_str(&format!("highlight_theme: \"{}\"\n", theme.replace('\\', "\\\\").replace('"', "\\\"")));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_round_trip() {
+        assert_eq!(RenderOptions::default().to_json(), None);
+        let options = RenderOptions { math: Some(true), toc: None, highlight_theme: Some("Solarized (dark)".to_string()) };
+        let json = options.to_json().unwrap();
+        assert_eq!(json, r#"{"math":true,"highlight_theme":"Solarized (dark)"}"#);
+        assert_eq!(RenderOptions::from_json(Some(&json)), options);
+        assert_eq!(RenderOptions::from_json(Some("not json")), RenderOptions::default());
+        assert_eq!(RenderOptions::from_json(None), RenderOptions::default());
+    }
+
+    #[test]
+    fn test_show_toc() {
+        let auto = RenderOptions::default();
+        assert!(!auto.show_toc(AUTO_TOC_MIN_HEADINGS - 1));
+        assert!(auto.show_toc(AUTO_TOC_MIN_HEADINGS));
+        let on = RenderOptions { toc: Some(true), ..Default::default() };
+        assert!(on.show_toc(1) && !on.show_toc(0));
+        let off = RenderOptions { toc: Some(false), ..Default::default() };
+        assert!(!off.show_toc(10));
+        assert_eq!(off.features(10), RenderFeatures::default());
+    }
+}
@@ -0,0 +1,253 @@
+//! The stages `MarkdownService` renders through, and the extension point for
+//! adding more. The built-in stages run in this order:
+//!
+//! - `parse`: heading anchors are taken from the text, which is then parsed into events
+//! - `highlight`: code blocks are highlighted, inline code and headings get their
+//!   classes and ids, and lite renders are simplified
+//! - `sanitize`: the HTML is cleaned with ammonia
+//! - `enhance`: external links, images and tables get their extra attributes
+//!
+//! Definition lists and math are stages too, registered after `highlight` and
+//! `enhance`. A feature such as shortcodes or embeds implements `RenderStage`
+//! and is registered before or after one of the named stages:
+//!
+//! ```ignore
+//! markdown_service.register_stage(StagePosition::Before(SANITIZE), Arc::new(Shortcodes))?;
+//! ```
+//!
+//! A stage can work on three forms of the document: the Markdown text, the
+//! parsed events and the HTML. Every stage's `markdown` hook runs before the
+//! text is parsed, every `events` hook before the HTML is generated and every
+//! `html` hook at the end; within each of them, stages run in pipeline order.
+//! So what a stage's `html` hook returns after `sanitize` is not cleaned again.
+
+use super::markdown::RenderFeatures;
+use pulldown_cmark::{Event, Options};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub const PARSE: &str = "parse";
+pub const HIGHLIGHT: &str = "highlight";
+pub const SANITIZE: &str = "sanitize";
+pub const ENHANCE: &str = "enhance";
+
+#[derive(Debug, thiserror::Error)]
+pub enum PipelineError {
+    #[error("No render stage named {0}")]
+    UnknownStage(String),
+    #[error("A render stage named {0} is already registered")]
+    DuplicateStage(String),
+}
+
+/// State of one render, shared by the stages working on it
+pub struct RenderContext {
+    pub features: RenderFeatures,
+    /// The options the Markdown is parsed with, for stages that scan the text themselves
+    pub options: Options,
+    // 阶段之间传递的数据，每种类型一份
+    state: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl RenderContext {
+    pub fn new(features: RenderFeatures, options: Options) -> Self {
+        Self { features, options, state: HashMap::new() }
+    }
+
+    /// Keep a value for a later hook of this render, replacing one of the same type
+    pub fn insert<T: Any>(&mut self, value: T) {
+        self.state.insert(TypeId::of::<T>(), Box::new(value));
+    }
+
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.state.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.state.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+}
+
+/// A step of the render. The hooks leave the document unchanged by default,
+/// so a stage implements only those it needs; they cannot fail, and a stage
+/// that cannot do its work should pass its input on as it is.
+pub trait RenderStage: Send + Sync {
+    /// Unique within the pipeline; other stages are positioned relative to it
+    fn name(&self) -> &str;
+
+    /// Whether the stage takes part in renders with these features
+    fn enabled(&self, _features: RenderFeatures) -> bool {
+        true
+    }
+
+    /// The Markdown text, before it is parsed
+    fn markdown(&self, markdown: String, _context: &mut RenderContext) -> String {
+        markdown
+    }
+
+    /// The parsed document
+    fn events<'a>(&self, events: Vec<Event<'a>>, _context: &mut RenderContext) -> Vec<Event<'a>> {
+        events
+    }
+
+    /// The generated HTML
+    fn html(&self, html: String, _context: &mut RenderContext) -> String {
+        html
+    }
+}
+
+/// Where a stage is registered, relative to a stage already in the pipeline
+#[derive(Debug, Clone, Copy)]
+pub enum StagePosition<'a> {
+    Before(&'a str),
+    After(&'a str),
+    /// After every other stage
+    Last,
+}
+
+/// The stages `MarkdownService` runs itself, since they need its syntaxes,
+/// settings and metrics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Builtin {
+    Parse,
+    Highlight,
+    Sanitize,
+    Enhance,
+}
+
+impl Builtin {
+    fn name(self) -> &'static str {
+        match self {
+            Builtin::Parse => PARSE,
+            Builtin::Highlight => HIGHLIGHT,
+            Builtin::Sanitize => SANITIZE,
+            Builtin::Enhance => ENHANCE,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) enum Step {
+    Builtin(Builtin),
+    Stage(Arc<dyn RenderStage>),
+}
+
+impl Step {
+    fn name(&self) -> &str {
+        match self {
+            Step::Builtin(builtin) => builtin.name(),
+            Step::Stage(stage) => stage.name(),
+        }
+    }
+}
+
+/// The stages of a render, in order
+#[derive(Clone)]
+pub struct Pipeline {
+    steps: Vec<Step>,
+}
+
+impl Pipeline {
+    /// The built-in stages, with definition lists and math
+    pub fn new() -> Self {
+        Self {
+            steps: vec![
+                Step::Builtin(Builtin::Parse),
+                Step::Builtin(Builtin::Highlight),
+                Step::Stage(Arc::new(super::definition_list::DefinitionListStage)),
+                Step::Builtin(Builtin::Sanitize),
+                Step::Builtin(Builtin::Enhance),
+                Step::Stage(Arc::new(super::math::MathStage)),
+            ],
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.steps.iter().map(Step::name).collect()
+    }
+
+    pub fn register(&mut self, position: StagePosition, stage: Arc<dyn RenderStage>) -> Result<(), PipelineError> {
+        if self.position(stage.name()).is_some() {
+            return Err(PipelineError::DuplicateStage(stage.name().to_string()));
+        }
+        let index = match position {
+            StagePosition::Before(name) => self.position(name).ok_or_else(|| PipelineError::UnknownStage(name.to_string()))?,
+            StagePosition::After(name) => self.position(name).ok_or_else(|| PipelineError::UnknownStage(name.to_string()))? + 1,
+            StagePosition::Last => self.steps.len(),
+        };
+        self.steps.insert(index, Step::Stage(stage));
+        Ok(())
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.steps.iter().position(|step| step.name() == name)
+    }
+
+    pub(crate) fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Named(&'static str);
+
+    impl RenderStage for Named {
+        fn name(&self) -> &str {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_default_order() {
+        assert_eq!(
+            Pipeline::new().names(),
+            vec!["parse", "highlight", "definition_list", "sanitize", "enhance", "math"]
+        );
+    }
+
+    #[test]
+    fn test_register() {
+        let mut pipeline = Pipeline::new();
+        pipeline.register(StagePosition::Before(SANITIZE), Arc::new(Named("embeds"))).unwrap();
+        pipeline.register(StagePosition::After(PARSE), Arc::new(Named("shortcodes"))).unwrap();
+        pipeline.register(StagePosition::Last, Arc::new(Named("footer"))).unwrap();
+        pipeline.register(StagePosition::After("embeds"), Arc::new(Named("after_embeds"))).unwrap();
+        assert_eq!(
+            pipeline.names(),
+            vec![
+                "parse", "shortcodes", "highlight", "definition_list", "embeds", "after_embeds",
+                "sanitize", "enhance", "math", "footer"
+            ]
+        );
+
+        assert!(matches!(
+            pipeline.register(StagePosition::Last, Arc::new(Named("embeds"))),
+            Err(PipelineError::DuplicateStage(_))
+        ));
+        assert!(matches!(
+            pipeline.register(StagePosition::Before("toc"), Arc::new(Named("other"))),
+            Err(PipelineError::UnknownStage(_))
+        ));
+        assert!(!pipeline.names().contains(&"other"));
+    }
+
+    #[test]
+    fn test_context_state() {
+        let mut context = RenderContext::new(RenderFeatures::default(), Options::empty());
+        assert_eq!(context.get::<usize>(), None);
+        context.insert(2usize);
+        context.insert("text");
+        *context.get_mut::<usize>().unwrap() += 1;
+        assert_eq!(context.get::<usize>(), Some(&3));
+        assert_eq!(context.get::<&str>(), Some(&"text"));
+    }
+}
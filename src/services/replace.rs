@@ -0,0 +1,149 @@
+//! Find and replace across article content, e.g. after a domain or image path
+//! changes. Patterns are literal text or regular expressions; a preview lists
+//! each changed line before anything is written.
+
+use regex::{NoExpand, Regex, RegexBuilder};
+
+/// Changed lines shown per article in a preview
+pub const MAX_PREVIEW_LINES: usize = 20;
+/// Longest find pattern accepted
+pub const MAX_PATTERN_LENGTH: usize = 1000;
+/// Compiled regex size limit, to keep pathological patterns cheap
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ReplaceError {
+    #[error("Search text is required (max {MAX_PATTERN_LENGTH} characters)")]
+    InvalidLength,
+    #[error("Invalid regular expression: {0}")]
+    InvalidRegex(String),
+    #[error("Pattern matches empty text")]
+    MatchesEmpty,
+}
+
+/// One changed line, 1-based
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct LineChange {
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+/// Result of applying a replacement to one text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replaced {
+    pub content: String,
+    pub matches: usize,
+    /// At most `MAX_PREVIEW_LINES` changed lines
+    pub changes: Vec<LineChange>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FindReplace {
+    pattern: Regex,
+    replacement: String,
+    /// `$1` / `${name}` in the replacement refer to capture groups
+    expand: bool,
+}
+
+impl FindReplace {
+    pub fn new(find: &str, replacement: &str, is_regex: bool, case_insensitive: bool) -> Result<Self, ReplaceError> {
+        if find.is_empty() || find.chars().count() > MAX_PATTERN_LENGTH {
+            return Err(ReplaceError::InvalidLength);
+        }
+        let source = if is_regex { find.to_string() } else { regex::escape(find) };
+        let pattern = RegexBuilder::new(&source)
+            .case_insensitive(case_insensitive)
+            .multi_line(true)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .build()
+            .map_err(|e| ReplaceError::InvalidRegex(e.to_string()))?;
+        // 能匹配空串的模式会在每个位置插入替换内容
+        if pattern.is_match("") {
+            return Err(ReplaceError::MatchesEmpty);
+        }
+        Ok(Self {
+            pattern,
+            replacement: replacement.to_string(),
+            expand: is_regex,
+        })
+    }
+
+    fn replace_all(&self, text: &str) -> String {
+        if self.expand {
+            self.pattern.replace_all(text, self.replacement.as_str()).into_owned()
+        } else {
+            self.pattern.replace_all(text, NoExpand(&self.replacement)).into_owned()
+        }
+    }
+
+    /// The replaced content, or `None` when nothing matches
+    pub fn apply(&self, content: &str) -> Option<Replaced> {
+        let matches = self.pattern.find_iter(content).count();
+        if matches == 0 {
+            return None;
+        }
+        let replaced = self.replace_all(content);
+
+        // 匹配可能跨行，按原文行号逐行对比，只展示匹配所在的行
+        let mut changes = Vec::new();
+        let mut line_starts = vec![0];
+        line_starts.extend(content.match_indices('\n').map(|(i, _)| i + 1));
+        let mut lines_with_matches: Vec<usize> = self
+            .pattern
+            .find_iter(content)
+            .map(|m| line_starts.partition_point(|&start| start <= m.start()) - 1)
+            .collect();
+        lines_with_matches.dedup();
+        for index in lines_with_matches.into_iter().take(MAX_PREVIEW_LINES) {
+            let start = line_starts[index];
+            let end = line_starts.get(index + 1).map_or(content.len(), |next| next - 1);
+            let before = &content[start..end];
+            changes.push(LineChange {
+                line: index + 1,
+                before: before.to_string(),
+                after: self.replace_all(before),
+            });
+        }
+        Some(Replaced { content: replaced, matches, changes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literal() {
+        let replace = FindReplace::new("old.example.com", "new.example.com", false, false).unwrap();
+        let content = "See https://old.example.com/a\n\nand ![x](https://old.example.com/b.png) or old.example.com.";
+        let result = replace.apply(content).unwrap();
+        assert_eq!(result.matches, 3);
+        assert_eq!(result.content, content.replace("old.example.com", "new.example.com"));
+        assert_eq!(result.changes.iter().map(|c| c.line).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(result.changes[1].after, "and ![x](https://new.example.com/b.png) or new.example.com.");
+
+        // Regex syntax and `$` are literal
+        let dollars = FindReplace::new("$5.00", "$1", false, false).unwrap();
+        assert_eq!(dollars.apply("costs $5.00").unwrap().content, "costs $1");
+        assert_eq!(replace.apply("nothing here"), None);
+    }
+
+    #[test]
+    fn test_regex() {
+        let replace = FindReplace::new(r"/uploads/(\w+)\.png", "/media/$1.webp", true, false).unwrap();
+        assert_eq!(replace.apply("![a](/uploads/cat.png)").unwrap().content, "![a](/media/cat.webp)");
+
+        let insensitive = FindReplace::new("rust", "Rust", false, true).unwrap();
+        assert_eq!(insensitive.apply("RUST and rust").unwrap().content, "Rust and Rust");
+        let anchored = FindReplace::new("^TODO", "NOTE", true, false).unwrap();
+        assert_eq!(anchored.apply("a TODO\nTODO b").unwrap().content, "a TODO\nNOTE b");
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert_eq!(FindReplace::new("", "x", false, false).unwrap_err(), ReplaceError::InvalidLength);
+        assert!(matches!(FindReplace::new("(", "x", true, false), Err(ReplaceError::InvalidRegex(_))));
+        assert_eq!(FindReplace::new("a*", "x", true, false).unwrap_err(), ReplaceError::MatchesEmpty);
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Entries kept at most; the oldest is evicted first
+const MAX_ENTRIES: usize = 256;
+
+/// Serialized API responses keyed by normalized query parameters.
+///
+/// Every article mutation calls `invalidate`, which bumps a generation counter.
+/// Responses are stored with the generation they were built under, so a request
+/// that read the database before a mutation cannot put stale data back.
+pub struct ResponseCache {
+    ttl: Duration,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    generation: u64,
+    entries: HashMap<String, Entry>,
+}
+
+struct Entry {
+    body: String,
+    stored_at: Instant,
+}
+
+impl ResponseCache {
+    /// A zero `ttl` disables caching
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Cache key from query parameters, independent of their order
+    pub fn key<'a>(params: impl IntoIterator<Item = (&'a str, String)>) -> String {
+        let mut params: Vec<(&str, String)> = params.into_iter().collect();
+        params.sort();
+        params
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    /// Generation to pass to `insert` for a response built from now on
+    pub fn generation(&self) -> u64 {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).generation
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state
+            .entries
+            .get(key)
+            .filter(|entry| entry.stored_at.elapsed() < self.ttl)
+            .map(|entry| entry.body.clone())
+    }
+
+    /// Store a response unless the cache was invalidated since `generation` was read
+    pub fn insert(&self, key: String, generation: u64, body: String) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.generation != generation {
+            return;
+        }
+        let ttl = self.ttl;
+        state.entries.retain(|_, entry| entry.stored_at.elapsed() < ttl);
+        if state.entries.len() >= MAX_ENTRIES
+            && !state.entries.contains_key(&key)
+            && let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.stored_at)
+                .map(|(key, _)| key.clone())
+        {
+            state.entries.remove(&oldest);
+        }
+        state.entries.insert(key, Entry { body, stored_at: Instant::now() });
+    }
+
+    /// Drop every entry; called after any article is created, changed or deleted
+    pub fn invalidate(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.generation += 1;
+        state.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_ignores_parameter_order() {
+        let a = ResponseCache::key([("page", "2".to_string()), ("per_page", "10".to_string())]);
+        let b = ResponseCache::key([("per_page", "10".to_string()), ("page", "2".to_string())]);
+        assert_eq!(a, b);
+        assert_eq!(a, "page=2&per_page=10");
+    }
+
+    #[test]
+    fn test_invalidate_rejects_stale_inserts() {
+        let cache = ResponseCache::new(Duration::from_secs(60));
+        let generation = cache.generation();
+        cache.insert("page=1".to_string(), generation, "[1]".to_string());
+        assert_eq!(cache.get("page=1").as_deref(), Some("[1]"));
+
+        // A response built before this mutation must not be cached afterwards
+        let before = cache.generation();
+        cache.invalidate();
+        assert!(cache.get("page=1").is_none());
+        cache.insert("page=1".to_string(), before, "[stale]".to_string());
+        assert!(cache.is_empty());
+
+        cache.insert("page=1".to_string(), cache.generation(), "[2]".to_string());
+        assert_eq!(cache.get("page=1").as_deref(), Some("[2]"));
+    }
+
+    #[test]
+    fn test_zero_ttl_disables_cache() {
+        let cache = ResponseCache::new(Duration::ZERO);
+        cache.insert("k".to_string(), cache.generation(), "v".to_string());
+        assert!(cache.get("k").is_none());
+    }
+}
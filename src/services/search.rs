@@ -0,0 +1,316 @@
+//! Full-text search over articles backed by the SQLite FTS5 table `articles_fts`.
+//! Reader input is never passed to `MATCH` as is: it is split into words and
+//! each word quoted, so FTS5 operators typed into a search box are plain text.
+
+use super::pagination::Pagination;
+use crate::models;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+pub const SEARCH_TITLE_WEIGHT_KEY: &str = "search_title_weight";
+pub const SEARCH_RECENCY_HALF_LIFE_KEY: &str = "search_recency_half_life_days";
+
+/// Largest title boost that can be configured
+pub const MAX_TITLE_WEIGHT: u32 = 50;
+/// Longest recency half-life that can be configured, about ten years
+pub const MAX_RECENCY_HALF_LIFE_DAYS: u32 = 3650;
+
+/// Longest query accepted, in characters; the rest is ignored
+pub const MAX_QUERY_LENGTH: usize = 100;
+/// Words used from a query
+pub const MAX_TERMS: usize = 8;
+
+/// Results per page of `/search` and `/api/search`, adjustable with `?per_page=`
+pub const RESULTS_PER_PAGE: i64 = 10;
+pub const MAX_RESULTS_PER_PAGE: i64 = 50;
+
+/// Around each matched word in `highlight()` / `snippet()` output; control
+/// characters, so they are told apart from the article's own text before
+/// the result is escaped for HTML
+pub const MATCH_START: char = '\u{2}';
+pub const MATCH_END: char = '\u{3}';
+
+/// Lowercased words of a search query
+pub fn terms(input: &str) -> Vec<String> {
+    let input: String = input.chars().take(MAX_QUERY_LENGTH).collect();
+    input
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .take(MAX_TERMS)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// An FTS5 string literal
+fn quote(term: &str) -> String {
+    format!("\"{}\"", term.replace('"', "\"\""))
+}
+
+/// Type-ahead match over titles: every word must appear, the last one as a
+/// prefix since the reader is still typing it. `None` when there are no words.
+pub fn title_prefix_query(input: &str) -> Option<String> {
+    let terms = terms(input);
+    let (last, rest) = terms.split_last()?;
+    let mut parts: Vec<String> = rest.iter().map(|term| quote(term)).collect();
+    parts.push(format!("{}*", quote(last)));
+    Some(format!("title : ({})", parts.join(" ")))
+}
+
+/// Type-ahead match over tag names: `LIKE` pattern for names starting with
+/// the input, with `%`, `_` and `\` escaped. `None` when blank.
+pub fn tag_prefix_pattern(input: &str) -> Option<String> {
+    let input: String = input.trim().chars().take(MAX_QUERY_LENGTH).collect();
+    if input.is_empty() {
+        return None;
+    }
+    let mut pattern = String::with_capacity(input.len() + 1);
+    for c in input.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    Some(pattern)
+}
+
+/// Full search: every word must appear in the title or the body, the last
+/// one also as a prefix. `None` when there are no words.
+pub fn match_query(input: &str) -> Option<String> {
+    let terms = terms(input);
+    let (last, rest) = terms.split_last()?;
+    let mut parts: Vec<String> = rest.iter().map(|term| quote(term)).collect();
+    parts.push(format!("{}*", quote(last)));
+    Some(parts.join(" "))
+}
+
+/// HTML for text marked with `MATCH_START` / `MATCH_END`: the text is escaped,
+/// whitespace collapsed and matches wrapped in `<mark>`
+pub fn highlight(marked: &str) -> String {
+    let marked = marked.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut html = String::with_capacity(marked.len());
+    let mut text = String::new();
+    let mut open = false;
+    for c in marked.chars() {
+        if c != MATCH_START && c != MATCH_END {
+            text.push(c);
+            continue;
+        }
+        html.push_str(&html_escape::encode_text(&text));
+        text.clear();
+        // 成对出现；多余的标记忽略
+        if c == MATCH_START && !open {
+            html.push_str("<mark>");
+            open = true;
+        } else if c == MATCH_END && open {
+            html.push_str("</mark>");
+            open = false;
+        }
+    }
+    html.push_str(&html_escape::encode_text(&text));
+    if open {
+        html.push_str("</mark>");
+    }
+    html
+}
+
+/// Path of a results page, for `Pagination::new`
+pub fn results_path(input: &str) -> String {
+    format!("/search?q={}", super::avatar::url_encode(input))
+}
+
+/// A search result ready to show; the `_html` fields are escaped, with the
+/// matched words in `<mark>`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    pub id: i64,
+    /// Path of the article's page
+    pub path: String,
+    pub title: String,
+    pub title_html: String,
+    pub snippet_html: String,
+    #[serde(with = "super::dates::rfc3339")]
+    pub date: String,
+}
+
+impl From<models::SearchResult> for SearchHit {
+    fn from(result: models::SearchResult) -> Self {
+        Self {
+            id: result.id,
+            path: super::slugs::post_path(result.id, result.slug.as_deref()),
+            title_html: highlight(&result.title_marked),
+            title: result.title,
+            snippet_html: highlight(&result.snippet),
+            date: result.created_at,
+        }
+    }
+}
+
+/// One page of published articles matching `input`; no words means no results
+pub async fn search(
+    pool: &SqlitePool,
+    input: &str,
+    ranking: &SearchRanking,
+    page: Option<i64>,
+    per_page: Option<i64>,
+) -> Result<(Pagination, Vec<SearchHit>), sqlx::Error> {
+    let match_expr = match_query(input);
+    let total = match &match_expr {
+        Some(match_expr) => models::count_search_results(pool, match_expr).await?,
+        None => 0,
+    };
+    let pagination = Pagination::new(&results_path(input), page, per_page, RESULTS_PER_PAGE, MAX_RESULTS_PER_PAGE, total);
+    let hits = match &match_expr {
+        Some(match_expr) if !pagination.is_out_of_range() => {
+            models::search_articles(pool, match_expr, ranking, pagination.per_page, pagination.offset())
+                .await?
+                .into_iter()
+                .map(SearchHit::from)
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+    Ok((pagination, hits))
+}
+
+/// Type-ahead completions for `input`: published articles whose title matches
+/// `title_prefix_query()` or that carry a tag starting with the input, each once
+pub async fn suggest(
+    pool: &SqlitePool,
+    input: &str,
+    ranking: &SearchRanking,
+    limit: i64,
+) -> Result<Vec<models::SearchSuggestion>, sqlx::Error> {
+    let (Some(match_expr), Some(tag_pattern)) = (title_prefix_query(input), tag_prefix_pattern(input)) else {
+        return Ok(Vec::new());
+    };
+    models::search_suggestions(pool, &match_expr, &tag_pattern, ranking, limit).await
+}
+
+/// How matches are ordered. The relevance score is FTS5's `bm25()` with title
+/// matches counted `title_weight` times as much as body matches, then divided
+/// by `1 + age / half_life` so that older articles rank lower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SearchRanking {
+    /// Weight of the title column relative to the body (1 = equal)
+    pub title_weight: u32,
+    /// Age in days at which an article's score is halved; 0 ignores age
+    pub recency_half_life_days: u32,
+}
+
+impl Default for SearchRanking {
+    fn default() -> Self {
+        Self { title_weight: 5, recency_half_life_days: 0 }
+    }
+}
+
+impl SearchRanking {
+    /// Read from site settings; missing or invalid values keep their defaults
+    pub fn from_settings(settings: &HashMap<String, String>) -> Self {
+        let defaults = Self::default();
+        Self {
+            title_weight: settings
+                .get(SEARCH_TITLE_WEIGHT_KEY)
+                .and_then(|v| v.parse::<u32>().ok())
+                .filter(|weight| (1..=MAX_TITLE_WEIGHT).contains(weight))
+                .unwrap_or(defaults.title_weight),
+            recency_half_life_days: settings
+                .get(SEARCH_RECENCY_HALF_LIFE_KEY)
+                .and_then(|v| v.parse::<u32>().ok())
+                .filter(|&days| days <= MAX_RECENCY_HALF_LIFE_DAYS)
+                .unwrap_or(defaults.recency_half_life_days),
+        }
+    }
+
+    pub fn to_settings(&self) -> [(&'static str, String); 2] {
+        [
+            (SEARCH_TITLE_WEIGHT_KEY, self.title_weight.to_string()),
+            (SEARCH_RECENCY_HALF_LIFE_KEY, self.recency_half_life_days.to_string()),
+        ]
+    }
+
+    /// `bm25()` weights for the `title` and `content` columns
+    pub fn column_weights(&self) -> (f64, f64) {
+        (f64::from(self.title_weight), 1.0)
+    }
+
+    /// Per-day factor of the age penalty, so the score is divided by
+    /// `1 + age_days * rate`; 0 when recency is ignored
+    pub fn recency_rate(&self) -> f64 {
+        if self.recency_half_life_days == 0 {
+            0.0
+        } else {
+            1.0 / f64::from(self.recency_half_life_days)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terms() {
+        assert_eq!(terms("  Rust's ownership, explained! "), vec!["rust", "s", "ownership", "explained"]);
+        assert_eq!(terms("Café 编程"), vec!["café", "编程"]);
+        assert_eq!(terms("a b c d e f g h i j").len(), MAX_TERMS);
+        assert!(terms(" -*- ").is_empty());
+    }
+
+    #[test]
+    fn test_title_prefix_query() {
+        assert_eq!(title_prefix_query("rust own").as_deref(), Some(r#"title : ("rust" "own"*)"#));
+        // FTS5 syntax is searched as text
+        assert_eq!(
+            title_prefix_query("content: NEAR(\"x\" OR").as_deref(),
+            Some(r#"title : ("content" "near" "x" "or"*)"#)
+        );
+        assert_eq!(title_prefix_query(""), None);
+        assert_eq!(title_prefix_query("\"*:"), None);
+    }
+
+    #[test]
+    fn test_tag_prefix_pattern() {
+        assert_eq!(tag_prefix_pattern(" Rust ").as_deref(), Some("Rust%"));
+        assert_eq!(tag_prefix_pattern("c++ web").as_deref(), Some("c++ web%"));
+        assert_eq!(tag_prefix_pattern("100%_\\").as_deref(), Some(r"100\%\_\\%"));
+        assert_eq!(tag_prefix_pattern("  "), None);
+    }
+
+    #[test]
+    fn test_match_query() {
+        assert_eq!(match_query("Rust own").as_deref(), Some(r#""rust" "own"*"#));
+        assert_eq!(match_query("title: x OR").as_deref(), Some(r#""title" "x" "or"*"#));
+        assert_eq!(match_query(" ** "), None);
+        assert_eq!(results_path("a b&c"), "/search?q=a%20b%26c");
+    }
+
+    #[test]
+    fn test_highlight() {
+        let marked = format!("x <b> {}Rust{}  and\n{}rusty{} …", MATCH_START, MATCH_END, MATCH_START, MATCH_END);
+        assert_eq!(highlight(&marked), "x &lt;b&gt; <mark>Rust</mark> and <mark>rusty</mark> …");
+        // 未闭合的标记在末尾闭合
+        assert_eq!(highlight(&format!("{}open", MATCH_START)), "<mark>open</mark>");
+        assert_eq!(highlight(&format!("stray{} end", MATCH_END)), "stray end");
+        assert_eq!(highlight("plain"), "plain");
+    }
+
+    #[test]
+    fn test_ranking_settings() {
+        let ranking = SearchRanking { title_weight: 10, recency_half_life_days: 180 };
+        let settings: HashMap<String, String> =
+            ranking.to_settings().into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+        assert_eq!(SearchRanking::from_settings(&settings), ranking);
+        assert_eq!(ranking.column_weights(), (10.0, 1.0));
+        assert_eq!(ranking.recency_rate(), 1.0 / 180.0);
+        assert_eq!(SearchRanking::default().recency_rate(), 0.0);
+
+        let invalid: HashMap<String, String> = [
+            (SEARCH_TITLE_WEIGHT_KEY.to_string(), "0".to_string()),
+            (SEARCH_RECENCY_HALF_LIFE_KEY.to_string(), "-1".to_string()),
+        ]
+        .into();
+        assert_eq!(SearchRanking::from_settings(&invalid), SearchRanking::default());
+    }
+}
@@ -0,0 +1,35 @@
+use rand::Rng;
+
+const ALPHABET: &[u8] = b"abcdefghijkmnpqrstuvwxyz23456789";
+/// Codes start this long and grow if collisions keep happening
+pub const CODE_LENGTH: usize = 4;
+
+/// Random code from an alphabet without look-alike characters (0/o, 1/l)
+pub fn generate_code(len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())] as char)
+        .collect()
+}
+
+/// Cheap check before hitting the database for `/s/{code}`
+pub fn is_valid_code(code: &str) -> bool {
+    (CODE_LENGTH..=16).contains(&code.len()) && code.bytes().all(|b| ALPHABET.contains(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_codes_are_valid() {
+        for _ in 0..100 {
+            let code = generate_code(CODE_LENGTH);
+            assert_eq!(code.len(), CODE_LENGTH);
+            assert!(is_valid_code(&code));
+        }
+        assert!(!is_valid_code("ab1"));
+        assert!(!is_valid_code("abcl"));
+        assert!(!is_valid_code("../x"));
+    }
+}
@@ -0,0 +1,105 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// HMAC-signed, expiring tokens for links that act without a session (e.g. in emails)
+#[derive(Clone)]
+pub struct Signer {
+    key: Vec<u8>,
+}
+
+impl Signer {
+    pub fn new(secret: &[u8]) -> Self {
+        Self { key: secret.to_vec() }
+    }
+
+    /// Use `SIGNING_SECRET`, or a random key that only lives as long as the process
+    pub fn from_env() -> Self {
+        match std::env::var("SIGNING_SECRET") {
+            Ok(secret) if !secret.trim().is_empty() => Self::new(secret.trim().as_bytes()),
+            _ => {
+                log::warn!("SIGNING_SECRET is not set; signed links will stop working after a restart");
+                let key: Vec<u8> = (0..32).map(|_| rand::random::<u8>()).collect();
+                Self::new(&key)
+            }
+        }
+    }
+
+    /// Token for `payload`, valid until the unix timestamp `expires_at`
+    pub fn sign(&self, payload: &str, expires_at: i64) -> String {
+        format!("{}.{}", expires_at, hex::encode(self.mac(payload, expires_at).finalize().into_bytes()))
+    }
+
+    pub fn verify(&self, payload: &str, token: &str, now: i64) -> bool {
+        let Some((expires_at, signature)) = token.split_once('.') else {
+            return false;
+        };
+        let (Ok(expires_at), Ok(signature)) = (expires_at.parse::<i64>(), hex::decode(signature)) else {
+            return false;
+        };
+        expires_at >= now && self.mac(payload, expires_at).verify_slice(&signature).is_ok()
+    }
+
+    /// Keyed, non-expiring digest of `value` (hex); stable for a given secret
+    pub fn digest(&self, value: &str) -> String {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(value.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Check a `digest` in constant time
+    pub fn verify_digest(&self, value: &str, digest: &str) -> bool {
+        let Ok(digest) = hex::decode(digest) else {
+            return false;
+        };
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(value.as_bytes());
+        mac.verify_slice(&digest).is_ok()
+    }
+
+    fn mac(&self, payload: &str, expires_at: i64) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        mac.update(b"|");
+        mac.update(expires_at.to_string().as_bytes());
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signer = Signer::new(b"secret");
+        let token = signer.sign("comment:1:approve", 2000);
+        assert!(signer.verify("comment:1:approve", &token, 1000));
+        assert!(!signer.verify("comment:1:spam", &token, 1000));
+        assert!(!Signer::new(b"other").verify("comment:1:approve", &token, 1000));
+    }
+
+    #[test]
+    fn test_digest_is_keyed() {
+        let signer = Signer::new(b"secret");
+        assert_eq!(signer.digest("a@example.com"), signer.digest("a@example.com"));
+        assert_ne!(signer.digest("a@example.com"), Signer::new(b"other").digest("a@example.com"));
+        assert_eq!(signer.digest("x").len(), 64);
+        assert!(signer.verify_digest("x", &signer.digest("x")));
+        assert!(!signer.verify_digest("y", &signer.digest("x")));
+        assert!(!signer.verify_digest("x", "not hex"));
+    }
+
+    #[test]
+    fn test_expired_and_malformed_tokens() {
+        let signer = Signer::new(b"secret");
+        let token = signer.sign("payload", 1000);
+        assert!(!signer.verify("payload", &token, 1001));
+        assert!(!signer.verify("payload", "garbage", 0));
+        assert!(!signer.verify("payload", "1000.zz", 0));
+        // 篡改过期时间后签名失效
+        let forged = token.replacen("1000", "9999", 1);
+        assert!(!signer.verify("payload", &forged, 1001));
+    }
+}
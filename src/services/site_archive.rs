@@ -0,0 +1,192 @@
+//! Whole-site archives for moving a blog between servers with
+//! `bluster export-site` and `bluster import-site`. An archive is a ZIP file
+//! holding `site.json` (users, articles, pages, comments, snippets, content
+//! templates, navigation, short links, settings and the media manifest) and
+//! every uploaded file under `media/`. Revisions, analytics and the activity
+//! log stay behind.
+
+use crate::models::{
+    SiteArticle, SiteComment, SiteContentTemplate, SiteMedia, SiteNavItem, SitePage, SiteShortLink, SiteSnippet,
+    SiteUser,
+};
+use crate::services::archive::{read_zip, ArchiveError, ZipWriter};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Bumped whenever `site.json` changes incompatibly
+pub const FORMAT_VERSION: u32 = 1;
+/// Name of the manifest inside the archive
+pub const MANIFEST_NAME: &str = "site.json";
+/// Directory of uploaded files inside the archive
+const MEDIA_PREFIX: &str = "media/";
+/// Entries are written without ZIP64 extensions, so offsets must fit in 32 bits
+const MAX_ARCHIVE_SIZE: usize = u32::MAX as usize;
+
+/// An uploaded file: stored name and contents
+pub type MediaFile = (String, Vec<u8>);
+
+#[derive(Debug, thiserror::Error)]
+pub enum SiteArchiveError {
+    #[error("{0}")]
+    Archive(#[from] ArchiveError),
+    #[error("{MANIFEST_NAME} is missing; not a site archive")]
+    MissingManifest,
+    #[error("Invalid {MANIFEST_NAME}: {0}")]
+    InvalidManifest(String),
+    #[error("Unsupported archive version {0} (this build reads version {FORMAT_VERSION})")]
+    UnsupportedVersion(u32),
+    #[error("Archive would exceed 4 GiB")]
+    TooLarge,
+    #[error("The site already has {0} articles; import into a fresh data directory")]
+    NotEmpty(i64),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SiteArchive {
+    pub version: u32,
+    /// UTC, `%Y-%m-%d %H:%M:%S`
+    pub exported_at: String,
+    pub users: Vec<SiteUser>,
+    pub articles: Vec<SiteArticle>,
+    pub pages: Vec<SitePage>,
+    pub comments: Vec<SiteComment>,
+    pub snippets: Vec<SiteSnippet>,
+    pub content_templates: Vec<SiteContentTemplate>,
+    pub nav_items: Vec<SiteNavItem>,
+    pub short_links: Vec<SiteShortLink>,
+    pub settings: BTreeMap<String, String>,
+    pub media: Vec<SiteMedia>,
+}
+
+impl SiteArchive {
+    /// ZIP archive of this manifest and the given uploaded files
+    pub fn write(&self, files: &[MediaFile]) -> Result<Vec<u8>, SiteArchiveError> {
+        let manifest =
+            serde_json::to_vec_pretty(self).map_err(|e| SiteArchiveError::InvalidManifest(e.to_string()))?;
+        let total = manifest.len() + files.iter().map(|(_, data)| data.len()).sum::<usize>();
+        if total > MAX_ARCHIVE_SIZE {
+            return Err(SiteArchiveError::TooLarge);
+        }
+        let mut zip = ZipWriter::new();
+        zip.add_file(MANIFEST_NAME, &manifest);
+        for (name, data) in files {
+            zip.add_file(&format!("{}{}", MEDIA_PREFIX, name), data);
+        }
+        Ok(zip.finish())
+    }
+
+    /// Manifest and uploaded files of an archive; files whose names `MediaStorage`
+    /// could not have produced are dropped
+    pub fn read(bytes: &[u8]) -> Result<(Self, Vec<MediaFile>), SiteArchiveError> {
+        let mut manifest = None;
+        let mut files = Vec::new();
+        for entry in read_zip(bytes, MAX_ARCHIVE_SIZE)? {
+            if entry.name == MANIFEST_NAME {
+                manifest = Some(entry.data);
+            } else if let Some(name) = entry.name.strip_prefix(MEDIA_PREFIX)
+                && super::media::is_valid_stored_name(name)
+            {
+                files.push((name.to_string(), entry.data));
+            }
+        }
+        let manifest = manifest.ok_or(SiteArchiveError::MissingManifest)?;
+        // 先只读版本号，新版本的归档给出明确提示而不是字段解析错误
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        let version: Version =
+            serde_json::from_slice(&manifest).map_err(|e| SiteArchiveError::InvalidManifest(e.to_string()))?;
+        if version.version != FORMAT_VERSION {
+            return Err(SiteArchiveError::UnsupportedVersion(version.version));
+        }
+        let archive = serde_json::from_slice(&manifest).map_err(|e| SiteArchiveError::InvalidManifest(e.to_string()))?;
+        Ok((archive, files))
+    }
+}
+
+/// Password for an imported account whose hash was not exported; the user must
+/// change it after signing in
+pub fn temporary_password() -> String {
+    let token: [u8; 9] = rand::thread_rng().r#gen();
+    hex::encode(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive() -> SiteArchive {
+        SiteArchive {
+            version: FORMAT_VERSION,
+            exported_at: "2025-05-01 10:00:00".to_string(),
+            users: vec![SiteUser {
+                username: "admin".to_string(),
+                display_name: Some("Ada".to_string()),
+                bio: None,
+                website: None,
+                avatar_url: None,
+                created_at: "2024-01-01 00:00:00".to_string(),
+                password_hash: None,
+                security_question: None,
+                security_answer_hash: None,
+            }],
+            articles: vec![SiteArticle {
+                id: 7,
+                title: "Hello".to_string(),
+                content: "![cat](/media/0123456789abcdef0123456789abcdef.png)".to_string(),
+                author: Some("admin".to_string()),
+                created_at: "2024-02-01 00:00:00".to_string(),
+                updated_at: "2024-02-02 00:00:00".to_string(),
+                meta_description: None,
+                meta_keywords: None,
+                noindex: false,
+                text_direction: "auto".to_string(),
+                deleted_at: None,
+                render_options: None,
+                canonical_url: Some("https://dev.example/hello".to_string()),
+                slug: Some("hello".to_string()),
+            }],
+            pages: Vec::new(),
+            comments: Vec::new(),
+            snippets: Vec::new(),
+            content_templates: Vec::new(),
+            nav_items: vec![SiteNavItem { article_id: 7, label: "Hello".to_string(), position: 0 }],
+            short_links: Vec::new(),
+            settings: [("summary_length".to_string(), "200".to_string())].into(),
+            media: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let files = vec![("0123456789abcdef0123456789abcdef.png".to_string(), b"png".to_vec())];
+        let bytes = archive().write(&files).unwrap();
+        let (read, read_files) = SiteArchive::read(&bytes).unwrap();
+        assert_eq!(read, archive());
+        assert_eq!(read_files, files);
+        // 密码哈希缺省时不写入清单
+        assert!(!String::from_utf8_lossy(&bytes).contains("password_hash"));
+    }
+
+    #[test]
+    fn test_invalid_archives() {
+        let mut zip = ZipWriter::new();
+        zip.add_file("media/notes.txt", b"x");
+        assert!(matches!(SiteArchive::read(&zip.finish()), Err(SiteArchiveError::MissingManifest)));
+
+        let mut zip = ZipWriter::new();
+        zip.add_file(MANIFEST_NAME, br#"{"version": 99}"#);
+        assert!(matches!(SiteArchive::read(&zip.finish()), Err(SiteArchiveError::UnsupportedVersion(99))));
+
+        // Names outside the media directory or not produced by uploads are ignored
+        let mut zip = ZipWriter::new();
+        zip.add_file(MANIFEST_NAME, &serde_json::to_vec(&archive()).unwrap());
+        zip.add_file("media/../../etc/passwd", b"x");
+        assert!(SiteArchive::read(&zip.finish()).unwrap().1.is_empty());
+        assert!(SiteArchive::read(b"not a zip").is_err());
+    }
+}
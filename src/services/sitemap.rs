@@ -0,0 +1,137 @@
+/// URLs allowed in one sitemap file by the sitemaps.org protocol. At well under
+/// 200 bytes per entry this also keeps each file far below the 50 MB size limit.
+pub const MAX_URLS_PER_SITEMAP: usize = 50_000;
+
+/// One `<url>` entry, or one `<sitemap>` entry of an index; `path` is relative to the site URL
+pub struct SitemapEntry {
+    pub path: String,
+    pub lastmod: Option<String>,
+}
+
+/// A child sitemap listed in `/sitemap.xml`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SitemapPart {
+    /// Home and about pages
+    Pages,
+    /// Articles created in `year`; `page` starts at 1 and only grows past it when
+    /// a year has more than `MAX_URLS_PER_SITEMAP` articles
+    Year { year: i32, page: usize },
+}
+
+impl SitemapPart {
+    /// From the `{part}` in `/sitemap-{part}.xml`: `pages`, `2024` or `2024-2`
+    pub fn parse(value: &str) -> Option<Self> {
+        if value == "pages" {
+            return Some(SitemapPart::Pages);
+        }
+        let (year, page) = match value.split_once('-') {
+            Some((year, page)) => (year, page.parse::<usize>().ok().filter(|&p| p >= 2)?),
+            None => (value, 1),
+        };
+        if year.len() != 4 || !year.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        Some(SitemapPart::Year { year: year.parse().ok()?, page })
+    }
+
+    pub fn path(&self) -> String {
+        match self {
+            SitemapPart::Pages => "/sitemap-pages.xml".to_string(),
+            SitemapPart::Year { year, page: 1 } => format!("/sitemap-{}.xml", year),
+            SitemapPart::Year { year, page } => format!("/sitemap-{}-{}.xml", year, page),
+        }
+    }
+
+    /// Parts needed for `count` articles from `year`
+    pub fn for_year(year: i32, count: usize, per_sitemap: usize) -> Vec<Self> {
+        let pages = count.div_ceil(per_sitemap.max(1)).max(1);
+        (1..=pages).map(|page| SitemapPart::Year { year, page }).collect()
+    }
+}
+
+/// SQLite 时间 "YYYY-MM-DD HH:MM:SS"，sitemap 只需日期部分
+fn lastmod_date(lastmod: &str) -> &str {
+    lastmod.split_whitespace().next().unwrap_or(lastmod)
+}
+
+/// Render a sitemaps.org urlset for `site_url` (no trailing slash)
+pub fn build_sitemap(site_url: &str, entries: &[SitemapEntry]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for entry in entries {
+        xml.push_str("  <url>\n");
+        xml.push_str(&format!(
+            "    <loc>{}</loc>\n",
+            html_escape::encode_text(&format!("{}{}", site_url, entry.path))
+        ));
+        if let Some(lastmod) = &entry.lastmod {
+            xml.push_str(&format!("    <lastmod>{}</lastmod>\n", html_escape::encode_text(lastmod_date(lastmod))));
+        }
+        xml.push_str("  </url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// Render a sitemaps.org sitemap index pointing at child sitemaps
+pub fn build_sitemap_index(site_url: &str, sitemaps: &[SitemapEntry]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for sitemap in sitemaps {
+        xml.push_str("  <sitemap>\n");
+        xml.push_str(&format!(
+            "    <loc>{}</loc>\n",
+            html_escape::encode_text(&format!("{}{}", site_url, sitemap.path))
+        ));
+        if let Some(lastmod) = &sitemap.lastmod {
+            xml.push_str(&format!("    <lastmod>{}</lastmod>\n", html_escape::encode_text(lastmod_date(lastmod))));
+        }
+        xml.push_str("  </sitemap>\n");
+    }
+    xml.push_str("</sitemapindex>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_sitemap() {
+        let entries = vec![
+            SitemapEntry { path: "/".to_string(), lastmod: None },
+            SitemapEntry { path: "/post/1?a=1&b=2".to_string(), lastmod: Some("2024-05-01 10:00:00".to_string()) },
+        ];
+        let xml = build_sitemap("https://blog.example.com", &entries);
+        assert!(xml.contains("<loc>https://blog.example.com/</loc>"));
+        assert!(xml.contains("<loc>https://blog.example.com/post/1?a=1&amp;b=2</loc>"));
+        assert!(xml.contains("<lastmod>2024-05-01</lastmod>"));
+        assert_eq!(xml.matches("<url>").count(), 2);
+    }
+
+    #[test]
+    fn test_sitemap_parts() {
+        for name in ["pages", "2024", "2024-2"] {
+            let part = SitemapPart::parse(name).unwrap();
+            assert_eq!(part.path(), format!("/sitemap-{}.xml", name));
+        }
+        for name in ["", "24", "2024-1", "2024-x", "abcd", "../x"] {
+            assert_eq!(SitemapPart::parse(name), None, "{}", name);
+        }
+        assert_eq!(SitemapPart::for_year(2023, 0, 10), vec![SitemapPart::Year { year: 2023, page: 1 }]);
+        assert_eq!(SitemapPart::for_year(2023, 10, 10).len(), 1);
+        assert_eq!(SitemapPart::for_year(2023, 11, 10).last(), Some(&SitemapPart::Year { year: 2023, page: 2 }));
+    }
+
+    #[test]
+    fn test_build_sitemap_index() {
+        let sitemaps = vec![
+            SitemapEntry { path: SitemapPart::Pages.path(), lastmod: None },
+            SitemapEntry { path: SitemapPart::Year { year: 2024, page: 1 }.path(), lastmod: Some("2024-12-30 08:00:00".to_string()) },
+        ];
+        let xml = build_sitemap_index("https://blog.example.com", &sitemaps);
+        assert!(xml.contains("<sitemapindex "));
+        assert!(xml.contains("<loc>https://blog.example.com/sitemap-2024.xml</loc>\n    <lastmod>2024-12-30</lastmod>"));
+        assert_eq!(xml.matches("<sitemap>").count(), 2);
+    }
+}
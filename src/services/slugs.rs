@@ -0,0 +1,81 @@
+//! Article slugs: the readable part of `/post/{slug}`, made from the title when
+//! the article is created. A slug is unique among articles and among the old
+//! slugs kept as redirects, so a link that once worked never reaches a
+//! different article. `/post/{id}` keeps working and redirects to the slug.
+
+use super::avatar::url_encode;
+
+/// Used when the title has no letters or digits
+pub const FALLBACK_SLUG: &str = "post";
+
+/// Slug for a title, before making it unique; never all ASCII digits, which
+/// would read as an article id
+pub fn from_title(title: &str) -> String {
+    let slug = super::categories::slug_from_name(title).unwrap_or_else(|| FALLBACK_SLUG.to_string());
+    if slug.chars().all(|c| c.is_ascii_digit()) {
+        format!("{}-{}", FALLBACK_SLUG, slug)
+    } else {
+        slug
+    }
+}
+
+/// The `n`th candidate for `base`: the base itself, then `base-2`, `base-3`, ...
+pub fn candidate(base: &str, n: u32) -> String {
+    if n <= 1 {
+        base.to_string()
+    } else {
+        format!("{}-{}", base, n)
+    }
+}
+
+/// Whether `slug` is one of `base`'s candidates, i.e. the title it was made
+/// from gives the same slug as the current one
+pub fn is_candidate_of(slug: &str, base: &str) -> bool {
+    slug == base
+        || slug
+            .strip_prefix(base)
+            .and_then(|rest| rest.strip_prefix('-'))
+            .and_then(|n| n.parse::<u32>().ok())
+            .is_some_and(|n| n >= 2 && candidate(base, n) == slug)
+}
+
+/// Path of an article's page; by id for articles without a slug
+pub fn post_path(id: i64, slug: Option<&str>) -> String {
+    match slug {
+        Some(slug) => format!("/post/{}", url_encode(slug)),
+        None => format!("/post/{}", id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_title() {
+        assert_eq!(from_title("Hello, World!  Again"), "hello-world-again");
+        assert_eq!(from_title("Rust 编程入门"), "rust-编程入门");
+        assert_eq!(from_title("2024"), "post-2024");
+        assert_eq!(from_title("?!"), FALLBACK_SLUG);
+        assert!(from_title(&"long ".repeat(40)).chars().count() <= super::super::categories::MAX_SLUG_LENGTH);
+    }
+
+    #[test]
+    fn test_candidates() {
+        assert_eq!(candidate("intro", 1), "intro");
+        assert_eq!(candidate("intro", 3), "intro-3");
+        assert!(is_candidate_of("intro", "intro"));
+        assert!(is_candidate_of("intro-3", "intro"));
+        assert!(!is_candidate_of("intro-1", "intro"));
+        assert!(!is_candidate_of("intro-03", "intro"));
+        assert!(!is_candidate_of("intro-to-rust", "intro"));
+        assert!(!is_candidate_of("intros", "intro"));
+    }
+
+    #[test]
+    fn test_post_path() {
+        assert_eq!(post_path(3, Some("hello-world")), "/post/hello-world");
+        assert_eq!(post_path(3, Some("编程")), "/post/%E7%BC%96%E7%A8%8B");
+        assert_eq!(post_path(3, None), "/post/3");
+    }
+}
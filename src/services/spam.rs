@@ -0,0 +1,120 @@
+use futures_util::future::BoxFuture;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpamError {
+    #[error("Spam check request failed: {0}")]
+    Http(String),
+    #[error("Spam check rejected: {0}")]
+    Api(String),
+}
+
+/// A user submission to be checked before it is stored
+#[derive(Debug, Default)]
+pub struct Submission<'a> {
+    /// e.g. "comment", "reply" or "contact-form"
+    pub kind: &'a str,
+    pub ip: &'a str,
+    pub user_agent: &'a str,
+    pub referrer: &'a str,
+    /// Absolute URL of the page the submission was made on
+    pub permalink: &'a str,
+    pub author: &'a str,
+    pub author_email: Option<&'a str>,
+    pub content: &'a str,
+}
+
+/// Decides whether a submission looks like spam; implementations are swappable in `AppState`
+pub trait SpamChecker: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn is_spam<'a>(&'a self, submission: &'a Submission<'a>) -> BoxFuture<'a, Result<bool, SpamError>>;
+}
+
+/// Akismet comment-check API (https://akismet.com/developers/)
+pub struct Akismet {
+    client: reqwest::Client,
+    api_key: String,
+    blog_url: String,
+}
+
+impl Akismet {
+    pub fn new(api_key: &str, blog_url: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(5))
+            .user_agent(concat!("Bluster/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            api_key: api_key.to_string(),
+            blog_url: blog_url.to_string(),
+        }
+    }
+
+    /// Enabled when `AKISMET_API_KEY` is set
+    pub fn from_env(blog_url: &str) -> Option<Self> {
+        let api_key = std::env::var("AKISMET_API_KEY").ok().filter(|k| !k.trim().is_empty())?;
+        log::info!("Akismet spam filtering enabled");
+        Some(Self::new(api_key.trim(), blog_url))
+    }
+
+    /// Akismet answers "true" for spam and "false" for ham; anything else is an error
+    fn parse_response(body: &str, debug_help: Option<&str>) -> Result<bool, SpamError> {
+        match body.trim() {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            other => Err(SpamError::Api(debug_help.unwrap_or(other).to_string())),
+        }
+    }
+}
+
+impl SpamChecker for Akismet {
+    fn name(&self) -> &'static str {
+        "akismet"
+    }
+
+    fn is_spam<'a>(&'a self, submission: &'a Submission<'a>) -> BoxFuture<'a, Result<bool, SpamError>> {
+        Box::pin(async move {
+            let url = format!("https://{}.rest.akismet.com/1.1/comment-check", self.api_key);
+            let params = [
+                ("blog", self.blog_url.as_str()),
+                ("user_ip", submission.ip),
+                ("user_agent", submission.user_agent),
+                ("referrer", submission.referrer),
+                ("permalink", submission.permalink),
+                ("comment_type", submission.kind),
+                ("comment_author", submission.author),
+                ("comment_author_email", submission.author_email.unwrap_or("")),
+                ("comment_content", submission.content),
+            ];
+            let response = self
+                .client
+                .post(&url)
+                .form(&params)
+                .send()
+                .await
+                .map_err(|e| SpamError::Http(e.to_string()))?;
+            let debug_help = response
+                .headers()
+                .get("X-akismet-debug-help")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let body = response.text().await.map_err(|e| SpamError::Http(e.to_string()))?;
+            Self::parse_response(&body, debug_help.as_deref())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        assert!(Akismet::parse_response("true", None).unwrap());
+        assert!(!Akismet::parse_response("false\n", None).unwrap());
+        match Akismet::parse_response("invalid", Some("Empty \"blog\" value")) {
+            Err(SpamError::Api(msg)) => assert_eq!(msg, "Empty \"blog\" value"),
+            _ => panic!("Expected Api error"),
+        }
+    }
+}
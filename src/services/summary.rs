@@ -0,0 +1,250 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Unit `SummaryOptions::length` is measured in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryUnit {
+    Characters,
+    Words,
+    Sentences,
+}
+
+/// Text a summary is taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummarySource {
+    /// The article's meta description, falling back to the first paragraph
+    Excerpt,
+    /// The first paragraph of the rendered article, falling back to all content
+    FirstParagraph,
+    /// All content with markup stripped
+    Content,
+}
+
+impl SummaryUnit {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "characters" => Some(SummaryUnit::Characters),
+            "words" => Some(SummaryUnit::Words),
+            "sentences" => Some(SummaryUnit::Sentences),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SummaryUnit::Characters => "characters",
+            SummaryUnit::Words => "words",
+            SummaryUnit::Sentences => "sentences",
+        }
+    }
+}
+
+impl SummarySource {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "excerpt" => Some(SummarySource::Excerpt),
+            "first_paragraph" => Some(SummarySource::FirstParagraph),
+            "content" => Some(SummarySource::Content),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SummarySource::Excerpt => "excerpt",
+            SummarySource::FirstParagraph => "first_paragraph",
+            SummarySource::Content => "content",
+        }
+    }
+}
+
+/// How article summaries on the index, feeds and API are built
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SummaryOptions {
+    pub length: usize,
+    pub unit: SummaryUnit,
+    pub source: SummarySource,
+}
+
+pub const SUMMARY_LENGTH_KEY: &str = "summary_length";
+pub const SUMMARY_UNIT_KEY: &str = "summary_unit";
+pub const SUMMARY_SOURCE_KEY: &str = "summary_source";
+
+impl Default for SummaryOptions {
+    fn default() -> Self {
+        Self {
+            length: 100,
+            unit: SummaryUnit::Characters,
+            source: SummarySource::Content,
+        }
+    }
+}
+
+impl SummaryOptions {
+    /// Read from site settings; missing or invalid values keep their defaults
+    pub fn from_settings(settings: &HashMap<String, String>) -> Self {
+        let defaults = Self::default();
+        Self {
+            length: settings
+                .get(SUMMARY_LENGTH_KEY)
+                .and_then(|v| v.parse::<usize>().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or(defaults.length),
+            unit: settings
+                .get(SUMMARY_UNIT_KEY)
+                .and_then(|v| SummaryUnit::parse(v))
+                .unwrap_or(defaults.unit),
+            source: settings
+                .get(SUMMARY_SOURCE_KEY)
+                .and_then(|v| SummarySource::parse(v))
+                .unwrap_or(defaults.source),
+        }
+    }
+
+    /// Key/value pairs to store as site settings
+    pub fn to_settings(&self) -> [(&'static str, String); 3] {
+        [
+            (SUMMARY_LENGTH_KEY, self.length.to_string()),
+            (SUMMARY_UNIT_KEY, self.unit.as_str().to_string()),
+            (SUMMARY_SOURCE_KEY, self.source.as_str().to_string()),
+        ]
+    }
+}
+
+/// Text content of HTML with whitespace collapsed
+pub fn strip_html_tags(html: &str) -> String {
+    let mut result = String::new();
+    let mut in_tag = false;
+
+    for ch in html.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => result.push(ch),
+            _ => {}
+        }
+    }
+
+    result.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Inner HTML of the first `<p>` element
+fn first_paragraph(html: &str) -> Option<&str> {
+    let start = html.find("<p>").or_else(|| html.find("<p "))?;
+    let open_end = start + html[start..].find('>')? + 1;
+    let close = open_end + html[open_end..].find("</p>")?;
+    Some(&html[open_end..close])
+}
+
+/// Byte offset just past the first `count` sentences
+fn sentence_end(text: &str, count: usize) -> Option<usize> {
+    let mut seen = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, ch)) = chars.next() {
+        let terminal = matches!(ch, '。' | '！' | '？')
+            || (matches!(ch, '.' | '!' | '?') && chars.peek().is_none_or(|(_, next)| next.is_whitespace()));
+        if terminal {
+            seen += 1;
+            if seen == count {
+                return Some(i + ch.len_utf8());
+            }
+        }
+    }
+    None
+}
+
+/// Shorten text to `length` units; cut words and characters end with an ellipsis.
+/// Characters are counted as grapheme clusters, so combining marks such as Arabic
+/// and Hebrew vowel points stay with their letter, and the cut moves back to a word
+/// or CJK clause boundary when one is close (see `text::truncate`).
+pub fn truncate(text: &str, length: usize, unit: SummaryUnit) -> String {
+    let cut = match unit {
+        SummaryUnit::Characters => return super::text::truncate(text, length),
+        SummaryUnit::Words => text
+            .split_whitespace()
+            .nth(length)
+            .map(|word| word.as_ptr() as usize - text.as_ptr() as usize),
+        SummaryUnit::Sentences => sentence_end(text, length),
+    };
+    match cut {
+        Some(end) if !text[end..].trim().is_empty() => {
+            let kept = text[..end].trim_end();
+            // 整句截断本身就是完整的结尾，不加省略号
+            if unit == SummaryUnit::Sentences { kept.to_string() } else { format!("{}…", kept) }
+        }
+        _ => text.to_string(),
+    }
+}
+
+/// Summary of an article from its rendered HTML and optional explicit excerpt
+pub fn summarize(options: &SummaryOptions, excerpt: Option<&str>, html: &str) -> String {
+    let excerpt = excerpt.map(str::trim).filter(|e| !e.is_empty());
+    let text = match (options.source, excerpt) {
+        (SummarySource::Excerpt, Some(excerpt)) => excerpt.split_whitespace().collect::<Vec<_>>().join(" "),
+        (SummarySource::Excerpt, None) | (SummarySource::FirstParagraph, _) => {
+            strip_html_tags(first_paragraph(html).unwrap_or(html))
+        }
+        (SummarySource::Content, _) => strip_html_tags(html),
+    };
+    truncate(&text, options.length, options.unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &str = "<h1>Title</h1>\n<pre><code>fn main() {}</code></pre>\n\
+                        <p>First one. Second <em>one</em>! Third?</p>\n<p>Next paragraph.</p>";
+
+    fn options(length: usize, unit: SummaryUnit, source: SummarySource) -> SummaryOptions {
+        SummaryOptions { length, unit, source }
+    }
+
+    #[test]
+    fn test_sources() {
+        let first = options(100, SummaryUnit::Characters, SummarySource::FirstParagraph);
+        assert_eq!(summarize(&first, None, HTML), "First one. Second one! Third?");
+
+        let excerpt = options(100, SummaryUnit::Characters, SummarySource::Excerpt);
+        assert_eq!(summarize(&excerpt, Some("  Hand  written "), HTML), "Hand written");
+        assert_eq!(summarize(&excerpt, Some(" "), HTML), "First one. Second one! Third?");
+
+        let content = options(100, SummaryUnit::Characters, SummarySource::Content);
+        assert!(summarize(&content, Some("ignored"), HTML).starts_with("Title fn main() {} First one."));
+    }
+
+    #[test]
+    fn test_units() {
+        let text = "<p>First one. Second one! Third? 第四句。</p>";
+        let summary = |length, unit| summarize(&options(length, unit, SummarySource::FirstParagraph), None, text);
+        assert_eq!(summary(8, SummaryUnit::Characters), "First on…");
+        assert_eq!(summary(3, SummaryUnit::Words), "First one. Second…");
+        assert_eq!(summary(2, SummaryUnit::Sentences), "First one. Second one!");
+        assert_eq!(summary(4, SummaryUnit::Sentences), "First one. Second one! Third? 第四句。");
+        assert_eq!(summary(50, SummaryUnit::Words), "First one. Second one! Third? 第四句。");
+        // Decimal points are not sentence ends
+        let version = summarize(&options(1, SummaryUnit::Sentences, SummarySource::Content), None, "Version 1.5 is out. More");
+        assert_eq!(version, "Version 1.5 is out.");
+    }
+
+    #[test]
+    fn test_truncate_keeps_graphemes() {
+        // Harakat and combining accents are separate chars but belong to the letter before them
+        assert_eq!(truncate("مَرْحَبًا بِكُمْ", 2, SummaryUnit::Characters), "مَرْ…");
+        assert_eq!(truncate("e\u{301}e\u{301}e\u{301}", 2, SummaryUnit::Characters), "e\u{301}e\u{301}…");
+        assert_eq!(truncate("👨‍👩‍👧 family", 1, SummaryUnit::Characters), "👨‍👩‍👧…");
+    }
+
+    #[test]
+    fn test_settings_round_trip() {
+        let options = options(3, SummaryUnit::Sentences, SummarySource::Excerpt);
+        let settings: HashMap<String, String> =
+            options.to_settings().into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+        assert_eq!(SummaryOptions::from_settings(&settings), options);
+
+        let invalid: HashMap<String, String> = [(SUMMARY_LENGTH_KEY.to_string(), "0".to_string())].into();
+        assert_eq!(SummaryOptions::from_settings(&invalid), SummaryOptions::default());
+    }
+}
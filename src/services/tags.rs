@@ -0,0 +1,84 @@
+//! Article tags. Editors enter them as a comma separated list ("rust, web");
+//! each tag is stored once, compared without regard to case, and has a public
+//! listing at `/tag/{name}`.
+
+use serde::Serialize;
+
+/// Longer tags are cut to this many characters
+pub const MAX_TAG_LENGTH: usize = 50;
+
+/// A tag as shown under an article
+#[derive(Debug, Clone, Serialize)]
+pub struct TagLink {
+    pub name: String,
+    pub url: String,
+}
+
+impl TagLink {
+    pub fn new(name: String) -> Self {
+        Self { url: tag_url(&name), name }
+    }
+}
+
+/// Path of the tag's listing page
+pub fn tag_url(name: &str) -> String {
+    format!("/tag/{}", super::avatar::url_encode(name))
+}
+
+/// Tags in a comma separated list, in the order given: surrounding and
+/// repeated whitespace removed, empty and repeated entries (ignoring case)
+/// dropped; full-width commas separate too, and slashes, which would break
+/// the tag's URL, are removed
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for part in input.split([',', '，']) {
+        let cleaned: String = part.chars().filter(|c| !c.is_control() && *c != '/' && *c != '\\').collect();
+        let tag: String = cleaned
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(MAX_TAG_LENGTH)
+            .collect();
+        let tag = tag.trim_end().to_string();
+        if !tag.is_empty() && !tags.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// The form `parse_tags` reads, for prefilling the editor
+pub fn join_tags(tags: &[String]) -> String {
+    tags.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(parse_tags("rust, web ,actix"), vec!["rust", "web", "actix"]);
+        assert_eq!(parse_tags("Rust, rust, RUST"), vec!["Rust"]);
+        assert_eq!(parse_tags("  machine   learning ,, ,"), vec!["machine learning"]);
+        assert_eq!(parse_tags("编程，随笔"), vec!["编程", "随笔"]);
+        assert_eq!(parse_tags("c/c++, tcp\\ip"), vec!["cc++", "tcpip"]);
+        assert!(parse_tags("").is_empty());
+        assert_eq!(parse_tags(&"x".repeat(80))[0].chars().count(), MAX_TAG_LENGTH);
+    }
+
+    #[test]
+    fn test_tag_url() {
+        assert_eq!(tag_url("rust"), "/tag/rust");
+        assert_eq!(tag_url("machine learning"), "/tag/machine%20learning");
+        assert_eq!(TagLink::new("编程".to_string()).url, "/tag/%E7%BC%96%E7%A8%8B");
+    }
+
+    #[test]
+    fn test_join_tags() {
+        let tags = parse_tags("rust, web");
+        assert_eq!(join_tags(&tags), "rust, web");
+        assert_eq!(parse_tags(&join_tags(&tags)), tags);
+    }
+}
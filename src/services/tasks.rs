@@ -0,0 +1,80 @@
+use pulldown_cmark::{Event, Options, Parser};
+use serde::Serialize;
+use std::ops::Range;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum TaskError {
+    #[error("No task with index {0}")]
+    NotFound(usize),
+    #[error("Tasks from content snippets cannot be mapped to the article source")]
+    FromSnippet,
+}
+
+/// A task list checkbox: its index among the rendered checkboxes and where its
+/// `[ ]` / `[x]` marker sits in the markdown source
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TaskMarker {
+    pub index: usize,
+    pub checked: bool,
+    /// 1-based source line
+    pub line: usize,
+    #[serde(skip)]
+    pub range: Range<usize>,
+}
+
+/// Task markers in document order, which is the order the checkboxes appear in the rendered HTML
+pub fn task_markers(markdown: &str, options: Options) -> Vec<TaskMarker> {
+    Parser::new_ext(markdown, options)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::TaskListMarker(checked) => Some((checked, range)),
+            _ => None,
+        })
+        .enumerate()
+        .map(|(index, (checked, range))| TaskMarker {
+            index,
+            checked,
+            line: markdown[..range.start].matches('\n').count() + 1,
+            range,
+        })
+        .collect()
+}
+
+/// Markdown with one task marker rewritten; everything else is left byte for byte
+pub fn set_task(markdown: &str, marker: &TaskMarker, checked: bool) -> String {
+    let replacement = if checked { "[x]" } else { "[ ]" };
+    let mut updated = String::with_capacity(markdown.len());
+    updated.push_str(&markdown[..marker.range.start]);
+    updated.push_str(replacement);
+    updated.push_str(&markdown[marker.range.end..]);
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> Options {
+        let mut options = Options::empty();
+        options.insert(Options::ENABLE_TASKLISTS);
+        options
+    }
+
+    #[test]
+    fn test_task_markers() {
+        let md = "- [ ] one\n- [x] two\n  - [X] nested\n\n```\n- [ ] in code\n```\n\n> - [ ] quoted\n";
+        let markers = task_markers(md, options());
+        let summary: Vec<(usize, bool, usize)> = markers.iter().map(|m| (m.index, m.checked, m.line)).collect();
+        assert_eq!(summary, vec![(0, false, 1), (1, true, 2), (2, true, 3), (3, false, 9)]);
+        assert_eq!(&md[markers[2].range.clone()], "[X]");
+    }
+
+    #[test]
+    fn test_set_task() {
+        let md = "Todo:\n\n- [ ] write\n- [x] test\r\n";
+        let markers = task_markers(md, options());
+        let done = set_task(md, &markers[0], true);
+        assert_eq!(done, "Todo:\n\n- [x] write\n- [x] test\r\n");
+        assert_eq!(set_task(&done, &markers[1], false), "Todo:\n\n- [x] write\n- [ ] test\r\n");
+    }
+}
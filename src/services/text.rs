@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Writing direction of an article, emitted as the `dir` attribute of its content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextDirection {
+    /// Detected from the text when the article is shown
+    #[default]
+    Auto,
+    Ltr,
+    Rtl,
+}
+
+impl TextDirection {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "auto" => Some(TextDirection::Auto),
+            "ltr" => Some(TextDirection::Ltr),
+            "rtl" => Some(TextDirection::Rtl),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TextDirection::Auto => "auto",
+            TextDirection::Ltr => "ltr",
+            TextDirection::Rtl => "rtl",
+        }
+    }
+
+    /// `ltr` or `rtl` for the given text; `Auto` is resolved with `detect_direction`
+    pub fn resolve(self, text: &str) -> Self {
+        match self {
+            TextDirection::Auto => detect_direction(text),
+            direction => direction,
+        }
+    }
+}
+
+/// Letters of right-to-left scripts: Hebrew, Arabic, Syriac, Thaana, N'Ko and
+/// their presentation forms
+pub fn is_rtl_char(ch: char) -> bool {
+    matches!(ch as u32,
+        0x0590..=0x08FF
+        | 0xFB1D..=0xFDFF
+        | 0xFE70..=0xFEFF
+        | 0x10800..=0x10FFF
+        | 0x1E800..=0x1EFFF)
+        && ch.is_alphabetic()
+}
+
+/// Direction of the script most letters are written in; text without letters is `Ltr`.
+/// Counting all letters rather than taking the first one keeps an Arabic post that
+/// opens with a Latin product name right-to-left.
+pub fn detect_direction(text: &str) -> TextDirection {
+    let (rtl, ltr) = text
+        .chars()
+        .filter(|ch| ch.is_alphabetic())
+        .fold((0usize, 0usize), |(rtl, ltr), ch| {
+            if is_rtl_char(ch) { (rtl + 1, ltr) } else { (rtl, ltr + 1) }
+        });
+    if rtl > ltr { TextDirection::Rtl } else { TextDirection::Ltr }
+}
+
+/// Scripts written without spaces between words: CJK ideographs, kana, hangul
+/// and the fullwidth forms used alongside them
+pub fn is_cjk_char(ch: char) -> bool {
+    matches!(ch as u32,
+        0x1100..=0x11FF
+        | 0x2E80..=0x2FDF
+        | 0x3000..=0x30FF
+        | 0x3100..=0x31FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xAC00..=0xD7AF
+        | 0xF900..=0xFAFF
+        | 0xFF00..=0xFFEF
+        | 0x20000..=0x2FA1F)
+}
+
+/// CJK punctuation after which a line or summary can end naturally
+fn is_cjk_break(ch: char) -> bool {
+    matches!(ch, '。' | '，' | '、' | '；' | '：' | '！' | '？' | '」' | '』' | '）' | '】' | '》' | '…')
+}
+
+/// Clause punctuation that looks wrong directly before an ellipsis
+fn is_dangling_punctuation(ch: char) -> bool {
+    matches!(ch, '，' | '、' | '；' | '：' | ',' | ';' | ':' | '-' | '—' | '(' | '（' | '「' | '《')
+}
+
+/// The longest prefix of `text` with at most `max` grapheme clusters that ends at a
+/// natural break. A break after CJK punctuation or before whitespace is preferred
+/// when one lies in the last third of the allowance; otherwise the text is cut at
+/// `max`, which only splits a word in scripts that use spaces.
+pub fn truncate_at_boundary(text: &str, max: usize) -> &str {
+    let graphemes: Vec<(usize, &str)> = text.grapheme_indices(true).collect();
+    if graphemes.len() <= max {
+        return text;
+    }
+    let breaks_before = |i: usize| {
+        graphemes[i].1.starts_with(char::is_whitespace)
+            || graphemes[i - 1].1.chars().next_back().is_some_and(is_cjk_break)
+    };
+    let earliest = (max - max / 3).max(1);
+    let cut = (earliest..=max).rev().find(|&i| breaks_before(i)).unwrap_or(max);
+    text[..graphemes[cut].0].trim_end()
+}
+
+/// Shorten text to `max` grapheme clusters plus an ellipsis, cutting at a natural
+/// break (see `truncate_at_boundary`). CJK text ends with `……`, other text with `…`;
+/// a cut right after a full sentence needs neither.
+pub fn truncate(text: &str, max: usize) -> String {
+    let kept = truncate_at_boundary(text, max);
+    if kept.len() == text.len() {
+        return text.to_string();
+    }
+    let kept = kept.trim_end_matches(is_dangling_punctuation).trim_end();
+    if kept.ends_with(['。', '！', '？', '.', '!', '?']) {
+        return kept.to_string();
+    }
+    let ellipsis = if kept.chars().next_back().is_some_and(is_cjk_char) { "……" } else { "…" };
+    format!("{}{}", kept, ellipsis)
+}
+
+/// At most `max_bytes` of `text`, cut between grapheme clusters; for limits measured
+/// in bytes such as file names
+pub fn truncate_bytes(text: &str, max_bytes: usize) -> &str {
+    let end = text
+        .grapheme_indices(true)
+        .map(|(i, g)| i + g.len())
+        .take_while(|&end| end <= max_bytes)
+        .last()
+        .unwrap_or(0);
+    &text[..end]
+}
+
+/// Keep the last word of a heading from ending up alone on its own line: the space
+/// before it becomes a no-break space, or in CJK text a word joiner ties the last
+/// two characters together
+pub fn no_widow(text: &str) -> String {
+    let text = text.trim_end();
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() >= 3 {
+        let last = words[words.len() - 1];
+        let before = text[..text.len() - last.len()].trim_end();
+        return format!("{}\u{a0}{}", before, last);
+    }
+    let graphemes: Vec<&str> = text.graphemes(true).collect();
+    let n = graphemes.len();
+    if n >= 4 && graphemes[n - 2..].iter().all(|g| g.chars().next().is_some_and(is_cjk_char)) {
+        return format!("{}\u{2060}{}", graphemes[..n - 1].concat(), graphemes[n - 1]);
+    }
+    text.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_direction() {
+        assert_eq!(detect_direction("Hello, world"), TextDirection::Ltr);
+        assert_eq!(detect_direction("مرحبا بالعالم"), TextDirection::Rtl);
+        assert_eq!(detect_direction("שלום עולם"), TextDirection::Rtl);
+        assert_eq!(detect_direction("Rust هي لغة برمجة سريعة وآمنة"), TextDirection::Rtl);
+        assert_eq!(detect_direction("你好，世界"), TextDirection::Ltr);
+        assert_eq!(detect_direction("123 ...؟"), TextDirection::Ltr);
+    }
+
+    #[test]
+    fn test_truncate_breaks() {
+        // Latin text backs off to the last word boundary
+        assert_eq!(truncate("The quick brown fox jumps", 12), "The quick…");
+        // CJK text prefers clause punctuation, and drops a trailing comma
+        assert_eq!(truncate("今天天气很好，我们去公园散步吧", 9), "今天天气很好……");
+        assert_eq!(truncate("第一句。第二句很长很长很长", 6), "第一句。");
+        assert_eq!(truncate("第一句。第二句很长很长很长", 9), "第一句。第二句很长……");
+        // No break in reach: cut between any two CJK characters
+        assert_eq!(truncate("一二三四五六七八九十", 4), "一二三四……");
+        assert_eq!(truncate("short", 10), "short");
+    }
+
+    #[test]
+    fn test_truncate_bytes() {
+        assert_eq!(truncate_bytes("日本語", 7), "日本");
+        assert_eq!(truncate_bytes("e\u{301}x", 2), "");
+        assert_eq!(truncate_bytes("abc", 10), "abc");
+    }
+
+    #[test]
+    fn test_no_widow() {
+        assert_eq!(no_widow("A title with words"), "A title with\u{a0}words");
+        assert_eq!(no_widow("Two words"), "Two words");
+        assert_eq!(no_widow("深入理解所有权"), "深入理解所有\u{2060}权");
+        assert_eq!(no_widow("短标"), "短标");
+    }
+
+    #[test]
+    fn test_parse_and_resolve() {
+        assert_eq!(TextDirection::parse(" RTL "), Some(TextDirection::Rtl));
+        assert_eq!(TextDirection::parse("sideways"), None);
+        assert_eq!(TextDirection::Auto.resolve("עברית"), TextDirection::Rtl);
+        assert_eq!(TextDirection::Ltr.resolve("עברית"), TextDirection::Ltr);
+    }
+}
@@ -0,0 +1,78 @@
+//! The reader's colour theme. The choice is kept in a cookie set by
+//! `POST /prefs/theme`, so the page is rendered with the right theme on the
+//! server: nothing flashes while scripts load, and it works without JavaScript.
+
+use serde::Serialize;
+
+/// Cookie holding the chosen theme
+pub const THEME_COOKIE: &str = "theme";
+/// How long the choice is remembered, in days
+pub const THEME_COOKIE_DAYS: i64 = 365;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Follow the operating system's light or dark setting
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "auto" => Some(Theme::Auto),
+            "light" => Some(Theme::Light),
+            "dark" => Some(Theme::Dark),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Theme::Auto => "auto",
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+        }
+    }
+
+    /// Theme stored in the cookie; missing or unknown values mean `Auto`
+    pub fn from_cookie(value: Option<&str>) -> Self {
+        value.and_then(Self::parse).unwrap_or_default()
+    }
+}
+
+/// Where to send the reader after changing the theme: a path on this site,
+/// or `/` for anything that could lead elsewhere
+pub fn return_path(next: Option<&str>) -> &str {
+    match next {
+        Some(path) if path.starts_with('/') && !path.starts_with("//") && !path.contains('\\') => path,
+        _ => "/",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Theme::parse("Dark"), Some(Theme::Dark));
+        assert_eq!(Theme::parse(" light "), Some(Theme::Light));
+        assert_eq!(Theme::parse("sepia"), None);
+        assert_eq!(Theme::from_cookie(Some("dark")), Theme::Dark);
+        assert_eq!(Theme::from_cookie(Some("x")), Theme::Auto);
+        assert_eq!(Theme::from_cookie(None), Theme::Auto);
+        assert_eq!(serde_json::to_value(Theme::Light).unwrap(), "light");
+    }
+
+    #[test]
+    fn test_return_path() {
+        assert_eq!(return_path(Some("/post/3?page=2")), "/post/3?page=2");
+        assert_eq!(return_path(Some("//evil.example/")), "/");
+        assert_eq!(return_path(Some("/\\evil.example")), "/");
+        assert_eq!(return_path(Some("https://evil.example/")), "/");
+        assert_eq!(return_path(None), "/");
+    }
+}
@@ -0,0 +1,198 @@
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A heading in document order, as found by the heading-collection pass
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heading {
+    pub level: u8,
+    pub text: String,
+    /// URL fragment, unique within the document
+    pub anchor: String,
+}
+
+/// A node of the nested table of contents
+#[derive(Debug, Serialize, PartialEq)]
+pub struct TocEntry {
+    pub level: u8,
+    pub text: String,
+    pub anchor: String,
+    pub children: Vec<TocEntry>,
+}
+
+/// GitHub-style slug: lowercase, letters/digits kept (including CJK), spaces and
+/// hyphens become `-`, everything else is dropped
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for ch in text.trim().chars() {
+        if ch.is_alphanumeric() || ch == '_' {
+            slug.extend(ch.to_lowercase());
+        } else if ch == ' ' || ch == '-' {
+            slug.push('-');
+        }
+    }
+    if slug.is_empty() {
+        slug.push_str("section");
+    }
+    slug
+}
+
+/// Whether an author-supplied heading id or class (`{#id .class}`) is safe to
+/// emit: an ASCII letter followed by up to 63 letters, digits, `-` or `_`
+pub fn is_safe_identifier(value: &str) -> bool {
+    let mut chars = value.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && value.len() <= 64
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Collect headings with their plain text. An explicit `{#id}` is used as the
+/// anchor when it is safe; repeated generated anchors get `-1`, `-2`, ... suffixes
+pub fn collect_headings(markdown: &str, options: Options) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut current: Option<(u8, String, Option<String>)> = None;
+
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Start(Tag::Heading(level, id, _)) => {
+                let id = id.filter(|id| is_safe_identifier(id)).map(str::to_string);
+                current = Some((level as u8, String::new(), id));
+            }
+            Event::End(Tag::Heading(..)) => {
+                if let Some((level, text, id)) = current.take() {
+                    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    let anchor = match id {
+                        Some(id) => {
+                            seen.entry(id.clone()).or_insert(1);
+                            id
+                        }
+                        None => {
+                            let base = slugify(&text);
+                            let count = seen.entry(base.clone()).or_insert(0);
+                            let anchor = if *count == 0 { base } else { format!("{}-{}", base, count) };
+                            *count += 1;
+                            anchor
+                        }
+                    };
+                    headings.push(Heading { level, text, anchor });
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, buffer, _)) = current.as_mut() {
+                    buffer.push_str(&text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some((_, buffer, _)) = current.as_mut() {
+                    buffer.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+    headings
+}
+
+/// Nest headings under the closest preceding heading of a lower level
+pub fn build_tree(headings: Vec<Heading>) -> Vec<TocEntry> {
+    let mut roots: Vec<TocEntry> = Vec::new();
+    // Open ancestors of the next heading, outermost first
+    let mut stack: Vec<TocEntry> = Vec::new();
+
+    for heading in headings {
+        while stack.last().is_some_and(|open| open.level >= heading.level) {
+            close_last(&mut stack, &mut roots);
+        }
+        stack.push(TocEntry {
+            level: heading.level,
+            text: heading.text,
+            anchor: heading.anchor,
+            children: Vec::new(),
+        });
+    }
+    while !stack.is_empty() {
+        close_last(&mut stack, &mut roots);
+    }
+    roots
+}
+
+/// A table of contents line for templates, indented `depth` levels below the
+/// highest heading of the document
+#[derive(Debug, Serialize, PartialEq)]
+pub struct TocLink {
+    pub text: String,
+    pub anchor: String,
+    pub depth: u8,
+}
+
+/// Flat table of contents in document order
+pub fn links(headings: &[Heading]) -> Vec<TocLink> {
+    let top = headings.iter().map(|h| h.level).min().unwrap_or(1);
+    headings
+        .iter()
+        .map(|h| TocLink { text: h.text.clone(), anchor: h.anchor.clone(), depth: h.level - top })
+        .collect()
+}
+
+fn close_last(stack: &mut Vec<TocEntry>, roots: &mut Vec<TocEntry>) {
+    if let Some(entry) = stack.pop() {
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(entry),
+            None => roots.push(entry),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  Rust & `async`  "), "rust--async");
+        assert_eq!(slugify("部署 指南"), "部署-指南");
+        assert_eq!(slugify("???"), "section");
+    }
+
+    #[test]
+    fn test_collect_headings() {
+        let md = "# Intro\n\ntext\n\n## Setup `cargo`\n\n## Intro\n\n```\n# not a heading\n```\n";
+        let headings = collect_headings(md, Options::empty());
+        let summary: Vec<(u8, &str, &str)> =
+            headings.iter().map(|h| (h.level, h.text.as_str(), h.anchor.as_str())).collect();
+        assert_eq!(summary, vec![(1, "Intro", "intro"), (2, "Setup cargo", "setup-cargo"), (2, "Intro", "intro-1")]);
+    }
+
+    #[test]
+    fn test_explicit_heading_ids() {
+        let md = "# Intro {#start}\n\n## Intro\n\n## Bad {#1bad}\n\n## Quote {#a\"b}\n";
+        let headings = collect_headings(md, Options::ENABLE_HEADING_ATTRIBUTES);
+        let anchors: Vec<&str> = headings.iter().map(|h| h.anchor.as_str()).collect();
+        assert_eq!(anchors, vec!["start", "intro", "bad", "quote"]);
+        assert!(is_safe_identifier("section-2_b"));
+        assert!(!is_safe_identifier("2nd"));
+        assert!(!is_safe_identifier("x y"));
+        assert!(!is_safe_identifier(""));
+    }
+
+    #[test]
+    fn test_build_tree() {
+        let md = "## A\n### A.1\n#### A.1.a\n## B\n# C\n### C.1\n";
+        let tree = build_tree(collect_headings(md, Options::empty()));
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree[0].text, "A");
+        assert_eq!(tree[0].children[0].text, "A.1");
+        assert_eq!(tree[0].children[0].children[0].text, "A.1.a");
+        assert!(tree[1].children.is_empty());
+        assert_eq!(tree[2].children[0].text, "C.1");
+    }
+
+    #[test]
+    fn test_links() {
+        let links = links(&collect_headings("## A\n### A.1\n## B\n", Options::empty()));
+        let summary: Vec<(&str, u8)> = links.iter().map(|l| (l.anchor.as_str(), l.depth)).collect();
+        assert_eq!(summary, vec![("a", 0), ("a1", 1), ("b", 0)]);
+    }
+}
@@ -0,0 +1,80 @@
+//! Deleted articles first go to the trash (`articles.deleted_at` is set) and are
+//! removed for good once they have been there longer than the retention period.
+
+use chrono::{Duration, NaiveDateTime};
+use serde::Serialize;
+use std::collections::HashMap;
+
+pub const TRASH_RETENTION_DAYS_KEY: &str = "trash_retention_days";
+
+/// Longest retention that can be configured, about ten years
+pub const MAX_RETENTION_DAYS: u32 = 3650;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TrashOptions {
+    /// Days an article stays in the trash before it is purged; 0 keeps it until removed by hand
+    pub retention_days: u32,
+}
+
+impl Default for TrashOptions {
+    fn default() -> Self {
+        Self { retention_days: 30 }
+    }
+}
+
+impl TrashOptions {
+    pub fn from_settings(settings: &HashMap<String, String>) -> Self {
+        Self {
+            retention_days: settings
+                .get(TRASH_RETENTION_DAYS_KEY)
+                .and_then(|v| v.parse::<u32>().ok())
+                .filter(|&days| days <= MAX_RETENTION_DAYS)
+                .unwrap_or(Self::default().retention_days),
+        }
+    }
+
+    pub fn to_settings(&self) -> [(&'static str, String); 1] {
+        [(TRASH_RETENTION_DAYS_KEY, self.retention_days.to_string())]
+    }
+
+    /// Articles trashed before this time (UTC, as stored in `deleted_at`) are due
+    /// for purging; `None` when automatic purging is off
+    pub fn purge_before(&self, now: NaiveDateTime) -> Option<String> {
+        (self.retention_days > 0).then(|| {
+            (now - Duration::days(i64::from(self.retention_days)))
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+    }
+
+    /// When an article trashed at `deleted_at` will be purged, in the same
+    /// format; `None` when automatic purging is off or the time is unreadable
+    pub fn purge_at(&self, deleted_at: &str) -> Option<String> {
+        let deleted_at = NaiveDateTime::parse_from_str(deleted_at, "%Y-%m-%d %H:%M:%S").ok()?;
+        (self.retention_days > 0).then(|| {
+            (deleted_at + Duration::days(i64::from(self.retention_days)))
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trash_options() {
+        let now = NaiveDateTime::parse_from_str("2025-03-31 08:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(TrashOptions::default().purge_before(now).as_deref(), Some("2025-03-01 08:00:00"));
+        assert_eq!(TrashOptions { retention_days: 0 }.purge_before(now), None);
+        assert_eq!(TrashOptions::default().purge_at("2025-03-01 08:00:00").as_deref(), Some("2025-03-31 08:00:00"));
+        assert_eq!(TrashOptions { retention_days: 0 }.purge_at("2025-03-01 08:00:00"), None);
+        assert_eq!(TrashOptions::default().purge_at("yesterday"), None);
+
+        let settings: HashMap<String, String> = [(TRASH_RETENTION_DAYS_KEY.to_string(), "7".to_string())].into();
+        assert_eq!(TrashOptions::from_settings(&settings).retention_days, 7);
+        let invalid: HashMap<String, String> = [(TRASH_RETENTION_DAYS_KEY.to_string(), "99999".to_string())].into();
+        assert_eq!(TrashOptions::from_settings(&invalid), TrashOptions::default());
+    }
+}
@@ -0,0 +1,273 @@
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use super::toc::{collect_headings, is_safe_identifier, slugify};
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    BrokenLink,
+    MissingUpload,
+    EmptyAltText,
+    DuplicateAnchor,
+    OversizedCodeBlock,
+}
+
+/// A problem found in submitted markdown; `line` is 1-based
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub line: usize,
+    pub target: String,
+    pub message: String,
+}
+
+/// Internal target that can only be checked against the database
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Reference {
+    Post(i64),
+    Upload(String),
+}
+
+/// A `Reference` and where it appeared
+#[derive(Debug, PartialEq)]
+pub struct ReferenceSite {
+    pub reference: Reference,
+    pub line: usize,
+    pub target: String,
+}
+
+impl ReferenceSite {
+    /// Issue to report when the referenced article or upload does not exist
+    pub fn missing(&self) -> Issue {
+        let (kind, message) = match &self.reference {
+            Reference::Post(id) => (IssueKind::BrokenLink, format!("Article {} does not exist", id)),
+            Reference::Upload(name) => (IssueKind::MissingUpload, format!("Upload {} does not exist", name)),
+        };
+        Issue { kind, line: self.line, target: self.target.clone(), message }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Findings {
+    /// Problems detectable from the markdown alone
+    pub issues: Vec<Issue>,
+    /// Article and upload references still to be looked up
+    pub references: Vec<ReferenceSite>,
+}
+
+/// 1-based line of a byte offset
+fn line_of(line_starts: &[usize], offset: usize) -> usize {
+    line_starts.partition_point(|&start| start <= offset)
+}
+
+/// Site-relative path for links to this site, None for external links
+fn internal_path<'a>(dest: &'a str, site_url: &str) -> Option<&'a str> {
+    let dest = dest.trim();
+    if !site_url.is_empty()
+        && let Some(rest) = dest.strip_prefix(site_url)
+    {
+        return Some(if rest.is_empty() { "/" } else { rest }).filter(|p| p.starts_with('/'));
+    }
+    (dest.starts_with('/') && !dest.starts_with("//")).then_some(dest)
+}
+
+fn classify(path: &str, target: &str, line: usize, findings: &mut Findings) {
+    let path = path.split(['?', '#']).next().unwrap_or(path).trim_end_matches('/');
+    let reference = if let Some(id) = path.strip_prefix("/post/") {
+        match id.parse::<i64>() {
+            Ok(id) => Reference::Post(id),
+            Err(_) => {
+                findings.issues.push(Issue {
+                    kind: IssueKind::BrokenLink,
+                    line,
+                    target: target.to_string(),
+                    message: "Article links must look like /post/<id>".to_string(),
+                });
+                return;
+            }
+        }
+    } else if let Some(name) = path.strip_prefix("/media/") {
+        Reference::Upload(name.to_string())
+    } else {
+        return;
+    };
+    findings.references.push(ReferenceSite { reference, line, target: target.to_string() });
+}
+
+/// Check links, images and headings. Fragment links are checked against the
+/// document's own heading anchors; `/post/<id>` and `/media/<name>` targets are
+/// returned as references for the caller to look up.
+pub fn check(markdown: &str, options: Options, site_url: &str) -> Findings {
+    let mut findings = Findings::default();
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(markdown.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let anchors: HashSet<String> = collect_headings(markdown, options).into_iter().map(|h| h.anchor).collect();
+
+    // Heading and image currently open, with the line they started on and their text so far
+    let mut heading: Option<(usize, String, Option<String>)> = None;
+    let mut image: Option<(usize, String, String)> = None;
+    let mut first_heading_line: HashMap<String, usize> = HashMap::new();
+
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        let line = line_of(&line_starts, range.start);
+        match event {
+            Event::Start(Tag::Heading(_, id, _)) => {
+                let id = id.filter(|id| is_safe_identifier(id)).map(str::to_string);
+                heading = Some((line, String::new(), id));
+            }
+            Event::End(Tag::Heading(..)) => {
+                if let Some((line, text, id)) = heading.take() {
+                    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    let anchor = id.unwrap_or_else(|| slugify(&text));
+                    match first_heading_line.get(&anchor) {
+                        Some(first) => findings.issues.push(Issue {
+                            kind: IssueKind::DuplicateAnchor,
+                            line,
+                            target: format!("#{}", anchor),
+                            message: format!("Heading \"{}\" has the same anchor as the heading on line {}", text, first),
+                        }),
+                        None => {
+                            first_heading_line.insert(anchor, line);
+                        }
+                    }
+                }
+            }
+            Event::Start(Tag::Link(_, dest, _)) => {
+                if let Some(fragment) = dest.strip_prefix('#') {
+                    if !fragment.is_empty() && !anchors.contains(fragment) {
+                        findings.issues.push(Issue {
+                            kind: IssueKind::BrokenLink,
+                            line,
+                            target: dest.to_string(),
+                            message: format!("No heading with anchor #{}", fragment),
+                        });
+                    }
+                } else if let Some(path) = internal_path(&dest, site_url) {
+                    classify(path, &dest, line, &mut findings);
+                }
+            }
+            Event::Start(Tag::Image(_, dest, _)) => image = Some((line, dest.to_string(), String::new())),
+            Event::End(Tag::Image(..)) => {
+                if let Some((line, dest, alt)) = image.take() {
+                    if alt.trim().is_empty() {
+                        findings.issues.push(Issue {
+                            kind: IssueKind::EmptyAltText,
+                            line,
+                            target: dest.clone(),
+                            message: "Image has no alt text".to_string(),
+                        });
+                    }
+                    if let Some(path) = internal_path(&dest, site_url) {
+                        classify(path, &dest, line, &mut findings);
+                    }
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, _, alt)) = image.as_mut() {
+                    alt.push_str(&text);
+                } else if let Some((_, buffer, _)) = heading.as_mut() {
+                    buffer.push_str(&text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some((_, buffer, _)) = heading.as_mut() {
+                    buffer.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+    findings
+}
+
+/// Code blocks longer than `limit` bytes, which rendering truncates; 0 means no limit
+pub fn oversized_code_blocks(markdown: &str, options: Options, limit: usize) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    if limit == 0 {
+        return issues;
+    }
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(markdown.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    // Line the open block started on, its language and its size so far
+    let mut block: Option<(usize, String, usize)> = None;
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(lang) => lang.to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                block = Some((line_of(&line_starts, range.start), lang, 0));
+            }
+            Event::Text(text) => {
+                if let Some((_, _, size)) = block.as_mut() {
+                    *size += text.len();
+                }
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                if let Some((line, lang, size)) = block.take()
+                    && size > limit
+                {
+                    issues.push(Issue {
+                        kind: IssueKind::OversizedCodeBlock,
+                        line,
+                        target: lang,
+                        message: format!("Code block is {} bytes; only the first {} will be shown", size, limit),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fragments_and_duplicate_anchors() {
+        let md = "# Intro\n\nSee [setup](#setup) and [missing](#nope).\n\n## Setup\n\n## Setup\n";
+        let findings = check(md, Options::empty(), "");
+        let kinds: Vec<(IssueKind, usize)> = findings.issues.iter().map(|i| (i.kind, i.line)).collect();
+        assert_eq!(kinds, vec![(IssueKind::BrokenLink, 3), (IssueKind::DuplicateAnchor, 7)]);
+        assert_eq!(findings.issues[0].target, "#nope");
+        assert!(findings.references.is_empty());
+
+        // Explicit ids are the anchors links and duplicates are checked against
+        let md = "# Setup {#install}\n\n[go](#install) [old](#setup)\n\n## Other {#install}\n";
+        let findings = check(md, Options::ENABLE_HEADING_ATTRIBUTES, "");
+        let kinds: Vec<(IssueKind, usize)> = findings.issues.iter().map(|i| (i.kind, i.line)).collect();
+        assert_eq!(kinds, vec![(IssueKind::BrokenLink, 3), (IssueKind::DuplicateAnchor, 5)]);
+    }
+
+    #[test]
+    fn test_oversized_code_blocks() {
+        let md = "Intro\n\n```rust\nfn main() {}\n```\n\n    short\n";
+        let issues = oversized_code_blocks(md, Options::empty(), 8);
+        assert_eq!(issues.len(), 1);
+        assert_eq!((issues[0].kind, issues[0].line, issues[0].target.as_str()), (IssueKind::OversizedCodeBlock, 3, "rust"));
+        assert!(oversized_code_blocks(md, Options::empty(), 0).is_empty());
+    }
+
+    #[test]
+    fn test_images_and_references() {
+        let md = "![](/media/abc.png)\n\n![Chart](https://cdn.example/c.png)\n\n[post](/post/12#comments) \
+                  [abs](https://blog.example/post/3) [bad](/post/latest) [ext](https://other.example/post/9)\n";
+        let findings = check(md, Options::empty(), "https://blog.example");
+        let kinds: Vec<IssueKind> = findings.issues.iter().map(|i| i.kind).collect();
+        assert_eq!(kinds, vec![IssueKind::EmptyAltText, IssueKind::BrokenLink]);
+        let references: Vec<&Reference> = findings.references.iter().map(|r| &r.reference).collect();
+        assert_eq!(
+            references,
+            vec![&Reference::Upload("abc.png".to_string()), &Reference::Post(12), &Reference::Post(3)]
+        );
+        assert_eq!(findings.references[1].line, 5);
+        assert_eq!(findings.references[1].missing().kind, IssueKind::BrokenLink);
+    }
+}
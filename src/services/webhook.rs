@@ -0,0 +1,96 @@
+//! The `webhook` plugin: POSTs each content event as JSON to `WEBHOOK_URL`,
+//! e.g. `{"event":"publish","article_id":3,"url":"https://example.com/post/3"}`.
+//! With `WEBHOOK_SECRET` set, the body is signed in an
+//! `X-Bluster-Signature: sha256=<hex HMAC>` header.
+
+use super::plugins::{ContentEvent, Plugin, PluginConfig, PluginError};
+use super::signing::Signer;
+use futures_util::future::BoxFuture;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+pub const NAME: &str = "webhook";
+pub const SIGNATURE_HEADER: &str = "X-Bluster-Signature";
+
+pub struct WebhookPlugin {
+    client: reqwest::Client,
+    url: String,
+    signer: Option<Signer>,
+}
+
+impl WebhookPlugin {
+    pub fn new(url: &str, secret: Option<&str>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .user_agent(concat!("Bluster/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            url: url.to_string(),
+            signer: secret.map(|secret| Signer::new(secret.as_bytes())),
+        }
+    }
+
+    /// Header value for `body`, when a secret is configured
+    fn signature(&self, body: &str) -> Option<String> {
+        self.signer.as_ref().map(|signer| format!("sha256={}", signer.digest(body)))
+    }
+
+    async fn send(&self, event: &ContentEvent) -> Result<(), PluginError> {
+        let body = serde_json::to_string(event).map_err(|e| PluginError::Failed(e.to_string()))?;
+        let mut request = self.client.post(&self.url).header("Content-Type", "application/json");
+        if let Some(signature) = self.signature(&body) {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+        let response = request.body(body).send().await.map_err(|e| PluginError::Failed(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(PluginError::Failed(format!("{} answered HTTP {}", self.url, response.status())));
+        }
+        Ok(())
+    }
+}
+
+/// Reads `WEBHOOK_URL` (required) and `WEBHOOK_SECRET`
+pub fn factory(_config: &PluginConfig) -> Result<Arc<dyn Plugin>, PluginError> {
+    let url = std::env::var("WEBHOOK_URL").unwrap_or_default();
+    let url = url.trim();
+    if reqwest::Url::parse(url).is_err() {
+        return Err(PluginError::Config("WEBHOOK_URL must be an absolute URL".to_string()));
+    }
+    let secret = std::env::var("WEBHOOK_SECRET").ok().filter(|s| !s.trim().is_empty());
+    Ok(Arc::new(WebhookPlugin::new(url, secret.as_deref().map(str::trim))))
+}
+
+impl Plugin for WebhookPlugin {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn on_publish<'a>(&'a self, _pool: &'a SqlitePool, event: &'a ContentEvent) -> BoxFuture<'a, Result<(), PluginError>> {
+        Box::pin(self.send(event))
+    }
+
+    fn on_delete<'a>(&'a self, _pool: &'a SqlitePool, event: &'a ContentEvent) -> BoxFuture<'a, Result<(), PluginError>> {
+        Box::pin(self.send(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::plugins::ContentEventKind;
+
+    #[test]
+    fn test_payload_and_signature() {
+        let event = ContentEvent::new(ContentEventKind::Delete, 3, "https://example.com");
+        let body = serde_json::to_string(&event).unwrap();
+        assert_eq!(body, r#"{"event":"delete","article_id":3,"url":"https://example.com/post/3"}"#);
+
+        let signed = WebhookPlugin::new("https://hooks.example/blog", Some("secret"));
+        let signature = signed.signature(&body).unwrap();
+        let digest = signature.strip_prefix("sha256=").unwrap();
+        assert!(Signer::new(b"secret").verify_digest(&body, digest));
+        assert_eq!(WebhookPlugin::new("https://hooks.example/blog", None).signature(&body), None);
+    }
+}
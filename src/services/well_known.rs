@@ -0,0 +1,205 @@
+//! `/robots.txt` and `/.well-known/security.txt` (RFC 9116), generated from site settings
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+pub const ROBOTS_DISALLOW_ADMIN_KEY: &str = "robots_disallow_admin";
+pub const ROBOTS_EXTRA_RULES_KEY: &str = "robots_extra_rules";
+pub const SECURITY_CONTACT_KEY: &str = "security_contact";
+pub const SECURITY_EXPIRES_KEY: &str = "security_expires";
+pub const SECURITY_POLICY_KEY: &str = "security_policy";
+pub const SECURITY_LANGUAGES_KEY: &str = "security_languages";
+
+/// Days ahead `Expires` is set when no date is configured; RFC 9116 recommends less than a year
+const DEFAULT_EXPIRY_DAYS: i64 = 180;
+
+/// Paths kept out of crawlers when `disallow_admin` is on
+const ADMIN_PATHS: &[&str] = &["/admin", "/login"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RobotsOptions {
+    pub disallow_admin: bool,
+    /// Extra lines appended to the `User-agent: *` group, e.g. `Disallow: /drafts/`
+    pub extra_rules: String,
+}
+
+impl Default for RobotsOptions {
+    fn default() -> Self {
+        Self {
+            disallow_admin: true,
+            extra_rules: String::new(),
+        }
+    }
+}
+
+impl RobotsOptions {
+    pub fn from_settings(settings: &HashMap<String, String>) -> Self {
+        let defaults = Self::default();
+        Self {
+            disallow_admin: settings
+                .get(ROBOTS_DISALLOW_ADMIN_KEY)
+                .map(|v| v == "true")
+                .unwrap_or(defaults.disallow_admin),
+            extra_rules: settings.get(ROBOTS_EXTRA_RULES_KEY).cloned().unwrap_or(defaults.extra_rules),
+        }
+    }
+
+    pub fn to_settings(&self) -> [(&'static str, String); 2] {
+        [
+            (ROBOTS_DISALLOW_ADMIN_KEY, self.disallow_admin.to_string()),
+            (ROBOTS_EXTRA_RULES_KEY, self.extra_rules.clone()),
+        ]
+    }
+}
+
+/// robots.txt for `site_url` (no trailing slash), pointing crawlers at the sitemap index
+pub fn robots_txt(options: &RobotsOptions, site_url: &str) -> String {
+    let mut txt = String::from("User-agent: *\n");
+    if options.disallow_admin {
+        for path in ADMIN_PATHS {
+            txt.push_str(&format!("Disallow: {}\n", path));
+        }
+    }
+    for line in options.extra_rules.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        txt.push_str(line);
+        txt.push('\n');
+    }
+    if !options.disallow_admin && options.extra_rules.trim().is_empty() {
+        // An empty group would be invalid; this allows everything
+        txt.push_str("Disallow:\n");
+    }
+    txt.push_str(&format!("\nSitemap: {}/sitemap.xml\n", site_url));
+    txt
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SecurityTxtOptions {
+    /// `mailto:`, `https:` or `tel:` URIs; security.txt is only served when there is one
+    pub contacts: Vec<String>,
+    /// Last day the file is valid (`YYYY-MM-DD`)
+    pub expires: Option<String>,
+    pub policy: Option<String>,
+    /// e.g. `en, zh`
+    pub preferred_languages: Option<String>,
+}
+
+/// A contact as an RFC 9116 URI: bare e-mail addresses get `mailto:`; anything
+/// else that is not a mailto, https or tel URI is rejected
+pub fn normalize_contact(contact: &str) -> Option<String> {
+    let contact = contact.trim();
+    if contact.is_empty() || contact.chars().any(char::is_whitespace) {
+        return None;
+    }
+    if ["mailto:", "https://", "tel:"].iter().any(|scheme| contact.starts_with(scheme)) {
+        Some(contact.to_string())
+    } else if contact.contains('@') && !contact.contains(':') {
+        Some(format!("mailto:{}", contact))
+    } else {
+        None
+    }
+}
+
+/// `YYYY-MM-DD` if the text is a valid date
+pub fn parse_expiry_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()
+}
+
+fn non_empty(value: Option<&String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+impl SecurityTxtOptions {
+    pub fn from_settings(settings: &HashMap<String, String>) -> Self {
+        Self {
+            contacts: settings
+                .get(SECURITY_CONTACT_KEY)
+                .map(|v| v.lines().filter_map(normalize_contact).collect())
+                .unwrap_or_default(),
+            expires: non_empty(settings.get(SECURITY_EXPIRES_KEY)).filter(|d| parse_expiry_date(d).is_some()),
+            policy: non_empty(settings.get(SECURITY_POLICY_KEY)),
+            preferred_languages: non_empty(settings.get(SECURITY_LANGUAGES_KEY)),
+        }
+    }
+
+    pub fn to_settings(&self) -> [(&'static str, String); 4] {
+        [
+            (SECURITY_CONTACT_KEY, self.contacts.join("\n")),
+            (SECURITY_EXPIRES_KEY, self.expires.clone().unwrap_or_default()),
+            (SECURITY_POLICY_KEY, self.policy.clone().unwrap_or_default()),
+            (SECURITY_LANGUAGES_KEY, self.preferred_languages.clone().unwrap_or_default()),
+        ]
+    }
+}
+
+/// security.txt, or `None` when no contact is configured (the field is mandatory)
+pub fn security_txt(options: &SecurityTxtOptions, site_url: &str, now: DateTime<Utc>) -> Option<String> {
+    if options.contacts.is_empty() {
+        return None;
+    }
+    let expires = options
+        .expires
+        .as_deref()
+        .and_then(parse_expiry_date)
+        .and_then(|date| date.and_hms_opt(23, 59, 59))
+        .map(|end| end.and_utc())
+        .unwrap_or_else(|| now + Duration::days(DEFAULT_EXPIRY_DAYS));
+
+    let mut txt = String::new();
+    for contact in &options.contacts {
+        txt.push_str(&format!("Contact: {}\n", contact));
+    }
+    txt.push_str(&format!("Expires: {}\n", expires.format("%Y-%m-%dT%H:%M:%SZ")));
+    if let Some(policy) = &options.policy {
+        txt.push_str(&format!("Policy: {}\n", policy));
+    }
+    if let Some(languages) = &options.preferred_languages {
+        txt.push_str(&format!("Preferred-Languages: {}\n", languages));
+    }
+    txt.push_str(&format!("Canonical: {}/.well-known/security.txt\n", site_url));
+    Some(txt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_robots_txt() {
+        let txt = robots_txt(&RobotsOptions::default(), "https://blog.example.com");
+        assert_eq!(
+            txt,
+            "User-agent: *\nDisallow: /admin\nDisallow: /login\n\nSitemap: https://blog.example.com/sitemap.xml\n"
+        );
+
+        let open = RobotsOptions { disallow_admin: false, extra_rules: String::new() };
+        assert!(robots_txt(&open, "https://b").starts_with("User-agent: *\nDisallow:\n\n"));
+
+        let custom = RobotsOptions { disallow_admin: false, extra_rules: "  Disallow: /drafts/\r\n\r\n".to_string() };
+        assert!(robots_txt(&custom, "https://b").starts_with("User-agent: *\nDisallow: /drafts/\n\nSitemap:"));
+    }
+
+    #[test]
+    fn test_security_txt() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(security_txt(&SecurityTxtOptions::default(), "https://b", now), None);
+
+        let settings: HashMap<String, String> = [
+            (SECURITY_CONTACT_KEY.to_string(), "security@example.com\nhttps://example.com/report\nnot a contact".to_string()),
+            (SECURITY_EXPIRES_KEY.to_string(), "2025-06-30".to_string()),
+            (SECURITY_LANGUAGES_KEY.to_string(), "en, zh".to_string()),
+        ]
+        .into();
+        let options = SecurityTxtOptions::from_settings(&settings);
+        assert_eq!(options.contacts, vec!["mailto:security@example.com", "https://example.com/report"]);
+        assert_eq!(
+            security_txt(&options, "https://b", now).unwrap(),
+            "Contact: mailto:security@example.com\nContact: https://example.com/report\n\
+             Expires: 2025-06-30T23:59:59Z\nPreferred-Languages: en, zh\nCanonical: https://b/.well-known/security.txt\n"
+        );
+
+        let undated = SecurityTxtOptions { expires: None, ..options };
+        assert!(security_txt(&undated, "https://b", now).unwrap().contains("Expires: 2025-06-30T00:00:00Z"));
+    }
+}
@@ -0,0 +1,201 @@
+//! Side effects of content changes, run by the event bus (`services::events`)
+//! instead of the handlers that made the change.
+
+use actix_web::web;
+use crate::models;
+use crate::services::events::{Event, EventBus, Subscriber};
+use crate::services::plugins::{ContentEvent, ContentEventKind};
+use futures_util::future::BoxFuture;
+use log::error;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use crate::app::{AppState, articles_changed, notify_comment_reply, signed_comment_link};
+
+/// Subscribe everything that reacts to events; returns the workers, which end
+/// after the bus is closed
+pub fn subscribe_all(bus: &EventBus, data: &web::Data<AppState>, pool: &SqlitePool) -> Vec<JoinHandle<()>> {
+    let mut workers = vec![
+        bus.subscribe(Arc::new(Caches(data.clone()))),
+        bus.subscribe(Arc::new(CommentNotifications { data: data.clone(), pool: pool.clone() })),
+        bus.subscribe(Arc::new(LoginNotifications(data.clone()))),
+    ];
+    if data.pinger.is_some() {
+        workers.push(bus.subscribe(Arc::new(PublishPings { data: data.clone(), pool: pool.clone() })));
+    }
+    if !data.plugins.is_empty() {
+        workers.push(bus.subscribe(Arc::new(Plugins { data: data.clone(), pool: pool.clone() })));
+    }
+    workers
+}
+
+// 文章变化后立即清除依赖文章列表的缓存，后续请求不会再读到旧内容
+struct Caches(web::Data<AppState>);
+
+impl Subscriber for Caches {
+    fn name(&self) -> &str {
+        "caches"
+    }
+
+    fn immediate(&self, event: &Event) {
+        if event.article_id().is_some() {
+            articles_changed(&self.0);
+        }
+    }
+}
+
+// 文章公开后通知搜索引擎和 WebSub hub，结果写入审计日志
+struct PublishPings {
+    data: web::Data<AppState>,
+    pool: SqlitePool,
+}
+
+impl Subscriber for PublishPings {
+    fn name(&self) -> &str {
+        "publish_pings"
+    }
+
+    fn handle<'a>(&'a self, event: &'a Event) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let (Some(article_id), Some(pinger)) = (event.published_article(), self.data.pinger.as_ref()) else {
+                return;
+            };
+            for result in pinger.ping_all().await {
+                let detail = match &result.outcome {
+                    Ok(status) => format!("article {}: HTTP {}", article_id, status),
+                    Err(e) => {
+                        log::warn!("Ping to {} failed: {}", result.endpoint, e);
+                        format!("article {}: failed: {}", article_id, e)
+                    }
+                };
+                if let Err(e) = models::record_audit(&self.pool, "system", "publish_ping", &result.endpoint, &detail).await {
+                    error!("Failed to record ping result: {}", e);
+                }
+            }
+        })
+    }
+}
+
+// 文章公开或移入回收站时调用插件的 on_publish / on_delete
+struct Plugins {
+    data: web::Data<AppState>,
+    pool: SqlitePool,
+}
+
+impl Subscriber for Plugins {
+    fn name(&self) -> &str {
+        "plugins"
+    }
+
+    fn handle<'a>(&'a self, event: &'a Event) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let (kind, article_id) = match (event.published_article(), event) {
+                (Some(article_id), _) => (ContentEventKind::Publish, article_id),
+                (None, Event::ArticleDeleted { article_id }) => (ContentEventKind::Delete, *article_id),
+                _ => return,
+            };
+            let event = ContentEvent::new(kind, article_id, &self.data.site_url);
+            self.data.plugins.dispatch(&self.pool, &event).await;
+        })
+    }
+}
+
+// 新评论通知管理员（待审核的附带一键审核链接），公开的回复通知父评论作者
+struct CommentNotifications {
+    data: web::Data<AppState>,
+    pool: SqlitePool,
+}
+
+impl Subscriber for CommentNotifications {
+    fn name(&self) -> &str {
+        "comment_notifications"
+    }
+
+    fn handle<'a>(&'a self, event: &'a Event) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let Event::CommentPosted { comment_id, .. } = event else {
+                return;
+            };
+            let comment = match models::get_comment(&self.pool, *comment_id).await {
+                Ok(comment) => comment,
+                Err(e) => {
+                    error!("Failed to load comment {}: {}", comment_id, e);
+                    return;
+                }
+            };
+            // 垃圾评论只进入审核队列，不打扰管理员
+            if comment.status != "spam" {
+                self.notify_admin(&comment).await;
+            }
+            if comment.status == "approved" {
+                notify_comment_reply(&self.data, &self.pool, &comment).await;
+            }
+        })
+    }
+}
+
+impl CommentNotifications {
+    async fn notify_admin(&self, comment: &models::Comment) {
+        let data = &self.data;
+        let Some(email) = data.email_service.as_ref().filter(|e| e.has_admin_recipient()) else {
+            return;
+        };
+        let article_title = match models::get_article(&self.pool, comment.article_id).await {
+            Ok(Some(article)) => article.title,
+            Ok(None) => return,
+            Err(e) => {
+                error!("Failed to load article {}: {}", comment.article_id, e);
+                return;
+            }
+        };
+        let subject = format!("New comment on \"{}\"", article_title);
+        let mut body = format!(
+            "{} commented on \"{}\":\n\n{}\n\nView: {}/post/{}#comments\n",
+            comment.author_name, article_title, comment.content, data.site_url, comment.article_id
+        );
+        if comment.status != "approved" {
+            body.push_str(&format!(
+                "\nThis comment is awaiting moderation.\nApprove: {}\nMark as spam: {}\n",
+                signed_comment_link(data, comment.id, "approve", 7),
+                signed_comment_link(data, comment.id, "spam", 7),
+            ));
+        }
+        body.push_str(&format!("\nModeration queue: {}/admin/comments\n", data.site_url));
+        if let Err(e) = email.send_to_admin(&subject, &body).await {
+            error!("Failed to send comment notification: {}", e);
+        }
+    }
+}
+
+// 从未用过的IP登录时发邮件提醒管理员
+struct LoginNotifications(web::Data<AppState>);
+
+impl Subscriber for LoginNotifications {
+    fn name(&self) -> &str {
+        "login_notifications"
+    }
+
+    fn handle<'a>(&'a self, event: &'a Event) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let Event::UserLoggedIn { username, ip, new_ip: true } = event else {
+                return;
+            };
+            if !self.0.notify_new_login_ip {
+                return;
+            }
+            let Some(email) = self.0.email_service.as_ref().filter(|e| e.has_admin_recipient()) else {
+                log::warn!("New login IP {} for {} but no admin email is configured", ip, username);
+                return;
+            };
+            let subject = format!("New sign-in to Bluster from {}", ip);
+            let body = format!(
+                "The account '{}' just signed in from an IP address that has not been used before: {}\n\n\
+                 If this wasn't you, change the password immediately at /admin/password.",
+                username, ip
+            );
+            if let Err(e) = email.send_to_admin(&subject, &body).await {
+                error!("Failed to send login notification: {}", e);
+            }
+        })
+    }
+}
@@ -0,0 +1,381 @@
+//! Fixtures for tests, behind the `test-support` feature (enabled for this
+//! crate's own tests): an in-memory database with the full schema, builders for
+//! users, articles and comments, and a client that keeps its session cookie so
+//! handlers behind the login can be called.
+//!
+//! ```ignore
+//! let pool = test_pool().await;
+//! let alice = UserBuilder::new("alice").create(&pool).await;
+//! let article = ArticleBuilder::new("Hello").author(alice.id).create(&pool).await;
+//!
+//! let state = web::Data::new(AppState::from_env().unwrap());
+//! let mut client = TestClient::new(pool.clone(), move |cfg| {
+//!     cfg.app_data(state);
+//!     handlers::configure(cfg);
+//! })
+//! .await;
+//! client.login("alice").await;
+//! let response = client.get("/admin").await;
+//! ```
+
+use crate::models::{self, Article, Comment, NewComment, User};
+use actix_session::storage::CookieSessionStore;
+use actix_session::{Session, SessionMiddleware};
+use actix_web::body::MessageBody;
+use actix_web::cookie::{Cookie, Key};
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::test::TestRequest;
+use actix_web::{web, App, HttpResponse};
+use actix_http::Request;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
+
+/// Cookie the server keeps the session in
+pub const SESSION_COOKIE: &str = "bluster_session";
+/// Route `TestClient` adds to sign in without a password
+pub const LOGIN_PATH: &str = "/__test/login";
+/// Password of users created by `UserBuilder` unless set
+pub const DEFAULT_PASSWORD: &str = "password";
+
+// 测试不需要真正的强度，降低 bcrypt 成本以免每个用户耗时数秒
+const TEST_BCRYPT_COST: u32 = 4;
+
+/// A fresh in-memory database with every table the server creates
+pub async fn test_pool() -> SqlitePool {
+    // 内存数据库只属于打开它的连接，所以池中只保留一个且不回收
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .expect("open in-memory database");
+    models::create_schema(&pool).await.expect("create schema");
+    pool
+}
+
+pub struct UserBuilder {
+    username: String,
+    password: String,
+    display_name: Option<String>,
+    must_change_password: bool,
+}
+
+impl UserBuilder {
+    pub fn new(username: &str) -> Self {
+        Self {
+            username: username.to_string(),
+            password: DEFAULT_PASSWORD.to_string(),
+            display_name: None,
+            must_change_password: false,
+        }
+    }
+
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = password.to_string();
+        self
+    }
+
+    pub fn display_name(mut self, display_name: &str) -> Self {
+        self.display_name = Some(display_name.to_string());
+        self
+    }
+
+    pub fn must_change_password(mut self) -> Self {
+        self.must_change_password = true;
+        self
+    }
+
+    pub async fn create(self, pool: &SqlitePool) -> User {
+        let password_hash = bcrypt::hash(&self.password, TEST_BCRYPT_COST).expect("hash password");
+        sqlx::query_as::<_, User>(
+            "INSERT INTO users (username, password_hash, display_name, must_change_password) VALUES (?, ?, ?, ?) RETURNING *"
+        )
+        .bind(&self.username)
+        .bind(&password_hash)
+        .bind(&self.display_name)
+        .bind(self.must_change_password)
+        .fetch_one(pool)
+        .await
+        .expect("insert user")
+    }
+}
+
+pub struct ArticleBuilder {
+    title: String,
+    content: String,
+    author_id: Option<i64>,
+    created_at: Option<String>,
+    meta_description: Option<String>,
+    meta_keywords: Option<String>,
+    noindex: bool,
+    render_options: Option<String>,
+    canonical_url: Option<String>,
+    category_id: Option<i64>,
+    draft: bool,
+    trashed: bool,
+}
+
+impl ArticleBuilder {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            content: format!("Content of {}.", title),
+            author_id: None,
+            created_at: None,
+            meta_description: None,
+            meta_keywords: None,
+            noindex: false,
+            render_options: None,
+            canonical_url: None,
+            category_id: None,
+            draft: false,
+            trashed: false,
+        }
+    }
+
+    pub fn content(mut self, content: &str) -> Self {
+        self.content = content.to_string();
+        self
+    }
+
+    pub fn author(mut self, user_id: i64) -> Self {
+        self.author_id = Some(user_id);
+        self
+    }
+
+    /// Publication time in the stored form, "YYYY-MM-DD HH:MM:SS" UTC;
+    /// a future time makes the article scheduled
+    pub fn created_at(mut self, created_at: &str) -> Self {
+        self.created_at = Some(created_at.to_string());
+        self
+    }
+
+    pub fn meta_description(mut self, description: &str) -> Self {
+        self.meta_description = Some(description.to_string());
+        self
+    }
+
+    pub fn meta_keywords(mut self, keywords: &str) -> Self {
+        self.meta_keywords = Some(keywords.to_string());
+        self
+    }
+
+    pub fn noindex(mut self) -> Self {
+        self.noindex = true;
+        self
+    }
+
+    /// JSON as in `articles.render_options`, e.g. `{"math":true}`
+    pub fn render_options(mut self, json: &str) -> Self {
+        self.render_options = Some(json.to_string());
+        self
+    }
+
+    pub fn canonical_url(mut self, url: &str) -> Self {
+        self.canonical_url = Some(url.to_string());
+        self
+    }
+
+    pub fn category(mut self, category_id: i64) -> Self {
+        self.category_id = Some(category_id);
+        self
+    }
+
+    /// Saved as a draft, not yet published
+    pub fn draft(mut self) -> Self {
+        self.draft = true;
+        self
+    }
+
+    /// In the trash, as if deleted just now
+    pub fn trashed(mut self) -> Self {
+        self.trashed = true;
+        self
+    }
+
+    /// Stored with a slug made from the title, as the server does
+    pub async fn create(self, pool: &SqlitePool) -> Article {
+        let mut article = sqlx::query_as::<_, Article>(
+            "INSERT INTO articles (title, content, author_id, created_at, updated_at, meta_description, meta_keywords, \
+             noindex, render_options, canonical_url, category_id, status, deleted_at) \
+             VALUES (?, ?, ?, COALESCE(?, datetime('now')), COALESCE(?, datetime('now')), ?, ?, ?, ?, ?, ?, ?, \
+             CASE WHEN ? THEN datetime('now') END) RETURNING *"
+        )
+        .bind(&self.title)
+        .bind(&self.content)
+        .bind(self.author_id)
+        .bind(&self.created_at)
+        .bind(&self.created_at)
+        .bind(&self.meta_description)
+        .bind(&self.meta_keywords)
+        .bind(self.noindex)
+        .bind(&self.render_options)
+        .bind(&self.canonical_url)
+        .bind(self.category_id)
+        .bind(if self.draft { models::STATUS_DRAFT } else { models::STATUS_PUBLISHED })
+        .bind(self.trashed)
+        .fetch_one(pool)
+        .await
+        .expect("insert article");
+        article.slug = models::update_slug(pool, article.id).await.expect("assign slug");
+        article
+    }
+}
+
+pub struct CommentBuilder {
+    article_id: i64,
+    parent_id: Option<i64>,
+    author_name: String,
+    author_email: Option<String>,
+    content: String,
+    status: String,
+}
+
+impl CommentBuilder {
+    /// An approved comment on the article
+    pub fn new(article_id: i64) -> Self {
+        Self {
+            article_id,
+            parent_id: None,
+            author_name: "Reader".to_string(),
+            author_email: None,
+            content: "Nice post!".to_string(),
+            status: "approved".to_string(),
+        }
+    }
+
+    pub fn reply_to(mut self, comment_id: i64) -> Self {
+        self.parent_id = Some(comment_id);
+        self
+    }
+
+    pub fn author(mut self, name: &str, email: Option<&str>) -> Self {
+        self.author_name = name.to_string();
+        self.author_email = email.map(str::to_string);
+        self
+    }
+
+    pub fn content(mut self, content: &str) -> Self {
+        self.content = content.to_string();
+        self
+    }
+
+    /// pending / approved / spam
+    pub fn status(mut self, status: &str) -> Self {
+        self.status = status.to_string();
+        self
+    }
+
+    pub async fn create(self, pool: &SqlitePool) -> Comment {
+        models::create_comment(
+            pool,
+            &NewComment {
+                article_id: self.article_id,
+                parent_id: self.parent_id,
+                author_name: &self.author_name,
+                author_email: self.author_email.as_deref(),
+                content: &self.content,
+                status: &self.status,
+                notify_replies: false,
+            },
+        )
+        .await
+        .expect("insert comment")
+    }
+}
+
+#[derive(Deserialize)]
+struct LoginForm {
+    username: String,
+}
+
+// 与登录成功后相同的会话内容
+async fn test_login(form: web::Form<LoginForm>, session: Session) -> HttpResponse {
+    session.renew();
+    match session.insert("username", &form.username) {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Calls an app built from `configure` with the server's session middleware,
+/// sending back the cookies it was given like a browser would
+pub struct TestClient<S> {
+    service: S,
+    cookies: Vec<Cookie<'static>>,
+}
+
+impl TestClient<()> {
+    /// The pool is available to handlers as `web::Data<SqlitePool>`; anything
+    /// else they need, such as `AppState`, is added by `configure`. The server's
+    /// other middleware (password change, template globals) is not applied.
+    pub async fn new(
+        pool: SqlitePool,
+        configure: impl FnOnce(&mut web::ServiceConfig),
+    ) -> TestClient<impl Service<Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error>> {
+        let app = App::new()
+            .app_data(web::Data::new(pool))
+            .wrap(
+                SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                    .cookie_name(SESSION_COOKIE.to_string())
+                    .cookie_secure(false)
+                    .cookie_http_only(true)
+                    .build(),
+            )
+            .route(LOGIN_PATH, web::post().to(test_login))
+            .configure(configure);
+        TestClient {
+            service: actix_web::test::init_service(app).await,
+            cookies: Vec::new(),
+        }
+    }
+}
+
+impl<S, B> TestClient<S>
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    pub async fn call(&mut self, request: TestRequest) -> ServiceResponse<B> {
+        let request = self.cookies.iter().fold(request, |request, cookie| request.cookie(cookie.clone()));
+        let response = actix_web::test::call_service(&self.service, request.to_request()).await;
+        for cookie in response.response().cookies() {
+            self.cookies.retain(|kept| kept.name() != cookie.name());
+            if !cookie.value().is_empty() {
+                self.cookies.push(cookie.into_owned());
+            }
+        }
+        response
+    }
+
+    pub async fn get(&mut self, path: &str) -> ServiceResponse<B> {
+        self.call(TestRequest::get().uri(path)).await
+    }
+
+    pub async fn post_form(&mut self, path: &str, form: &impl Serialize) -> ServiceResponse<B> {
+        self.call(TestRequest::post().uri(path).set_form(form)).await
+    }
+
+    pub async fn post_json(&mut self, path: &str, json: &impl Serialize) -> ServiceResponse<B> {
+        self.call(TestRequest::post().uri(path).set_json(json)).await
+    }
+
+    /// Sign in as `username` without a password; the user does not have to exist
+    pub async fn login(&mut self, username: &str) {
+        let response = self.post_form(LOGIN_PATH, &[("username", username)]).await;
+        assert!(response.status().is_success(), "test login failed: {}", response.status());
+    }
+
+    /// Forget the session, as after signing out
+    pub fn logout(&mut self) {
+        self.cookies.clear();
+    }
+}
+
+/// The response body as text
+pub async fn body_text<B: MessageBody>(response: ServiceResponse<B>) -> String {
+    let bytes = actix_web::test::read_body(response).await;
+    String::from_utf8(bytes.to_vec()).expect("UTF-8 response body")
+}
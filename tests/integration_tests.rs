@@ -0,0 +1,521 @@
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::LOCATION;
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use actix_web::web;
+use bluster::app::AppState;
+use bluster::{handlers, models};
+use bluster::test_support::{body_text, test_pool, ArticleBuilder, CommentBuilder, TestClient, UserBuilder, DEFAULT_PASSWORD};
+
+#[actix_web::test]
+async fn test_builders() {
+    let pool = test_pool().await;
+    let alice = UserBuilder::new("alice").display_name("Alice").create(&pool).await;
+    assert!(models::verify_user(&pool, "alice", "password").await.is_ok());
+    assert!(!alice.must_change_password);
+
+    let article = ArticleBuilder::new("Hello")
+        .author(alice.id)
+        .created_at("2024-03-05 08:09:10")
+        .meta_keywords("rust, web")
+        .create(&pool)
+        .await;
+    assert_eq!(article.author_id, Some(alice.id));
+    assert_eq!(article.created_at, "2024-03-05 08:09:10");
+    assert_eq!(article.deleted_at, None);
+
+    let comment = CommentBuilder::new(article.id).create(&pool).await;
+    let reply = CommentBuilder::new(article.id).reply_to(comment.id).author("Bob", None).create(&pool).await;
+    CommentBuilder::new(article.id).status("pending").create(&pool).await;
+    let approved = models::get_approved_comments(&pool, article.id).await.unwrap();
+    assert_eq!(approved.iter().map(|c| c.id).collect::<Vec<_>>(), vec![comment.id, reply.id]);
+    assert_eq!(approved[1].parent_id, Some(comment.id));
+}
+
+#[actix_web::test]
+async fn test_trashed_articles_are_not_duplicates() {
+    let pool = test_pool().await;
+    let kept = ArticleBuilder::new("Getting started").create(&pool).await;
+    ArticleBuilder::new("Old draft").trashed().create(&pool).await;
+    let titles = models::get_article_titles(&pool).await.unwrap();
+    assert_eq!(titles, vec![(kept.id, "Getting started".to_string())]);
+}
+
+#[actix_web::test]
+async fn test_seed_demo_is_repeatable() {
+    let pool = test_pool().await;
+    UserBuilder::new("admin").create(&pool).await;
+    let first = models::seed_demo(&pool).await.unwrap();
+    assert_eq!(first.articles, bluster::services::demo::ARTICLES.len());
+    assert!(first.comments > 0 && first.skipped.is_empty());
+
+    let second = models::seed_demo(&pool).await.unwrap();
+    assert_eq!((second.articles, second.comments), (0, 0));
+    assert_eq!(second.skipped.len(), first.articles);
+
+    let replies: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM comments WHERE parent_id IS NOT NULL")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(replies > 0);
+}
+
+#[actix_web::test]
+async fn test_schema_normalizes_timestamps() {
+    let pool = test_pool().await;
+    let article = ArticleBuilder::new("Imported").created_at("2024-03-05T16:09:10+08:00").create(&pool).await;
+    // 启动时再次执行，旧格式的时间改写为 UTC
+    models::create_schema(&pool).await.unwrap();
+    let article = models::get_article(&pool, article.id).await.unwrap().unwrap();
+    assert_eq!(article.created_at, "2024-03-05 08:09:10");
+    assert_eq!(serde_json::to_value(&article).unwrap()["created_at"], "2024-03-05T08:09:10Z");
+}
+
+#[actix_web::test]
+async fn test_article_content_wire_format() {
+    let pool = test_pool().await;
+    let article = ArticleBuilder::new("Legacy").content("Body").create(&pool).await;
+    let row = sqlx::query_as::<_, models::ArticleContent>("SELECT id, title, content FROM articles WHERE id = ?")
+        .bind(article.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    // 旧版 /articles 接口返回 [id, title, content] 数组
+    assert_eq!(serde_json::to_value(&row).unwrap(), serde_json::json!([article.id, "Legacy", "Body"]));
+}
+
+#[actix_web::test]
+async fn test_article_tags() {
+    let pool = test_pool().await;
+    let first = ArticleBuilder::new("First").create(&pool).await;
+    let second = ArticleBuilder::new("Second").create(&pool).await;
+    let tags = |names: &str| bluster::services::tags::parse_tags(names);
+    models::set_article_tags(&pool, first.id, &tags("web, Rust")).await.unwrap();
+    models::set_article_tags(&pool, second.id, &tags("rust")).await.unwrap();
+    assert_eq!(models::get_article_tags(&pool, first.id).await.unwrap(), vec!["Rust", "web"]);
+
+    // 标签名不区分大小写，沿用最先建立时的写法
+    let rust = models::get_tag(&pool, "RUST").await.unwrap().unwrap();
+    assert_eq!(rust.name, "Rust");
+    let tagged = models::get_tagged_articles(&pool, rust.id).await.unwrap();
+    assert_eq!(tagged.len(), 2);
+
+    models::set_article_tags(&pool, first.id, &tags("rust")).await.unwrap();
+    assert_eq!(models::get_tag(&pool, "web").await.unwrap().map(|t| t.name), None);
+    models::trash_article(&pool, second.id).await.unwrap();
+    let tagged = models::get_tagged_articles(&pool, rust.id).await.unwrap();
+    assert_eq!(tagged.iter().map(|a| a.id).collect::<Vec<_>>(), vec![first.id]);
+
+    models::delete_article(&pool, first.id).await.unwrap();
+    models::delete_article(&pool, second.id).await.unwrap();
+    assert!(models::get_tag(&pool, "rust").await.unwrap().is_none());
+}
+
+#[actix_web::test]
+async fn test_merge_and_purge_tags() {
+    let pool = test_pool().await;
+    let first = ArticleBuilder::new("First").create(&pool).await;
+    let second = ArticleBuilder::new("Second").create(&pool).await;
+    let tags = |names: &str| bluster::services::tags::parse_tags(names);
+    models::set_article_tags(&pool, first.id, &tags("rustlang, web")).await.unwrap();
+    models::set_article_tags(&pool, second.id, &tags("rust")).await.unwrap();
+
+    let old = models::get_tag(&pool, "rustlang").await.unwrap().unwrap();
+    let rust = models::get_tag(&pool, "rust").await.unwrap().unwrap();
+    models::merge_tags(&pool, &old, &rust).await.unwrap();
+    assert_eq!(models::get_article_tags(&pool, first.id).await.unwrap(), vec!["rust", "web"]);
+    assert!(models::get_tag(&pool, "rustlang").await.unwrap().is_none());
+    assert_eq!(models::get_tag_redirect(&pool, "RustLang").await.unwrap().map(|t| t.id), Some(rust.id));
+
+    // 再次合并时，之前的跳转也指向新的标签
+    let web = models::get_tag(&pool, "web").await.unwrap().unwrap();
+    models::merge_tags(&pool, &rust, &web).await.unwrap();
+    assert_eq!(models::get_tag_redirect(&pool, "rustlang").await.unwrap().map(|t| t.id), Some(web.id));
+    let usage = models::get_tag_usage(&pool).await.unwrap();
+    assert_eq!(usage.iter().map(|t| (t.name.as_str(), t.articles)).collect::<Vec<_>>(), vec![("web", 2)]);
+
+    // 没有文章使用的标签被清理，指向它的跳转随之删除
+    sqlx::query("INSERT INTO tags (name) VALUES ('orphan')").execute(&pool).await.unwrap();
+    assert_eq!(models::purge_unused_tags(&pool).await.unwrap(), 1);
+    models::set_article_tags(&pool, first.id, &[]).await.unwrap();
+    models::set_article_tags(&pool, second.id, &[]).await.unwrap();
+    assert!(models::get_tag_usage(&pool).await.unwrap().is_empty());
+    assert!(models::get_tag_redirect(&pool, "rustlang").await.unwrap().is_none());
+}
+
+#[actix_web::test]
+async fn test_categories() {
+    use bluster::services::categories;
+
+    let pool = test_pool().await;
+    let programming = models::create_category(&pool, "Programming", "programming", None).await.unwrap().unwrap();
+    let rust = models::create_category(&pool, "Rust", "rust", Some(programming.id)).await.unwrap().unwrap();
+    let asynchronous = models::create_category(&pool, "Async", "async", Some(rust.id)).await.unwrap().unwrap();
+    assert!(models::create_category(&pool, "Rust again", "rust", None).await.unwrap().is_none());
+
+    let top = ArticleBuilder::new("Why program").category(programming.id).create(&pool).await;
+    let deep = ArticleBuilder::new("Pinning explained").category(asynchronous.id).create(&pool).await;
+    ArticleBuilder::new("Trashed").category(rust.id).trashed().create(&pool).await;
+    ArticleBuilder::new("Uncategorized").create(&pool).await;
+
+    // 上级分类的页面包含子分类中的文章
+    let all = models::get_categories(&pool).await.unwrap();
+    let ids = categories::descendants(&all, programming.id);
+    let listed: Vec<i64> = models::get_category_articles(&pool, &ids).await.unwrap().iter().map(|a| a.id).collect();
+    assert_eq!(listed.len(), 2);
+    assert!(listed.contains(&top.id) && listed.contains(&deep.id));
+
+    // 删除中间一级：子分类移到上级，文章变为未分类
+    assert!(models::delete_category(&pool, rust.id).await.unwrap());
+    let all = models::get_categories(&pool).await.unwrap();
+    assert_eq!(all.iter().find(|c| c.id == asynchronous.id).unwrap().parent_id, Some(programming.id));
+    assert_eq!(models::get_article(&pool, deep.id).await.unwrap().unwrap().category_id, Some(asynchronous.id));
+    assert!(!models::delete_category(&pool, rust.id).await.unwrap());
+}
+
+#[actix_web::test]
+async fn test_drafts() {
+    let pool = test_pool().await;
+    let published = ArticleBuilder::new("Out now").created_at("2024-03-05 08:09:10").create(&pool).await;
+    let draft = ArticleBuilder::new("Work in progress").draft().create(&pool).await;
+    let tags = bluster::services::tags::parse_tags("rust");
+    models::set_article_tags(&pool, published.id, &tags).await.unwrap();
+    models::set_article_tags(&pool, draft.id, &tags).await.unwrap();
+
+    // 直接发布的文章以发表时间为发布时间
+    let published = models::get_article(&pool, published.id).await.unwrap().unwrap();
+    assert_eq!(published.published_at.as_deref(), Some("2024-03-05 08:09:10"));
+    assert_eq!(draft.published_at, None);
+    let counts = models::get_content_counts(&pool).await.unwrap();
+    assert_eq!((counts.published, counts.drafts), (1, 1));
+
+    // 草稿不出现在公开列表中
+    let rust = models::get_tag(&pool, "rust").await.unwrap().unwrap();
+    let tagged = models::get_tagged_articles(&pool, rust.id).await.unwrap();
+    assert_eq!(tagged.iter().map(|a| a.id).collect::<Vec<_>>(), vec![published.id]);
+
+    assert_eq!(models::publish_article(&pool, draft.id).await.unwrap().as_deref(), Some("Work in progress"));
+    let draft = models::get_article(&pool, draft.id).await.unwrap().unwrap();
+    assert_eq!(draft.status, models::STATUS_PUBLISHED);
+    assert!(draft.published_at.is_some());
+    assert_eq!(models::get_tagged_articles(&pool, rust.id).await.unwrap().len(), 2);
+    // 已发布的文章不能再次发布
+    assert_eq!(models::publish_article(&pool, draft.id).await.unwrap(), None);
+    assert_eq!(models::publish_article(&pool, published.id).await.unwrap(), None);
+}
+
+#[actix_web::test]
+async fn test_published_pages() {
+    use bluster::services::pagination::Cursor;
+
+    let pool = test_pool().await;
+    // 两篇同一时间发表，按 id 区分先后
+    let oldest = ArticleBuilder::new("Oldest").created_at("2024-01-01 00:00:00").create(&pool).await;
+    let twin_a = ArticleBuilder::new("Twin A").created_at("2024-02-01 00:00:00").create(&pool).await;
+    let twin_b = ArticleBuilder::new("Twin B").created_at("2024-02-01 00:00:00").create(&pool).await;
+    ArticleBuilder::new("Draft").draft().create(&pool).await;
+    let newest = ArticleBuilder::new("Newest").created_at("2024-03-01 00:00:00").create(&pool).await;
+    assert_eq!(models::count_published_articles(&pool).await.unwrap(), 4);
+
+    let ids = |articles: Vec<models::Article>| articles.iter().map(|a| a.id).collect::<Vec<_>>();
+    let expected = vec![newest.id, twin_b.id, twin_a.id, oldest.id];
+    assert_eq!(ids(models::get_published_articles(&pool, 2, 2).await.unwrap()), expected[2..]);
+
+    // 按游标逐页取完，不重复也不遗漏
+    let mut seen = Vec::new();
+    let mut cursor: Option<Cursor> = None;
+    loop {
+        let page = models::get_published_articles_after(&pool, cursor.as_ref(), 1).await.unwrap();
+        let Some(last) = page.last() else { break };
+        cursor = Cursor::decode(&Cursor::new(&last.created_at, last.id).encode());
+        seen.extend(ids(page));
+    }
+    assert_eq!(seen, expected);
+}
+
+#[actix_web::test]
+async fn test_search() {
+    use bluster::services::search::{self, SearchRanking};
+
+    let pool = test_pool().await;
+    let ranking = SearchRanking::default();
+    let in_title = ArticleBuilder::new("Borrowing in Rust").content("Lifetimes & references.").create(&pool).await;
+    let in_body = ArticleBuilder::new("Notes")
+        .content("Some text before. The borrow checker <rejects> this code.")
+        .create(&pool)
+        .await;
+    ArticleBuilder::new("Borrowed draft").draft().create(&pool).await;
+    ArticleBuilder::new("Borrowed trash").trashed().create(&pool).await;
+
+    // 标题匹配排在前面；草稿和回收站中的文章不出现
+    let (pagination, hits) = search::search(&pool, "borrow", &ranking, None, None).await.unwrap();
+    assert_eq!(pagination.total, 2);
+    assert_eq!(hits.iter().map(|h| h.id).collect::<Vec<_>>(), vec![in_title.id, in_body.id]);
+    assert_eq!(hits[0].title_html, "<mark>Borrowing</mark> in Rust");
+    assert!(hits[1].snippet_html.contains("The <mark>borrow</mark> checker &lt;rejects&gt; this"));
+
+    // 修改后索引随之更新
+    sqlx::query("UPDATE articles SET title = 'Ownership' WHERE id = ?").bind(in_title.id).execute(&pool).await.unwrap();
+    let (_, hits) = search::search(&pool, "borrowing", &ranking, None, None).await.unwrap();
+    assert!(hits.is_empty());
+
+    let (pagination, hits) = search::search(&pool, "rust notes", &ranking, Some(1), Some(1)).await.unwrap();
+    assert_eq!((pagination.total, hits.len()), (0, 0));
+    let (pagination, hits) = search::search(&pool, "\"*", &ranking, None, None).await.unwrap();
+    assert_eq!((pagination.total, hits.len()), (0, 0));
+}
+
+#[actix_web::test]
+async fn test_search_suggestions() {
+    use bluster::services::search::{self, SearchRanking};
+    use bluster::services::tags::parse_tags;
+
+    let pool = test_pool().await;
+    let ranking = SearchRanking::default();
+    let both = ArticleBuilder::new("Rust traits").create(&pool).await;
+    let title = ArticleBuilder::new("Rusty nails").create(&pool).await;
+    let tagged = ArticleBuilder::new("Ownership").create(&pool).await;
+    let hidden = ArticleBuilder::new("Unrelated draft").draft().create(&pool).await;
+    models::set_article_tags(&pool, both.id, &parse_tags("rust, RustConf")).await.unwrap();
+    models::set_article_tags(&pool, tagged.id, &parse_tags("Rust")).await.unwrap();
+    models::set_article_tags(&pool, hidden.id, &parse_tags("rust")).await.unwrap();
+
+    // 标题和标签都命中的文章排在最前，且只出现一次；草稿不出现
+    let ids = |rows: Vec<models::SearchSuggestion>| rows.into_iter().map(|row| row.id).collect::<Vec<_>>();
+    let rows = search::suggest(&pool, "rus", &ranking, 10).await.unwrap();
+    assert_eq!(rows[0].id, both.id);
+    let mut found = ids(rows);
+    found.sort();
+    assert_eq!(found, vec![both.id, title.id, tagged.id]);
+
+    // 只有标签命中的前缀
+    assert_eq!(ids(search::suggest(&pool, "rustc", &ranking, 10).await.unwrap()), vec![both.id]);
+    // LIKE 通配符按普通字符匹配
+    assert!(search::suggest(&pool, "r_st", &ranking, 10).await.unwrap().is_empty());
+    assert!(search::suggest(&pool, "", &ranking, 10).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_slugs() {
+    let pool = test_pool().await;
+    let first = ArticleBuilder::new("Hello World").create(&pool).await;
+    let second = ArticleBuilder::new("Hello, world!").create(&pool).await;
+    let numeric = ArticleBuilder::new("2024").create(&pool).await;
+    assert_eq!(first.slug.as_deref(), Some("hello-world"));
+    assert_eq!(second.slug.as_deref(), Some("hello-world-2"));
+    assert_eq!(numeric.slug.as_deref(), Some("post-2024"));
+    assert_eq!(models::get_article_id_by_slug(&pool, "hello-world-2").await.unwrap(), Some(second.id));
+
+    let rename = |id: i64, title: &'static str| {
+        let pool = pool.clone();
+        async move {
+            sqlx::query("UPDATE articles SET title = ? WHERE id = ?").bind(title).bind(id).execute(&pool).await.unwrap();
+            models::update_slug(&pool, id).await.unwrap()
+        }
+    };
+    // 生成相同 slug 的标题不改变地址
+    assert_eq!(rename(second.id, "Hello world").await.as_deref(), Some("hello-world-2"));
+
+    // 改标题后旧 slug 跳转到新地址，且不会分配给其他文章
+    assert_eq!(rename(first.id, "Goodbye").await.as_deref(), Some("goodbye"));
+    assert_eq!(
+        models::get_slug_redirect(&pool, "hello-world").await.unwrap(),
+        Some((first.id, Some("goodbye".to_string())))
+    );
+    let third = ArticleBuilder::new("Hello World").create(&pool).await;
+    assert_eq!(third.slug.as_deref(), Some("hello-world-3"));
+
+    // 改回原标题时收回旧 slug
+    assert_eq!(rename(first.id, "Hello World").await.as_deref(), Some("hello-world"));
+    assert_eq!(models::get_slug_redirect(&pool, "hello-world").await.unwrap(), None);
+    assert_eq!(
+        models::get_slug_redirect(&pool, "goodbye").await.unwrap(),
+        Some((first.id, Some("hello-world".to_string())))
+    );
+
+    // 升级前没有 slug 的文章在启动时补上
+    sqlx::query("UPDATE articles SET slug = NULL WHERE id = ?").bind(numeric.id).execute(&pool).await.unwrap();
+    assert_eq!(models::assign_missing_slugs(&pool).await.unwrap(), 1);
+    assert_eq!(models::get_article_id_by_slug(&pool, "post-2024").await.unwrap(), Some(numeric.id));
+}
+
+#[actix_web::test]
+async fn test_editor_presence() {
+    let pool = test_pool().await;
+    UserBuilder::new("alice").display_name("Alice").create(&pool).await;
+    UserBuilder::new("bob").create(&pool).await;
+    let article = ArticleBuilder::new("Shared").create(&pool).await;
+    let names = |editors: Vec<models::ActiveEditor>| editors.into_iter().map(|e| e.name).collect::<Vec<_>>();
+
+    models::touch_presence(&pool, article.id, "alice").await.unwrap();
+    models::touch_presence(&pool, article.id, "bob").await.unwrap();
+    // 自己不算在内；没有显示名时用用户名
+    assert_eq!(names(models::get_active_editors(&pool, article.id, "alice", 60).await.unwrap()), vec!["bob"]);
+    assert_eq!(names(models::get_active_editors(&pool, article.id, "bob", 60).await.unwrap()), vec!["Alice"]);
+
+    // 超过时间窗口没有心跳的不再算作在编辑
+    sqlx::query("UPDATE article_presence SET last_seen = datetime('now', '-2 minutes') WHERE username = 'bob'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(models::get_active_editors(&pool, article.id, "alice", 60).await.unwrap().is_empty());
+
+    models::clear_presence(&pool, article.id, "alice").await.unwrap();
+    assert!(models::get_active_editors(&pool, article.id, "bob", 600).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_trash_restore() {
+    let pool = test_pool().await;
+    let article = ArticleBuilder::new("Second thoughts").create(&pool).await;
+    let slug = article.slug.clone().unwrap();
+    ArticleBuilder::new("Still here").create(&pool).await;
+
+    assert!(models::trash_article(&pool, article.id).await.unwrap());
+    assert!(models::get_article(&pool, article.id).await.unwrap().is_none());
+    let trashed = models::get_trashed_articles(&pool).await.unwrap();
+    assert_eq!(trashed.iter().map(|a| a.title.as_str()).collect::<Vec<_>>(), vec!["Second thoughts"]);
+
+    // 恢复后重新公开，slug 不变
+    assert!(models::restore_article(&pool, article.id).await.unwrap());
+    assert!(!models::restore_article(&pool, article.id).await.unwrap());
+    assert!(models::get_trashed_article(&pool, article.id).await.unwrap().is_none());
+    let restored = models::get_article(&pool, article.id).await.unwrap().unwrap();
+    assert_eq!(restored.slug.as_deref(), Some(slug.as_str()));
+    assert_eq!(models::get_article_id_by_slug(&pool, &slug).await.unwrap(), Some(article.id));
+}
+
+fn new_article<'a>(title: &'a str, tags: &[&str]) -> models::NewArticle<'a> {
+    models::NewArticle {
+        title,
+        content: "Body",
+        meta_description: None,
+        meta_keywords: None,
+        noindex: false,
+        text_direction: "auto",
+        canonical_url: None,
+        category_id: None,
+        status: models::STATUS_PUBLISHED,
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        links: Vec::new(),
+    }
+}
+
+#[actix_web::test]
+async fn test_create_articles_batch() {
+    let pool = test_pool().await;
+    UserBuilder::new("alice").create(&pool).await;
+    let linked = ArticleBuilder::new("Linked").create(&pool).await;
+
+    let mut notes = vec![new_article("Daily note", &["notes"]), new_article("Daily note", &["notes", "rust"])];
+    notes[1].links = vec![linked.id];
+    let created = models::create_articles(&pool, &notes, Some("alice")).await.unwrap();
+    // 同名文章在同一批中也得到不同的 slug
+    assert_eq!(created.iter().map(|(_, slug)| slug.as_str()).collect::<Vec<_>>(), vec!["daily-note", "daily-note-2"]);
+    let (second, _) = created[1];
+    assert_eq!(models::get_article_tags(&pool, second).await.unwrap(), vec!["notes", "rust"]);
+    assert_eq!(models::get_article_revisions(&pool, second).await.unwrap().len(), 1);
+    let article = models::get_article(&pool, second).await.unwrap().unwrap();
+    assert_eq!(article.author_id, models::get_user_by_username(&pool, "alice").await.unwrap().map(|u| u.id));
+
+    // 任何一篇失败时整批回滚
+    let before = models::get_article_titles(&pool).await.unwrap().len();
+    sqlx::query("CREATE TRIGGER reject_broken BEFORE INSERT ON articles WHEN NEW.title = 'Broken' BEGIN SELECT RAISE(ABORT, 'broken'); END")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let broken = vec![new_article("Fine", &[]), new_article("Broken", &[])];
+    assert!(models::create_articles(&pool, &broken, None).await.is_err());
+    assert_eq!(models::get_article_titles(&pool).await.unwrap().len(), before);
+}
+
+
+#[actix_web::test]
+async fn test_legacy_article_endpoint() {
+    let pool = test_pool().await;
+    let published = ArticleBuilder::new("Hello").content("Body").create(&pool).await;
+    let draft = ArticleBuilder::new("Draft").draft().create(&pool).await;
+    let trashed = ArticleBuilder::new("Trashed").trashed().create(&pool).await;
+    let state = web::Data::new(AppState::from_env().unwrap());
+    let mut client = TestClient::new(pool, move |cfg| {
+        cfg.app_data(state);
+        handlers::configure(cfg);
+    })
+    .await;
+
+    let response = client.get(&format!("/articles/{}", published.id)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_text(response).await, format!(r#"[{},"Hello","Body"]"#, published.id));
+    // 未公开或不存在的文章返回 404 而不是 500
+    for id in [draft.id, trashed.id, 9999] {
+        assert_eq!(client.get(&format!("/articles/{}", id)).await.status(), StatusCode::NOT_FOUND);
+    }
+}
+
+#[actix_web::test]
+async fn test_client_session() {
+    let pool = test_pool().await;
+    UserBuilder::new("alice").display_name("Alice").create(&pool).await;
+    let state = web::Data::new(AppState::from_env().unwrap());
+    let mut client = TestClient::new(pool, move |cfg| {
+        cfg.app_data(state);
+        handlers::configure(cfg);
+    })
+    .await;
+    let location = |response: &ServiceResponse<_>| response.headers().get(LOCATION).unwrap().to_str().unwrap().to_string();
+
+    let response = client.get("/admin").await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(location(&response), "/login");
+
+    let response = client.post_form("/login", &[("username", "alice"), ("password", "wrong")]).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = client.post_form("/login", &[("username", "alice"), ("password", DEFAULT_PASSWORD)]).await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(location(&response), "/admin");
+    assert_eq!(client.get("/admin").await.status(), StatusCode::OK);
+    // 会话在多次请求间保持
+    assert_eq!(client.get("/admin/articles").await.status(), StatusCode::OK);
+
+    let response = client.call(TestRequest::post().uri("/logout")).await;
+    assert_eq!(location(&response), "/login");
+    assert_eq!(client.get("/admin").await.status(), StatusCode::FOUND);
+
+    // 测试用的登录捷径同样建立会话
+    client.login("alice").await;
+    assert_eq!(client.get("/admin").await.status(), StatusCode::OK);
+    client.logout();
+    assert_eq!(client.get("/admin").await.status(), StatusCode::FOUND);
+}
+
+#[actix_web::test]
+async fn test_admin_scope_requires_login() {
+    let pool = test_pool().await;
+    UserBuilder::new("alice").create(&pool).await;
+    let state = web::Data::new(AppState::from_env().unwrap());
+    let mut client = TestClient::new(pool, move |cfg| {
+        cfg.app_data(state);
+        handlers::configure(cfg);
+    })
+    .await;
+    let filter = serde_json::json!({ "name": "Recent", "criteria": { "newer_than_days": 7 } });
+
+    // 页面跳转到登录页，写操作和要求 JSON 的请求得到 401
+    let response = client.get("/admin/settings").await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(response.headers().get(LOCATION).unwrap(), "/login");
+    let response = client.post_json("/admin/filters", &filter).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(body_text(response).await, r#"{"message":"未登录","success":false}"#);
+    let response = client.call(TestRequest::get().uri("/admin/filters").insert_header(("Accept", "application/json"))).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = client.call(TestRequest::delete().uri("/admin/articles/1")).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // 登录后处理函数拿到当前用户
+    client.login("alice").await;
+    let response = client.post_json("/admin/filters", &filter).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client.get("/admin/filters").await;
+    assert!(body_text(response).await.contains("\"name\":\"Recent\""));
+}